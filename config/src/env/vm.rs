@@ -7,8 +7,18 @@ pub struct Vm {
     pub vmm: usize,
     /// Address of [ConsoleMemory].
     pub console: usize,
+    /// Address of [StatsMemory].
+    pub stats: usize,
+    /// Address of [FsMemory].
+    pub fs: usize,
     /// Page size on the host.
     pub host_page_size: NonZero<usize>,
+    /// Virtual address where the whole guest physical memory is directly mapped.
+    pub dmap: usize,
+    /// Physical address of the memory that the kernel can use to allocate pages.
+    pub phys_addr: usize,
+    /// Size of the memory at [`Self::phys_addr`].
+    pub phys_len: usize,
 }
 
 /// Layout of a memory for Memory-mapped I/O to communicate with VMM.
@@ -25,6 +35,8 @@ pub struct VmmMemory {
 pub enum KernelExit {
     Success,
     Panic,
+    /// The kernel failed to boot without panicking (e.g. no init).
+    Failure,
 }
 
 /// Layout of console memory for Memory-mapped I/O.
@@ -56,3 +68,202 @@ pub enum ConsoleType {
     Warn,
     Error,
 }
+
+/// Layout of memory statistics memory for Memory-mapped I/O.
+///
+/// The kernel will publish a snapshot of its memory statistics by:
+///
+/// 1. Write [`Self::zone`] for each UMA zone.
+/// 2. Write [`Self::malloc`] for each malloc type. Currently the kernel writes only a single entry
+///    named `(total)` for the whole heap since it does not have malloc types yet.
+/// 3. Write [`Self::commit`].
+///
+/// The VMM should discard all statistics it received when [`Self::commit`] is written.
+#[cfg(feature = "virt")]
+#[repr(C)]
+pub struct StatsMemory {
+    /// Address of [`ZoneStats`].
+    pub zone: usize,
+    /// Address of [`MallocStats`].
+    pub malloc: usize,
+    pub commit: u8,
+}
+
+/// Statistics of a UMA zone.
+///
+/// This is an equivalent of `uma_type_header` structure on the Orbis.
+#[cfg(feature = "virt")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ZoneStats {
+    /// Name of the zone padded with NUL.
+    pub name: [u8; 32],
+    pub size: u64,
+    /// Number of pages allocated by the keg.
+    pub pages: u64,
+    /// Number of free items in the slabs.
+    pub keg_free: u64,
+    /// Number of free items in the zone buckets.
+    pub zone_free: u64,
+    pub allocs: u64,
+    pub frees: u64,
+    pub fails: u64,
+}
+
+/// Statistics of a malloc type.
+///
+/// This is an equivalent of `malloc_type_header` and `malloc_type_stats` structure on the Orbis. See
+/// [`StatsMemory`] for the entry that contains the statistics of the whole heap.
+#[cfg(feature = "virt")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct MallocStats {
+    /// Name of the malloc type padded with NUL.
+    pub name: [u8; 32],
+    pub alloc_bytes: u64,
+    pub free_bytes: u64,
+    pub alloc_count: u64,
+    pub free_count: u64,
+    /// Bitmap of the zone index that has been used.
+    pub zones: u64,
+}
+
+/// Layout of filesystem memory for Memory-mapped I/O.
+///
+/// This device provides read-only access to the filesystems that stored on the host (e.g. the
+/// partitions of the firmware). The kernel will execute a request by:
+///
+/// 1. Fill [`FsRequest`] with the operation and its arguments.
+/// 2. Write the address of [`FsRequest`] to [`Self::exec`].
+///
+/// The request will be completed when the write in step 2 returns, with the result written back to
+/// the same [`FsRequest`]. All addresses in [`FsRequest`] are virtual addresses and the memory they
+/// point to does not need to be physically contiguous.
+#[cfg(feature = "virt")]
+#[repr(C)]
+pub struct FsMemory {
+    pub exec: usize,
+}
+
+/// Request to [`FsMemory`].
+#[cfg(feature = "virt")]
+#[repr(C)]
+pub struct FsRequest {
+    /// One of [`FsOp`].
+    pub op: u8,
+    /// Node to operate on. Ignored by [`FsOp::Mount`].
+    pub node: u64,
+    /// Offset for [`FsOp::Read`] and [`FsOp::Write`] or the index of the entry for
+    /// [`FsOp::ReadDir`].
+    pub off: u64,
+    /// Address of the name for [`FsOp::Mount`] and [`FsOp::Lookup`].
+    pub name_addr: usize,
+    /// Length of the name at [`Self::name_addr`], in bytes.
+    pub name_len: usize,
+    /// Address of the buffer to receive the data for [`FsOp::Read`] and [`FsOp::ReadDir`] or the
+    /// data to write for [`FsOp::Write`].
+    pub buf_addr: usize,
+    /// Length of the buffer at [`Self::buf_addr`], in bytes.
+    pub buf_len: usize,
+    /// Output: the error number on the PS4 or zero if the request was successful.
+    pub errno: i32,
+    /// Output: the value specific to each operation. See [`FsOp`] for details.
+    pub out: u64,
+    /// Output: attributes of the node. See [`FsOp`] for details.
+    pub attrs: FsAttrs,
+}
+
+/// Operation of [`FsRequest`].
+#[cfg(feature = "virt")]
+#[repr(u8)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, num_enum::IntoPrimitive, num_enum::TryFromPrimitive,
+)]
+pub enum FsOp {
+    /// Mount a volume named [`FsRequest::name_addr`]. [`FsRequest::out`] will be one of [`FsType`]
+    /// and [`FsRequest::attrs`] will be the attributes of its root, which is a file for
+    /// [`FsType::Image`] or a directory for the others.
+    Mount,
+    /// Lookup [`FsRequest::name_addr`] on the directory [`FsRequest::node`]. The name can be `..`
+    /// except on the root directory. [`FsRequest::attrs`] will be the attributes of the result.
+    ///
+    /// Each successful lookup add a reference to the result, which must be released with
+    /// [`FsOp::Forget`] once it is no longer used.
+    Lookup,
+    /// Get the attributes of [`FsRequest::node`] into [`FsRequest::attrs`].
+    GetAttr,
+    /// Read [`FsRequest::node`] at [`FsRequest::off`]. [`FsRequest::out`] will be the number of
+    /// bytes read, which will be zero at the end of the file.
+    Read,
+    /// Read the name of the entry at index [`FsRequest::off`] on the directory
+    /// [`FsRequest::node`]. [`FsRequest::out`] will be the length of the name, which will be zero
+    /// if there are no more entries, and [`FsRequest::attrs`] will be the attributes of the entry.
+    /// The buffer must be at least 255 bytes.
+    ReadDir,
+    /// Write the buffer to [`FsRequest::node`] at [`FsRequest::off`]. [`FsRequest::out`] will be
+    /// the number of bytes written. Only a node with [`FsAttrs::writable`] support this.
+    Write,
+    /// Release [`FsRequest::off`] references to [`FsRequest::node`] that were added by
+    /// [`FsOp::Lookup`]. The ID of the node may be reused once all of its references have been
+    /// released. This has no effect on the root of a volume.
+    Forget,
+}
+
+/// Type of filesystem on the volume of [`FsMemory`].
+#[cfg(feature = "virt")]
+#[repr(u16)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, num_enum::IntoPrimitive, num_enum::TryFromPrimitive,
+)]
+pub enum FsType {
+    ExFat = 1,
+    /// The volume is a disk image on the host. The kernel need to parse the filesystem on the image
+    /// itself by reading the root of the volume.
+    Image,
+    /// The volume is a directory on the host for development. The volume is writable if
+    /// [`FsAttrs::writable`] of its root is non-zero.
+    Host,
+}
+
+/// Attributes of a node on [`FsMemory`].
+#[cfg(feature = "virt")]
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct FsAttrs {
+    /// Unique identifier of the node across all volumes. This is never zero.
+    pub id: u64,
+    /// One of [`FsNodeType`].
+    pub ty: u8,
+    pub mode: u16,
+    pub nlink: u16,
+    pub uid: i32,
+    pub gid: i32,
+    pub size: u64,
+    pub atime: FsTime,
+    pub mtime: FsTime,
+    pub ctime: FsTime,
+    pub birthtime: FsTime,
+    /// Non-zero if the node can be written with [`FsOp::Write`].
+    pub writable: u8,
+}
+
+/// Type of node on [`FsMemory`].
+#[cfg(feature = "virt")]
+#[repr(u8)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, num_enum::IntoPrimitive, num_enum::TryFromPrimitive,
+)]
+pub enum FsNodeType {
+    File = 1,
+    Directory,
+    Link,
+}
+
+/// Timestamp on [`FsMemory`], relative to the Unix epoch.
+#[cfg(feature = "virt")]
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct FsTime {
+    pub sec: i64,
+    pub nsec: i64,
+}
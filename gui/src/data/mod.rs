@@ -15,6 +15,7 @@ pub struct DataMgr {
     part: Part,
     prof: Prof,
     logs: PathBuf,
+    stats: PathBuf,
 }
 
 impl DataMgr {
@@ -24,6 +25,7 @@ impl DataMgr {
         let part = root.join("part");
         let prof = root.join("prof");
         let logs = root.join("kernel.txt");
+        let stats = root.join("kernel-stats.txt");
 
         // Create top-level directories.
         Self::create_dir(&part)?;
@@ -33,6 +35,7 @@ impl DataMgr {
             part: Part::new(part),
             prof: Prof::new(prof),
             logs,
+            stats,
         })
     }
 
@@ -48,6 +51,10 @@ impl DataMgr {
         &self.logs
     }
 
+    pub fn stats(&self) -> &Path {
+        &self.stats
+    }
+
     fn create_dir(path: &Path) -> Result<(), DataError> {
         if let Err(e) = std::fs::create_dir(path) {
             if e.kind() != ErrorKind::AlreadyExists {
@@ -17,4 +17,10 @@ impl Part {
     pub fn data(&self, name: impl AsRef<str>) -> PathBuf {
         self.root.join(name.as_ref())
     }
+
+    /// Returns the directory to extract the partition before moving it to [`Self::data()`] and
+    /// [`Self::meta()`].
+    pub fn staging(&self, name: impl AsRef<str>) -> PathBuf {
+        self.root.join(format!("{}.tmp", name.as_ref()))
+    }
 }
@@ -1,3 +1,5 @@
+pub use self::stats::*;
+
 use self::file::LogFile;
 use anstyle_parse::Parser;
 use config::ConsoleType;
@@ -6,6 +8,7 @@ use std::io::{stderr, stdout, Write};
 use std::path::{Path, PathBuf};
 
 mod file;
+mod stats;
 
 /// Provides method to write kernel logs.
 pub struct LogWriter {
@@ -0,0 +1,79 @@
+use crate::vmm::KernelStats;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Provides method to write kernel memory statistics.
+///
+/// Each snapshot will replace the previous one so the file always contains the latest statistics.
+pub struct StatsWriter {
+    path: PathBuf,
+}
+
+impl StatsWriter {
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        Self { path: file.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&self, stats: &KernelStats) -> Result<(), std::io::Error> {
+        let mut f = BufWriter::new(File::create(&self.path)?);
+
+        // Write zones with the same format as vmstat -z.
+        writeln!(
+            f,
+            "{:<24} {:>8} {:>8} {:>10} {:>10} {:>12} {:>6}",
+            "ITEM", "SIZE", "PAGES", "USED", "FREE", "REQ", "FAIL"
+        )?;
+
+        for z in &stats.zones {
+            writeln!(
+                f,
+                "{:<24} {:>8} {:>8} {:>10} {:>10} {:>12} {:>6}",
+                format!("{}:", Self::name(&z.name)),
+                z.size,
+                z.pages,
+                z.allocs.saturating_sub(z.frees),
+                z.keg_free + z.zone_free,
+                z.allocs,
+                z.fails
+            )?;
+        }
+
+        // Write malloc types with the same format as vmstat -m.
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:>24} {:>10} {:>10} {:>12}  Size(s)",
+            "Type", "InUse", "MemUse", "Requests"
+        )?;
+
+        for m in &stats.mallocs {
+            let sizes: Vec<String> = (0..64)
+                .filter(|i| m.zones & (1 << i) != 0)
+                .map(|i| (16u64 << i).to_string())
+                .collect();
+
+            writeln!(
+                f,
+                "{:>24} {:>10} {:>9}K {:>12}  {}",
+                Self::name(&m.name),
+                m.alloc_count.saturating_sub(m.free_count),
+                m.alloc_bytes.saturating_sub(m.free_bytes).div_ceil(1024),
+                m.alloc_count,
+                sizes.join(",")
+            )?;
+        }
+
+        f.flush()
+    }
+
+    fn name(v: &[u8]) -> String {
+        let len = v.iter().position(|&b| b == 0).unwrap_or(v.len());
+
+        String::from_utf8_lossy(&v[..len]).into_owned()
+    }
+}
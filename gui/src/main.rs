@@ -4,16 +4,16 @@ use self::data::{DataError, DataMgr};
 use self::gdb::{GdbDispatcher, GdbError, GdbSession};
 use self::graphics::{EngineBuilder, GraphicsError, PhysicalDevice};
 use self::hv::Hypervisor;
-use self::log::LogWriter;
+use self::log::{LogWriter, StatsWriter};
 use self::profile::{DisplayResolution, Profile};
-use self::setup::{run_setup, SetupError};
+use self::setup::{run_install, run_setup, SetupError};
 use self::ui::{
     DesktopExt, MainWindow, ProfileModel, ResolutionModel, RuntimeExt, SlintBackend,
     WaitForDebugger,
 };
 use self::vmm::{CpuError, Vmm, VmmError, VmmEvent};
 use async_net::{TcpListener, TcpStream};
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
 use erdp::ErrorDisplay;
 use futures::{
     select_biased, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, FutureExt, TryStreamExt,
@@ -51,6 +51,14 @@ fn main() -> ExitCode {
         None => {}
     }
 
+    // Run the command that does not require a display.
+    match &args.command {
+        Some(ProgramCommand::InstallFirmware { root, dump, verify }) => {
+            return run_install(root, dump, *verify)
+        }
+        None => {}
+    }
+
     #[cfg(target_os = "windows")]
     fn error(msg: impl AsRef<str>) {
         todo!()
@@ -262,6 +270,7 @@ async fn run(args: ProgramArgs, exe: PathBuf) -> Result<(), ProgramError> {
     let logs = data.logs();
     let mut logs =
         LogWriter::new(logs).map_err(|e| ProgramError::CreateKernelLog(logs.into(), e))?;
+    let stats = StatsWriter::new(data.stats());
     let shutdown = Arc::default();
     let graphics = graphics
         .build(&profile, attrs, &shutdown)
@@ -270,7 +279,7 @@ async fn run(args: ProgramArgs, exe: PathBuf) -> Result<(), ProgramError> {
     let mut gdb_buf = [0; 1024];
 
     // Start VMM.
-    let mut vmm = match Vmm::new(&profile, &kernel, &shutdown) {
+    let mut vmm = match Vmm::new(&profile, &data, &kernel, &shutdown) {
         Ok(v) => v,
         Err(e) => return Err(ProgramError::StartVmm(kernel, e)),
     };
@@ -281,7 +290,7 @@ async fn run(args: ProgramArgs, exe: PathBuf) -> Result<(), ProgramError> {
             v = gdb_read.read(&mut gdb_buf).fuse() => {
                 dispatch_gdb(v, &mut gdb, &gdb_buf, &mut vmm, &mut gdb_write).await?
             }
-            v = vmm.recv().fuse() => dispatch_vmm(v, &mut logs).await?,
+            v = vmm.recv().fuse() => dispatch_vmm(v, &mut logs, &stats).await?,
         };
 
         if !r {
@@ -456,7 +465,11 @@ async fn dispatch_gdb<H: Hypervisor>(
     Ok(true)
 }
 
-async fn dispatch_vmm(ev: VmmEvent, logs: &mut LogWriter) -> Result<bool, ProgramError> {
+async fn dispatch_vmm(
+    ev: VmmEvent,
+    logs: &mut LogWriter,
+    stats: &StatsWriter,
+) -> Result<bool, ProgramError> {
     match ev {
         VmmEvent::Exit(id, r) => {
             if !r.map_err(ProgramError::CpuThread)? {
@@ -466,6 +479,9 @@ async fn dispatch_vmm(ev: VmmEvent, logs: &mut LogWriter) -> Result<bool, Progra
             }
         }
         VmmEvent::Log(t, m) => logs.write(t, m),
+        VmmEvent::Stats(v) => stats
+            .write(&v)
+            .map_err(|e| ProgramError::WriteKernelStats(stats.path().into(), e))?,
     }
 
     Ok(true)
@@ -478,6 +494,9 @@ struct ProgramArgs {
     #[arg(long, value_enum, hide = true)]
     mode: Option<ProgramMode>,
 
+    #[command(subcommand)]
+    command: Option<ProgramCommand>,
+
     /// Immediate launch the VMM in debug mode.
     #[arg(long)]
     debug: Option<SocketAddrV4>,
@@ -493,6 +512,23 @@ enum ExitAction {
     RunDebug(SocketAddrV4),
 }
 
+/// Command to run instead of the launcher.
+#[derive(Subcommand)]
+enum ProgramCommand {
+    /// Install a firmware dump to a data root without the setup wizard.
+    InstallFirmware {
+        /// Path to the data root, which will be created if it does not exist.
+        root: PathBuf,
+
+        /// Path to the firmware dump.
+        dump: PathBuf,
+
+        /// Verify the installed firmware against the dump instead of installing it.
+        #[arg(long)]
+        verify: bool,
+    },
+}
+
 /// Mode of our program.
 #[derive(Clone, ValueEnum)]
 enum ProgramMode {
@@ -558,6 +594,9 @@ enum ProgramError {
     #[error("couldn't create {0}")]
     CreateKernelLog(PathBuf, #[source] std::io::Error),
 
+    #[error("couldn't write {0}")]
+    WriteKernelStats(PathBuf, #[source] std::io::Error),
+
     #[error("couldn't build graphics engine")]
     BuildGraphicsEngine(#[source] GraphicsError),
 
@@ -16,6 +16,9 @@ pub struct Profile {
     name: String,
     display_resolution: DisplayResolution,
     kernel_config: Config,
+    app_image: Option<PathBuf>,
+    host_dir: Option<PathBuf>,
+    host_dir_writable: bool,
     created: SystemTime,
 }
 
@@ -58,6 +61,33 @@ impl Profile {
         &self.kernel_config
     }
 
+    /// Returns the path to the PFS image of the application to launch.
+    pub fn app_image(&self) -> Option<&Path> {
+        self.app_image.as_deref()
+    }
+
+    pub fn set_app_image(&mut self, v: Option<PathBuf>) {
+        self.app_image = v;
+    }
+
+    /// Returns the directory on the host to expose to the kernel for development.
+    pub fn host_dir(&self) -> Option<&Path> {
+        self.host_dir.as_deref()
+    }
+
+    pub fn set_host_dir(&mut self, v: Option<PathBuf>) {
+        self.host_dir = v;
+    }
+
+    /// Returns `true` if the kernel can write to [`Self::host_dir()`].
+    pub fn host_dir_writable(&self) -> bool {
+        self.host_dir_writable
+    }
+
+    pub fn set_host_dir_writable(&mut self, v: bool) {
+        self.host_dir_writable = v;
+    }
+
     pub fn save(&self, root: impl AsRef<Path>) -> Result<(), SaveError> {
         // Write profile.
         let root = root.as_ref();
@@ -84,6 +114,9 @@ impl Default for Profile {
             kernel_config: Config {
                 max_cpu: NonZero::new(8).unwrap(),
             },
+            app_image: None,
+            host_dir: None,
+            host_dir_writable: false,
             created: SystemTime::now(),
         }
     }
@@ -0,0 +1,102 @@
+use super::firmware::{install_firmware, verify_firmware, Progress};
+use crate::data::DataMgr;
+use erdp::ErrorDisplay;
+use obfw::DumpReader;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Install the firmware dump at `dump` to the data root at `root` without a display.
+///
+/// If `verify` is `true` this will verify the installed firmware against `dump` instead.
+pub fn run_install(root: &Path, dump: &Path, verify: bool) -> ExitCode {
+    // Open firmware dump.
+    let mut reader = match File::open(dump) {
+        Ok(v) => match DumpReader::new(v) {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("Failed to open {}: {}.", dump.display(), e.display());
+                return ExitCode::FAILURE;
+            }
+        },
+        Err(e) => {
+            eprintln!("Failed to open {}: {}.", dump.display(), e.display());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Create data manager.
+    if let Err(e) = std::fs::create_dir_all(root) {
+        eprintln!("Failed to create {}: {}.", root.display(), e.display());
+        return ExitCode::FAILURE;
+    }
+
+    let dmgr = match DataMgr::new(root) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!(
+                "Failed to create data manager on {}: {}.",
+                root.display(),
+                e.display()
+            );
+
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Run. The tasks only use the runtime to yield so any executor will do.
+    let mut progress = StdoutProgress {
+        total: reader.items() as f32,
+        current: 0,
+    };
+
+    let r = futures::executor::block_on(async {
+        if verify {
+            verify_firmware(&dmgr, &mut reader, &mut progress).await
+        } else {
+            install_firmware(&dmgr, &mut reader, &mut progress).await
+        }
+    });
+
+    match r {
+        Ok(_) if verify => println!("The firmware matches with {}.", dump.display()),
+        Ok(_) => println!("The firmware was installed to {}.", root.display()),
+        Err(e) if verify => {
+            eprintln!("Failed to verify {}: {}.", dump.display(), e.display());
+            return ExitCode::FAILURE;
+        }
+        Err(e) => {
+            eprintln!(
+                "Failed to install {}: {}. Run the same command again to resume the installation.",
+                dump.display(),
+                e.display()
+            );
+
+            return ExitCode::FAILURE;
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Implementation of [`Progress`] that print to stdout.
+struct StdoutProgress {
+    total: f32,
+    current: u32,
+}
+
+impl Progress for StdoutProgress {
+    fn set_status(&mut self, v: &str) {
+        let p = (self.current as f32 / self.total * 100.0).min(100.0);
+        let mut stdout = std::io::stdout().lock();
+
+        // Ignore the error since the progress is not important.
+        writeln!(stdout, "[{p:>3.0}%] {v}").ok();
+        stdout.flush().ok();
+    }
+
+    fn step(&mut self) {
+        self.current += 1;
+    }
+}
@@ -0,0 +1,640 @@
+use crate::data::DataMgr;
+use crate::rt::yield_now;
+use crate::vfs::{FsType, NodeAttrs, NodeTime, NodeType, FS_TYPE, NODES};
+use obfw::ps4::{PartData, PartReader};
+use obfw::{DumpReader, ItemReader};
+use redb::{Database, DatabaseError};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::hash::{DefaultHasher, Hasher};
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Receives progress from [`install_firmware()`] and [`verify_firmware()`].
+pub trait Progress {
+    fn set_status(&mut self, v: &str);
+
+    /// Invoked when an item on the dump has been processed.
+    fn step(&mut self);
+}
+
+/// Extract the firmware from `dump` to `dmgr`.
+///
+/// Each partition will be extracted to a staging directory and moved to the data root only when
+/// all of its files were verified. A partition that was already installed will be skipped so an
+/// interrupted installation can be resumed by invoke this function with the same dump again.
+pub async fn install_firmware(
+    dmgr: &DataMgr,
+    dump: &mut DumpReader<File>,
+    progress: &mut impl Progress,
+) -> Result<(), FirmwareError> {
+    loop {
+        // Get next item.
+        let mut item = match dump.next_item().map_err(FirmwareError::NextItem)? {
+            Some(v) => v,
+            None => break,
+        };
+
+        // Update status.
+        let name = item.to_string();
+
+        progress.set_status(&format!("Extracting {name}..."));
+
+        yield_now().await;
+
+        // Extract item.
+        let r: Result<(), Box<dyn Error>> = match &mut item {
+            ItemReader::Ps4Part(r) => install_partition(dmgr, r, progress)
+                .await
+                .map_err(|e| e.into()),
+        };
+
+        if let Err(e) = r {
+            return Err(FirmwareError::ExtractItem(name, e));
+        }
+
+        progress.step();
+
+        yield_now().await;
+    }
+
+    Ok(())
+}
+
+/// Check if the firmware on `dmgr` is the same as `dump`.
+pub async fn verify_firmware(
+    dmgr: &DataMgr,
+    dump: &mut DumpReader<File>,
+    progress: &mut impl Progress,
+) -> Result<(), FirmwareError> {
+    loop {
+        // Get next item.
+        let mut item = match dump.next_item().map_err(FirmwareError::NextItem)? {
+            Some(v) => v,
+            None => break,
+        };
+
+        // Update status.
+        let name = item.to_string();
+
+        progress.set_status(&format!("Verifying {name}..."));
+
+        yield_now().await;
+
+        // Verify item.
+        let r: Result<(), Box<dyn Error>> = match &mut item {
+            ItemReader::Ps4Part(r) => verify_partition(dmgr, r, progress)
+                .await
+                .map_err(|e| e.into()),
+        };
+
+        if let Err(e) = r {
+            return Err(FirmwareError::VerifyItem(name, e));
+        }
+
+        progress.step();
+
+        yield_now().await;
+    }
+
+    Ok(())
+}
+
+async fn install_partition(
+    dmgr: &DataMgr,
+    part: &mut PartReader<'_, File>,
+    progress: &mut impl Progress,
+) -> Result<(), PartitionError> {
+    // Check if the partition already installed. The metadata database is the last thing to be
+    // moved to the data root so the partition is complete if it exists.
+    let (fs, dev) = partition_info(part)?;
+
+    if dmgr.partitions().meta(&dev).is_file() {
+        progress.set_status(&format!("Skipping {dev} (already installed)..."));
+
+        yield_now().await;
+
+        return skip_partition(part, progress).await;
+    }
+
+    // Discard the previous attempt.
+    let staging = dmgr.partitions().staging(&dev);
+
+    if let Err(e) = std::fs::remove_dir_all(&staging) {
+        if e.kind() != ErrorKind::NotFound {
+            return Err(PartitionError::RemoveStaging(staging, e));
+        }
+    }
+
+    if let Err(e) = std::fs::create_dir(&staging) {
+        return Err(PartitionError::CreateDirectory(staging, e));
+    }
+
+    // Create database file for file/directory metadata.
+    let mp = staging.join("meta.obp");
+    let meta = match File::create_new(&mp) {
+        Ok(v) => v,
+        Err(e) => return Err(PartitionError::CreateFile(mp, e)),
+    };
+
+    // Create metadata database.
+    let meta = match Database::builder().create_file(meta) {
+        Ok(v) => v,
+        Err(e) => return Err(PartitionError::CreateMeta(mp, e)),
+    };
+
+    // Start metadata transaction.
+    let meta = match meta.begin_write() {
+        Ok(v) => v,
+        Err(e) => return Err(PartitionError::MetaTransaction(mp, e)),
+    };
+
+    // Write FS type.
+    let mut tab = match meta.open_table(FS_TYPE) {
+        Ok(v) => v,
+        Err(e) => return Err(PartitionError::MetaTable(mp, FS_TYPE.to_string(), e)),
+    };
+
+    if let Err(e) = tab.insert((), fs) {
+        return Err(PartitionError::WriteFs(mp, e));
+    }
+
+    drop(tab);
+
+    // PartReader only provides the name and the data of each item so the attributes from the
+    // console are not available (including symbolic links and hard links). What we store here are
+    // placeholders that make the partition usable: everything is a read-only file or directory that
+    // owned by root with the time of installation as the timestamps.
+    //
+    // TODO: Store the real attributes once the dump contains them.
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|v| NodeTime {
+            sec: v.as_secs().try_into().unwrap(),
+            nsec: v.subsec_nanos(),
+        })
+        .unwrap();
+    let attrs = |ty, size| NodeAttrs {
+        ty,
+        mode: 0o555,
+        nlink: 1,
+        uid: 0,
+        gid: 0,
+        size,
+        atime: now,
+        mtime: now,
+        ctime: now,
+        birthtime: now,
+    };
+
+    // Extract items.
+    let root = staging.join("data");
+    let mut nodes = BTreeMap::from([(String::from("/"), attrs(NodeType::Directory, 0))]);
+    let mut files = Vec::new();
+    let mut buf = vec![0u8; 0xFFFF];
+
+    if let Err(e) = std::fs::create_dir(&root) {
+        return Err(PartitionError::CreateDirectory(root, e));
+    }
+
+    loop {
+        // Get next item.
+        let item = match part.next_item().map_err(PartitionError::NextItem)? {
+            Some(v) => v,
+            None => break,
+        };
+
+        // Unpack item.
+        let (name, data) = match item {
+            PartData::Directory(n) => (n, None),
+            PartData::File(n, r) => (n, Some(r)),
+        };
+
+        // Get local path.
+        let name = item_name(name)?;
+        let path = item_path(&root, &name)?;
+
+        // Extract item.
+        match data {
+            Some(mut data) => {
+                progress.set_status(&format!("Extracting {name}..."));
+
+                yield_now().await;
+
+                // Create only if not exists.
+                let mut file = match File::create_new(&path) {
+                    Ok(v) => v,
+                    Err(e) => return Err(PartitionError::CreateFile(path, e)),
+                };
+
+                // Copy data.
+                let mut hash = DefaultHasher::new();
+                let mut size = 0;
+
+                loop {
+                    let n = match read(&mut data, &mut buf) {
+                        Ok(v) => v,
+                        Err(e) => return Err(PartitionError::ExtractFile(name, path, e)),
+                    };
+
+                    if n == 0 {
+                        break;
+                    }
+
+                    // Write file.
+                    if let Err(e) = file.write_all(&buf[..n]) {
+                        return Err(PartitionError::ExtractFile(name, path, e));
+                    }
+
+                    hash.write(&buf[..n]);
+                    size += u64::try_from(n).unwrap();
+
+                    yield_now().await;
+                }
+
+                nodes.insert(name.clone(), attrs(NodeType::File, size));
+                files.push((name, path, size, hash.finish()));
+            }
+            None => {
+                // Create only if not exists.
+                if let Err(e) = std::fs::create_dir(&path) {
+                    return Err(PartitionError::CreateDirectory(path, e));
+                }
+
+                nodes.insert(name, attrs(NodeType::Directory, 0));
+            }
+        }
+
+        progress.step();
+
+        yield_now().await;
+    }
+
+    // A directory is linked from its parent (or ".." for the root), its "." and ".." of each
+    // sub-directory. The first one was already counted when we create the attributes.
+    let dirs: Vec<String> = nodes
+        .iter()
+        .filter(|(_, a)| a.ty == NodeType::Directory)
+        .map(|(n, _)| n.clone())
+        .collect();
+
+    for dir in dirs {
+        let parent = match dir.rsplit_once('/') {
+            Some(("", "")) | None => continue,
+            Some(("", _)) => "/",
+            Some((v, _)) => v,
+        };
+
+        if let Some(v) = nodes.get_mut(parent) {
+            v.nlink += 1;
+        }
+    }
+
+    for v in nodes.values_mut().filter(|v| v.ty == NodeType::Directory) {
+        v.nlink += 1;
+    }
+
+    // Write attributes.
+    progress.set_status("Writing metadata...");
+
+    yield_now().await;
+
+    let mut tab = match meta.open_table(NODES) {
+        Ok(v) => v,
+        Err(e) => return Err(PartitionError::MetaTable(mp, NODES.to_string(), e)),
+    };
+
+    for (name, attrs) in nodes {
+        if let Err(e) = tab.insert(name.as_str(), attrs) {
+            return Err(PartitionError::WriteNode(mp, name, e));
+        }
+    }
+
+    drop(tab);
+
+    // Commit metadata transaction.
+    progress.set_status("Committing metadata database...");
+
+    yield_now().await;
+
+    if let Err(e) = meta.commit() {
+        return Err(PartitionError::MetaCommit(mp, e));
+    }
+
+    // Verify extracted files.
+    for (name, path, size, hash) in files {
+        progress.set_status(&format!("Verifying {name}..."));
+
+        yield_now().await;
+
+        let mut file = match File::open(&path) {
+            Ok(v) => v,
+            Err(e) => return Err(PartitionError::VerifyFile(path, e)),
+        };
+
+        let mut actual = DefaultHasher::new();
+        let mut len = 0;
+
+        loop {
+            let n = match read(&mut file, &mut buf) {
+                Ok(v) => v,
+                Err(e) => return Err(PartitionError::VerifyFile(path, e)),
+            };
+
+            if n == 0 {
+                break;
+            }
+
+            actual.write(&buf[..n]);
+            len += u64::try_from(n).unwrap();
+
+            yield_now().await;
+        }
+
+        if len != size || actual.finish() != hash {
+            return Err(PartitionError::Mismatch(name));
+        }
+    }
+
+    // Move the partition to the data root. The data directory may already exists if the previous
+    // attempt was interrupted in the middle of this.
+    let data = dmgr.partitions().data(&dev);
+    let mv = |from: &Path, to: &Path| {
+        std::fs::rename(from, to)
+            .map_err(|e| PartitionError::MoveStaging(from.to_owned(), to.to_owned(), e))
+    };
+
+    progress.set_status(&format!("Installing {dev}..."));
+
+    yield_now().await;
+
+    if let Err(e) = std::fs::remove_dir_all(&data) {
+        if e.kind() != ErrorKind::NotFound {
+            return Err(PartitionError::RemoveStaging(data, e));
+        }
+    }
+
+    mv(&root, &data)?;
+    mv(&mp, &dmgr.partitions().meta(&dev))?;
+
+    if let Err(e) = std::fs::remove_dir(&staging) {
+        return Err(PartitionError::RemoveStaging(staging, e));
+    }
+
+    Ok(())
+}
+
+async fn skip_partition(
+    part: &mut PartReader<'_, File>,
+    progress: &mut impl Progress,
+) -> Result<(), PartitionError> {
+    while let Some(item) = part.next_item().map_err(PartitionError::NextItem)? {
+        if let PartData::File(n, mut data) = item {
+            let n = String::from_utf8_lossy(&n).into_owned();
+
+            if let Err(e) = std::io::copy(&mut data, &mut std::io::sink()) {
+                return Err(PartitionError::ReadFile(n, e));
+            }
+        }
+
+        progress.step();
+
+        yield_now().await;
+    }
+
+    Ok(())
+}
+
+async fn verify_partition(
+    dmgr: &DataMgr,
+    part: &mut PartReader<'_, File>,
+    progress: &mut impl Progress,
+) -> Result<(), PartitionError> {
+    // Check if the partition installed.
+    let (_, dev) = partition_info(part)?;
+
+    if !dmgr.partitions().meta(&dev).is_file() {
+        return Err(PartitionError::NotInstalled(dev));
+    }
+
+    // Verify items.
+    let root = dmgr.partitions().data(&dev);
+    let mut expect = vec![0u8; 0xFFFF];
+    let mut actual = vec![0u8; 0xFFFF];
+
+    while let Some(item) = part.next_item().map_err(PartitionError::NextItem)? {
+        // Unpack item.
+        let (name, data) = match item {
+            PartData::Directory(n) => (n, None),
+            PartData::File(n, r) => (n, Some(r)),
+        };
+
+        // Get local path.
+        let name = item_name(name)?;
+        let path = item_path(&root, &name)?;
+
+        // Verify item.
+        match data {
+            Some(mut data) => {
+                progress.set_status(&format!("Verifying {name}..."));
+
+                yield_now().await;
+
+                let mut file = match File::open(&path) {
+                    Ok(v) => v,
+                    Err(e) if e.kind() == ErrorKind::NotFound => {
+                        return Err(PartitionError::Mismatch(name))
+                    }
+                    Err(e) => return Err(PartitionError::VerifyFile(path, e)),
+                };
+
+                // Compare data.
+                loop {
+                    let n = match read(&mut data, &mut expect) {
+                        Ok(v) => v,
+                        Err(e) => return Err(PartitionError::ReadFile(name, e)),
+                    };
+
+                    // Check if the file has the same size.
+                    if n == 0 {
+                        match read(&mut file, &mut actual) {
+                            Ok(0) => break,
+                            Ok(_) => return Err(PartitionError::Mismatch(name)),
+                            Err(e) => return Err(PartitionError::VerifyFile(path, e)),
+                        }
+                    }
+
+                    match file.read_exact(&mut actual[..n]) {
+                        Ok(_) => {}
+                        Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                            return Err(PartitionError::Mismatch(name))
+                        }
+                        Err(e) => return Err(PartitionError::VerifyFile(path, e)),
+                    }
+
+                    if actual[..n] != expect[..n] {
+                        return Err(PartitionError::Mismatch(name));
+                    }
+
+                    yield_now().await;
+                }
+            }
+            None => {
+                if !path.is_dir() {
+                    return Err(PartitionError::Mismatch(name));
+                }
+            }
+        }
+
+        progress.step();
+
+        yield_now().await;
+    }
+
+    Ok(())
+}
+
+/// Returns the filesystem type and the device name of `part`.
+fn partition_info(part: &PartReader<'_, File>) -> Result<(FsType, String), PartitionError> {
+    // Get FS type.
+    let fs = match part.fs() {
+        b"exfatfs" => FsType::ExFat,
+        n => {
+            let n = String::from_utf8_lossy(n);
+            return Err(PartitionError::UnexpectedFs(n.into_owned()));
+        }
+    };
+
+    // Get device path.
+    let dev = part.dev();
+    let dev = match std::str::from_utf8(dev) {
+        Ok(v) => v,
+        Err(_) => {
+            let n = String::from_utf8_lossy(dev);
+            return Err(PartitionError::UnexpectedDevice(n.into_owned()));
+        }
+    };
+
+    // Get device name.
+    let dev = if dev == "md0" {
+        dev
+    } else if let Some(v) = dev.strip_prefix("/dev/") {
+        if v.contains(['/', '\\']) {
+            return Err(PartitionError::UnexpectedDevice(dev.into()));
+        }
+
+        v
+    } else {
+        return Err(PartitionError::UnexpectedDevice(dev.into()));
+    };
+
+    Ok((fs, dev.to_owned()))
+}
+
+fn item_name(name: Vec<u8>) -> Result<String, PartitionError> {
+    String::from_utf8(name).map_err(|e| {
+        let n = String::from_utf8_lossy(e.as_bytes());
+        PartitionError::UnexpectedFile(n.into_owned())
+    })
+}
+
+fn item_path(root: &Path, name: &str) -> Result<PathBuf, PartitionError> {
+    let mut path = root.to_path_buf();
+
+    for com in name.split('/').skip(1) {
+        if com.is_empty() || com.contains('\\') {
+            return Err(PartitionError::UnexpectedFile(name.into()));
+        }
+
+        path.push(com);
+    }
+
+    Ok(path)
+}
+
+fn read(src: &mut impl Read, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+    loop {
+        match src.read(buf) {
+            Ok(v) => return Ok(v),
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Represents an error when [`install_firmware()`] or [`verify_firmware()`] fails.
+#[derive(Debug, Error)]
+pub enum FirmwareError {
+    #[error("couldn't get dumped item")]
+    NextItem(#[source] obfw::ReaderError),
+
+    #[error("couldn't extract {0}")]
+    ExtractItem(String, #[source] Box<dyn Error>),
+
+    #[error("couldn't verify {0}")]
+    VerifyItem(String, #[source] Box<dyn Error>),
+}
+
+/// Represents an error when [`install_partition()`] or [`verify_partition()`] fails.
+#[derive(Debug, Error)]
+enum PartitionError {
+    #[error("unexpected filesystem {0}")]
+    UnexpectedFs(String),
+
+    #[error("unexpected device {0}")]
+    UnexpectedDevice(String),
+
+    #[error("{0} is not installed")]
+    NotInstalled(String),
+
+    #[error("couldn't remove {0}")]
+    RemoveStaging(PathBuf, #[source] std::io::Error),
+
+    #[error("couldn't create metadata database on {0}")]
+    CreateMeta(PathBuf, #[source] DatabaseError),
+
+    #[error("couldn't start metadata transaction on {0}")]
+    MetaTransaction(PathBuf, #[source] redb::TransactionError),
+
+    #[error("couldn't open table {1} on {0}")]
+    MetaTable(PathBuf, String, #[source] redb::TableError),
+
+    #[error("couldn't write filesystem type to {0}")]
+    WriteFs(PathBuf, #[source] redb::StorageError),
+
+    #[error("couldn't get partition item")]
+    NextItem(#[source] obfw::ps4::PartError),
+
+    #[error("couldn't write attributes of {1} to {0}")]
+    WriteNode(PathBuf, String, #[source] redb::StorageError),
+
+    #[error("unexpected file {0}")]
+    UnexpectedFile(String),
+
+    #[error("couldn't create {0}")]
+    CreateDirectory(PathBuf, #[source] std::io::Error),
+
+    #[error("couldn't create {0}")]
+    CreateFile(PathBuf, #[source] std::io::Error),
+
+    #[error("couldn't extract {0} to {1}")]
+    ExtractFile(String, PathBuf, #[source] std::io::Error),
+
+    #[error("couldn't read {0}")]
+    ReadFile(String, #[source] std::io::Error),
+
+    #[error("couldn't commit metadata transaction to {0}")]
+    MetaCommit(PathBuf, #[source] redb::CommitError),
+
+    #[error("couldn't read {0}")]
+    VerifyFile(PathBuf, #[source] std::io::Error),
+
+    #[error("{0} does not match with the dump")]
+    Mismatch(String),
+
+    #[error("couldn't move {0} to {1}")]
+    MoveStaging(PathBuf, PathBuf, #[source] std::io::Error),
+}
@@ -1,32 +1,31 @@
+pub use self::cli::*;
 pub use self::data::DataRootError;
 
 use self::data::{read_data_root, write_data_root};
+use self::firmware::Progress;
 use crate::data::{DataError, DataMgr};
 use crate::rt::yield_now;
 use crate::ui::{
     error, open_dir, open_file, spawn_handler, DesktopExt, FileType, InstallFirmware, RuntimeExt,
     SetupWizard,
 };
-use crate::vfs::{FsType, FS_TYPE};
 use erdp::ErrorDisplay;
-use obfw::ps4::{PartData, PartReader};
-use obfw::{DumpReader, ItemReader};
-use redb::{Database, DatabaseError};
+use obfw::DumpReader;
 use slint::{CloseRequestResponse, ComponentHandle, PlatformError, SharedString};
 use std::cell::Cell;
 use std::error::Error;
 use std::fs::File;
-use std::future::Future;
-use std::io::{ErrorKind, Write};
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use thiserror::Error;
 
+mod cli;
 #[cfg_attr(target_os = "linux", path = "linux.rs")]
 #[cfg_attr(target_os = "macos", path = "macos.rs")]
 #[cfg_attr(target_os = "windows", path = "windows.rs")]
 mod data;
+mod firmware;
 
 pub async fn run_setup() -> Result<Option<DataMgr>, SetupError> {
     // Load data root.
@@ -291,46 +290,18 @@ async fn install_firmware(win: SetupWizard) {
         }
     };
 
-    // Setup progress updater.
-    let n = dump.items();
-    let mut p = 0u32;
-    let mut step = || {
-        p += 1;
-        pw.set_progress(p as f32 / n as f32);
-        yield_now()
+    // Extract.
+    let mut progress = InstallProgress {
+        win: &pw,
+        total: dump.items() as f32,
+        current: 0,
     };
 
     yield_now().await;
 
-    // Extract.
-    let e = loop {
-        // Get next item.
-        let mut item = match dump.next_item() {
-            Ok(Some(v)) => v,
-            Ok(None) => break None,
-            Err(e) => break Some(FirmwareError::NextItem(e)),
-        };
-
-        // Update status.
-        let name = item.to_string();
-
-        pw.set_status(slint::format!("Extracting {name}..."));
-
-        yield_now().await;
-
-        // Extract item.
-        let r: Result<(), Box<dyn Error>> = match &mut item {
-            ItemReader::Ps4Part(r) => extract_partition(&pw, &dmgr, r, &mut step)
-                .await
-                .map_err(|e| e.into()),
-        };
-
-        if let Err(e) = r {
-            break Some(FirmwareError::ExtractItem(name, e));
-        }
-
-        step().await;
-    };
+    let e = self::firmware::install_firmware(&dmgr, &mut dump, &mut progress)
+        .await
+        .err();
 
     // Check status.
     if let Err(e) = pw.hide() {
@@ -343,223 +314,33 @@ async fn install_firmware(win: SetupWizard) {
 
     match e {
         Some(e) => {
-            let m = slint::format!("Failed to install {}: {}.", path, e.display());
+            let m = slint::format!(
+                "Failed to install {}: {}. Install the same dump again to resume the installation.",
+                path,
+                e.display()
+            );
             error(Some(&win), m).await;
         }
         None => win.invoke_set_firmware_finished(),
     }
 }
 
-async fn extract_partition<F: Future<Output = ()>>(
-    pw: &InstallFirmware,
-    dmgr: &DataMgr,
-    part: &mut PartReader<'_, File>,
-    step: &mut impl FnMut() -> F,
-) -> Result<(), PartitionError> {
-    // Get FS type.
-    let fs = match part.fs() {
-        b"exfatfs" => FsType::ExFat,
-        n => {
-            let n = String::from_utf8_lossy(n);
-            return Err(PartitionError::UnexpectedFs(n.into_owned()));
-        }
-    };
-
-    // Get device path.
-    let dev = part.dev();
-    let dev = match std::str::from_utf8(dev) {
-        Ok(v) => v,
-        Err(_) => {
-            let n = String::from_utf8_lossy(dev);
-            return Err(PartitionError::UnexpectedDevice(n.into_owned()));
-        }
-    };
-
-    // Get device name.
-    let dev = if dev == "md0" {
-        dev
-    } else if let Some(v) = dev.strip_prefix("/dev/") {
-        if v.contains(['/', '\\']) {
-            return Err(PartitionError::UnexpectedDevice(dev.into()));
-        }
-
-        v
-    } else {
-        return Err(PartitionError::UnexpectedDevice(dev.into()));
-    };
-
-    // Create database file for file/directory metadata.
-    let mp = dmgr.partitions().meta(dev);
-    let meta = match File::create_new(&mp) {
-        Ok(v) => v,
-        Err(e) => return Err(PartitionError::CreateFile(mp, e)),
-    };
-
-    // Create metadata database.
-    let meta = match Database::builder().create_file(meta) {
-        Ok(v) => v,
-        Err(e) => return Err(PartitionError::CreateMeta(mp, e)),
-    };
-
-    // Start metadata transaction.
-    let meta = match meta.begin_write() {
-        Ok(v) => v,
-        Err(e) => return Err(PartitionError::MetaTransaction(mp, e)),
-    };
-
-    // Write FS type.
-    let mut tab = match meta.open_table(FS_TYPE) {
-        Ok(v) => v,
-        Err(e) => return Err(PartitionError::MetaTable(mp, FS_TYPE.to_string(), e)),
-    };
-
-    if let Err(e) = tab.insert((), fs) {
-        return Err(PartitionError::WriteFs(mp, e));
-    }
-
-    drop(tab);
-
-    // Extract items.
-    let root = dmgr.partitions().data(dev);
-    let mut buf = vec![0u8; 0xFFFF];
-
-    loop {
-        // Get next item.
-        let item = match part.next_item().map_err(PartitionError::NextItem)? {
-            Some(v) => v,
-            None => break,
-        };
-
-        // Unpack item.
-        let (name, data) = match item {
-            PartData::Directory(n) => (n, None),
-            PartData::File(n, r) => (n, Some(r)),
-        };
-
-        // Get name.
-        let name = match String::from_utf8(name) {
-            Ok(v) => v,
-            Err(e) => {
-                let n = String::from_utf8_lossy(e.as_bytes());
-                return Err(PartitionError::UnexpectedFile(n.into_owned()));
-            }
-        };
-
-        // Get local path.
-        let mut path = root.to_path_buf();
-
-        for com in name.split('/').skip(1) {
-            if com.is_empty() || com.contains('\\') {
-                return Err(PartitionError::UnexpectedFile(name));
-            }
-
-            path.push(com);
-        }
-
-        // Extract item.
-        match data {
-            Some(mut data) => {
-                pw.set_status(slint::format!("Extracting {name}..."));
-
-                yield_now().await;
-
-                // Create only if not exists.
-                let mut file = match File::create_new(&path) {
-                    Ok(v) => v,
-                    Err(e) => return Err(PartitionError::CreateFile(path, e)),
-                };
-
-                // Copy data.
-                loop {
-                    let n = match data.read(&mut buf) {
-                        Ok(v) => v,
-                        Err(e) if e.kind() == ErrorKind::Interrupted => continue,
-                        Err(e) => return Err(PartitionError::ExtractFile(name, path, e)),
-                    };
-
-                    if n == 0 {
-                        break;
-                    }
-
-                    // Write file.
-                    if let Err(e) = file.write_all(&buf[..n]) {
-                        return Err(PartitionError::ExtractFile(name, path, e));
-                    }
-
-                    yield_now().await;
-                }
-            }
-            None => {
-                // Create only if not exists.
-                if let Err(e) = std::fs::create_dir(&path) {
-                    return Err(PartitionError::CreateDirectory(path, e));
-                }
-            }
-        }
+/// Implementation of [`Progress`] for [`InstallFirmware`].
+struct InstallProgress<'a> {
+    win: &'a InstallFirmware,
+    total: f32,
+    current: u32,
+}
 
-        step().await;
+impl Progress for InstallProgress<'_> {
+    fn set_status(&mut self, v: &str) {
+        self.win.set_status(v.into());
     }
 
-    // Commit metadata transaction.
-    pw.set_status("Committing metadata database...".into());
-
-    yield_now().await;
-
-    if let Err(e) = meta.commit() {
-        return Err(PartitionError::MetaCommit(mp, e));
+    fn step(&mut self) {
+        self.current += 1;
+        self.win.set_progress(self.current as f32 / self.total);
     }
-
-    Ok(())
-}
-
-/// Represents an error when [`extract_firmware_dump()`] fails.
-#[derive(Debug, Error)]
-enum FirmwareError {
-    #[error("couldn't get dumped item")]
-    NextItem(#[source] obfw::ReaderError),
-
-    #[error("couldn't extract {0}")]
-    ExtractItem(String, #[source] Box<dyn Error>),
-}
-
-/// Represents an error when [`extract_partition()`] fails.
-#[derive(Debug, Error)]
-enum PartitionError {
-    #[error("unexpected filesystem {0}")]
-    UnexpectedFs(String),
-
-    #[error("unexpected device {0}")]
-    UnexpectedDevice(String),
-
-    #[error("couldn't create metadata database on {0}")]
-    CreateMeta(PathBuf, #[source] DatabaseError),
-
-    #[error("couldn't start metadata transaction on {0}")]
-    MetaTransaction(PathBuf, #[source] redb::TransactionError),
-
-    #[error("couldn't open table {1} on {0}")]
-    MetaTable(PathBuf, String, #[source] redb::TableError),
-
-    #[error("couldn't write filesystem type to {0}")]
-    WriteFs(PathBuf, #[source] redb::StorageError),
-
-    #[error("couldn't get partition item")]
-    NextItem(#[source] obfw::ps4::PartError),
-
-    #[error("unexpected file {0}")]
-    UnexpectedFile(String),
-
-    #[error("couldn't create {0}")]
-    CreateDirectory(PathBuf, #[source] std::io::Error),
-
-    #[error("couldn't create {0}")]
-    CreateFile(PathBuf, #[source] std::io::Error),
-
-    #[error("couldn't extract {0} to {1}")]
-    ExtractFile(String, PathBuf, #[source] std::io::Error),
-
-    #[error("couldn't commit metadata transaction to {0}")]
-    MetaCommit(PathBuf, #[source] redb::CommitError),
 }
 
 /// Represents an error when [`run_setup()`] fails.
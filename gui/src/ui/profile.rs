@@ -3,6 +3,7 @@ use crate::profile::{DisplayResolution, Profile};
 use slint::{Model, ModelNotify, ModelTracker, SharedString};
 use std::any::Any;
 use std::cell::{RefCell, RefMut};
+use std::path::PathBuf;
 use std::rc::Rc;
 
 /// Implementation of [`Model`] for [`DisplayResolution`].
@@ -74,6 +75,19 @@ impl ProfileModel {
         let p = &profiles[row];
 
         dst.set_selected_resolution(self.resolutions.position(p.display_resolution()).unwrap());
+        dst.set_app_image(
+            p.app_image()
+                .and_then(|v| v.to_str())
+                .unwrap_or_default()
+                .into(),
+        );
+        dst.set_host_dir(
+            p.host_dir()
+                .and_then(|v| v.to_str())
+                .unwrap_or_default()
+                .into(),
+        );
+        dst.set_host_dir_writable(p.host_dir_writable());
     }
 
     /// # Panics
@@ -84,6 +98,17 @@ impl ProfileModel {
         let p = &mut profiles[row];
 
         p.set_display_resolution(self.resolutions.get(src.get_selected_resolution()).unwrap());
+        p.set_app_image(
+            Some(src.get_app_image().as_str())
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from),
+        );
+        p.set_host_dir(
+            Some(src.get_host_dir().as_str())
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from),
+        );
+        p.set_host_dir_writable(src.get_host_dir_writable());
 
         RefMut::map(profiles, move |v| &mut v[row])
     }
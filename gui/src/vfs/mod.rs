@@ -3,6 +3,12 @@ use redb::{TableDefinition, TypeName};
 
 pub const FS_TYPE: TableDefinition<(), FsType> = TableDefinition::new("fs_type");
 
+/// Attributes of each file and directory on the partition, keyed by its path on the partition
+/// (e.g. `/system/common`). The path of the root directory is `/`.
+///
+/// Each path has its own entry so a hard link will have the same value on all of its paths.
+pub const NODES: TableDefinition<&str, NodeAttrs> = TableDefinition::new("nodes");
+
 /// Filesystem type.
 #[repr(u16)]
 #[derive(Debug, Clone, Copy, IntoPrimitive, TryFromPrimitive)]
@@ -39,3 +45,134 @@ impl redb::Value for FsType {
         TypeName::new("obliteration::FsType")
     }
 }
+
+/// Attributes of a file or directory on the partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeAttrs {
+    pub ty: NodeType,
+    pub mode: u16,
+    pub nlink: u16,
+    pub uid: i32,
+    pub gid: i32,
+    pub size: u64,
+    pub atime: NodeTime,
+    pub mtime: NodeTime,
+    pub ctime: NodeTime,
+    pub birthtime: NodeTime,
+}
+
+impl NodeAttrs {
+    const LEN: usize = 1 + 2 + 2 + 4 + 4 + 8 + NodeTime::LEN * 4;
+}
+
+impl redb::Value for NodeAttrs {
+    type SelfType<'a> = Self;
+    type AsBytes<'a> = [u8; NodeAttrs::LEN];
+
+    fn fixed_width() -> Option<usize> {
+        Some(Self::LEN)
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> Self::SelfType<'a>
+    where
+        Self: 'a,
+    {
+        let mut r = Decoder(data);
+
+        Self {
+            ty: u8::from_le_bytes(r.read()).try_into().unwrap(),
+            mode: u16::from_le_bytes(r.read()),
+            nlink: u16::from_le_bytes(r.read()),
+            uid: i32::from_le_bytes(r.read()),
+            gid: i32::from_le_bytes(r.read()),
+            size: u64::from_le_bytes(r.read()),
+            atime: NodeTime::decode(&mut r),
+            mtime: NodeTime::decode(&mut r),
+            ctime: NodeTime::decode(&mut r),
+            birthtime: NodeTime::decode(&mut r),
+        }
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> Self::AsBytes<'a>
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        let mut buf = [0; Self::LEN];
+        let mut w = Encoder(&mut buf);
+
+        w.write(&[value.ty.into()]);
+        w.write(&value.mode.to_le_bytes());
+        w.write(&value.nlink.to_le_bytes());
+        w.write(&value.uid.to_le_bytes());
+        w.write(&value.gid.to_le_bytes());
+        w.write(&value.size.to_le_bytes());
+        value.atime.encode(&mut w);
+        value.mtime.encode(&mut w);
+        value.ctime.encode(&mut w);
+        value.birthtime.encode(&mut w);
+
+        buf
+    }
+
+    fn type_name() -> TypeName {
+        TypeName::new("obliteration::NodeAttrs")
+    }
+}
+
+/// Type of [`NodeAttrs`].
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+pub enum NodeType {
+    File = 1,
+    Directory,
+    Link,
+}
+
+/// Timestamp of [`NodeAttrs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeTime {
+    pub sec: i64,
+    pub nsec: u32,
+}
+
+impl NodeTime {
+    const LEN: usize = 8 + 4;
+
+    fn decode(r: &mut Decoder) -> Self {
+        Self {
+            sec: i64::from_le_bytes(r.read()),
+            nsec: u32::from_le_bytes(r.read()),
+        }
+    }
+
+    fn encode(&self, w: &mut Encoder) {
+        w.write(&self.sec.to_le_bytes());
+        w.write(&self.nsec.to_le_bytes());
+    }
+}
+
+/// Sequential reader for [`redb::Value::from_bytes()`].
+struct Decoder<'a>(&'a [u8]);
+
+impl Decoder<'_> {
+    fn read<const N: usize>(&mut self) -> [u8; N] {
+        let (v, r) = self.0.split_first_chunk().unwrap();
+
+        self.0 = r;
+
+        *v
+    }
+}
+
+/// Sequential writer for [`redb::Value::as_bytes()`].
+struct Encoder<'a>(&'a mut [u8]);
+
+impl Encoder<'_> {
+    fn write(&mut self, data: &[u8]) {
+        let (v, r) = std::mem::take(&mut self.0).split_at_mut(data.len());
+
+        v.copy_from_slice(data);
+        self.0 = r;
+    }
+}
@@ -0,0 +1,185 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use super::{Fs, FsError};
+use crate::hv::{Cpu, CpuExit, CpuIo, Hypervisor};
+use crate::vmm::hw::{read_bytes, read_usize, write_bytes, DeviceContext, MmioError};
+use config::{FsAttrs, FsMemory, FsOp, FsRequest};
+use std::error::Error;
+use std::mem::offset_of;
+use thiserror::Error;
+
+/// Implementation of [`DeviceContext`].
+pub struct Context<'a, H> {
+    dev: &'a Fs,
+    hv: &'a H,
+}
+
+impl<'a, H> Context<'a, H> {
+    pub fn new(dev: &'a Fs, hv: &'a H) -> Self {
+        Self { dev, hv }
+    }
+
+    fn exec(
+        &self,
+        exit: &mut impl CpuIo,
+        req: &FsRequest,
+        op: FsOp,
+    ) -> Result<Result<(u64, FsAttrs), FsError>, ExecError>
+    where
+        H: Hypervisor,
+    {
+        let r = match op {
+            FsOp::Mount => {
+                let name = self.read_name(exit, req)?;
+
+                self.dev
+                    .mount(&name)
+                    .map(|(ty, attrs)| (u16::from(ty).into(), attrs))
+            }
+            FsOp::Lookup => {
+                let name = self.read_name(exit, req)?;
+
+                self.dev.lookup(req.node, &name).map(|attrs| (0, attrs))
+            }
+            FsOp::GetAttr => self.dev.getattr(req.node).map(|attrs| (0, attrs)),
+            FsOp::Read => match self.dev.read(req.node, req.off, req.buf_len) {
+                Ok(data) => {
+                    write_bytes(exit, req.buf_addr, &data, self.hv)
+                        .map_err(ExecError::WriteBufFailed)?;
+
+                    Ok((data.len().try_into().unwrap(), FsAttrs::default()))
+                }
+                Err(e) => Err(e),
+            },
+            FsOp::ReadDir => match self.dev.readdir(req.node, req.off) {
+                Ok(Some((name, attrs))) => {
+                    if name.len() > req.buf_len {
+                        return Err(ExecError::BufTooSmall);
+                    }
+
+                    write_bytes(exit, req.buf_addr, name.as_bytes(), self.hv)
+                        .map_err(ExecError::WriteBufFailed)?;
+
+                    Ok((name.len().try_into().unwrap(), attrs))
+                }
+                Ok(None) => Ok((0, FsAttrs::default())),
+                Err(e) => Err(e),
+            },
+            FsOp::Write => {
+                // A short write is allowed so limit the size of the buffer we need to allocate.
+                let mut data = vec![0; req.buf_len.min(Fs::WRITE_MAX)];
+
+                read_bytes(exit, req.buf_addr, &mut data, self.hv)
+                    .map_err(ExecError::ReadBufFailed)?;
+
+                self.dev
+                    .write(req.node, req.off, &data)
+                    .map(|n| (n.try_into().unwrap(), FsAttrs::default()))
+            }
+            FsOp::Forget => self
+                .dev
+                .forget(req.node, req.off)
+                .map(|_| (0, FsAttrs::default())),
+        };
+
+        Ok(r)
+    }
+
+    fn read_name(&self, exit: &mut impl CpuIo, req: &FsRequest) -> Result<String, ExecError>
+    where
+        H: Hypervisor,
+    {
+        // The name on the PS4 cannot be longer than MAXPATHLEN.
+        if req.name_len > 1024 {
+            return Err(ExecError::NameTooLong);
+        }
+
+        let mut name = vec![0; req.name_len];
+
+        read_bytes(exit, req.name_addr, &mut name, self.hv).map_err(ExecError::ReadNameFailed)?;
+
+        String::from_utf8(name).map_err(|_| ExecError::InvalidName)
+    }
+}
+
+impl<H: Hypervisor, C: Cpu> DeviceContext<C> for Context<'_, H> {
+    fn mmio(
+        &mut self,
+        exit: &mut <C::Exit<'_> as CpuExit>::Io,
+    ) -> Result<Option<bool>, Box<dyn Error + Send + Sync>> {
+        // Check field.
+        let off = exit.addr() - self.dev.addr;
+
+        if off != offset_of!(FsMemory, exec) {
+            return Err(Box::new(ExecError::UnknownField(off)));
+        }
+
+        // Read the request.
+        let addr = read_usize(exit).map_err(|e| ExecError::ReadFailed(off, e))?;
+        let mut buf = [0u8; size_of::<FsRequest>()];
+
+        read_bytes(exit, addr, &mut buf, self.hv).map_err(ExecError::ReadRequestFailed)?;
+
+        let mut req = unsafe { buf.as_ptr().cast::<FsRequest>().read_unaligned() };
+        let op = FsOp::try_from(req.op).map_err(|_| ExecError::InvalidOp(req.op))?;
+
+        // Execute.
+        match self.exec(exit, &req, op)? {
+            Ok((out, attrs)) => {
+                req.errno = 0;
+                req.out = out;
+                req.attrs = attrs;
+            }
+            Err(e) => {
+                req.errno = e.0;
+                req.out = 0;
+                req.attrs = FsAttrs::default();
+            }
+        }
+
+        // Write the result back.
+        let data = unsafe {
+            std::slice::from_raw_parts((&raw const req).cast::<u8>(), size_of::<FsRequest>())
+        };
+
+        write_bytes(exit, addr, data, self.hv).map_err(ExecError::WriteRequestFailed)?;
+
+        Ok(None)
+    }
+}
+
+/// Represents an error when [`Context::mmio()`] fails.
+#[derive(Debug, Error)]
+enum ExecError {
+    #[error("unknown field at offset {0:#x}")]
+    UnknownField(usize),
+
+    #[error("couldn't read data for offset {0:#x}")]
+    ReadFailed(usize, #[source] MmioError),
+
+    #[error("couldn't read the request")]
+    ReadRequestFailed(#[source] MmioError),
+
+    #[error("{0:#x} is not a valid operation")]
+    InvalidOp(u8),
+
+    #[error("name too long")]
+    NameTooLong,
+
+    #[error("couldn't read the name")]
+    ReadNameFailed(#[source] MmioError),
+
+    #[error("invalid name")]
+    InvalidName,
+
+    #[error("the buffer is too small")]
+    BufTooSmall,
+
+    #[error("couldn't read the buffer")]
+    ReadBufFailed(#[source] MmioError),
+
+    #[error("couldn't write the buffer")]
+    WriteBufFailed(#[source] MmioError),
+
+    #[error("couldn't write the request")]
+    WriteRequestFailed(#[source] MmioError),
+}
@@ -0,0 +1,656 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use self::context::Context;
+use super::{Device, DeviceContext};
+use crate::data::DataMgr;
+use crate::hv::Hypervisor;
+use crate::vfs::{NodeAttrs, NodeTime, NodeType, FS_TYPE, NODES};
+use config::{FsAttrs, FsMemory, FsNodeType, FsTime, FsType};
+use redb::{Database, ReadableTable, TableError};
+use std::collections::HashMap;
+use std::fs::{File, Metadata, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::num::NonZero;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod context;
+
+/// Virtual device to access the filesystems on the host.
+///
+/// Each volume on this device is a partition that was extracted during firmware installation. The
+/// attributes of each item are taken from the metadata database of the partition, which was
+/// populated by the installer. A volume can also be a disk image on the host, which the kernel will
+/// parse by itself, or a directory on the host for development. The state of this device is shared
+/// among all CPUs.
+pub struct Fs {
+    addr: usize,
+    len: NonZero<usize>,
+    data: Arc<DataMgr>,
+    images: HashMap<String, PathBuf>,
+    host: Option<HostDir>,
+    state: Mutex<State>,
+}
+
+impl Fs {
+    /// Maximum number of bytes for each read request.
+    const READ_MAX: usize = 1024 * 1024;
+
+    /// Maximum number of bytes for each write request.
+    const WRITE_MAX: usize = 1024 * 1024;
+
+    pub fn new(
+        addr: usize,
+        block_size: NonZero<usize>,
+        data: Arc<DataMgr>,
+        images: HashMap<String, PathBuf>,
+        host: Option<HostDir>,
+    ) -> Self {
+        let len = size_of::<FsMemory>()
+            .checked_next_multiple_of(block_size.get())
+            .and_then(NonZero::new)
+            .unwrap();
+
+        Self {
+            addr,
+            len,
+            data,
+            images,
+            host,
+            state: Mutex::default(),
+        }
+    }
+
+    pub fn create_context<'a, H: Hypervisor>(
+        &'a self,
+        hv: &'a H,
+    ) -> Box<dyn DeviceContext<H::Cpu<'a>> + 'a> {
+        Box::new(Context::new(self, hv))
+    }
+
+    fn mount(&self, name: &str) -> Result<(FsType, FsAttrs), FsError> {
+        if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+            return Err(FsError::EINVAL);
+        }
+
+        // Check if the volume already mounted.
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(v) = state.volumes.get(name) {
+            let ty = v.ty;
+            let root = v.root;
+
+            return state.getattr(root).map(|attrs| (ty, attrs));
+        }
+
+        // Check if the volume is a disk image.
+        if let Some(path) = self.images.get(name) {
+            let top = path.canonicalize().map_err(FsError::from)?.into();
+            let root = state.alloc(path.clone(), "/".into(), &top, &Arc::default(), true, false);
+            let attrs = state.getattr(root)?;
+
+            if attrs.ty != u8::from(FsNodeType::File) {
+                return Err(FsError::EINVAL);
+            }
+
+            state.volumes.insert(
+                name.to_owned(),
+                Volume {
+                    ty: FsType::Image,
+                    root,
+                },
+            );
+
+            return Ok((FsType::Image, attrs));
+        }
+
+        // Check if the volume is the directory on the host.
+        if let Some(host) = self.host.as_ref().filter(|_| name == HostDir::VOLUME) {
+            let top = host.path.canonicalize().map_err(FsError::from)?.into();
+            let root = state.alloc(
+                host.path.clone(),
+                "/".into(),
+                &top,
+                &Arc::default(),
+                true,
+                host.writable,
+            );
+            let attrs = state.getattr(root)?;
+
+            if attrs.ty != u8::from(FsNodeType::Directory) {
+                return Err(FsError::ENOTDIR);
+            }
+
+            state.volumes.insert(
+                name.to_owned(),
+                Volume {
+                    ty: FsType::Host,
+                    root,
+                },
+            );
+
+            return Ok((FsType::Host, attrs));
+        }
+
+        // Load metadata. We don't keep the database open since redb allows only a single instance
+        // for each file.
+        let meta = self.data.partitions().meta(name);
+
+        if !meta.is_file() {
+            return Err(FsError::ENOENT);
+        }
+
+        let meta = Database::open(&meta)
+            .map_err(|_| FsError::EIO)?
+            .begin_read()
+            .map_err(|_| FsError::EIO)?;
+        let ty = meta
+            .open_table(FS_TYPE)
+            .map_err(|_| FsError::EIO)?
+            .get(())
+            .map_err(|_| FsError::EIO)?
+            .ok_or(FsError::EIO)?
+            .value();
+
+        let ty = match ty {
+            crate::vfs::FsType::ExFat => FsType::ExFat,
+        };
+
+        // The partitions that were installed before we store the attributes don't have this table.
+        let mut nodes = HashMap::new();
+
+        match meta.open_table(NODES) {
+            Ok(t) => {
+                for e in t.iter().map_err(|_| FsError::EIO)? {
+                    let (k, v) = e.map_err(|_| FsError::EIO)?;
+
+                    nodes.insert(k.value().to_owned(), v.value());
+                }
+            }
+            Err(TableError::TableDoesNotExist(_)) => {}
+            Err(_) => return Err(FsError::EIO),
+        }
+
+        // Get the root directory.
+        let nodes = Arc::new(nodes);
+        let path = self.data.partitions().data(name);
+        let top = path.canonicalize().map_err(FsError::from)?.into();
+        let root = state.alloc(path, "/".into(), &top, &nodes, true, false);
+        let attrs = state.getattr(root)?;
+
+        if attrs.ty != u8::from(FsNodeType::Directory) {
+            return Err(FsError::ENOTDIR);
+        }
+
+        state.volumes.insert(name.to_owned(), Volume { ty, root });
+
+        Ok((ty, attrs))
+    }
+
+    fn lookup(&self, node: u64, name: &str) -> Result<FsAttrs, FsError> {
+        let mut state = self.state.lock().unwrap();
+        let dir = state.node(node)?;
+
+        if !dir.real_path()?.is_dir() {
+            return Err(FsError::ENOTDIR);
+        }
+
+        // Get the path.
+        let (path, key) = if name == ".." {
+            if dir.root {
+                return Err(FsError::EINVAL);
+            }
+
+            let key = match dir.key.rsplit_once('/') {
+                Some(("", _)) => "/",
+                Some((v, _)) => v,
+                None => unreachable!(),
+            };
+
+            (dir.path.parent().unwrap().to_owned(), key.to_owned())
+        } else if name.is_empty() || name == "." || name.contains(['/', '\\']) {
+            return Err(FsError::EINVAL);
+        } else if name.len() > 255 {
+            return Err(FsError::ENAMETOOLONG);
+        } else {
+            (dir.path.join(name), dir.child(name))
+        };
+
+        // Get the node. The path may be a symbolic link so make sure it does not point outside the
+        // volume before we allocate a node for it.
+        resolve(&path, &dir.top)?;
+
+        let top = dir.top.clone();
+        let attrs = dir.attrs.clone();
+        let writable = dir.writable;
+        let id = state.alloc(path, key, &top, &attrs, false, writable);
+        let attrs = state.getattr(id);
+
+        // Only a successful lookup add a reference.
+        match attrs {
+            Ok(_) => state.node_mut(id)?.refs += 1,
+            Err(_) => state.release(id, 0)?,
+        }
+
+        attrs
+    }
+
+    fn getattr(&self, node: u64) -> Result<FsAttrs, FsError> {
+        self.state.lock().unwrap().getattr(node)
+    }
+
+    fn read(&self, node: u64, off: u64, len: usize) -> Result<Vec<u8>, FsError> {
+        let state = self.state.lock().unwrap();
+        let node = state.node(node)?;
+
+        let path = node.real_path()?;
+
+        if path.is_dir() {
+            return Err(FsError::EISDIR);
+        }
+
+        // Read.
+        let mut file = File::open(path).map_err(FsError::from)?;
+        let len = len.min(Self::READ_MAX);
+        let mut buf = Vec::with_capacity(len);
+
+        // The capacity of the buffer may be larger than what we requested so we can't use it here.
+        file.seek(SeekFrom::Start(off)).map_err(FsError::from)?;
+        file.take(len.try_into().unwrap())
+            .read_to_end(&mut buf)
+            .map_err(FsError::from)?;
+
+        Ok(buf)
+    }
+
+    fn readdir(&self, node: u64, index: u64) -> Result<Option<(String, FsAttrs)>, FsError> {
+        let mut state = self.state.lock().unwrap();
+        let dir = state.node(node)?;
+        let path = dir.real_path()?;
+
+        if !path.is_dir() {
+            return Err(FsError::ENOTDIR);
+        }
+
+        // Load the entries when the caller start reading from the beginning so the caller will see
+        // the same list of entries until it has finished.
+        if index == 0 || dir.entries.is_none() {
+            let mut entries = Vec::new();
+
+            for e in std::fs::read_dir(&path).map_err(FsError::from)? {
+                let e = e.map_err(FsError::from)?;
+
+                // Hide the symbolic links that point outside the volume.
+                if e.file_type().map_err(FsError::from)?.is_symlink()
+                    && resolve(&e.path(), &dir.top).is_err()
+                {
+                    continue;
+                }
+
+                if let Ok(v) = e.file_name().into_string() {
+                    if v.len() <= 255 {
+                        entries.push((v, None));
+                    }
+                }
+            }
+
+            entries.sort_unstable();
+
+            // Release the nodes from the previous list.
+            let old = state.node_mut(node)?.entries.replace(entries);
+
+            for id in old.into_iter().flatten().filter_map(|(_, id)| id) {
+                state.release(id, 1)?;
+            }
+        }
+
+        // Get the entry.
+        let dir = state.node(node)?;
+        let i = match usize::try_from(index)
+            .ok()
+            .filter(|&i| i < dir.entries.as_ref().unwrap().len())
+        {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        let (name, id) = dir.entries.as_ref().unwrap()[i].clone();
+        let id = match id {
+            Some(v) => v,
+            None => {
+                let path = dir.path.join(&name);
+
+                resolve(&path, &dir.top)?;
+
+                // The list hold a reference to each node it has returned so the ID of the entry
+                // remains the same until the list is reloaded.
+                let key = dir.child(&name);
+                let top = dir.top.clone();
+                let attrs = dir.attrs.clone();
+                let writable = dir.writable;
+                let id = state.alloc(path, key, &top, &attrs, false, writable);
+
+                state.node_mut(id)?.refs += 1;
+                state.node_mut(node)?.entries.as_mut().unwrap()[i].1 = Some(id);
+
+                id
+            }
+        };
+
+        state.getattr(id).map(|attrs| Some((name, attrs)))
+    }
+
+    fn write(&self, node: u64, off: u64, data: &[u8]) -> Result<usize, FsError> {
+        let state = self.state.lock().unwrap();
+        let node = state.node(node)?;
+
+        if !node.writable {
+            return Err(FsError::EROFS);
+        }
+
+        let path = node.real_path()?;
+
+        if path.is_dir() {
+            return Err(FsError::EISDIR);
+        }
+
+        // Write.
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(path)
+            .map_err(FsError::from)?;
+
+        file.seek(SeekFrom::Start(off)).map_err(FsError::from)?;
+        file.write_all(data).map_err(FsError::from)?;
+
+        Ok(data.len())
+    }
+
+    fn forget(&self, node: u64, n: u64) -> Result<(), FsError> {
+        self.state.lock().unwrap().release(node, n)
+    }
+}
+
+impl Device for Fs {
+    fn name(&self) -> &str {
+        "Virtual Filesystem"
+    }
+
+    fn addr(&self) -> usize {
+        self.addr
+    }
+
+    fn len(&self) -> NonZero<usize> {
+        self.len
+    }
+}
+
+/// Directory on the host to expose as a volume on [`Fs`].
+pub struct HostDir {
+    pub path: PathBuf,
+    pub writable: bool,
+}
+
+impl HostDir {
+    /// Name of the volume.
+    const VOLUME: &str = "host";
+}
+
+/// Mutable state of [`Fs`].
+#[derive(Default)]
+struct State {
+    volumes: HashMap<String, Volume>,
+    nodes: Vec<Option<Node>>,
+    free: Vec<usize>,
+    ids: HashMap<PathBuf, u64>,
+}
+
+impl State {
+    /// Returns the existing node if `path` already has one. The new node has no references so the
+    /// caller is responsible for adding one or releasing it.
+    fn alloc(
+        &mut self,
+        path: PathBuf,
+        key: String,
+        top: &Arc<PathBuf>,
+        attrs: &Arc<HashMap<String, NodeAttrs>>,
+        root: bool,
+        writable: bool,
+    ) -> u64 {
+        if let Some(&v) = self.ids.get(&path) {
+            return v;
+        }
+
+        let node = Node {
+            path: path.clone(),
+            key,
+            top: top.clone(),
+            attrs: attrs.clone(),
+            root,
+            writable,
+            refs: 0,
+            entries: None,
+        };
+
+        // Reuse the slot of the released node if available. Zero is not a valid ID.
+        let i = match self.free.pop() {
+            Some(i) => {
+                self.nodes[i] = Some(node);
+                i
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+
+        let id = (i + 1).try_into().unwrap();
+
+        self.ids.insert(path, id);
+
+        id
+    }
+
+    /// Release `n` references to `id` and free it if it does not have any references left. The
+    /// root of a volume is never freed.
+    fn release(&mut self, id: u64, n: u64) -> Result<(), FsError> {
+        let node = self.node_mut(id)?;
+
+        node.refs = node.refs.saturating_sub(n);
+
+        if node.refs != 0 || node.root {
+            return Ok(());
+        }
+
+        // Free the node then release the nodes on its list.
+        let i = usize::try_from(id - 1).unwrap();
+        let node = self.nodes[i].take().unwrap();
+
+        self.ids.remove(&node.path);
+        self.free.push(i);
+
+        for id in node.entries.into_iter().flatten().filter_map(|(_, id)| id) {
+            self.release(id, 1)?;
+        }
+
+        Ok(())
+    }
+
+    fn node(&self, id: u64) -> Result<&Node, FsError> {
+        usize::try_from(id)
+            .ok()
+            .and_then(|i| i.checked_sub(1))
+            .and_then(|i| self.nodes.get(i))
+            .and_then(|n| n.as_ref())
+            .ok_or(FsError::EINVAL)
+    }
+
+    fn node_mut(&mut self, id: u64) -> Result<&mut Node, FsError> {
+        usize::try_from(id)
+            .ok()
+            .and_then(|i| i.checked_sub(1))
+            .and_then(|i| self.nodes.get_mut(i))
+            .and_then(|n| n.as_mut())
+            .ok_or(FsError::EINVAL)
+    }
+
+    fn getattr(&self, id: u64) -> Result<FsAttrs, FsError> {
+        let node = self.node(id)?;
+        let meta = std::fs::metadata(node.real_path()?).map_err(FsError::from)?;
+
+        // Use the attributes from the dump if available.
+        let attrs = match node.attrs.get(&node.key) {
+            Some(v) => v,
+            None => return Ok(Self::attrs(id, node.writable, &meta)),
+        };
+
+        let ty = match attrs.ty {
+            NodeType::File => FsNodeType::File,
+            NodeType::Directory => FsNodeType::Directory,
+            NodeType::Link => FsNodeType::Link,
+        };
+
+        let time = |v: NodeTime| FsTime {
+            sec: v.sec,
+            nsec: v.nsec.into(),
+        };
+
+        Ok(FsAttrs {
+            id,
+            ty: ty.into(),
+            mode: attrs.mode,
+            nlink: attrs.nlink,
+            uid: attrs.uid,
+            gid: attrs.gid,
+            size: attrs.size,
+            atime: time(attrs.atime),
+            mtime: time(attrs.mtime),
+            ctime: time(attrs.ctime),
+            birthtime: time(attrs.birthtime),
+            writable: node.writable.into(),
+        })
+    }
+
+    fn attrs(id: u64, writable: bool, meta: &Metadata) -> FsAttrs {
+        let mtime = meta.modified().map(time).unwrap_or_default();
+        let (ty, nlink) = if meta.is_dir() {
+            (FsNodeType::Directory, 2)
+        } else {
+            (FsNodeType::File, 1)
+        };
+
+        // exFAT does not have permissions and only the directory on the host can be writable.
+        FsAttrs {
+            id,
+            ty: ty.into(),
+            mode: if writable { 0o777 } else { 0o555 },
+            nlink,
+            uid: 0,
+            gid: 0,
+            size: meta.len(),
+            atime: meta.accessed().map(time).unwrap_or(mtime),
+            mtime,
+            ctime: mtime,
+            birthtime: meta.created().map(time).unwrap_or(mtime),
+            writable: writable.into(),
+        }
+    }
+}
+
+/// Volume on [`Fs`].
+struct Volume {
+    ty: FsType,
+    root: u64,
+}
+
+/// Node on [`Fs`].
+struct Node {
+    path: PathBuf,
+    key: String,
+    top: Arc<PathBuf>,
+    attrs: Arc<HashMap<String, NodeAttrs>>,
+    root: bool,
+    writable: bool,
+    refs: u64,
+    entries: Option<Vec<(String, Option<u64>)>>,
+}
+
+impl Node {
+    /// Returns the path on the host with all symbolic links resolved.
+    ///
+    /// The host may have changed a path component to a symbolic link after we looked it up so this
+    /// needs to be called every time we access the node.
+    fn real_path(&self) -> Result<PathBuf, FsError> {
+        resolve(&self.path, &self.top)
+    }
+
+    /// Returns the key on [`NODES`] for `name` in this directory.
+    fn child(&self, name: &str) -> String {
+        if self.key == "/" {
+            format!("/{name}")
+        } else {
+            format!("{}/{name}", self.key)
+        }
+    }
+}
+
+/// Error number on the PS4 to return to the kernel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FsError(i32);
+
+impl FsError {
+    const ENOENT: Self = Self(2);
+    const EIO: Self = Self(5);
+    const EACCES: Self = Self(13);
+    const ENOTDIR: Self = Self(20);
+    const EISDIR: Self = Self(21);
+    const EINVAL: Self = Self(22);
+    const EROFS: Self = Self(30);
+    const ENAMETOOLONG: Self = Self(63);
+}
+
+impl From<std::io::Error> for FsError {
+    fn from(value: std::io::Error) -> Self {
+        match value.kind() {
+            ErrorKind::NotFound => Self::ENOENT,
+            ErrorKind::PermissionDenied => Self::EACCES,
+            ErrorKind::NotADirectory => Self::ENOTDIR,
+            ErrorKind::IsADirectory => Self::EISDIR,
+            ErrorKind::ReadOnlyFilesystem => Self::EROFS,
+            _ => Self::EIO,
+        }
+    }
+}
+
+/// Resolves all symbolic links in `path` and checks if the result is inside `top`, which must
+/// already be canonicalized.
+fn resolve(path: &Path, top: &Path) -> Result<PathBuf, FsError> {
+    let path = path.canonicalize().map_err(FsError::from)?;
+
+    if !path.starts_with(top) {
+        return Err(FsError::EACCES);
+    }
+
+    Ok(path)
+}
+
+fn time(v: SystemTime) -> FsTime {
+    match v.duration_since(UNIX_EPOCH) {
+        Ok(v) => FsTime {
+            sec: v.as_secs().try_into().unwrap_or(i64::MAX),
+            nsec: v.subsec_nanos().into(),
+        },
+        Err(e) => {
+            let v = e.duration();
+            let mut sec = -i64::try_from(v.as_secs()).unwrap_or(i64::MAX);
+            let mut nsec = i64::from(v.subsec_nanos());
+
+            if nsec != 0 {
+                sec -= 1;
+                nsec = 1_000_000_000 - nsec;
+            }
+
+            FsTime { sec, nsec }
+        }
+    }
+}
@@ -1,18 +1,31 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 pub use self::console::*;
+pub use self::fs::*;
+pub use self::stats::*;
 pub use self::vmm::*;
 
+use crate::data::DataMgr;
 use crate::hv::{Cpu, CpuExit, CpuIo, Hypervisor, IoBuf, LockedAddr};
-use std::collections::BTreeMap;
+use std::cmp::min;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::num::NonZero;
+use std::path::PathBuf;
 use std::sync::Arc;
 use thiserror::Error;
 
 mod console;
+mod fs;
+mod stats;
 mod vmm;
 
-pub fn setup_devices(start_addr: usize, block_size: NonZero<usize>) -> DeviceTree {
+pub fn setup_devices(
+    start_addr: usize,
+    block_size: NonZero<usize>,
+    data: &Arc<DataMgr>,
+    images: HashMap<String, PathBuf>,
+    host: Option<HostDir>,
+) -> DeviceTree {
     let mut b = MapBuilder {
         map: BTreeMap::new(),
         next: start_addr,
@@ -20,10 +33,14 @@ pub fn setup_devices(start_addr: usize, block_size: NonZero<usize>) -> DeviceTre
 
     let vmm = b.push(|addr| Vmm::new(addr, block_size));
     let console = b.push(|addr| Console::new(addr, block_size));
+    let stats = b.push(|addr| Stats::new(addr, block_size));
+    let fs = b.push(|addr| Fs::new(addr, block_size, data.clone(), images, host));
 
     DeviceTree {
         vmm,
         console,
+        stats,
+        fs,
         map: b.map,
     }
 }
@@ -75,12 +92,86 @@ fn read_ptr<'a>(
         .ok_or(MmioError::InvalidAddr { vaddr, paddr })
 }
 
+/// Copy data from the guest virtual address `vaddr` to `buf`. Unlike [`read_ptr()`], the memory
+/// does not need to be physically contiguous.
+fn read_bytes(
+    exit: &mut impl CpuIo,
+    vaddr: usize,
+    buf: &mut [u8],
+    hv: &impl Hypervisor,
+) -> Result<(), MmioError> {
+    let mut off = 0;
+
+    for_each_page(exit, vaddr, buf.len(), hv, |mut src| {
+        let len = src.len().get();
+        let src = unsafe { std::slice::from_raw_parts(src.as_mut_ptr(), len) };
+
+        buf[off..(off + len)].copy_from_slice(src);
+        off += len;
+    })
+}
+
+/// Copy `data` to the guest virtual address `vaddr`. Unlike [`read_ptr()`], the memory does not
+/// need to be physically contiguous.
+fn write_bytes(
+    exit: &mut impl CpuIo,
+    vaddr: usize,
+    data: &[u8],
+    hv: &impl Hypervisor,
+) -> Result<(), MmioError> {
+    let mut off = 0;
+
+    for_each_page(exit, vaddr, data.len(), hv, |mut dst| {
+        let len = dst.len().get();
+        let dst = unsafe { std::slice::from_raw_parts_mut(dst.as_mut_ptr(), len) };
+
+        dst.copy_from_slice(&data[off..(off + len)]);
+        off += len;
+    })
+}
+
+/// Invoke `f` for each physically contiguous range of `len` bytes starting at the guest virtual
+/// address `vaddr`.
+fn for_each_page(
+    exit: &mut impl CpuIo,
+    vaddr: usize,
+    len: usize,
+    hv: &impl Hypervisor,
+    mut f: impl FnMut(LockedAddr),
+) -> Result<(), MmioError> {
+    // We use the smallest page size on all supported architectures so we don't need to know the
+    // actual page size of the guest.
+    const PAGE_SIZE: usize = 0x1000;
+
+    let end = vaddr.checked_add(len).ok_or(MmioError::InvalidData)?;
+    let mut next = vaddr;
+
+    while next < end {
+        let len = min(end - next, PAGE_SIZE - next % PAGE_SIZE);
+        let paddr = exit
+            .cpu()
+            .translate(next)
+            .map_err(|e| MmioError::TranslateVaddrFailed(next, Box::new(e)))?;
+        let data = hv
+            .ram()
+            .lock(paddr, NonZero::new(len).unwrap())
+            .ok_or(MmioError::InvalidAddr { vaddr: next, paddr })?;
+
+        f(data);
+        next += len;
+    }
+
+    Ok(())
+}
+
 /// Contains all virtual devices (except RAM) for the VM.
 ///
 /// All devices guarantee to not overlapped.
 pub struct DeviceTree {
     vmm: Arc<Vmm>,
     console: Arc<Console>,
+    stats: Arc<Stats>,
+    fs: Arc<Fs>,
     map: BTreeMap<usize, Arc<dyn Device>>,
 }
 
@@ -93,6 +184,14 @@ impl DeviceTree {
         self.console.as_ref()
     }
 
+    pub fn stats(&self) -> &Stats {
+        self.stats.as_ref()
+    }
+
+    pub fn fs(&self) -> &Fs {
+        self.fs.as_ref()
+    }
+
     /// Returns iterator ordered by physical address.
     pub fn all(&self) -> impl Iterator<Item = (usize, &dyn Device)> + '_ {
         self.map.iter().map(|(addr, dev)| (*addr, dev.as_ref()))
@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use super::{KernelStats, Stats};
+use crate::hv::{Cpu, CpuExit, CpuIo, Hypervisor};
+use crate::vmm::channel::VmmStream;
+use crate::vmm::hw::{read_ptr, read_u8, DeviceContext, MmioError};
+use config::{MallocStats, StatsMemory, ZoneStats};
+use std::error::Error;
+use std::mem::offset_of;
+use std::num::NonZero;
+use thiserror::Error;
+
+/// Implementation of [`DeviceContext`].
+pub struct Context<'a, H> {
+    dev: &'a Stats,
+    hv: &'a H,
+    stats: &'a VmmStream<KernelStats>,
+    pending: KernelStats,
+}
+
+impl<'a, H> Context<'a, H> {
+    pub fn new(dev: &'a Stats, hv: &'a H, stats: &'a VmmStream<KernelStats>) -> Self {
+        Self {
+            dev,
+            hv,
+            stats,
+            pending: KernelStats::default(),
+        }
+    }
+}
+
+impl<H: Hypervisor, C: Cpu> DeviceContext<C> for Context<'_, H> {
+    fn mmio(
+        &mut self,
+        exit: &mut <C::Exit<'_> as CpuExit>::Io,
+    ) -> Result<Option<bool>, Box<dyn Error + Send + Sync>> {
+        // Check field.
+        let off = exit.addr() - self.dev.addr;
+
+        if off == offset_of!(StatsMemory, zone) {
+            let len = const { NonZero::new(size_of::<ZoneStats>()).unwrap() };
+            let data = read_ptr(exit, len, self.hv).map_err(|e| ExecError::ReadFailed(off, e))?;
+            let data = unsafe { data.as_ptr().cast::<ZoneStats>().read_unaligned() };
+
+            self.pending.zones.push(data);
+        } else if off == offset_of!(StatsMemory, malloc) {
+            let len = const { NonZero::new(size_of::<MallocStats>()).unwrap() };
+            let data = read_ptr(exit, len, self.hv).map_err(|e| ExecError::ReadFailed(off, e))?;
+            let data = unsafe { data.as_ptr().cast::<MallocStats>().read_unaligned() };
+
+            self.pending.mallocs.push(data);
+        } else if off == offset_of!(StatsMemory, commit) {
+            read_u8(exit).map_err(|e| ExecError::ReadFailed(off, e))?;
+
+            self.stats.send(std::mem::take(&mut self.pending));
+        } else {
+            return Err(Box::new(ExecError::UnknownField(off)));
+        }
+
+        Ok(None)
+    }
+}
+
+/// Represents an error when [`Context::mmio()`] fails.
+#[derive(Debug, Error)]
+enum ExecError {
+    #[error("unknown field at offset {0:#x}")]
+    UnknownField(usize),
+
+    #[error("couldn't read data for offset {0:#x}")]
+    ReadFailed(usize, #[source] MmioError),
+}
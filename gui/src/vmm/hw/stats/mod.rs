@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use self::context::Context;
+use super::{Device, DeviceContext};
+use crate::hv::Hypervisor;
+use crate::vmm::channel::VmmStream;
+use config::{MallocStats, StatsMemory, ZoneStats};
+use std::num::NonZero;
+
+mod context;
+
+/// Virtual device for the kernel to publish its memory statistics.
+pub struct Stats {
+    addr: usize,
+    len: NonZero<usize>,
+}
+
+impl Stats {
+    pub fn new(addr: usize, block_size: NonZero<usize>) -> Self {
+        let len = size_of::<StatsMemory>()
+            .checked_next_multiple_of(block_size.get())
+            .and_then(NonZero::new)
+            .unwrap();
+
+        Self { addr, len }
+    }
+
+    pub fn create_context<'a, H: Hypervisor>(
+        &'a self,
+        hv: &'a H,
+        stats: &'a VmmStream<KernelStats>,
+    ) -> Box<dyn DeviceContext<H::Cpu<'a>> + 'a> {
+        Box::new(Context::new(self, hv, stats))
+    }
+}
+
+impl Device for Stats {
+    fn name(&self) -> &str {
+        "Memory Statistics"
+    }
+
+    fn addr(&self) -> usize {
+        self.addr
+    }
+
+    fn len(&self) -> NonZero<usize> {
+        self.len
+    }
+}
+
+/// A snapshot of memory statistics from the kernel.
+#[derive(Default)]
+pub struct KernelStats {
+    pub zones: Vec<ZoneStats>,
+    pub mallocs: Vec<MallocStats>,
+}
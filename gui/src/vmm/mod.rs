@@ -1,12 +1,15 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 use self::arch::{GdbRegs, BREAKPOINT_SIZE};
+pub use self::hw::KernelStats;
+
 use self::channel::VmmStream;
-use self::hw::{setup_devices, Device, DeviceTree};
+use self::hw::{setup_devices, Device, DeviceTree, HostDir};
 use self::kernel::{
     Kernel, NoteError, PT_DYNAMIC, PT_GNU_EH_FRAME, PT_GNU_RELRO, PT_GNU_STACK, PT_LOAD, PT_NOTE,
     PT_PHDR,
 };
 use self::ram::{RamBuilder, RamMap};
+use crate::data::DataMgr;
 use crate::gdb::GdbHandler;
 use crate::hv::{CpuDebug, CpuExit, CpuIo, CpuRun, CpuStates, Hypervisor, Ram};
 use crate::profile::Profile;
@@ -49,12 +52,14 @@ pub struct Vmm<H> {
     breakpoint: Arc<Mutex<()>>,
     sw_breakpoints: HashMap<u64, [u8; BREAKPOINT_SIZE.get()]>,
     logs: Arc<VmmStream<(ConsoleType, String)>>,
+    stats: Arc<VmmStream<KernelStats>>,
     shutdown: Arc<AtomicBool>,
 }
 
 impl Vmm<()> {
     pub fn new(
         profile: &Profile,
+        data: &Arc<DataMgr>,
         kernel: &Path,
         shutdown: &Arc<AtomicBool>,
     ) -> Result<Vmm<impl Hypervisor>, VmmError> {
@@ -178,8 +183,23 @@ impl Vmm<()> {
         // Setup RAM.
         let ram_size = NonZero::new(1024 * 1024 * 1024 * 8).unwrap();
 
-        // Setup virtual devices.
-        let devices = Arc::new(setup_devices(ram_size.get(), block_size));
+        // Setup virtual devices. The PS4 mount the application image from /dev/lvd2.
+        let images = profile
+            .app_image()
+            .map(|v| (String::from("lvd2"), v.to_owned()))
+            .into_iter()
+            .collect();
+        let host = profile.host_dir().map(|v| HostDir {
+            path: v.to_owned(),
+            writable: profile.host_dir_writable(),
+        });
+        let devices = Arc::new(setup_devices(
+            ram_size.get(),
+            block_size,
+            data,
+            images,
+            host,
+        ));
 
         // Setup hypervisor.
         let mut hv = unsafe { crate::hv::new(8, ram_size, block_size, false) }
@@ -212,11 +232,21 @@ impl Vmm<()> {
         ram.alloc_stack(NonZero::new(1024 * 1024 * 2).unwrap())
             .map_err(VmmError::AllocateRamForStack)?;
 
+        // Allocate physical pages for the kernel.
+        let phys = ram
+            .alloc_phys(NonZero::new(1024 * 1024 * 256).unwrap())
+            .map_err(VmmError::AllocateRamForPhys)?;
+
         // Allocate arguments.
         let env = BootEnv::Vm(Vm {
             vmm: devices.vmm().addr(),
             console: devices.console().addr(),
+            stats: devices.stats().addr(),
+            fs: devices.fs().addr(),
             host_page_size,
+            dmap: self::ram::DMAP_ADDR,
+            phys_addr: phys.start,
+            phys_len: phys.end - phys.start,
         });
 
         ram.alloc_args(env, profile.kernel_config().clone())
@@ -236,6 +266,7 @@ impl Vmm<()> {
             breakpoint: Arc::default(),
             sw_breakpoints: HashMap::new(),
             logs: Arc::new(VmmStream::new(const { NonZero::new(100).unwrap() })),
+            stats: Arc::new(VmmStream::new(const { NonZero::new(10).unwrap() })),
             shutdown: shutdown.clone(),
         };
 
@@ -266,6 +297,7 @@ impl<H> Vmm<H> {
         // Poll.
         select_biased! {
             v = self.logs.recv().fuse() => VmmEvent::Log(v.0, v.1),
+            v = self.stats.recv().fuse() => VmmEvent::Stats(v),
             v = exit.fuse() => VmmEvent::Exit(v.0, v.1)
         }
     }
@@ -321,6 +353,7 @@ impl<H: Hypervisor> Vmm<H> {
             devices: self.devices.clone(),
             breakpoint: self.breakpoint.clone(),
             logs: self.logs.clone(),
+            stats: self.stats.clone(),
             shutdown: self.shutdown.clone(),
         };
 
@@ -396,10 +429,13 @@ impl<H: Hypervisor> Vmm<H> {
         let hv = args.hv.as_ref();
         let t = &args.devices;
         let logs = args.logs.as_ref();
+        let stats = args.stats.as_ref();
         let mut devices = BTreeMap::<usize, self::cpu::Device<'c, H::Cpu<'c>>>::new();
 
         self::cpu::Device::insert(&mut devices, t.console(), |d| d.create_context(hv, logs));
         self::cpu::Device::insert(&mut devices, t.vmm(), |d| d.create_context());
+        self::cpu::Device::insert(&mut devices, t.stats(), |d| d.create_context(hv, stats));
+        self::cpu::Device::insert(&mut devices, t.fs(), |d| d.create_context(hv));
 
         // Dispatch CPU events until shutdown.
         loop {
@@ -772,6 +808,7 @@ struct CpuArgs<H> {
     devices: Arc<DeviceTree>,
     breakpoint: Arc<Mutex<()>>,
     logs: Arc<VmmStream<(ConsoleType, String)>>,
+    stats: Arc<VmmStream<KernelStats>>,
     shutdown: Arc<AtomicBool>,
 }
 
@@ -779,6 +816,7 @@ struct CpuArgs<H> {
 pub enum VmmEvent {
     Exit(usize, Result<bool, CpuError>),
     Log(ConsoleType, String),
+    Stats(KernelStats),
 }
 
 /// Represents an error when [`Vmm::new()`] fails.
@@ -871,6 +909,9 @@ pub enum VmmError {
     #[error("couldn't allocate RAM for stack")]
     AllocateRamForStack(#[source] crate::hv::RamError),
 
+    #[error("couldn't allocate RAM for physical pages")]
+    AllocateRamForPhys(#[source] crate::hv::RamError),
+
     #[error("couldn't allocate RAM for arguments")]
     AllocateRamForArgs(#[source] crate::hv::RamError),
 
@@ -8,12 +8,16 @@ use std::num::NonZero;
 use std::ops::Range;
 use thiserror::Error;
 
+/// Virtual address where the whole RAM is directly mapped.
+pub const DMAP_ADDR: usize = 0xfffffe0000000000;
+
 /// Struct to build [`Ram`].
 pub struct RamBuilder<'a, M: RamMapper> {
     ram: &'a mut Ram<M>,
     next: usize,
     kern: Option<Range<usize>>,
     stack: Option<Range<usize>>,
+    phys: Option<Range<usize>>,
     args: Option<KernelArgs>,
 }
 
@@ -24,6 +28,7 @@ impl<'a, M: RamMapper> RamBuilder<'a, M> {
             next: 0,
             kern: None,
             stack: None,
+            phys: None,
             args: None,
         }
     }
@@ -59,6 +64,27 @@ impl<'a, M: RamMapper> RamBuilder<'a, M> {
         Ok(())
     }
 
+    /// Allocates a memory for the kernel to use as a physical pages. The kernel will access this
+    /// memory via [`DMAP_ADDR`] so this does not map it anywhere.
+    ///
+    /// # Panics
+    /// - If `len` is not multiplied by block size.
+    /// - If called a second time.
+    pub fn alloc_phys(&mut self, len: NonZero<usize>) -> Result<Range<usize>, RamError> {
+        assert!(self.phys.is_none());
+
+        let addr = self.next;
+
+        self.ram.alloc(addr, len)?;
+
+        let phys = addr..(addr + len.get());
+
+        self.phys = Some(phys.clone());
+        self.next += len.get();
+
+        Ok(phys)
+    }
+
     /// # Panics
     /// If called a second time.
     pub fn alloc_args(&mut self, env: BootEnv, conf: Config) -> Result<(), RamError> {
@@ -243,6 +269,14 @@ impl<M: RamMapper> RamBuilder<'_, M> {
 
         self.setup_4k_page_tables(pml4t, vaddr, ram.start, ram.end - ram.start)?;
 
+        // Setup page tables to map the whole RAM at DMAP_ADDR. We use 2M pages here to reduce the
+        // number of page tables. The unallocated part will never be accessed by the kernel.
+        let len = self.ram.len().get();
+
+        assert!(DMAP_ADDR + len <= kern_vaddr);
+
+        self.setup_2m_page_tables(pml4t, DMAP_ADDR, 0, len)?;
+
         // Relocate the kernel to virtual address.
         let map = RamMap {
             page_size,
@@ -340,6 +374,72 @@ impl<M: RamMapper> RamBuilder<'_, M> {
         Ok(())
     }
 
+    fn setup_2m_page_tables(
+        &mut self,
+        pml4t: &mut [usize; 512],
+        vaddr: usize,
+        paddr: usize,
+        len: usize,
+    ) -> Result<(), RamBuilderError> {
+        let ram = self.ram.host_addr().cast_mut(); // TODO: Make this safer.
+
+        assert_eq!(len % 0x200000, 0);
+
+        fn set_page_entry(entry: &mut usize, addr: usize) {
+            assert_eq!(addr & 0x7FF0000000000000, 0);
+            assert_eq!(addr & 0xFFF, 0);
+
+            *entry = addr;
+            *entry |= 0b01; // Present (P) Bit.
+            *entry |= 0b10; // Read/Write (R/W) Bit.
+        }
+
+        for off in (0..len).step_by(0x200000) {
+            // Get page-directory pointer table.
+            let addr = vaddr + off;
+            let pml4o = (addr & 0xFF8000000000) >> 39;
+            let pdpt = match pml4t[pml4o] {
+                0 => {
+                    let (pdpt, addr) = self
+                        .alloc_page_table()
+                        .map_err(RamBuilderError::AllocPdpTableFailed)?;
+
+                    set_page_entry(&mut pml4t[pml4o], addr);
+
+                    unsafe { &mut *pdpt }
+                }
+                v => unsafe { &mut *ram.add(v & 0xFFFFFFFFFF000).cast() },
+            };
+
+            // Get page-directory table.
+            let pdpo = (addr & 0x7FC0000000) >> 30;
+            let pdt: &mut [usize; 512] = match pdpt[pdpo] {
+                0 => {
+                    let (pdt, addr) = self
+                        .alloc_page_table()
+                        .map_err(RamBuilderError::AllocPdTableFailed)?;
+
+                    set_page_entry(&mut pdpt[pdpo], addr);
+
+                    unsafe { &mut *pdt }
+                }
+                v => unsafe { &mut *ram.add(v & 0xFFFFFFFFFF000).cast() },
+            };
+
+            // Set page-directory entry.
+            let pdo = (addr & 0x3FE00000) >> 21;
+            let addr = paddr + off;
+
+            assert_eq!(pdt[pdo], 0);
+
+            set_page_entry(&mut pdt[pdo], addr);
+
+            pdt[pdo] |= 1 << 7; // Page Size (PS) Bit.
+        }
+
+        Ok(())
+    }
+
     fn alloc_page_table(&mut self) -> Result<(*mut [usize; 512], usize), RamError> {
         // Get address and length.
         let addr = self.next;
@@ -455,6 +555,14 @@ impl<'a, M: RamMapper> RamBuilder<'a, M> {
             Self::MA_NOR,
         )?;
 
+        // Map the whole RAM at DMAP_ADDR. We use 32M blocks here to reduce the number of page
+        // tables. The unallocated part will never be accessed by the kernel.
+        let len = self.ram.len().get();
+
+        assert!(DMAP_ADDR + len <= kern_vaddr);
+
+        self.setup_32m_blocks(feats, l0t, DMAP_ADDR, 0, len, Self::MA_NOR)?;
+
         Ok(RamMap {
             page_size: unsafe { NonZero::new_unchecked(0x4000) },
             page_table,
@@ -559,6 +667,81 @@ impl<'a, M: RamMapper> RamBuilder<'a, M> {
         Ok(())
     }
 
+    fn setup_32m_blocks(
+        &mut self,
+        _: &CpuFeats,
+        l0t: &mut [usize; 32],
+        vaddr: usize,
+        paddr: usize,
+        len: usize,
+        attr: u8,
+    ) -> Result<(), RamBuilderError> {
+        let attr: usize = attr.into();
+        let ram = self.ram.host_addr().cast_mut(); // TODO: Make this safer.
+
+        assert_eq!(len % 0x2000000, 0);
+        assert_eq!(attr & 0b11111000, 0);
+
+        fn set_table_descriptor(entry: &mut usize, addr: usize) {
+            assert_eq!(addr & 0xFFFF000000003FFF, 0);
+
+            *entry = addr;
+            *entry |= 0b11; // Valid + Table descriptor/Page descriptor
+            *entry |= 1 << 10; // AF
+        }
+
+        for off in (0..len).step_by(0x2000000) {
+            // Get level 1 table.
+            let addr = vaddr + off;
+            let l0o = (addr & 0x800000000000) >> 47;
+            let l1t = match l0t[l0o] {
+                0 => {
+                    let (l1t, addr) = self
+                        .alloc_16k_page_table()
+                        .map_err(RamBuilderError::AllocPageTableLevel1Failed)?;
+
+                    set_table_descriptor(&mut l0t[l0o], addr);
+
+                    unsafe { &mut *l1t }
+                }
+                v => unsafe { &mut *ram.add(v & 0xFFFFFFFFC000).cast() },
+            };
+
+            // Get level 2 table.
+            let l1o = (addr & 0x7FF000000000) >> 36;
+            let l2t: &mut [usize; 2048] = match l1t[l1o] {
+                0 => {
+                    let (l2t, addr) = self
+                        .alloc_16k_page_table()
+                        .map_err(RamBuilderError::AllocPageTableLevel2Failed)?;
+
+                    set_table_descriptor(&mut l1t[l1o], addr);
+
+                    unsafe { &mut *l2t }
+                }
+                v => unsafe { &mut *ram.add(v & 0xFFFFFFFFC000).cast() },
+            };
+
+            // Set block descriptor.
+            let l2o = (addr & 0xFFE000000) >> 25;
+            let addr = paddr + off;
+            let mut desc = addr;
+
+            assert_eq!(addr & 0xFFFF000001FFFFFF, 0);
+            assert_eq!(l2t[l2o], 0);
+
+            desc |= 0b01; // Valid descriptor + Block descriptor
+            desc |= attr << 2; // AttrIndx[2:0]
+            desc |= 0b00 << 6; // AP[2:1]
+            desc |= 0b11 << 8; // Inner Shareable
+            desc |= 1 << 10; // AF
+
+            l2t[l2o] = desc;
+        }
+
+        Ok(())
+    }
+
     fn alloc_16k_page_table(&mut self) -> Result<(*mut [usize; 2048], usize), RamError> {
         // Get address and length.
         let addr = self.next;
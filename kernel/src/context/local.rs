@@ -35,6 +35,13 @@ impl<T> CpuLocal<T> {
     }
 }
 
+impl<T: Sync> CpuLocal<T> {
+    /// Returns an iterator over the value of all CPUs.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.0.iter()
+    }
+}
+
 unsafe impl<T: Send> Send for CpuLocal<T> {}
 unsafe impl<T: Send> Sync for CpuLocal<T> {}
 
@@ -2,7 +2,10 @@ pub use self::arc::*;
 pub use self::arch::*;
 pub use self::local::*;
 
+use crate::dmem::Dmem;
+use crate::fs::Fs;
 use crate::proc::{ProcMgr, Thread};
+use crate::sysctl::Sysctl;
 use crate::uma::Uma;
 use alloc::rc::Rc;
 use alloc::sync::Arc;
@@ -44,6 +47,9 @@ pub unsafe fn run_with_context(
             thread: Arc::into_raw(td),
             uma: null(),
             pmgr: null(),
+            sysctl: null(),
+            fs: null(),
+            dmem: null(),
         },
         args,
     ));
@@ -58,6 +64,9 @@ pub unsafe fn run_with_context(
 
     cx.as_mut().get_unchecked_mut().base.uma = Arc::into_raw(r.uma);
     cx.as_mut().get_unchecked_mut().base.pmgr = Arc::into_raw(r.pmgr);
+    cx.as_mut().get_unchecked_mut().base.sysctl = Arc::into_raw(r.sysctl);
+    cx.as_mut().get_unchecked_mut().base.fs = Arc::into_raw(r.fs);
+    cx.as_mut().get_unchecked_mut().base.dmem = Arc::into_raw(r.dmem);
 
     main();
 }
@@ -96,6 +105,36 @@ pub fn current_procmgr() -> Option<BorrowedArc<ProcMgr>> {
     unsafe { BorrowedArc::new(Context::load_ptr::<{ offset_of!(Base, pmgr) }, _>()) }
 }
 
+/// Returns [`None`] if called from context setup function.
+///
+/// # Interrupt safety
+/// This function can be called from interrupt handle.
+pub fn current_sysctl() -> Option<BorrowedArc<Sysctl>> {
+    // It does not matter if we are on a different CPU after we load the Context::sysctl because it
+    // is always the same for all CPU.
+    unsafe { BorrowedArc::new(Context::load_ptr::<{ offset_of!(Base, sysctl) }, _>()) }
+}
+
+/// Returns [`None`] if called from context setup function.
+///
+/// # Interrupt safety
+/// This function can be called from interrupt handle.
+pub fn current_fs() -> Option<BorrowedArc<Fs>> {
+    // It does not matter if we are on a different CPU after we load the Context::fs because it is
+    // always the same for all CPU.
+    unsafe { BorrowedArc::new(Context::load_ptr::<{ offset_of!(Base, fs) }, _>()) }
+}
+
+/// Returns [`None`] if called from context setup function.
+///
+/// # Interrupt safety
+/// This function can be called from interrupt handle.
+pub fn current_dmem() -> Option<BorrowedArc<Dmem>> {
+    // It does not matter if we are on a different CPU after we load the Context::dmem because it
+    // is always the same for all CPU.
+    unsafe { BorrowedArc::new(Context::load_ptr::<{ offset_of!(Base, dmem) }, _>()) }
+}
+
 /// Pin the calling thread to one CPU.
 ///
 /// This thread will never switch to a different CPU until the returned [`PinnedContext`] is dropped
@@ -120,6 +159,9 @@ pub fn pin_cpu() -> PinnedContext {
 pub struct ContextSetup {
     pub uma: Arc<Uma>,
     pub pmgr: Arc<ProcMgr>,
+    pub sysctl: Arc<Sysctl>,
+    pub fs: Arc<Fs>,
+    pub dmem: Arc<Dmem>,
 }
 
 /// Implementation of `pcpu` structure.
@@ -146,6 +188,9 @@ struct Base {
     thread: *const Thread, // pc_curthread
     uma: *const Uma,
     pmgr: *const ProcMgr,
+    sysctl: *const Sysctl,
+    fs: *const Fs,
+    dmem: *const Dmem,
 }
 
 impl Drop for Base {
@@ -0,0 +1,473 @@
+use super::{read_arg, Dmem};
+use crate::context::current_dmem;
+use crate::errno::{Errno, EINVAL, ENOMEM};
+use crate::fs::{
+    DefaultFileBackendError, File, FileBackend, FileFlags, IoCmd, OpenFlags, PollEvents, Stat,
+};
+use crate::lock::Mutex;
+use crate::proc::Thread;
+use crate::syscalls::{SysErr, SysIn, SysOut, Syscalls};
+use crate::vm::{MapError, VmProt, VmSpace};
+use alloc::boxed::Box;
+use alloc::collections::btree_map::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::mem::offset_of;
+use core::num::NonZero;
+use core::ops::Range;
+
+/// Pool of direct memory that can be committed to the virtual address in a unit of
+/// [`BlockPool::BLOCK_SIZE`].
+///
+/// The process can have only one blockpool, which is the one that was opened most recently.
+pub struct BlockPool {
+    dmem: Arc<Dmem>,
+    blocks: Mutex<Blocks>,
+}
+
+impl BlockPool {
+    pub const BLOCK_SIZE: usize = 0x10000;
+
+    pub fn new(dmem: Arc<Dmem>) -> Self {
+        Self {
+            dmem,
+            blocks: Mutex::new(Blocks {
+                free: Vec::new(),
+                committed: BTreeMap::new(),
+            }),
+        }
+    }
+
+    pub fn register_syscalls(sys: &mut Syscalls) {
+        sys.register(653, Self::sys_blockpool_open);
+        sys.register(654, Self::sys_blockpool_map);
+        sys.register(655, Self::sys_blockpool_unmap);
+        sys.register(657, Self::sys_blockpool_batch);
+        sys.register(673, Self::sys_blockpool_move);
+    }
+
+    /// Allocate `len` bytes of direct memory within `range` and add it to the pool. Returns the
+    /// start of the allocated memory.
+    pub fn expand(
+        &self,
+        range: Range<usize>,
+        len: usize,
+        align: usize,
+    ) -> Result<usize, BlockPoolError> {
+        let align = Dmem::align(align)
+            .map(|v| v.max(Self::BLOCK_SIZE))
+            .ok_or(BlockPoolError::InvalidAlignment)?;
+
+        if len == 0 || !len.is_multiple_of(Self::BLOCK_SIZE) {
+            return Err(BlockPoolError::InvalidLength);
+        }
+
+        // The memory type of the blocks will be specified when committing.
+        let start = self
+            .dmem
+            .map_mut()
+            .alloc(range, len, align, 0)
+            .ok_or(BlockPoolError::NoSpace)?;
+
+        self.blocks
+            .lock()
+            .free
+            .extend((start..(start + len)).step_by(Self::BLOCK_SIZE).rev());
+
+        Ok(start)
+    }
+
+    /// Map the free blocks to `addr`. All blocks within the range must not be committed.
+    pub fn commit(
+        &self,
+        vm: &VmSpace,
+        addr: usize,
+        len: usize,
+        prot: VmProt,
+    ) -> Result<(), BlockPoolError> {
+        let range = Self::range(addr, len)?;
+        let mut blocks = self.blocks.lock();
+
+        if blocks.committed.range(range.clone()).next().is_some() {
+            return Err(BlockPoolError::AlreadyCommitted);
+        } else if blocks.free.len() < len / Self::BLOCK_SIZE {
+            return Err(BlockPoolError::NoBlocks);
+        }
+
+        for addr in range.clone().step_by(Self::BLOCK_SIZE) {
+            let off = blocks.free.pop().unwrap();
+
+            if let Err(e) = self.map(vm, addr, prot, off) {
+                blocks.free.push(off);
+
+                // Roll back the blocks we have committed so far. This is the same as decommit
+                // except we can't do anything if it fails.
+                for addr in (range.start..addr).step_by(Self::BLOCK_SIZE) {
+                    let off = blocks.committed.remove(&addr).unwrap();
+
+                    blocks.free.push(off);
+
+                    let _ = vm.mmap(addr, Self::BLOCK_SIZE, VmProt::zeroed(), true);
+                }
+
+                return Err(BlockPoolError::MapFailed(e));
+            }
+
+            blocks.committed.insert(addr, off);
+        }
+
+        Ok(())
+    }
+
+    /// Return the committed blocks within the range to the pool. The range will be left as
+    /// inaccessible.
+    pub fn decommit(&self, vm: &VmSpace, addr: usize, len: usize) -> Result<(), BlockPoolError> {
+        let range = Self::range(addr, len)?;
+        let mut blocks = self.blocks.lock();
+
+        for addr in range.step_by(Self::BLOCK_SIZE) {
+            let off = match blocks.committed.remove(&addr) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            blocks.free.push(off);
+
+            vm.mmap(addr, Self::BLOCK_SIZE, VmProt::zeroed(), true)
+                .map_err(BlockPoolError::MapFailed)?;
+        }
+
+        Ok(())
+    }
+
+    /// Move the committed blocks from `src` to `dst`. The blocks keep their data and protection.
+    pub fn move_blocks(
+        &self,
+        vm: &VmSpace,
+        dst: usize,
+        src: usize,
+        len: usize,
+    ) -> Result<(), BlockPoolError> {
+        let dst = Self::range(dst, len)?;
+        let src = Self::range(src, len)?;
+        let mut blocks = self.blocks.lock();
+
+        if dst.start < src.end && src.start < dst.end {
+            return Err(BlockPoolError::InvalidAddress);
+        } else if blocks.committed.range(dst.clone()).next().is_some() {
+            return Err(BlockPoolError::AlreadyCommitted);
+        }
+
+        for (to, from) in dst
+            .step_by(Self::BLOCK_SIZE)
+            .zip(src.step_by(Self::BLOCK_SIZE))
+        {
+            let off = match blocks.committed.remove(&from) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            let prot = vm.protection(from).unwrap_or(VmProt::zeroed());
+
+            blocks.committed.insert(to, off);
+
+            self.map(vm, to, prot, off)
+                .and_then(|_| vm.mmap(from, Self::BLOCK_SIZE, VmProt::zeroed(), true))
+                .map_err(BlockPoolError::MapFailed)?;
+        }
+
+        Ok(())
+    }
+
+    fn stats(&self) -> BlockPoolStats {
+        let blocks = self.blocks.lock();
+
+        // We don't have a cache so all blocks are flushed.
+        BlockPoolStats {
+            avail_flushed: blocks.free.len().try_into().unwrap(),
+            avail_cached: 0,
+            allocated_flushed: blocks.committed.len().try_into().unwrap(),
+            allocated_cached: 0,
+        }
+    }
+
+    fn map(&self, vm: &VmSpace, addr: usize, prot: VmProt, off: usize) -> Result<(), MapError> {
+        vm.mmap_object(addr, Self::BLOCK_SIZE, prot, true, self.dmem.clone(), off)?;
+
+        Ok(())
+    }
+
+    /// Returns an error if `addr` or `len` is not aligned to [`Self::BLOCK_SIZE`].
+    fn range(addr: usize, len: usize) -> Result<Range<usize>, BlockPoolError> {
+        if len == 0
+            || !addr.is_multiple_of(Self::BLOCK_SIZE)
+            || !len.is_multiple_of(Self::BLOCK_SIZE)
+        {
+            return Err(BlockPoolError::InvalidAddress);
+        }
+
+        let end = addr
+            .checked_add(len)
+            .ok_or(BlockPoolError::InvalidAddress)?;
+
+        Ok(addr..end)
+    }
+
+    fn current(td: &Thread) -> Result<Arc<Self>, SysErr> {
+        td.proc().blockpool_mut().clone().ok_or(SysErr::Raw(EINVAL))
+    }
+
+    fn sys_blockpool_open(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let flags: u32 = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+
+        if (flags & 0xffafffff) != 0 {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        // Create the pool.
+        let pool = Arc::new(Self::new(current_dmem().unwrap().into_owned()));
+        let file = File::new(FileFlags::FWRITE, BlockPoolFileBackend(pool.clone()));
+        let cloexec = OpenFlags::from(flags).has(OpenFlags::O_CLOEXEC);
+        let fd = td.proc().files().alloc(Arc::new(file), cloexec)?;
+
+        *td.proc().blockpool_mut() = Some(pool);
+
+        Ok(fd.into())
+    }
+
+    fn sys_blockpool_map(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let addr: usize = i.args[0].into();
+        let len: usize = i.args[1].into();
+        let prot = VmSpace::prot(i.args[3].get())?;
+        let pool = Self::current(td)?;
+
+        // TODO: Find out how the PS4 use the memory type and the flags.
+        pool.commit(td.proc().vm(), addr, len, prot)?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    fn sys_blockpool_unmap(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let addr: usize = i.args[0].into();
+        let len: usize = i.args[1].into();
+        let pool = Self::current(td)?;
+
+        pool.decommit(td.proc().vm(), addr, len)?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// The number of the operations that was completed will be written to the third argument
+    /// even if some operation was failed.
+    fn sys_blockpool_batch(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let ops: usize = i.args[0].into();
+        let count: c_int = i.args[1].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let done: usize = i.args[2].into();
+        let count: usize = count.try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let pool = Self::current(td)?;
+        let vm = td.proc().vm();
+        let mut n: c_int = 0;
+        let mut r = Ok(());
+
+        for i in 0..count {
+            // Read the operation.
+            let mut data = [0u8; size_of::<BatchOp>()];
+            let addr = size_of::<BatchOp>()
+                .checked_mul(i)
+                .and_then(|v| ops.checked_add(v))
+                .ok_or(SysErr::Raw(EINVAL))?;
+
+            vm.read(addr, &mut data)?;
+
+            // Execute.
+            let op: BatchOp = unsafe { read_arg(&data) };
+
+            r = match op.op {
+                1 => VmSpace::prot(op.args[2] & 0xff).and_then(|p| {
+                    pool.commit(vm, op.args[0], op.args[1], p)
+                        .map_err(SysErr::from)
+                }),
+                2 => pool
+                    .decommit(vm, op.args[0], op.args[1])
+                    .map_err(SysErr::from),
+                3 | 4 => VmSpace::prot(op.args[2] & 0xff)
+                    .and_then(|p| vm.mprotect(op.args[0], op.args[1], p).map_err(SysErr::from)),
+                5 => pool
+                    .move_blocks(vm, op.args[0], op.args[1], op.args[2])
+                    .map_err(SysErr::from),
+                _ => Err(SysErr::Raw(EINVAL)),
+            };
+
+            if r.is_err() {
+                break;
+            }
+
+            n += 1;
+        }
+
+        if done != 0 {
+            vm.write(done, &n.to_ne_bytes())?;
+        }
+
+        r.map(|_| SysOut::ZERO)
+    }
+
+    fn sys_blockpool_move(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let dst: usize = i.args[0].into();
+        let src: usize = i.args[1].into();
+        let len: usize = i.args[2].into();
+        let pool = Self::current(td)?;
+
+        pool.move_blocks(td.proc().vm(), dst, src, len)?;
+
+        Ok(SysOut::ZERO)
+    }
+}
+
+/// Blocks of [`BlockPool`].
+struct Blocks {
+    free: Vec<usize>,
+    committed: BTreeMap<usize, usize>,
+}
+
+/// Implementation of [`FileBackend`] for [`BlockPool`].
+struct BlockPoolFileBackend(Arc<BlockPool>);
+
+impl FileBackend for BlockPoolFileBackend {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn ioctl(
+        &self,
+        _: &File,
+        cmd: IoCmd,
+        data: &mut [u8],
+        _: &Thread,
+    ) -> Result<(), Box<dyn Errno>> {
+        match cmd {
+            IoCmd::BPOOLEXPAND => {
+                let arg: BlockPoolExpand = unsafe { read_arg(data) };
+                let start = self
+                    .0
+                    .expand(arg.start..arg.end, arg.len, arg.align)
+                    .map_err(|e| Box::new(e) as Box<dyn Errno>)?;
+
+                let off = offset_of!(BlockPoolExpand, start);
+
+                data[off..(off + size_of::<usize>())].copy_from_slice(&start.to_ne_bytes());
+            }
+            IoCmd::BPOOLSTATS => {
+                let st = self.0.stats();
+
+                data[0x0..0x4].copy_from_slice(&st.avail_flushed.to_ne_bytes());
+                data[0x4..0x8].copy_from_slice(&st.avail_cached.to_ne_bytes());
+                data[0x8..0xC].copy_from_slice(&st.allocated_flushed.to_ne_bytes());
+                data[0xC..0x10].copy_from_slice(&st.allocated_cached.to_ne_bytes());
+            }
+            _ => return Err(Box::new(DefaultFileBackendError::Ioctl)),
+        }
+
+        Ok(())
+    }
+
+    fn poll(&self, _: &File, events: PollEvents, _: &Thread) -> PollEvents {
+        // The block pool is always ready, the same as vop_nopoll.
+        events & (PollEvents::POLLIN | PollEvents::POLLOUT | PollEvents::POLLRDNORM)
+    }
+
+    fn stat(&self, _: &File, _: &Thread) -> Result<Stat, Box<dyn Errno>> {
+        Ok(Stat {
+            mode: 0o130000,
+            blksize: BlockPool::BLOCK_SIZE as u32,
+            ..Default::default()
+        })
+    }
+}
+
+/// Argument of [`IoCmd::BPOOLEXPAND`].
+///
+/// The start of the expanded memory will be written back to `start`.
+#[repr(C)]
+pub struct BlockPoolExpand {
+    len: usize,
+    start: usize,
+    end: usize,
+    align: usize,
+}
+
+/// Output of [`IoCmd::BPOOLSTATS`].
+pub struct BlockPoolStats {
+    avail_flushed: i32,
+    avail_cached: i32,
+    allocated_flushed: i32,
+    allocated_cached: i32,
+}
+
+/// An operation of `sys_blockpool_batch`.
+///
+/// The arguments for each operation are:
+///
+/// - Commit: address, length and protection on the first byte followed by memory type.
+/// - Decommit: address and length.
+/// - Protect and type protect: same as commit.
+/// - Move: destination, source and length.
+///
+/// TODO: Verify this layout and the meaning of the flags with the PS4.
+#[repr(C)]
+struct BatchOp {
+    op: u32,
+    _flags: u32,
+    args: [usize; 3],
+}
+
+/// Represents an error when operation on [`BlockPool`] fails.
+#[derive(Debug)]
+pub enum BlockPoolError {
+    InvalidAlignment,
+    InvalidLength,
+    InvalidAddress,
+    NoSpace,
+    NoBlocks,
+    AlreadyCommitted,
+    MapFailed(MapError),
+}
+
+impl Error for BlockPoolError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::MapFailed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Display for BlockPoolError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidAlignment => f.write_str("invalid alignment"),
+            Self::InvalidLength => f.write_str("invalid length"),
+            Self::InvalidAddress => f.write_str("invalid address"),
+            Self::NoSpace => f.write_str("no free direct memory available"),
+            Self::NoBlocks => f.write_str("not enough free blocks in the pool"),
+            Self::AlreadyCommitted => f.write_str("the range is already committed"),
+            Self::MapFailed(_) => f.write_str("couldn't map the blocks"),
+        }
+    }
+}
+
+impl Errno for BlockPoolError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::InvalidAlignment
+            | Self::InvalidLength
+            | Self::InvalidAddress
+            | Self::AlreadyCommitted => EINVAL,
+            Self::NoSpace | Self::NoBlocks => ENOMEM,
+            Self::MapFailed(e) => e.errno(),
+        }
+    }
+}
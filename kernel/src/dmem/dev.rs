@@ -0,0 +1,222 @@
+use super::{read_arg, Dmem, DmemContainer};
+use crate::config::PAGE_MASK;
+use crate::errno::{Errno, EINVAL, ENOENT, ENOMEM, ENOTTY, EPERM};
+use crate::fs::{CharacterDevice, DeviceDriver, IoCmd};
+use crate::proc::Thread;
+use crate::vm::{FaultError, VmObject, VmProt};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::mem::offset_of;
+use core::num::NonZero;
+
+/// Implementation of [`DeviceDriver`] for `/dev/dmem0`, `/dev/dmem1` and `/dev/dmem2`.
+pub struct DmemDevice {
+    dmem: Arc<Dmem>,
+    container: DmemContainer,
+}
+
+impl DmemDevice {
+    pub fn new(dmem: Arc<Dmem>, container: DmemContainer) -> Self {
+        Self { dmem, container }
+    }
+
+    fn alloc(&self, data: &mut [u8]) -> Result<(), IoctlError> {
+        let arg: DmemAllocate = unsafe { read_arg(data) };
+        let align = Dmem::align(arg.align).ok_or(IoctlError::InvalidAlignment)?;
+
+        if arg.len == 0 || (arg.len & PAGE_MASK.get()) != 0 {
+            return Err(IoctlError::InvalidLength);
+        }
+
+        let addr = self
+            .dmem
+            .map_mut()
+            .alloc(arg.start..arg.end, arg.len, align, arg.ty)
+            .ok_or(IoctlError::NoSpace)?;
+
+        write_usize(data, offset_of!(DmemAllocate, start), addr);
+
+        Ok(())
+    }
+
+    fn avail(&self, data: &mut [u8]) -> Result<(), IoctlError> {
+        let arg: DmemAvailable = unsafe { read_arg(data) };
+        let align = Dmem::align(arg.align).ok_or(IoctlError::InvalidAlignment)?;
+        let (start, size) = match self.dmem.map_mut().avail(arg.start..arg.end, align) {
+            Some(v) => (v.start, v.len() & !PAGE_MASK.get()),
+            None => (arg.start, 0),
+        };
+
+        write_usize(data, offset_of!(DmemAvailable, start), start);
+        write_usize(data, offset_of!(DmemAvailable, size), size);
+
+        Ok(())
+    }
+
+    fn query(&self, data: &[u8], td: &Thread) -> Result<(), IoctlError> {
+        // TODO: Find out what is the purpose of the field after the flags.
+        let arg: DmemQuery = unsafe { read_arg(data) };
+
+        DmemContainer::try_from(arg.container).map_err(|_| IoctlError::InvalidContainer)?;
+
+        // Get the range. The info is start, end and the memory type.
+        let mut buf = [0u8; 0x18];
+
+        match self.dmem.map_mut().query(arg.addr, (arg.flags & 1) != 0) {
+            Some(v) => {
+                write_usize(&mut buf, 0x00, v.start());
+                write_usize(&mut buf, 0x08, v.end());
+                buf[0x10..0x14].copy_from_slice(&v.ty().to_ne_bytes());
+            }
+            None => return Err(IoctlError::NotAllocated),
+        }
+
+        // Write the info.
+        let len = arg.info_len.min(buf.len());
+
+        td.proc()
+            .vm()
+            .write(arg.info, &buf[..len])
+            .map_err(IoctlError::WriteInfoFailed)
+    }
+}
+
+impl DeviceDriver for DmemDevice {
+    fn ioctl(
+        &self,
+        _: &Arc<CharacterDevice>,
+        cmd: IoCmd,
+        data: &mut [u8],
+        td: &Thread,
+    ) -> Result<(), Box<dyn Errno>> {
+        let cred = td.cred_mut().clone();
+
+        if cred.is_unk1() || cred.is_unk2() {
+            return Err(Box::new(IoctlError::NoPermission));
+        }
+
+        // The container #2 is shared between all processes.
+        if self.container != DmemContainer::Two
+            && self.container != *td.proc().dmem_container_mut()
+            && !cred.is_system()
+        {
+            return Err(Box::new(IoctlError::NoPermission));
+        }
+
+        // TODO: Implement DMEMGETPRT.
+        let r = match cmd {
+            IoCmd::DMEMTOTAL => {
+                data.copy_from_slice(&self.dmem.map_mut().size().to_ne_bytes());
+                Ok(())
+            }
+            // TODO: Find out how DMEMALLOCMAIN differ from DMEMALLOC.
+            IoCmd::DMEMALLOC | IoCmd::DMEMALLOCMAIN => self.alloc(data),
+            IoCmd::DMEMQUERY => self.query(data, td),
+            IoCmd::DMEMGETAVAIL => self.avail(data),
+            _ => Err(IoctlError::UnknownCommand(cmd)),
+        };
+
+        r.map_err(|e| Box::new(e) as Box<dyn Errno>)
+    }
+
+    fn mmap(
+        &self,
+        _: &Arc<CharacterDevice>,
+        off: u64,
+        prot: VmProt,
+    ) -> Result<u64, Box<dyn Errno>> {
+        self.dmem.page(off as usize, prot).map(|v| v as u64)
+    }
+}
+
+/// Write `v` to `data` at `off`.
+fn write_usize(data: &mut [u8], off: usize, v: usize) {
+    data[off..(off + size_of::<usize>())].copy_from_slice(&v.to_ne_bytes());
+}
+
+/// Argument of [`IoCmd::DMEMALLOC`] and [`IoCmd::DMEMALLOCMAIN`].
+///
+/// The allocated address will be written back to `start`.
+#[repr(C)]
+pub struct DmemAllocate {
+    start: usize,
+    end: usize,
+    len: usize,
+    align: usize,
+    ty: i32,
+}
+
+/// Argument of [`IoCmd::DMEMGETAVAIL`].
+///
+/// The start of the largest free range will be written back to `start`.
+#[repr(C)]
+pub struct DmemAvailable {
+    start: usize,
+    end: usize,
+    align: usize,
+    size: usize,
+}
+
+/// Argument of [`IoCmd::DMEMQUERY`].
+#[repr(C)]
+pub struct DmemQuery {
+    container: c_int,
+    flags: c_int,
+    _unk: usize,
+    addr: usize,
+    info: usize,
+    info_len: usize,
+}
+
+/// Represents an error when [`DmemDevice::ioctl()`] fails.
+#[derive(Debug)]
+enum IoctlError {
+    NoPermission,
+    InvalidAlignment,
+    InvalidLength,
+    InvalidContainer,
+    NoSpace,
+    NotAllocated,
+    WriteInfoFailed(FaultError),
+    UnknownCommand(IoCmd),
+}
+
+impl Error for IoctlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::WriteInfoFailed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Display for IoctlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoPermission => f.write_str("no permission to access the container"),
+            Self::InvalidAlignment => f.write_str("invalid alignment"),
+            Self::InvalidLength => f.write_str("invalid length"),
+            Self::InvalidContainer => f.write_str("invalid container"),
+            Self::NoSpace => f.write_str("no free direct memory available"),
+            Self::NotAllocated => f.write_str("no allocated direct memory at the address"),
+            Self::WriteInfoFailed(_) => f.write_str("couldn't write the information"),
+            Self::UnknownCommand(v) => write!(f, "unknown command {v:?}"),
+        }
+    }
+}
+
+impl Errno for IoctlError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NoPermission => EPERM,
+            Self::InvalidAlignment | Self::InvalidLength | Self::InvalidContainer => EINVAL,
+            Self::NoSpace => ENOMEM,
+            Self::NotAllocated => ENOENT,
+            Self::WriteInfoFailed(e) => e.errno(),
+            Self::UnknownCommand(_) => ENOTTY,
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use alloc::collections::btree_map::BTreeMap;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Allocated ranges of direct memory.
+///
+/// The ranges are keyed by the start address, which is an offset from the beginning of the direct
+/// memory. Adjacent allocations are kept as a separated range so each one can have its own type.
+pub struct DmemMap {
+    len: usize,
+    ranges: BTreeMap<usize, DmemRange>,
+}
+
+impl DmemMap {
+    pub fn new(len: usize) -> Self {
+        Self {
+            len,
+            ranges: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the size of the whole direct memory.
+    pub fn size(&self) -> usize {
+        self.len
+    }
+
+    /// Allocate `len` bytes aligned to `align` within `range`. Returns the start of the allocated
+    /// range or [`None`] if there are no free space for it.
+    ///
+    /// # Panics
+    /// If `align` is not a power of two.
+    pub fn alloc(
+        &mut self,
+        range: Range<usize>,
+        len: usize,
+        align: usize,
+        ty: i32,
+    ) -> Option<usize> {
+        assert!(align.is_power_of_two());
+
+        if len == 0 {
+            return None;
+        }
+
+        for free in self.holes(range) {
+            let start = match free.start.checked_next_multiple_of(align) {
+                Some(v) => v,
+                None => break,
+            };
+
+            if start.checked_add(len).is_none_or(|v| v > free.end) {
+                continue;
+            }
+
+            self.ranges.insert(
+                start,
+                DmemRange {
+                    start,
+                    end: start + len,
+                    ty,
+                },
+            );
+
+            return Some(start);
+        }
+
+        None
+    }
+
+    /// Returns the largest free range within `range` after aligned its start to `align`.
+    ///
+    /// # Panics
+    /// If `align` is not a power of two.
+    pub fn avail(&self, range: Range<usize>, align: usize) -> Option<Range<usize>> {
+        assert!(align.is_power_of_two());
+
+        let mut found: Option<Range<usize>> = None;
+
+        for free in self.holes(range) {
+            let start = match free.start.checked_next_multiple_of(align) {
+                Some(v) if v < free.end => v,
+                _ => continue,
+            };
+
+            if found.as_ref().is_none_or(|v| v.len() < free.end - start) {
+                found = Some(start..free.end);
+            }
+        }
+
+        found
+    }
+
+    /// Returns the allocated range that contains `addr`. If `next` is `true` the first allocated
+    /// range after `addr` will be returned when `addr` is not allocated.
+    pub fn query(&self, addr: usize, next: bool) -> Option<&DmemRange> {
+        let found = self
+            .ranges
+            .range(..=addr)
+            .next_back()
+            .map(|(_, r)| r)
+            .filter(|r| addr < r.end);
+
+        if found.is_some() || !next {
+            return found;
+        }
+
+        self.ranges.range(addr..).next().map(|(_, r)| r)
+    }
+
+    /// Returns `true` if the whole `range` is allocated.
+    pub fn is_allocated(&self, range: Range<usize>) -> bool {
+        let mut addr = range.start;
+
+        while addr < range.end {
+            match self.query(addr, false) {
+                Some(r) => addr = r.end,
+                None => return false,
+            }
+        }
+
+        true
+    }
+
+    /// Returns all free ranges within `range`.
+    fn holes(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        let end = range.end.min(self.len);
+        let mut addr = range.start;
+        let mut holes = Vec::new();
+
+        if let Some(r) = self.query(addr, false) {
+            addr = r.end;
+        }
+
+        for r in self.ranges.range(addr..).map(|(_, r)| r) {
+            if r.start >= end {
+                break;
+            }
+
+            if addr < r.start {
+                holes.push(addr..r.start);
+            }
+
+            addr = r.end;
+        }
+
+        if addr < end {
+            holes.push(addr..end);
+        }
+
+        holes
+    }
+}
+
+/// A range of allocated direct memory.
+pub struct DmemRange {
+    start: usize,
+    end: usize,
+    ty: i32,
+}
+
+impl DmemRange {
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the memory type that was specified when allocate this range.
+    pub fn ty(&self) -> i32 {
+        self.ty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alloc() {
+        let mut map = DmemMap::new(0x100000);
+
+        assert_eq!(map.alloc(0..0x100000, 0x4000, 0x4000, 0), Some(0));
+        assert_eq!(map.alloc(0..0x100000, 0x4000, 0x10000, 3), Some(0x10000));
+        assert_eq!(map.alloc(0..0x100000, 0x8000, 0x4000, 0), Some(0x4000));
+        assert_eq!(
+            map.alloc(0x20000..0x28000, 0x8000, 0x4000, 0),
+            Some(0x20000)
+        );
+        assert_eq!(map.alloc(0x20000..0x28000, 0x4000, 0x4000, 0), None);
+        assert_eq!(map.alloc(0..0x200000, 0x100000, 0x4000, 0), None);
+        assert_eq!(map.alloc(0..0x100000, 0, 0x4000, 0), None);
+        assert_eq!(map.query(0x14000, false).map(|r| r.ty()), None);
+        assert_eq!(map.query(0x10000, false).map(|r| r.ty()), Some(3));
+    }
+
+    #[test]
+    fn avail() {
+        let mut map = DmemMap::new(0x100000);
+
+        map.alloc(0x4000..0x100000, 0x4000, 0x4000, 0).unwrap();
+        map.alloc(0x20000..0x100000, 0xE0000, 0x4000, 0).unwrap();
+
+        assert_eq!(map.avail(0..0x100000, 0x4000), Some(0x8000..0x20000));
+        assert_eq!(map.avail(0..0x100000, 0x10000), Some(0x10000..0x20000));
+        assert_eq!(map.avail(0..0x8000, 0x4000), Some(0..0x4000));
+        assert_eq!(map.avail(0x4000..0x8000, 0x4000), None);
+    }
+
+    #[test]
+    fn query() {
+        let mut map = DmemMap::new(0x100000);
+
+        map.alloc(0x4000..0x100000, 0x4000, 0x4000, 0).unwrap();
+        map.alloc(0x8000..0x100000, 0x4000, 0x4000, 10).unwrap();
+
+        assert!(map.query(0, false).is_none());
+        assert_eq!(map.query(0, true).map(|r| r.start()), Some(0x4000));
+        assert_eq!(map.query(0x8000, false).map(|r| r.end()), Some(0xC000));
+        assert!(map.query(0xC000, true).is_none());
+        assert!(map.is_allocated(0x4000..0xC000));
+        assert!(!map.is_allocated(0x4000..0xC001));
+        assert!(!map.is_allocated(0..0x8000));
+    }
+}
@@ -0,0 +1,220 @@
+pub use self::blockpool::*;
+pub use self::dev::*;
+pub use self::map::*;
+
+use crate::config::{PAGE_MASK, PAGE_SIZE};
+use crate::context::current_dmem;
+use crate::errno::{Errno, EINVAL, ENOMEM, EPERM};
+use crate::fs::{Fs, MakeDevArgs, MakeDevError, MakeDevFlags};
+use crate::lock::{Mutex, MutexGuard};
+use crate::proc::Thread;
+use crate::syscalls::{SysErr, SysIn, SysOut, Syscalls};
+use crate::ucred::{Gid, Uid};
+use crate::vm::{PhysMem, VmObject, VmProt, VmSpace, MAP_FIXED};
+use alloc::boxed::Box;
+use alloc::collections::btree_map::BTreeMap;
+use alloc::format;
+use alloc::sync::Arc;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+
+mod blockpool;
+mod dev;
+mod map;
+
+/// Manager of direct memory.
+///
+/// Direct memory is the memory that the process allocate by its physical address so it can be
+/// shared with the GPU. The address is an offset from the beginning of the direct memory instead of
+/// the real physical address. We don't reserve any physical memory for it so the page for each
+/// offset will be allocated from [`PhysMem`] on the first access.
+pub struct Dmem {
+    phys: Arc<PhysMem>,
+    map: Mutex<DmemMap>,
+    pages: Mutex<BTreeMap<usize, usize>>,
+}
+
+impl Dmem {
+    /// Size of the direct memory.
+    const SIZE: usize = 0x13C000000;
+
+    /// Create `/dev/dmem0`, `/dev/dmem1` and `/dev/dmem2`.
+    pub fn new(fs: &Fs, phys: Arc<PhysMem>) -> Result<Arc<Self>, MakeDevError> {
+        let dmem = Arc::new(Self {
+            phys,
+            map: Mutex::new(DmemMap::new(Self::SIZE)),
+            pages: Mutex::new(BTreeMap::new()),
+        });
+
+        for c in [DmemContainer::Zero, DmemContainer::One, DmemContainer::Two] {
+            let name = format!("dmem{}", c as c_int);
+            let args = MakeDevArgs {
+                unit: 0,
+                uid: Uid::ROOT,
+                gid: Gid::ROOT,
+                mode: 0o777,
+                cred: None,
+                flags: MakeDevFlags::zeroed(),
+            };
+
+            fs.make_dev(&name, DmemDevice::new(dmem.clone(), c), args)?;
+        }
+
+        Ok(dmem)
+    }
+
+    pub fn map_mut(&self) -> MutexGuard<'_, DmemMap> {
+        self.map.lock()
+    }
+
+    pub fn register_syscalls(sys: &mut Syscalls) {
+        sys.register(586, Self::sys_dmem_container);
+        sys.register(628, Self::sys_mmap_dmem);
+    }
+
+    /// Returns the alignment to use for the alignment that specified by the user or [`None`] if
+    /// it is not valid. Zero means the page size.
+    fn align(v: usize) -> Option<usize> {
+        match v {
+            0 => Some(PAGE_SIZE.get()),
+            v if v.is_power_of_two() => Some(v.max(PAGE_SIZE.get())),
+            _ => None,
+        }
+    }
+
+    /// Setting the container require the system credential.
+    fn sys_dmem_container(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let id: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let mut cur = td.proc().dmem_container_mut();
+        let old = *cur;
+
+        if id != -1 {
+            // TODO: Check what the PS4 require to change the container.
+            if !td.cred_mut().is_system() {
+                return Err(SysErr::Raw(EPERM));
+            }
+
+            *cur = DmemContainer::try_from(id).map_err(|_| SysErr::Raw(EINVAL))?;
+        }
+
+        Ok((old as usize).into())
+    }
+
+    fn sys_mmap_dmem(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let addr: usize = i.args[0].into();
+        let len: usize = i.args[1].into();
+        let prot = VmSpace::prot(i.args[3].get())?;
+        let flags: usize = i.args[4].into();
+        let off: usize = i.args[5].into();
+        let dmem = current_dmem().unwrap();
+
+        // TODO: Find out how the PS4 use the memory type in the third argument. We use the type that
+        // was specified when allocating the memory for now.
+        let end = off
+            .checked_add(len)
+            .and_then(|v| v.checked_next_multiple_of(PAGE_SIZE.get()))
+            .ok_or(SysErr::Raw(EINVAL))?;
+
+        if len == 0 || (off & PAGE_MASK.get()) != 0 || !dmem.map_mut().is_allocated(off..end) {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        let object: Arc<Dmem> = dmem.into_owned();
+        let addr =
+            td.proc()
+                .vm()
+                .mmap_object(addr, len, prot, (flags & MAP_FIXED) != 0, object, off)?;
+
+        Ok(addr.into())
+    }
+}
+
+impl VmObject for Dmem {
+    fn page(&self, off: usize, _: VmProt) -> Result<usize, Box<dyn Errno>> {
+        if self.map.lock().query(off, false).is_none() {
+            return Err(Box::new(PageError::NotAllocated(off)));
+        }
+
+        // Get the page.
+        let off = off & !PAGE_MASK.get();
+        let mut pages = self.pages.lock();
+
+        if let Some(&v) = pages.get(&off) {
+            return Ok(v);
+        }
+
+        let page = self
+            .phys
+            .alloc()
+            .ok_or(PageError::NoMemory)
+            .map_err(|e| Box::new(e) as Box<dyn Errno>)?;
+
+        pages.insert(off, page);
+
+        Ok(page)
+    }
+}
+
+/// Container of direct memory.
+///
+/// Each container has its own device. The process can only access the device of its own container
+/// and [`DmemContainer::Two`], unless it has the system credential.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmemContainer {
+    Zero,
+    One,
+    Two,
+}
+
+impl TryFrom<c_int> for DmemContainer {
+    type Error = c_int;
+
+    fn try_from(value: c_int) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Zero),
+            1 => Ok(Self::One),
+            2 => Ok(Self::Two),
+            v => Err(v),
+        }
+    }
+}
+
+/// Read `T` from the argument of `ioctl`.
+///
+/// # Safety
+/// All fields of `T` must be integer.
+unsafe fn read_arg<T>(data: &[u8]) -> T {
+    assert_eq!(data.len(), size_of::<T>());
+
+    unsafe { data.as_ptr().cast::<T>().read_unaligned() }
+}
+
+/// Represents an error when [`Dmem`] fails to provide a page.
+#[derive(Debug)]
+enum PageError {
+    NotAllocated(usize),
+    NoMemory,
+}
+
+impl Error for PageError {}
+
+impl Display for PageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotAllocated(v) => write!(f, "direct memory at {v:#x} is not allocated"),
+            Self::NoMemory => f.write_str("no physical memory available"),
+        }
+    }
+}
+
+impl Errno for PageError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NotAllocated(_) => EINVAL,
+            Self::NoMemory => ENOMEM,
+        }
+    }
+}
@@ -12,3 +12,8 @@ impl<A: 'static> EventType for for<'a> fn(&'a mut A) {
     type Handler<S: Subsystem> = fn(&Arc<S>, &mut A);
     type Wrapper = Box<dyn Fn(&mut A) + Send + Sync>;
 }
+
+impl<A: 'static> EventType for for<'a> fn(&'a A) {
+    type Handler<S: Subsystem> = fn(&Arc<S>, &A);
+    type Wrapper = Box<dyn Fn(&A) + Send + Sync>;
+}
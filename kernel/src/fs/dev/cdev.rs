@@ -0,0 +1,374 @@
+use super::dirent::Dirent;
+use crate::errno::{Errno, ENODEV};
+use crate::fs::{File, FileBackend, FileFlags, IoCmd, PollEvents, Stat, Vnode};
+use crate::lock::{Gutex, GutexGroup, GutexWrite};
+use crate::proc::Thread;
+use crate::ucred::{Gid, Ucred, Uid};
+use crate::vm::{VmObject, VmProt};
+use alloc::boxed::Box;
+use alloc::sync::{Arc, Weak};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+use macros::bitflag;
+
+/// Implementation of `cdev` and `cdev_priv` structures.
+pub struct CharacterDevice {
+    driver: Box<dyn DeviceDriver>,             // si_devsw
+    unit: c_int,                               // si_drv0
+    name: Box<str>,                            // si_name
+    uid: Uid,                                  // si_uid
+    gid: Gid,                                  // si_gid
+    mode: u16,                                 // si_mode
+    cred: Option<Arc<Ucred>>,                  // si_cred
+    flags: DeviceFlags,                        // si_flags
+    inode: u32,                                // cdp_inode
+    dirents: Gutex<Vec<Option<Weak<Dirent>>>>, // cdp_dirents + cdp_maxdirent
+}
+
+impl CharacterDevice {
+    /// See `devfs_alloc` on the PS4 for a reference.
+    pub(super) fn new(
+        driver: Box<dyn DeviceDriver>,
+        name: Box<str>,
+        args: MakeDevArgs,
+        flags: DeviceFlags,
+        inode: u32,
+    ) -> Self {
+        let gg = GutexGroup::new();
+
+        Self {
+            driver,
+            unit: args.unit,
+            name,
+            uid: args.uid,
+            gid: args.gid,
+            mode: args.mode,
+            cred: args.cred,
+            flags,
+            inode,
+            dirents: gg.spawn(vec![None]),
+        }
+    }
+
+    /// Returns [`None`] if the driver is not `T`.
+    pub fn driver<T: DeviceDriver>(&self) -> Option<&T> {
+        let d: &dyn core::any::Any = self.driver.as_ref();
+
+        d.downcast_ref()
+    }
+
+    #[allow(dead_code)] // TODO: Remove this once we have a device that use it.
+    pub fn unit(&self) -> c_int {
+        self.unit
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn uid(&self) -> Uid {
+        self.uid
+    }
+
+    pub fn gid(&self) -> Gid {
+        self.gid
+    }
+
+    pub fn mode(&self) -> u16 {
+        self.mode
+    }
+
+    #[allow(dead_code)] // TODO: Remove this once we have a device that use it.
+    pub fn cred(&self) -> Option<&Arc<Ucred>> {
+        self.cred.as_ref()
+    }
+
+    #[allow(dead_code)] // TODO: Remove this once we have destroy_dev.
+    pub fn flags(&self) -> DeviceFlags {
+        self.flags
+    }
+
+    pub(super) fn inode(&self) -> u32 {
+        self.inode
+    }
+
+    pub(super) fn dirents_mut(&self) -> GutexWrite<'_, Vec<Option<Weak<Dirent>>>> {
+        self.dirents.write()
+    }
+
+    /// See `d_open` on the PS4 for a reference.
+    pub(super) fn open(
+        self: &Arc<Self>,
+        flags: FileFlags,
+        td: &Thread,
+    ) -> Result<(), Box<dyn Errno>> {
+        self.driver.open(self, flags, td)
+    }
+}
+
+/// Implementation of `devfs_ops_f`.
+pub struct CdevFileBackend {
+    vn: Arc<Vnode>,
+    dev: Arc<CharacterDevice>,
+}
+
+impl CdevFileBackend {
+    pub fn new(vn: Arc<Vnode>, dev: Arc<CharacterDevice>) -> Self {
+        Self { vn, dev }
+    }
+
+    pub fn device(&self) -> &Arc<CharacterDevice> {
+        &self.dev
+    }
+}
+
+impl FileBackend for CdevFileBackend {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    /// See `devfs_read_f` on the PS4 for a reference.
+    fn read(
+        &self,
+        _: &File,
+        off: &mut u64,
+        buf: &mut [u8],
+        td: &Thread,
+    ) -> Result<usize, Box<dyn Errno>> {
+        let len = self.dev.driver.read(&self.dev, *off, buf, td)?;
+
+        *off += len as u64;
+
+        Ok(len)
+    }
+
+    /// See `devfs_write_f` on the PS4 for a reference.
+    fn write(
+        &self,
+        _: &File,
+        off: &mut u64,
+        buf: &[u8],
+        td: &Thread,
+    ) -> Result<usize, Box<dyn Errno>> {
+        let len = self.dev.driver.write(&self.dev, *off, buf, td)?;
+
+        *off += len as u64;
+
+        Ok(len)
+    }
+
+    /// See `devfs_ioctl_f` on the PS4 for a reference.
+    fn ioctl(
+        &self,
+        _: &File,
+        cmd: IoCmd,
+        data: &mut [u8],
+        td: &Thread,
+    ) -> Result<(), Box<dyn Errno>> {
+        // TODO: Handle FIODGNAME.
+        if cmd == IoCmd::FIODTYPE {
+            let ty = self.dev.driver.flags().into_bits() & DriverFlags::D_TYPEMASK;
+
+            data.copy_from_slice(&(ty as c_int).to_ne_bytes());
+
+            return Ok(());
+        }
+
+        self.dev.driver.ioctl(&self.dev, cmd, data, td)
+    }
+
+    /// See `devfs_poll_f` on the PS4 for a reference.
+    fn poll(&self, _: &File, events: PollEvents, td: &Thread) -> PollEvents {
+        self.dev.driver.poll(&self.dev, events, td)
+    }
+
+    /// See `devfs_stat_f` on the PS4 for a reference.
+    fn stat(&self, _: &File, td: &Thread) -> Result<Stat, Box<dyn Errno>> {
+        self.vn.stat(td)
+    }
+}
+
+/// Implementation of [`VmObject`] for [`CharacterDevice`].
+///
+/// See `cdev_pager_ops` on the PS4 for a reference.
+pub struct DevicePager {
+    dev: Arc<CharacterDevice>,
+}
+
+impl DevicePager {
+    pub fn new(dev: Arc<CharacterDevice>) -> Self {
+        Self { dev }
+    }
+}
+
+impl VmObject for DevicePager {
+    /// See `dev_pager_getpages` on the PS4 for a reference.
+    fn page(&self, off: usize, prot: VmProt) -> Result<usize, Box<dyn Errno>> {
+        let pa = self.dev.driver.mmap(&self.dev, off as u64, prot)?;
+
+        Ok(pa.try_into().unwrap())
+    }
+}
+
+/// Implementation of `cdevsw` structure.
+///
+/// Each [`CharacterDevice`] has its own instance so the implementation can keep the data for the
+/// device in itself instead of `si_drv1` and `si_drv2`. All default implementation here are the
+/// implementation of `no_*` functions (e.g. `no_read`).
+pub trait DeviceDriver: core::any::Any + Send + Sync {
+    /// Implementation of `d_flags`.
+    fn flags(&self) -> DriverFlags {
+        DriverFlags::zeroed()
+    }
+
+    /// Implementation of `d_open`.
+    fn open(
+        &self,
+        _: &Arc<CharacterDevice>,
+        _: FileFlags,
+        _: &Thread,
+    ) -> Result<(), Box<dyn Errno>> {
+        Ok(())
+    }
+
+    /// Implementation of `d_read`.
+    fn read(
+        &self,
+        _: &Arc<CharacterDevice>,
+        _: u64,
+        _: &mut [u8],
+        _: &Thread,
+    ) -> Result<usize, Box<dyn Errno>> {
+        Err(Box::new(DefaultDeviceError::Read))
+    }
+
+    /// Implementation of `d_write`.
+    fn write(
+        &self,
+        _: &Arc<CharacterDevice>,
+        _: u64,
+        _: &[u8],
+        _: &Thread,
+    ) -> Result<usize, Box<dyn Errno>> {
+        Err(Box::new(DefaultDeviceError::Write))
+    }
+
+    /// `data` is the argument that was copied in from the user. It will be copied back to the user
+    /// if the command has `IOC_OUT`.
+    ///
+    /// Implementation of `d_ioctl`.
+    fn ioctl(
+        &self,
+        _: &Arc<CharacterDevice>,
+        _: IoCmd,
+        _: &mut [u8],
+        _: &Thread,
+    ) -> Result<(), Box<dyn Errno>> {
+        Err(Box::new(DefaultDeviceError::Ioctl))
+    }
+
+    /// Returns the physical address of the page at `off`.
+    ///
+    /// Implementation of `d_mmap`.
+    fn mmap(&self, _: &Arc<CharacterDevice>, _: u64, _: VmProt) -> Result<u64, Box<dyn Errno>> {
+        Err(Box::new(DefaultDeviceError::Mmap))
+    }
+
+    /// Returns the events in `events` that are ready.
+    ///
+    /// Implementation of `d_poll`.
+    fn poll(&self, _: &Arc<CharacterDevice>, events: PollEvents, _: &Thread) -> PollEvents {
+        // This is the implementation of poll_no_poll.
+        events & (PollEvents::POLLIN | PollEvents::POLLOUT | PollEvents::POLLRDNORM)
+    }
+}
+
+/// Arguments for [`super::Fs::make_dev()`].
+pub struct MakeDevArgs {
+    pub unit: c_int,              // si_drv0
+    pub uid: Uid,                 // si_uid
+    pub gid: Gid,                 // si_gid
+    pub mode: u16,                // si_mode
+    pub cred: Option<Arc<Ucred>>, // si_cred
+    pub flags: MakeDevFlags,
+}
+
+/// Flags for [`super::Fs::make_dev()`].
+#[bitflag(u32)]
+pub enum MakeDevFlags {
+    /// Return an error instead of panic if the name is not valid.
+    MAKEDEV_CHECKNAME = 0x20,
+    /// The device will never be destroyed.
+    MAKEDEV_ETERNAL = 0x10,
+}
+
+/// Flags of [`CharacterDevice`].
+#[bitflag(u32)]
+pub enum DeviceFlags {
+    /// The device will never be destroyed.
+    SI_ETERNAL = 0x01,
+}
+
+/// Flags of [`DeviceDriver`].
+#[bitflag(u32)]
+pub enum DriverFlags {
+    /// Tape device.
+    D_TAPE = 0x0001,
+    /// Disk device.
+    D_DISK = 0x0002,
+    /// Terminal device.
+    D_TTY = 0x0004,
+    /// Memory device.
+    D_MEM = 0x0008,
+    /// Track all closes.
+    D_TRACKCLOSE = 0x00080000,
+    /// Special `mmap` handling.
+    D_MMAP_ANON = 0x00100000,
+    /// Driver need to be run with Giant.
+    D_NEEDGIANT = 0x00400000,
+    /// Device require a unique minor number.
+    D_NEEDMINOR = 0x00800000,
+}
+
+impl DriverFlags {
+    /// Mask for type of the device.
+    pub const D_TYPEMASK: u32 = 0xFFFF;
+
+    pub fn into_bits(self) -> u32 {
+        self.0
+    }
+}
+
+/// Represents an error when the default implementation of [`DeviceDriver`] fails.
+#[derive(Debug)]
+enum DefaultDeviceError {
+    Read,
+    Write,
+    Ioctl,
+    Mmap,
+}
+
+impl Error for DefaultDeviceError {}
+
+impl Display for DefaultDeviceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Read => f.write_str("the device does not support read"),
+            Self::Write => f.write_str("the device does not support write"),
+            Self::Ioctl => f.write_str("the device does not support ioctl"),
+            Self::Mmap => f.write_str("the device does not support mmap"),
+        }
+    }
+}
+
+impl Errno for DefaultDeviceError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::Read | Self::Write | Self::Ioctl | Self::Mmap => ENODEV,
+        }
+    }
+}
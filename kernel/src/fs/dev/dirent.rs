@@ -0,0 +1,135 @@
+use super::CharacterDevice;
+use crate::fs::Vnode;
+use crate::lock::{Gutex, GutexGroup, GutexWrite};
+use crate::ucred::{Gid, Uid};
+use alloc::boxed::Box;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+
+/// Implementation of `devfs_dirent` structure.
+///
+/// Unlike the PS4 we don't keep `.` and `..` in the children. The parent is stored directly on
+/// [`Dirent::parent()`] instead.
+pub struct Dirent {
+    ty: DirentType,                      // de_dirent.d_type
+    inode: u32,                          // de_inode
+    name: Box<str>,                      // de_dirent.d_name
+    parent: Option<Weak<Self>>,          // de_dir
+    cdev: Option<Weak<CharacterDevice>>, // de_cdp
+    uid: Gutex<Uid>,                     // de_uid
+    gid: Gutex<Gid>,                     // de_gid
+    mode: Gutex<u16>,                    // de_mode
+    children: Gutex<Vec<Arc<Self>>>,     // de_dlist
+    vnode: Gutex<Option<Weak<Vnode>>>,   // de_vnode
+}
+
+impl Dirent {
+    /// Create a new directory. The directory will be owned by root with `0555` mode.
+    ///
+    /// See `devfs_vmkdir` on the PS4 for a reference.
+    pub fn new_dir(inode: u32, name: impl Into<Box<str>>, parent: Option<&Arc<Self>>) -> Self {
+        Self::new(
+            DirentType::Directory,
+            inode,
+            name.into(),
+            parent,
+            None,
+            (Uid::ROOT, Gid::ROOT, 0o555),
+        )
+    }
+
+    /// Create a new entry for `dev`. `name` is the last component of the device name.
+    pub fn new_cdev(dev: &Arc<CharacterDevice>, name: &str, parent: &Arc<Self>) -> Self {
+        Self::new(
+            DirentType::Character,
+            dev.inode(),
+            name.into(),
+            Some(parent),
+            Some(dev),
+            (dev.uid(), dev.gid(), dev.mode()),
+        )
+    }
+
+    pub fn ty(&self) -> DirentType {
+        self.ty
+    }
+
+    pub fn inode(&self) -> u32 {
+        self.inode
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns [`None`] if this is the root directory or the parent has been removed.
+    ///
+    /// See `devfs_parent_dirent` on the PS4 for a reference.
+    pub fn parent(&self) -> Option<Arc<Self>> {
+        self.parent.as_ref().and_then(|v| v.upgrade())
+    }
+
+    /// Returns [`None`] if this is not a device or the device has been destroyed.
+    pub fn cdev(&self) -> Option<Arc<CharacterDevice>> {
+        self.cdev.as_ref().and_then(|v| v.upgrade())
+    }
+
+    /// Returns the owner, group and mode as a single snapshot.
+    pub fn perm(&self) -> (Uid, Gid, u16) {
+        let uid = self.uid.write();
+        let gid = self.gid.write();
+        let mode = self.mode.write();
+
+        (*uid, *gid, *mode)
+    }
+
+    pub fn children_mut(&self) -> GutexWrite<'_, Vec<Arc<Self>>> {
+        self.children.write()
+    }
+
+    pub fn vnode_mut(&self) -> GutexWrite<'_, Option<Weak<Vnode>>> {
+        self.vnode.write()
+    }
+
+    /// See `devfs_find` on the PS4 for a reference.
+    pub fn find(&self, name: &str) -> Option<Arc<Self>> {
+        self.children
+            .write()
+            .iter()
+            .find(|c| c.name() == name)
+            .cloned()
+    }
+
+    fn new(
+        ty: DirentType,
+        inode: u32,
+        name: Box<str>,
+        parent: Option<&Arc<Self>>,
+        cdev: Option<&Arc<CharacterDevice>>,
+        (uid, gid, mode): (Uid, Gid, u16),
+    ) -> Self {
+        let gg = GutexGroup::new();
+
+        Self {
+            ty,
+            inode,
+            name,
+            parent: parent.map(Arc::downgrade),
+            cdev: cdev.map(Arc::downgrade),
+            uid: gg.clone().spawn(uid),
+            gid: gg.clone().spawn(gid),
+            mode: gg.clone().spawn(mode),
+            children: gg.clone().spawn(Vec::new()),
+            vnode: gg.spawn(None),
+        }
+    }
+}
+
+/// Type of [`Dirent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirentType {
+    /// AKA `DT_CHR`.
+    Character,
+    /// AKA `DT_DIR`.
+    Directory,
+}
@@ -0,0 +1,415 @@
+pub use self::cdev::*;
+
+use self::dirent::{Dirent, DirentType};
+use self::vnode::DevVnode;
+use super::{Filesystem, Fs, FsConfig, Mount, MountFlags, MountOpts, Vnode, VnodeItem, VnodeType};
+use crate::errno::{Errno, EEXIST, EINVAL, ENAMETOOLONG, ENOENT, EOPNOTSUPP};
+use crate::lock::{Gutex, GutexGroup};
+use crate::proc::Thread;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+
+mod cdev;
+mod dirent;
+mod vnode;
+
+/// Configuration of devfs.
+pub static DEVFS: FsConfig = FsConfig {
+    name: "devfs",
+    ty: 0x71,
+    mount: DevFs::mount,
+};
+
+/// List of devices in the system.
+///
+/// The locks must be acquired in the same order as the fields.
+pub(super) struct Devices {
+    list: Gutex<Vec<Arc<CharacterDevice>>>, // cdevp_list
+    generation: Gutex<u32>,                 // devfs_generation
+    last_inode: Gutex<u32>,                 // devfs_inos
+    last_index: Gutex<usize>,               // devfs_unr
+}
+
+impl Devices {
+    /// # Context safety
+    /// This function does not require a CPU context.
+    pub fn new() -> Self {
+        let gg = GutexGroup::new();
+
+        Self {
+            list: gg.clone().spawn(Vec::new()),
+            generation: gg.clone().spawn(0),
+            last_inode: gg.clone().spawn(DevFs::DEVFS_ROOTINO),
+            last_index: gg.spawn(0),
+        }
+    }
+
+    /// See `make_dev_credv` on the PS4 for a reference.
+    pub fn make_dev(
+        &self,
+        name: &str,
+        driver: Box<dyn DeviceDriver>,
+        args: MakeDevArgs,
+    ) -> Result<Arc<CharacterDevice>, MakeDevError> {
+        // TODO: Implement clone_create() for D_NEEDMINOR.
+        if driver.flags().has(DriverFlags::D_NEEDMINOR) {
+            return Err(MakeDevError::NeedMinor);
+        }
+
+        // Check name. The PS4 panic if the name is not valid and MAKEDEV_CHECKNAME is not
+        // specified but we let the caller handle it instead.
+        let name = prep_devname(name)?;
+
+        let mut list = self.list.write();
+
+        if list.iter().any(|d| name_conflict(d.name(), &name)) {
+            return Err(MakeDevError::AlreadyExist(name.into()));
+        }
+
+        // Create the device.
+        let mut flags = DeviceFlags::zeroed();
+
+        if args.flags.has(MakeDevFlags::MAKEDEV_ETERNAL) {
+            flags |= DeviceFlags::SI_ETERNAL;
+        }
+
+        let inode = self.alloc_inode();
+        let dev = CharacterDevice::new(driver, name.into(), args, flags, inode);
+        let dev = Arc::new(dev);
+
+        list.push(dev.clone());
+
+        // Notify devfs.
+        let mut gen = self.generation.write();
+
+        *gen = gen.wrapping_add(1);
+
+        Ok(dev)
+    }
+
+    fn alloc_inode(&self) -> u32 {
+        let mut last = self.last_inode.write();
+
+        *last += 1;
+        *last
+    }
+
+    fn alloc_index(&self) -> usize {
+        // TODO: Reuse the index of the unmounted devfs.
+        let mut last = self.last_index.write();
+        let index = *last;
+
+        *last += 1;
+        index
+    }
+}
+
+/// Implementation of `devfs_mount` structure.
+pub struct DevFs {
+    devices: Arc<Devices>,
+    index: usize,           // dm_idx
+    root: Arc<Dirent>,      // dm_rootdir
+    generation: Gutex<u32>, // dm_generation
+}
+
+impl DevFs {
+    const DEVFS_ROOTINO: u32 = 2;
+
+    /// See `devfs_mount` on the PS4 for a reference.
+    fn mount(
+        fs: &Fs,
+        _: Option<&Arc<Vnode>>,
+        _: &mut MountOpts,
+        flags: &mut MountFlags,
+        _: &Thread,
+    ) -> Result<Box<dyn Filesystem>, Box<dyn Errno>> {
+        if flags.has(MountFlags::MNT_ROOTFS) {
+            return Err(Box::new(MountError::RootFs));
+        }
+
+        // TODO: Handle ruleset option.
+        let gg = GutexGroup::new();
+        let devices = fs.devices.clone();
+        let index = devices.alloc_index();
+        let root = Dirent::new_dir(Self::DEVFS_ROOTINO, "", None);
+
+        *flags |= MountFlags::MNT_LOCAL;
+
+        Ok(Box::new(Self {
+            devices,
+            index,
+            root: Arc::new(root),
+            generation: gg.spawn(0),
+        }))
+    }
+
+    /// Create the entries for the devices that was created since the last call.
+    ///
+    /// See `devfs_populate` on the PS4 for a reference.
+    fn populate(&self) {
+        // Check if our entries already latest.
+        let mut gen = self.generation.write();
+        let list = self.devices.list.write();
+        let latest = *self.devices.generation.write();
+
+        if *gen == latest {
+            return;
+        }
+
+        for dev in list.iter() {
+            // Check if we already populated this device.
+            let mut dirents = dev.dirents_mut();
+
+            if dirents
+                .get(self.index)
+                .and_then(|v| v.as_ref())
+                .is_some_and(|v| v.strong_count() != 0)
+            {
+                continue;
+            }
+
+            // Create directories along the path.
+            let mut dir = self.root.clone();
+            let mut name = dev.name();
+
+            while let Some(i) = name.find('/') {
+                let n = &name[..i];
+                let d = {
+                    let mut children = dir.children_mut();
+
+                    match children.iter().find(|c| c.name() == n) {
+                        Some(v) => v.clone(),
+                        None => {
+                            // TODO: Implement devfs_rules_apply.
+                            let inode = self.devices.alloc_inode();
+                            let d = Arc::new(Dirent::new_dir(inode, n, Some(&dir)));
+
+                            children.push(d.clone());
+                            d
+                        }
+                    }
+                };
+
+                dir = d;
+                name = &name[(i + 1)..];
+            }
+
+            // Create the entry.
+            let ent = Arc::new(Dirent::new_cdev(dev, name, &dir));
+
+            dir.children_mut().push(ent.clone());
+
+            // TODO: Implement devfs_rules_apply.
+            if self.index >= dirents.len() {
+                dirents.resize(self.index + 1, None);
+            }
+
+            dirents[self.index] = Some(Arc::downgrade(&ent));
+        }
+
+        *gen = latest;
+    }
+
+    /// See `devfs_allocv` on the PS4 for a reference.
+    fn alloc_vnode(
+        &self,
+        mnt: &Arc<Mount>,
+        ent: Arc<Dirent>,
+    ) -> Result<Arc<Vnode>, AllocVnodeError> {
+        // Check for active vnode.
+        let mut current = ent.vnode_mut();
+
+        if let Some(v) = current.as_ref().and_then(|v| v.upgrade()) {
+            return Ok(v);
+        }
+
+        // Create a new vnode.
+        let tag = "devfs";
+        let vn = match ent.ty() {
+            DirentType::Character => {
+                let dev = ent.cdev().ok_or(AllocVnodeError::DeviceGone)?;
+                let backend = DevVnode::new(ent.clone());
+                let vn = Vnode::new(mnt, VnodeType::CharacterDevice, tag, backend);
+
+                *vn.item_mut() = Some(VnodeItem::Device(dev));
+                vn
+            }
+            DirentType::Directory => {
+                let root = ent.inode() == Self::DEVFS_ROOTINO;
+                let backend = DevVnode::new(ent.clone());
+
+                Vnode::new(mnt, VnodeType::Directory(root), tag, backend)
+            }
+        };
+
+        *current = Some(Arc::downgrade(&vn));
+
+        Ok(vn)
+    }
+}
+
+impl Filesystem for DevFs {
+    fn root(&self, mnt: &Arc<Mount>, _: &Thread) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        self.alloc_vnode(mnt, self.root.clone())
+            .map_err(|e| Box::new(e) as Box<dyn Errno>)
+    }
+}
+
+/// Returns the normalized name.
+///
+/// See `prep_devname` on the PS4 for a reference.
+fn prep_devname(name: &str) -> Result<String, MakeDevError> {
+    let mut buf = String::with_capacity(name.len());
+
+    // Remove leading, trailing and duplicated slashes.
+    for c in name.split('/').filter(|c| !c.is_empty()) {
+        if c == "." || c == ".." {
+            return Err(MakeDevError::InvalidName(name.into()));
+        }
+
+        if !buf.is_empty() {
+            buf.push('/');
+        }
+
+        buf.push_str(c);
+    }
+
+    if buf.is_empty() {
+        Err(MakeDevError::InvalidName(name.into()))
+    } else if buf.len() > SPECNAMELEN {
+        Err(MakeDevError::NameTooLong)
+    } else {
+        Ok(buf)
+    }
+}
+
+/// Returns `true` if `a` and `b` cannot be exists at the same time.
+///
+/// See `devfs_dev_exists` on the PS4 for a reference.
+fn name_conflict(a: &str, b: &str) -> bool {
+    let contains = |a: &str, b: &str| match b.strip_prefix(a) {
+        Some(v) => v.is_empty() || v.starts_with('/'),
+        None => false,
+    };
+
+    contains(a, b) || contains(b, a)
+}
+
+/// Maximum length of a device name.
+const SPECNAMELEN: usize = 63;
+
+/// Represents an error when [`Fs::make_dev()`] fails.
+#[derive(Debug)]
+pub enum MakeDevError {
+    InvalidName(Box<str>),
+    NameTooLong,
+    AlreadyExist(Box<str>),
+    NeedMinor,
+}
+
+impl Error for MakeDevError {}
+
+impl Display for MakeDevError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidName(n) => write!(f, "'{n}' is not a valid device name"),
+            Self::NameTooLong => f.write_str("device name is too long"),
+            Self::AlreadyExist(n) => write!(f, "device '{n}' already exists"),
+            Self::NeedMinor => f.write_str("D_NEEDMINOR is not supported"),
+        }
+    }
+}
+
+impl Errno for MakeDevError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::InvalidName(_) => EINVAL,
+            Self::NameTooLong => ENAMETOOLONG,
+            Self::AlreadyExist(_) => EEXIST,
+            Self::NeedMinor => EINVAL,
+        }
+    }
+}
+
+/// Represents an error when [`DevFs::mount()`] fails.
+#[derive(Debug)]
+enum MountError {
+    RootFs,
+}
+
+impl Error for MountError {}
+
+impl Display for MountError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::RootFs => f.write_str("devfs cannot be mounted as a root filesystem"),
+        }
+    }
+}
+
+impl Errno for MountError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::RootFs => EOPNOTSUPP,
+        }
+    }
+}
+
+/// Represents an error when [`DevFs::alloc_vnode()`] fails.
+#[derive(Debug)]
+enum AllocVnodeError {
+    DeviceGone,
+}
+
+impl Error for AllocVnodeError {}
+
+impl Display for AllocVnodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::DeviceGone => f.write_str("the device has been destroyed"),
+        }
+    }
+}
+
+impl Errno for AllocVnodeError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::DeviceGone => ENOENT,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn devname() {
+        assert_eq!(prep_devname("rng").unwrap(), "rng");
+        assert_eq!(prep_devname("//a//b/").unwrap(), "a/b");
+        assert!(matches!(
+            prep_devname("/"),
+            Err(MakeDevError::InvalidName(_))
+        ));
+        assert!(matches!(
+            prep_devname("a/../b"),
+            Err(MakeDevError::InvalidName(_))
+        ));
+        assert!(matches!(
+            prep_devname(&"a".repeat(64)),
+            Err(MakeDevError::NameTooLong)
+        ));
+
+        assert!(name_conflict("a", "a"));
+        assert!(name_conflict("a", "a/b"));
+        assert!(name_conflict("a/b", "a"));
+        assert!(!name_conflict("a", "ab"));
+        assert!(!name_conflict("a/b", "a/c"));
+    }
+}
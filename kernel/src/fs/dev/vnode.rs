@@ -0,0 +1,177 @@
+use super::dirent::Dirent;
+use super::DevFs;
+use crate::errno::{Errno, EIO, ENOENT, ENOTDIR, ENXIO};
+use crate::fs::{check_access, Access, FileFlags, Vnode, VnodeAttrs, VnodeBackend, NODEV};
+use crate::proc::Thread;
+use crate::time::TimeSpec;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+
+/// Implementation of [`VnodeBackend`] for devfs.
+///
+/// This implementation merge `devfs_vnodeops` and `devfs_specops` together.
+pub struct DevVnode {
+    dirent: Arc<Dirent>,
+}
+
+impl DevVnode {
+    pub fn new(dirent: Arc<Dirent>) -> Self {
+        Self { dirent }
+    }
+
+    fn fs(vn: &Vnode) -> &DevFs {
+        vn.mount().fs().unwrap()
+    }
+}
+
+impl VnodeBackend for DevVnode {
+    /// See `devfs_access` on the PS4 for a reference.
+    fn access(&self, vn: &Arc<Vnode>, td: &Thread, mode: Access) -> Result<(), Box<dyn Errno>> {
+        let (uid, gid, perm) = self.dirent.perm();
+        let cred = td.cred_mut();
+
+        // TODO: Check if the file is a controlling terminal.
+        check_access(&cred, uid, gid, perm, mode, vn.is_directory())
+            .map_err(|e| Box::new(e) as Box<dyn Errno>)?;
+
+        Ok(())
+    }
+
+    /// See `devfs_getattr` on the PS4 for a reference.
+    fn getattr(&self, vn: &Arc<Vnode>, _: &Thread) -> Result<VnodeAttrs, Box<dyn Errno>> {
+        Self::fs(vn).populate();
+
+        // Get attributes. We don't have a clock yet so all timestamps are zero.
+        let (uid, gid, mode) = self.dirent.perm();
+        let (nlink, size, rdev) = if vn.is_directory() {
+            (2, 512, NODEV)
+        } else {
+            (1, 0, self.dirent.inode())
+        };
+
+        Ok(VnodeAttrs {
+            mode,
+            nlink,
+            uid,
+            gid,
+            fsid: vn.mount().id()[0],
+            id: self.dirent.inode().into(),
+            size,
+            blksize: DEV_BSIZE,
+            atime: TimeSpec::default(),
+            mtime: TimeSpec::default(),
+            ctime: TimeSpec::default(),
+            birthtime: TimeSpec::default(),
+            gen: 0,
+            flags: 0,
+            rdev,
+            bytes: 0,
+        })
+    }
+
+    /// See `devfs_lookup` on the PS4 for a reference.
+    fn lookup(
+        &self,
+        vn: &Arc<Vnode>,
+        _: &Thread,
+        name: &str,
+    ) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        let fs = Self::fs(vn);
+
+        fs.populate();
+
+        if !vn.is_directory() {
+            return Err(Box::new(LookupError::NotDirectory));
+        }
+
+        // Get the entry.
+        let ent = if name == ".." {
+            if vn.is_mount_root() {
+                return Err(Box::new(LookupError::DotdotOnRoot));
+            }
+
+            self.dirent.parent().ok_or(LookupError::NotFound)
+        } else {
+            // TODO: Implement devfs_prison_check.
+            self.dirent.find(name).ok_or(LookupError::NotFound)
+        };
+
+        let ent = ent.map_err(|e| Box::new(e) as Box<dyn Errno>)?;
+
+        fs.alloc_vnode(vn.mount(), ent)
+            .map_err(|e| Box::new(e) as Box<dyn Errno>)
+    }
+
+    /// See `devfs_open` on the PS4 for a reference.
+    fn open(&self, vn: &Arc<Vnode>, td: &Thread, flags: FileFlags) -> Result<(), Box<dyn Errno>> {
+        if vn.is_directory() {
+            return Ok(());
+        }
+
+        match vn.device() {
+            Some(v) => v.open(flags, td),
+            None => Err(Box::new(OpenError::DeviceGone)),
+        }
+    }
+}
+
+/// Represents an error when [`DevVnode::lookup()`] fails.
+#[derive(Debug)]
+enum LookupError {
+    NotDirectory,
+    DotdotOnRoot,
+    NotFound,
+}
+
+impl Error for LookupError {}
+
+impl Display for LookupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotDirectory => f.write_str("the vnode is not a directory"),
+            Self::DotdotOnRoot => f.write_str("cannot resolve '..' on the root directory"),
+            Self::NotFound => f.write_str("no such file or directory"),
+        }
+    }
+}
+
+impl Errno for LookupError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NotDirectory => ENOTDIR,
+            Self::DotdotOnRoot => EIO,
+            Self::NotFound => ENOENT,
+        }
+    }
+}
+
+/// Represents an error when [`DevVnode::open()`] fails.
+#[derive(Debug)]
+enum OpenError {
+    DeviceGone,
+}
+
+impl Error for OpenError {}
+
+impl Display for OpenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::DeviceGone => f.write_str("the device has been destroyed"),
+        }
+    }
+}
+
+impl Errno for OpenError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::DeviceGone => ENXIO,
+        }
+    }
+}
+
+/// Block size of the device.
+const DEV_BSIZE: u32 = 512;
@@ -0,0 +1,65 @@
+/// Implementation of `dirent` structure.
+pub struct DirEntry<'a> {
+    id: u32,       // d_fileno
+    ty: DirType,   // d_type
+    name: &'a str, // d_name + d_namlen
+}
+
+impl<'a> DirEntry<'a> {
+    /// # Panics
+    /// If `name` is longer than 255 bytes.
+    pub fn new(id: u32, ty: DirType, name: &'a str) -> Self {
+        assert!(name.len() <= 255);
+
+        Self { id, ty, name }
+    }
+
+    /// Returns the size of this entry when written with [`DirEntry::write()`] (AKA
+    /// `GENERIC_DIRSIZ`).
+    pub fn size(&self) -> usize {
+        8 + (self.name.len() + 1).next_multiple_of(4)
+    }
+
+    /// Returns [`None`] if `buf` is not large enough.
+    pub fn write(&self, buf: &mut [u8]) -> Option<usize> {
+        let len = self.size();
+        let buf = buf.get_mut(..len)?;
+
+        buf[..4].copy_from_slice(&self.id.to_ne_bytes());
+        buf[4..6].copy_from_slice(&(len as u16).to_ne_bytes());
+        buf[6] = self.ty as u8;
+        buf[7] = self.name.len() as u8;
+        buf[8..(8 + self.name.len())].copy_from_slice(self.name.as_bytes());
+        buf[(8 + self.name.len())..].fill(0);
+
+        Some(len)
+    }
+}
+
+/// Value of `d_type`.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirType {
+    Directory = 4, // DT_DIR
+    Regular = 8,   // DT_REG
+    Link = 10,     // DT_LNK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write() {
+        let ent = DirEntry::new(5, DirType::Regular, "abc");
+        let mut buf = [0xFF; 16];
+
+        assert_eq!(ent.size(), 12);
+        assert_eq!(ent.write(&mut buf[..11]), None);
+        assert_eq!(ent.write(&mut buf), Some(12));
+        assert_eq!(buf[..12], [5, 0, 0, 0, 12, 0, 8, 3, b'a', b'b', b'c', 0]);
+        assert_eq!(buf[12..], [0xFF; 4]);
+
+        assert_eq!(DirEntry::new(1, DirType::Directory, "abcd").size(), 16);
+    }
+}
@@ -0,0 +1,59 @@
+use super::host::{HostVnode, MountError};
+use super::{Filesystem, Fs, FsConfig, Mount, MountFlags, MountOpts, Vnode};
+use crate::errno::Errno;
+use crate::proc::Thread;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use config::FsType;
+
+/// Configuration of exFAT filesystem.
+pub static EXFATFS: FsConfig = FsConfig {
+    name: "exfatfs",
+    ty: 0x2C,
+    mount: ExFatFs::mount,
+};
+
+/// Implementation of exFAT filesystem.
+///
+/// The partitions of the firmware were extracted to the host during the installation so we don't
+/// have the actual exFAT image. Instead, we read the extracted data from the host through
+/// [`super::host`], which keep the directory tree and the attributes from the dump.
+pub struct ExFatFs {
+    root: u64,
+}
+
+impl ExFatFs {
+    fn mount(
+        _: &Fs,
+        _: Option<&Arc<Vnode>>,
+        opts: &mut MountOpts,
+        flags: &mut MountFlags,
+        _: &Thread,
+    ) -> Result<Box<dyn Filesystem>, Box<dyn Errno>> {
+        // Get the partition.
+        let from = match opts.remove_str("from") {
+            Ok(Some(v)) => v,
+            Ok(None) => return Err(Box::new(MountError::NoFrom)),
+            Err(e) => return Err(Box::new(e)),
+        };
+
+        let name = from.strip_prefix("/dev/").unwrap_or(&from);
+        let root = match super::host::mount_dir(name, FsType::ExFat) {
+            Ok(v) => v,
+            Err(e) => return Err(Box::new(e)),
+        };
+
+        // We don't support writing to the host.
+        *flags |= MountFlags::MNT_RDONLY | MountFlags::MNT_LOCAL;
+
+        Ok(Box::new(Self { root: root.id }))
+    }
+}
+
+impl Filesystem for ExFatFs {
+    fn root(&self, mnt: &Arc<Mount>, _: &Thread) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        super::host::getattr(self.root)
+            .and_then(|attrs| HostVnode::alloc(mnt, &attrs, self.root, "exfatfs"))
+            .map_err(|e| Box::new(e) as Box<dyn Errno>)
+    }
+}
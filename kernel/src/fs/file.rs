@@ -0,0 +1,261 @@
+use super::{IoCmd, Stat};
+use crate::errno::{Errno, EINVAL, ENOTTY, ENXIO};
+use crate::lock::{Gutex, GutexGroup, GutexWrite};
+use crate::proc::Thread;
+use alloc::boxed::Box;
+use core::any::Any;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+use macros::bitflag;
+
+/// Implementation of `file` structure.
+pub struct File {
+    flags: Gutex<FileFlags>,       // f_flag
+    offset: Gutex<u64>,            // f_offset
+    backend: Box<dyn FileBackend>, // f_data + f_ops
+}
+
+impl File {
+    pub fn new(flags: FileFlags, backend: impl FileBackend) -> Self {
+        let gg = GutexGroup::new();
+
+        Self {
+            flags: gg.clone().spawn(flags),
+            offset: gg.spawn(0),
+            backend: Box::new(backend),
+        }
+    }
+
+    pub fn flags(&self) -> FileFlags {
+        *self.flags.write()
+    }
+
+    pub fn flags_mut(&self) -> GutexWrite<'_, FileFlags> {
+        self.flags.write()
+    }
+
+    /// Returns [`None`] if the backend is not `T`.
+    ///
+    /// The backend should use this method to get its data from the file instead of accessing
+    /// [`File`] itself.
+    pub fn backend<T: FileBackend>(&self) -> Option<&T> {
+        let b: &dyn Any = self.backend.as_ref();
+
+        b.downcast_ref()
+    }
+
+    /// Read the data starting at the current offset and advance the offset if the file is
+    /// seekable.
+    ///
+    /// See `fo_read` on the PS4 for a reference.
+    pub fn read(&self, buf: &mut [u8], td: &Thread) -> Result<usize, Box<dyn Errno>> {
+        if self.backend.is_seekable() {
+            let mut off = self.offset.write();
+
+            self.backend.read(self, &mut off, buf, td)
+        } else {
+            self.backend.read(self, &mut 0, buf, td)
+        }
+    }
+
+    /// Write the data starting at the current offset and advance the offset if the file is
+    /// seekable.
+    ///
+    /// See `fo_write` on the PS4 for a reference.
+    pub fn write(&self, buf: &[u8], td: &Thread) -> Result<usize, Box<dyn Errno>> {
+        if self.backend.is_seekable() {
+            let mut off = self.offset.write();
+
+            self.backend.write(self, &mut off, buf, td)
+        } else {
+            self.backend.write(self, &mut 0, buf, td)
+        }
+    }
+
+    /// See `fo_truncate` on the PS4 for a reference.
+    pub fn truncate(&self, len: u64, td: &Thread) -> Result<(), Box<dyn Errno>> {
+        self.backend.truncate(self, len, td)
+    }
+
+    /// Read the directory entries starting at the current offset. Returns the number of bytes
+    /// written to `buf` and the offset before reading.
+    pub fn readdir(&self, buf: &mut [u8], td: &Thread) -> Result<(usize, u64), Box<dyn Errno>> {
+        let mut off = self.offset.write();
+        let base = *off;
+        let len = self.backend.readdir(self, &mut off, buf, td)?;
+
+        Ok((len, base))
+    }
+
+    /// See `fo_ioctl` on the PS4 for a reference.
+    pub fn ioctl(&self, cmd: IoCmd, data: &mut [u8], td: &Thread) -> Result<(), Box<dyn Errno>> {
+        self.backend.ioctl(self, cmd, data, td)
+    }
+
+    /// See `fo_poll` on the PS4 for a reference.
+    #[allow(dead_code)] // TODO: Remove this once we have poll or select.
+    pub fn poll(&self, events: PollEvents, td: &Thread) -> PollEvents {
+        self.backend.poll(self, events, td)
+    }
+
+    /// See `fo_stat` on the PS4 for a reference.
+    pub fn stat(&self, td: &Thread) -> Result<Stat, Box<dyn Errno>> {
+        self.backend.stat(self, td)
+    }
+}
+
+/// Flags of [`File`].
+#[bitflag(u32)]
+pub enum FileFlags {
+    /// Readable.
+    FREAD = 0x00000001,
+    /// Writable.
+    FWRITE = 0x00000002,
+    /// No delay.
+    FNONBLOCK = 0x00000004,
+    /// Set append mode.
+    FAPPEND = 0x00000008,
+    /// Signal pgrp when data ready.
+    FASYNC = 0x00000040,
+    /// Synchronous writes.
+    FFSYNC = 0x00000080,
+    /// Attempt to bypass buffer cache.
+    O_DIRECT = 0x00010000,
+}
+
+impl FileFlags {
+    /// Flags that can be changed with `F_SETFL` (AKA `FCNTLFLAGS`).
+    pub const FCNTLFLAGS: Self = Self(
+        Self::FAPPEND.0 | Self::FASYNC.0 | Self::FFSYNC.0 | Self::FNONBLOCK.0 | Self::O_DIRECT.0,
+    );
+
+    pub fn into_bits(self) -> u32 {
+        self.0
+    }
+}
+
+/// Implementation of `fileops` structure.
+///
+/// The implementation should not expose itself to the outside of its subsystem. Other subsystems
+/// should use [`File`] instead so sockets, pipes, vnodes and devices can be used the same way.
+pub trait FileBackend: Any + Send + Sync {
+    /// Returns `true` if the offset of [`File`] is meaningful for this backend (AKA
+    /// `DFLAG_SEEKABLE`).
+    fn is_seekable(&self) -> bool;
+
+    /// `off` is the current offset of the file, which the implementation is responsible to advance.
+    /// It is always zero if the backend is not seekable.
+    ///
+    /// Implementation of `fo_read`.
+    fn read(
+        &self,
+        _: &File,
+        _: &mut u64,
+        _: &mut [u8],
+        _: &Thread,
+    ) -> Result<usize, Box<dyn Errno>> {
+        Err(Box::new(DefaultFileBackendError::Read))
+    }
+
+    /// `off` is the current offset of the file, which the implementation is responsible to advance.
+    /// It is always zero if the backend is not seekable.
+    ///
+    /// Implementation of `fo_write`.
+    fn write(&self, _: &File, _: &mut u64, _: &[u8], _: &Thread) -> Result<usize, Box<dyn Errno>> {
+        Err(Box::new(DefaultFileBackendError::Write))
+    }
+
+    /// Implementation of `fo_truncate`.
+    fn truncate(&self, _: &File, _: u64, _: &Thread) -> Result<(), Box<dyn Errno>> {
+        Err(Box::new(DefaultFileBackendError::Truncate))
+    }
+
+    /// `off` is the current offset of the file, which the implementation is responsible to advance.
+    ///
+    /// This is not a part of `fileops` on the PS4 but `kern_getdirentries` will fail with `EINVAL`
+    /// if the file is not a directory.
+    fn readdir(
+        &self,
+        _: &File,
+        _: &mut u64,
+        _: &mut [u8],
+        _: &Thread,
+    ) -> Result<usize, Box<dyn Errno>> {
+        Err(Box::new(DefaultFileBackendError::ReadDir))
+    }
+
+    /// `data` is the argument that was copied in from the user. It will be copied back to the user
+    /// if the command has `IOC_OUT`.
+    ///
+    /// Implementation of `fo_ioctl`.
+    fn ioctl(&self, _: &File, _: IoCmd, _: &mut [u8], _: &Thread) -> Result<(), Box<dyn Errno>> {
+        Err(Box::new(DefaultFileBackendError::Ioctl))
+    }
+
+    /// Returns the events in `events` that are ready.
+    ///
+    /// Implementation of `fo_poll`.
+    fn poll(&self, file: &File, events: PollEvents, td: &Thread) -> PollEvents;
+
+    /// Implementation of `fo_stat`.
+    fn stat(&self, file: &File, td: &Thread) -> Result<Stat, Box<dyn Errno>>;
+}
+
+/// Events of [`FileBackend::poll()`].
+#[bitflag(u16)]
+pub enum PollEvents {
+    /// Any readable data available.
+    POLLIN = 0x0001,
+    /// OOB/Urgent readable data.
+    POLLPRI = 0x0002,
+    /// File descriptor is writeable.
+    POLLOUT = 0x0004,
+    /// Some poll error occurred.
+    POLLERR = 0x0008,
+    /// File descriptor was "hung up".
+    POLLHUP = 0x0010,
+    /// Requested events "invalid".
+    POLLNVAL = 0x0020,
+    /// Non-OOB/URG data available.
+    POLLRDNORM = 0x0040,
+    /// OOB/Urgent readable data.
+    POLLRDBAND = 0x0080,
+    /// OOB/Urgent data can be written.
+    POLLWRBAND = 0x0100,
+}
+
+/// Represents an error when the default implementation of [`FileBackend`] fails.
+#[derive(Debug)]
+pub enum DefaultFileBackendError {
+    Read,
+    Write,
+    Truncate,
+    ReadDir,
+    Ioctl,
+}
+
+impl Error for DefaultFileBackendError {}
+
+impl Display for DefaultFileBackendError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Read => f.write_str("reading is not supported"),
+            Self::Write => f.write_str("writing is not supported"),
+            Self::Truncate => f.write_str("truncating is not supported"),
+            Self::ReadDir => f.write_str("the file is not a directory"),
+            Self::Ioctl => f.write_str("ioctl is not supported"),
+        }
+    }
+}
+
+impl Errno for DefaultFileBackendError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::Read | Self::Write => ENXIO,
+            Self::Truncate | Self::ReadDir => EINVAL,
+            Self::Ioctl => ENOTTY,
+        }
+    }
+}
@@ -0,0 +1,227 @@
+pub use self::vnode::*;
+
+use crate::errno::{Errno, EINVAL, EIO, ENOTDIR};
+use config::{BootEnv, FsAttrs, FsNodeType, FsOp, FsRequest, FsType};
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+use krt::boot_env;
+
+mod vm;
+mod vnode;
+
+/// Maximum length of the name returned from [`readdir()`].
+pub const NAME_MAX: usize = 255;
+
+/// Mount the volume `name` on the host. Returns the type of filesystem on the volume and the
+/// attributes of its root directory.
+pub fn mount(name: &str) -> Result<(FsType, FsAttrs), HostError> {
+    let mut req = request(FsOp::Mount, 0);
+
+    req.name_addr = name.as_ptr() as usize;
+    req.name_len = name.len();
+
+    exec(&mut req)?;
+
+    let ty = u16::try_from(req.out)
+        .ok()
+        .and_then(|v| FsType::try_from(v).ok())
+        .ok_or(HostError::InvalidResponse)?;
+
+    Ok((ty, req.attrs))
+}
+
+/// Mount the volume `name` on the host, which must contain a filesystem `ty` with a directory as
+/// its root. Returns the attributes of the root directory.
+///
+/// This is a common part of `mount` for the filesystems that expose a directory tree on the host.
+pub fn mount_dir(name: &str, ty: FsType) -> Result<FsAttrs, MountError> {
+    let (actual, root) = mount(name).map_err(MountError::MountFailed)?;
+
+    if actual != ty {
+        return Err(MountError::WrongType);
+    } else if root.ty != u8::from(FsNodeType::Directory) {
+        return Err(MountError::InvalidRoot);
+    }
+
+    Ok(root)
+}
+
+/// Lookup `name` on the directory `node`, which can be `..` if `node` is not a root directory.
+///
+/// The result must be released with [`forget()`] once it is no longer used.
+pub fn lookup(node: u64, name: &str) -> Result<FsAttrs, HostError> {
+    let mut req = request(FsOp::Lookup, node);
+
+    req.name_addr = name.as_ptr() as usize;
+    req.name_len = name.len();
+
+    exec(&mut req)?;
+
+    Ok(req.attrs)
+}
+
+pub fn getattr(node: u64) -> Result<FsAttrs, HostError> {
+    let mut req = request(FsOp::GetAttr, node);
+
+    exec(&mut req)?;
+
+    Ok(req.attrs)
+}
+
+/// Returns the number of bytes read, which will be zero at the end of the file.
+pub fn read(node: u64, off: u64, buf: &mut [u8]) -> Result<usize, HostError> {
+    let mut req = request(FsOp::Read, node);
+
+    req.off = off;
+    req.buf_addr = buf.as_mut_ptr() as usize;
+    req.buf_len = buf.len();
+
+    exec(&mut req)?;
+
+    usize::try_from(req.out)
+        .ok()
+        .filter(|&v| v <= buf.len())
+        .ok_or(HostError::InvalidResponse)
+}
+
+/// Returns the number of bytes written, which can be less than `buf`.
+pub fn write(node: u64, off: u64, buf: &[u8]) -> Result<usize, HostError> {
+    let mut req = request(FsOp::Write, node);
+
+    req.off = off;
+    req.buf_addr = buf.as_ptr() as usize;
+    req.buf_len = buf.len();
+
+    exec(&mut req)?;
+
+    usize::try_from(req.out)
+        .ok()
+        .filter(|&v| v <= buf.len())
+        .ok_or(HostError::InvalidResponse)
+}
+
+/// Release `n` references to `node` that were returned from [`lookup()`]. The ID of `node` may be
+/// reused by the host once all of its references have been released.
+pub fn forget(node: u64, n: u64) -> Result<(), HostError> {
+    let mut req = request(FsOp::Forget, node);
+
+    req.off = n;
+
+    exec(&mut req)
+}
+
+/// Read the entry at `index` on the directory `node`. Returns the length of the name written to
+/// `buf` and the attributes of the entry or [`None`] if there are no more entries.
+pub fn readdir(
+    node: u64,
+    index: u64,
+    buf: &mut [u8; NAME_MAX],
+) -> Result<Option<(usize, FsAttrs)>, HostError> {
+    let mut req = request(FsOp::ReadDir, node);
+
+    req.off = index;
+    req.buf_addr = buf.as_mut_ptr() as usize;
+    req.buf_len = buf.len();
+
+    exec(&mut req)?;
+
+    match usize::try_from(req.out) {
+        Ok(0) => Ok(None),
+        Ok(v) if v <= buf.len() => Ok(Some((v, req.attrs))),
+        _ => Err(HostError::InvalidResponse),
+    }
+}
+
+fn request(op: FsOp, node: u64) -> FsRequest {
+    FsRequest {
+        op: op.into(),
+        node,
+        off: 0,
+        name_addr: 0,
+        name_len: 0,
+        buf_addr: 0,
+        buf_len: 0,
+        errno: 0,
+        out: 0,
+        attrs: FsAttrs::default(),
+    }
+}
+
+fn exec(req: &mut FsRequest) -> Result<(), HostError> {
+    match boot_env() {
+        BootEnv::Vm(env) => self::vm::exec(env, req),
+    }
+
+    match NonZero::new(req.errno) {
+        Some(v) => Err(HostError::Failed(v)),
+        None => Ok(()),
+    }
+}
+
+/// Represents an error when a request to the filesystem on the host fails.
+#[derive(Debug)]
+pub enum HostError {
+    Failed(NonZero<c_int>),
+    InvalidResponse,
+}
+
+impl Error for HostError {}
+
+impl Display for HostError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Failed(v) => write!(f, "the host returned error {v}"),
+            Self::InvalidResponse => f.write_str("the host returned an invalid response"),
+        }
+    }
+}
+
+impl Errno for HostError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::Failed(v) => *v,
+            Self::InvalidResponse => EIO,
+        }
+    }
+}
+
+/// Represents an error when the filesystem backed by a volume on the host fails to mount.
+#[derive(Debug)]
+pub enum MountError {
+    NoFrom,
+    MountFailed(HostError),
+    WrongType,
+    InvalidRoot,
+}
+
+impl Error for MountError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::MountFailed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Display for MountError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoFrom => f.write_str("no from option"),
+            Self::MountFailed(_) => f.write_str("couldn't mount the volume on the host"),
+            Self::WrongType => f.write_str("the volume does not contain the requested filesystem"),
+            Self::InvalidRoot => f.write_str("the root of the volume is not a directory"),
+        }
+    }
+}
+
+impl Errno for MountError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NoFrom | Self::WrongType => EINVAL,
+            Self::MountFailed(e) => e.errno(),
+            Self::InvalidRoot => ENOTDIR,
+        }
+    }
+}
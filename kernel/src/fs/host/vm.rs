@@ -0,0 +1,15 @@
+use config::{FsMemory, FsRequest, Vm};
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{compiler_fence, Ordering};
+
+pub fn exec(env: &Vm, req: &mut FsRequest) {
+    let m = env.fs as *mut FsMemory;
+    let req = &raw mut *req;
+
+    // The VMM will write the result to the request while we are writing its address so we need to
+    // make sure the compiler does not use the values it has before the write.
+    compiler_fence(Ordering::SeqCst);
+    unsafe { write_volatile(&raw mut (*m).exec, req as usize) };
+    compiler_fence(Ordering::SeqCst);
+    unsafe { req.write(read_volatile(req)) };
+}
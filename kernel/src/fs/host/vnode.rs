@@ -0,0 +1,235 @@
+use super::{HostError, NAME_MAX};
+use crate::config::PAGE_SIZE;
+use crate::errno::{Errno, EINVAL};
+use crate::fs::{DirEntry, DirType, Mount, Vnode, VnodeAttrs, VnodeBackend, VnodeType, NODEV};
+use crate::proc::Thread;
+use crate::time::TimeSpec;
+use crate::ucred::{Gid, Uid};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use config::{FsAttrs, FsNodeType, FsTime};
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// Implementation of [`VnodeBackend`] for a node on [`super`].
+///
+/// This is shared by all filesystems that forward everything to the host.
+pub struct HostVnode {
+    node: u64,
+    root: u64,
+    tag: &'static str,
+    refs: AtomicU64,
+}
+
+impl HostVnode {
+    /// Returns the vnode for the node with `attrs`. `root` is the ID of the root directory on the
+    /// volume.
+    ///
+    /// Each call take over a reference to the node from [`super::lookup()`], which will be released
+    /// when the vnode is destroyed.
+    pub fn alloc(
+        mnt: &Arc<Mount>,
+        attrs: &FsAttrs,
+        root: u64,
+        tag: &'static str,
+    ) -> Result<Arc<Vnode>, HostError> {
+        let vn = mnt.hash_get_or_insert(attrs.id, || {
+            let ty = match FsNodeType::try_from(attrs.ty) {
+                Ok(FsNodeType::File) => VnodeType::File,
+                Ok(FsNodeType::Directory) => VnodeType::Directory(attrs.id == root),
+                Ok(FsNodeType::Link) => VnodeType::Link,
+                Err(_) => return Err(HostError::InvalidResponse),
+            };
+
+            let backend = Self {
+                node: attrs.id,
+                root,
+                tag,
+                refs: AtomicU64::new(0),
+            };
+
+            Ok(Vnode::new(mnt, ty, tag, backend))
+        })?;
+
+        vn.backend::<Self>()
+            .unwrap()
+            .refs
+            .fetch_add(1, Ordering::Relaxed);
+
+        Ok(vn)
+    }
+}
+
+impl Drop for HostVnode {
+    fn drop(&mut self) {
+        // The host never release the root of the volume so it is fine to forget the references that
+        // were not come from lookup (e.g. the root vnode).
+        let _ = super::forget(self.node, *self.refs.get_mut());
+    }
+}
+
+impl VnodeBackend for HostVnode {
+    fn getattr(&self, vn: &Arc<Vnode>, _: &Thread) -> Result<VnodeAttrs, Box<dyn Errno>> {
+        let attrs = super::getattr(self.node).map_err(err)?;
+        let time = |v: FsTime| TimeSpec {
+            sec: v.sec,
+            nsec: v.nsec,
+        };
+
+        Ok(VnodeAttrs {
+            mode: attrs.mode,
+            nlink: attrs.nlink,
+            uid: Uid::new(attrs.uid).ok_or_else(|| err(HostError::InvalidResponse))?,
+            gid: Gid::new(attrs.gid).ok_or_else(|| err(HostError::InvalidResponse))?,
+            fsid: vn.mount().id()[0],
+            id: attrs.id,
+            size: attrs.size,
+            blksize: PAGE_SIZE.get() as u32,
+            atime: time(attrs.atime),
+            mtime: time(attrs.mtime),
+            ctime: time(attrs.ctime),
+            birthtime: time(attrs.birthtime),
+            gen: 0,
+            flags: 0,
+            rdev: NODEV,
+            bytes: attrs.size,
+        })
+    }
+
+    fn lookup(
+        &self,
+        vn: &Arc<Vnode>,
+        _: &Thread,
+        name: &str,
+    ) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        super::lookup(self.node, name)
+            .and_then(|attrs| Self::alloc(vn.mount(), &attrs, self.root, self.tag))
+            .map_err(err)
+    }
+
+    fn readdir(
+        &self,
+        vn: &Arc<Vnode>,
+        _: &Thread,
+        off: &mut u64,
+        buf: &mut [u8],
+    ) -> Result<usize, Box<dyn Errno>> {
+        let mut written = 0;
+        let mut full = false;
+        let mut put = |ent: DirEntry, next: u64, off: &mut u64| match ent.write(&mut buf[written..])
+        {
+            Some(n) => {
+                written += n;
+                *off = next;
+                true
+            }
+            None => {
+                full = true;
+                false
+            }
+        };
+
+        // Write "." and "..". The offset 0 and 1 are reserved for them.
+        if *off == 0 {
+            put(
+                DirEntry::new(self.node as u32, DirType::Directory, "."),
+                1,
+                off,
+            );
+        }
+
+        if *off == 1 {
+            let id = if vn.is_mount_root() {
+                self.node
+            } else {
+                let id = super::lookup(self.node, "..").map_err(err)?.id;
+
+                super::forget(id, 1).map_err(err)?;
+
+                id
+            };
+
+            put(DirEntry::new(id as u32, DirType::Directory, ".."), 2, off);
+        }
+
+        // Write the entries.
+        let mut name = [0; NAME_MAX];
+
+        while *off >= 2 {
+            let (len, attrs) = match super::readdir(self.node, *off - 2, &mut name).map_err(err)? {
+                Some(v) => v,
+                None => break,
+            };
+
+            let name =
+                core::str::from_utf8(&name[..len]).map_err(|_| err(HostError::InvalidResponse))?;
+            let ty = match FsNodeType::try_from(attrs.ty) {
+                Ok(FsNodeType::File) => DirType::Regular,
+                Ok(FsNodeType::Directory) => DirType::Directory,
+                Ok(FsNodeType::Link) => DirType::Link,
+                Err(_) => return Err(err(HostError::InvalidResponse)),
+            };
+
+            if !put(DirEntry::new(attrs.id as u32, ty, name), *off + 1, off) {
+                break;
+            }
+        }
+
+        if written == 0 && full {
+            return Err(Box::new(ReadDirError::BufferTooSmall));
+        }
+
+        Ok(written)
+    }
+
+    fn read(
+        &self,
+        _: &Arc<Vnode>,
+        _: &Thread,
+        off: u64,
+        buf: &mut [u8],
+    ) -> Result<usize, Box<dyn Errno>> {
+        super::read(self.node, off, buf).map_err(err)
+    }
+
+    fn write(
+        &self,
+        _: &Arc<Vnode>,
+        _: &Thread,
+        off: u64,
+        buf: &[u8],
+    ) -> Result<usize, Box<dyn Errno>> {
+        super::write(self.node, off, buf).map_err(err)
+    }
+}
+
+fn err(e: HostError) -> Box<dyn Errno> {
+    Box::new(e)
+}
+
+/// Represents an error when [`HostVnode::readdir()`] fails.
+#[derive(Debug)]
+enum ReadDirError {
+    BufferTooSmall,
+}
+
+impl Error for ReadDirError {}
+
+impl Display for ReadDirError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BufferTooSmall => f.write_str("the buffer is too small for the entry"),
+        }
+    }
+}
+
+impl Errno for ReadDirError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::BufferTooSmall => EINVAL,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use super::host::HostVnode;
+use super::{Filesystem, Fs, FsConfig, Mount, MountFlags, MountOpts, Vnode};
+use crate::errno::Errno;
+use crate::proc::Thread;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use config::FsType;
+
+/// Configuration of host filesystem.
+///
+/// This filesystem does not exist on the PS4 so the type number was chosen to not conflict with
+/// the known filesystems.
+pub static HOSTFS: FsConfig = FsConfig {
+    name: "hostfs",
+    ty: 0xF0,
+    mount: HostFs::mount,
+};
+
+/// Implementation of a filesystem that expose a directory on the host.
+///
+/// This is for development only so the developers can run their binaries without building an
+/// image. The directory is read-only unless it was configured as writable on the host.
+pub struct HostFs {
+    root: u64,
+}
+
+impl HostFs {
+    /// Name of the volume on the host.
+    const VOLUME: &str = "host";
+
+    fn mount(
+        _: &Fs,
+        _: Option<&Arc<Vnode>>,
+        _: &mut MountOpts,
+        flags: &mut MountFlags,
+        _: &Thread,
+    ) -> Result<Box<dyn Filesystem>, Box<dyn Errno>> {
+        let root = match super::host::mount_dir(Self::VOLUME, FsType::Host) {
+            Ok(v) => v,
+            Err(e) => return Err(Box::new(e)),
+        };
+
+        // Force read-only if the host does not allow writing.
+        if root.writable == 0 {
+            *flags |= MountFlags::MNT_RDONLY;
+        }
+
+        *flags |= MountFlags::MNT_LOCAL;
+
+        Ok(Box::new(Self { root: root.id }))
+    }
+}
+
+impl Filesystem for HostFs {
+    fn root(&self, mnt: &Arc<Mount>, _: &Thread) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        super::host::getattr(self.root)
+            .and_then(|attrs| HostVnode::alloc(mnt, &attrs, self.root, "hostfs"))
+            .map_err(|e| Box::new(e) as Box<dyn Errno>)
+    }
+}
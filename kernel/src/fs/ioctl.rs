@@ -0,0 +1,133 @@
+use crate::dmem::{BlockPoolExpand, BlockPoolStats, DmemAllocate, DmemAvailable, DmemQuery};
+use core::ffi::c_int;
+
+/// Command of `ioctl`.
+///
+/// The PS4 use `u_long` for the command but only the lower 32-bits are used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoCmd(u32);
+
+impl IoCmd {
+    /// Set close-on-exec on the file descriptor.
+    pub const FIOCLEX: Self = Self::io(b'f', 1);
+    /// Remove close-on-exec from the file descriptor.
+    pub const FIONCLEX: Self = Self::io(b'f', 2);
+    /// Get `d_flags` type part.
+    pub const FIODTYPE: Self = Self::ior::<c_int>(b'f', 122);
+    /// Set or clear async I/O.
+    pub const FIOASYNC: Self = Self::iow::<c_int>(b'f', 125);
+    /// Set or clear non-blocking I/O.
+    pub const FIONBIO: Self = Self::iow::<c_int>(b'f', 126);
+    /// Allocate direct memory.
+    pub const DMEMALLOC: Self = Self::iowr::<DmemAllocate>(0x80, 0x01);
+    /// Get the size of direct memory.
+    pub const DMEMTOTAL: Self = Self::ior::<usize>(0x80, 0x0A);
+    /// Allocate main direct memory.
+    pub const DMEMALLOCMAIN: Self = Self::iowr::<DmemAllocate>(0x80, 0x11);
+    /// Query allocated direct memory.
+    pub const DMEMQUERY: Self = Self::iow::<DmemQuery>(0x80, 0x12);
+    /// Get the largest free range of direct memory.
+    pub const DMEMGETAVAIL: Self = Self::iowr::<DmemAvailable>(0x80, 0x16);
+    /// Add direct memory to the blockpool.
+    pub const BPOOLEXPAND: Self = Self::iowr::<BlockPoolExpand>(0xA8, 0x01);
+    /// Get statistics of the blockpool.
+    pub const BPOOLSTATS: Self = Self::ior::<BlockPoolStats>(0xA8, 0x02);
+
+    const IOCPARM_SHIFT: u32 = 13;
+    const IOCPARM_MASK: u32 = (1 << Self::IOCPARM_SHIFT) - 1;
+    const IOC_VOID: u32 = 0x20000000;
+    const IOC_OUT: u32 = 0x40000000;
+    const IOC_IN: u32 = 0x80000000;
+
+    /// Returns [`None`] if `v` is not a valid command.
+    pub fn new(v: u32) -> Option<Self> {
+        let cmd = Self(v);
+
+        if (v & (Self::IOC_VOID | Self::IOC_IN | Self::IOC_OUT)) == 0 {
+            return None;
+        }
+
+        if cmd.is_void() && cmd.len() != 0 && cmd.len() != size_of::<c_int>() {
+            return None;
+        }
+
+        Some(cmd)
+    }
+
+    /// See `_IO` on the PS4 for a reference.
+    pub const fn io(group: u8, num: u8) -> Self {
+        Self::ioc(Self::IOC_VOID, group, num, 0)
+    }
+
+    /// See `_IOR` on the PS4 for a reference.
+    pub const fn ior<T>(group: u8, num: u8) -> Self {
+        Self::ioc(Self::IOC_OUT, group, num, size_of::<T>())
+    }
+
+    /// See `_IOW` on the PS4 for a reference.
+    pub const fn iow<T>(group: u8, num: u8) -> Self {
+        Self::ioc(Self::IOC_IN, group, num, size_of::<T>())
+    }
+
+    /// See `_IOWR` on the PS4 for a reference.
+    pub const fn iowr<T>(group: u8, num: u8) -> Self {
+        Self::ioc(Self::IOC_IN | Self::IOC_OUT, group, num, size_of::<T>())
+    }
+
+    /// Returns `true` if the argument is an integer instead of a pointer (AKA `IOC_VOID`).
+    pub fn is_void(self) -> bool {
+        (self.0 & Self::IOC_VOID) != 0
+    }
+
+    /// Returns `true` if the argument need to be copied in (AKA `IOC_IN`).
+    pub fn is_in(self) -> bool {
+        (self.0 & Self::IOC_IN) != 0
+    }
+
+    /// Returns `true` if the argument need to be copied out (AKA `IOC_OUT`).
+    pub fn is_out(self) -> bool {
+        (self.0 & Self::IOC_OUT) != 0
+    }
+
+    /// Returns the size of the argument.
+    ///
+    /// See `IOCPARM_LEN` on the PS4 for a reference.
+    pub fn len(self) -> usize {
+        ((self.0 >> 16) & Self::IOCPARM_MASK) as usize
+    }
+
+    /// See `_IOC` on the PS4 for a reference.
+    const fn ioc(inout: u32, group: u8, num: u8, len: usize) -> Self {
+        let len = len as u32;
+
+        assert!(len <= Self::IOCPARM_MASK);
+
+        Self(inout | (len << 16) | ((group as u32) << 8) | num as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cmd() {
+        assert_eq!(IoCmd::FIOCLEX.0, 0x20006601);
+        assert_eq!(IoCmd::FIONBIO.0, 0x8004667e);
+        assert_eq!(IoCmd::FIONBIO.len(), 4);
+        assert!(IoCmd::FIONBIO.is_in());
+        assert!(!IoCmd::FIONBIO.is_out());
+        assert_eq!(IoCmd::FIODTYPE.0, 0x4004667a);
+        assert!(IoCmd::FIODTYPE.is_out());
+        assert_eq!(IoCmd::new(0x6601), None);
+        assert_eq!(IoCmd::new(0x20086601), None);
+        assert_eq!(IoCmd::new(0xc0106601).map(|v| v.len()), Some(0x10));
+        assert_eq!(IoCmd::DMEMALLOC.0, 0xc0288001);
+        assert_eq!(IoCmd::DMEMTOTAL.0, 0x4008800a);
+        assert_eq!(IoCmd::DMEMALLOCMAIN.0, 0xc0288011);
+        assert_eq!(IoCmd::DMEMQUERY.0, 0x80288012);
+        assert_eq!(IoCmd::DMEMGETAVAIL.0, 0xc0208016);
+        assert_eq!(IoCmd::BPOOLEXPAND.0, 0xc020a801);
+        assert_eq!(IoCmd::BPOOLSTATS.0, 0x4010a802);
+    }
+}
@@ -0,0 +1,1910 @@
+pub use self::dev::*;
+pub use self::dirent::*;
+pub use self::exfat::*;
+pub use self::file::*;
+pub use self::hostfs::*;
+pub use self::ioctl::*;
+pub use self::mount::*;
+pub use self::null::*;
+pub use self::perm::*;
+pub use self::pfs::*;
+pub use self::stat::*;
+pub use self::tmp::*;
+pub use self::vnode::*;
+
+use self::dev::Devices;
+use crate::context::current_fs;
+use crate::errno::{
+    Errno, EACCES, EBUSY, EEXIST, EINVAL, EISDIR, ELOOP, ENAMETOOLONG, ENODEV, ENOENT, ENOEXEC,
+    ENOTDIR, EPERM, EXDEV,
+};
+use crate::lock::{Gutex, GutexGroup};
+use crate::proc::Thread;
+use crate::subsystem::Subsystem;
+use crate::syscalls::{SysErr, SysIn, SysOut, Syscalls};
+use crate::time::{TimeSpec, TimeVal};
+use crate::ucred::{Privilege, PrivilegeError};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+use macros::bitflag;
+
+mod dev;
+mod dirent;
+mod exfat;
+mod file;
+mod host;
+mod hostfs;
+mod ioctl;
+mod mount;
+mod null;
+mod perm;
+mod pfs;
+mod stat;
+mod tmp;
+mod vnode;
+
+/// Virtual filesystem layer.
+///
+/// The locks must be acquired in the same order as the fields.
+pub struct Fs {
+    confs: Vec<&'static FsConfig>,   // vfsconf
+    mounts: Gutex<Vec<Arc<Mount>>>,  // mountlist
+    root: Gutex<Option<Arc<Vnode>>>, // rootvnode
+    last_id: Gutex<u16>,             // mntid_base
+    devices: Arc<Devices>,
+}
+
+impl Fs {
+    /// # Context safety
+    /// This function does not require a CPU context.
+    pub fn new() -> Self {
+        let gg = GutexGroup::new();
+
+        Self {
+            confs: Vec::new(),
+            mounts: gg.clone().spawn(Vec::new()),
+            root: gg.clone().spawn(None),
+            last_id: gg.spawn(0),
+            devices: Arc::new(Devices::new()),
+        }
+    }
+
+    pub fn register_syscalls(sys: &mut Syscalls) {
+        sys.register(5, Self::sys_open);
+        sys.register(10, Self::sys_unlink);
+        sys.register(12, Self::sys_chdir);
+        sys.register(22, Self::sys_unmount);
+        sys.register(57, Self::sys_symlink);
+        sys.register(58, Self::sys_readlink);
+        sys.register(61, Self::sys_chroot);
+        sys.register(128, Self::sys_rename);
+        sys.register(136, Self::sys_mkdir);
+        sys.register(137, Self::sys_rmdir);
+        sys.register(138, Self::sys_utimes);
+        sys.register(188, Self::sys_stat);
+        sys.register(190, Self::sys_lstat);
+        sys.register(196, Self::sys_getdirentries);
+        sys.register(272, Self::sys_getdents);
+        sys.register(378, Self::sys_nmount);
+        sys.register(479, Self::sys_truncate);
+        sys.register(480, Self::sys_ftruncate);
+    }
+
+    /// See `vfs_register` on the PS4 for a reference.
+    ///
+    /// # Panics
+    /// If the filesystem with the same name already registered.
+    pub fn register(&mut self, conf: &'static FsConfig) {
+        if self.confs.iter().any(|c| c.name == conf.name) {
+            panic!("filesystem {} is already registered", conf.name);
+        }
+
+        self.confs.push(conf);
+    }
+
+    /// Create a new device on devfs.
+    ///
+    /// See `make_dev_credv` on the PS4 for a reference.
+    pub fn make_dev(
+        &self,
+        name: &str,
+        driver: impl DeviceDriver,
+        args: MakeDevArgs,
+    ) -> Result<Arc<CharacterDevice>, MakeDevError> {
+        self.devices.make_dev(name, Box::new(driver), args)
+    }
+
+    /// Returns [`None`] if the root filesystem has not been mounted.
+    pub fn root(&self) -> Option<Arc<Vnode>> {
+        self.root.write().clone()
+    }
+
+    /// Returns a vnode for `path`. `follow` specify whether to follow the last component if it is
+    /// a symbolic link.
+    pub fn lookup(&self, path: &str, follow: bool, td: &Thread) -> Result<Arc<Vnode>, LookupError> {
+        let r = self.namei(path, NameiOp::Lookup, follow, td)?;
+
+        Ok(r.vn.unwrap())
+    }
+
+    /// `follow` specify whether to follow the last component if it is a symbolic link. The symbolic
+    /// link in the other components will always be followed.
+    ///
+    /// The last component may not exists if `op` is [`NameiOp::Create`] or [`NameiOp::Rename`].
+    ///
+    /// See `namei` and `lookup` on the PS4 for a reference.
+    pub fn namei(
+        &self,
+        path: &str,
+        op: NameiOp,
+        follow: bool,
+        td: &Thread,
+    ) -> Result<NameiResult, LookupError> {
+        if path.is_empty() {
+            return Err(LookupError::EmptyPath);
+        } else if path.len() >= MAXPATHLEN {
+            return Err(LookupError::NameTooLong);
+        }
+
+        // Get root directory. The ".." on this directory will be the directory itself so the
+        // process cannot escape from chroot.
+        let files = td.proc().files();
+        let top = self.root().ok_or(LookupError::NoRoot)?;
+        let root = files.root().unwrap_or_else(|| top.clone());
+
+        // Get starting directory.
+        let mut dir = if path.starts_with('/') {
+            root.clone()
+        } else {
+            files.cwd().unwrap_or_else(|| root.clone())
+        };
+
+        // Walk on the path.
+        let mut path = String::from(path);
+        let mut pos = 0;
+        let mut links = 0;
+
+        loop {
+            // Skip separators.
+            while path.as_bytes().get(pos) == Some(&b'/') {
+                pos += 1;
+            }
+
+            // Check for degenerate name (e.g. "/").
+            if pos == path.len() {
+                if op != NameiOp::Lookup {
+                    return Err(LookupError::IsDirectory);
+                }
+
+                return Ok(NameiResult {
+                    dir: dir.clone(),
+                    name: "".into(),
+                    vn: Some(dir),
+                });
+            }
+
+            // Get the component.
+            let end = path[pos..].find('/').map_or(path.len(), |i| pos + i);
+            let name = &path[pos..end];
+            let last = path[end..].bytes().all(|b| b == b'/');
+
+            if name.len() > NAME_MAX {
+                return Err(LookupError::NameTooLong);
+            }
+
+            // The directory must be searchable.
+            if !dir.is_directory() {
+                return Err(LookupError::NotDirectory);
+            }
+
+            dir.access(td, Access::EXEC)
+                .map_err(LookupError::AccessFailed)?;
+
+            // The caller cannot remove or rename "." and "..".
+            let modify = matches!(op, NameiOp::Delete | NameiOp::Rename);
+
+            if last && modify && (name == "." || name == "..") {
+                return Err(LookupError::InvalidName);
+            }
+
+            // Lookup the component.
+            let vn = match name {
+                "." => dir.clone(),
+                ".." => {
+                    // Cross the mount point if the directory is the root of a mount.
+                    let mut found = None;
+
+                    loop {
+                        if Arc::ptr_eq(&dir, &root) || Arc::ptr_eq(&dir, &top) {
+                            found = Some(dir.clone());
+                            break;
+                        }
+
+                        if !dir.is_mount_root() {
+                            break;
+                        }
+
+                        dir = match dir.mount().parent() {
+                            Some(v) => v,
+                            None => break,
+                        };
+                    }
+
+                    match found {
+                        Some(v) => v,
+                        None => dir.lookup(td, "..").map_err(LookupError::LookupFailed)?,
+                    }
+                }
+                _ => match Self::lookup_union(&mut dir, td, name)? {
+                    Some(v) => v,
+                    None => {
+                        if !last || !matches!(op, NameiOp::Create | NameiOp::Rename) {
+                            return Err(LookupError::NotFound);
+                        }
+
+                        // The caller need to write the directory to create the last component.
+                        dir.access(td, Access::WRITE)
+                            .map_err(LookupError::AccessFailed)?;
+
+                        return Ok(NameiResult {
+                            dir,
+                            name: name.into(),
+                            vn: None,
+                        });
+                    }
+                },
+            };
+
+            // Cross the mount points.
+            let mut vn = vn;
+
+            while vn.is_directory() {
+                let mp = match vn.mounted_here() {
+                    Some(v) => v,
+                    None => break,
+                };
+
+                vn = mp.root(td).map_err(LookupError::GetRootFailed)?;
+            }
+
+            // Follow the symbolic link.
+            let slash = end != path.len();
+
+            if vn.is_link() && (!last || follow || slash) {
+                if vn.mount().flags().has(MountFlags::MNT_NOSYMFOLLOW) {
+                    return Err(LookupError::SymlinkNotAllowed);
+                }
+
+                links += 1;
+
+                if links > MAXSYMLINKS {
+                    return Err(LookupError::TooManyLinks);
+                }
+
+                // Replace the component with the target.
+                let target = vn.readlink(td).map_err(LookupError::ReadLinkFailed)?;
+
+                if target.is_empty() {
+                    return Err(LookupError::NotFound);
+                } else if target.len() + (path.len() - end) >= MAXPATHLEN {
+                    return Err(LookupError::NameTooLong);
+                }
+
+                if target.starts_with('/') {
+                    dir = root.clone();
+                }
+
+                path = String::from(target) + &path[end..];
+                pos = 0;
+                continue;
+            }
+
+            // Check if this is the last component.
+            if last {
+                if slash && !vn.is_directory() {
+                    return Err(LookupError::NotDirectory);
+                }
+
+                if modify {
+                    Self::check_modify(&dir, &vn, td)?;
+                }
+
+                return Ok(NameiResult {
+                    dir,
+                    name: name.into(),
+                    vn: Some(vn),
+                });
+            }
+
+            dir = vn;
+            pos = end;
+        }
+    }
+
+    /// See `vn_open_cred` on the PS4 for a reference.
+    pub fn open(
+        &self,
+        path: &str,
+        flags: OpenFlags,
+        mode: u16,
+        td: &Thread,
+    ) -> Result<File, OpenError> {
+        // Check flags.
+        let acc = flags & OpenFlags::O_ACCMODE;
+
+        if flags.has(OpenFlags::O_EXEC) {
+            if acc.has(OpenFlags::O_ACCMODE) {
+                return Err(OpenError::InvalidFlags);
+            }
+        } else if acc.has_all(OpenFlags::O_ACCMODE) {
+            return Err(OpenError::InvalidFlags);
+        }
+
+        // Get the vnode.
+        let fflags = flags.into_fflags();
+        let nofollow = flags.has(OpenFlags::O_NOFOLLOW);
+        let (vn, created) = if flags.has(OpenFlags::O_CREAT) {
+            let follow = !nofollow && !flags.has(OpenFlags::O_EXCL);
+            let r = self.namei(path, NameiOp::Create, follow, td)?;
+
+            match r.vn {
+                Some(_) if flags.has(OpenFlags::O_EXCL) => return Err(OpenError::Exists),
+                Some(v) if v.is_directory() => return Err(OpenError::IsDirectory),
+                Some(v) => (v, false),
+                None => {
+                    // TODO: Apply umask.
+                    let vn = r
+                        .dir
+                        .create(td, &r.name, mode & 0o7777)
+                        .map_err(OpenError::CreateFailed)?;
+
+                    (vn, true)
+                }
+            }
+        } else {
+            (self.lookup(path, !nofollow, td)?, false)
+        };
+
+        // Check the vnode. See vn_open_vnode on the PS4 for a reference.
+        if vn.is_link() {
+            return Err(OpenError::SymbolicLink);
+        } else if fflags.has(FileFlags::FWRITE) && vn.is_directory() {
+            return Err(OpenError::IsDirectory);
+        } else if flags.has(OpenFlags::O_DIRECTORY) && !vn.is_directory() {
+            return Err(OpenError::NotDirectory);
+        }
+
+        if !created {
+            let mut access = Access::zeroed();
+
+            if fflags.has(FileFlags::FREAD) {
+                access |= Access::READ;
+            }
+
+            if fflags.has(FileFlags::FWRITE) || flags.has(OpenFlags::O_TRUNC) {
+                access |= Access::WRITE;
+            }
+
+            if flags.has(OpenFlags::O_EXEC) {
+                access |= Access::EXEC;
+            }
+
+            if flags.has(OpenFlags::O_APPEND) && fflags.has(FileFlags::FWRITE) {
+                access |= Access::APPEND;
+            }
+
+            vn.access(td, access).map_err(OpenError::AccessFailed)?;
+        }
+
+        vn.open(td, fflags).map_err(OpenError::OpenFailed)?;
+
+        if flags.has(OpenFlags::O_TRUNC) && !created && vn.is_file() {
+            let attrs = VnodeSetAttrs {
+                size: Some(0),
+                ..Default::default()
+            };
+
+            vn.setattr(td, &attrs).map_err(OpenError::TruncateFailed)?;
+        }
+
+        // The devfs_open on the PS4 replace the file operations with devfs_ops_f.
+        let file = match vn.device() {
+            Some(dev) => File::new(fflags, CdevFileBackend::new(vn, dev)),
+            None => File::new(fflags, VnodeFileBackend::new(vn)),
+        };
+
+        Ok(file)
+    }
+
+    /// See `kern_mkdirat` on the PS4 for a reference.
+    pub fn mkdir(&self, path: &str, mode: u16, td: &Thread) -> Result<Arc<Vnode>, MkdirError> {
+        let r = self.namei(path, NameiOp::Create, false, td)?;
+
+        if r.vn.is_some() {
+            return Err(MkdirError::Exists);
+        }
+
+        // TODO: Apply umask.
+        r.dir
+            .mkdir(td, &r.name, mode & 0o7777)
+            .map_err(MkdirError::CreateFailed)
+    }
+
+    /// See `kern_unlinkat` on the PS4 for a reference.
+    pub fn unlink(&self, path: &str, td: &Thread) -> Result<(), RemoveError> {
+        let r = self.namei(path, NameiOp::Delete, false, td)?;
+        let vn = r.vn.unwrap();
+
+        if vn.is_directory() {
+            return Err(RemoveError::IsDirectory);
+        }
+
+        r.dir
+            .remove(td, &vn, &r.name)
+            .map_err(RemoveError::RemoveFailed)
+    }
+
+    /// See `kern_rmdirat` on the PS4 for a reference.
+    pub fn rmdir(&self, path: &str, td: &Thread) -> Result<(), RemoveError> {
+        let r = self.namei(path, NameiOp::Delete, false, td)?;
+        let vn = r.vn.unwrap();
+
+        if !vn.is_directory() {
+            return Err(RemoveError::NotDirectory);
+        } else if vn.is_mount_root() {
+            return Err(RemoveError::Busy);
+        }
+
+        r.dir
+            .rmdir(td, &vn, &r.name)
+            .map_err(RemoveError::RemoveFailed)
+    }
+
+    /// See `kern_renameat` on the PS4 for a reference.
+    pub fn rename(&self, from: &str, to: &str, td: &Thread) -> Result<(), RenameError> {
+        let f = self.namei(from, NameiOp::Delete, false, td)?;
+        let t = self.namei(to, NameiOp::Rename, false, td)?;
+        let vn = f.vn.unwrap();
+
+        // Check the target.
+        if let Some(tv) = &t.vn {
+            if vn.is_directory() && !tv.is_directory() {
+                return Err(RenameError::NotDirectory);
+            } else if !vn.is_directory() && tv.is_directory() {
+                return Err(RenameError::IsDirectory);
+            } else if !Arc::ptr_eq(vn.mount(), tv.mount()) {
+                return Err(RenameError::CrossDevice);
+            } else if Arc::ptr_eq(&vn, tv) {
+                return Ok(());
+            }
+        }
+
+        if !Arc::ptr_eq(vn.mount(), t.dir.mount()) || vn.is_mount_root() {
+            return Err(RenameError::CrossDevice);
+        } else if Arc::ptr_eq(&vn, &t.dir) {
+            return Err(RenameError::InvalidTarget);
+        }
+
+        f.dir
+            .rename(td, &vn, &f.name, &t.dir, t.vn.as_ref(), &t.name)
+            .map_err(RenameError::RenameFailed)
+    }
+
+    /// See `kern_symlinkat` on the PS4 for a reference.
+    pub fn symlink(
+        &self,
+        target: &str,
+        path: &str,
+        td: &Thread,
+    ) -> Result<Arc<Vnode>, SymlinkError> {
+        let r = self.namei(path, NameiOp::Create, false, td)?;
+
+        if r.vn.is_some() {
+            return Err(SymlinkError::Exists);
+        }
+
+        // TODO: Apply umask.
+        r.dir
+            .symlink(td, &r.name, 0o777, target)
+            .map_err(SymlinkError::CreateFailed)
+    }
+
+    /// See `vfs_donmount` on the PS4 for a reference.
+    pub fn mount(
+        &self,
+        mut opts: MountOpts,
+        mut flags: MountFlags,
+        td: &Thread,
+    ) -> Result<Arc<Mount>, MountError> {
+        // TODO: Check usermount and jail.
+        td.priv_check(Privilege::VFS_MOUNT)
+            .map_err(MountError::NoPrivilege)?;
+
+        // Get required options. The length on the PS4 include NUL.
+        let ty = opts.remove_str("fstype")?.ok_or(MountError::NoFsType)?;
+        let path = opts.remove_str("fspath")?.ok_or(MountError::NoPath)?;
+
+        if ty.len() + 1 >= MFSNAMELEN - 1 {
+            return Err(MountError::FsTypeTooLong);
+        } else if path.len() + 1 >= MNAMELEN - 1 {
+            return Err(MountError::PathTooLong);
+        }
+
+        // Process generic options.
+        for (name, set, flag) in [
+            ("async", true, MountFlags::MNT_ASYNC),
+            ("noasync", false, MountFlags::MNT_ASYNC),
+            ("noatime", true, MountFlags::MNT_NOATIME),
+            ("atime", false, MountFlags::MNT_NOATIME),
+            ("noexec", true, MountFlags::MNT_NOEXEC),
+            ("exec", false, MountFlags::MNT_NOEXEC),
+            ("nosuid", true, MountFlags::MNT_NOSUID),
+            ("suid", false, MountFlags::MNT_NOSUID),
+            ("nosymfollow", true, MountFlags::MNT_NOSYMFOLLOW),
+            ("symfollow", false, MountFlags::MNT_NOSYMFOLLOW),
+            ("rdonly", true, MountFlags::MNT_RDONLY),
+            ("ro", true, MountFlags::MNT_RDONLY),
+            ("rw", false, MountFlags::MNT_RDONLY),
+            ("noro", false, MountFlags::MNT_RDONLY),
+            ("sync", true, MountFlags::MNT_SYNCHRONOUS),
+            ("union", true, MountFlags::MNT_UNION),
+            ("force", true, MountFlags::MNT_FORCE),
+            ("update", true, MountFlags::MNT_UPDATE),
+        ] {
+            if !opts.remove_flag(name) {
+                continue;
+            }
+
+            if set {
+                flags |= flag;
+            } else {
+                flags.remove(flag);
+            }
+        }
+
+        if flags.has(MountFlags::MNT_UPDATE) {
+            self.update(&path, opts, flags, td)
+        } else {
+            self.mount_new(&ty, path, opts, flags, td)
+        }
+    }
+
+    /// See `dounmount` on the PS4 for a reference.
+    pub fn unmount(&self, path: &str, flags: MountFlags, td: &Thread) -> Result<(), UnmountError> {
+        // TODO: Check usermount, jail and the owner of the mount.
+        td.priv_check(Privilege::VFS_UNMOUNT)
+            .map_err(UnmountError::NoPrivilege)?;
+
+        // Get the mount.
+        let vn = self.lookup(path, true, td)?;
+
+        if !vn.is_mount_root() {
+            return Err(UnmountError::NotMountPoint);
+        }
+
+        let mp = vn.mount().clone();
+
+        drop(vn);
+
+        if mp.flags().has(MountFlags::MNT_ROOTFS) || mp.parent().is_none() {
+            return Err(UnmountError::Busy);
+        }
+
+        // Check if the other filesystems was mounted on this filesystem or using it as a lower
+        // filesystem.
+        let force = flags.has(MountFlags::MNT_FORCE);
+        let mut mounts = self.mounts.write();
+
+        for m in mounts.iter() {
+            if m.parent().is_some_and(|p| Arc::ptr_eq(p.mount(), &mp)) {
+                return Err(UnmountError::Busy);
+            }
+
+            if !force
+                && m.fs::<NullFs>()
+                    .is_some_and(|n| Arc::ptr_eq(n.lower().mount(), &mp))
+            {
+                return Err(UnmountError::Busy);
+            }
+        }
+
+        // Unmount.
+        mp.unmount(force, td).map_err(UnmountError::UnmountFailed)?;
+
+        if let Some(p) = mp.parent_mut().take() {
+            *p.item_mut() = None;
+        }
+
+        mounts.retain(|m| !Arc::ptr_eq(m, &mp));
+
+        Ok(())
+    }
+
+    /// Mount devfs as the initial root filesystem. The path of the mount will be `/dev`, which is
+    /// where it will be moved to once the actual root filesystem has been mounted.
+    ///
+    /// See `vfs_mountroot_devfs` on the PS4 for a reference.
+    pub fn mount_devfs(&self, td: &Thread) -> Result<Arc<Mount>, MountError> {
+        let mut opts = MountOpts::new();
+        let mut flags = MountFlags::zeroed();
+        let fs = (DEVFS.mount)(self, None, &mut opts, &mut flags, td)
+            .map_err(MountError::MountFailed)?;
+        let mp = {
+            let mut mounts = self.mounts.write();
+            let id = self.new_id(&mounts, &DEVFS);
+            let cred = td.cred_mut().clone();
+            let from = DEVFS.name.into();
+            let mp = Mount::new(&DEVFS, fs, cred, None, flags, id, from, "/dev".into());
+            let mp = Arc::new(mp);
+
+            mounts.push(mp.clone());
+            mp
+        };
+
+        // Set as a root.
+        let r = mp
+            .root(td)
+            .map_err(MountError::GetRootFailed)
+            .and_then(|vn| {
+                let mut root = self.root.write();
+
+                if root.is_some() {
+                    return Err(MountError::Busy);
+                }
+
+                *root = Some(vn);
+
+                Ok(())
+            });
+
+        if let Err(e) = r {
+            self.mounts.write().retain(|m| !Arc::ptr_eq(m, &mp));
+            return Err(e);
+        }
+
+        Ok(mp)
+    }
+
+    /// Mount the root filesystem on top of devfs that was mounted with [`Fs::mount_devfs()`] then
+    /// move devfs to `/dev`. Unlike the PS4, `/dev` will be created if it does not exists.
+    ///
+    /// See `vfs_mountroot_shuffle` on the PS4 for a reference.
+    pub fn mount_root(
+        &self,
+        mut opts: MountOpts,
+        flags: MountFlags,
+        td: &Thread,
+    ) -> Result<Arc<Mount>, MountError> {
+        let devfs = self.root().ok_or(MountError::NoDevFs)?.mount().clone();
+
+        // Mount the filesystem on the root of devfs.
+        opts.insert("fspath", "/");
+
+        let mp = self.mount(opts, flags, td)?;
+        let root = mp.root(td).map_err(MountError::GetRootFailed)?;
+
+        // Make it a root.
+        if let Some(vn) = mp.parent_mut().take() {
+            *vn.item_mut() = None;
+        }
+
+        *mp.flags_mut() |= MountFlags::MNT_ROOTFS;
+        *self.root.write() = Some(root);
+
+        // Move devfs to /dev.
+        let vn = match self.lookup("/dev", true, td) {
+            Ok(v) => v,
+            Err(LookupError::NotFound) => self
+                .mkdir("/dev", 0o555, td)
+                .map_err(|e| MountError::MoveDevFsFailed(Box::new(e)))?,
+            Err(e) => return Err(MountError::MoveDevFsFailed(Box::new(e))),
+        };
+
+        if !vn.is_directory() {
+            return Err(MountError::MoveDevFsFailed(Box::new(
+                LookupError::NotDirectory,
+            )));
+        }
+
+        *vn.item_mut() = Some(VnodeItem::Mount(Arc::downgrade(&devfs)));
+        *devfs.parent_mut() = Some(vn);
+
+        Ok(mp)
+    }
+
+    /// Returns the attributes of `vn` to use for executing. The `S_ISUID` and `S_ISGID` will be
+    /// removed if the filesystem was mounted with `nosuid`.
+    ///
+    /// See `exec_check_permissions` on the PS4 for a reference.
+    pub fn check_exec(vn: &Arc<Vnode>, td: &Thread) -> Result<VnodeAttrs, ExecCheckError> {
+        let mp = vn.mount();
+        let mut attrs = vn.getattr(td).map_err(ExecCheckError::GetAttrFailed)?;
+
+        if mp.flags().has(MountFlags::MNT_NOEXEC) || (attrs.mode & 0o111) == 0 || !vn.is_file() {
+            return Err(ExecCheckError::NotExecutable);
+        } else if attrs.size == 0 {
+            return Err(ExecCheckError::EmptyFile);
+        }
+
+        vn.access(td, Access::EXEC)
+            .map_err(ExecCheckError::AccessFailed)?;
+
+        if mp.flags().has(MountFlags::MNT_NOSUID) {
+            attrs.mode &= !(S_ISUID | S_ISGID);
+        }
+
+        Ok(attrs)
+    }
+
+    /// See `vfs_domount_update` on the PS4 for a reference.
+    fn update(
+        &self,
+        path: &str,
+        mut opts: MountOpts,
+        flags: MountFlags,
+        td: &Thread,
+    ) -> Result<Arc<Mount>, MountError> {
+        // Get the mount.
+        let vn = self.lookup(path, true, td)?;
+
+        if !vn.is_mount_root() {
+            return Err(MountError::NotMountPoint);
+        }
+
+        let mp = vn.mount().clone();
+
+        drop(vn);
+
+        // Update.
+        let mask = MountFlags::MNT_UPDATEMASK | MountFlags::MNT_RDONLY;
+        let mut new = mp.flags();
+
+        new.remove(mask);
+        new |= flags & mask;
+
+        mp.update(&mut opts, new, td)
+            .map_err(MountError::UpdateFailed)?;
+
+        *mp.flags_mut() = new;
+
+        Ok(mp)
+    }
+
+    /// See `vfs_domount_first` on the PS4 for a reference.
+    fn mount_new(
+        &self,
+        ty: &str,
+        path: Box<str>,
+        mut opts: MountOpts,
+        mut flags: MountFlags,
+        td: &Thread,
+    ) -> Result<Arc<Mount>, MountError> {
+        // Get the filesystem.
+        let conf = self
+            .confs
+            .iter()
+            .find(|c| c.name == ty)
+            .copied()
+            .ok_or(MountError::UnknownFs)?;
+
+        // Get the vnode to mount on. The first mount will become a root filesystem.
+        let parent = if self.root().is_none() && &*path == "/" {
+            None
+        } else {
+            let vn = self.lookup(&path, true, td)?;
+
+            if !vn.is_directory() {
+                return Err(MountError::NotDirectory);
+            }
+
+            Some(vn)
+        };
+
+        // Mount.
+        flags = flags & (MountFlags::MNT_UPDATEMASK | MountFlags::MNT_RDONLY);
+
+        if parent.is_none() {
+            flags |= MountFlags::MNT_ROOTFS;
+        }
+
+        // The filesystem may need the value of "from" so we don't remove it.
+        let from = match opts.get_str("from")? {
+            Some(v) => v,
+            None => ty.into(),
+        };
+
+        let fs = (conf.mount)(self, parent.as_ref(), &mut opts, &mut flags, td)
+            .map_err(MountError::MountFailed)?;
+        let mp = {
+            let mut mounts = self.mounts.write();
+            let id = self.new_id(&mounts, conf);
+            let cred = td.cred_mut().clone();
+            let mp = Mount::new(conf, fs, cred, parent.clone(), flags, id, from, path);
+            let mp = Arc::new(mp);
+
+            mounts.push(mp.clone());
+            mp
+        };
+
+        // Attach to the vnode.
+        let r = mp
+            .root(td)
+            .map_err(MountError::GetRootFailed)
+            .and_then(|root| {
+                match &parent {
+                    Some(vn) => {
+                        let mut item = vn.item_mut();
+
+                        if item.is_some() {
+                            return Err(MountError::Busy);
+                        }
+
+                        *item = Some(VnodeItem::Mount(Arc::downgrade(&mp)));
+                    }
+                    None => {
+                        let mut cur = self.root.write();
+
+                        if cur.is_some() {
+                            return Err(MountError::Busy);
+                        }
+
+                        *cur = Some(root);
+                    }
+                }
+
+                Ok(())
+            });
+
+        if let Err(e) = r {
+            self.mounts.write().retain(|m| !Arc::ptr_eq(m, &mp));
+            return Err(e);
+        }
+
+        Ok(mp)
+    }
+
+    /// See `vfs_getnewfsid` on the PS4 for a reference.
+    fn new_id(&self, mounts: &[Arc<Mount>], conf: &FsConfig) -> [u32; 2] {
+        let mut base = self.last_id.write();
+        let ty = conf.ty;
+
+        loop {
+            let b = u32::from(*base);
+            let id = [
+                ((ty & 0xFF) << 24) | ((b & 0xFF00) << 8) | 0xFF00 | (b & 0xFF),
+                ty,
+            ];
+
+            *base = base.wrapping_add(1);
+
+            if !mounts.iter().any(|m| m.id() == id) {
+                break id;
+            }
+        }
+    }
+
+    /// Check if `vn` on `dir` can be removed or renamed.
+    fn check_modify(dir: &Arc<Vnode>, vn: &Arc<Vnode>, td: &Thread) -> Result<(), LookupError> {
+        dir.access(td, Access::WRITE)
+            .map_err(LookupError::AccessFailed)?;
+
+        // Only the owner of the directory or the file can remove the file if the directory has the
+        // sticky bit.
+        let attrs = dir.getattr(td).map_err(LookupError::GetAttrFailed)?;
+
+        if (attrs.mode & S_ISVTX) != 0
+            && dir.access(td, Access::ADMIN).is_err()
+            && vn.access(td, Access::ADMIN).is_err()
+        {
+            return Err(LookupError::NotOwner);
+        }
+
+        Ok(())
+    }
+
+    /// On success `dir` will be replaced with the directory where `name` was found, which may be
+    /// the directory covered by a union mount.
+    fn lookup_union(
+        dir: &mut Arc<Vnode>,
+        td: &Thread,
+        name: &str,
+    ) -> Result<Option<Arc<Vnode>>, LookupError> {
+        let mut cur = dir.clone();
+
+        loop {
+            let e = match cur.lookup(td, name) {
+                Ok(v) => {
+                    *dir = cur;
+                    return Ok(Some(v));
+                }
+                Err(e) => e,
+            };
+
+            if e.errno() != ENOENT {
+                return Err(LookupError::LookupFailed(e));
+            }
+
+            // Lookup on the underlying directory if this is a union mount.
+            let mp = cur.mount();
+
+            if !cur.is_mount_root() || !mp.flags().has(MountFlags::MNT_UNION) {
+                return Ok(None);
+            }
+
+            cur = match mp.parent() {
+                Some(v) => v,
+                None => return Ok(None),
+            };
+        }
+    }
+
+    fn sys_open(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let path: usize = i.args[0].into();
+        let flags: c_int = i.args[1].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let mode: u32 = i.args[2].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let path = td.proc().vm().read_str(path, MAXPATHLEN)?;
+        let flags = OpenFlags::from(flags as u32);
+        let file = current_fs().unwrap().open(&path, flags, mode as u16, td)?;
+        let fd = td
+            .proc()
+            .files()
+            .alloc(Arc::new(file), flags.has(OpenFlags::O_CLOEXEC))?;
+
+        Ok(fd.into())
+    }
+
+    /// See `sys_unlink` on the PS4 for a reference.
+    fn sys_unlink(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let path: usize = i.args[0].into();
+        let path = td.proc().vm().read_str(path, MAXPATHLEN)?;
+
+        current_fs().unwrap().unlink(&path, td)?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_chdir` and `kern_chdir` on the PS4 for a reference.
+    fn sys_chdir(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let path: usize = i.args[0].into();
+        let path = td.proc().vm().read_str(path, MAXPATHLEN)?;
+        let vn = current_fs().unwrap().lookup(&path, true, td)?;
+
+        if !vn.is_directory() {
+            return Err(SysErr::Raw(ENOTDIR));
+        }
+
+        vn.access(td, Access::EXEC).map_err(SysErr::Object)?;
+
+        td.proc().files().set_cwd(vn);
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_unmount` on the PS4 for a reference.
+    fn sys_unmount(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let path: usize = i.args[0].into();
+        let flags: c_int = i.args[1].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let path = td.proc().vm().read_str(path, MNAMELEN)?;
+        let flags = MountFlags::from(flags as u32 as u64);
+
+        current_fs().unwrap().unmount(&path, flags, td)?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_symlink` on the PS4 for a reference.
+    fn sys_symlink(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let target: usize = i.args[0].into();
+        let path: usize = i.args[1].into();
+        let target = td.proc().vm().read_str(target, MAXPATHLEN)?;
+        let path = td.proc().vm().read_str(path, MAXPATHLEN)?;
+
+        current_fs().unwrap().symlink(&target, &path, td)?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_readlink` and `kern_readlinkat` on the PS4 for a reference.
+    fn sys_readlink(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let path: usize = i.args[0].into();
+        let buf: usize = i.args[1].into();
+        let count: usize = i.args[2].into();
+
+        if count > IOSIZE_MAX {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        // Read the link.
+        let path = td.proc().vm().read_str(path, MAXPATHLEN)?;
+        let vn = current_fs().unwrap().lookup(&path, false, td)?;
+
+        if !vn.is_link() {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        let target = vn.readlink(td).map_err(SysErr::Object)?;
+        let len = target.len().min(count);
+
+        td.proc().vm().write(buf, &target.as_bytes()[..len])?;
+
+        Ok(len.into())
+    }
+
+    /// See `sys_chroot` on the PS4 for a reference.
+    fn sys_chroot(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let path: usize = i.args[0].into();
+
+        td.priv_check(Privilege::VFS_CHROOT)?;
+
+        // Get the directory.
+        let path = td.proc().vm().read_str(path, MAXPATHLEN)?;
+        let vn = current_fs().unwrap().lookup(&path, true, td)?;
+
+        if !vn.is_directory() {
+            return Err(SysErr::Raw(ENOTDIR));
+        }
+
+        vn.access(td, Access::EXEC).map_err(SysErr::Object)?;
+
+        td.proc().files().set_root(vn);
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_rename` on the PS4 for a reference.
+    fn sys_rename(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let from: usize = i.args[0].into();
+        let to: usize = i.args[1].into();
+        let from = td.proc().vm().read_str(from, MAXPATHLEN)?;
+        let to = td.proc().vm().read_str(to, MAXPATHLEN)?;
+
+        current_fs().unwrap().rename(&from, &to, td)?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_mkdir` on the PS4 for a reference.
+    fn sys_mkdir(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let path: usize = i.args[0].into();
+        let mode: u32 = i.args[1].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let path = td.proc().vm().read_str(path, MAXPATHLEN)?;
+
+        current_fs().unwrap().mkdir(&path, mode as u16, td)?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_rmdir` on the PS4 for a reference.
+    fn sys_rmdir(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let path: usize = i.args[0].into();
+        let path = td.proc().vm().read_str(path, MAXPATHLEN)?;
+
+        current_fs().unwrap().rmdir(&path, td)?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_utimes` and `kern_utimesat` on the PS4 for a reference.
+    fn sys_utimes(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let path: usize = i.args[0].into();
+        let times: usize = i.args[1].into();
+
+        // Get the times. See getutimes on the PS4 for a reference.
+        let mut attrs = VnodeSetAttrs::default();
+
+        if times == 0 {
+            let now = TimeSpec::now();
+
+            attrs.atime = Some(now);
+            attrs.mtime = Some(now);
+            attrs.utimes_null = true;
+        } else {
+            let mut buf = [0; size_of::<TimeVal>() * 2];
+
+            td.proc().vm().read(times, &mut buf)?;
+
+            let mut times = buf.chunks_exact(size_of::<TimeVal>()).map(|v| {
+                let sec = i64::from_ne_bytes(v[..8].try_into().unwrap());
+                let usec = i64::from_ne_bytes(v[8..].try_into().unwrap());
+
+                TimeVal { sec, usec }.to_timespec()
+            });
+
+            attrs.atime = Some(times.next().unwrap().ok_or(SysErr::Raw(EINVAL))?);
+            attrs.mtime = Some(times.next().unwrap().ok_or(SysErr::Raw(EINVAL))?);
+        }
+
+        // Set the times. See setutimes on the PS4 for a reference.
+        let path = td.proc().vm().read_str(path, MAXPATHLEN)?;
+        let vn = current_fs().unwrap().lookup(&path, true, td)?;
+
+        vn.setattr(td, &attrs).map_err(SysErr::Object)?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_stat` on the PS4 for a reference.
+    fn sys_stat(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        Self::stat(td, i, true)
+    }
+
+    /// See `sys_lstat` on the PS4 for a reference.
+    fn sys_lstat(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        Self::stat(td, i, false)
+    }
+
+    /// See `sys_getdirentries` on the PS4 for a reference.
+    fn sys_getdirentries(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let basep: usize = i.args[3].into();
+        let (len, base) = Self::getdirentries(td, i)?;
+
+        if basep != 0 {
+            td.proc().vm().write(basep, &(base as i64).to_ne_bytes())?;
+        }
+
+        Ok(len.into())
+    }
+
+    /// See `sys_getdents` on the PS4 for a reference.
+    fn sys_getdents(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let (len, _) = Self::getdirentries(td, i)?;
+
+        Ok(len.into())
+    }
+
+    /// See `sys_nmount` on the PS4 for a reference.
+    fn sys_nmount(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let iovp: usize = i.args[0].into();
+        let iovcnt: u32 = i.args[1].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let flags: c_int = i.args[2].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+
+        if (iovcnt & 1) != 0 || iovcnt > UIO_MAXIOV {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        // Read the vectors.
+        let vm = td.proc().vm();
+        let mut iov = vec![0; iovcnt as usize * 16];
+
+        vm.read(iovp, &mut iov)?;
+
+        // Build the options. See vfs_buildopts on the PS4 for a reference.
+        let mut opts = MountOpts::new();
+
+        for pair in iov.chunks_exact(32) {
+            let read = |v: &[u8]| -> Result<Vec<u8>, SysErr> {
+                let base = usize::from_le_bytes(v[..8].try_into().unwrap());
+                let len = usize::from_le_bytes(v[8..16].try_into().unwrap());
+
+                if len > 65536 {
+                    return Err(SysErr::Raw(EINVAL));
+                }
+
+                let mut buf = vec![0; len];
+
+                vm.read(base, &mut buf)?;
+
+                Ok(buf)
+            };
+
+            // Get name.
+            let mut name = read(&pair[..16])?;
+
+            if name.pop() != Some(0) || name.contains(&0) {
+                return Err(SysErr::Raw(EINVAL));
+            }
+
+            let name = String::from_utf8(name).map_err(|_| SysErr::Raw(EINVAL))?;
+
+            // Get value.
+            let value = read(&pair[16..])?;
+
+            opts.insert(name, value);
+        }
+
+        // Userspace is not allowed to set MNT_ROOTFS.
+        let mut flags = MountFlags::from(flags as u32 as u64);
+
+        flags.remove(MountFlags::MNT_ROOTFS);
+
+        current_fs().unwrap().mount(opts, flags, td)?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_truncate` and `kern_truncate` on the PS4 for a reference.
+    fn sys_truncate(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let path: usize = i.args[0].into();
+        let len: i64 = i.args[1].into();
+        let len: u64 = len.try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+
+        // Get the file.
+        let path = td.proc().vm().read_str(path, MAXPATHLEN)?;
+        let vn = current_fs().unwrap().lookup(&path, true, td)?;
+
+        if vn.is_directory() {
+            return Err(SysErr::Raw(EISDIR));
+        }
+
+        vn.access(td, Access::WRITE).map_err(SysErr::Object)?;
+
+        // Truncate.
+        let attrs = VnodeSetAttrs {
+            size: Some(len),
+            ..Default::default()
+        };
+
+        vn.setattr(td, &attrs).map_err(SysErr::Object)?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_ftruncate` and `kern_ftruncate` on the PS4 for a reference.
+    fn sys_ftruncate(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let fd: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let len: i64 = i.args[1].into();
+        let len: u64 = len.try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let file = td.proc().files().get(fd)?;
+
+        if !file.flags().has(FileFlags::FWRITE) {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        file.truncate(len, td).map_err(SysErr::Object)?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// Returns the number of bytes that was read and the offset before reading.
+    ///
+    /// See `kern_getdirentries` on the PS4 for a reference.
+    fn getdirentries(td: &Thread, i: &SysIn) -> Result<(usize, u64), SysErr> {
+        let fd: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let buf: usize = i.args[1].into();
+        let count: u32 = i.args[2].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+
+        if count > IOSIZE_MAX as u32 {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        // Read the entries.
+        let file = td.proc().files().get_for_read(fd)?;
+        let mut data = vec![0; count as usize];
+        let (len, base) = file.readdir(&mut data, td).map_err(SysErr::Object)?;
+
+        td.proc().vm().write(buf, &data[..len])?;
+
+        Ok((len, base))
+    }
+
+    /// See `kern_statat_vnhook` on the PS4 for a reference.
+    fn stat(td: &Thread, i: &SysIn, follow: bool) -> Result<SysOut, SysErr> {
+        let path: usize = i.args[0].into();
+        let ptr: usize = i.args[1].into();
+        let path = td.proc().vm().read_str(path, MAXPATHLEN)?;
+        let vn = current_fs().unwrap().lookup(&path, follow, td)?;
+        let st = vn.stat(td).map_err(SysErr::Object)?;
+
+        td.proc().vm().write(ptr, st.as_bytes())?;
+
+        Ok(SysOut::ZERO)
+    }
+}
+
+impl Subsystem for Fs {}
+
+/// Operation of [`Fs::namei()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameiOp {
+    /// Perform name lookup only (AKA `LOOKUP`).
+    Lookup,
+    /// Setup for file creation (AKA `CREATE`).
+    Create,
+    /// Setup for file deletion (AKA `DELETE`).
+    Delete,
+    /// Setup for the target of file renaming (AKA `RENAME`).
+    Rename,
+}
+
+/// Result of [`Fs::namei()`].
+pub struct NameiResult {
+    /// Directory of the last component (AKA `ni_dvp`).
+    pub dir: Arc<Vnode>,
+    /// Name of the last component. This will be empty if the path refer to the root directory.
+    pub name: Box<str>,
+    /// Vnode of the last component (AKA `ni_vp`). This will be [`None`] if the last component does
+    /// not exists.
+    pub vn: Option<Arc<Vnode>>,
+}
+
+/// Flags for `open`.
+#[bitflag(u32)]
+pub enum OpenFlags {
+    /// Open for writing only.
+    O_WRONLY = 0x00000001,
+    /// Open for reading and writing.
+    O_RDWR = 0x00000002,
+    /// No delay.
+    O_NONBLOCK = 0x00000004,
+    /// Set append mode.
+    O_APPEND = 0x00000008,
+    /// Signal pgrp when data ready.
+    O_ASYNC = 0x00000040,
+    /// Synchronous writes.
+    O_FSYNC = 0x00000080,
+    /// Don't follow symlinks.
+    O_NOFOLLOW = 0x00000100,
+    /// Create if nonexistent.
+    O_CREAT = 0x00000200,
+    /// Truncate to zero length.
+    O_TRUNC = 0x00000400,
+    /// Error if already exists.
+    O_EXCL = 0x00000800,
+    /// Attempt to bypass buffer cache.
+    O_DIRECT = 0x00010000,
+    /// Fail if not directory.
+    O_DIRECTORY = 0x00020000,
+    /// Open for execute only.
+    O_EXEC = 0x00040000,
+    /// Set FD_CLOEXEC upon open.
+    O_CLOEXEC = 0x00100000,
+}
+
+impl OpenFlags {
+    /// Mask for access mode.
+    pub const O_ACCMODE: Self = Self(Self::O_WRONLY.0 | Self::O_RDWR.0);
+
+    /// See `FFLAGS` on the PS4 for a reference.
+    pub fn into_fflags(self) -> FileFlags {
+        let v = if self.has(Self::O_EXEC) {
+            self.0
+        } else {
+            self.0 + 1
+        };
+
+        FileFlags::from(v) & (FileFlags::FREAD | FileFlags::FWRITE | FileFlags::FCNTLFLAGS)
+    }
+}
+
+/// Maximum length of a path, including NUL.
+const MAXPATHLEN: usize = 1024;
+
+/// Maximum length of a component.
+const NAME_MAX: usize = 255;
+
+/// Maximum number of symbolic links in a path.
+const MAXSYMLINKS: usize = 32;
+
+/// Length of a filesystem type name, including NUL.
+const MFSNAMELEN: usize = 16;
+
+/// Length of a path for a mount point, including NUL.
+const MNAMELEN: usize = 88;
+
+/// Maximum number of `iovec` in a single call.
+const UIO_MAXIOV: u32 = 1024;
+
+/// Maximum size of each read or write.
+const IOSIZE_MAX: usize = c_int::MAX as usize;
+
+/// Represents an error when [`Fs::namei()`] fails.
+#[derive(Debug)]
+pub enum LookupError {
+    EmptyPath,
+    NameTooLong,
+    NoRoot,
+    NotDirectory,
+    IsDirectory,
+    NotFound,
+    TooManyLinks,
+    SymlinkNotAllowed,
+    InvalidName,
+    NotOwner,
+    AccessFailed(Box<dyn Errno>),
+    GetAttrFailed(Box<dyn Errno>),
+    LookupFailed(Box<dyn Errno>),
+    ReadLinkFailed(Box<dyn Errno>),
+    GetRootFailed(Box<dyn Errno>),
+}
+
+impl Error for LookupError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::AccessFailed(e)
+            | Self::GetAttrFailed(e)
+            | Self::LookupFailed(e)
+            | Self::ReadLinkFailed(e)
+            | Self::GetRootFailed(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for LookupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::EmptyPath => f.write_str("the path is empty"),
+            Self::NameTooLong => f.write_str("the path is too long"),
+            Self::NoRoot => f.write_str("the root filesystem has not been mounted"),
+            Self::NotDirectory => f.write_str("a component of the path is not a directory"),
+            Self::IsDirectory => f.write_str("the path is a root directory"),
+            Self::NotFound => f.write_str("no such file or directory"),
+            Self::TooManyLinks => f.write_str("too many levels of symbolic links"),
+            Self::SymlinkNotAllowed => f.write_str("the filesystem does not allow symbolic link"),
+            Self::InvalidName => f.write_str("the last component cannot be '.' or '..'"),
+            Self::NotOwner => f.write_str("the directory has sticky bit and not owned by the user"),
+            Self::AccessFailed(_) => f.write_str("couldn't search the directory"),
+            Self::GetAttrFailed(_) => f.write_str("couldn't get attributes of the directory"),
+            Self::LookupFailed(_) => f.write_str("couldn't lookup the component"),
+            Self::ReadLinkFailed(_) => f.write_str("couldn't read the symbolic link"),
+            Self::GetRootFailed(_) => f.write_str("couldn't get the root of the mount point"),
+        }
+    }
+}
+
+impl Errno for LookupError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::EmptyPath | Self::NoRoot | Self::NotFound => ENOENT,
+            Self::NameTooLong => ENAMETOOLONG,
+            Self::NotDirectory => ENOTDIR,
+            Self::IsDirectory => EISDIR,
+            Self::TooManyLinks => ELOOP,
+            Self::SymlinkNotAllowed => EACCES,
+            Self::InvalidName => EINVAL,
+            Self::NotOwner => EPERM,
+            Self::AccessFailed(e)
+            | Self::GetAttrFailed(e)
+            | Self::LookupFailed(e)
+            | Self::ReadLinkFailed(e)
+            | Self::GetRootFailed(e) => e.errno(),
+        }
+    }
+}
+
+/// Represents an error when [`Fs::open()`] fails.
+#[derive(Debug)]
+pub enum OpenError {
+    InvalidFlags,
+    LookupFailed(LookupError),
+    Exists,
+    IsDirectory,
+    NotDirectory,
+    SymbolicLink,
+    CreateFailed(Box<dyn Errno>),
+    AccessFailed(Box<dyn Errno>),
+    OpenFailed(Box<dyn Errno>),
+    TruncateFailed(Box<dyn Errno>),
+}
+
+impl Error for OpenError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::LookupFailed(e) => Some(e),
+            Self::CreateFailed(e)
+            | Self::AccessFailed(e)
+            | Self::OpenFailed(e)
+            | Self::TruncateFailed(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for OpenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidFlags => f.write_str("invalid flags"),
+            Self::LookupFailed(_) => f.write_str("couldn't lookup the file"),
+            Self::Exists => f.write_str("the file already exists"),
+            Self::IsDirectory => f.write_str("the file is a directory"),
+            Self::NotDirectory => f.write_str("the file is not a directory"),
+            Self::SymbolicLink => f.write_str("the file is a symbolic link"),
+            Self::CreateFailed(_) => f.write_str("couldn't create the file"),
+            Self::AccessFailed(_) => f.write_str("access denied"),
+            Self::OpenFailed(_) => f.write_str("couldn't open the file"),
+            Self::TruncateFailed(_) => f.write_str("couldn't truncate the file"),
+        }
+    }
+}
+
+impl Errno for OpenError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::InvalidFlags => EINVAL,
+            Self::LookupFailed(e) => e.errno(),
+            Self::Exists => EEXIST,
+            Self::IsDirectory => EISDIR,
+            Self::NotDirectory => ENOTDIR,
+            Self::SymbolicLink => ELOOP,
+            Self::CreateFailed(e)
+            | Self::AccessFailed(e)
+            | Self::OpenFailed(e)
+            | Self::TruncateFailed(e) => e.errno(),
+        }
+    }
+}
+
+impl From<LookupError> for OpenError {
+    fn from(value: LookupError) -> Self {
+        Self::LookupFailed(value)
+    }
+}
+
+/// Represents an error when [`Fs::mkdir()`] fails.
+#[derive(Debug)]
+pub enum MkdirError {
+    LookupFailed(LookupError),
+    Exists,
+    CreateFailed(Box<dyn Errno>),
+}
+
+impl Error for MkdirError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::LookupFailed(e) => Some(e),
+            Self::CreateFailed(e) => Some(e.as_ref()),
+            Self::Exists => None,
+        }
+    }
+}
+
+impl Display for MkdirError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::LookupFailed(_) => f.write_str("couldn't lookup the path"),
+            Self::Exists => f.write_str("the path already exists"),
+            Self::CreateFailed(_) => f.write_str("couldn't create the directory"),
+        }
+    }
+}
+
+impl Errno for MkdirError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::LookupFailed(e) => e.errno(),
+            Self::Exists => EEXIST,
+            Self::CreateFailed(e) => e.errno(),
+        }
+    }
+}
+
+impl From<LookupError> for MkdirError {
+    fn from(value: LookupError) -> Self {
+        Self::LookupFailed(value)
+    }
+}
+
+/// Represents an error when [`Fs::unlink()`] or [`Fs::rmdir()`] fails.
+#[derive(Debug)]
+pub enum RemoveError {
+    LookupFailed(LookupError),
+    IsDirectory,
+    NotDirectory,
+    Busy,
+    RemoveFailed(Box<dyn Errno>),
+}
+
+impl Error for RemoveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::LookupFailed(e) => Some(e),
+            Self::RemoveFailed(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for RemoveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::LookupFailed(_) => f.write_str("couldn't lookup the path"),
+            Self::IsDirectory => f.write_str("the path is a directory"),
+            Self::NotDirectory => f.write_str("the path is not a directory"),
+            Self::Busy => f.write_str("the path is a mount point"),
+            Self::RemoveFailed(_) => f.write_str("couldn't remove the path"),
+        }
+    }
+}
+
+impl Errno for RemoveError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::LookupFailed(e) => e.errno(),
+            Self::IsDirectory => EPERM,
+            Self::NotDirectory => ENOTDIR,
+            Self::Busy => EBUSY,
+            Self::RemoveFailed(e) => e.errno(),
+        }
+    }
+}
+
+impl From<LookupError> for RemoveError {
+    fn from(value: LookupError) -> Self {
+        Self::LookupFailed(value)
+    }
+}
+
+/// Represents an error when [`Fs::rename()`] fails.
+#[derive(Debug)]
+pub enum RenameError {
+    LookupFailed(LookupError),
+    NotDirectory,
+    IsDirectory,
+    CrossDevice,
+    InvalidTarget,
+    RenameFailed(Box<dyn Errno>),
+}
+
+impl Error for RenameError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::LookupFailed(e) => Some(e),
+            Self::RenameFailed(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for RenameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::LookupFailed(_) => f.write_str("couldn't lookup the path"),
+            Self::NotDirectory => f.write_str("the target is not a directory"),
+            Self::IsDirectory => f.write_str("the target is a directory"),
+            Self::CrossDevice => f.write_str("the source and the target are on different mounts"),
+            Self::InvalidTarget => f.write_str("the target is the source itself"),
+            Self::RenameFailed(_) => f.write_str("couldn't rename the path"),
+        }
+    }
+}
+
+impl Errno for RenameError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::LookupFailed(e) => e.errno(),
+            Self::NotDirectory => ENOTDIR,
+            Self::IsDirectory => EISDIR,
+            Self::CrossDevice => EXDEV,
+            Self::InvalidTarget => EINVAL,
+            Self::RenameFailed(e) => e.errno(),
+        }
+    }
+}
+
+impl From<LookupError> for RenameError {
+    fn from(value: LookupError) -> Self {
+        Self::LookupFailed(value)
+    }
+}
+
+/// Represents an error when [`Fs::symlink()`] fails.
+#[derive(Debug)]
+pub enum SymlinkError {
+    LookupFailed(LookupError),
+    Exists,
+    CreateFailed(Box<dyn Errno>),
+}
+
+impl Error for SymlinkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::LookupFailed(e) => Some(e),
+            Self::CreateFailed(e) => Some(e.as_ref()),
+            Self::Exists => None,
+        }
+    }
+}
+
+impl Display for SymlinkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::LookupFailed(_) => f.write_str("couldn't lookup the path"),
+            Self::Exists => f.write_str("the path already exists"),
+            Self::CreateFailed(_) => f.write_str("couldn't create the symbolic link"),
+        }
+    }
+}
+
+impl Errno for SymlinkError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::LookupFailed(e) => e.errno(),
+            Self::Exists => EEXIST,
+            Self::CreateFailed(e) => e.errno(),
+        }
+    }
+}
+
+impl From<LookupError> for SymlinkError {
+    fn from(value: LookupError) -> Self {
+        Self::LookupFailed(value)
+    }
+}
+
+/// Represents an error when [`Fs::mount()`] fails.
+#[derive(Debug)]
+pub enum MountError {
+    NoPrivilege(PrivilegeError),
+    InvalidOpt(MountOptError),
+    NoFsType,
+    NoPath,
+    FsTypeTooLong,
+    PathTooLong,
+    UnknownFs,
+    LookupFailed(LookupError),
+    NotDirectory,
+    NotMountPoint,
+    Busy,
+    NoDevFs,
+    MountFailed(Box<dyn Errno>),
+    UpdateFailed(Box<dyn Errno>),
+    GetRootFailed(Box<dyn Errno>),
+    MoveDevFsFailed(Box<dyn Errno>),
+}
+
+impl Error for MountError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::NoPrivilege(e) => Some(e),
+            Self::InvalidOpt(e) => Some(e),
+            Self::LookupFailed(e) => Some(e),
+            Self::MountFailed(e)
+            | Self::UpdateFailed(e)
+            | Self::GetRootFailed(e)
+            | Self::MoveDevFsFailed(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for MountError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoPrivilege(_) => f.write_str("no privilege to mount"),
+            Self::InvalidOpt(_) => f.write_str("invalid mount option"),
+            Self::NoFsType => f.write_str("no filesystem type is specified"),
+            Self::NoPath => f.write_str("no mount point is specified"),
+            Self::FsTypeTooLong => f.write_str("filesystem type is too long"),
+            Self::PathTooLong => f.write_str("path of the mount point is too long"),
+            Self::UnknownFs => f.write_str("unknown filesystem type"),
+            Self::LookupFailed(_) => f.write_str("couldn't lookup the mount point"),
+            Self::NotDirectory => f.write_str("the mount point is not a directory"),
+            Self::NotMountPoint => f.write_str("the path is not a mount point"),
+            Self::Busy => f.write_str("the mount point is busy"),
+            Self::NoDevFs => f.write_str("devfs has not been mounted"),
+            Self::MountFailed(_) => f.write_str("couldn't mount the filesystem"),
+            Self::UpdateFailed(_) => f.write_str("couldn't update the filesystem"),
+            Self::GetRootFailed(_) => f.write_str("couldn't get the root of the filesystem"),
+            Self::MoveDevFsFailed(_) => f.write_str("couldn't move devfs to /dev"),
+        }
+    }
+}
+
+impl Errno for MountError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NoPrivilege(e) => e.errno(),
+            Self::InvalidOpt(e) => e.errno(),
+            Self::NoFsType | Self::NoPath | Self::NotMountPoint => EINVAL,
+            Self::FsTypeTooLong | Self::PathTooLong => ENAMETOOLONG,
+            Self::UnknownFs => ENODEV,
+            Self::LookupFailed(e) => e.errno(),
+            Self::NotDirectory => ENOTDIR,
+            Self::Busy => EBUSY,
+            Self::NoDevFs => ENOENT,
+            Self::MountFailed(e)
+            | Self::UpdateFailed(e)
+            | Self::GetRootFailed(e)
+            | Self::MoveDevFsFailed(e) => e.errno(),
+        }
+    }
+}
+
+impl From<MountOptError> for MountError {
+    fn from(value: MountOptError) -> Self {
+        Self::InvalidOpt(value)
+    }
+}
+
+impl From<LookupError> for MountError {
+    fn from(value: LookupError) -> Self {
+        Self::LookupFailed(value)
+    }
+}
+
+/// Represents an error when [`Fs::unmount()`] fails.
+#[derive(Debug)]
+pub enum UnmountError {
+    NoPrivilege(PrivilegeError),
+    LookupFailed(LookupError),
+    NotMountPoint,
+    Busy,
+    UnmountFailed(Box<dyn Errno>),
+}
+
+impl Error for UnmountError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::NoPrivilege(e) => Some(e),
+            Self::LookupFailed(e) => Some(e),
+            Self::UnmountFailed(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for UnmountError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoPrivilege(_) => f.write_str("no privilege to unmount"),
+            Self::LookupFailed(_) => f.write_str("couldn't lookup the mount point"),
+            Self::NotMountPoint => f.write_str("the path is not a mount point"),
+            Self::Busy => f.write_str("the filesystem is busy"),
+            Self::UnmountFailed(_) => f.write_str("couldn't unmount the filesystem"),
+        }
+    }
+}
+
+impl Errno for UnmountError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NoPrivilege(e) => e.errno(),
+            Self::LookupFailed(e) => e.errno(),
+            Self::NotMountPoint => EINVAL,
+            Self::Busy => EBUSY,
+            Self::UnmountFailed(e) => e.errno(),
+        }
+    }
+}
+
+impl From<LookupError> for UnmountError {
+    fn from(value: LookupError) -> Self {
+        Self::LookupFailed(value)
+    }
+}
+
+/// Represents an error when [`Fs::check_exec()`] fails.
+#[derive(Debug)]
+pub enum ExecCheckError {
+    GetAttrFailed(Box<dyn Errno>),
+    NotExecutable,
+    EmptyFile,
+    AccessFailed(Box<dyn Errno>),
+}
+
+impl Error for ExecCheckError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::GetAttrFailed(e) | Self::AccessFailed(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ExecCheckError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::GetAttrFailed(_) => f.write_str("couldn't get file attributes"),
+            Self::NotExecutable => f.write_str("the file is not executable"),
+            Self::EmptyFile => f.write_str("the file is empty"),
+            Self::AccessFailed(_) => f.write_str("access denied"),
+        }
+    }
+}
+
+impl Errno for ExecCheckError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::GetAttrFailed(e) | Self::AccessFailed(e) => e.errno(),
+            Self::NotExecutable => EACCES,
+            Self::EmptyFile => ENOEXEC,
+        }
+    }
+}
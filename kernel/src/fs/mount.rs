@@ -0,0 +1,431 @@
+use super::{Fs, Vnode};
+use crate::errno::{Errno, EINVAL, EOPNOTSUPP};
+use crate::lock::{Gutex, GutexGroup, GutexWrite};
+use crate::proc::Thread;
+use crate::ucred::{Ucred, Uid};
+use alloc::boxed::Box;
+use alloc::collections::btree_map::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+use hashbrown::HashMap;
+use macros::bitflag;
+
+/// Implementation of `mount` structure.
+pub struct Mount {
+    config: &'static FsConfig,                // mnt_vfc
+    fs: Box<dyn Filesystem>,                  // mnt_op + mnt_data
+    cred: Arc<Ucred>,                         // mnt_cred
+    parent: Gutex<Option<Arc<Vnode>>>,        // mnt_vnodecovered
+    flags: Gutex<MountFlags>,                 // mnt_flag
+    vnodes: Gutex<HashMap<u64, Weak<Vnode>>>, // vfs_hash_tbl
+    id: [u32; 2],                             // mnt_stat.f_fsid
+    from: Box<str>,                           // mnt_stat.f_mntfromname
+    path: Box<str>,                           // mnt_stat.f_mntonname
+}
+
+impl Mount {
+    /// See `vfs_mount_alloc` on the PS4 for a reference.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn new(
+        config: &'static FsConfig,
+        fs: Box<dyn Filesystem>,
+        cred: Arc<Ucred>,
+        parent: Option<Arc<Vnode>>,
+        flags: MountFlags,
+        id: [u32; 2],
+        from: Box<str>,
+        path: Box<str>,
+    ) -> Self {
+        let gg = GutexGroup::new();
+
+        Self {
+            config,
+            fs,
+            cred,
+            parent: gg.clone().spawn(parent),
+            flags: gg.clone().spawn(flags),
+            vnodes: gg.spawn(HashMap::new()),
+            id,
+            from,
+            path,
+        }
+    }
+
+    pub fn config(&self) -> &'static FsConfig {
+        self.config
+    }
+
+    /// Returns [`None`] if the backend is not `T`.
+    pub fn fs<T: Filesystem>(&self) -> Option<&T> {
+        let fs: &dyn core::any::Any = self.fs.as_ref();
+
+        fs.downcast_ref()
+    }
+
+    pub fn owner(&self) -> Uid {
+        self.cred.effective_uid()
+    }
+
+    /// Returns the vnode that this filesystem was mounted on. Returns [`None`] if this is the root
+    /// filesystem.
+    pub fn parent(&self) -> Option<Arc<Vnode>> {
+        self.parent.write().clone()
+    }
+
+    pub fn parent_mut(&self) -> GutexWrite<'_, Option<Arc<Vnode>>> {
+        self.parent.write()
+    }
+
+    pub fn flags(&self) -> MountFlags {
+        *self.flags.write()
+    }
+
+    pub fn flags_mut(&self) -> GutexWrite<'_, MountFlags> {
+        self.flags.write()
+    }
+
+    pub fn id(&self) -> [u32; 2] {
+        self.id
+    }
+
+    pub fn from(&self) -> &str {
+        &self.from
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// See `VFS_ROOT` on the PS4 for a reference.
+    pub fn root(self: &Arc<Self>, td: &Thread) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        self.fs.root(self, td)
+    }
+
+    /// Returns the active vnode for `hash` or create a new one with `f` if there are no active
+    /// vnode for `hash`. `f` must not call into this method for the same mount.
+    ///
+    /// See `vfs_hash_get` and `vfs_hash_insert` on the PS4 for a reference.
+    pub fn hash_get_or_insert<E>(
+        &self,
+        hash: u64,
+        f: impl FnOnce() -> Result<Arc<Vnode>, E>,
+    ) -> Result<Arc<Vnode>, E> {
+        let mut vnodes = self.vnodes.write();
+
+        if let Some(vn) = vnodes.get(&hash).and_then(|v| v.upgrade()) {
+            return Ok(vn);
+        }
+
+        // Remove inactive vnodes before the table grow.
+        if vnodes.len() == vnodes.capacity() {
+            vnodes.retain(|_, v| v.strong_count() != 0);
+        }
+
+        let vn = f()?;
+
+        vnodes.insert(hash, Arc::downgrade(&vn));
+
+        Ok(vn)
+    }
+
+    /// Remove `hash` from the vnode cache (e.g. the file has been deleted).
+    ///
+    /// See `vfs_hash_remove` on the PS4 for a reference.
+    #[allow(dead_code)] // TODO: Remove this once we have a filesystem that delete files.
+    pub fn hash_remove(&self, hash: u64) {
+        self.vnodes.write().remove(&hash);
+    }
+
+    /// See `VFS_MOUNT` with `MNT_UPDATE` on the PS4 for a reference.
+    pub(super) fn update(
+        self: &Arc<Self>,
+        opts: &mut MountOpts,
+        flags: MountFlags,
+        td: &Thread,
+    ) -> Result<(), Box<dyn Errno>> {
+        self.fs.update(self, opts, flags, td)
+    }
+
+    /// See `VFS_UNMOUNT` on the PS4 for a reference.
+    pub(super) fn unmount(
+        self: &Arc<Self>,
+        force: bool,
+        td: &Thread,
+    ) -> Result<(), Box<dyn Errno>> {
+        self.fs.unmount(self, force, td)
+    }
+}
+
+/// Implementation of `vfsops` structure.
+///
+/// The `vfs_mount` without `MNT_UPDATE` is moved to [`FsConfig::mount`] instead.
+pub trait Filesystem: core::any::Any + Send + Sync {
+    /// Implementation of `vfs_root`.
+    fn root(&self, mnt: &Arc<Mount>, td: &Thread) -> Result<Arc<Vnode>, Box<dyn Errno>>;
+
+    /// `flags` is the new flags for the mount, which will be applied after this method return
+    /// successfully.
+    ///
+    /// Implementation of `vfs_mount` with `MNT_UPDATE`.
+    fn update(
+        &self,
+        _: &Arc<Mount>,
+        _: &mut MountOpts,
+        _: MountFlags,
+        _: &Thread,
+    ) -> Result<(), Box<dyn Errno>> {
+        Err(Box::new(DefaultFilesystemError::UpdateNotSupported))
+    }
+
+    /// Implementation of `vfs_unmount`.
+    fn unmount(&self, _: &Arc<Mount>, _: bool, _: &Thread) -> Result<(), Box<dyn Errno>> {
+        Ok(())
+    }
+}
+
+/// Implementation of `vfsconf` structure.
+pub struct FsConfig {
+    pub name: &'static str, // vfc_name
+    pub ty: u32,            // vfc_typenum
+    pub mount: FsMount,     // vfs_mount
+}
+
+/// Function to mount a new filesystem. Implementation can change the flags of the mount by
+/// modifying `flags`. `parent` is the vnode to mount on, which will be [`None`] for the initial
+/// devfs and the first root filesystem.
+pub type FsMount = fn(
+    fs: &Fs,
+    parent: Option<&Arc<Vnode>>,
+    opts: &mut MountOpts,
+    flags: &mut MountFlags,
+    td: &Thread,
+) -> Result<Box<dyn Filesystem>, Box<dyn Errno>>;
+
+/// Flags of [`Mount`].
+#[bitflag(u64)]
+pub enum MountFlags {
+    /// Read only filesystem.
+    MNT_RDONLY = 0x0000000000000001,
+    /// Filesystem written synchronously.
+    MNT_SYNCHRONOUS = 0x0000000000000002,
+    /// Can't exec from filesystem.
+    MNT_NOEXEC = 0x0000000000000004,
+    /// Don't honor setuid fs bits.
+    MNT_NOSUID = 0x0000000000000008,
+    /// Union with underlying fs.
+    MNT_UNION = 0x0000000000000020,
+    /// Filesystem written asynchronously.
+    MNT_ASYNC = 0x0000000000000040,
+    /// Do not follow symlinks.
+    MNT_NOSYMFOLLOW = 0x0000000000400000,
+    /// Disable update of file access time.
+    MNT_NOATIME = 0x0000000010000000,
+    /// Filesystem is stored locally.
+    MNT_LOCAL = 0x0000000000001000,
+    /// Identifies the root filesystem.
+    MNT_ROOTFS = 0x0000000000004000,
+    /// Mounted by a user.
+    MNT_USER = 0x0000000000008000,
+    /// Not a real mount, just an update.
+    MNT_UPDATE = 0x0000000000010000,
+    /// Force unmount or readonly.
+    MNT_FORCE = 0x0000000000080000,
+}
+
+impl MountFlags {
+    /// Flags that can be changed by `MNT_UPDATE` in addition to [`MountFlags::MNT_RDONLY`].
+    pub const MNT_UPDATEMASK: Self = Self(
+        Self::MNT_SYNCHRONOUS.0
+            | Self::MNT_NOEXEC.0
+            | Self::MNT_NOSUID.0
+            | Self::MNT_UNION.0
+            | Self::MNT_ASYNC.0
+            | Self::MNT_NOSYMFOLLOW.0
+            | Self::MNT_NOATIME.0
+            | Self::MNT_USER.0,
+    );
+
+    pub fn into_bits(self) -> u64 {
+        self.0
+    }
+}
+
+/// Implementation of `vfsoptlist`.
+///
+/// The value of each option is a raw bytes. The string value may or may not contains a NUL at the
+/// end.
+#[derive(Default)]
+pub struct MountOpts(BTreeMap<Box<str>, Box<[u8]>>);
+
+impl MountOpts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Replace the previous value if `name` already exists.
+    pub fn insert(&mut self, name: impl Into<Box<str>>, value: impl AsRef<[u8]>) {
+        self.0.insert(name.into(), value.as_ref().into());
+    }
+
+    /// Returns `true` if `name` was present.
+    pub fn remove_flag(&mut self, name: &str) -> bool {
+        self.0.remove(name).is_some()
+    }
+
+    /// Unlike [`MountOpts::remove_str()`], this does not remove the option.
+    ///
+    /// See `vfs_getopts` on the PS4 for a reference.
+    pub fn get_str(&self, name: &str) -> Result<Option<Box<str>>, MountOptError> {
+        self.0
+            .get(name)
+            .map(|v| Self::parse_str(name, v))
+            .transpose()
+    }
+
+    /// See `vfs_getopts` on the PS4 for a reference.
+    pub fn remove_str(&mut self, name: &str) -> Result<Option<Box<str>>, MountOptError> {
+        self.0
+            .remove(name)
+            .map(|v| Self::parse_str(name, &v))
+            .transpose()
+    }
+
+    /// See `vfs_scanopt` on the PS4 for a reference.
+    pub fn remove_int(&mut self, name: &str, radix: u32) -> Result<Option<i64>, MountOptError> {
+        let v = match self.remove_str(name)? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        i64::from_str_radix(&v, radix)
+            .map(Some)
+            .map_err(|_| MountOptError::InvalidValue(name.into()))
+    }
+
+    /// Parse the value as a size with an optional suffix (e.g. `16m`).
+    ///
+    /// See `vfs_getopt_size` on the PS4 for a reference.
+    pub fn remove_size(&mut self, name: &str) -> Result<Option<u64>, MountOptError> {
+        let v = match self.remove_str(name)? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        // Parse the suffix.
+        let e = || MountOptError::InvalidValue(name.into());
+        let (num, shift) = match v.as_bytes().last().ok_or_else(e)? {
+            b'e' | b'E' => (&v[..(v.len() - 1)], 60),
+            b'p' | b'P' => (&v[..(v.len() - 1)], 50),
+            b't' | b'T' => (&v[..(v.len() - 1)], 40),
+            b'g' | b'G' => (&v[..(v.len() - 1)], 30),
+            b'm' | b'M' => (&v[..(v.len() - 1)], 20),
+            b'k' | b'K' => (&v[..(v.len() - 1)], 10),
+            _ => (&*v, 0),
+        };
+
+        num.parse::<u64>()
+            .ok()
+            .and_then(|v| v.checked_shl(shift).filter(|r| (r >> shift) == v))
+            .map(Some)
+            .ok_or_else(e)
+    }
+
+    fn parse_str(name: &str, v: &[u8]) -> Result<Box<str>, MountOptError> {
+        // Strip NUL.
+        let v = match v.split_last() {
+            Some((0, v)) => v,
+            _ => v,
+        };
+
+        if v.contains(&0) {
+            return Err(MountOptError::InvalidString(name.into()));
+        }
+
+        core::str::from_utf8(v)
+            .map(|v| v.into())
+            .map_err(|_| MountOptError::InvalidString(name.into()))
+    }
+}
+
+/// Represents an error when the mount option is not valid.
+#[derive(Debug)]
+pub enum MountOptError {
+    InvalidString(Box<str>),
+    InvalidValue(Box<str>),
+}
+
+impl Error for MountOptError {}
+
+impl Display for MountOptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidString(n) => {
+                write!(f, "value of mount option '{n}' is not a valid string")
+            }
+            Self::InvalidValue(n) => write!(f, "value of mount option '{n}' is not valid"),
+        }
+    }
+}
+
+impl Errno for MountOptError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::InvalidString(_) | Self::InvalidValue(_) => EINVAL,
+        }
+    }
+}
+
+/// Represents an error when the default implementation of [`Filesystem`] fails.
+#[derive(Debug)]
+enum DefaultFilesystemError {
+    UpdateNotSupported,
+}
+
+impl Error for DefaultFilesystemError {}
+
+impl Display for DefaultFilesystemError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UpdateNotSupported => f.write_str("the filesystem does not support update"),
+        }
+    }
+}
+
+impl Errno for DefaultFilesystemError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::UpdateNotSupported => EOPNOTSUPP,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_opt() {
+        let mut opts = MountOpts::new();
+
+        opts.insert("a", b"4096\0");
+        opts.insert("b", "16m");
+        opts.insert("c", "1K");
+        opts.insert("d", "k");
+        opts.insert("e", "16777216t");
+
+        assert_eq!(opts.remove_size("a").unwrap(), Some(4096));
+        assert_eq!(opts.remove_size("b").unwrap(), Some(16 * 1024 * 1024));
+        assert_eq!(opts.remove_size("c").unwrap(), Some(1024));
+        assert!(opts.remove_size("d").is_err());
+        assert!(opts.remove_size("e").is_err());
+        assert_eq!(opts.remove_size("a").unwrap(), None);
+        assert!(opts.is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+use self::vnode::NullVnode;
+use super::{
+    Filesystem, Fs, FsConfig, LookupError, Mount, MountFlags, MountOptError, MountOpts, Vnode,
+    VnodeItem, VnodeType,
+};
+use crate::errno::{Errno, EDEADLK, EINVAL, EOPNOTSUPP};
+use crate::proc::Thread;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::convert::Infallible;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+
+mod vnode;
+
+/// Configuration of nullfs.
+pub static NULLFS: FsConfig = FsConfig {
+    name: "nullfs",
+    ty: 0x29,
+    mount: NullFs::mount,
+};
+
+/// Implementation of `null_mount` structure.
+pub struct NullFs {
+    lower: Arc<Vnode>, // nullm_rootvp
+}
+
+impl NullFs {
+    /// See `nullfs_mount` on the PS4 for a reference.
+    fn mount(
+        fs: &Fs,
+        parent: Option<&Arc<Vnode>>,
+        opts: &mut MountOpts,
+        flags: &mut MountFlags,
+        td: &Thread,
+    ) -> Result<Box<dyn Filesystem>, Box<dyn Errno>> {
+        let parent = match parent {
+            Some(v) => v,
+            None => return Err(Box::new(MountError::RootFs)),
+        };
+
+        // Get the lower vnode.
+        let target = match opts.remove_str("target") {
+            Ok(Some(v)) => v,
+            Ok(None) => return Err(Box::new(MountError::NoTarget)),
+            Err(e) => return Err(Box::new(MountError::InvalidTarget(e))),
+        };
+
+        let lower = fs
+            .lookup(&target, true, td)
+            .map_err(|e| Box::new(MountError::LookupFailed(e)) as Box<dyn Errno>)?;
+
+        if !lower.is_directory() {
+            return Err(Box::new(MountError::NotDirectory));
+        }
+
+        // Mounting on top of the nullfs vnode for the same lower vnode will cause a lookup on the
+        // mount point to loop forever.
+        if parent
+            .backend::<NullVnode>()
+            .is_some_and(|v| Arc::ptr_eq(v.lower(), &lower))
+        {
+            return Err(Box::new(MountError::Recursive));
+        }
+
+        if lower.mount().flags().has(MountFlags::MNT_LOCAL) {
+            *flags |= MountFlags::MNT_LOCAL;
+        }
+
+        Ok(Box::new(Self { lower }))
+    }
+
+    /// Returns the vnode on the lower filesystem that this filesystem was mounted from.
+    pub fn lower(&self) -> &Arc<Vnode> {
+        &self.lower
+    }
+
+    /// Returns the nullfs vnode for `lower`. All vnodes on `mnt` with the same `lower` will share
+    /// the same nullfs vnode.
+    ///
+    /// See `null_nodeget` on the PS4 for a reference.
+    fn alloc_vnode(&self, mnt: &Arc<Mount>, lower: &Arc<Vnode>) -> Arc<Vnode> {
+        // The lower vnode will outlive the entry so its address is unique for the entry.
+        let hash = Arc::as_ptr(lower) as usize as u64;
+        let vn = mnt.hash_get_or_insert(hash, || {
+            // The root of the lower vnode can be any directory so we can't use its type as-is.
+            let ty = match lower.ty() {
+                VnodeType::Directory(_) => VnodeType::Directory(Arc::ptr_eq(lower, &self.lower)),
+                v => *v,
+            };
+
+            let vn = Vnode::new(mnt, ty, "null", NullVnode::new(lower.clone()));
+
+            if let Some(dev) = lower.device() {
+                *vn.item_mut() = Some(VnodeItem::Device(dev));
+            }
+
+            Ok::<_, Infallible>(vn)
+        });
+
+        vn.unwrap_or_else(|e| match e {})
+    }
+}
+
+impl Filesystem for NullFs {
+    /// See `nullfs_root` on the PS4 for a reference.
+    fn root(&self, mnt: &Arc<Mount>, _: &Thread) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        Ok(self.alloc_vnode(mnt, &self.lower))
+    }
+
+    /// See `nullfs_mount` with `MNT_UPDATE` on the PS4 for a reference.
+    fn update(
+        &self,
+        _: &Arc<Mount>,
+        opts: &mut MountOpts,
+        _: MountFlags,
+        _: &Thread,
+    ) -> Result<(), Box<dyn Errno>> {
+        // The only things that can be changed are the flags, which the caller will apply.
+        opts.remove_flag("export");
+
+        Ok(())
+    }
+}
+
+/// Represents an error when [`NullFs::mount()`] fails.
+#[derive(Debug)]
+enum MountError {
+    RootFs,
+    NoTarget,
+    InvalidTarget(MountOptError),
+    LookupFailed(LookupError),
+    NotDirectory,
+    Recursive,
+}
+
+impl Error for MountError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidTarget(e) => Some(e),
+            Self::LookupFailed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Display for MountError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::RootFs => f.write_str("nullfs cannot be mounted as a root filesystem"),
+            Self::NoTarget => f.write_str("no target option"),
+            Self::InvalidTarget(_) => f.write_str("invalid target option"),
+            Self::LookupFailed(_) => f.write_str("couldn't lookup the target"),
+            Self::NotDirectory => f.write_str("the target is not a directory"),
+            Self::Recursive => f.write_str("the target is the same as the mount point"),
+        }
+    }
+}
+
+impl Errno for MountError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::RootFs => EOPNOTSUPP,
+            Self::NoTarget => EINVAL,
+            Self::InvalidTarget(e) => e.errno(),
+            Self::LookupFailed(e) => e.errno(),
+            Self::NotDirectory => EINVAL,
+            Self::Recursive => EDEADLK,
+        }
+    }
+}
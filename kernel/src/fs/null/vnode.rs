@@ -0,0 +1,232 @@
+use super::NullFs;
+use crate::errno::{Errno, EINVAL};
+use crate::fs::{Access, FileFlags, IoCmd, Vnode, VnodeAttrs, VnodeBackend, VnodeSetAttrs};
+use crate::proc::Thread;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+
+/// Implementation of [`VnodeBackend`] for nullfs.
+///
+/// All operations are forwarded to the lower vnode with the same error (AKA `null_bypass`). Any
+/// vnode returned from the lower vnode will be replaced with its nullfs vnode.
+pub struct NullVnode {
+    lower: Arc<Vnode>, // null_lowervp
+}
+
+impl NullVnode {
+    pub fn new(lower: Arc<Vnode>) -> Self {
+        Self { lower }
+    }
+
+    pub fn lower(&self) -> &Arc<Vnode> {
+        &self.lower
+    }
+
+    fn fs(vn: &Vnode) -> &NullFs {
+        vn.mount().fs().unwrap()
+    }
+
+    /// # Panics
+    /// If `vn` is not a nullfs vnode.
+    fn lower_of(vn: &Vnode) -> &Arc<Vnode> {
+        &vn.backend::<Self>().unwrap().lower
+    }
+}
+
+impl VnodeBackend for NullVnode {
+    fn access(&self, _: &Arc<Vnode>, td: &Thread, mode: Access) -> Result<(), Box<dyn Errno>> {
+        self.lower.access(td, mode)
+    }
+
+    fn open(&self, _: &Arc<Vnode>, td: &Thread, flags: FileFlags) -> Result<(), Box<dyn Errno>> {
+        self.lower.open(td, flags)
+    }
+
+    /// See `null_getattr` on the PS4 for a reference.
+    fn getattr(&self, vn: &Arc<Vnode>, td: &Thread) -> Result<VnodeAttrs, Box<dyn Errno>> {
+        let mut attrs = self.lower.getattr(td)?;
+
+        attrs.fsid = vn.mount().id()[0];
+
+        Ok(attrs)
+    }
+
+    /// See `null_lookup` on the PS4 for a reference.
+    fn lookup(
+        &self,
+        vn: &Arc<Vnode>,
+        td: &Thread,
+        name: &str,
+    ) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        // The ".." on the root should be handled by the caller.
+        if name == ".." && vn.is_mount_root() {
+            return Err(Box::new(LookupError::DotdotOnRoot));
+        }
+
+        let lower = self.lower.lookup(td, name)?;
+
+        if Arc::ptr_eq(&lower, &self.lower) {
+            Ok(vn.clone())
+        } else {
+            Ok(Self::fs(vn).alloc_vnode(vn.mount(), &lower))
+        }
+    }
+
+    fn create(
+        &self,
+        vn: &Arc<Vnode>,
+        td: &Thread,
+        name: &str,
+        mode: u16,
+    ) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        let lower = self.lower.create(td, name, mode)?;
+
+        Ok(Self::fs(vn).alloc_vnode(vn.mount(), &lower))
+    }
+
+    fn mkdir(
+        &self,
+        vn: &Arc<Vnode>,
+        td: &Thread,
+        name: &str,
+        mode: u16,
+    ) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        let lower = self.lower.mkdir(td, name, mode)?;
+
+        Ok(Self::fs(vn).alloc_vnode(vn.mount(), &lower))
+    }
+
+    /// See `null_remove` on the PS4 for a reference.
+    fn remove(
+        &self,
+        _: &Arc<Vnode>,
+        td: &Thread,
+        vn: &Arc<Vnode>,
+        name: &str,
+    ) -> Result<(), Box<dyn Errno>> {
+        self.lower.remove(td, Self::lower_of(vn), name)
+    }
+
+    fn rmdir(
+        &self,
+        _: &Arc<Vnode>,
+        td: &Thread,
+        vn: &Arc<Vnode>,
+        name: &str,
+    ) -> Result<(), Box<dyn Errno>> {
+        self.lower.rmdir(td, Self::lower_of(vn), name)
+    }
+
+    /// See `null_rename` on the PS4 for a reference.
+    fn rename(
+        &self,
+        _: &Arc<Vnode>,
+        td: &Thread,
+        vn: &Arc<Vnode>,
+        name: &str,
+        to: &Arc<Vnode>,
+        target: Option<&Arc<Vnode>>,
+        to_name: &str,
+    ) -> Result<(), Box<dyn Errno>> {
+        let vn = Self::lower_of(vn);
+        let to = Self::lower_of(to);
+        let target = target.map(|v| Self::lower_of(v));
+
+        self.lower.rename(td, vn, name, to, target, to_name)
+    }
+
+    fn symlink(
+        &self,
+        vn: &Arc<Vnode>,
+        td: &Thread,
+        name: &str,
+        mode: u16,
+        target: &str,
+    ) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        let lower = self.lower.symlink(td, name, mode, target)?;
+
+        Ok(Self::fs(vn).alloc_vnode(vn.mount(), &lower))
+    }
+
+    fn readlink(&self, _: &Arc<Vnode>, td: &Thread) -> Result<Box<str>, Box<dyn Errno>> {
+        self.lower.readlink(td)
+    }
+
+    /// See `null_setattr` on the PS4 for a reference.
+    fn setattr(
+        &self,
+        _: &Arc<Vnode>,
+        td: &Thread,
+        attrs: &VnodeSetAttrs,
+    ) -> Result<(), Box<dyn Errno>> {
+        self.lower.setattr(td, attrs)
+    }
+
+    fn readdir(
+        &self,
+        _: &Arc<Vnode>,
+        td: &Thread,
+        off: &mut u64,
+        buf: &mut [u8],
+    ) -> Result<usize, Box<dyn Errno>> {
+        self.lower.readdir(td, off, buf)
+    }
+
+    fn read(
+        &self,
+        _: &Arc<Vnode>,
+        td: &Thread,
+        off: u64,
+        buf: &mut [u8],
+    ) -> Result<usize, Box<dyn Errno>> {
+        self.lower.read(td, off, buf)
+    }
+
+    fn write(
+        &self,
+        _: &Arc<Vnode>,
+        td: &Thread,
+        off: u64,
+        buf: &[u8],
+    ) -> Result<usize, Box<dyn Errno>> {
+        self.lower.write(td, off, buf)
+    }
+
+    fn ioctl(
+        &self,
+        _: &Arc<Vnode>,
+        td: &Thread,
+        cmd: IoCmd,
+        data: &mut [u8],
+    ) -> Result<(), Box<dyn Errno>> {
+        self.lower.ioctl(td, cmd, data)
+    }
+}
+
+/// Represents an error when [`NullVnode::lookup()`] fails.
+#[derive(Debug)]
+enum LookupError {
+    DotdotOnRoot,
+}
+
+impl Error for LookupError {}
+
+impl Display for LookupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::DotdotOnRoot => f.write_str("lookup '..' on the root of the mount"),
+        }
+    }
+}
+
+impl Errno for LookupError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::DotdotOnRoot => EINVAL,
+        }
+    }
+}
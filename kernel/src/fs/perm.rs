@@ -0,0 +1,225 @@
+use crate::errno::{Errno, EACCES, EPERM};
+use crate::ucred::{Gid, Privilege, Ucred, Uid};
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+use macros::bitflag;
+
+/// Set user ID on execution.
+pub const S_ISUID: u16 = 0o4000;
+
+/// Set group ID on execution.
+pub const S_ISGID: u16 = 0o2000;
+
+/// Sticky bit.
+pub const S_ISVTX: u16 = 0o1000;
+
+/// Returns [`Ok`] if access was granted. The boolean value indicated whether privilege was used to
+/// satisfy the request.
+///
+/// See `vaccess` on the PS4 for a reference.
+pub fn check_access(
+    cred: &Ucred,
+    file_uid: Uid,
+    file_gid: Gid,
+    file_mode: u16,
+    access: Access,
+    is_dir: bool,
+) -> Result<bool, AccessError> {
+    // Get the permissions that was granted by the file mode.
+    let mode = u32::from(file_mode);
+    let mut dac_granted = 0;
+
+    if cred.effective_uid() == file_uid {
+        dac_granted |= Access::ADMIN.0;
+
+        if (mode & 0o400) != 0 {
+            dac_granted |= Access::READ.0;
+        }
+
+        if (mode & 0o200) != 0 {
+            dac_granted |= Access::WRITE.0 | Access::APPEND.0;
+        }
+
+        if (mode & 0o100) != 0 {
+            dac_granted |= Access::EXEC.0;
+        }
+    } else if cred.is_member(file_gid) {
+        if (mode & 0o040) != 0 {
+            dac_granted |= Access::READ.0;
+        }
+
+        if (mode & 0o020) != 0 {
+            dac_granted |= Access::WRITE.0 | Access::APPEND.0;
+        }
+
+        if (mode & 0o010) != 0 {
+            dac_granted |= Access::EXEC.0;
+        }
+    } else {
+        if (mode & 0o004) != 0 {
+            dac_granted |= Access::READ.0;
+        }
+
+        if (mode & 0o002) != 0 {
+            dac_granted |= Access::WRITE.0 | Access::APPEND.0;
+        }
+
+        if (mode & 0o001) != 0 {
+            dac_granted |= Access::EXEC.0;
+        }
+    }
+
+    if (access.0 & !dac_granted) == 0 {
+        return Ok(false);
+    }
+
+    // Check if privilege can be used to grant the remaining permissions.
+    let mut priv_granted = 0;
+    let missing = access.0 & !dac_granted;
+
+    if (missing & Access::EXEC.0) != 0 {
+        // For directories, use VFS_LOOKUP to grant the search permission. For files, only grant
+        // the execute permission if at least one execute bit is set.
+        let p = if is_dir {
+            Some(Privilege::VFS_LOOKUP)
+        } else if (mode & 0o111) != 0 {
+            Some(Privilege::VFS_EXEC)
+        } else {
+            None
+        };
+
+        if p.is_some_and(|p| cred.priv_check(p).is_ok()) {
+            priv_granted |= Access::EXEC.0;
+        }
+    }
+
+    if (missing & Access::READ.0) != 0 && cred.priv_check(Privilege::VFS_READ).is_ok() {
+        priv_granted |= Access::READ.0;
+    }
+
+    if (missing & (Access::WRITE.0 | Access::APPEND.0)) != 0
+        && cred.priv_check(Privilege::VFS_WRITE).is_ok()
+    {
+        priv_granted |= Access::WRITE.0 | Access::APPEND.0;
+    }
+
+    if (missing & Access::ADMIN.0) != 0 && cred.priv_check(Privilege::VFS_ADMIN).is_ok() {
+        priv_granted |= Access::ADMIN.0;
+    }
+
+    if (missing & !priv_granted) == 0 {
+        Ok(true)
+    } else if (access.0 & Access::ADMIN.0) != 0 {
+        Err(AccessError::NotPermitted)
+    } else {
+        Err(AccessError::PermissionDenied)
+    }
+}
+
+/// Implementation of `accmode_t`.
+#[bitflag(u32)]
+pub enum Access {
+    /// Execute or search permission.
+    EXEC = 0o000100,
+    /// Write permission.
+    WRITE = 0o000200,
+    /// Read permission.
+    READ = 0o000400,
+    /// Being owner.
+    ADMIN = 0o010000,
+    /// Only append.
+    APPEND = 0o040000,
+}
+
+/// Represents an error when [`check_access()`] fails.
+#[derive(Debug)]
+pub enum AccessError {
+    NotPermitted,
+    PermissionDenied,
+}
+
+impl Error for AccessError {}
+
+impl Display for AccessError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotPermitted => f.write_str("operation not permitted"),
+            Self::PermissionDenied => f.write_str("permission denied"),
+        }
+    }
+}
+
+impl Errno for AccessError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NotPermitted => EPERM,
+            Self::PermissionDenied => EACCES,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ucred::AuthInfo;
+    use alloc::vec;
+
+    #[test]
+    fn check_access() {
+        let uid = Uid::new(1).unwrap();
+        let gid = Gid::new(1).unwrap();
+        let other = Uid::new(2).unwrap();
+        let root = Ucred::new(Uid::ROOT, Uid::ROOT, vec![Gid::ROOT], AuthInfo::KERNEL);
+        let user = Ucred::new(uid, uid, vec![gid], AuthInfo::KERNEL);
+        let check = super::check_access;
+
+        // Owner.
+        assert!(matches!(
+            check(&user, uid, gid, 0o600, Access::READ | Access::WRITE, false),
+            Ok(false)
+        ));
+        assert!(matches!(
+            check(&user, uid, gid, 0o400, Access::WRITE, false),
+            Err(AccessError::PermissionDenied)
+        ));
+        assert!(matches!(
+            check(&user, uid, gid, 0, Access::ADMIN, false),
+            Ok(false)
+        ));
+
+        // Group and other.
+        assert!(matches!(
+            check(&user, other, gid, 0o040, Access::READ, false),
+            Ok(false)
+        ));
+        assert!(check(&user, other, Gid::ROOT, 0o040, Access::READ, false).is_err());
+        assert!(matches!(
+            check(&user, other, Gid::ROOT, 0o001, Access::EXEC, true),
+            Ok(false)
+        ));
+        assert!(matches!(
+            check(&user, other, gid, 0o600, Access::ADMIN, false),
+            Err(AccessError::NotPermitted)
+        ));
+
+        // Privilege.
+        assert!(matches!(
+            check(&root, uid, gid, 0, Access::READ | Access::WRITE, false),
+            Ok(true)
+        ));
+        assert!(matches!(
+            check(&root, uid, gid, 0, Access::EXEC, true),
+            Ok(true)
+        ));
+        assert!(matches!(
+            check(&root, uid, gid, 0o644, Access::EXEC, false),
+            Err(AccessError::PermissionDenied)
+        ));
+        assert!(matches!(
+            check(&root, uid, gid, 0o100, Access::EXEC, false),
+            Ok(true)
+        ));
+    }
+}
@@ -0,0 +1,215 @@
+use super::inflate::{zlib_decompress, InflateError};
+use crate::errno::{Errno, EIO};
+use crate::fs::host::{self, HostError};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+
+/// Random access to a read-only data that contains a PFS.
+pub trait Image: Send + Sync {
+    fn len(&self) -> u64;
+
+    /// Fill the whole `buf` with the data at `off`.
+    fn read(&self, off: u64, buf: &mut [u8]) -> Result<(), ImageError>;
+}
+
+/// Implementation of [`Image`] for a file on the host.
+pub struct HostImage {
+    node: u64,
+    len: u64,
+}
+
+impl HostImage {
+    pub fn new(node: u64, len: u64) -> Self {
+        Self { node, len }
+    }
+}
+
+impl Image for HostImage {
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn read(&self, mut off: u64, mut buf: &mut [u8]) -> Result<(), ImageError> {
+        // The host may return less than we requested so we need a loop here.
+        while !buf.is_empty() {
+            let n = host::read(self.node, off, buf).map_err(ImageError::HostFailed)?;
+
+            if n == 0 {
+                return Err(ImageError::UnexpectedEof);
+            }
+
+            buf = &mut buf[n..];
+            off += n as u64;
+        }
+
+        Ok(())
+    }
+}
+
+/// Implementation of [`Image`] for PFSC, which is a block-compressed wrapper of another image.
+///
+/// This format is used for both the whole PFS image and the compressed files inside it.
+pub struct Pfsc<I> {
+    inner: I,
+    block_size: u64,
+    offsets: Vec<u64>,
+    len: u64,
+}
+
+impl<I: Image> Pfsc<I> {
+    pub const MAGIC: &[u8; 4] = b"PFSC";
+
+    pub fn open(inner: I) -> Result<Self, ImageError> {
+        // Read header.
+        let mut hdr = [0u8; 0x30];
+
+        inner.read(0, &mut hdr)?;
+
+        if &hdr[..4] != Self::MAGIC {
+            return Err(ImageError::InvalidPfsc);
+        }
+
+        let block_size = u64::from_le_bytes(hdr[0x10..0x18].try_into().unwrap());
+        let table = u64::from_le_bytes(hdr[0x18..0x20].try_into().unwrap());
+        let len = u64::from_le_bytes(hdr[0x28..0x30].try_into().unwrap());
+
+        // We need to allocate a block when reading so don't allow a crazy block size.
+        if !(0x200..=0x100000).contains(&block_size) {
+            return Err(ImageError::InvalidPfsc);
+        }
+
+        // Read block offsets. The last one is the end of the last block.
+        let count = len.div_ceil(block_size) + 1;
+
+        if count.checked_mul(8).is_none_or(|v| v > inner.len()) {
+            return Err(ImageError::InvalidPfsc);
+        }
+
+        let mut data = vec![0u8; (count * 8) as usize];
+
+        inner.read(table, &mut data)?;
+
+        let offsets: Vec<u64> = data
+            .chunks_exact(8)
+            .map(|v| u64::from_le_bytes(v.try_into().unwrap()))
+            .collect();
+
+        if offsets
+            .windows(2)
+            .any(|v| v[1] < v[0] || v[1] - v[0] > block_size)
+            || offsets.last().is_some_and(|&v| v > inner.len())
+        {
+            return Err(ImageError::InvalidPfsc);
+        }
+
+        Ok(Self {
+            inner,
+            block_size,
+            offsets,
+            len,
+        })
+    }
+}
+
+impl<I: Image> Image for Pfsc<I> {
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn read(&self, mut off: u64, mut buf: &mut [u8]) -> Result<(), ImageError> {
+        if off
+            .checked_add(buf.len() as u64)
+            .is_none_or(|v| v > self.len)
+        {
+            return Err(ImageError::UnexpectedEof);
+        }
+
+        let mut block = vec![0u8; self.block_size as usize];
+
+        while !buf.is_empty() {
+            let i = (off / self.block_size) as usize;
+            let start = self.offsets[i];
+            let size = self.offsets[i + 1] - start;
+
+            // A block with the same size as the block size is not compressed and a block with
+            // zero size is filled with zeroes.
+            if size == self.block_size {
+                self.inner.read(start, &mut block)?;
+            } else if size == 0 {
+                block.fill(0);
+            } else {
+                let mut data = vec![0u8; size as usize];
+
+                self.inner.read(start, &mut data)?;
+
+                let n = zlib_decompress(&data, &mut block).map_err(ImageError::InflateFailed)?;
+
+                block[n..].fill(0);
+            }
+
+            // Copy.
+            let pos = (off % self.block_size) as usize;
+            let n = buf.len().min(block.len() - pos);
+
+            buf[..n].copy_from_slice(&block[pos..(pos + n)]);
+            buf = &mut buf[n..];
+            off += n as u64;
+        }
+
+        Ok(())
+    }
+}
+
+/// Represents an error when reading data from [`Image`] fails.
+#[derive(Debug)]
+pub enum ImageError {
+    HostFailed(HostError),
+    UnexpectedEof,
+    InvalidPfsc,
+    InflateFailed(InflateError),
+    InvalidInode(u64),
+    UnsupportedBlock(u64),
+    InvalidDirent,
+}
+
+impl Error for ImageError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::HostFailed(e) => Some(e),
+            Self::InflateFailed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ImageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::HostFailed(_) => f.write_str("couldn't read the image on the host"),
+            Self::UnexpectedEof => f.write_str("unexpected end of the image"),
+            Self::InvalidPfsc => f.write_str("invalid PFSC"),
+            Self::InflateFailed(_) => f.write_str("couldn't decompress a PFSC block"),
+            Self::InvalidInode(v) => write!(f, "invalid inode #{v}"),
+            Self::UnsupportedBlock(v) => write!(f, "block #{v} of the inode is not supported"),
+            Self::InvalidDirent => f.write_str("invalid directory entry"),
+        }
+    }
+}
+
+impl Errno for ImageError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::HostFailed(e) => e.errno(),
+            Self::UnexpectedEof
+            | Self::InvalidPfsc
+            | Self::InflateFailed(_)
+            | Self::InvalidInode(_)
+            | Self::UnsupportedBlock(_)
+            | Self::InvalidDirent => EIO,
+        }
+    }
+}
@@ -0,0 +1,397 @@
+use core::error::Error;
+use core::fmt::{Display, Formatter};
+
+/// Decompress zlib stream in `src` into `dst`. Returns the number of bytes written to `dst`.
+///
+/// The checksum at the end of the stream is not verified.
+pub fn zlib_decompress(src: &[u8], dst: &mut [u8]) -> Result<usize, InflateError> {
+    // Check header.
+    let (cmf, flg) = match src {
+        [a, b, ..] => (*a, *b),
+        _ => return Err(InflateError::UnexpectedEof),
+    };
+
+    if cmf & 0xF != 8 || (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 || flg & 0x20 != 0 {
+        return Err(InflateError::InvalidHeader);
+    }
+
+    // Decompress.
+    let mut s = Inflater {
+        src: &src[2..],
+        pos: 0,
+        buf: 0,
+        nbits: 0,
+        dst,
+        len: 0,
+    };
+
+    loop {
+        let last = s.bits(1)?;
+
+        match s.bits(2)? {
+            0 => s.stored()?,
+            1 => {
+                let (lit, dist) = fixed();
+                s.codes(&lit, &dist)?;
+            }
+            2 => {
+                let (lit, dist) = s.dynamic()?;
+                s.codes(&lit, &dist)?;
+            }
+            _ => return Err(InflateError::InvalidBlock),
+        }
+
+        if last == 1 {
+            break;
+        }
+    }
+
+    Ok(s.len)
+}
+
+/// State of [`zlib_decompress()`].
+struct Inflater<'a, 'b> {
+    src: &'a [u8],
+    pos: usize,
+    buf: u32,
+    nbits: u32,
+    dst: &'b mut [u8],
+    len: usize,
+}
+
+impl Inflater<'_, '_> {
+    const LBASE: [u16; 29] = [
+        3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+        131, 163, 195, 227, 258,
+    ];
+    const LEXT: [u8; 29] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+    ];
+    const DBASE: [u16; 30] = [
+        1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+        2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+    ];
+    const DEXT: [u8; 30] = [
+        0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12,
+        13, 13,
+    ];
+
+    fn bits(&mut self, n: u32) -> Result<u32, InflateError> {
+        while self.nbits < n {
+            let v = self.src.get(self.pos).ok_or(InflateError::UnexpectedEof)?;
+
+            self.buf |= u32::from(*v) << self.nbits;
+            self.pos += 1;
+            self.nbits += 8;
+        }
+
+        let v = self.buf & ((1 << n) - 1);
+
+        self.buf >>= n;
+        self.nbits -= n;
+
+        Ok(v)
+    }
+
+    fn put(&mut self, v: u8) -> Result<(), InflateError> {
+        let d = self
+            .dst
+            .get_mut(self.len)
+            .ok_or(InflateError::OutputTooSmall)?;
+
+        *d = v;
+        self.len += 1;
+
+        Ok(())
+    }
+
+    fn stored(&mut self) -> Result<(), InflateError> {
+        // Discard the remaining bits in the current byte.
+        self.bits(self.nbits % 8)?;
+
+        // Get length.
+        let len = self.bits(16)?;
+        let nlen = self.bits(16)?;
+
+        if len != !nlen & 0xFFFF {
+            return Err(InflateError::InvalidBlock);
+        }
+
+        for _ in 0..len {
+            let v = self.bits(8)?;
+            self.put(v as u8)?;
+        }
+
+        Ok(())
+    }
+
+    fn dynamic(&mut self) -> Result<(Huffman<288>, Huffman<30>), InflateError> {
+        const ORDER: [usize; 19] = [
+            16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+        ];
+
+        // Get the number of codes.
+        let nlen = self.bits(5)? as usize + 257;
+        let ndist = self.bits(5)? as usize + 1;
+        let ncode = self.bits(4)? as usize + 4;
+
+        if nlen > 286 || ndist > 30 {
+            return Err(InflateError::InvalidBlock);
+        }
+
+        // Get code length code lengths.
+        let mut lengths = [0u8; 320];
+
+        for &i in &ORDER[..ncode] {
+            lengths[i] = self.bits(3)? as u8;
+        }
+
+        let lencode = Huffman::<19>::new(&lengths[..19])?;
+
+        // Get literal/length and distance code lengths.
+        let mut i = 0;
+
+        while i < nlen + ndist {
+            let (v, n) = match self.decode(&lencode)? {
+                v @ 0..16 => (v as u8, 1),
+                16 => match i.checked_sub(1) {
+                    Some(p) => (lengths[p], 3 + self.bits(2)?),
+                    None => return Err(InflateError::InvalidBlock),
+                },
+                17 => (0, 3 + self.bits(3)?),
+                _ => (0, 11 + self.bits(7)?),
+            };
+
+            for _ in 0..n {
+                *lengths
+                    .get_mut(i)
+                    .filter(|_| i < nlen + ndist)
+                    .ok_or(InflateError::InvalidBlock)? = v;
+                i += 1;
+            }
+        }
+
+        // The end-of-block code is required.
+        if lengths[256] == 0 {
+            return Err(InflateError::InvalidBlock);
+        }
+
+        let lit = Huffman::new(&lengths[..nlen])?;
+        let dist = Huffman::new(&lengths[nlen..(nlen + ndist)])?;
+
+        Ok((lit, dist))
+    }
+
+    fn codes<const L: usize, const D: usize>(
+        &mut self,
+        lit: &Huffman<L>,
+        dist: &Huffman<D>,
+    ) -> Result<(), InflateError> {
+        loop {
+            let sym = self.decode(lit)?;
+
+            if sym < 256 {
+                self.put(sym as u8)?;
+                continue;
+            } else if sym == 256 {
+                break;
+            }
+
+            // Get length.
+            let sym = usize::from(sym - 257);
+            let base = *Self::LBASE.get(sym).ok_or(InflateError::InvalidBlock)?;
+            let len = usize::from(base) + self.bits(Self::LEXT[sym].into())? as usize;
+
+            // Get distance.
+            let sym = usize::from(self.decode(dist)?);
+            let base = *Self::DBASE.get(sym).ok_or(InflateError::InvalidBlock)?;
+            let dist = usize::from(base) + self.bits(Self::DEXT[sym].into())? as usize;
+
+            if dist > self.len {
+                return Err(InflateError::InvalidDistance);
+            }
+
+            // Copy. The source and destination can overlap so we need to copy one byte at a time.
+            for _ in 0..len {
+                self.put(self.dst[self.len - dist])?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn decode<const N: usize>(&mut self, h: &Huffman<N>) -> Result<u16, InflateError> {
+        let mut code = 0;
+        let mut first = 0;
+        let mut index = 0;
+
+        for len in 1..16 {
+            code |= self.bits(1)? as usize;
+
+            let count = usize::from(h.counts[len]);
+
+            if code < first + count {
+                return Ok(h.symbols[index + code - first]);
+            }
+
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+
+        Err(InflateError::InvalidCode)
+    }
+}
+
+/// Canonical Huffman code with up to `N` symbols.
+struct Huffman<const N: usize> {
+    counts: [u16; 16],
+    symbols: [u16; N],
+}
+
+impl<const N: usize> Huffman<N> {
+    fn new(lengths: &[u8]) -> Result<Self, InflateError> {
+        let mut h = Self {
+            counts: [0; 16],
+            symbols: [0; N],
+        };
+
+        // Count the number of codes for each length.
+        for &l in lengths {
+            h.counts[usize::from(l)] += 1;
+        }
+
+        // Check if the lengths are valid. An incomplete code is allowed.
+        let mut left = 1i32;
+
+        for len in 1..16 {
+            left <<= 1;
+            left -= i32::from(h.counts[len]);
+
+            if left < 0 {
+                return Err(InflateError::InvalidCode);
+            }
+        }
+
+        // Generate symbols.
+        let mut offs = [0u16; 16];
+
+        for len in 1..15 {
+            offs[len + 1] = offs[len] + h.counts[len];
+        }
+
+        for (sym, &l) in lengths.iter().enumerate() {
+            if l != 0 {
+                let i = &mut offs[usize::from(l)];
+
+                h.symbols[usize::from(*i)] = sym as u16;
+                *i += 1;
+            }
+        }
+
+        Ok(h)
+    }
+}
+
+/// Returns the literal/length and distance codes for the fixed block.
+fn fixed() -> (Huffman<288>, Huffman<30>) {
+    let mut lengths = [0u8; 288];
+
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+
+    let lit = Huffman::new(&lengths).unwrap();
+    let dist = Huffman::new(&[5; 30]).unwrap();
+
+    (lit, dist)
+}
+
+/// Represents an error when [`zlib_decompress()`] fails.
+#[derive(Debug)]
+pub enum InflateError {
+    UnexpectedEof,
+    InvalidHeader,
+    InvalidBlock,
+    InvalidCode,
+    InvalidDistance,
+    OutputTooSmall,
+}
+
+impl Error for InflateError {}
+
+impl Display for InflateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnexpectedEof => f.write_str("unexpected end of data"),
+            Self::InvalidHeader => f.write_str("invalid zlib header"),
+            Self::InvalidBlock => f.write_str("invalid block"),
+            Self::InvalidCode => f.write_str("invalid Huffman code"),
+            Self::InvalidDistance => f.write_str("invalid distance"),
+            Self::OutputTooSmall => f.write_str("the output is too small"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored() {
+        let src = [
+            0x78, 0x01, 0x01, 0x0b, 0x00, 0xf4, 0xff, 0x73, 0x74, 0x6f, 0x72, 0x65, 0x64, 0x20,
+            0x64, 0x61, 0x74, 0x61, 0x1a, 0xb2, 0x04, 0x4c,
+        ];
+        let mut dst = [0; 32];
+        let len = zlib_decompress(&src, &mut dst).unwrap();
+
+        assert_eq!(&dst[..len], b"stored data");
+    }
+
+    #[test]
+    fn fixed() {
+        let src = [
+            0x78, 0x01, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x22, 0xcb, 0xf3, 0x8b,
+            0x72, 0x52, 0x00, 0x68, 0x7d, 0x08, 0xc5,
+        ];
+        let mut dst = [0; 32];
+        let len = zlib_decompress(&src, &mut dst).unwrap();
+
+        assert_eq!(&dst[..len], b"hello hello hello world");
+    }
+
+    #[test]
+    fn dynamic() {
+        let src = [
+            0x78, 0xda, 0xed, 0xca, 0x41, 0x11, 0x00, 0x30, 0x0c, 0x02, 0x41, 0xad, 0xd0, 0x23,
+            0xa9, 0x7f, 0x05, 0x8d, 0x8d, 0xce, 0x84, 0x1f, 0xb0, 0x22, 0x92, 0x1a, 0x73, 0x6d,
+            0x2b, 0x27, 0x3e, 0xe0, 0x9e, 0xca, 0x44, 0x73, 0x90, 0xe0, 0x32, 0xd5, 0x9d, 0x19,
+            0xd6, 0xaf, 0x5f, 0xff, 0x9b, 0x7f, 0x2c, 0xbe, 0x85, 0xd5,
+        ];
+        let mut dst = [0; 1000];
+        let len = zlib_decompress(&src, &mut dst).unwrap();
+
+        assert_eq!(len, dst.len());
+
+        for (i, &v) in dst.iter().enumerate() {
+            assert_eq!(v, b"aabbbcdddddeefgh"[(i * i * 7 + i / 3) % 16]);
+        }
+    }
+
+    #[test]
+    fn output_too_small() {
+        let src = [
+            0x78, 0x01, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x22, 0xcb, 0xf3, 0x8b,
+            0x72, 0x52, 0x00, 0x68, 0x7d, 0x08, 0xc5,
+        ];
+        let mut dst = [0; 8];
+
+        assert!(matches!(
+            zlib_decompress(&src, &mut dst),
+            Err(InflateError::OutputTooSmall)
+        ));
+    }
+}
@@ -0,0 +1,135 @@
+use alloc::vec::Vec;
+
+/// Contains a parsed inode of PFS.
+pub struct Inode {
+    mode: u16,
+    nlink: u16,
+    flags: u32,
+    size: u64,
+    decompressed_size: u64,
+    times: [(i64, u32); 4],
+    uid: u32,
+    gid: u32,
+    blocks: u32,
+    direct: [u64; 12],
+    indirect: [u64; 5],
+}
+
+impl Inode {
+    /// Size of the fixed part before the block pointers.
+    pub const HEADER_SIZE: usize = 0x64;
+
+    const FLAG_COMPRESSED: u32 = 0x1;
+
+    /// `ptr_size` is the size of each block pointer, which is 4, 8, 36 or 40.
+    pub fn parse(data: &[u8], ptr_size: usize) -> Self {
+        let u16 = |o: usize| u16::from_le_bytes(data[o..(o + 2)].try_into().unwrap());
+        let u32 = |o: usize| u32::from_le_bytes(data[o..(o + 4)].try_into().unwrap());
+        let u64 = |o: usize| u64::from_le_bytes(data[o..(o + 8)].try_into().unwrap());
+        let time = |i: usize| (u64(0x18 + i * 8) as i64, u32(0x38 + i * 4));
+
+        let ptrs: Vec<u64> = (0..17)
+            .map(|i| parse_ptr(&data[(Self::HEADER_SIZE + i * ptr_size)..], ptr_size))
+            .collect();
+
+        Self {
+            mode: u16(0x00),
+            nlink: u16(0x02),
+            flags: u32(0x04),
+            size: u64(0x08),
+            decompressed_size: u64(0x10),
+            times: [time(0), time(1), time(2), time(3)],
+            uid: u32(0x48),
+            gid: u32(0x4C),
+            blocks: u32(0x60),
+            direct: ptrs[..12].try_into().unwrap(),
+            indirect: ptrs[12..].try_into().unwrap(),
+        }
+    }
+
+    pub fn mode(&self) -> u16 {
+        self.mode
+    }
+
+    pub fn nlink(&self) -> u16 {
+        self.nlink
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.mode & 0o170000 == 0o040000
+    }
+
+    pub fn is_link(&self) -> bool {
+        self.mode & 0o170000 == 0o120000
+    }
+
+    /// Returns `true` if the data is PFSC.
+    pub fn is_compressed(&self) -> bool {
+        self.flags & Self::FLAG_COMPRESSED != 0
+    }
+
+    /// Returns the size of the data on the image.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the size of the file as seen by the user.
+    pub fn file_size(&self) -> u64 {
+        if self.is_compressed() {
+            self.decompressed_size
+        } else {
+            self.size
+        }
+    }
+
+    pub fn atime(&self) -> (i64, u32) {
+        self.times[0]
+    }
+
+    pub fn mtime(&self) -> (i64, u32) {
+        self.times[1]
+    }
+
+    pub fn ctime(&self) -> (i64, u32) {
+        self.times[2]
+    }
+
+    pub fn birthtime(&self) -> (i64, u32) {
+        self.times[3]
+    }
+
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    pub fn blocks(&self) -> u32 {
+        self.blocks
+    }
+
+    pub fn direct(&self) -> &[u64; 12] {
+        &self.direct
+    }
+
+    pub fn indirect(&self) -> &[u64; 5] {
+        &self.indirect
+    }
+}
+
+/// Parse a block pointer at the beginning of `data`.
+///
+/// # Panics
+/// If `size` is not 4, 8, 36 or 40.
+pub fn parse_ptr(data: &[u8], size: usize) -> u64 {
+    // The pointers on a signed image are prefixed with a 32-bytes signature.
+    match size {
+        4 => u32::from_le_bytes(data[..4].try_into().unwrap()).into(),
+        8 => u64::from_le_bytes(data[..8].try_into().unwrap()),
+        36 => u32::from_le_bytes(data[32..36].try_into().unwrap()).into(),
+        40 => u64::from_le_bytes(data[32..40].try_into().unwrap()),
+        _ => panic!("invalid pointer size {size}"),
+    }
+}
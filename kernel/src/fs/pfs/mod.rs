@@ -0,0 +1,601 @@
+use self::image::{HostImage, Image, ImageError, Pfsc};
+use self::inode::{parse_ptr, Inode};
+use self::vnode::PfsVnode;
+use super::host::HostError;
+use super::{Filesystem, Fs, FsConfig, Mount, MountFlags, MountOpts, Vnode, VnodeType};
+use crate::errno::{Errno, EFTYPE, EINVAL, EOPNOTSUPP};
+use crate::proc::Thread;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec;
+use config::{FsNodeType, FsType};
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+
+mod image;
+mod inflate;
+mod inode;
+mod vnode;
+
+/// Configuration of PlayStation File System.
+pub static PFS: FsConfig = FsConfig {
+    name: "pfs",
+    ty: 0xA4,
+    mount: PfsFs::mount,
+};
+
+/// Implementation of PlayStation File System.
+///
+/// This is a read-only implementation that read the image exposed by the host as
+/// [`FsType::Image`]. The image can be either a plain PFS or a PFS wrapped in PFSC.
+pub struct PfsFs {
+    image: Box<dyn Image>,
+    block_size: u64,
+    ptr_size: usize, // 4 or 8 with an optional 32-bytes signature.
+    inode_size: usize,
+    ninodes: u64,
+    root: u64,
+}
+
+impl PfsFs {
+    const VERSION: u64 = 1;
+    const MAGIC: u64 = 20130315;
+
+    /// The PS4 always use 64K blocks. We need to allocate a block when reading a directory so don't
+    /// allow anything larger than this.
+    const MAX_BLOCK_SIZE: u32 = 0x10000;
+
+    const MODE_SIGNED: u16 = 0x1;
+    const MODE_64: u16 = 0x2;
+    const MODE_ENCRYPTED: u16 = 0x4;
+
+    fn mount(
+        _: &Fs,
+        _: Option<&Arc<Vnode>>,
+        opts: &mut MountOpts,
+        flags: &mut MountFlags,
+        _: &Thread,
+    ) -> Result<Box<dyn Filesystem>, Box<dyn Errno>> {
+        // Get the image.
+        let from = match opts.remove_str("from") {
+            Ok(Some(v)) => v,
+            Ok(None) => return Err(Box::new(MountError::NoFrom)),
+            Err(e) => return Err(Box::new(e)),
+        };
+
+        let name = from.strip_prefix("/dev/").unwrap_or(&from);
+        let (ty, root) = match super::host::mount(name) {
+            Ok(v) => v,
+            Err(e) => return Err(Box::new(MountError::MountFailed(e))),
+        };
+
+        if ty != FsType::Image {
+            return Err(Box::new(MountError::NotImage));
+        } else if root.ty != u8::from(FsNodeType::File) {
+            return Err(Box::new(MountError::InvalidImage));
+        }
+
+        // Check if the image is compressed.
+        let image = HostImage::new(root.id, root.size);
+        let mut magic = [0u8; 4];
+
+        if let Err(e) = image.read(0, &mut magic) {
+            return Err(Box::new(MountError::ReadImageFailed(e)));
+        }
+
+        let mut fs = if &magic == Pfsc::<HostImage>::MAGIC {
+            match Pfsc::open(image) {
+                Ok(v) => Self::new(Box::new(v))?,
+                Err(e) => return Err(Box::new(MountError::ReadImageFailed(e))),
+            }
+        } else {
+            Self::new(Box::new(image))?
+        };
+
+        // Get the root directory. The superroot contains the root of the user data as "uroot".
+        let root = match fs.inode(fs.root) {
+            Ok(v) => v,
+            Err(e) => return Err(Box::new(MountError::ReadImageFailed(e))),
+        };
+
+        fs.root = match fs.find(&root, "uroot") {
+            Ok(Some(v)) => v,
+            Ok(None) => return Err(Box::new(MountError::NoRoot)),
+            Err(e) => return Err(Box::new(MountError::ReadImageFailed(e))),
+        };
+
+        *flags |= MountFlags::MNT_RDONLY | MountFlags::MNT_LOCAL;
+
+        Ok(Box::new(fs))
+    }
+
+    fn new(image: Box<dyn Image>) -> Result<Self, Box<dyn Errno>> {
+        // Read header.
+        let mut hdr = [0u8; 0x50];
+
+        if let Err(e) = image.read(0, &mut hdr) {
+            return Err(Box::new(MountError::ReadImageFailed(e)));
+        }
+
+        let u16 = |o: usize| u16::from_le_bytes(hdr[o..(o + 2)].try_into().unwrap());
+        let u32 = |o: usize| u32::from_le_bytes(hdr[o..(o + 4)].try_into().unwrap());
+        let u64 = |o: usize| u64::from_le_bytes(hdr[o..(o + 8)].try_into().unwrap());
+
+        if u64(0x00) != Self::VERSION || u64(0x08) != Self::MAGIC {
+            return Err(Box::new(MountError::InvalidImage));
+        }
+
+        // Check mode.
+        let mode = u16(0x1C);
+
+        if mode & Self::MODE_ENCRYPTED != 0 {
+            return Err(Box::new(MountError::Encrypted));
+        }
+
+        let ptr_size = match (mode & Self::MODE_SIGNED != 0, mode & Self::MODE_64 != 0) {
+            (false, false) => 4,
+            (false, true) => 8,
+            (true, false) => 36,
+            (true, true) => 40,
+        };
+
+        // Check block size. Each block must be able to hold at least one inode.
+        let block_size = u32(0x20);
+        let inode_size = Inode::HEADER_SIZE + 17 * ptr_size;
+
+        if !block_size.is_power_of_two()
+            || (block_size as usize) < inode_size
+            || block_size > Self::MAX_BLOCK_SIZE
+        {
+            return Err(Box::new(MountError::InvalidImage));
+        }
+
+        Ok(Self {
+            image,
+            block_size: block_size.into(),
+            ptr_size,
+            inode_size,
+            ninodes: u64(0x30),
+            root: u64(0x48), // Superroot until the mount is completed.
+        })
+    }
+
+    fn inode(&self, ino: u64) -> Result<Inode, ImageError> {
+        if ino >= self.ninodes {
+            return Err(ImageError::InvalidInode(ino));
+        }
+
+        // The inode table start at the second block and an inode never cross the block boundary.
+        let per_block = self.block_size / self.inode_size as u64;
+        let block = 1 + ino / per_block;
+        let off = self
+            .offset(block, (ino % per_block) * self.inode_size as u64)
+            .ok_or(ImageError::InvalidInode(ino))?;
+        let mut data = vec![0u8; self.inode_size];
+
+        self.image.read(off, &mut data)?;
+
+        Ok(Inode::parse(&data, self.ptr_size))
+    }
+
+    /// Returns the block number on the image for the block `n` of `inode`.
+    fn block(&self, inode: &Inode, n: u64) -> Result<u64, ImageError> {
+        let direct = inode.direct();
+        let per_block = self.block_size / self.ptr_size as u64;
+
+        if let Some(&v) = direct.get(n as usize) {
+            return Ok(v);
+        }
+
+        // Read the pointer at the specified index on the indirect block.
+        let ptr = |block: u64, index: u64| {
+            let mut data = [0u8; 40];
+            let data = &mut data[..self.ptr_size];
+            let off = self
+                .offset(block, index * self.ptr_size as u64)
+                .ok_or(ImageError::UnsupportedBlock(n))?;
+
+            self.image.read(off, data)?;
+
+            Ok(parse_ptr(data, self.ptr_size))
+        };
+
+        // Single indirect.
+        let i = n - direct.len() as u64;
+
+        if i < per_block {
+            return ptr(inode.indirect()[0], i);
+        }
+
+        // Double indirect.
+        let i = i - per_block;
+
+        if i < per_block * per_block {
+            let b = ptr(inode.indirect()[1], i / per_block)?;
+
+            return ptr(b, i % per_block);
+        }
+
+        Err(ImageError::UnsupportedBlock(n))
+    }
+
+    /// Returns the offset on the image for `pos` within `block` or [`None`] if it is too large.
+    fn offset(&self, block: u64, pos: u64) -> Option<u64> {
+        block.checked_mul(self.block_size)?.checked_add(pos)
+    }
+
+    /// Invoke `f` with inode, type and name for each entry in `dir` until `f` return `false`.
+    ///
+    /// The type is the raw type of PFS.
+    fn entries(
+        &self,
+        dir: &Inode,
+        mut f: impl FnMut(u64, u32, &str) -> bool,
+    ) -> Result<(), ImageError> {
+        let data = FileData::new(self, dir);
+        let mut block = vec![0u8; self.block_size as usize];
+        let mut off = 0;
+
+        while off < dir.size() {
+            let len = (dir.size() - off).min(self.block_size) as usize;
+            let block = &mut block[..len];
+
+            data.read(off, block)?;
+            off += self.block_size;
+
+            // Parse entries. The entry with zero size indicate the end of the block.
+            let mut pos = 0;
+
+            while let Some(hdr) = block.get(pos..(pos + 16)) {
+                let u32 = |o: usize| u32::from_le_bytes(hdr[o..(o + 4)].try_into().unwrap());
+                let ino = u32(0x0);
+                let ty = u32(0x4);
+                let namelen = u32(0x8) as usize;
+                let entsize = u32(0xC) as usize;
+
+                if entsize == 0 {
+                    break;
+                } else if 16 + namelen > entsize {
+                    return Err(ImageError::InvalidDirent);
+                }
+
+                let name = block
+                    .get((pos + 16)..(pos + 16 + namelen))
+                    .and_then(|v| core::str::from_utf8(v).ok())
+                    .ok_or(ImageError::InvalidDirent)?;
+
+                if !f(ino.into(), ty, name) {
+                    return Ok(());
+                }
+
+                pos += entsize;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns inode of `name` in `dir`.
+    fn find(&self, dir: &Inode, name: &str) -> Result<Option<u64>, ImageError> {
+        let mut found = None;
+
+        self.entries(dir, |ino, _, n| {
+            if n == name {
+                found = Some(ino);
+                false
+            } else {
+                true
+            }
+        })?;
+
+        Ok(found)
+    }
+
+    /// Returns the vnode for `ino`.
+    fn alloc_vnode(&self, mnt: &Arc<Mount>, ino: u64) -> Result<Arc<Vnode>, ImageError> {
+        mnt.hash_get_or_insert(ino, || {
+            let inode = self.inode(ino)?;
+            let ty = if inode.is_dir() {
+                VnodeType::Directory(ino == self.root)
+            } else if inode.is_link() {
+                VnodeType::Link
+            } else {
+                VnodeType::File
+            };
+
+            Ok(Vnode::new(mnt, ty, "pfs", PfsVnode::new(ino)))
+        })
+    }
+}
+
+impl Filesystem for PfsFs {
+    fn root(&self, mnt: &Arc<Mount>, _: &Thread) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        self.alloc_vnode(mnt, self.root)
+            .map_err(|e| Box::new(e) as Box<dyn Errno>)
+    }
+}
+
+/// Implementation of [`Image`] for the data of an inode.
+struct FileData<'a> {
+    fs: &'a PfsFs,
+    inode: &'a Inode,
+}
+
+impl<'a> FileData<'a> {
+    fn new(fs: &'a PfsFs, inode: &'a Inode) -> Self {
+        Self { fs, inode }
+    }
+}
+
+impl Image for FileData<'_> {
+    fn len(&self) -> u64 {
+        self.inode.size()
+    }
+
+    fn read(&self, mut off: u64, mut buf: &mut [u8]) -> Result<(), ImageError> {
+        let bs = self.fs.block_size;
+
+        if off
+            .checked_add(buf.len() as u64)
+            .is_none_or(|v| v > self.inode.size())
+        {
+            return Err(ImageError::UnexpectedEof);
+        }
+
+        while !buf.is_empty() {
+            let i = off / bs;
+            let block = self.fs.block(self.inode, i)?;
+            let pos = off % bs;
+            let n = buf.len().min((bs - pos) as usize);
+            let addr = self
+                .fs
+                .offset(block, pos)
+                .ok_or(ImageError::UnsupportedBlock(i))?;
+
+            self.fs.image.read(addr, &mut buf[..n])?;
+
+            buf = &mut buf[n..];
+            off += n as u64;
+        }
+
+        Ok(())
+    }
+}
+
+/// Represents an error when [`PfsFs::mount()`] fails.
+#[derive(Debug)]
+enum MountError {
+    NoFrom,
+    MountFailed(HostError),
+    NotImage,
+    ReadImageFailed(ImageError),
+    InvalidImage,
+    Encrypted,
+    NoRoot,
+}
+
+impl Error for MountError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::MountFailed(e) => Some(e),
+            Self::ReadImageFailed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Display for MountError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoFrom => f.write_str("no from option"),
+            Self::MountFailed(_) => f.write_str("couldn't mount the image on the host"),
+            Self::NotImage => f.write_str("the volume is not an image"),
+            Self::ReadImageFailed(_) => f.write_str("couldn't read the image"),
+            Self::InvalidImage => f.write_str("the image is not a valid PFS"),
+            Self::Encrypted => f.write_str("encrypted PFS is not supported"),
+            Self::NoRoot => f.write_str("no uroot on the image"),
+        }
+    }
+}
+
+impl Errno for MountError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NoFrom | Self::NotImage => EINVAL,
+            Self::MountFailed(e) => e.errno(),
+            Self::ReadImageFailed(e) => e.errno(),
+            Self::InvalidImage | Self::NoRoot => EFTYPE,
+            Self::Encrypted => EOPNOTSUPP,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errno::EFTYPE;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn superblock() {
+        let fs = PfsFs::new(Box::new(image(0x1000, 0))).unwrap();
+
+        assert_eq!(fs.block_size, 0x1000);
+        assert_eq!(fs.ptr_size, 4);
+        assert_eq!(fs.inode_size, 0xA8);
+        assert_eq!(fs.ninodes, 4);
+        assert_eq!(fs.root, 1);
+
+        // 64-bit pointers with signature.
+        let fs = PfsFs::new(Box::new(image(0x1000, PfsFs::MODE_SIGNED | PfsFs::MODE_64))).unwrap();
+
+        assert_eq!(fs.ptr_size, 40);
+        assert_eq!(fs.inode_size, 0x64 + 17 * 40);
+
+        // Invalid images.
+        let mut img = image(0x1000, 0);
+
+        img.0[0x08] = 0;
+
+        assert_eq!(PfsFs::new(Box::new(img)).err().unwrap().errno(), EFTYPE);
+        assert!(PfsFs::new(Box::new(image(0x1000, PfsFs::MODE_ENCRYPTED))).is_err());
+        assert!(PfsFs::new(Box::new(image(0x1800, 0))).is_err());
+        assert!(PfsFs::new(Box::new(image(0x80, 0))).is_err());
+        assert!(PfsFs::new(Box::new(image(0x20000, 0))).is_err());
+    }
+
+    #[test]
+    fn inode() {
+        let fs = PfsFs::new(Box::new(image(0x1000, 0))).unwrap();
+        let root = fs.inode(1).unwrap();
+
+        assert!(root.is_dir());
+        assert_eq!(root.nlink(), 2);
+        assert_eq!(root.size(), 0x1000);
+        assert_eq!(root.direct()[0], 2);
+
+        let file = fs.inode(2).unwrap();
+
+        assert!(!file.is_dir());
+        assert_eq!(file.mode(), 0o100644);
+        assert_eq!(file.size(), 5);
+        assert_eq!(file.direct()[0], 3);
+
+        assert!(matches!(fs.inode(4), Err(ImageError::InvalidInode(4))));
+    }
+
+    #[test]
+    fn dirent() {
+        let fs = PfsFs::new(Box::new(image(0x1000, 0))).unwrap();
+        let root = fs.inode(1).unwrap();
+        let mut entries = Vec::new();
+
+        fs.entries(&root, |ino, ty, name| {
+            entries.push((ino, ty, name.to_string()));
+            true
+        })
+        .unwrap();
+
+        assert_eq!(
+            entries,
+            [(1, 3, String::from(".")), (2, 2, String::from("uroot"))]
+        );
+        assert_eq!(fs.find(&root, "uroot").unwrap(), Some(2));
+        assert_eq!(fs.find(&root, "foo").unwrap(), None);
+
+        // Read the file.
+        let file = fs.inode(2).unwrap();
+        let mut buf = [0u8; 5];
+
+        FileData::new(&fs, &file).read(0, &mut buf).unwrap();
+
+        assert_eq!(&buf, b"hello");
+
+        // Name longer than the entry.
+        let mut img = image(0x1000, 0);
+
+        img.0[0x2008..0x200C].copy_from_slice(&100u32.to_le_bytes());
+
+        let fs = PfsFs::new(Box::new(img)).unwrap();
+        let root = fs.inode(1).unwrap();
+
+        assert!(matches!(
+            fs.entries(&root, |_, _, _| true),
+            Err(ImageError::InvalidDirent)
+        ));
+    }
+
+    #[test]
+    fn huge_block() {
+        let mut img = image(0x1000, PfsFs::MODE_64);
+
+        // Point the first block of the file to a block that overflow the offset.
+        let off = 0x1000 + 2 * (Inode::HEADER_SIZE + 17 * 8) + Inode::HEADER_SIZE;
+
+        img.0[off..(off + 8)].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        let fs = PfsFs::new(Box::new(img)).unwrap();
+        let file = fs.inode(2).unwrap();
+        let mut buf = [0u8; 5];
+
+        assert!(matches!(
+            FileData::new(&fs, &file).read(0, &mut buf),
+            Err(ImageError::UnsupportedBlock(0))
+        ));
+    }
+
+    /// Build an image with `block_size` and `mode`. The superroot (#1) is on block 2 and contains
+    /// a file named "uroot" (#2) with "hello" on block 3.
+    fn image(block_size: u32, mode: u16) -> MemImage {
+        let bs = (block_size as usize).max(0x1000);
+        let mut data = vec![0u8; bs * 4];
+
+        // Superblock.
+        data[0x00..0x08].copy_from_slice(&PfsFs::VERSION.to_le_bytes());
+        data[0x08..0x10].copy_from_slice(&PfsFs::MAGIC.to_le_bytes());
+        data[0x1C..0x1E].copy_from_slice(&mode.to_le_bytes());
+        data[0x20..0x24].copy_from_slice(&block_size.to_le_bytes());
+        data[0x30..0x38].copy_from_slice(&4u64.to_le_bytes());
+        data[0x48..0x50].copy_from_slice(&1u64.to_le_bytes());
+
+        // Inodes.
+        let ptr_size = if mode & PfsFs::MODE_64 != 0 { 8 } else { 4 };
+        let inode_size = Inode::HEADER_SIZE + 17 * ptr_size;
+        let mut inode = |ino: usize, mode: u16, nlink: u16, size: u64, block: u64| {
+            let data = &mut data[(bs + ino * inode_size)..][..inode_size];
+
+            data[0x00..0x02].copy_from_slice(&mode.to_le_bytes());
+            data[0x02..0x04].copy_from_slice(&nlink.to_le_bytes());
+            data[0x08..0x10].copy_from_slice(&size.to_le_bytes());
+            data[Inode::HEADER_SIZE..][..ptr_size]
+                .copy_from_slice(&block.to_le_bytes()[..ptr_size]);
+        };
+
+        inode(1, 0o040755, 2, bs as u64, 2);
+        inode(2, 0o100644, 1, 5, 3);
+
+        // Directory entries.
+        let mut off = bs * 2;
+
+        for (ino, ty, name) in [(1u32, 3u32, "."), (2, 2, "uroot")] {
+            let size = (16 + name.len()).next_multiple_of(8);
+            let ent = &mut data[off..(off + size)];
+
+            ent[0x0..0x4].copy_from_slice(&ino.to_le_bytes());
+            ent[0x4..0x8].copy_from_slice(&ty.to_le_bytes());
+            ent[0x8..0xC].copy_from_slice(&(name.len() as u32).to_le_bytes());
+            ent[0xC..0x10].copy_from_slice(&(size as u32).to_le_bytes());
+            ent[16..(16 + name.len())].copy_from_slice(name.as_bytes());
+
+            off += size;
+        }
+
+        // File data.
+        data[(bs * 3)..][..5].copy_from_slice(b"hello");
+
+        MemImage(data)
+    }
+
+    /// Implementation of [`Image`] on the memory.
+    struct MemImage(Vec<u8>);
+
+    impl Image for MemImage {
+        fn len(&self) -> u64 {
+            self.0.len() as u64
+        }
+
+        fn read(&self, off: u64, buf: &mut [u8]) -> Result<(), ImageError> {
+            let src = usize::try_from(off)
+                .ok()
+                .and_then(|off| self.0.get(off..)?.get(..buf.len()))
+                .ok_or(ImageError::UnexpectedEof)?;
+
+            buf.copy_from_slice(src);
+
+            Ok(())
+        }
+    }
+}
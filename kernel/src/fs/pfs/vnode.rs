@@ -0,0 +1,227 @@
+use super::image::{Image, ImageError, Pfsc};
+use super::{FileData, PfsFs};
+use crate::errno::{Errno, EINVAL, ENOENT, ENOTDIR};
+use crate::fs::{DirEntry, DirType, Vnode, VnodeAttrs, VnodeBackend, NODEV};
+use crate::proc::Thread;
+use crate::time::TimeSpec;
+use crate::ucred::{Gid, Uid};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+
+/// Implementation of [`VnodeBackend`] for PFS.
+pub struct PfsVnode {
+    ino: u64,
+}
+
+impl PfsVnode {
+    pub fn new(ino: u64) -> Self {
+        Self { ino }
+    }
+
+    fn fs(vn: &Vnode) -> &PfsFs {
+        vn.mount().fs().unwrap()
+    }
+}
+
+impl VnodeBackend for PfsVnode {
+    fn getattr(&self, vn: &Arc<Vnode>, _: &Thread) -> Result<VnodeAttrs, Box<dyn Errno>> {
+        let fs = Self::fs(vn);
+        let inode = fs.inode(self.ino).map_err(err)?;
+        let time = |(sec, nsec): (i64, u32)| TimeSpec {
+            sec,
+            nsec: nsec.into(),
+        };
+
+        Ok(VnodeAttrs {
+            mode: inode.mode() & 0o7777,
+            nlink: inode.nlink(),
+            uid: Uid::new(inode.uid() as i32)
+                .ok_or_else(|| err(ImageError::InvalidInode(self.ino)))?,
+            gid: Gid::new(inode.gid() as i32)
+                .ok_or_else(|| err(ImageError::InvalidInode(self.ino)))?,
+            fsid: vn.mount().id()[0],
+            id: self.ino,
+            size: inode.file_size(),
+            blksize: fs.block_size as u32,
+            atime: time(inode.atime()),
+            mtime: time(inode.mtime()),
+            ctime: time(inode.ctime()),
+            birthtime: time(inode.birthtime()),
+            gen: 0,
+            flags: 0,
+            rdev: NODEV,
+            bytes: u64::from(inode.blocks()) * fs.block_size,
+        })
+    }
+
+    fn lookup(
+        &self,
+        vn: &Arc<Vnode>,
+        _: &Thread,
+        name: &str,
+    ) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        // The parent of the root directory is the superroot, which is not visible to the user.
+        if name == ".." && vn.is_mount_root() {
+            return Ok(vn.clone());
+        }
+
+        let fs = Self::fs(vn);
+        let dir = fs.inode(self.ino).map_err(err)?;
+
+        if !dir.is_dir() {
+            return Err(Box::new(LookupError::NotDirectory));
+        }
+
+        match fs.find(&dir, name).map_err(err)? {
+            Some(ino) => fs.alloc_vnode(vn.mount(), ino).map_err(err),
+            None => Err(Box::new(LookupError::NotFound)),
+        }
+    }
+
+    fn readdir(
+        &self,
+        vn: &Arc<Vnode>,
+        _: &Thread,
+        off: &mut u64,
+        buf: &mut [u8],
+    ) -> Result<usize, Box<dyn Errno>> {
+        let fs = Self::fs(vn);
+        let dir = fs.inode(self.ino).map_err(err)?;
+        let mut index = 0;
+        let mut written = 0;
+        let mut full = false;
+        let mut invalid = false;
+
+        // The offset is the index of the entry, including "." and ".." on the image.
+        fs.entries(&dir, |ino, ty, name| {
+            if index < *off {
+                index += 1;
+                return true;
+            }
+
+            let (ino, ty) = match ty {
+                2 => (ino, DirType::Regular),
+                3 | 4 => (ino, DirType::Directory),
+                5 if vn.is_mount_root() => (self.ino, DirType::Directory),
+                5 => (ino, DirType::Directory),
+                _ => {
+                    invalid = true;
+                    return false;
+                }
+            };
+
+            match DirEntry::new(ino as u32, ty, name).write(&mut buf[written..]) {
+                Some(n) => {
+                    written += n;
+                    index += 1;
+                    *off = index;
+                    true
+                }
+                None => {
+                    full = true;
+                    false
+                }
+            }
+        })
+        .map_err(err)?;
+
+        if invalid {
+            return Err(err(ImageError::InvalidDirent));
+        } else if written == 0 && full {
+            return Err(Box::new(ReadDirError::BufferTooSmall));
+        }
+
+        Ok(written)
+    }
+
+    fn read(
+        &self,
+        vn: &Arc<Vnode>,
+        _: &Thread,
+        off: u64,
+        buf: &mut [u8],
+    ) -> Result<usize, Box<dyn Errno>> {
+        let fs = Self::fs(vn);
+        let inode = fs.inode(self.ino).map_err(err)?;
+        let len = inode.file_size();
+
+        if off >= len {
+            return Ok(0);
+        }
+
+        // Read.
+        let n = buf.len().min((len - off).try_into().unwrap_or(usize::MAX));
+        let buf = &mut buf[..n];
+        let data = FileData::new(fs, &inode);
+
+        if inode.is_compressed() {
+            // TODO: Cache the PFSC header so we don't need to re-read it on every read.
+            Pfsc::open(data)
+                .and_then(|d| d.read(off, buf))
+                .map_err(err)?;
+        } else {
+            data.read(off, buf).map_err(err)?;
+        }
+
+        Ok(n)
+    }
+}
+
+fn err(e: ImageError) -> Box<dyn Errno> {
+    Box::new(e)
+}
+
+/// Represents an error when [`PfsVnode::lookup()`] fails.
+#[derive(Debug)]
+enum LookupError {
+    NotDirectory,
+    NotFound,
+}
+
+impl Error for LookupError {}
+
+impl Display for LookupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotDirectory => f.write_str("current file is not a directory"),
+            Self::NotFound => f.write_str("file not found"),
+        }
+    }
+}
+
+impl Errno for LookupError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NotDirectory => ENOTDIR,
+            Self::NotFound => ENOENT,
+        }
+    }
+}
+
+/// Represents an error when [`PfsVnode::readdir()`] fails.
+#[derive(Debug)]
+enum ReadDirError {
+    BufferTooSmall,
+}
+
+impl Error for ReadDirError {}
+
+impl Display for ReadDirError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BufferTooSmall => f.write_str("the buffer is too small for the entry"),
+        }
+    }
+}
+
+impl Errno for ReadDirError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::BufferTooSmall => EINVAL,
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::time::TimeSpec;
+
+/// Implementation of `stat` structure.
+#[repr(C)]
+#[derive(Default)]
+pub struct Stat {
+    pub dev: u32,            // st_dev
+    pub ino: u32,            // st_ino
+    pub mode: u16,           // st_mode
+    pub nlink: u16,          // st_nlink
+    pub uid: u32,            // st_uid
+    pub gid: u32,            // st_gid
+    pub rdev: u32,           // st_rdev
+    pub atime: TimeSpec,     // st_atim
+    pub mtime: TimeSpec,     // st_mtim
+    pub ctime: TimeSpec,     // st_ctim
+    pub size: i64,           // st_size
+    pub blocks: i64,         // st_blocks
+    pub blksize: u32,        // st_blksize
+    pub flags: u32,          // st_flags
+    pub gen: u32,            // st_gen
+    pub spare: i32,          // st_lspare
+    pub birthtime: TimeSpec, // st_birthtim
+}
+
+impl Stat {
+    pub fn as_bytes(&self) -> &[u8] {
+        // SAFETY: The struct does not have any padding.
+        unsafe { core::slice::from_raw_parts((self as *const Self).cast(), size_of::<Self>()) }
+    }
+}
+
+const _: () = assert!(size_of::<Stat>() == 120);
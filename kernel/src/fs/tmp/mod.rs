@@ -0,0 +1,154 @@
+use self::node::{AllocError, Node, NodeType, Usage};
+use self::vnode::TmpVnode;
+use super::{
+    Filesystem, Fs, FsConfig, Mount, MountFlags, MountOptError, MountOpts, Vnode, VnodeType,
+};
+use crate::config::PAGE_SIZE;
+use crate::errno::Errno;
+use crate::lock::GutexGroup;
+use crate::proc::Thread;
+use crate::ucred::{Gid, Uid};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::ffi::c_int;
+
+mod node;
+mod vnode;
+
+/// Configuration of tmpfs.
+pub static TMPFS: FsConfig = FsConfig {
+    name: "tmpfs",
+    ty: 0x87,
+    mount: TmpFs::mount,
+};
+
+/// Implementation of `tmpfs_mount` structure.
+pub struct TmpFs {
+    gg: Arc<GutexGroup>, // tm_allnode_lock
+    usage: Arc<Usage>,
+    root: Arc<Node>, // tm_root
+}
+
+impl TmpFs {
+    /// See `tmpfs_mount` on the PS4 for a reference.
+    fn mount(
+        _: &Fs,
+        parent: Option<&Arc<Vnode>>,
+        opts: &mut MountOpts,
+        flags: &mut MountFlags,
+        td: &Thread,
+    ) -> Result<Box<dyn Filesystem>, Box<dyn Errno>> {
+        // Get the default attributes of the root directory from the mount point.
+        let (mut uid, mut gid, mut mode) = match parent {
+            Some(vn) => {
+                let attrs = vn.getattr(td)?;
+
+                (attrs.uid, attrs.gid, attrs.mode)
+            }
+            None => (Uid::ROOT, Gid::ROOT, 0o755),
+        };
+
+        // Only root can override the attributes of the root directory.
+        let err = |e: MountOptError| Box::new(e) as Box<dyn Errno>;
+        let invalid = |n: &str| err(MountOptError::InvalidValue(n.into()));
+
+        if td.cred_mut().real_uid() == Uid::ROOT {
+            if let Some(v) = opts.remove_int("gid", 10).map_err(err)? {
+                gid = c_int::try_from(v)
+                    .ok()
+                    .and_then(Gid::new)
+                    .ok_or_else(|| invalid("gid"))?;
+            }
+
+            if let Some(v) = opts.remove_int("uid", 10).map_err(err)? {
+                uid = c_int::try_from(v)
+                    .ok()
+                    .and_then(Uid::new)
+                    .ok_or_else(|| invalid("uid"))?;
+            }
+
+            if let Some(v) = opts.remove_int("mode", 8).map_err(err)? {
+                mode = u16::try_from(v)
+                    .ok()
+                    .filter(|&v| v <= 0o7777)
+                    .ok_or_else(|| invalid("mode"))?;
+            }
+        }
+
+        // Get the limits.
+        let max_nodes = opts.remove_int("inodes", 10).map_err(err)?.unwrap_or(0);
+        let max_size = opts.remove_size("size").map_err(err)?.unwrap_or(0);
+        let max_file_size = opts.remove_size("maxfilesize").map_err(err)?.unwrap_or(0);
+        let page = PAGE_SIZE.get() as u64;
+        let max_pages = if max_size < page || max_size > (i64::MAX as u64) - page {
+            u64::MAX
+        } else {
+            max_size.div_ceil(page)
+        };
+
+        let max_nodes = if max_nodes <= 3 {
+            max_pages.saturating_add(3).min(c_int::MAX as u64)
+        } else {
+            max_nodes as u64
+        };
+
+        let max_file_size = match max_file_size {
+            0 => i64::MAX as u64,
+            v => v,
+        };
+
+        // Allocate the root directory.
+        let gg = GutexGroup::new();
+        let usage = Arc::new(Usage::new(&gg, max_pages, max_nodes, max_file_size));
+        let ty = NodeType::Directory(None);
+        let root = Node::new(&gg, &usage, ty, (uid, gid, mode))
+            .map_err(|e| Box::new(e) as Box<dyn Errno>)?;
+
+        // The root directory has no entry so we need to add its link manually.
+        *root.links_mut() += 1;
+
+        *flags |= MountFlags::MNT_LOCAL;
+
+        Ok(Box::new(Self {
+            gg,
+            usage,
+            root: Arc::new(root),
+        }))
+    }
+
+    /// See `tmpfs_alloc_node` on the PS4 for a reference.
+    fn alloc_node(&self, ty: NodeType, perm: (Uid, Gid, u16)) -> Result<Arc<Node>, AllocError> {
+        Node::new(&self.gg, &self.usage, ty, perm).map(Arc::new)
+    }
+
+    /// See `tmpfs_alloc_vp` on the PS4 for a reference.
+    fn alloc_vnode(&self, mnt: &Arc<Mount>, node: &Arc<Node>) -> Arc<Vnode> {
+        // Check for active vnode.
+        let mut current = node.vnode_mut();
+
+        if let Some(v) = current.as_ref().and_then(|v| v.upgrade()) {
+            return v;
+        }
+
+        // Create a new vnode.
+        let ty = if node.is_directory() {
+            VnodeType::Directory(Arc::ptr_eq(node, &self.root))
+        } else if node.target().is_some() {
+            VnodeType::Link
+        } else {
+            VnodeType::File
+        };
+
+        let vn = Vnode::new(mnt, ty, "tmpfs", TmpVnode::new(node.clone()));
+
+        *current = Some(Arc::downgrade(&vn));
+
+        vn
+    }
+}
+
+impl Filesystem for TmpFs {
+    fn root(&self, mnt: &Arc<Mount>, _: &Thread) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        Ok(self.alloc_vnode(mnt, &self.root))
+    }
+}
@@ -0,0 +1,556 @@
+use crate::config::PAGE_SIZE;
+use crate::errno::{Errno, EFBIG, ENOSPC};
+use crate::fs::{DirType, Vnode};
+use crate::lock::{Gutex, GutexGroup, GutexWrite};
+use crate::time::TimeSpec;
+use crate::ucred::{Gid, Uid};
+use alloc::boxed::Box;
+use alloc::sync::{Arc, Weak};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+
+/// Resource usage of a tmpfs mount.
+///
+/// This is shared with all of [`Node`] so it can release the resources when it is destroyed.
+pub struct Usage {
+    max_pages: u64,     // tm_pages_max
+    max_nodes: u64,     // tm_nodes_max
+    max_file_size: u64, // tm_maxfilesize
+    pages: Gutex<u64>,  // tm_pages_used
+    nodes: Gutex<u64>,  // tm_nodes_inuse
+    last_id: Gutex<u64>,
+}
+
+impl Usage {
+    pub fn new(gg: &Arc<GutexGroup>, max_pages: u64, max_nodes: u64, max_file_size: u64) -> Self {
+        Self {
+            max_pages,
+            max_nodes,
+            max_file_size,
+            pages: gg.clone().spawn(0),
+            nodes: gg.clone().spawn(0),
+            last_id: gg.clone().spawn(1),
+        }
+    }
+
+    fn alloc_node(&self) -> Result<u64, AllocError> {
+        let mut nodes = self.nodes.write();
+
+        if *nodes >= self.max_nodes {
+            return Err(AllocError::TooManyNodes);
+        }
+
+        // TODO: Reuse the ID of the destroyed node like tm_ino_unr on the PS4.
+        let mut id = self.last_id.write();
+
+        *nodes += 1;
+        *id += 1;
+
+        Ok(*id)
+    }
+
+    fn alloc_page(&self) -> Result<Box<[u8]>, AllocError> {
+        let mut pages = self.pages.write();
+
+        if *pages >= self.max_pages {
+            return Err(AllocError::NoSpace);
+        }
+
+        *pages += 1;
+
+        Ok(vec![0; PAGE_SIZE.get()].into_boxed_slice())
+    }
+
+    fn free_pages(&self, n: usize) {
+        *self.pages.write() -= n as u64;
+    }
+}
+
+/// Implementation of `tmpfs_node` structure.
+pub struct Node {
+    usage: Arc<Usage>,
+    id: u64,                           // tn_id
+    data: NodeData,                    // tn_type + tn_spec
+    uid: Gutex<Uid>,                   // tn_uid
+    gid: Gutex<Gid>,                   // tn_gid
+    mode: Gutex<u16>,                  // tn_mode
+    links: Gutex<u16>,                 // tn_links
+    times: Gutex<NodeTimes>,           // tn_atime + tn_mtime + tn_ctime + tn_birthtime
+    vnode: Gutex<Option<Weak<Vnode>>>, // tn_vnode
+}
+
+impl Node {
+    /// Maximum number of links to a node (AKA `LINK_MAX`).
+    pub const LINK_MAX: u16 = 32767;
+
+    /// All nodes on the same mount must use the same `gg` as [`Usage`]. Each new directory will
+    /// have 1 link for `.` and the other types will have no links. The link for the entry itself
+    /// will be added by [`Directory::attach()`].
+    ///
+    /// See `tmpfs_alloc_node` on the PS4 for a reference.
+    pub fn new(
+        gg: &Arc<GutexGroup>,
+        usage: &Arc<Usage>,
+        ty: NodeType,
+        (uid, gid, mode): (Uid, Gid, u16),
+    ) -> Result<Self, AllocError> {
+        let id = usage.alloc_node()?;
+        let (data, links) = match ty {
+            NodeType::Directory(parent) => {
+                let dir = Directory {
+                    parent,
+                    entries: Vec::new(),
+                    last_cookie: Directory::FIRST_COOKIE,
+                };
+
+                (NodeData::Directory(gg.clone().spawn(dir)), 1)
+            }
+            NodeType::File => (NodeData::File(gg.clone().spawn(Pages::default())), 0),
+            NodeType::Link(target) => (NodeData::Link(target), 0),
+        };
+
+        let now = TimeSpec::now();
+        let times = NodeTimes {
+            atime: now,
+            mtime: now,
+            ctime: now,
+            birthtime: now,
+        };
+
+        Ok(Self {
+            usage: usage.clone(),
+            id,
+            data,
+            uid: gg.clone().spawn(uid),
+            gid: gg.clone().spawn(gid),
+            mode: gg.clone().spawn(mode),
+            links: gg.clone().spawn(links),
+            times: gg.clone().spawn(times),
+            vnode: gg.clone().spawn(None),
+        })
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn is_directory(&self) -> bool {
+        matches!(self.data, NodeData::Directory(_))
+    }
+
+    pub fn dir_type(&self) -> DirType {
+        match self.data {
+            NodeData::Directory(_) => DirType::Directory,
+            NodeData::File(_) => DirType::Regular,
+            NodeData::Link(_) => DirType::Link,
+        }
+    }
+
+    /// Returns [`None`] if this node is not a directory.
+    pub fn dir_mut(&self) -> Option<GutexWrite<'_, Directory>> {
+        match &self.data {
+            NodeData::Directory(v) => Some(v.write()),
+            _ => None,
+        }
+    }
+
+    /// Returns [`None`] if this node is not a regular file.
+    pub fn pages_mut(&self) -> Option<GutexWrite<'_, Pages>> {
+        match &self.data {
+            NodeData::File(v) => Some(v.write()),
+            _ => None,
+        }
+    }
+
+    /// Returns [`None`] if this node is not a symbolic link.
+    pub fn target(&self) -> Option<&str> {
+        match &self.data {
+            NodeData::Link(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the size of the content and the number of bytes that was allocated for it.
+    pub fn size(&self) -> (u64, u64) {
+        match &self.data {
+            NodeData::Directory(v) => {
+                let len = (v.write().entries.len() * size_of::<Dirent>()) as u64;
+
+                (len, len)
+            }
+            NodeData::File(v) => {
+                let pages = v.write();
+                let used = pages.pages.iter().filter(|p| p.is_some()).count();
+
+                (pages.size, (used * PAGE_SIZE.get()) as u64)
+            }
+            NodeData::Link(v) => (v.len() as u64, v.len() as u64),
+        }
+    }
+
+    /// Returns the owner, group and mode as a single snapshot.
+    pub fn perm(&self) -> (Uid, Gid, u16) {
+        let uid = self.uid.write();
+        let gid = self.gid.write();
+        let mode = self.mode.write();
+
+        (*uid, *gid, *mode)
+    }
+
+    pub fn links_mut(&self) -> GutexWrite<'_, u16> {
+        self.links.write()
+    }
+
+    pub fn times_mut(&self) -> GutexWrite<'_, NodeTimes> {
+        self.times.write()
+    }
+
+    pub fn vnode_mut(&self) -> GutexWrite<'_, Option<Weak<Vnode>>> {
+        self.vnode.write()
+    }
+
+    /// Update the modification and status change time to the current time.
+    pub fn touch(&self) {
+        let now = TimeSpec::now();
+        let mut times = self.times.write();
+
+        times.mtime = now;
+        times.ctime = now;
+    }
+
+    /// Read the content of this file at `off`. Returns the number of bytes read.
+    ///
+    /// # Panics
+    /// If this node is not a regular file.
+    pub fn read(&self, off: u64, buf: &mut [u8]) -> usize {
+        let pages = self.pages_mut().unwrap();
+
+        if off >= pages.size {
+            return 0;
+        }
+
+        // Copy the data page by page. A hole will be read as zeros.
+        let len = buf.len().min((pages.size - off) as usize);
+        let mut done = 0;
+
+        while done < len {
+            let pos = off + done as u64;
+            let (i, o) = Pages::locate(pos);
+            let n = (PAGE_SIZE.get() - o).min(len - done);
+            let dst = &mut buf[done..(done + n)];
+
+            match pages.pages.get(i).and_then(|p| p.as_ref()) {
+                Some(p) => dst.copy_from_slice(&p[o..(o + n)]),
+                None => dst.fill(0),
+            }
+
+            done += n;
+        }
+
+        len
+    }
+
+    /// Write `buf` to this file at `off`. Returns the number of bytes written.
+    ///
+    /// # Panics
+    /// If this node is not a regular file.
+    pub fn write(&self, off: u64, buf: &[u8]) -> Result<usize, WriteError> {
+        off.checked_add(buf.len() as u64)
+            .filter(|&v| v <= self.usage.max_file_size)
+            .ok_or(WriteError::TooLarge)?;
+
+        let mut pages = self.pages_mut().unwrap();
+        let mut done = 0;
+
+        while done < buf.len() {
+            let pos = off + done as u64;
+            let (i, o) = Pages::locate(pos);
+            let n = (PAGE_SIZE.get() - o).min(buf.len() - done);
+
+            if i >= pages.pages.len() {
+                pages.pages.resize_with(i + 1, || None);
+            }
+
+            // Allocate the page.
+            let page = match &mut pages.pages[i] {
+                Some(v) => v,
+                v => match self.usage.alloc_page() {
+                    Ok(p) => v.insert(p),
+                    Err(e) if done == 0 => return Err(WriteError::AllocFailed(e)),
+                    Err(_) => break,
+                },
+            };
+
+            page[o..(o + n)].copy_from_slice(&buf[done..(done + n)]);
+            done += n;
+        }
+
+        pages.size = pages.size.max(off + done as u64);
+
+        drop(pages);
+
+        if done != 0 {
+            self.touch();
+        }
+
+        Ok(done)
+    }
+
+    /// Change the size of this file. The new space will be a hole.
+    ///
+    /// See `tmpfs_reg_resize` on the PS4 for a reference.
+    ///
+    /// # Panics
+    /// If this node is not a regular file.
+    pub fn resize(&self, size: u64) -> Result<(), WriteError> {
+        if size > self.usage.max_file_size {
+            return Err(WriteError::TooLarge);
+        }
+
+        let mut pages = self.pages_mut().unwrap();
+
+        if size < pages.size {
+            // Release the pages after the new size.
+            let (i, o) = Pages::locate(size);
+            let keep = if o == 0 { i } else { i + 1 };
+            let keep = keep.min(pages.pages.len());
+            let freed = pages.pages.drain(keep..).filter(|p| p.is_some()).count();
+
+            self.usage.free_pages(freed);
+
+            // Zero the tail of the last page so it will be read as zeros when the file grow.
+            if o != 0 {
+                if let Some(p) = pages.pages.get_mut(i).and_then(|p| p.as_mut()) {
+                    p[o..].fill(0);
+                }
+            }
+        }
+
+        pages.size = size;
+
+        drop(pages);
+
+        self.touch();
+
+        Ok(())
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        // See tmpfs_free_node on the PS4 for a reference.
+        if let NodeData::File(v) = &mut self.data {
+            let used = v.get_mut().pages.iter().filter(|p| p.is_some()).count();
+
+            self.usage.free_pages(used);
+        }
+
+        *self.usage.nodes.write() -= 1;
+    }
+}
+
+/// Type of [`Node`] to create.
+pub enum NodeType {
+    /// Directory with the parent (AKA `VDIR`). The parent will be [`None`] for the root directory.
+    Directory(Option<Weak<Node>>),
+    /// Regular file (AKA `VREG`).
+    File,
+    /// Symbolic link with the target (AKA `VLNK`).
+    Link(Box<str>),
+}
+
+/// Content of [`Node`].
+enum NodeData {
+    Directory(Gutex<Directory>), // tn_dir
+    File(Gutex<Pages>),          // tn_reg
+    Link(Box<str>),              // tn_link
+}
+
+/// Content of a directory.
+pub struct Directory {
+    parent: Option<Weak<Node>>, // tn_parent
+    entries: Vec<Dirent>,       // tn_dirhead
+    last_cookie: u64,
+}
+
+impl Directory {
+    /// Cookie for `.`.
+    pub const DOT_COOKIE: u64 = 0;
+
+    /// Cookie for `..`.
+    pub const DOTDOT_COOKIE: u64 = 1;
+
+    /// Cookie for the first entry.
+    pub const FIRST_COOKIE: u64 = 2;
+
+    /// Returns [`None`] if this is the root directory or the directory has been removed.
+    pub fn parent(&self) -> Option<Arc<Node>> {
+        self.parent.as_ref().and_then(|v| v.upgrade())
+    }
+
+    pub fn set_parent(&mut self, v: Option<Weak<Node>>) {
+        self.parent = v;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// See `tmpfs_dir_lookup` on the PS4 for a reference.
+    pub fn find(&self, name: &str) -> Option<&Arc<Node>> {
+        self.entries
+            .iter()
+            .find(|e| *e.name == *name)
+            .map(|e| &e.node)
+    }
+
+    /// Returns the entries starting from `cookie`.
+    pub fn entries_from(&self, cookie: u64) -> impl Iterator<Item = &Dirent> {
+        let i = self.entries.partition_point(|e| e.cookie < cookie);
+
+        self.entries[i..].iter()
+    }
+
+    /// The caller is responsible for making sure `name` does not exists.
+    ///
+    /// See `tmpfs_dir_attach` on the PS4 for a reference.
+    pub fn attach(&mut self, name: &str, node: Arc<Node>) {
+        let cookie = self.last_cookie;
+
+        *node.links_mut() += 1;
+
+        self.last_cookie += 1;
+        self.entries.push(Dirent {
+            cookie,
+            name: name.into(),
+            node,
+        });
+    }
+
+    /// Returns [`None`] if `name` does not exists or it is not `node`.
+    ///
+    /// See `tmpfs_dir_detach` on the PS4 for a reference.
+    pub fn detach(&mut self, name: &str, node: &Arc<Node>) -> Option<Arc<Node>> {
+        let i = self
+            .entries
+            .iter()
+            .position(|e| *e.name == *name && Arc::ptr_eq(&e.node, node))?;
+
+        let node = self.entries.remove(i).node;
+
+        *node.links_mut() -= 1;
+
+        Some(node)
+    }
+}
+
+/// Implementation of `tmpfs_dirent` structure.
+pub struct Dirent {
+    cookie: u64,     // td_cookie
+    name: Box<str>,  // td_name + td_namelen
+    node: Arc<Node>, // td_node
+}
+
+impl Dirent {
+    pub fn cookie(&self) -> u64 {
+        self.cookie
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn node(&self) -> &Arc<Node> {
+        &self.node
+    }
+}
+
+/// Content of a regular file.
+///
+/// Each page will be allocated when it is written for the first time so the file can be sparse.
+#[derive(Default)]
+pub struct Pages {
+    pages: Vec<Option<Box<[u8]>>>, // tn_aobj
+    size: u64,                     // tn_size
+}
+
+impl Pages {
+    /// Returns the index of the page and the offset within the page for `off`.
+    fn locate(off: u64) -> (usize, usize) {
+        let size = PAGE_SIZE.get() as u64;
+
+        ((off / size) as usize, (off % size) as usize)
+    }
+}
+
+/// Timestamps of [`Node`].
+pub struct NodeTimes {
+    pub atime: TimeSpec,     // tn_atime
+    pub mtime: TimeSpec,     // tn_mtime
+    pub ctime: TimeSpec,     // tn_ctime
+    pub birthtime: TimeSpec, // tn_birthtime
+}
+
+/// Represents an error when [`Node`] or a page fails to allocate.
+#[derive(Debug)]
+pub enum AllocError {
+    TooManyNodes,
+    NoSpace,
+}
+
+impl Error for AllocError {}
+
+impl Display for AllocError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::TooManyNodes => f.write_str("maximum number of nodes has been reached"),
+            Self::NoSpace => f.write_str("maximum number of pages has been reached"),
+        }
+    }
+}
+
+impl Errno for AllocError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::TooManyNodes | Self::NoSpace => ENOSPC,
+        }
+    }
+}
+
+/// Represents an error when [`Node::write()`] or [`Node::resize()`] fails.
+#[derive(Debug)]
+pub enum WriteError {
+    TooLarge,
+    AllocFailed(AllocError),
+}
+
+impl Error for WriteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::TooLarge => None,
+            Self::AllocFailed(e) => Some(e),
+        }
+    }
+}
+
+impl Display for WriteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::TooLarge => f.write_str("the file will exceed the maximum size"),
+            Self::AllocFailed(_) => f.write_str("couldn't allocate a page"),
+        }
+    }
+}
+
+impl Errno for WriteError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::TooLarge => EFBIG,
+            Self::AllocFailed(e) => e.errno(),
+        }
+    }
+}
@@ -0,0 +1,745 @@
+use super::node::{Directory, Node, NodeType};
+use super::TmpFs;
+use crate::config::PAGE_SIZE;
+use crate::errno::{Errno, EINVAL, EISDIR, EMLINK, ENOENT, ENOTDIR, ENOTEMPTY};
+use crate::fs::{
+    Access, DirEntry, DirType, MountFlags, Vnode, VnodeAttrs, VnodeBackend, VnodeSetAttrs,
+    VnodeType, NODEV,
+};
+use crate::proc::Thread;
+use crate::time::TimeSpec;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+
+/// Implementation of [`VnodeBackend`] for tmpfs.
+pub struct TmpVnode {
+    node: Arc<Node>,
+}
+
+impl TmpVnode {
+    pub fn new(node: Arc<Node>) -> Self {
+        Self { node }
+    }
+
+    fn fs(vn: &Vnode) -> &TmpFs {
+        vn.mount().fs().unwrap()
+    }
+
+    fn node(vn: &Vnode) -> &Arc<Node> {
+        &vn.backend::<Self>().unwrap().node
+    }
+
+    /// See `tmpfs_alloc_file` on the PS4 for a reference.
+    fn alloc_file(
+        &self,
+        dir: &Arc<Vnode>,
+        td: &Thread,
+        name: &str,
+        ty: NodeType,
+        mode: u16,
+    ) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        let fs = Self::fs(dir);
+        let is_dir = matches!(ty, NodeType::Directory(_));
+        let Some(mut ents) = self.node.dir_mut() else {
+            return Err(Box::new(CreateError::NotDirectory));
+        };
+
+        if is_dir && *self.node.links_mut() >= Node::LINK_MAX {
+            return Err(Box::new(CreateError::TooManyLinks));
+        }
+
+        // The new file will be owned by the caller with the same group as the directory.
+        let uid = td.cred_mut().effective_uid();
+        let (_, gid, _) = self.node.perm();
+        let node = fs
+            .alloc_node(ty, (uid, gid, mode))
+            .map_err(|e| Box::new(CreateError::AllocFailed(e)) as Box<dyn Errno>)?;
+
+        ents.attach(name, node.clone());
+
+        if is_dir {
+            *self.node.links_mut() += 1;
+        }
+
+        drop(ents);
+
+        self.node.touch();
+
+        Ok(fs.alloc_vnode(dir.mount(), &node))
+    }
+
+    /// Update the access time unless the filesystem was mounted with `noatime`.
+    fn accessed(&self, vn: &Vnode) {
+        if !vn.mount().flags().has(MountFlags::MNT_NOATIME) {
+            self.node.times_mut().atime = TimeSpec::now();
+        }
+    }
+}
+
+impl VnodeBackend for TmpVnode {
+    /// See `tmpfs_getattr` on the PS4 for a reference.
+    fn getattr(&self, vn: &Arc<Vnode>, _: &Thread) -> Result<VnodeAttrs, Box<dyn Errno>> {
+        let (uid, gid, mode) = self.node.perm();
+        let (size, bytes) = self.node.size();
+        let nlink = *self.node.links_mut();
+        let times = self.node.times_mut();
+
+        Ok(VnodeAttrs {
+            mode,
+            nlink,
+            uid,
+            gid,
+            fsid: vn.mount().id()[0],
+            id: self.node.id(),
+            size,
+            blksize: PAGE_SIZE.get() as u32,
+            atime: times.atime,
+            mtime: times.mtime,
+            ctime: times.ctime,
+            birthtime: times.birthtime,
+            gen: 0,
+            flags: 0,
+            rdev: NODEV,
+            bytes,
+        })
+    }
+
+    /// See `tmpfs_lookup` on the PS4 for a reference.
+    fn lookup(
+        &self,
+        vn: &Arc<Vnode>,
+        _: &Thread,
+        name: &str,
+    ) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        let fs = Self::fs(vn);
+        let Some(dir) = self.node.dir_mut() else {
+            return Err(Box::new(LookupError::NotDirectory));
+        };
+        let node = if name == ".." {
+            // The parent of the root directory is the root directory itself.
+            match dir.parent() {
+                Some(v) => v,
+                None if Arc::ptr_eq(&self.node, &fs.root) => self.node.clone(),
+                None => return Err(Box::new(LookupError::NotFound)),
+            }
+        } else {
+            match dir.find(name) {
+                Some(v) => v.clone(),
+                None => return Err(Box::new(LookupError::NotFound)),
+            }
+        };
+
+        drop(dir);
+
+        Ok(fs.alloc_vnode(vn.mount(), &node))
+    }
+
+    /// See `tmpfs_create` on the PS4 for a reference.
+    fn create(
+        &self,
+        vn: &Arc<Vnode>,
+        td: &Thread,
+        name: &str,
+        mode: u16,
+    ) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        self.alloc_file(vn, td, name, NodeType::File, mode)
+    }
+
+    /// See `tmpfs_mkdir` on the PS4 for a reference.
+    fn mkdir(
+        &self,
+        vn: &Arc<Vnode>,
+        td: &Thread,
+        name: &str,
+        mode: u16,
+    ) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        let ty = NodeType::Directory(Some(Arc::downgrade(&self.node)));
+
+        self.alloc_file(vn, td, name, ty, mode)
+    }
+
+    /// See `tmpfs_remove` on the PS4 for a reference.
+    fn remove(
+        &self,
+        _: &Arc<Vnode>,
+        _: &Thread,
+        vn: &Arc<Vnode>,
+        name: &str,
+    ) -> Result<(), Box<dyn Errno>> {
+        let node = Self::node(vn);
+
+        if node.is_directory() {
+            return Err(Box::new(RemoveError::IsDirectory));
+        }
+
+        let Some(mut ents) = self.node.dir_mut() else {
+            return Err(Box::new(RemoveError::NotDirectory));
+        };
+
+        if ents.detach(name, node).is_none() {
+            return Err(Box::new(RemoveError::NotFound));
+        }
+
+        drop(ents);
+
+        node.times_mut().ctime = TimeSpec::now();
+        self.node.touch();
+
+        Ok(())
+    }
+
+    /// See `tmpfs_rmdir` on the PS4 for a reference.
+    fn rmdir(
+        &self,
+        _: &Arc<Vnode>,
+        _: &Thread,
+        vn: &Arc<Vnode>,
+        name: &str,
+    ) -> Result<(), Box<dyn Errno>> {
+        let node = Self::node(vn);
+        let Some(mut ents) = self.node.dir_mut() else {
+            return Err(Box::new(RemoveError::NotDirectory));
+        };
+
+        let Some(mut sub) = node.dir_mut() else {
+            return Err(Box::new(RemoveError::NotDirectory));
+        };
+
+        if !sub.is_empty() {
+            return Err(Box::new(RemoveError::NotEmpty));
+        }
+
+        if ents.detach(name, node).is_none() {
+            return Err(Box::new(RemoveError::NotFound));
+        }
+
+        sub.set_parent(None);
+
+        drop(sub);
+        drop(ents);
+
+        // Remove the links for "." and "..".
+        *node.links_mut() = 0;
+        *self.node.links_mut() -= 1;
+
+        node.times_mut().ctime = TimeSpec::now();
+        self.node.touch();
+
+        Ok(())
+    }
+
+    /// See `tmpfs_rename` on the PS4 for a reference.
+    fn rename(
+        &self,
+        _: &Arc<Vnode>,
+        td: &Thread,
+        vn: &Arc<Vnode>,
+        name: &str,
+        to: &Arc<Vnode>,
+        target: Option<&Arc<Vnode>>,
+        to_name: &str,
+    ) -> Result<(), Box<dyn Errno>> {
+        let node = Self::node(vn);
+        let dst = Self::node(to);
+        let same = Arc::ptr_eq(&self.node, dst);
+
+        // Moving a directory to the other directory requires updating its "..".
+        if node.is_directory() && !same {
+            vn.access(td, Access::WRITE)?;
+
+            // Prevent moving the directory into its own subtree.
+            let mut cur = Some(dst.clone());
+
+            while let Some(n) = cur {
+                if Arc::ptr_eq(&n, node) {
+                    return Err(Box::new(RenameError::InvalidTarget));
+                }
+
+                cur = n.dir_mut().unwrap().parent();
+            }
+
+            if *dst.links_mut() >= Node::LINK_MAX {
+                return Err(Box::new(RenameError::TooManyLinks));
+            }
+        }
+
+        // Check the entries.
+        let target = target.map(|v| Self::node(v));
+        let mut src = self.node.dir_mut().unwrap();
+        let mut other = if same { None } else { dst.dir_mut() };
+
+        if !src.find(name).is_some_and(|n| Arc::ptr_eq(n, node)) {
+            return Err(Box::new(RenameError::NotFound));
+        }
+
+        if let Some(t) = target {
+            let dir = other.as_deref().unwrap_or(&src);
+
+            if !dir.find(to_name).is_some_and(|n| Arc::ptr_eq(n, t)) {
+                return Err(Box::new(RenameError::NotFound));
+            } else if t.dir_mut().is_some_and(|d| !d.is_empty()) {
+                return Err(Box::new(RenameError::NotEmpty));
+            }
+        }
+
+        // Remove the target.
+        if let Some(t) = target {
+            let dir = other.as_deref_mut().unwrap_or(&mut src);
+
+            dir.detach(to_name, t).unwrap();
+
+            if let Some(mut d) = t.dir_mut() {
+                d.set_parent(None);
+                drop(d);
+
+                *t.links_mut() = 0;
+                *dst.links_mut() -= 1;
+            }
+
+            t.times_mut().ctime = TimeSpec::now();
+        }
+
+        // Move the entry.
+        let moved = src.detach(name, node).unwrap();
+
+        other
+            .as_deref_mut()
+            .unwrap_or(&mut src)
+            .attach(to_name, moved);
+
+        drop(other);
+        drop(src);
+
+        if node.is_directory() && !same {
+            node.dir_mut()
+                .unwrap()
+                .set_parent(Some(Arc::downgrade(dst)));
+
+            *self.node.links_mut() -= 1;
+            *dst.links_mut() += 1;
+        }
+
+        node.times_mut().ctime = TimeSpec::now();
+        self.node.touch();
+
+        if !same {
+            dst.touch();
+        }
+
+        Ok(())
+    }
+
+    /// See `tmpfs_symlink` on the PS4 for a reference.
+    fn symlink(
+        &self,
+        vn: &Arc<Vnode>,
+        td: &Thread,
+        name: &str,
+        mode: u16,
+        target: &str,
+    ) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        self.alloc_file(vn, td, name, NodeType::Link(target.into()), mode)
+    }
+
+    /// See `tmpfs_readlink` on the PS4 for a reference.
+    fn readlink(&self, vn: &Arc<Vnode>, _: &Thread) -> Result<Box<str>, Box<dyn Errno>> {
+        let Some(target) = self.node.target() else {
+            return Err(Box::new(ReadLinkError::NotLink));
+        };
+
+        self.accessed(vn);
+
+        Ok(target.into())
+    }
+
+    /// See `tmpfs_setattr` on the PS4 for a reference.
+    fn setattr(
+        &self,
+        vn: &Arc<Vnode>,
+        td: &Thread,
+        attrs: &VnodeSetAttrs,
+    ) -> Result<(), Box<dyn Errno>> {
+        // Only the owner can change the times unless it is changing to the current time and the
+        // caller can write the file. See tmpfs_chtimes on the PS4 for a reference.
+        let times = attrs.atime.is_some() || attrs.mtime.is_some();
+
+        if times {
+            if let Err(e) = vn.access(td, Access::ADMIN) {
+                if !attrs.utimes_null || vn.access(td, Access::WRITE).is_err() {
+                    return Err(e);
+                }
+            }
+        }
+
+        // Change the size. See tmpfs_chsize on the PS4 for a reference.
+        if let Some(v) = attrs.size {
+            match vn.ty() {
+                VnodeType::Directory(_) => return Err(Box::new(SetAttrError::IsDirectory)),
+                VnodeType::File => self
+                    .node
+                    .resize(v)
+                    .map_err(|e| Box::new(e) as Box<dyn Errno>)?,
+                _ => return Err(Box::new(SetAttrError::NotFile)),
+            }
+        }
+
+        // Change the times.
+        if times {
+            let mut t = self.node.times_mut();
+
+            if let Some(v) = attrs.atime {
+                t.atime = v;
+            }
+
+            if let Some(v) = attrs.mtime {
+                t.mtime = v;
+            }
+
+            t.ctime = TimeSpec::now();
+        }
+
+        Ok(())
+    }
+
+    /// See `tmpfs_readdir` on the PS4 for a reference.
+    fn readdir(
+        &self,
+        vn: &Arc<Vnode>,
+        _: &Thread,
+        off: &mut u64,
+        buf: &mut [u8],
+    ) -> Result<usize, Box<dyn Errno>> {
+        let Some(dir) = self.node.dir_mut() else {
+            return Err(Box::new(ReadDirError::NotDirectory));
+        };
+        let mut written = 0;
+        let mut full = false;
+        let mut put = |ent: DirEntry, next: u64, off: &mut u64| match ent.write(&mut buf[written..])
+        {
+            Some(n) => {
+                written += n;
+                *off = next;
+                true
+            }
+            None => {
+                full = true;
+                false
+            }
+        };
+
+        // Write "." and "..".
+        if *off == Directory::DOT_COOKIE {
+            let id = self.node.id() as u32;
+
+            put(
+                DirEntry::new(id, DirType::Directory, "."),
+                Directory::DOTDOT_COOKIE,
+                off,
+            );
+        }
+
+        if *off == Directory::DOTDOT_COOKIE {
+            let id = dir.parent().map_or(self.node.id(), |p| p.id()) as u32;
+
+            put(
+                DirEntry::new(id, DirType::Directory, ".."),
+                Directory::FIRST_COOKIE,
+                off,
+            );
+        }
+
+        // Write the entries.
+        if *off >= Directory::FIRST_COOKIE {
+            for e in dir.entries_from(*off) {
+                let node = e.node();
+                let ent = DirEntry::new(node.id() as u32, node.dir_type(), e.name());
+
+                if !put(ent, e.cookie() + 1, off) {
+                    break;
+                }
+            }
+        }
+
+        drop(dir);
+
+        if written == 0 && full {
+            return Err(Box::new(ReadDirError::BufferTooSmall));
+        }
+
+        self.accessed(vn);
+
+        Ok(written)
+    }
+
+    /// See `tmpfs_read` on the PS4 for a reference.
+    fn read(
+        &self,
+        vn: &Arc<Vnode>,
+        _: &Thread,
+        off: u64,
+        buf: &mut [u8],
+    ) -> Result<usize, Box<dyn Errno>> {
+        if !vn.is_file() {
+            return Err(Box::new(IoError::IsDirectory));
+        }
+
+        let len = self.node.read(off, buf);
+
+        self.accessed(vn);
+
+        Ok(len)
+    }
+
+    /// See `tmpfs_write` on the PS4 for a reference.
+    fn write(
+        &self,
+        vn: &Arc<Vnode>,
+        _: &Thread,
+        off: u64,
+        buf: &[u8],
+    ) -> Result<usize, Box<dyn Errno>> {
+        if !vn.is_file() {
+            return Err(Box::new(IoError::IsDirectory));
+        }
+
+        self.node
+            .write(off, buf)
+            .map_err(|e| Box::new(e) as Box<dyn Errno>)
+    }
+}
+
+/// Represents an error when [`TmpVnode::lookup()`] fails.
+#[derive(Debug)]
+enum LookupError {
+    NotDirectory,
+    NotFound,
+}
+
+impl Error for LookupError {}
+
+impl Display for LookupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotDirectory => f.write_str("the vnode is not a directory"),
+            Self::NotFound => f.write_str("no such file or directory"),
+        }
+    }
+}
+
+impl Errno for LookupError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NotDirectory => ENOTDIR,
+            Self::NotFound => ENOENT,
+        }
+    }
+}
+
+/// Represents an error when [`TmpVnode::alloc_file()`] fails.
+#[derive(Debug)]
+enum CreateError {
+    NotDirectory,
+    TooManyLinks,
+    AllocFailed(super::node::AllocError),
+}
+
+impl Error for CreateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::AllocFailed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Display for CreateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotDirectory => f.write_str("the vnode is not a directory"),
+            Self::TooManyLinks => f.write_str("too many links to the directory"),
+            Self::AllocFailed(_) => f.write_str("couldn't allocate a node"),
+        }
+    }
+}
+
+impl Errno for CreateError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NotDirectory => ENOTDIR,
+            Self::TooManyLinks => EMLINK,
+            Self::AllocFailed(e) => e.errno(),
+        }
+    }
+}
+
+/// Represents an error when [`TmpVnode::remove()`] or [`TmpVnode::rmdir()`] fails.
+#[derive(Debug)]
+enum RemoveError {
+    NotDirectory,
+    IsDirectory,
+    NotEmpty,
+    NotFound,
+}
+
+impl Error for RemoveError {}
+
+impl Display for RemoveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotDirectory => f.write_str("the vnode is not a directory"),
+            Self::IsDirectory => f.write_str("the vnode is a directory"),
+            Self::NotEmpty => f.write_str("the directory is not empty"),
+            Self::NotFound => f.write_str("no such file or directory"),
+        }
+    }
+}
+
+impl Errno for RemoveError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NotDirectory => ENOTDIR,
+            Self::IsDirectory => EISDIR,
+            Self::NotEmpty => ENOTEMPTY,
+            Self::NotFound => ENOENT,
+        }
+    }
+}
+
+/// Represents an error when [`TmpVnode::rename()`] fails.
+#[derive(Debug)]
+enum RenameError {
+    InvalidTarget,
+    TooManyLinks,
+    NotFound,
+    NotEmpty,
+}
+
+impl Error for RenameError {}
+
+impl Display for RenameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidTarget => f.write_str("cannot move a directory into its own subtree"),
+            Self::TooManyLinks => f.write_str("too many links to the target directory"),
+            Self::NotFound => f.write_str("no such file or directory"),
+            Self::NotEmpty => f.write_str("the target directory is not empty"),
+        }
+    }
+}
+
+impl Errno for RenameError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::InvalidTarget => EINVAL,
+            Self::TooManyLinks => EMLINK,
+            Self::NotFound => ENOENT,
+            Self::NotEmpty => ENOTEMPTY,
+        }
+    }
+}
+
+/// Represents an error when [`TmpVnode::readlink()`] fails.
+#[derive(Debug)]
+enum ReadLinkError {
+    NotLink,
+}
+
+impl Error for ReadLinkError {}
+
+impl Display for ReadLinkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotLink => f.write_str("the vnode is not a symbolic link"),
+        }
+    }
+}
+
+impl Errno for ReadLinkError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NotLink => EINVAL,
+        }
+    }
+}
+
+/// Represents an error when [`TmpVnode::setattr()`] fails.
+#[derive(Debug)]
+enum SetAttrError {
+    IsDirectory,
+    NotFile,
+}
+
+impl Error for SetAttrError {}
+
+impl Display for SetAttrError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::IsDirectory => f.write_str("cannot change the size of a directory"),
+            Self::NotFile => f.write_str("cannot change the size of a non-regular file"),
+        }
+    }
+}
+
+impl Errno for SetAttrError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::IsDirectory => EISDIR,
+            Self::NotFile => EINVAL,
+        }
+    }
+}
+
+/// Represents an error when [`TmpVnode::readdir()`] fails.
+#[derive(Debug)]
+enum ReadDirError {
+    NotDirectory,
+    BufferTooSmall,
+}
+
+impl Error for ReadDirError {}
+
+impl Display for ReadDirError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotDirectory => f.write_str("the vnode is not a directory"),
+            Self::BufferTooSmall => f.write_str("the buffer is too small for the entry"),
+        }
+    }
+}
+
+impl Errno for ReadDirError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NotDirectory => ENOTDIR,
+            Self::BufferTooSmall => EINVAL,
+        }
+    }
+}
+
+/// Represents an error when [`TmpVnode::read()`] or [`TmpVnode::write()`] fails.
+#[derive(Debug)]
+enum IoError {
+    IsDirectory,
+}
+
+impl Error for IoError {}
+
+impl Display for IoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::IsDirectory => f.write_str("the vnode is not a regular file"),
+        }
+    }
+}
+
+impl Errno for IoError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::IsDirectory => EISDIR,
+        }
+    }
+}
@@ -0,0 +1,704 @@
+use super::{
+    check_access, Access, CharacterDevice, DefaultFileBackendError, File, FileBackend, FileFlags,
+    IoCmd, Mount, MountFlags, PollEvents, Stat,
+};
+use crate::config::PAGE_SIZE;
+use crate::errno::{Errno, EFBIG, EINVAL, EISDIR, ENOTDIR, ENOTTY, EOPNOTSUPP, EROFS};
+use crate::lock::{Gutex, GutexGroup, GutexWrite};
+use crate::proc::Thread;
+use crate::time::TimeSpec;
+use crate::ucred::{Gid, Uid};
+use alloc::boxed::Box;
+use alloc::sync::{Arc, Weak};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+
+/// Implementation of `vnode` structure.
+///
+/// Each file or directory in the filesystem must have only one active vnode. The filesystem can use
+/// [`Mount::hash_get_or_insert()`] to make sure of this.
+pub struct Vnode {
+    mount: Arc<Mount>,              // v_mount
+    ty: VnodeType,                  // v_type
+    tag: &'static str,              // v_tag
+    backend: Box<dyn VnodeBackend>, // v_op + v_data
+    item: Gutex<Option<VnodeItem>>, // v_un
+}
+
+impl Vnode {
+    /// See `getnewvnode` on the PS4 for a reference.
+    pub fn new(
+        mount: &Arc<Mount>,
+        ty: VnodeType,
+        tag: &'static str,
+        backend: impl VnodeBackend,
+    ) -> Arc<Self> {
+        let gg = GutexGroup::new();
+
+        Arc::new(Self {
+            mount: mount.clone(),
+            ty,
+            tag,
+            backend: Box::new(backend),
+            item: gg.spawn(None),
+        })
+    }
+
+    pub fn mount(&self) -> &Arc<Mount> {
+        &self.mount
+    }
+
+    pub fn ty(&self) -> &VnodeType {
+        &self.ty
+    }
+
+    pub fn tag(&self) -> &'static str {
+        self.tag
+    }
+
+    pub fn is_file(&self) -> bool {
+        matches!(self.ty, VnodeType::File)
+    }
+
+    pub fn is_directory(&self) -> bool {
+        matches!(self.ty, VnodeType::Directory(_))
+    }
+
+    /// Returns `true` if this vnode is the root of its mount (AKA `VV_ROOT`).
+    pub fn is_mount_root(&self) -> bool {
+        matches!(self.ty, VnodeType::Directory(true))
+    }
+
+    pub fn is_link(&self) -> bool {
+        matches!(self.ty, VnodeType::Link)
+    }
+
+    /// Returns [`None`] if the backend is not `T`.
+    pub fn backend<T: VnodeBackend>(&self) -> Option<&T> {
+        let b: &dyn Any = self.backend.as_ref();
+
+        b.downcast_ref()
+    }
+
+    pub fn item_mut(&self) -> GutexWrite<'_, Option<VnodeItem>> {
+        self.item.write()
+    }
+
+    /// Returns the filesystem that was mounted on this vnode (AKA `v_mountedhere`).
+    pub fn mounted_here(&self) -> Option<Arc<Mount>> {
+        let mut item = self.item.write();
+        let mp = match item.as_ref() {
+            Some(VnodeItem::Mount(v)) => v.upgrade(),
+            Some(VnodeItem::Device(_)) | None => return None,
+        };
+
+        // Remove a dangling reference.
+        if mp.is_none() {
+            *item = None;
+        }
+
+        mp
+    }
+
+    /// Returns the device of this vnode (AKA `v_rdev`).
+    pub fn device(&self) -> Option<Arc<CharacterDevice>> {
+        match self.item.write().as_ref() {
+            Some(VnodeItem::Device(v)) => Some(v.clone()),
+            Some(VnodeItem::Mount(_)) | None => None,
+        }
+    }
+
+    /// Writing to a directory, a regular file or a symlink on a read-only filesystem will fail
+    /// with `EROFS`.
+    pub fn access(self: &Arc<Self>, td: &Thread, mode: Access) -> Result<(), Box<dyn Errno>> {
+        if mode.has(Access::WRITE)
+            && matches!(
+                self.ty,
+                VnodeType::File | VnodeType::Directory(_) | VnodeType::Link
+            )
+            && self.mount.flags().has(MountFlags::MNT_RDONLY)
+        {
+            return Err(Box::new(VnodeError::ReadOnlyFs));
+        }
+
+        self.backend.access(self, td, mode)
+    }
+
+    /// `flags` is the flags of the file that going to be opened.
+    pub fn open(self: &Arc<Self>, td: &Thread, flags: FileFlags) -> Result<(), Box<dyn Errno>> {
+        self.backend.open(self, td, flags)
+    }
+
+    pub fn getattr(self: &Arc<Self>, td: &Thread) -> Result<VnodeAttrs, Box<dyn Errno>> {
+        self.backend.getattr(self, td)
+    }
+
+    /// `name` will never be `.`.
+    pub fn lookup(self: &Arc<Self>, td: &Thread, name: &str) -> Result<Arc<Self>, Box<dyn Errno>> {
+        self.backend.lookup(self, td, name)
+    }
+
+    pub fn create(
+        self: &Arc<Self>,
+        td: &Thread,
+        name: &str,
+        mode: u16,
+    ) -> Result<Arc<Self>, Box<dyn Errno>> {
+        self.check_writable()?;
+        self.backend.create(self, td, name, mode)
+    }
+
+    pub fn mkdir(
+        self: &Arc<Self>,
+        td: &Thread,
+        name: &str,
+        mode: u16,
+    ) -> Result<Arc<Self>, Box<dyn Errno>> {
+        self.check_writable()?;
+        self.backend.mkdir(self, td, name, mode)
+    }
+
+    /// `vn` is the vnode of `name`, which must not be `.` or `..`.
+    pub fn remove(
+        self: &Arc<Self>,
+        td: &Thread,
+        vn: &Arc<Self>,
+        name: &str,
+    ) -> Result<(), Box<dyn Errno>> {
+        self.check_writable()?;
+        self.backend.remove(self, td, vn, name)
+    }
+
+    /// `vn` is the vnode of `name`, which must not be `.` or `..`.
+    pub fn rmdir(
+        self: &Arc<Self>,
+        td: &Thread,
+        vn: &Arc<Self>,
+        name: &str,
+    ) -> Result<(), Box<dyn Errno>> {
+        self.check_writable()?;
+        self.backend.rmdir(self, td, vn, name)
+    }
+
+    /// Move `vn` with `name` on this directory to `to_name` on `to`. `target` is the current vnode
+    /// of `to_name`, which will be replaced. All vnodes must be on the same mount.
+    #[allow(clippy::too_many_arguments)]
+    pub fn rename(
+        self: &Arc<Self>,
+        td: &Thread,
+        vn: &Arc<Self>,
+        name: &str,
+        to: &Arc<Self>,
+        target: Option<&Arc<Self>>,
+        to_name: &str,
+    ) -> Result<(), Box<dyn Errno>> {
+        self.check_writable()?;
+        self.backend.rename(self, td, vn, name, to, target, to_name)
+    }
+
+    pub fn symlink(
+        self: &Arc<Self>,
+        td: &Thread,
+        name: &str,
+        mode: u16,
+        target: &str,
+    ) -> Result<Arc<Self>, Box<dyn Errno>> {
+        self.check_writable()?;
+        self.backend.symlink(self, td, name, mode, target)
+    }
+
+    pub fn readlink(self: &Arc<Self>, td: &Thread) -> Result<Box<str>, Box<dyn Errno>> {
+        self.backend.readlink(self, td)
+    }
+
+    pub fn setattr(
+        self: &Arc<Self>,
+        td: &Thread,
+        attrs: &VnodeSetAttrs,
+    ) -> Result<(), Box<dyn Errno>> {
+        self.check_writable()?;
+        self.backend.setattr(self, td, attrs)
+    }
+
+    /// Write the entries starting at `off` to `buf` and returns the number of bytes written.
+    /// `off` will be advanced past the last entry that was written.
+    pub fn readdir(
+        self: &Arc<Self>,
+        td: &Thread,
+        off: &mut u64,
+        buf: &mut [u8],
+    ) -> Result<usize, Box<dyn Errno>> {
+        self.backend.readdir(self, td, off, buf)
+    }
+
+    pub fn read(
+        self: &Arc<Self>,
+        td: &Thread,
+        off: u64,
+        buf: &mut [u8],
+    ) -> Result<usize, Box<dyn Errno>> {
+        self.backend.read(self, td, off, buf)
+    }
+
+    /// Read the whole content of a regular file. This does not check the permission so the caller
+    /// is responsible for that.
+    pub fn read_all(self: &Arc<Self>, td: &Thread) -> Result<Vec<u8>, Box<dyn Errno>> {
+        if self.is_directory() {
+            return Err(Box::new(VnodeError::IsDirectory));
+        }
+
+        // Allocate a buffer for the whole file.
+        let len = self.getattr(td)?.size;
+        let len: usize = match len.try_into() {
+            Ok(v) => v,
+            Err(_) => return Err(Box::new(VnodeError::TooLarge)),
+        };
+        let mut data = vec![0; len];
+        let mut off = 0;
+
+        while off < len {
+            let n = self.read(td, off as u64, &mut data[off..])?;
+
+            if n == 0 {
+                data.truncate(off);
+                break;
+            }
+
+            off += n;
+        }
+
+        Ok(data)
+    }
+
+    pub fn write(
+        self: &Arc<Self>,
+        td: &Thread,
+        off: u64,
+        buf: &[u8],
+    ) -> Result<usize, Box<dyn Errno>> {
+        self.check_writable()?;
+        self.backend.write(self, td, off, buf)
+    }
+
+    pub fn ioctl(
+        self: &Arc<Self>,
+        td: &Thread,
+        cmd: IoCmd,
+        data: &mut [u8],
+    ) -> Result<(), Box<dyn Errno>> {
+        self.backend.ioctl(self, td, cmd, data)
+    }
+
+    /// See `vn_stat` on the PS4 for a reference.
+    pub fn stat(self: &Arc<Self>, td: &Thread) -> Result<Stat, Box<dyn Errno>> {
+        let attrs = self.getattr(td)?;
+        let ty = match self.ty {
+            VnodeType::File => S_IFREG,
+            VnodeType::Directory(_) => S_IFDIR,
+            VnodeType::Link => S_IFLNK,
+            VnodeType::CharacterDevice => S_IFCHR,
+        };
+
+        Ok(Stat {
+            dev: attrs.fsid,
+            ino: attrs.id as u32,
+            mode: ty | attrs.mode,
+            nlink: attrs.nlink,
+            uid: c_int::from(attrs.uid) as u32,
+            gid: c_int::from(attrs.gid) as u32,
+            rdev: attrs.rdev,
+            atime: attrs.atime,
+            mtime: attrs.mtime,
+            ctime: attrs.ctime,
+            size: attrs.size as i64,
+            blocks: attrs.bytes.div_ceil(512) as i64,
+            blksize: attrs.blksize.max(PAGE_SIZE.get() as u32),
+            flags: attrs.flags,
+            gen: attrs.gen,
+            spare: 0,
+            birthtime: attrs.birthtime,
+        })
+    }
+
+    fn check_writable(&self) -> Result<(), Box<dyn Errno>> {
+        if self.mount.flags().has(MountFlags::MNT_RDONLY) {
+            Err(Box::new(VnodeError::ReadOnlyFs))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Content of [`Vnode::item_mut()`].
+pub enum VnodeItem {
+    Mount(Weak<Mount>),
+    Device(Arc<CharacterDevice>),
+}
+
+/// Implementation of `vtype`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VnodeType {
+    /// Regular file (AKA `VREG`).
+    File,
+    /// `true` if this is the root of the mount (AKA `VDIR`).
+    Directory(bool),
+    /// Symbolic link (AKA `VLNK`).
+    Link,
+    /// Character device (AKA `VCHR`).
+    CharacterDevice,
+}
+
+/// Implementation of `vop_vector` structure.
+///
+/// We don't support `vop_bypass` because it required the return type for all operations to be the
+/// same. All default implementation here are the implementation of `default_vnodeops`.
+pub trait VnodeBackend: Any + Send + Sync {
+    /// The default implementation use [`VnodeBackend::getattr()`] with `vaccess`.
+    ///
+    /// Implementation of `vop_access`.
+    fn access(&self, vn: &Arc<Vnode>, td: &Thread, mode: Access) -> Result<(), Box<dyn Errno>> {
+        let attrs = self.getattr(vn, td)?;
+        let cred = td.cred_mut();
+
+        check_access(
+            &cred,
+            attrs.uid,
+            attrs.gid,
+            attrs.mode,
+            mode,
+            vn.is_directory(),
+        )
+        .map_err(|e| Box::new(e) as Box<dyn Errno>)?;
+
+        Ok(())
+    }
+
+    /// Implementation of `vop_open`.
+    fn open(&self, _: &Arc<Vnode>, _: &Thread, _: FileFlags) -> Result<(), Box<dyn Errno>> {
+        Ok(())
+    }
+
+    /// Implementation of `vop_getattr`.
+    fn getattr(&self, vn: &Arc<Vnode>, td: &Thread) -> Result<VnodeAttrs, Box<dyn Errno>>;
+
+    /// Implementation of `vop_lookup`.
+    fn lookup(&self, _: &Arc<Vnode>, _: &Thread, _: &str) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        Err(Box::new(VnodeError::NotDirectory))
+    }
+
+    /// Implementation of `vop_create`.
+    fn create(
+        &self,
+        _: &Arc<Vnode>,
+        _: &Thread,
+        _: &str,
+        _: u16,
+    ) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        Err(Box::new(VnodeError::NotSupported))
+    }
+
+    /// Implementation of `vop_mkdir`.
+    fn mkdir(
+        &self,
+        _: &Arc<Vnode>,
+        _: &Thread,
+        _: &str,
+        _: u16,
+    ) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        Err(Box::new(VnodeError::NotSupported))
+    }
+
+    /// Implementation of `vop_remove`.
+    fn remove(
+        &self,
+        _: &Arc<Vnode>,
+        _: &Thread,
+        _: &Arc<Vnode>,
+        _: &str,
+    ) -> Result<(), Box<dyn Errno>> {
+        Err(Box::new(VnodeError::NotSupported))
+    }
+
+    /// Implementation of `vop_rmdir`.
+    fn rmdir(
+        &self,
+        _: &Arc<Vnode>,
+        _: &Thread,
+        _: &Arc<Vnode>,
+        _: &str,
+    ) -> Result<(), Box<dyn Errno>> {
+        Err(Box::new(VnodeError::NotSupported))
+    }
+
+    /// Implementation of `vop_rename`.
+    #[allow(clippy::too_many_arguments)]
+    fn rename(
+        &self,
+        _: &Arc<Vnode>,
+        _: &Thread,
+        _: &Arc<Vnode>,
+        _: &str,
+        _: &Arc<Vnode>,
+        _: Option<&Arc<Vnode>>,
+        _: &str,
+    ) -> Result<(), Box<dyn Errno>> {
+        Err(Box::new(VnodeError::NotSupported))
+    }
+
+    /// Implementation of `vop_symlink`.
+    fn symlink(
+        &self,
+        _: &Arc<Vnode>,
+        _: &Thread,
+        _: &str,
+        _: u16,
+        _: &str,
+    ) -> Result<Arc<Vnode>, Box<dyn Errno>> {
+        Err(Box::new(VnodeError::NotSupported))
+    }
+
+    /// Implementation of `vop_readlink`.
+    fn readlink(&self, _: &Arc<Vnode>, _: &Thread) -> Result<Box<str>, Box<dyn Errno>> {
+        Err(Box::new(VnodeError::NotLink))
+    }
+
+    /// Implementation of `vop_setattr`.
+    fn setattr(&self, _: &Arc<Vnode>, _: &Thread, _: &VnodeSetAttrs) -> Result<(), Box<dyn Errno>> {
+        Err(Box::new(VnodeError::NotSupported))
+    }
+
+    /// See [`Vnode::readdir()`] for the meaning of the arguments and return value.
+    ///
+    /// Implementation of `vop_readdir`.
+    fn readdir(
+        &self,
+        _: &Arc<Vnode>,
+        _: &Thread,
+        _: &mut u64,
+        _: &mut [u8],
+    ) -> Result<usize, Box<dyn Errno>> {
+        Err(Box::new(VnodeError::NotDirectory))
+    }
+
+    /// Implementation of `vop_read`.
+    fn read(
+        &self,
+        vn: &Arc<Vnode>,
+        _: &Thread,
+        _: u64,
+        _: &mut [u8],
+    ) -> Result<usize, Box<dyn Errno>> {
+        if vn.is_directory() {
+            Err(Box::new(VnodeError::IsDirectory))
+        } else {
+            Err(Box::new(VnodeError::NotSupported))
+        }
+    }
+
+    /// Implementation of `vop_write`.
+    fn write(&self, _: &Arc<Vnode>, _: &Thread, _: u64, _: &[u8]) -> Result<usize, Box<dyn Errno>> {
+        Err(Box::new(VnodeError::NotSupported))
+    }
+
+    /// Implementation of `vop_ioctl`.
+    fn ioctl(
+        &self,
+        _: &Arc<Vnode>,
+        _: &Thread,
+        _: IoCmd,
+        _: &mut [u8],
+    ) -> Result<(), Box<dyn Errno>> {
+        Err(Box::new(VnodeError::IoctlNotSupported))
+    }
+}
+
+/// Implementation of `vattr` structure.
+pub struct VnodeAttrs {
+    pub mode: u16,           // va_mode
+    pub nlink: u16,          // va_nlink
+    pub uid: Uid,            // va_uid
+    pub gid: Gid,            // va_gid
+    pub fsid: u32,           // va_fsid
+    pub id: u64,             // va_fileid
+    pub size: u64,           // va_size
+    pub blksize: u32,        // va_blocksize
+    pub atime: TimeSpec,     // va_atime
+    pub mtime: TimeSpec,     // va_mtime
+    pub ctime: TimeSpec,     // va_ctime
+    pub birthtime: TimeSpec, // va_birthtime
+    pub gen: u32,            // va_gen
+    pub flags: u32,          // va_flags
+    pub rdev: u32,           // va_rdev
+    pub bytes: u64,          // va_bytes
+}
+
+/// Attributes to change with [`Vnode::setattr()`]. Each attribute with [`None`] will be unchanged.
+#[derive(Default)]
+pub struct VnodeSetAttrs {
+    pub size: Option<u64>,       // va_size
+    pub atime: Option<TimeSpec>, // va_atime
+    pub mtime: Option<TimeSpec>, // va_mtime
+    pub utimes_null: bool,       // VA_UTIMES_NULL
+}
+
+/// Implementation of `vnops`.
+pub struct VnodeFileBackend(Arc<Vnode>);
+
+impl VnodeFileBackend {
+    pub fn new(vn: Arc<Vnode>) -> Self {
+        Self(vn)
+    }
+
+    #[allow(dead_code)] // TODO: Remove this once we have mmap.
+    pub fn vnode(&self) -> &Arc<Vnode> {
+        &self.0
+    }
+}
+
+impl FileBackend for VnodeFileBackend {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    /// See `vn_read` on the PS4 for a reference.
+    fn read(
+        &self,
+        _: &File,
+        off: &mut u64,
+        buf: &mut [u8],
+        td: &Thread,
+    ) -> Result<usize, Box<dyn Errno>> {
+        let len = self.0.read(td, *off, buf)?;
+
+        *off += len as u64;
+
+        Ok(len)
+    }
+
+    /// See `vn_write` on the PS4 for a reference.
+    fn write(
+        &self,
+        file: &File,
+        off: &mut u64,
+        buf: &[u8],
+        td: &Thread,
+    ) -> Result<usize, Box<dyn Errno>> {
+        if file.flags().has(FileFlags::FAPPEND) {
+            *off = self.0.getattr(td)?.size;
+        }
+
+        let len = self.0.write(td, *off, buf)?;
+
+        *off += len as u64;
+
+        Ok(len)
+    }
+
+    /// See `vn_truncate` on the PS4 for a reference.
+    fn truncate(&self, _: &File, len: u64, td: &Thread) -> Result<(), Box<dyn Errno>> {
+        if self.0.is_directory() {
+            return Err(Box::new(VnodeError::IsDirectory));
+        }
+
+        let attrs = VnodeSetAttrs {
+            size: Some(len),
+            ..Default::default()
+        };
+
+        self.0.setattr(td, &attrs)
+    }
+
+    /// See `kern_getdirentries` on the PS4 for a reference.
+    fn readdir(
+        &self,
+        _: &File,
+        off: &mut u64,
+        buf: &mut [u8],
+        td: &Thread,
+    ) -> Result<usize, Box<dyn Errno>> {
+        if !self.0.is_directory() {
+            return Err(Box::new(DefaultFileBackendError::ReadDir));
+        }
+
+        self.0.readdir(td, off, buf)
+    }
+
+    /// See `vn_ioctl` on the PS4 for a reference.
+    fn ioctl(
+        &self,
+        _: &File,
+        cmd: IoCmd,
+        data: &mut [u8],
+        td: &Thread,
+    ) -> Result<(), Box<dyn Errno>> {
+        self.0.ioctl(td, cmd, data)
+    }
+
+    /// See `vn_poll` on the PS4 for a reference.
+    fn poll(&self, _: &File, events: PollEvents, _: &Thread) -> PollEvents {
+        // This is the implementation of vop_nopoll.
+        events & (PollEvents::POLLIN | PollEvents::POLLOUT | PollEvents::POLLRDNORM)
+    }
+
+    /// See `vn_statfile` on the PS4 for a reference.
+    fn stat(&self, _: &File, td: &Thread) -> Result<Stat, Box<dyn Errno>> {
+        self.0.stat(td)
+    }
+}
+
+/// Represents an error when the default implementation of [`VnodeBackend`] or the check on
+/// [`Vnode`] fails.
+#[derive(Debug)]
+enum VnodeError {
+    NotSupported,
+    NotDirectory,
+    IsDirectory,
+    NotLink,
+    IoctlNotSupported,
+    ReadOnlyFs,
+    TooLarge,
+}
+
+impl Error for VnodeError {}
+
+impl Display for VnodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotSupported => f.write_str("operation not supported"),
+            Self::NotDirectory => f.write_str("the vnode is not a directory"),
+            Self::IsDirectory => f.write_str("the vnode is a directory"),
+            Self::NotLink => f.write_str("the vnode is not a symbolic link"),
+            Self::IoctlNotSupported => f.write_str("ioctl is not supported"),
+            Self::ReadOnlyFs => f.write_str("the filesystem is read-only"),
+            Self::TooLarge => f.write_str("the file is too large"),
+        }
+    }
+}
+
+impl Errno for VnodeError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NotSupported => EOPNOTSUPP,
+            Self::NotDirectory => ENOTDIR,
+            Self::IsDirectory => EISDIR,
+            Self::NotLink => EINVAL,
+            Self::IoctlNotSupported => ENOTTY,
+            Self::ReadOnlyFs => EROFS,
+            Self::TooLarge => EFBIG,
+        }
+    }
+}
+
+/// Value of [`VnodeAttrs::rdev`] for non-device.
+pub const NODEV: u32 = u32::MAX;
+
+/// Type of file for [`Stat::mode`].
+const S_IFCHR: u16 = 0o020000;
+const S_IFDIR: u16 = 0o040000;
+const S_IFREG: u16 = 0o100000;
+const S_IFLNK: u16 = 0o120000;
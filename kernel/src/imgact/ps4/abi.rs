@@ -1,10 +1,175 @@
-use crate::proc::ProcAbi;
+use crate::errno::Errno;
+use crate::proc::{ProcAbi, Thread};
+use crate::signal::{Signal, SignalAct, SignalSet};
+use crate::syscalls::Syscalls;
+use crate::sysctl::Sysctl;
+use crate::trap::TrapFrame;
+#[cfg(target_arch = "x86_64")]
+use crate::trap::PSL_C;
+#[cfg(target_arch = "aarch64")]
+use crate::trap::PSR_C;
+use crate::vm::FaultError;
 
 /// Implementation of [`ProcAbi`] for PS4 processes.
-pub struct Ps4Abi;
+///
+/// See `self_orbis_sysvec` on the PS4 for a reference.
+pub struct Ps4Abi {
+    sys: Syscalls, // sv_table
+}
+
+impl Ps4Abi {
+    pub fn new(sys: Syscalls) -> Self {
+        Self { sys }
+    }
+
+    pub fn register_sysctls(ctl: &mut Sysctl) {
+        ctl.register(&super::KERN_USRSTACK);
+    }
+}
 
 impl ProcAbi for Ps4Abi {
-    fn syscall_handler(&self) {
-        todo!()
+    /// See `amd64_syscall` and `cpu_fetch_syscall_args` on the PS4 for a reference.
+    #[cfg(target_arch = "x86_64")]
+    fn syscall_handler(&self, td: &Thread, frame: &mut TrapFrame) {
+        use super::signal::sigreturn;
+        use crate::syscalls::SysIn;
+
+        // Fetch arguments.
+        let mut id = frame.rax;
+        let mut args = [
+            frame.rdi, frame.rsi, frame.rdx, frame.rcx, frame.r8, frame.r9,
+        ];
+
+        if id == SYS_SYSCALL || id == SYS___SYSCALL {
+            // The syscall number is passed as the first argument so the last argument is on the
+            // user stack right after the return address.
+            let mut buf = [0; 8];
+            let r = frame
+                .rsp
+                .checked_add(8)
+                .ok_or(FaultError::NotMapped)
+                .and_then(|addr| td.proc().vm().read(addr, &mut buf));
+
+            if let Err(e) = r {
+                frame.rax = e.errno().get().try_into().unwrap();
+                frame.rflags |= PSL_C;
+                return;
+            }
+
+            id = args[0];
+            args.copy_within(1.., 0);
+            args[5] = usize::from_le_bytes(buf);
+        }
+
+        // Pick up the credential that was changed by the other threads.
+        td.update_cred();
+
+        // Invoke the handler. Any number that does not fit in u32 will be out of the table.
+        let i = SysIn {
+            id: id.try_into().unwrap_or(u32::MAX),
+            offset: frame.rip,
+            args: args.map(|v| v.into()),
+        };
+
+        // The sigreturn need to restore the whole trap frame so it cannot be in the table.
+        let r = match i.id {
+            SYS_SIGRETURN => match sigreturn(td, frame, i.args[0].into()) {
+                Ok(_) => return,
+                Err(e) => Err(e),
+            },
+            _ => self.sys.exec(td, &i),
+        };
+
+        match r {
+            Ok(v) => {
+                frame.rax = v.rax();
+                frame.rdx = v.rdx();
+                frame.rflags &= !PSL_C;
+            }
+            Err(e) => {
+                frame.rax = e.errno().get().try_into().unwrap();
+                frame.rflags |= PSL_C;
+            }
+        }
+    }
+
+    /// See `svc_handler`, `cpu_fetch_syscall_args` and `cpu_set_syscall_retval` on FreeBSD for a
+    /// reference.
+    #[cfg(target_arch = "aarch64")]
+    fn syscall_handler(&self, td: &Thread, frame: &mut TrapFrame) {
+        use crate::syscalls::SysIn;
+
+        // Fetch arguments. There are enough registers for all arguments so we don't need to read
+        // anything from the user stack.
+        let mut id = frame.x[8];
+        let mut args = [
+            frame.x[0], frame.x[1], frame.x[2], frame.x[3], frame.x[4], frame.x[5],
+        ];
+
+        if id == SYS_SYSCALL || id == SYS___SYSCALL {
+            id = args[0];
+            args.copy_within(1.., 0);
+            args[5] = frame.x[6];
+        }
+
+        // Pick up the credential that was changed by the other threads.
+        td.update_cred();
+
+        // Invoke the handler. The sigreturn is not supported here since we don't have a signal
+        // frame for AArch64 so it will fail with ENOSYS.
+        let i = SysIn {
+            id: id.try_into().unwrap_or(u32::MAX),
+            offset: frame.elr,
+            args: args.map(|v| v.into()),
+        };
+
+        match self.sys.exec(td, &i) {
+            Ok(v) => {
+                frame.x[0] = v.rax();
+                frame.x[1] = v.rdx();
+                frame.spsr &= !PSR_C;
+            }
+            Err(e) => {
+                frame.x[0] = e.errno().get().try_into().unwrap();
+                frame.spsr |= PSR_C;
+            }
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn send_signal(
+        &self,
+        td: &Thread,
+        frame: &mut TrapFrame,
+        sig: Signal,
+        act: &SignalAct,
+        mask: SignalSet,
+    ) -> Result<(), FaultError> {
+        super::signal::send_signal(td, frame, sig, act, mask)
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    fn send_signal(
+        &self,
+        _: &Thread,
+        _: &mut TrapFrame,
+        _: Signal,
+        _: &SignalAct,
+        _: SignalSet,
+    ) -> Result<(), FaultError> {
+        // TODO: Implement sendsig for AArch64. We don't have a signal frame and a trampoline for it
+        // yet so we fail the same way as the user stack is not accessible, which will terminate
+        // the process.
+        Err(FaultError::NotMapped)
     }
 }
+
+/// Indirect syscall (AKA `SYS_syscall`).
+const SYS_SYSCALL: usize = 0;
+
+/// Indirect syscall with 64-bit number (AKA `SYS___syscall`).
+const SYS___SYSCALL: usize = 198;
+
+/// Return from the signal handler (AKA `SYS_sigreturn`).
+#[cfg(target_arch = "x86_64")]
+const SYS_SIGRETURN: u32 = 417;
@@ -0,0 +1,304 @@
+use crate::config::PAGE_SIZE;
+use crate::imgfmt::elf::{Elf, FileType, Program, ProgramFlags, ProgramType, ReadProgramError};
+use crate::sysctl::KERN;
+use crate::vm::{FaultError, MapError, VmProt, VmSpace};
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt::{Display, Formatter};
+use macros::sysctl;
+
+/// Top of the user stack (AKA `USRSTACK`).
+const USRSTACK: usize = VmSpace::USER_MAX;
+
+#[sysctl(
+    parent = KERN,
+    number = 33,
+    name = "usrstack",
+    flags = CTLFLAG_RD | CTLFLAG_CAPRD,
+    descr = ""
+)]
+pub static KERN_USRSTACK: usize = USRSTACK;
+
+/// Size of the user stack for the main thread (AKA `maxssiz`).
+const STACK_SIZE: usize = 0x100000;
+
+/// Address of the signal trampoline (AKA `sv_sigcode_base`), which is right below the user stack.
+pub const SIGCODE_BASE: usize = USRSTACK - STACK_SIZE - PAGE_SIZE.get();
+
+/// Map `elf` into `vm` and build the initial user stack with `args` and `envs`. The caller is
+/// responsible to make sure `vm` does not have any user mapping.
+///
+/// Only the executable itself will be mapped. The dynamic linking is the responsibility of the
+/// runtime linker, including `PT_TLS` and `PT_SCE_PROCPARAM`.
+///
+/// See `exec_self_imgact` and `exec_copyout_strings` on the PS4 for a reference.
+pub fn exec(vm: &VmSpace, elf: &Elf, args: &[&str], envs: &[&str]) -> Result<ExecImage, ExecError> {
+    // Get base address.
+    let base = match elf.ty() {
+        FileType::ET_EXEC | FileType::ET_SCE_EXEC | FileType::ET_SCE_REPLAY_EXEC => 0,
+        FileType::ET_SCE_DYNEXEC if elf.dynamic().is_some() => 0x400000,
+        t => return Err(ExecError::UnsupportedType(t)),
+    };
+
+    let entry = elf.entry_addr().ok_or(ExecError::NoEntry)?;
+    let entry = entry
+        .checked_add(base)
+        .ok_or(ExecError::InvalidAddr(entry))?;
+
+    map_programs(vm, elf, base).map_err(ExecError::MapProgramFailed)?;
+
+    // Setup the stack.
+    vm.mmap(
+        USRSTACK - STACK_SIZE,
+        STACK_SIZE,
+        VmProt::Read | VmProt::Write,
+        true,
+    )
+    .map_err(ExecError::MapStackFailed)?;
+
+    #[cfg(target_arch = "x86_64")]
+    map_sigcode(vm)?;
+
+    let (stack, argv) = copyout_strings(vm, base, entry, args, envs)?;
+
+    Ok(ExecImage {
+        base,
+        entry,
+        stack,
+        argv,
+    })
+}
+
+/// Map all `PT_LOAD` and `PT_SCE_RELRO` of `elf` into `vm` at `base`. The previous mappings within
+/// the range will be replaced.
+pub fn map_programs(vm: &VmSpace, elf: &Elf, base: usize) -> Result<(), MapProgramError> {
+    for (i, p) in elf.programs().iter().enumerate() {
+        if !matches!(p.ty(), ProgramType::PT_LOAD | ProgramType::PT_SCE_RELRO) {
+            continue;
+        }
+
+        // Get protection.
+        let flags = p.flags();
+        let mut prot = VmProt::zeroed();
+
+        if flags.has(ProgramFlags::Read) {
+            prot |= VmProt::Read;
+        }
+
+        if flags.has(ProgramFlags::Write) {
+            prot |= VmProt::Write;
+        }
+
+        if flags.has(ProgramFlags::Execute) {
+            prot |= VmProt::Execute;
+        }
+
+        // Map and copy the data.
+        let addr = base
+            .checked_add(p.addr())
+            .ok_or(MapProgramError::MapFailed(i, MapError::InvalidAddress))?;
+        let data = elf
+            .program_data(i)
+            .map_err(|e| MapProgramError::ReadFailed(i, e))?;
+
+        vm.mmap(addr, Program::align_page(p.memory_size()), prot, true)
+            .map_err(|e| MapProgramError::MapFailed(i, e))?;
+        vm.force_write(addr, data)
+            .map_err(|e| MapProgramError::WriteFailed(i, e))?;
+    }
+
+    Ok(())
+}
+
+/// Map the signal trampoline at [`SIGCODE_BASE`].
+#[cfg(target_arch = "x86_64")]
+fn map_sigcode(vm: &VmSpace) -> Result<(), ExecError> {
+    use super::signal::SIGCODE;
+
+    vm.mmap(
+        SIGCODE_BASE,
+        PAGE_SIZE.get(),
+        VmProt::Read | VmProt::Execute,
+        true,
+    )
+    .map_err(ExecError::MapSigcodeFailed)?;
+
+    vm.force_write(SIGCODE_BASE, SIGCODE)
+        .map_err(ExecError::WriteSigcodeFailed)
+}
+
+/// Returns the initial stack pointer and the address of `argc`.
+///
+/// The layout of the stack from the top is strings, auxiliary vector, `envp`, `argv` then `argc`.
+///
+/// See `exec_copyout_strings` on the PS4 for a reference.
+fn copyout_strings(
+    vm: &VmSpace,
+    base: usize,
+    entry: usize,
+    args: &[&str],
+    envs: &[&str],
+) -> Result<(usize, usize), ExecError> {
+    // Get the address of the strings.
+    let len = args.iter().chain(envs).map(|v| v.len() + 1).sum::<usize>();
+    let strings = (USRSTACK - len) & !7;
+    let auxv = [
+        (AT_PAGESZ, PAGE_SIZE.get()),
+        (AT_BASE, base),
+        (AT_ENTRY, entry),
+        (AT_NULL, 0),
+    ];
+
+    // Get the address of argc.
+    let vectors = 1 + args.len() + 1 + envs.len() + 1 + auxv.len() * 2;
+    let argc = (strings - vectors * 8) & !0xF;
+
+    if argc < USRSTACK - STACK_SIZE {
+        return Err(ExecError::TooLargeArguments);
+    }
+
+    // Build the stack.
+    let mut data = Vec::with_capacity(USRSTACK - argc);
+    let mut addr = strings;
+
+    data.extend_from_slice(&args.len().to_le_bytes());
+
+    for list in [args, envs] {
+        for v in list {
+            data.extend_from_slice(&addr.to_le_bytes());
+            addr += v.len() + 1;
+        }
+
+        data.extend_from_slice(&0usize.to_le_bytes());
+    }
+
+    for (k, v) in auxv {
+        data.extend_from_slice(&k.to_le_bytes());
+        data.extend_from_slice(&v.to_le_bytes());
+    }
+
+    data.resize(strings - argc, 0);
+
+    for v in args.iter().chain(envs) {
+        data.extend_from_slice(v.as_bytes());
+        data.push(0);
+    }
+
+    vm.write(argc, &data).map_err(ExecError::WriteStackFailed)?;
+
+    // The stack pointer need to be 16-byte aligned after the return address is pushed.
+    Ok((((argc - 8) & !0xF) + 8, argc))
+}
+
+/// Result of [`exec()`].
+pub struct ExecImage {
+    base: usize,
+    entry: usize,
+    stack: usize,
+    argv: usize,
+}
+
+impl ExecImage {
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    pub fn entry(&self) -> usize {
+        self.entry
+    }
+
+    /// Returns the initial value of the stack pointer.
+    pub fn stack(&self) -> usize {
+        self.stack
+    }
+
+    /// Returns the address of `argc`, which need to be passed as the first argument of the entry
+    /// point.
+    pub fn argv(&self) -> usize {
+        self.argv
+    }
+}
+
+/// End of the auxiliary vector.
+const AT_NULL: usize = 0;
+
+/// Page size in bytes.
+const AT_PAGESZ: usize = 6;
+
+/// Base address of the executable.
+const AT_BASE: usize = 7;
+
+/// Entry point of the executable.
+const AT_ENTRY: usize = 9;
+
+/// Represents an error when [`exec()`] fails.
+#[derive(Debug)]
+pub enum ExecError {
+    UnsupportedType(FileType),
+    NoEntry,
+    InvalidAddr(usize),
+    MapProgramFailed(MapProgramError),
+    MapStackFailed(MapError),
+    MapSigcodeFailed(MapError),
+    WriteSigcodeFailed(FaultError),
+    TooLargeArguments,
+    WriteStackFailed(FaultError),
+}
+
+impl Error for ExecError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::MapProgramFailed(e) => Some(e),
+            Self::MapStackFailed(e) => Some(e),
+            Self::MapSigcodeFailed(e) => Some(e),
+            Self::WriteSigcodeFailed(e) => Some(e),
+            Self::WriteStackFailed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ExecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnsupportedType(t) => write!(f, "{t} is not supported"),
+            Self::NoEntry => f.write_str("the executable does not have an entry point"),
+            Self::InvalidAddr(v) => write!(f, "invalid address {v:#x}"),
+            Self::MapProgramFailed(_) => f.write_str("couldn't map the executable"),
+            Self::MapStackFailed(_) => f.write_str("couldn't map the stack"),
+            Self::MapSigcodeFailed(_) => f.write_str("couldn't map the signal trampoline"),
+            Self::WriteSigcodeFailed(_) => f.write_str("couldn't write the signal trampoline"),
+            Self::TooLargeArguments => f.write_str("the arguments is too large"),
+            Self::WriteStackFailed(_) => f.write_str("couldn't write the stack"),
+        }
+    }
+}
+
+/// Represents an error when [`map_programs()`] fails.
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug)]
+pub enum MapProgramError {
+    ReadFailed(usize, ReadProgramError),
+    MapFailed(usize, MapError),
+    WriteFailed(usize, FaultError),
+}
+
+impl Error for MapProgramError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::ReadFailed(_, e) => Some(e),
+            Self::MapFailed(_, e) => Some(e),
+            Self::WriteFailed(_, e) => Some(e),
+        }
+    }
+}
+
+impl Display for MapProgramError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ReadFailed(i, _) => write!(f, "couldn't read program #{i}"),
+            Self::MapFailed(i, _) => write!(f, "couldn't map program #{i}"),
+            Self::WriteFailed(i, _) => write!(f, "couldn't write program #{i}"),
+        }
+    }
+}
@@ -1,3 +1,7 @@
 pub use self::abi::*;
+pub use self::exec::*;
 
 mod abi;
+mod exec;
+#[cfg(target_arch = "x86_64")]
+mod signal;
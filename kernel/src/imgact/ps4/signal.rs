@@ -0,0 +1,256 @@
+use super::SIGCODE_BASE;
+use crate::errno::EINVAL;
+use crate::proc::Thread;
+use crate::signal::{Signal, SignalAct, SignalFlags, SignalSet};
+use crate::syscalls::SysErr;
+use crate::trap::TrapFrame;
+use crate::vm::FaultError;
+use core::ffi::c_int;
+use core::mem::{offset_of, zeroed};
+
+/// Signal trampoline to invoke the handler then `sigreturn` with the context in [`SigFrame`].
+///
+/// See `sigcode` on the PS4 for a reference.
+pub const SIGCODE: &[u8] = &[
+    0xff, 0x14, 0x24, // call qword ptr [rsp]
+    0x48, 0x8d, 0x7c, 0x24, 0x10, // lea rdi, [rsp+0x10]
+    0x6a, 0x00, // push 0
+    0x48, 0xc7, 0xc0, 0xa1, 0x01, 0x00, 0x00, // mov rax, 417
+    0x0f, 0x05, // syscall
+    0xf4, // hlt
+    0xeb, 0xfd, // jmp -3
+];
+
+/// Setup the user stack and `frame` to invoke the handler of `sig` via [`SIGCODE`].
+///
+/// See `sendsig` on the PS4 for a reference.
+pub fn send_signal(
+    td: &Thread,
+    frame: &mut TrapFrame,
+    sig: Signal,
+    act: &SignalAct,
+    mask: SignalSet,
+) -> Result<(), FaultError> {
+    // Allocate the frame below the red zone. TODO: Use the alternate stack for SA_ONSTACK.
+    let sp = frame
+        .rsp
+        .checked_sub(128 + size_of::<SigFrame>())
+        .ok_or(FaultError::NotMapped)?
+        & !0xF;
+
+    // Build the frame. All fields are integer so zeroed is a valid value.
+    let mut sf: SigFrame = unsafe { zeroed() };
+    let mc = &mut sf.uc.mcontext;
+
+    mc.rdi = frame.rdi;
+    mc.rsi = frame.rsi;
+    mc.rdx = frame.rdx;
+    mc.rcx = frame.rcx;
+    mc.r8 = frame.r8;
+    mc.r9 = frame.r9;
+    mc.rax = frame.rax;
+    mc.rbx = frame.rbx;
+    mc.rbp = frame.rbp;
+    mc.r10 = frame.r10;
+    mc.r11 = frame.r11;
+    mc.r12 = frame.r12;
+    mc.r13 = frame.r13;
+    mc.r14 = frame.r14;
+    mc.r15 = frame.r15;
+    mc.err = frame.err;
+    mc.rip = frame.rip;
+    mc.cs = frame.cs;
+    mc.rflags = frame.rflags;
+    mc.rsp = frame.rsp;
+    mc.ss = frame.ss;
+    mc.len = size_of::<MContext>();
+
+    sf.handler = act.handler;
+    sf.uc.sigmask = mask;
+
+    // Setup the arguments for the handler.
+    frame.rdi = sig.get() as usize;
+    frame.rdx = sp + offset_of!(SigFrame, uc);
+
+    if act.flags.has(SignalFlags::SA_SIGINFO) {
+        // TODO: Fill the remaining fields once we have ksiginfo.
+        sf.si.signo = sig.get();
+
+        frame.rsi = sp + offset_of!(SigFrame, si);
+    } else {
+        frame.rsi = 0;
+    }
+
+    frame.rcx = 0;
+
+    td.proc().vm().write(sp, sf.as_bytes())?;
+
+    // Jump to the trampoline. TODO: RCX and R11 will be clobbered by sysret so we need a full iretq
+    // like PCB_FULL_IRET.
+    frame.rsp = sp;
+    frame.rip = SIGCODE_BASE;
+    frame.rflags &= !(PSL_T | PSL_D);
+
+    Ok(())
+}
+
+/// Restore `frame` and the signal mask from `ucontext_t` at `addr`.
+///
+/// See `sys_sigreturn` on the PS4 for a reference.
+pub fn sigreturn(td: &Thread, frame: &mut TrapFrame, addr: usize) -> Result<(), SysErr> {
+    // Read the context. All fields are integer so zeroed is a valid value.
+    let mut uc: UContext = unsafe { zeroed() };
+
+    td.proc().vm().read(addr, uc.as_bytes_mut())?;
+
+    // Prevent the user from changing the privileged flags and the code segment.
+    let mc = &uc.mcontext;
+
+    if ((mc.rflags ^ frame.rflags) & !PSL_USERCHANGE) != 0 || (mc.cs & 3) != 3 {
+        return Err(SysErr::Raw(EINVAL));
+    }
+
+    // Restore the frame. TODO: RCX and R11 will be clobbered by sysret so we need a full iretq like
+    // PCB_FULL_IRET.
+    frame.rdi = mc.rdi;
+    frame.rsi = mc.rsi;
+    frame.rdx = mc.rdx;
+    frame.rcx = mc.rcx;
+    frame.r8 = mc.r8;
+    frame.r9 = mc.r9;
+    frame.rax = mc.rax;
+    frame.rbx = mc.rbx;
+    frame.rbp = mc.rbp;
+    frame.r10 = mc.r10;
+    frame.r11 = mc.r11;
+    frame.r12 = mc.r12;
+    frame.r13 = mc.r13;
+    frame.r14 = mc.r14;
+    frame.r15 = mc.r15;
+    frame.rip = mc.rip;
+    frame.rflags = mc.rflags;
+    frame.rsp = mc.rsp;
+
+    // Restore the signal mask.
+    let mut mask = uc.sigmask;
+
+    mask.remove_uncatchable();
+
+    *td.sigmask_mut() = mask;
+
+    Ok(())
+}
+
+/// Trace trap.
+const PSL_T: usize = 0x100;
+
+/// String instruction direction bit.
+const PSL_D: usize = 0x400;
+
+/// Flags that the user can change with `sigreturn`.
+const PSL_USERCHANGE: usize = 0x244dd5;
+
+/// Implementation of `sigframe` structure.
+#[repr(C)]
+struct SigFrame {
+    handler: usize, // sf_ahu
+    pad: usize,
+    uc: UContext, // sf_uc
+    si: SigInfo,  // sf_si
+}
+
+impl SigFrame {
+    fn as_bytes(&self) -> &[u8] {
+        // SAFETY: The struct does not have any padding.
+        unsafe { core::slice::from_raw_parts((self as *const Self).cast(), size_of::<Self>()) }
+    }
+}
+
+const _: () = assert!(size_of::<SigFrame>() == 976);
+
+/// Implementation of `ucontext_t` structure.
+#[repr(C)]
+struct UContext {
+    sigmask: SignalSet, // uc_sigmask
+    mcontext: MContext, // uc_mcontext
+    link: usize,        // uc_link
+    stack: SignalStack, // uc_stack
+    flags: c_int,       // uc_flags
+    spare: [c_int; 4],  // __spare__
+    pad: [u8; 12],
+}
+
+impl UContext {
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        // SAFETY: The struct does not have any padding and all fields are integer.
+        unsafe { core::slice::from_raw_parts_mut((self as *mut Self).cast(), size_of::<Self>()) }
+    }
+}
+
+const _: () = assert!(size_of::<UContext>() == 880);
+
+/// Implementation of `mcontext_t` structure.
+#[repr(C)]
+struct MContext {
+    onstack: usize,     // mc_onstack
+    rdi: usize,         // mc_rdi
+    rsi: usize,         // mc_rsi
+    rdx: usize,         // mc_rdx
+    rcx: usize,         // mc_rcx
+    r8: usize,          // mc_r8
+    r9: usize,          // mc_r9
+    rax: usize,         // mc_rax
+    rbx: usize,         // mc_rbx
+    rbp: usize,         // mc_rbp
+    r10: usize,         // mc_r10
+    r11: usize,         // mc_r11
+    r12: usize,         // mc_r12
+    r13: usize,         // mc_r13
+    r14: usize,         // mc_r14
+    r15: usize,         // mc_r15
+    trapno: u32,        // mc_trapno
+    fs: u16,            // mc_fs
+    gs: u16,            // mc_gs
+    addr: usize,        // mc_addr
+    flags: u32,         // mc_flags
+    es: u16,            // mc_es
+    ds: u16,            // mc_ds
+    err: usize,         // mc_err
+    rip: usize,         // mc_rip
+    cs: usize,          // mc_cs
+    rflags: usize,      // mc_rflags
+    rsp: usize,         // mc_rsp
+    ss: usize,          // mc_ss
+    len: usize,         // mc_len
+    fpformat: usize,    // mc_fpformat
+    ownedfp: usize,     // mc_ownedfp
+    fpstate: [u64; 64], // mc_fpstate
+    fsbase: usize,      // mc_fsbase
+    gsbase: usize,      // mc_gsbase
+    spare: [usize; 6],  // mc_spare
+}
+
+const _: () = assert!(size_of::<MContext>() == 800);
+
+/// Implementation of `stack_t` structure.
+#[repr(C)]
+struct SignalStack {
+    sp: usize,    // ss_sp
+    size: usize,  // ss_size
+    flags: c_int, // ss_flags
+    pad: u32,
+}
+
+/// Implementation of `siginfo_t` structure.
+#[repr(C)]
+struct SigInfo {
+    signo: c_int,     // si_signo
+    errno: c_int,     // si_errno
+    code: c_int,      // si_code
+    pid: c_int,       // si_pid
+    uid: c_int,       // si_uid
+    status: c_int,    // si_status
+    addr: usize,      // si_addr
+    value: usize,     // si_value
+    reason: [u64; 5], // _reason
+}
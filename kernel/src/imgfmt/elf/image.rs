@@ -0,0 +1,600 @@
+use super::{FileInfo, FileInfoError, FileType, Program, ProgramFlags, ProgramType};
+use alloc::borrow::ToOwned;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt::{Display, Formatter};
+use core::ops::Range;
+use macros::bitflag;
+
+/// The first 8 bytes of SELF file.
+pub const SELF_MAGIC: [u8; 8] = [0x4f, 0x15, 0x3d, 0x1d, 0x00, 0x01, 0x01, 0x12];
+
+/// Represents a SELF or ELF image that was loaded into the memory.
+///
+/// The reason we need to support both SELF and ELF is because every SELF decryptors output ELF.
+/// See https://www.psdevwiki.com/ps4/SELF_File_Format for some basic information about SELF.
+pub struct Elf<'a> {
+    image: &'a [u8],
+    self_segments: Option<Vec<SelfSegment>>,
+    ty: FileType,
+    entry_addr: Option<usize>,
+    programs: Vec<Program>,
+    mapping: Range<usize>,
+    code: Option<usize>,
+    relro: Option<usize>,
+    data: Option<usize>,
+    dynamic: Option<usize>,
+    dyndata: Option<usize>,
+    tls: Option<usize>,
+    proc_param: Option<usize>,
+    mod_param: Option<usize>,
+    eh: Option<usize>,
+    info: Option<FileInfo>,
+}
+
+impl<'a> Elf<'a> {
+    /// See `exec_self_imgact` on the PS4 for a reference.
+    pub fn parse(image: &'a [u8]) -> Result<Self, OpenError> {
+        // Check if image is SELF.
+        let (offset, self_segments) = if image.starts_with(&SELF_MAGIC) {
+            // Kyty also checking if Category = 0x01 & Program Type = 0x01 & Padding = 0x00.
+            // Let's check only magic for now until something is broken.
+            if read_u16(image, 0x1a)? != 0x22 {
+                return Err(OpenError::InvalidSelfMagic);
+            }
+
+            // Load SELF segment headers.
+            let count: usize = read_u16(image, 0x18)?.into();
+            let mut segments = Vec::with_capacity(count);
+
+            for i in 0..count {
+                let off = 32 + i * 32;
+
+                segments.push(SelfSegment {
+                    flags: SelfSegmentFlags::from(read_u64(image, off)? as u64),
+                    offset: read_u64(image, off + 8)?,
+                    compressed_size: read_u64(image, off + 16)?,
+                    decompressed_size: read_u64(image, off + 24)?,
+                });
+            }
+
+            (32 + count * 32, Some(segments))
+        } else {
+            (0, None)
+        };
+
+        // Check ELF magic.
+        let hdr = image.get(offset..).ok_or(OpenError::TooSmall)?;
+
+        if !hdr.starts_with(b"\x7fELF") {
+            return Err(OpenError::InvalidElfMagic);
+        }
+
+        // Check ELF type.
+        if hdr.get(0x04) != Some(&2) {
+            return Err(OpenError::UnsupportedBitness);
+        }
+
+        if hdr.get(0x05) != Some(&1) {
+            return Err(OpenError::UnsupportedEndianness);
+        }
+
+        if read_u16(hdr, 0x36)? != 0x38 {
+            // PS4 make assumption that the program entry is 0x38 bytes.
+            return Err(OpenError::InvalidProgramEntrySize);
+        }
+
+        // Load ELF header.
+        let e_type = FileType::new(read_u16(hdr, 0x10)?);
+        let e_entry = read_u64(hdr, 0x18)?;
+        let e_phoff = offset + 0x40; // PS4 is hard-coded this value.
+        let e_phnum: usize = read_u16(hdr, 0x38)?.into();
+        let phdrs = image
+            .get(e_phoff..(e_phoff + e_phnum * Program::SIZE))
+            .ok_or(OpenError::TooSmall)?;
+
+        // Load program headers.
+        let mut elf = Self {
+            image,
+            self_segments,
+            ty: e_type,
+            entry_addr: match e_entry {
+                0 => None,
+                v => Some(v),
+            },
+            programs: Vec::with_capacity(e_phnum),
+            mapping: Range {
+                start: usize::MAX,
+                end: 0,
+            },
+            code: None,
+            relro: None,
+            data: None,
+            dynamic: None,
+            dyndata: None,
+            tls: None,
+            proc_param: None,
+            mod_param: None,
+            eh: None,
+            info: None,
+        };
+
+        for (i, h) in phdrs.chunks_exact(Program::SIZE).enumerate() {
+            let p = Program::parse(h);
+
+            match p.ty() {
+                ProgramType::PT_LOAD | ProgramType::PT_SCE_RELRO => elf.process_mappable(i, &p)?,
+                ProgramType::PT_DYNAMIC => elf.dynamic = Some(Self::check_sized(i, &p)?),
+                ProgramType::PT_TLS => elf.process_tls(i, &p)?,
+                ProgramType::PT_SCE_DYNLIBDATA => elf.dyndata = Some(Self::check_data(i, &p)?),
+                ProgramType::PT_SCE_PROCPARAM => elf.proc_param = Some(i),
+                ProgramType::PT_SCE_MODULEPARAM => elf.mod_param = Some(i),
+                ProgramType::PT_SCE_COMMENT => {
+                    Self::check_data(i, &p)?;
+                }
+                ProgramType::PT_GNU_EH_FRAME => elf.eh = Some(Self::check_sized(i, &p)?),
+                _ => {}
+            }
+
+            elf.programs.push(p);
+        }
+
+        // Check mapping range.
+        if elf.mapping.start == usize::MAX || elf.mapping.end == 0 {
+            return Err(OpenError::NoMappableProgram);
+        }
+
+        // Check dynamic linking.
+        if let Some(i) = elf.dynamic {
+            let dynamic = &elf.programs[i];
+
+            if dynamic.file_size() == 0 {
+                return Err(OpenError::InvalidDynamic);
+            }
+
+            let i = elf.dyndata.ok_or(OpenError::NoDynData)?;
+            let dyndata = &elf.programs[i];
+
+            if dyndata.file_size() == 0 {
+                return Err(OpenError::InvalidDynData);
+            }
+
+            // The offset of PT_DYNAMIC is relative to PT_SCE_DYNLIBDATA. It looks weird but this
+            // is how Sony actually did.
+            let start = dynamic
+                .offset()
+                .checked_sub(dyndata.offset())
+                .ok_or(OpenError::InvalidDynamic)?;
+            let end = start + dynamic.file_size();
+            let data = elf
+                .program_data(i)
+                .map_err(OpenError::ReadDynDataFailed)?
+                .to_owned();
+
+            elf.info = Some(FileInfo::parse(data, start..end).map_err(OpenError::InvalidInfo)?);
+        }
+
+        // Check PT_SCE_RELRO.
+        if let Some(i) = elf.relro {
+            let relro = &elf.programs[i];
+
+            if relro.addr() == 0 {
+                return Err(OpenError::InvalidRelroAddr);
+            } else if relro.memory_size() == 0 {
+                return Err(OpenError::InvalidRelroSize);
+            }
+
+            // Check if PT_SCE_RELRO follows the code.
+            if let Some(i) = elf.code {
+                let code = &elf.programs[i];
+
+                if Program::align_2mb(code.end()) != relro.addr()
+                    && Program::align_page(code.end()) != relro.addr()
+                {
+                    return Err(OpenError::InvalidRelroAddr);
+                }
+            };
+
+            // Check if data follows the PT_SCE_RELRO.
+            if let Some(i) = elf.data {
+                let data = &elf.programs[i];
+
+                if Program::align_2mb(relro.end()) != data.addr()
+                    && Program::align_page(relro.end()) != data.addr()
+                {
+                    return Err(OpenError::InvalidDataAddr(i));
+                }
+            }
+        }
+
+        Ok(elf)
+    }
+
+    pub fn ty(&self) -> FileType {
+        self.ty
+    }
+
+    pub fn entry_addr(&self) -> Option<usize> {
+        self.entry_addr
+    }
+
+    pub fn programs(&self) -> &[Program] {
+        &self.programs
+    }
+
+    /// Returns a range of the virtual address that covers all mappable programs.
+    pub fn mapping(&self) -> &Range<usize> {
+        &self.mapping
+    }
+
+    /// Returns index of the executable `PT_LOAD`.
+    pub fn code(&self) -> Option<usize> {
+        self.code
+    }
+
+    /// Returns index of the `PT_SCE_RELRO`.
+    pub fn relro(&self) -> Option<usize> {
+        self.relro
+    }
+
+    /// Returns index of the first non-executable `PT_LOAD`.
+    pub fn data(&self) -> Option<usize> {
+        self.data
+    }
+
+    pub fn dynamic(&self) -> Option<usize> {
+        self.dynamic
+    }
+
+    pub fn tls(&self) -> Option<usize> {
+        self.tls
+    }
+
+    pub fn proc_param(&self) -> Option<usize> {
+        self.proc_param
+    }
+
+    pub fn mod_param(&self) -> Option<usize> {
+        self.mod_param
+    }
+
+    /// Returns index of `PT_GNU_EH_FRAME`.
+    pub fn eh(&self) -> Option<usize> {
+        self.eh
+    }
+
+    /// Returns [`None`] if the image is not dynamic linking.
+    pub fn info(&self) -> Option<&FileInfo> {
+        self.info.as_ref()
+    }
+
+    pub fn into_info(self) -> Option<FileInfo> {
+        self.info
+    }
+
+    /// Returns the file data of the program at `index`.
+    pub fn program_data(&self, index: usize) -> Result<&'a [u8], ReadProgramError> {
+        let prog = self
+            .programs
+            .get(index)
+            .ok_or(ReadProgramError::InvalidIndex)?;
+        let len = prog.file_size();
+        let offset = match &self.self_segments {
+            Some(v) => self.self_offset(v, prog)?,
+            None => prog.offset(),
+        };
+
+        offset
+            .checked_add(len)
+            .and_then(|end| self.image.get(offset..end))
+            .ok_or(ReadProgramError::OutOfBounds)
+    }
+
+    fn process_mappable(&mut self, index: usize, prog: &Program) -> Result<(), OpenError> {
+        // Check offset.
+        let ty = prog.ty();
+
+        if prog.offset() > 0xffffffff || prog.offset() & 0x3fff != 0 {
+            return Err(OpenError::InvalidOffset(index, ty));
+        }
+
+        // Check address.
+        let addr = prog.addr();
+
+        if addr & 0x3fff != 0 {
+            return Err(OpenError::InvalidAddr(index, ty));
+        } else if prog.alignment() & 0x3fff != 0 {
+            return Err(OpenError::InvalidAligment(index, ty));
+        }
+
+        // Check size.
+        let memory_size = prog.memory_size();
+
+        if prog.file_size() > memory_size {
+            return Err(OpenError::InvalidFileSize(index, ty));
+        } else if memory_size > 0x7fffffff {
+            return Err(OpenError::InvalidMemSize(index, ty));
+        }
+
+        // Update mapping range. The address is already page-aligned so we can align the size
+        // instead.
+        let end = addr
+            .checked_add(Program::align_page(memory_size))
+            .ok_or(OpenError::InvalidAddr(index, ty))?;
+
+        self.mapping.start = self.mapping.start.min(addr);
+        self.mapping.end = self.mapping.end.max(end);
+
+        // Keep index of the header.
+        if ty == ProgramType::PT_SCE_RELRO {
+            self.relro = Some(index);
+        } else if prog.flags().has(ProgramFlags::Execute) {
+            self.code = Some(index);
+        } else if self.data.is_none() {
+            self.data = Some(index);
+        }
+
+        Ok(())
+    }
+
+    fn process_tls(&mut self, index: usize, prog: &Program) -> Result<(), OpenError> {
+        let index = Self::check_sized(index, prog)?;
+
+        if prog.alignment() > 32 {
+            return Err(OpenError::InvalidAligment(index, prog.ty()));
+        }
+
+        self.tls = Some(index);
+
+        Ok(())
+    }
+
+    /// Check the program that has both file data and memory data.
+    fn check_sized(index: usize, prog: &Program) -> Result<usize, OpenError> {
+        let ty = prog.ty();
+
+        if prog.offset() > 0xffffffff {
+            return Err(OpenError::InvalidOffset(index, ty));
+        }
+
+        if prog.file_size() > prog.memory_size() {
+            Err(OpenError::InvalidFileSize(index, ty))
+        } else if prog.memory_size() > 0x7fffffff {
+            Err(OpenError::InvalidMemSize(index, ty))
+        } else {
+            Ok(index)
+        }
+    }
+
+    /// Check the program that has only file data.
+    fn check_data(index: usize, prog: &Program) -> Result<usize, OpenError> {
+        let ty = prog.ty();
+
+        if prog.offset() > 0xffffffff {
+            return Err(OpenError::InvalidOffset(index, ty));
+        }
+
+        if prog.file_size() > 0x7fffffff {
+            Err(OpenError::InvalidFileSize(index, ty))
+        } else if prog.memory_size() != 0 {
+            Err(OpenError::InvalidMemSize(index, ty))
+        } else {
+            Ok(index)
+        }
+    }
+
+    /// Returns the offset in the SELF for `prog`.
+    fn self_offset(&self, segs: &[SelfSegment], prog: &Program) -> Result<usize, ReadProgramError> {
+        let offset = prog.offset();
+        let len = prog.file_size();
+
+        for (i, seg) in segs.iter().enumerate() {
+            // Skip if not blocked segment.
+            let flags = seg.flags;
+
+            if !flags.has(SelfSegmentFlags::SF_BFLG) {
+                continue;
+            }
+
+            // Check if the target offset inside the associated program.
+            let prog = self
+                .programs
+                .get(flags.program())
+                .ok_or(ReadProgramError::NoSegment)?;
+
+            let end = prog
+                .offset()
+                .checked_add(prog.file_size())
+                .ok_or(ReadProgramError::OutOfBounds)?;
+
+            if offset < prog.offset() || offset >= end {
+                continue;
+            }
+
+            // Check if segment supported.
+            if flags.has(SelfSegmentFlags::SF_ENCR) {
+                return Err(ReadProgramError::EncryptedSegment(i));
+            } else if seg.compressed_size != seg.decompressed_size {
+                return Err(ReadProgramError::CompressedSegment(i));
+            }
+
+            // Get data offset.
+            let offset = offset - prog.offset();
+
+            if offset
+                .checked_add(len)
+                .is_none_or(|end| end > seg.decompressed_size)
+            {
+                return Err(ReadProgramError::OutOfBounds);
+            }
+
+            return offset
+                .checked_add(seg.offset)
+                .ok_or(ReadProgramError::OutOfBounds);
+        }
+
+        Err(ReadProgramError::NoSegment)
+    }
+}
+
+/// Represents a SELF segment.
+pub struct SelfSegment {
+    flags: SelfSegmentFlags,
+    offset: usize,
+    compressed_size: usize,
+    decompressed_size: usize,
+}
+
+/// Represents flags of SELF segment.
+#[bitflag(u64)]
+pub enum SelfSegmentFlags {
+    SF_ENCR = 0x0000000000000002,
+    SF_BFLG = 0x0000000000000800,
+}
+
+impl SelfSegmentFlags {
+    /// Returns index of the program this segment belong to.
+    pub fn program(self) -> usize {
+        ((self.0 >> 20) & 0xfff) as usize
+    }
+}
+
+fn read_u16(data: &[u8], off: usize) -> Result<u16, OpenError> {
+    data.get(off..(off + 2))
+        .map(|v| u16::from_le_bytes(v.try_into().unwrap()))
+        .ok_or(OpenError::TooSmall)
+}
+
+fn read_u64(data: &[u8], off: usize) -> Result<usize, OpenError> {
+    data.get(off..(off + 8))
+        .map(|v| u64::from_le_bytes(v.try_into().unwrap()) as usize)
+        .ok_or(OpenError::TooSmall)
+}
+
+/// Represents an error when [`Elf::parse()`] fails.
+#[derive(Debug)]
+pub enum OpenError {
+    TooSmall,
+    InvalidSelfMagic,
+    InvalidElfMagic,
+    UnsupportedBitness,
+    UnsupportedEndianness,
+    InvalidProgramEntrySize,
+    InvalidOffset(usize, ProgramType),
+    InvalidAddr(usize, ProgramType),
+    InvalidAligment(usize, ProgramType),
+    InvalidFileSize(usize, ProgramType),
+    InvalidMemSize(usize, ProgramType),
+    NoMappableProgram,
+    InvalidDynamic,
+    NoDynData,
+    InvalidDynData,
+    ReadDynDataFailed(ReadProgramError),
+    InvalidInfo(FileInfoError),
+    InvalidRelroAddr,
+    InvalidRelroSize,
+    InvalidDataAddr(usize),
+}
+
+impl Error for OpenError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::ReadDynDataFailed(e) => Some(e),
+            Self::InvalidInfo(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Display for OpenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::TooSmall => f.write_str("the image is too small"),
+            Self::InvalidSelfMagic => f.write_str("invalid SELF magic"),
+            Self::InvalidElfMagic => f.write_str("invalid ELF magic"),
+            Self::UnsupportedBitness => f.write_str("unsupported bitness"),
+            Self::UnsupportedEndianness => f.write_str("unsupported endianness"),
+            Self::InvalidProgramEntrySize => f.write_str("e_phentsize is not valid"),
+            Self::InvalidOffset(i, t) => write!(f, "{t} at program {i} has invalid file offset"),
+            Self::InvalidAddr(i, t) => write!(f, "{t} at program {i} has invalid address"),
+            Self::InvalidAligment(i, t) => write!(f, "{t} at program {i} has invalid aligment"),
+            Self::InvalidFileSize(i, t) => write!(f, "{t} at program {i} has invalid file size"),
+            Self::InvalidMemSize(i, t) => write!(f, "{t} at program {i} has invalid memory size"),
+            Self::NoMappableProgram => f.write_str("no mappable program"),
+            Self::InvalidDynamic => f.write_str("PT_DYNAMIC is not valid"),
+            Self::NoDynData => f.write_str("no PT_SCE_DYNLIBDATA"),
+            Self::InvalidDynData => f.write_str("PT_SCE_DYNLIBDATA is not valid"),
+            Self::ReadDynDataFailed(_) => f.write_str("couldn't read PT_SCE_DYNLIBDATA"),
+            Self::InvalidInfo(_) => f.write_str("the dynamic linking information is not valid"),
+            Self::InvalidRelroAddr => f.write_str("PT_SCE_RELRO has invalid address"),
+            Self::InvalidRelroSize => f.write_str("PT_SCE_RELRO has invalid size"),
+            Self::InvalidDataAddr(i) => write!(f, "PT_LOAD at program {i} has invalid address"),
+        }
+    }
+}
+
+/// Represents an error when [`Elf::program_data()`] fails.
+#[derive(Debug)]
+pub enum ReadProgramError {
+    InvalidIndex,
+    EncryptedSegment(usize),
+    CompressedSegment(usize),
+    NoSegment,
+    OutOfBounds,
+}
+
+impl Error for ReadProgramError {}
+
+impl Display for ReadProgramError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidIndex => f.write_str("invalid program index"),
+            Self::EncryptedSegment(i) => write!(f, "SELF segment #{i} is encrypted"),
+            Self::CompressedSegment(i) => write!(f, "SELF segment #{i} is compressed"),
+            Self::NoSegment => f.write_str("no SELF segment for the program"),
+            Self::OutOfBounds => f.write_str("the program data is outside the image"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn parse_elf() {
+        // Build an ELF with a single PT_LOAD.
+        let mut image = vec![0u8; 0x8000];
+
+        image[..4].copy_from_slice(b"\x7fELF");
+        image[0x04] = 2;
+        image[0x05] = 1;
+        image[0x10..0x12].copy_from_slice(&0xfe00u16.to_le_bytes());
+        image[0x18..0x20].copy_from_slice(&0x4010u64.to_le_bytes());
+        image[0x36..0x38].copy_from_slice(&0x38u16.to_le_bytes());
+        image[0x38..0x3a].copy_from_slice(&1u16.to_le_bytes());
+
+        let phdr = &mut image[0x40..0x78];
+
+        phdr[0x00..0x04].copy_from_slice(&1u32.to_le_bytes());
+        phdr[0x04..0x08].copy_from_slice(&5u32.to_le_bytes());
+        phdr[0x08..0x10].copy_from_slice(&0x4000u64.to_le_bytes());
+        phdr[0x10..0x18].copy_from_slice(&0x4000u64.to_le_bytes());
+        phdr[0x20..0x28].copy_from_slice(&0x10u64.to_le_bytes());
+        phdr[0x28..0x30].copy_from_slice(&0x5000u64.to_le_bytes());
+        phdr[0x30..0x38].copy_from_slice(&0x4000u64.to_le_bytes());
+
+        image[0x4000] = 0xcc;
+
+        // Parse.
+        let elf = Elf::parse(&image).unwrap();
+
+        assert!(elf.ty() == FileType::ET_SCE_EXEC);
+        assert_eq!(elf.entry_addr(), Some(0x4010));
+        assert_eq!(*elf.mapping(), 0x4000..0xc000);
+        assert_eq!(elf.program_data(0).unwrap()[0], 0xcc);
+        assert_eq!(elf.program_data(0).unwrap().len(), 0x10);
+        assert!(Elf::parse(&image[..0x40]).is_err());
+    }
+}
@@ -0,0 +1,409 @@
+use super::{DynamicEntries, DynamicTag, Relocations, Symbols};
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt::{Display, Formatter};
+use core::ops::Range;
+
+/// An object that is initialized by `acquire_per_file_info_obj`.
+pub struct FileInfo {
+    data: Vec<u8>,            // PT_SCE_DYNLIBDATA
+    dynamic: Range<usize>,    // PT_DYNAMIC
+    relocs: Range<usize>,     // DT_SCE_RELA + DT_SCE_RELASZ
+    plt_relocs: Range<usize>, // DT_SCE_JMPREL + DT_SCE_PLTRELSZ
+    strtab: Range<usize>,     // DT_SCE_STRTAB + DT_SCE_STRSZ
+    symtab: Range<usize>,     // DT_SCE_SYMTAB + DT_SCE_SYMTABSZ
+    buckets: Vec<u32>,
+    chains: Vec<u32>,
+}
+
+impl FileInfo {
+    /// `dynamic` is the range of `PT_DYNAMIC` within `data`.
+    pub fn parse(data: Vec<u8>, dynamic: Range<usize>) -> Result<Self, FileInfoError> {
+        let mut pltrelsz = None;
+        let mut relasz = None;
+        let mut relaent = false;
+        let mut strsz = None;
+        let mut syment = false;
+        let mut pltrel = false;
+        let mut fingerprint = false;
+        let mut filename = false;
+        let mut module_info = false;
+        let mut hash = None;
+        let mut pltgot = false;
+        let mut jmprel = None;
+        let mut rela = None;
+        let mut strtab = None;
+        let mut symtab = None;
+        let mut hashsz = None;
+        let mut symtabsz = None;
+        let entries = data
+            .get(dynamic.clone())
+            .ok_or(FileInfoError::InvalidDynamic)?;
+
+        for (tag, value) in DynamicEntries::new(entries) {
+            let value = u64::from_le_bytes(value) as usize;
+
+            match tag {
+                DynamicTag::DT_NULL => break,
+                DynamicTag::DT_PLTRELSZ | DynamicTag::DT_SCE_PLTRELSZ => pltrelsz = Some(value),
+                DynamicTag::DT_PLTGOT
+                | DynamicTag::DT_RPATH
+                | DynamicTag::DT_BIND_NOW
+                | DynamicTag::DT_RUNPATH
+                | DynamicTag::DT_ENCODING
+                | DynamicTag::DT_SCE_UNK2
+                | DynamicTag::DT_SCE_UNK3
+                | DynamicTag::DT_SCE_UNK4
+                | DynamicTag::DT_SCE_UNK5
+                | DynamicTag::DT_SCE_UNK6
+                | DynamicTag::DT_SCE_UNK7
+                | DynamicTag::DT_SCE_UNK8
+                | DynamicTag::DT_SCE_UNK9
+                | DynamicTag::DT_SCE_UNK10
+                | DynamicTag::DT_SCE_UNK11
+                | DynamicTag::DT_SCE_UNK12
+                | DynamicTag::DT_SCE_UNK13
+                | DynamicTag::DT_SCE_UNK14
+                | DynamicTag::DT_SCE_STUB_MODULE_NAME
+                | DynamicTag::DT_SCE_UNK16
+                | DynamicTag::DT_SCE_STUB_MODULE_VERSION
+                | DynamicTag::DT_SCE_UNK18
+                | DynamicTag::DT_SCE_STUB_LIBRARY_NAME
+                | DynamicTag::DT_SCE_UNK20
+                | DynamicTag::DT_SCE_STUB_LIBRARY_VERSION
+                | DynamicTag::DT_SCE_UNK22
+                | DynamicTag::DT_SCE_UNK23
+                | DynamicTag::DT_SCE_UNK24
+                | DynamicTag::DT_SCE_UNK25
+                | DynamicTag::DT_SCE_UNK26
+                | DynamicTag::DT_SCE_UNK27
+                | DynamicTag::DT_SCE_UNK28
+                | DynamicTag::DT_SCE_UNK29
+                | DynamicTag::DT_SCE_UNK30
+                | DynamicTag::DT_SCE_UNK31
+                | DynamicTag::DT_SCE_UNK32
+                | DynamicTag::DT_SCE_UNK33
+                | DynamicTag::DT_SCE_UNK34
+                | DynamicTag::DT_SCE_UNK35 => return Err(FileInfoError::UnsupportedTag(tag)),
+                DynamicTag::DT_HASH
+                | DynamicTag::DT_STRTAB
+                | DynamicTag::DT_SYMTAB
+                | DynamicTag::DT_RELA
+                | DynamicTag::DT_JMPREL
+                | DynamicTag::DT_REL
+                | DynamicTag::DT_RELSZ
+                | DynamicTag::DT_RELENT => return Err(FileInfoError::OrbisUnsupported(tag)),
+                DynamicTag::DT_RELASZ | DynamicTag::DT_SCE_RELASZ => relasz = Some(value),
+                DynamicTag::DT_RELAENT | DynamicTag::DT_SCE_RELAENT => {
+                    if value != 24 {
+                        return Err(FileInfoError::InvalidRelaent);
+                    }
+
+                    relaent = true;
+                }
+                DynamicTag::DT_STRSZ | DynamicTag::DT_SCE_STRSZ => strsz = Some(value),
+                DynamicTag::DT_SYMENT | DynamicTag::DT_SCE_SYMENT => {
+                    if value != 24 {
+                        return Err(FileInfoError::InvalidSyment);
+                    }
+
+                    syment = true;
+                }
+                DynamicTag::DT_PLTREL | DynamicTag::DT_SCE_PLTREL => {
+                    if value != 7 {
+                        return Err(FileInfoError::InvalidPltrel);
+                    }
+
+                    pltrel = true;
+                }
+                DynamicTag::DT_NEEDED
+                | DynamicTag::DT_INIT
+                | DynamicTag::DT_FINI
+                | DynamicTag::DT_SONAME
+                | DynamicTag::DT_SYMBOLIC
+                | DynamicTag::DT_DEBUG
+                | DynamicTag::DT_TEXTREL
+                | DynamicTag::DT_INIT_ARRAY
+                | DynamicTag::DT_FINI_ARRAY
+                | DynamicTag::DT_INIT_ARRAYSZ
+                | DynamicTag::DT_FINI_ARRAYSZ
+                | DynamicTag::DT_FLAGS
+                | DynamicTag::DT_PREINIT_ARRAY
+                | DynamicTag::DT_PREINIT_ARRAYSZ
+                | DynamicTag::DT_SCE_UNK1
+                | DynamicTag::DT_SCE_NEEDED_MODULE
+                | DynamicTag::DT_SCE_MODULE_ATTR
+                | DynamicTag::DT_SCE_EXPORT_LIB
+                | DynamicTag::DT_SCE_IMPORT_LIB
+                | DynamicTag::DT_SCE_EXPORT_LIB_ATTR
+                | DynamicTag::DT_SCE_IMPORT_LIB_ATTR
+                | DynamicTag::DT_SCE_UNK36
+                | DynamicTag::DT_SCE_UNK37 => {}
+                DynamicTag::DT_SCE_FINGERPRINT => fingerprint = true,
+                DynamicTag::DT_SCE_ORIGINAL_FILENAME => filename = true,
+                DynamicTag::DT_SCE_MODULE_INFO => module_info = true,
+                DynamicTag::DT_SCE_HASH => hash = Some(value),
+                DynamicTag::DT_SCE_PLTGOT => pltgot = true,
+                DynamicTag::DT_SCE_JMPREL => jmprel = Some(value),
+                DynamicTag::DT_SCE_RELA => rela = Some(value),
+                DynamicTag::DT_SCE_STRTAB => strtab = Some(value),
+                DynamicTag::DT_SCE_SYMTAB => symtab = Some(value),
+                DynamicTag::DT_SCE_HASHSZ => hashsz = Some(value),
+                DynamicTag::DT_SCE_SYMTABSZ => symtabsz = Some(value),
+                v => return Err(FileInfoError::UnknownTag(v)),
+            }
+        }
+
+        // Check required tags.
+        let pltrelsz = pltrelsz.ok_or(FileInfoError::NoPltrelsz)?;
+        let relasz = relasz.ok_or(FileInfoError::NoRelasz)?;
+        let strsz = strsz.ok_or(FileInfoError::NoStrsz)?;
+        let hash = hash.ok_or(FileInfoError::NoHash)?;
+        let jmprel = jmprel.ok_or(FileInfoError::NoJmprel)?;
+        let rela = rela.ok_or(FileInfoError::NoRela)?;
+        let strtab = strtab.ok_or(FileInfoError::NoStrtab)?;
+        let symtab = symtab.ok_or(FileInfoError::NoSymtab)?;
+        let hashsz = hashsz.ok_or(FileInfoError::NoHashsz)?;
+        let symtabsz = symtabsz.ok_or(FileInfoError::NoSymtabsz)?;
+
+        if !relaent {
+            return Err(FileInfoError::NoRelaent);
+        } else if !syment {
+            return Err(FileInfoError::NoSyment);
+        } else if !pltrel {
+            return Err(FileInfoError::NoPltrel);
+        } else if !fingerprint {
+            return Err(FileInfoError::NoFingerprint);
+        } else if !filename {
+            return Err(FileInfoError::NoFilename);
+        } else if !module_info {
+            return Err(FileInfoError::NoModuleInfo);
+        } else if !pltgot {
+            return Err(FileInfoError::NoPltgot);
+        }
+
+        // The PS4 does not check if the tables are inside the data but we need to.
+        let table = |off: usize, len: usize, e: FileInfoError| {
+            off.checked_add(len)
+                .filter(|&end| end <= data.len())
+                .map(|end| off..end)
+                .ok_or(e)
+        };
+
+        let relocs = table(rela, relasz, FileInfoError::InvalidRela)?;
+        let plt_relocs = table(jmprel, pltrelsz, FileInfoError::InvalidJmprel)?;
+        let strtab = table(strtab, strsz, FileInfoError::InvalidStrtab)?;
+        let symtab = table(symtab, symtabsz, FileInfoError::InvalidSymtab)?;
+        let hash = table(hash, hashsz, FileInfoError::InvalidHash)?;
+
+        // Read hash table.
+        let hash = &data[hash];
+        let read = |i: usize| {
+            hash.get((i * 4)..(i * 4 + 4))
+                .map(|v| u32::from_le_bytes(v.try_into().unwrap()))
+                .ok_or(FileInfoError::InvalidHash)
+        };
+
+        let nbuckets = read(0)? as usize;
+        let nchains = read(1)? as usize;
+        let mut buckets = Vec::with_capacity(nbuckets);
+        let mut chains = Vec::with_capacity(nchains);
+
+        for i in 0..nbuckets {
+            buckets.push(read(2 + i)?);
+        }
+
+        for i in 0..nchains {
+            chains.push(read(2 + nbuckets + i)?);
+        }
+
+        if buckets.is_empty() {
+            return Err(FileInfoError::InvalidHash);
+        }
+
+        // TODO: Check acquire_per_file_info_obj to see what we have missing here.
+        Ok(Self {
+            data,
+            dynamic,
+            relocs,
+            plt_relocs,
+            strtab,
+            symtab,
+            buckets,
+            chains,
+        })
+    }
+
+    pub fn dynamic(&self) -> DynamicEntries<'_> {
+        DynamicEntries::new(&self.data[self.dynamic.clone()])
+    }
+
+    pub fn reloc_count(&self) -> usize {
+        self.relocs.len() / 24
+    }
+
+    pub fn relocs(&self) -> Relocations<'_> {
+        Relocations::new(&self.data[self.relocs.clone()])
+    }
+
+    pub fn plt_count(&self) -> usize {
+        self.plt_relocs.len() / 24
+    }
+
+    pub fn plt_relocs(&self) -> Relocations<'_> {
+        Relocations::new(&self.data[self.plt_relocs.clone()])
+    }
+
+    pub fn symbol_count(&self) -> usize {
+        self.symtab.len() / 24
+    }
+
+    pub fn symbols(&self) -> Symbols<'_> {
+        Symbols::new(&self.data[self.symtab.clone()], self)
+    }
+
+    pub fn buckets(&self) -> &[u32] {
+        &self.buckets
+    }
+
+    pub fn chains(&self) -> &[u32] {
+        &self.chains
+    }
+
+    /// Returns the name and the ID of `DT_SCE_MODULE_INFO` or `DT_SCE_NEEDED_MODULE`.
+    pub fn read_module(&self, data: [u8; 8]) -> Result<(&str, u16), StringTableError> {
+        self.read_id(data)
+    }
+
+    /// Returns the name and the ID of `DT_SCE_EXPORT_LIB` or `DT_SCE_IMPORT_LIB`.
+    pub fn read_library(&self, data: [u8; 8]) -> Result<(&str, u16), StringTableError> {
+        self.read_id(data)
+    }
+
+    /// `offset` is relative to `PT_DYNAMIC`, which is how the PS4 did.
+    pub fn read_fingerprint(&self, offset: usize) -> Option<[u8; 20]> {
+        let offset = self.dynamic.start.checked_add(offset)?;
+
+        self.data
+            .get(offset..offset.checked_add(20)?)
+            .map(|v| v.try_into().unwrap())
+    }
+
+    pub fn read_str(&self, offset: usize) -> Result<&str, StringTableError> {
+        // Get raw string.
+        let tab = &self.data[self.strtab.clone()];
+        let raw = match tab.get(offset..) {
+            Some(v) if !v.is_empty() => v,
+            _ => return Err(StringTableError::InvalidOffset),
+        };
+
+        // Find a NULL-terminated.
+        let raw = match raw.iter().position(|&b| b == 0) {
+            Some(i) => &raw[..i],
+            None => return Err(StringTableError::NotCString),
+        };
+
+        core::str::from_utf8(raw).map_err(|_| StringTableError::NotUtf8)
+    }
+
+    fn read_id(&self, data: [u8; 8]) -> Result<(&str, u16), StringTableError> {
+        let name = u32::from_le_bytes(data[..4].try_into().unwrap());
+        let id = u16::from_le_bytes(data[6..].try_into().unwrap());
+
+        Ok((self.read_str(name as usize)?, id))
+    }
+}
+
+/// Represents an error when [`FileInfo::parse()`] fails.
+#[derive(Debug)]
+pub enum FileInfoError {
+    InvalidDynamic,
+    UnknownTag(DynamicTag),
+    UnsupportedTag(DynamicTag),
+    OrbisUnsupported(DynamicTag),
+    NoPltrelsz,
+    NoRelasz,
+    InvalidRelaent,
+    NoRelaent,
+    NoStrsz,
+    InvalidSyment,
+    NoSyment,
+    InvalidPltrel,
+    NoPltrel,
+    NoFingerprint,
+    NoFilename,
+    NoModuleInfo,
+    NoHash,
+    NoPltgot,
+    NoJmprel,
+    NoRela,
+    NoStrtab,
+    NoSymtab,
+    NoHashsz,
+    NoSymtabsz,
+    InvalidRela,
+    InvalidJmprel,
+    InvalidStrtab,
+    InvalidSymtab,
+    InvalidHash,
+}
+
+impl Error for FileInfoError {}
+
+impl Display for FileInfoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidDynamic => f.write_str("PT_DYNAMIC is outside PT_SCE_DYNLIBDATA"),
+            Self::UnknownTag(t) => write!(f, "unknown tag {t}"),
+            Self::UnsupportedTag(t) => write!(f, "tag {t} is not supported"),
+            Self::OrbisUnsupported(t) => write!(f, "Orbis object file does not support tag {t}"),
+            Self::NoPltrelsz => f.write_str("no DT_PLTRELSZ or DT_SCE_PLTRELSZ"),
+            Self::NoRelasz => f.write_str("no DT_RELASZ or DT_SCE_RELASZ"),
+            Self::InvalidRelaent => f.write_str("DT_RELAENT or DT_SCE_RELAENT has invalid value"),
+            Self::NoRelaent => f.write_str("no DT_RELAENT or DT_SCE_RELAENT"),
+            Self::NoStrsz => f.write_str("no DT_STRSZ or DT_SCE_STRSZ"),
+            Self::InvalidSyment => f.write_str("DT_SYMENT or DT_SCE_SYMENT has invalid value"),
+            Self::NoSyment => f.write_str("no DT_SYMENT or DT_SCE_SYMENT"),
+            Self::InvalidPltrel => f.write_str("DT_PLTREL or DT_SCE_PLTREL has invalid value"),
+            Self::NoPltrel => f.write_str("no DT_PLTREL or DT_SCE_PLTREL"),
+            Self::NoFingerprint => f.write_str("no DT_SCE_FINGERPRINT"),
+            Self::NoFilename => f.write_str("no DT_SCE_ORIGINAL_FILENAME"),
+            Self::NoModuleInfo => f.write_str("no DT_SCE_MODULE_INFO"),
+            Self::NoHash => f.write_str("no DT_SCE_HASH"),
+            Self::NoPltgot => f.write_str("no DT_SCE_PLTGOT"),
+            Self::NoJmprel => f.write_str("no DT_SCE_JMPREL"),
+            Self::NoRela => f.write_str("no DT_SCE_RELA"),
+            Self::NoStrtab => f.write_str("no DT_SCE_STRTAB"),
+            Self::NoSymtab => f.write_str("no DT_SCE_SYMTAB"),
+            Self::NoHashsz => f.write_str("no DT_SCE_HASHSZ"),
+            Self::NoSymtabsz => f.write_str("no DT_SCE_SYMTABSZ"),
+            Self::InvalidRela => f.write_str("DT_SCE_RELA is not valid"),
+            Self::InvalidJmprel => f.write_str("DT_SCE_JMPREL is not valid"),
+            Self::InvalidStrtab => f.write_str("DT_SCE_STRTAB is not valid"),
+            Self::InvalidSymtab => f.write_str("DT_SCE_SYMTAB is not valid"),
+            Self::InvalidHash => f.write_str("DT_SCE_HASH is not valid"),
+        }
+    }
+}
+
+/// Represents an error when string table lookup fails.
+#[derive(Debug)]
+pub enum StringTableError {
+    InvalidOffset,
+    NotCString,
+    NotUtf8,
+}
+
+impl Error for StringTableError {}
+
+impl Display for StringTableError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidOffset => {
+                f.write_str("the offset is not a valid offset in the string table")
+            }
+            Self::NotCString => f.write_str("the offset is not a C string"),
+            Self::NotUtf8 => f.write_str("the offset is not a UTF-8 string"),
+        }
+    }
+}
@@ -1,5 +1,21 @@
+pub use self::dynamic::*;
+pub use self::image::*;
+pub use self::info::*;
+pub use self::program::*;
+pub use self::reloc::*;
+pub use self::symbol::*;
+pub use self::ty::*;
+
 use core::ops::Deref;
 
+mod dynamic;
+mod image;
+mod info;
+mod program;
+mod reloc;
+mod symbol;
+mod ty;
+
 /// Single ELF note.
 #[repr(C)]
 pub struct Note<const N: usize, const D: usize> {
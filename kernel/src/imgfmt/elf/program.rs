@@ -0,0 +1,140 @@
+use core::fmt::{Display, Formatter};
+use macros::bitflag;
+
+/// Contains information for each ELF program.
+pub struct Program {
+    ty: ProgramType,     // p_type
+    flags: ProgramFlags, // p_flags
+    offset: usize,       // p_offset
+    addr: usize,         // p_vaddr
+    file_size: usize,    // p_filesz
+    memory_size: usize,  // p_memsz
+    alignment: usize,    // p_align
+}
+
+impl Program {
+    /// Size of `Elf64_Phdr`.
+    pub const SIZE: usize = 0x38;
+
+    /// Parse `Elf64_Phdr` from `hdr`.
+    ///
+    /// # Panics
+    /// If `hdr` is smaller than [`Self::SIZE`].
+    pub fn parse(hdr: &[u8]) -> Self {
+        let u32 = |i: usize| u32::from_le_bytes(hdr[i..(i + 4)].try_into().unwrap());
+        let u64 = |i: usize| u64::from_le_bytes(hdr[i..(i + 8)].try_into().unwrap()) as usize;
+
+        Self {
+            ty: ProgramType::new(u32(0x00)),
+            flags: ProgramFlags::from(u32(0x04)),
+            offset: u64(0x08),
+            addr: u64(0x10),
+            file_size: u64(0x20),
+            memory_size: u64(0x28),
+            alignment: u64(0x30),
+        }
+    }
+
+    pub fn ty(&self) -> ProgramType {
+        self.ty
+    }
+
+    pub fn flags(&self) -> ProgramFlags {
+        self.flags
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn addr(&self) -> usize {
+        self.addr
+    }
+
+    pub fn end(&self) -> usize {
+        self.addr + self.memory_size
+    }
+
+    pub fn file_size(&self) -> usize {
+        self.file_size
+    }
+
+    pub fn memory_size(&self) -> usize {
+        self.memory_size
+    }
+
+    pub fn alignment(&self) -> usize {
+        self.alignment
+    }
+
+    /// Align `v` to 16K, which is a page size on the PS4.
+    pub fn align_page(v: usize) -> usize {
+        (v + 0x3fff) & !0x3fff
+    }
+
+    pub fn align_2mb(v: usize) -> usize {
+        (v + 0x1fffff) & !0x1fffff
+    }
+}
+
+/// Represents type of an ELF program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgramType(u32);
+
+impl ProgramType {
+    pub const PT_NULL: Self = Self(0x0);
+    pub const PT_LOAD: Self = Self(0x1);
+    pub const PT_DYNAMIC: Self = Self(0x2);
+    pub const PT_INTERP: Self = Self(0x3);
+    pub const PT_NOTE: Self = Self(0x4);
+    pub const PT_SHLIB: Self = Self(0x5);
+    pub const PT_PHDR: Self = Self(0x6);
+    pub const PT_TLS: Self = Self(0x7);
+    pub const PT_SCE_DYNLIBDATA: Self = Self(0x61000000);
+    pub const PT_SCE_PROCPARAM: Self = Self(0x61000001);
+    pub const PT_SCE_MODULEPARAM: Self = Self(0x61000002);
+    pub const PT_SCE_RELRO: Self = Self(0x61000010);
+    pub const PT_GNU_EH_FRAME: Self = Self(0x6474e550);
+    pub const PT_GNU_STACK: Self = Self(0x6474e551);
+    pub const PT_SCE_COMMENT: Self = Self(0x6fffff00);
+    pub const PT_SCE_VERSION: Self = Self(0x6fffff01);
+
+    pub const fn new(v: u32) -> Self {
+        Self(v)
+    }
+}
+
+impl Display for ProgramType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Self::PT_NULL => f.write_str("PT_NULL"),
+            Self::PT_LOAD => f.write_str("PT_LOAD"),
+            Self::PT_DYNAMIC => f.write_str("PT_DYNAMIC"),
+            Self::PT_INTERP => f.write_str("PT_INTERP"),
+            Self::PT_NOTE => f.write_str("PT_NOTE"),
+            Self::PT_SHLIB => f.write_str("PT_SHLIB"),
+            Self::PT_PHDR => f.write_str("PT_PHDR"),
+            Self::PT_TLS => f.write_str("PT_TLS"),
+            Self::PT_SCE_DYNLIBDATA => f.write_str("PT_SCE_DYNLIBDATA"),
+            Self::PT_SCE_PROCPARAM => f.write_str("PT_SCE_PROCPARAM"),
+            Self::PT_SCE_MODULEPARAM => f.write_str("PT_SCE_MODULEPARAM"),
+            Self::PT_SCE_RELRO => f.write_str("PT_SCE_RELRO"),
+            Self::PT_GNU_EH_FRAME => f.write_str("PT_GNU_EH_FRAME"),
+            Self::PT_GNU_STACK => f.write_str("PT_GNU_STACK"),
+            Self::PT_SCE_COMMENT => f.write_str("PT_SCE_COMMENT"),
+            Self::PT_SCE_VERSION => f.write_str("PT_SCE_VERSION"),
+            t => write!(f, "{:#010x}", t.0),
+        }
+    }
+}
+
+/// Represents flags for an ELF program.
+#[bitflag(u32)]
+pub enum ProgramFlags {
+    /// `PF_X`.
+    Execute = 0x00000001,
+    /// `PF_W`.
+    Write = 0x00000002,
+    /// `PF_R`.
+    Read = 0x00000004,
+}
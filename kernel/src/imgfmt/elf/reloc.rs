@@ -0,0 +1,69 @@
+/// An iterator over the `Elf64_Rela`.
+pub struct Relocations<'a> {
+    next: &'a [u8],
+}
+
+impl<'a> Relocations<'a> {
+    pub fn new(next: &'a [u8]) -> Self {
+        Self { next }
+    }
+}
+
+impl Iterator for Relocations<'_> {
+    type Item = Relocation;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Check if all entries has been read.
+        if self.next.len() < 24 {
+            return None;
+        }
+
+        // Read the entry.
+        let offset = u64::from_le_bytes(self.next[..8].try_into().unwrap());
+        let info = u64::from_le_bytes(self.next[8..16].try_into().unwrap());
+        let addend = i64::from_le_bytes(self.next[16..24].try_into().unwrap());
+
+        // Move to next entry.
+        self.next = &self.next[24..];
+
+        Some(Relocation {
+            offset: offset as usize,
+            info,
+            addend: addend as isize,
+        })
+    }
+}
+
+/// An implementation of `Elf64_Rela`.
+pub struct Relocation {
+    offset: usize, // r_offset
+    info: u64,     // r_info
+    addend: isize, // r_addend
+}
+
+impl Relocation {
+    pub const R_X86_64_NONE: u32 = 0;
+    pub const R_X86_64_64: u32 = 1;
+    pub const R_X86_64_COPY: u32 = 5;
+    pub const R_X86_64_GLOB_DAT: u32 = 6;
+    pub const R_X86_64_JUMP_SLOT: u32 = 7;
+    pub const R_X86_64_RELATIVE: u32 = 8;
+    pub const R_X86_64_DTPMOD64: u32 = 16;
+    pub const R_X86_64_DTPOFF64: u32 = 17;
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn ty(&self) -> u32 {
+        self.info as u32
+    }
+
+    pub fn symbol(&self) -> usize {
+        (self.info >> 32) as usize
+    }
+
+    pub fn addend(&self) -> isize {
+        self.addend
+    }
+}
@@ -0,0 +1,131 @@
+use super::{FileInfo, StringTableError};
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use core::error::Error;
+use core::fmt::{Display, Formatter};
+
+/// An iterator over the `Elf64_Sym`.
+pub struct Symbols<'a> {
+    next: &'a [u8],
+    info: &'a FileInfo,
+}
+
+impl<'a> Symbols<'a> {
+    pub fn new(next: &'a [u8], info: &'a FileInfo) -> Self {
+        Self { next, info }
+    }
+}
+
+impl Iterator for Symbols<'_> {
+    type Item = Result<Symbol, ReadSymbolError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Check if all entries has been read.
+        if self.next.is_empty() {
+            return None;
+        } else if self.next.len() < 24 {
+            return Some(Err(ReadSymbolError::InvalidEntry));
+        }
+
+        // Read the entry.
+        let name = u32::from_le_bytes(self.next[..4].try_into().unwrap());
+        let info = self.next[4];
+        let shndx = u16::from_le_bytes(self.next[6..8].try_into().unwrap());
+        let value = u64::from_le_bytes(self.next[8..16].try_into().unwrap());
+
+        // Move to next entry.
+        self.next = &self.next[24..];
+
+        // Load name.
+        let name = match self.info.read_str(name as usize) {
+            Ok(v) => v.to_owned(),
+            Err(e) => return Some(Err(ReadSymbolError::InvalidNameOffset(name, e))),
+        };
+
+        Some(Ok(Symbol {
+            name,
+            info,
+            shndx,
+            value: value as usize,
+        }))
+    }
+}
+
+/// Represents an `Elf64_Sym`.
+pub struct Symbol {
+    name: String, // st_name
+    info: u8,     // st_info
+    shndx: u16,   // st_shndx
+    value: usize, // st_value
+}
+
+impl Symbol {
+    /// Symbol's type is not specified.
+    pub const STT_NOTYPE: u8 = 0;
+
+    /// Symbol is a data object (variable, array, etc.)
+    pub const STT_OBJECT: u8 = 1;
+
+    /// Symbol is executable code (function, etc.)
+    pub const STT_FUNC: u8 = 2;
+
+    /// Symbol refers to a section.
+    pub const STT_SECTION: u8 = 3;
+
+    /// Thread local data object.
+    pub const STT_TLS: u8 = 6;
+
+    /// PS4 specific.
+    pub const STT_ENTRY: u8 = 11;
+
+    /// Local symbol, not visible outside obj file containing def.
+    pub const STB_LOCAL: u8 = 0;
+
+    /// Weak symbol, like global but lower-precedence.
+    pub const STB_WEAK: u8 = 2;
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn ty(&self) -> u8 {
+        self.info & 0xf
+    }
+
+    pub fn binding(&self) -> u8 {
+        self.info >> 4
+    }
+
+    pub fn shndx(&self) -> u16 {
+        self.shndx
+    }
+
+    pub fn value(&self) -> usize {
+        self.value
+    }
+}
+
+/// Represents an error when reading `Elf64_Sym` fails.
+#[derive(Debug)]
+pub enum ReadSymbolError {
+    InvalidEntry,
+    InvalidNameOffset(u32, StringTableError),
+}
+
+impl Error for ReadSymbolError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidNameOffset(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ReadSymbolError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidEntry => f.write_str("the entry is not a valid symbol entry"),
+            Self::InvalidNameOffset(v, _) => write!(f, "name offset {v} is not valid"),
+        }
+    }
+}
@@ -55,6 +55,11 @@ pub struct Gutex<T> {
 }
 
 impl<T> Gutex<T> {
+    /// Returns a mutable reference to the underlying data without locking.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
     /// # Panics
     /// If there are any active reader or writer.
     pub fn write(&self) -> GutexWrite<T> {
@@ -183,6 +188,7 @@ impl Drop for GroupGuard<'_> {
         // Release the lock.
         self.group.owning.store(MTX_UNOWNED, Ordering::Release);
 
-        todo!("wakeup waiting thread");
+        // TODO: Wakeup waiting thread. Currently there are no waiting thread since the contention
+        // is not implemented yet.
     }
 }
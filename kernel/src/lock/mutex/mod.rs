@@ -55,6 +55,11 @@ impl<T> Mutex<T> {
         }
     }
 
+    /// Returns a mutable reference to the underlying data without locking.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
     /// See `_mtx_unlock_flags` on the PS4 for a reference.
     ///
     /// # Safety
@@ -1,32 +1,62 @@
 #![no_std]
 #![cfg_attr(not(test), no_main)]
 
-use self::context::{current_procmgr, ContextSetup};
-use self::imgact::Ps4Abi;
+use self::context::{current_fs, current_procmgr, current_thread, ContextSetup};
+use self::dmem::{BlockPool, Dmem};
+use self::errno::{Errno, ENOSYS};
+use self::fs::{
+    ExecCheckError, Fs, LookupError, MountFlags, MountOpts, DEVFS, EXFATFS, HOSTFS, NULLFS, PFS,
+    TMPFS,
+};
+use self::imgact::{exec, ExecError, Ps4Abi};
+use self::imgfmt::elf::{Elf, OpenError};
 use self::malloc::KernelHeap;
-use self::proc::{Fork, Proc, ProcAbi, ProcMgr, Thread};
-use self::sched::sleep;
+use self::proc::{FileDesc, Fork, ForkError, Pid, Proc, ProcAbi, ProcMgr, Thread};
+use self::rtld::{Dynlib, NidDb};
+use self::sched::cpu_idle;
+use self::signal::{Signal, SignalAct, SignalSet};
+use self::syscalls::Syscalls;
+use self::sysctl::Sysctl;
+use self::trap::TrapFrame;
+use self::ucred::{AuthInfo, Gid, Ucred, Uid};
 use self::uma::Uma;
+use self::vm::{FaultError, KmemArena, PhysMem, VmSpace};
+use ::config::{BootEnv, KernelExit};
+use alloc::boxed::Box;
+use alloc::format;
 use alloc::sync::Arc;
+use alloc::vec;
+use core::error::Error;
+use core::fmt::{Display, Formatter};
 use core::mem::zeroed;
-use krt::info;
+use krt::{boot_env, error, info};
 
 #[cfg_attr(target_arch = "aarch64", path = "aarch64.rs")]
 #[cfg_attr(target_arch = "x86_64", path = "x86_64.rs")]
 mod arch;
 mod config;
 mod context;
+mod dmem;
+mod errno;
 mod event;
+mod fs;
 mod imgact;
 mod imgfmt;
 mod lock;
 mod malloc;
 mod proc;
+mod rtld;
 mod sched;
 mod signal;
+mod stats;
 mod subsystem;
+mod syscalls;
+mod sysctl;
+mod time;
 mod trap;
+mod ucred;
 mod uma;
+mod vm;
 
 extern crate alloc;
 
@@ -42,12 +72,21 @@ fn main() -> ! {
     // are working.
     let cx = unsafe { self::arch::setup_main_cpu() };
 
+    // Setup vmspace0 for proc0. The VMM give us a range of physical memory to allocate the pages.
+    let phys = match boot_env() {
+        BootEnv::Vm(vm) => unsafe { PhysMem::new(vm.dmap, vm.phys_addr, vm.phys_len) },
+    };
+
+    let vm0 = unsafe { VmSpace::kernel(Arc::new(phys)) };
+
     // Setup proc0 to represent the kernel.
-    let proc0 = Proc::new_bare(Arc::new(Proc0Abi));
+    let cred = Ucred::new(Uid::ROOT, Uid::ROOT, vec![Gid::ROOT], AuthInfo::KERNEL);
+    let cred = Arc::new(cred);
+    let proc0 = Proc::new_bare(Pid::KERNEL, Arc::new(Proc0Abi), vm0, cred.clone());
 
     // Setup thread0 to represent this thread.
     let proc0 = Arc::new(proc0);
-    let thread0 = Thread::new_bare(proc0);
+    let thread0 = Thread::new_bare(proc0, cred);
 
     // Activate CPU context.
     let thread0 = Arc::new(thread0);
@@ -62,8 +101,20 @@ fn setup() -> ContextSetup {
     // mi_startup function on the Orbis for a reference.
     let uma = init_vm(); // 161 on PS4 11.00.
     let pmgr = ProcMgr::new();
+    let sysctl = init_sysctl();
+    let fs = init_fs();
+    let dmem = match Dmem::new(&fs, current_thread().proc().vm().phys().clone()) {
+        Ok(v) => v,
+        Err(e) => boot_failed("Couldn't create direct memory devices", e),
+    };
 
-    ContextSetup { uma, pmgr }
+    ContextSetup {
+        uma,
+        pmgr,
+        sysctl,
+        fs,
+        dmem,
+    }
 }
 
 fn run() -> ! {
@@ -72,9 +123,17 @@ fn run() -> ! {
 
     unsafe { KERNEL_HEAP.activate_stage2() };
 
-    // Run remaining sysinit vector.
-    create_init(); // 659 on PS4 11.00.
-    swapper(); // 1119 on PS4 11.00.
+    self::stats::publish();
+
+    // TODO: Move this to the init process once we have it like the PS4.
+    mount_root();
+
+    // Run remaining sysinit vector. The create_init is 659 and the swapper is 1119 on PS4 11.00.
+    if let Err(e) = create_init() {
+        boot_failed("Couldn't create init", e);
+    }
+
+    swapper();
 }
 
 /// See `vm_mem_init` function on the Orbis for a reference.
@@ -84,38 +143,142 @@ fn run() -> ! {
 /// |---------|--------|
 /// |PS4 11.00|0x39A390|
 fn init_vm() -> Arc<Uma> {
-    Uma::new()
+    // SAFETY: This function is called only once and nothing else access KMEM_ARENA.
+    let kmem = Arc::new(unsafe { KmemArena::new(&raw mut KMEM_ARENA) });
+
+    Uma::new(kmem)
+}
+
+/// See `sysctl_register_all` function on the PS4 for a reference.
+fn init_sysctl() -> Arc<Sysctl> {
+    let mut ctl = Sysctl::new();
+
+    VmSpace::register_sysctls(&mut ctl);
+    Ps4Abi::register_sysctls(&mut ctl);
+
+    Arc::new(ctl)
+}
+
+/// See `vfs_register` function on the PS4 for a reference.
+fn init_fs() -> Arc<Fs> {
+    let mut fs = Fs::new();
+
+    fs.register(&DEVFS);
+    fs.register(&TMPFS);
+    fs.register(&NULLFS);
+    fs.register(&EXFATFS);
+    fs.register(&PFS);
+    fs.register(&HOSTFS);
+
+    Arc::new(fs)
+}
+
+/// See `vfs_mountroot` function on the PS4 for a reference.
+fn mount_root() {
+    let fs = current_fs().unwrap();
+    let td = current_thread();
+
+    info!("Mounting devfs.");
+
+    fs.mount_devfs(&td).unwrap();
+
+    // The root filesystem on the PS4 is the system partition of the firmware.
+    let mut opts = MountOpts::new();
+
+    opts.insert("fstype", "exfatfs");
+    opts.insert("from", "md0");
+    opts.insert("ro", "");
+
+    info!("Mounting root filesystem.");
+
+    fs.mount_root(opts, MountFlags::zeroed(), &td).unwrap();
 }
 
 /// See `create_init` function on the PS4 for a reference.
-fn create_init() {
+fn create_init() -> Result<(), InitError> {
     let pmgr = current_procmgr().unwrap();
-    let abi = Arc::new(Ps4Abi);
+    let mut sys = Syscalls::new();
+
+    ProcMgr::register_syscalls(&mut sys);
+    VmSpace::register_syscalls(&mut sys);
+    Ucred::register_syscalls(&mut sys);
+    Signal::register_syscalls(&mut sys);
+    Sysctl::register_syscalls(&mut sys);
+    FileDesc::register_syscalls(&mut sys);
+    Fs::register_syscalls(&mut sys);
+    Dynlib::register_syscalls(&mut sys);
+    Dmem::register_syscalls(&mut sys);
+    BlockPool::register_syscalls(&mut sys);
+
+    let abi = Arc::new(Ps4Abi::new(sys));
     let flags = Fork::new().with_copy_fd(true).with_create_process(true);
 
-    pmgr.fork(abi, flags).unwrap();
+    let p = pmgr.fork(abi, flags).map_err(InitError::ForkFailed)?;
+
+    // Load the executable. See start_init on the PS4 for a reference.
+    let fs = current_fs().unwrap();
+    let td = current_thread();
+    let path = "/mini-syscore.elf";
+    let vn = fs
+        .lookup(path, true, &td)
+        .map_err(InitError::LookupFailed)?;
 
-    todo!()
+    Fs::check_exec(&vn, &td).map_err(InitError::NotExecutable)?;
+
+    let data = vn.read_all(&td).map_err(InitError::ReadFailed)?;
+    let elf = Elf::parse(&data).map_err(InitError::ParseFailed)?;
+    let img = exec(p.vm(), &elf, &[path], &[]).map_err(InitError::ExecFailed)?;
+
+    info!(
+        "{} loaded at {:#x} with entry = {:#x}, stack = {:#x} and argv = {:#x}.",
+        path,
+        img.base(),
+        img.entry(),
+        img.stack(),
+        img.argv()
+    );
+
+    if elf.info().is_some() {
+        let nids = Arc::new(NidDb::builtin());
+        let dynlib = Dynlib::new(p.vm(), elf, img.base(), path.into(), nids)
+            .map_err(InitError::DynlibFailed)?;
+
+        *p.dynlib_mut() = Some(dynlib);
+    }
+
+    // TODO: Create the main thread of init once we are able to enter the user-space.
+    Ok(())
 }
 
 /// See `scheduler` function on the PS4 for a reference.
 fn swapper() -> ! {
     // TODO: Subscribe to "system_suspend_phase2_pre_sync" and "system_resume_phase2" event.
-    let procs = current_procmgr().unwrap();
+    // Let the host know about our memory usage now that all of the sysinit has been run.
+    self::stats::publish();
 
+    // We don't have a swap and none of the processes can be swapped out so there is nothing for
+    // the swapper to do. The PS4 sleep on proc0 until something wake it up but we don't have a
+    // clock or a scheduler yet so leave the CPU idle instead.
+    //
+    // TODO: Implement a call to vm_page_count_min() and swap in the processes once we have a
+    // scheduler.
     loop {
-        // TODO: Implement a call to vm_page_count_min().
-        let procs = procs.list();
-
-        if procs.len() == 0 {
-            // TODO: The PS4 check for some value for non-zero but it seems like that value always
-            // zero.
-            sleep();
-            continue;
-        }
+        cpu_idle();
+    }
+}
+
+/// Print `e` with all of its sources then tell the VMM that we can't continue.
+fn boot_failed(msg: &str, e: impl Error) -> ! {
+    let mut msg = format!("{msg}: {e}");
+    let mut src = e.source();
 
-        todo!();
+    while let Some(e) = src {
+        msg.push_str(&format!(" -> {e}"));
+        src = e.source();
     }
+
+    error!("{msg}.");
+    krt::shutdown(KernelExit::Failure);
 }
 
 /// Implementation of [`ProcAbi`] for kernel process.
@@ -125,14 +288,77 @@ struct Proc0Abi;
 
 impl ProcAbi for Proc0Abi {
     /// See `null_fetch_syscall_args` on the PS4 for a reference.
-    fn syscall_handler(&self) {
+    fn syscall_handler(&self, _: &Thread, frame: &mut TrapFrame) {
+        // The null_sysvec has no syscall table so any syscall is nosys.
+        #[cfg(target_arch = "x86_64")]
+        {
+            frame.rax = ENOSYS.get().try_into().unwrap();
+            frame.rflags |= self::trap::PSL_C;
+        }
+
+        #[cfg(target_arch = "aarch64")]
+        {
+            frame.x[0] = ENOSYS.get().try_into().unwrap();
+            frame.spsr |= self::trap::PSR_C;
+        }
+    }
+
+    fn send_signal(
+        &self,
+        _: &Thread,
+        _: &mut TrapFrame,
+        _: Signal,
+        _: &SignalAct,
+        _: SignalSet,
+    ) -> Result<(), FaultError> {
         unimplemented!()
     }
 }
 
+/// Represents an error when [`create_init()`] fails.
+#[derive(Debug)]
+enum InitError {
+    ForkFailed(ForkError),
+    LookupFailed(LookupError),
+    NotExecutable(ExecCheckError),
+    ReadFailed(Box<dyn Errno>),
+    ParseFailed(OpenError),
+    ExecFailed(ExecError),
+    DynlibFailed(self::rtld::MapError),
+}
+
+impl Error for InitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::ForkFailed(e) => Some(e),
+            Self::LookupFailed(e) => Some(e),
+            Self::NotExecutable(e) => Some(e),
+            Self::ReadFailed(e) => Some(e.as_ref()),
+            Self::ParseFailed(e) => Some(e),
+            Self::ExecFailed(e) => Some(e),
+            Self::DynlibFailed(e) => Some(e),
+        }
+    }
+}
+
+impl Display for InitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ForkFailed(_) => f.write_str("couldn't fork proc0"),
+            Self::LookupFailed(_) => f.write_str("couldn't find the executable"),
+            Self::NotExecutable(_) => f.write_str("the executable is not executable"),
+            Self::ReadFailed(_) => f.write_str("couldn't read the executable"),
+            Self::ParseFailed(_) => f.write_str("couldn't parse the executable"),
+            Self::ExecFailed(_) => f.write_str("couldn't load the executable"),
+            Self::DynlibFailed(_) => f.write_str("couldn't setup dynamic linker"),
+        }
+    }
+}
+
 // SAFETY: STAGE1_HEAP is a mutable static so it valid for reads and writes. This will be safe as
 // long as no one access STAGE1_HEAP.
 #[allow(dead_code)]
 #[cfg_attr(target_os = "none", global_allocator)]
 static KERNEL_HEAP: KernelHeap = unsafe { KernelHeap::new(&raw mut STAGE1_HEAP) };
 static mut STAGE1_HEAP: [u8; 1024 * 1024] = unsafe { zeroed() };
+static mut KMEM_ARENA: [u8; 1024 * 1024 * 64] = unsafe { zeroed() };
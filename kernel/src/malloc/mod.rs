@@ -1,3 +1,5 @@
+pub use self::stage2::MallocStats;
+
 use self::stage2::Stage2;
 use crate::lock::Mutex;
 use alloc::boxed::Box;
@@ -55,6 +57,16 @@ impl KernelHeap {
         // moving the value from Stage::One to Stage::Two.
         stage.write(Stage::Two(stage2, stage1));
     }
+
+    /// Returns [`None`] if stage 2 has not activated yet.
+    pub fn stats(&self) -> Option<MallocStats> {
+        // SAFETY: The stage can be changed only by activate_stage2(), which must be called before
+        // going multi-threaded.
+        match unsafe { &*self.stage.get() } {
+            Stage::One(_) => None,
+            Stage::Two(s, _) => Some(s.stats()),
+        }
+    }
 }
 
 unsafe impl GlobalAlloc for KernelHeap {
@@ -5,16 +5,17 @@ use alloc::string::ToString;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::alloc::Layout;
-use core::cell::RefCell;
 use core::num::NonZero;
+use core::sync::atomic::{AtomicU64, Ordering};
 
 /// Stage 2 kernel heap.
 ///
 /// This stage allocate a memory from a virtual memory management system. This struct is a merge of
-/// `malloc_type` and `malloc_type_internal` structure.
+/// `malloc_type` and `malloc_type_internal` structure. Unlike the Orbis, there is only a single
+/// malloc type for the whole kernel so the statistics here are the total of all allocations.
 pub struct Stage2 {
     zones: [Vec<Arc<UmaZone>>; (usize::BITS - 1) as usize], // kmemsize + kmemzones
-    stats: CpuLocal<RefCell<Stats>>,                        // mti_stats
+    stats: CpuLocal<Stats>,                                 // mti_stats
 }
 
 impl Stage2 {
@@ -52,12 +53,8 @@ impl Stage2 {
                 }
 
                 // Create zone.
-                let zone = Arc::new(uma.into_owned().create_zone(
-                    size.to_string(),
-                    size,
-                    Some(align - 1),
-                    UmaFlags::Malloc,
-                ));
+                let zone =
+                    uma.create_zone(size.to_string(), size, Some(align - 1), UmaFlags::Malloc);
 
                 while last <= size.get() {
                     zones.push(zone.clone());
@@ -70,7 +67,7 @@ impl Stage2 {
 
         Self {
             zones,
-            stats: CpuLocal::new(|_| RefCell::default()),
+            stats: CpuLocal::new(|_| Stats::default()),
         }
     }
 
@@ -89,53 +86,159 @@ impl Stage2 {
         }
 
         // Determine how to allocate.
-        let size = layout.size();
+        match self.zone(layout) {
+            Some(zone) => {
+                // Allocate a memory from UMA zone.
+                let mut mem = zone.alloc();
+
+                if mem.is_null() {
+                    // The Orbis do this by vm_lowmem event when kmem_malloc is failed.
+                    current_uma().unwrap().reclaim();
+                    mem = zone.alloc();
+                }
+
+                if !mem.is_null() {
+                    // The zone size is always a power of two and start at KMEM_ZBASE.
+                    let index = zone.size().trailing_zeros() - Self::KMEM_ZSHIFT as u32;
 
-        if size <= PAGE_SIZE.get() {
-            // Get zone to allocate from.
-            let align = layout.align().trailing_zeros() as usize;
-            let size = if (size & Self::KMEM_ZMASK) != 0 {
-                // TODO: Refactor this for readability.
-                (size + Self::KMEM_ZBASE) & !Self::KMEM_ZMASK
-            } else {
-                size
-            };
-
-            // Allocate a memory from UMA zone.
-            let zone = &self.zones[align][size >> Self::KMEM_ZSHIFT];
-            let mem = zone.alloc();
-
-            // Update stats.
-            let stats = self.stats.lock();
-            let mut stats = stats.borrow_mut();
-            let size = if mem.is_null() { 0 } else { zone.size().get() };
-
-            if size != 0 {
-                stats.alloc_bytes = stats
-                    .alloc_bytes
-                    .checked_add(size.try_into().unwrap())
-                    .unwrap();
-                stats.alloc_count += 1;
+                    self.allocated(zone.size().get(), Some(index));
+                }
+
+                mem
             }
+            None => {
+                // Allocate a memory from the kernel memory arena directly.
+                let uma = current_uma().unwrap();
+                let mut mem = uma.large_alloc(layout);
+
+                if mem.is_null() {
+                    uma.reclaim();
+                    mem = uma.large_alloc(layout);
+                }
 
-            // TODO: How to update mts_size here since our zone table also indexed by alignment?
-            mem
-        } else {
-            todo!()
+                if !mem.is_null() {
+                    self.allocated(Self::large_size(layout), None);
+                }
+
+                mem
+            }
         }
     }
 
+    /// See `free` on the PS4 for a reference.
+    ///
     /// # Safety
     /// `ptr` must be obtained with [`Self::alloc()`] and `layout` must be the same one that was
     /// passed to that method.
-    pub unsafe fn dealloc(&self, _: *mut u8, _: Layout) {
-        todo!()
+    pub unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let td = current_thread();
+
+        if !td.can_sleep() {
+            panic!("heap deallocation in a non-sleeping context is not supported");
+        }
+
+        // The Orbis lookup the slab from the address to determine which zone own the memory. We
+        // don't need this since we always know the layout of the memory.
+        match self.zone(layout) {
+            Some(zone) => {
+                zone.free(ptr);
+                self.freed(zone.size().get());
+            }
+            None => {
+                let uma = current_uma().unwrap();
+
+                uma.large_free(ptr, layout);
+                self.freed(Self::large_size(layout));
+            }
+        }
+    }
+
+    /// Returns [`None`] if `layout` is too large for any zone.
+    fn zone(&self, layout: Layout) -> Option<&Arc<UmaZone>> {
+        let size = layout.size();
+
+        if size > PAGE_SIZE.get() || layout.align() > PAGE_SIZE.get() {
+            return None;
+        }
+
+        // Round the size to the next KMEM_ZBASE.
+        let align = layout.align().trailing_zeros() as usize;
+        let size = if (size & Self::KMEM_ZMASK) != 0 {
+            (size + Self::KMEM_ZBASE) & !Self::KMEM_ZMASK
+        } else {
+            size
+        };
+
+        Some(&self.zones[align][size >> Self::KMEM_ZSHIFT])
+    }
+
+    /// Returns the statistics of this heap from all CPUs.
+    ///
+    /// See `sysctl_kern_malloc_stats` on the PS4 for a reference.
+    pub fn stats(&self) -> MallocStats {
+        let mut r = MallocStats::default();
+
+        for s in self.stats.iter() {
+            r.alloc_bytes += s.alloc_bytes.load(Ordering::Relaxed);
+            r.free_bytes += s.free_bytes.load(Ordering::Relaxed);
+            r.alloc_count += s.alloc_count.load(Ordering::Relaxed);
+            r.free_count += s.free_count.load(Ordering::Relaxed);
+            r.zones |= s.zones.load(Ordering::Relaxed);
+        }
+
+        r
+    }
+
+    /// See `malloc_type_zone_allocated` on the PS4 for a reference.
+    fn allocated(&self, size: usize, zone: Option<u32>) {
+        // We still need to pin the CPU here since the atomic operations on the other CPU can be
+        // interleaved with us.
+        let stats = self.stats.lock();
+
+        stats
+            .alloc_bytes
+            .fetch_add(size.try_into().unwrap(), Ordering::Relaxed);
+        stats.alloc_count.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(i) = zone {
+            stats.zones.fetch_or(1 << i, Ordering::Relaxed);
+        }
+    }
+
+    /// See `malloc_type_freed` on the PS4 for a reference.
+    fn freed(&self, size: usize) {
+        let stats = self.stats.lock();
+
+        stats
+            .free_bytes
+            .fetch_add(size.try_into().unwrap(), Ordering::Relaxed);
+        stats.free_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the size that will be accounted for an allocation that does not fit in any zone.
+    fn large_size(layout: Layout) -> usize {
+        layout.size().next_multiple_of(PAGE_SIZE.get())
     }
 }
 
 /// Implementation of `malloc_type_stats` structure.
+///
+/// We use atomic here so it can be read from the other CPUs.
 #[derive(Default)]
 struct Stats {
-    alloc_bytes: u64, // mts_memalloced
-    alloc_count: u64, // mts_numallocs
+    alloc_bytes: AtomicU64, // mts_memalloced
+    free_bytes: AtomicU64,  // mts_memfreed
+    alloc_count: AtomicU64, // mts_numallocs
+    free_count: AtomicU64,  // mts_numfrees
+    zones: AtomicU64,       // mts_size
+}
+
+/// Statistics of [`Stage2`], which is the total of all allocations on the heap.
+#[derive(Default)]
+pub struct MallocStats {
+    pub alloc_bytes: u64,
+    pub free_bytes: u64,
+    pub alloc_count: u64,
+    pub free_count: u64,
+    pub zones: u64,
 }
@@ -1,4 +1,24 @@
+use super::Thread;
+use crate::signal::{Signal, SignalAct, SignalSet};
+use crate::trap::TrapFrame;
+use crate::vm::FaultError;
+
 /// Implementation of `sysentvec` structure.
 pub trait ProcAbi: Send + Sync {
-    fn syscall_handler(&self);
+    /// This method is responsible for fetching the arguments from `frame`, invoke the syscall
+    /// handler and write the result back to `frame`.
+    fn syscall_handler(&self, td: &Thread, frame: &mut TrapFrame);
+
+    /// Setup `frame` to invoke the handler in `act` when returning to the user space. `mask` is
+    /// the signal mask to restore when the handler return.
+    ///
+    /// See `sv_sendsig` on the PS4 for a reference.
+    fn send_signal(
+        &self,
+        td: &Thread,
+        frame: &mut TrapFrame,
+        sig: Signal,
+        act: &SignalAct,
+        mask: SignalSet,
+    ) -> Result<(), FaultError>;
 }
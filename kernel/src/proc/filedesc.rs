@@ -0,0 +1,496 @@
+use super::Thread;
+use crate::errno::{Errno, EBADF, EINVAL, EMFILE, ENOTTY};
+use crate::fs::{File, FileFlags, IoCmd, Vnode};
+use crate::lock::{Gutex, GutexGroup};
+use crate::syscalls::{SysErr, SysIn, SysOut, Syscalls};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+
+/// Implementation of `filedesc` structure.
+pub struct FileDesc {
+    files: Gutex<Vec<Option<FileEntry>>>, // fd_ofiles + fd_ofileflags + fd_nfiles
+    cwd: Gutex<Option<Arc<Vnode>>>,       // fd_cdir
+    root: Gutex<Option<Arc<Vnode>>>,      // fd_rdir
+}
+
+impl FileDesc {
+    /// See `fdinit` on the PS4 for a reference.
+    ///
+    /// # Context safety
+    /// This function does not require a CPU context.
+    pub fn new() -> Arc<Self> {
+        Self::with(Vec::new(), None, None)
+    }
+
+    pub fn register_syscalls(sys: &mut Syscalls) {
+        sys.register(3, Self::sys_read);
+        sys.register(4, Self::sys_write);
+        sys.register(6, Self::sys_close);
+        sys.register(41, Self::sys_dup);
+        sys.register(54, Self::sys_ioctl);
+        sys.register(90, Self::sys_dup2);
+        sys.register(92, Self::sys_fcntl);
+        sys.register(189, Self::sys_fstat);
+    }
+
+    /// Returns a new empty table with the same current and root directory as this table.
+    ///
+    /// See `fdinit` on the PS4 for a reference.
+    pub fn init(&self) -> Arc<Self> {
+        Self::with(Vec::new(), self.cwd(), self.root())
+    }
+
+    /// Returns a new table with the same files as this table.
+    ///
+    /// See `fdcopy` on the PS4 for a reference.
+    pub fn copy(&self) -> Arc<Self> {
+        let files = self.files.write().clone();
+
+        Self::with(files, self.cwd(), self.root())
+    }
+
+    /// Returns [`None`] if the current directory is the root of the filesystem.
+    pub fn cwd(&self) -> Option<Arc<Vnode>> {
+        self.cwd.write().clone()
+    }
+
+    pub fn set_cwd(&self, vn: Arc<Vnode>) {
+        *self.cwd.write() = Some(vn);
+    }
+
+    /// Returns [`None`] if the process is not in `chroot`.
+    pub fn root(&self) -> Option<Arc<Vnode>> {
+        self.root.write().clone()
+    }
+
+    pub fn set_root(&self, vn: Arc<Vnode>) {
+        *self.root.write() = Some(vn);
+    }
+
+    /// Install `file` to the lowest available file descriptor.
+    ///
+    /// See `finstall` on the PS4 for a reference.
+    pub fn alloc(&self, file: Arc<File>, cloexec: bool) -> Result<c_int, FileDescError> {
+        let mut files = self.files.write();
+        let fd = Self::find_free(&files, 0)?;
+
+        Self::set(&mut files, fd, FileEntry { file, cloexec });
+
+        Ok(fd.try_into().unwrap())
+    }
+
+    /// See `fget` on the PS4 for a reference.
+    pub fn get(&self, fd: c_int) -> Result<Arc<File>, FileDescError> {
+        self.get_internal(fd, FileFlags::zeroed())
+    }
+
+    /// See `fget_read` on the PS4 for a reference.
+    pub fn get_for_read(&self, fd: c_int) -> Result<Arc<File>, FileDescError> {
+        self.get_internal(fd, FileFlags::FREAD)
+    }
+
+    /// See `fget_write` on the PS4 for a reference.
+    pub fn get_for_write(&self, fd: c_int) -> Result<Arc<File>, FileDescError> {
+        self.get_internal(fd, FileFlags::FWRITE)
+    }
+
+    /// Duplicate `fd` to the lowest available file descriptor that is not lower than `min`.
+    ///
+    /// See `do_dup` on the PS4 for a reference.
+    pub fn dup(&self, fd: c_int, min: c_int, cloexec: bool) -> Result<c_int, FileDescError> {
+        let min = match usize::try_from(min) {
+            Ok(v) if v < MAX_FILES => v,
+            _ => return Err(FileDescError::InvalidFd),
+        };
+
+        // Get the file.
+        let mut files = self.files.write();
+        let file = Self::lookup(&files, fd)?.file.clone();
+        let fd = Self::find_free(&files, min)?;
+
+        Self::set(&mut files, fd, FileEntry { file, cloexec });
+
+        Ok(fd.try_into().unwrap())
+    }
+
+    /// Duplicate `fd` to `to`. The file that was opened on `to` will be closed.
+    ///
+    /// See `do_dup` on the PS4 for a reference.
+    pub fn dup2(&self, fd: c_int, to: c_int, cloexec: bool) -> Result<c_int, FileDescError> {
+        let i: usize = to.try_into().map_err(|_| FileDescError::BadFd)?;
+
+        if i >= MAX_FILES {
+            return Err(FileDescError::TooManyFiles);
+        }
+
+        // Get the file.
+        let mut files = self.files.write();
+        let file = Self::lookup(&files, fd)?.file.clone();
+
+        if fd == to {
+            if cloexec {
+                files[i].as_mut().unwrap().cloexec = true;
+            }
+
+            return Ok(to);
+        }
+
+        // Replace the file. The old file will be closed when the last reference is dropped.
+        let old = Self::set(&mut files, i, FileEntry { file, cloexec });
+
+        drop(files);
+        drop(old);
+
+        Ok(to)
+    }
+
+    /// See `kern_close` on the PS4 for a reference.
+    pub fn close(&self, fd: c_int) -> Result<(), FileDescError> {
+        let mut files = self.files.write();
+
+        Self::lookup(&files, fd)?;
+
+        // Drop the file outside the lock since the backend may need to do something when closing.
+        let i: usize = fd.try_into().unwrap();
+        let file = files[i].take();
+
+        drop(files);
+        drop(file);
+
+        Ok(())
+    }
+
+    /// Returns `true` if `fd` will be closed on exec (AKA `UF_EXCLOSE`).
+    pub fn cloexec(&self, fd: c_int) -> Result<bool, FileDescError> {
+        Self::lookup(&self.files.write(), fd).map(|e| e.cloexec)
+    }
+
+    pub fn set_cloexec(&self, fd: c_int, v: bool) -> Result<(), FileDescError> {
+        let mut files = self.files.write();
+
+        Self::lookup(&files, fd)?;
+
+        files[usize::try_from(fd).unwrap()]
+            .as_mut()
+            .unwrap()
+            .cloexec = v;
+
+        Ok(())
+    }
+
+    /// Close all file descriptors that was marked as close-on-exec.
+    ///
+    /// See `fdcloseexec` on the PS4 for a reference.
+    #[allow(dead_code)] // TODO: Remove this once we have execve.
+    pub fn close_exec(&self) {
+        let mut files = self.files.write();
+        let mut closed = Vec::new();
+
+        for e in files.iter_mut() {
+            if e.as_ref().is_some_and(|e| e.cloexec) {
+                closed.push(e.take());
+            }
+        }
+
+        drop(files);
+        drop(closed);
+    }
+
+    /// See `sys_read` on the PS4 for a reference.
+    fn sys_read(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let fd: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let ptr: usize = i.args[1].into();
+        let len: usize = i.args[2].into();
+
+        if len > IOSIZE_MAX {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        // Read the file.
+        let file = td.proc().files().get_for_read(fd)?;
+        let mut buf = vec![0; len];
+        let len = file.read(&mut buf, td).map_err(SysErr::Object)?;
+
+        td.proc().vm().write(ptr, &buf[..len])?;
+
+        Ok(len.into())
+    }
+
+    /// See `sys_write` on the PS4 for a reference.
+    fn sys_write(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let fd: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let ptr: usize = i.args[1].into();
+        let len: usize = i.args[2].into();
+
+        if len > IOSIZE_MAX {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        // Write the file.
+        let file = td.proc().files().get_for_write(fd)?;
+        let mut buf = vec![0; len];
+
+        td.proc().vm().read(ptr, &mut buf)?;
+
+        let len = file.write(&buf, td).map_err(SysErr::Object)?;
+
+        Ok(len.into())
+    }
+
+    /// See `sys_close` on the PS4 for a reference.
+    fn sys_close(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let fd: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+
+        td.proc().files().close(fd)?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_dup` on the PS4 for a reference.
+    fn sys_dup(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let fd: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let fd = td.proc().files().dup(fd, 0, false)?;
+
+        Ok(fd.into())
+    }
+
+    /// See `sys_ioctl` and `kern_ioctl` on the PS4 for a reference.
+    fn sys_ioctl(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let fd: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let cmd: usize = i.args[1].into();
+        let arg: usize = i.args[2].into();
+
+        // Only the lower 32-bits of the command is used.
+        let cmd = IoCmd::new(cmd as u32).ok_or(SysErr::Raw(ENOTTY))?;
+
+        // Get the argument. The argument for the command without size is the pointer itself.
+        let copy = cmd.len() != 0 && !cmd.is_void();
+        let mut data = if cmd.len() == 0 {
+            arg.to_ne_bytes().to_vec()
+        } else if cmd.is_void() {
+            (arg as c_int).to_ne_bytes().to_vec()
+        } else {
+            vec![0; cmd.len()]
+        };
+
+        if copy && cmd.is_in() {
+            td.proc().vm().read(arg, &mut data)?;
+        }
+
+        // Execute the command.
+        let files = td.proc().files();
+        let file = files.get(fd)?;
+
+        if !file.flags().has(FileFlags::FREAD | FileFlags::FWRITE) {
+            return Err(SysErr::Raw(EBADF));
+        }
+
+        // The backend is not notified about FIONBIO and FIOASYNC since it can check the flags on
+        // the file directly.
+        match cmd {
+            IoCmd::FIOCLEX => files.set_cloexec(fd, true)?,
+            IoCmd::FIONCLEX => files.set_cloexec(fd, false)?,
+            IoCmd::FIONBIO | IoCmd::FIOASYNC => {
+                let v = c_int::from_ne_bytes(data.as_slice().try_into().unwrap());
+                let f = if cmd == IoCmd::FIONBIO {
+                    FileFlags::FNONBLOCK
+                } else {
+                    FileFlags::FASYNC
+                };
+
+                if v != 0 {
+                    *file.flags_mut() |= f;
+                } else {
+                    file.flags_mut().remove(f);
+                }
+            }
+            _ => file.ioctl(cmd, &mut data, td).map_err(SysErr::Object)?,
+        }
+
+        if copy && cmd.is_out() {
+            td.proc().vm().write(arg, &data)?;
+        }
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_dup2` on the PS4 for a reference.
+    fn sys_dup2(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let from: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let to: c_int = i.args[1].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let fd = td.proc().files().dup2(from, to, false)?;
+
+        Ok(fd.into())
+    }
+
+    /// See `sys_fcntl` and `kern_fcntl` on the PS4 for a reference.
+    fn sys_fcntl(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let fd: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let cmd: c_int = i.args[1].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let arg: usize = i.args[2].into();
+        let files = td.proc().files();
+
+        match cmd {
+            F_DUPFD => Ok(files.dup(fd, arg as c_int, false)?.into()),
+            F_GETFD => match files.cloexec(fd)? {
+                true => Ok(FD_CLOEXEC.into()),
+                false => Ok(SysOut::ZERO),
+            },
+            F_SETFD => {
+                files.set_cloexec(fd, (arg as c_int & FD_CLOEXEC) != 0)?;
+
+                Ok(SysOut::ZERO)
+            }
+            F_GETFL => {
+                let flags = files.get(fd)?.flags().into_bits();
+
+                // Convert to open flags (AKA OFLAGS).
+                Ok((flags.wrapping_sub(1) as c_int).into())
+            }
+            F_SETFL => {
+                let file = files.get(fd)?;
+                let mut flags = file.flags_mut();
+
+                flags.remove(FileFlags::FCNTLFLAGS);
+                *flags |= FileFlags::from(arg as u32) & FileFlags::FCNTLFLAGS;
+
+                Ok(SysOut::ZERO)
+            }
+            F_DUP2FD => Ok(files.dup2(fd, arg as c_int, false)?.into()),
+            // TODO: Implement the remaining commands (e.g. F_GETOWN, F_SETLK and F_READAHEAD).
+            _ => Err(SysErr::Raw(EINVAL)),
+        }
+    }
+
+    /// See `sys_fstat` and `kern_fstat` on the PS4 for a reference.
+    fn sys_fstat(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let fd: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let ptr: usize = i.args[1].into();
+        let file = td.proc().files().get(fd)?;
+        let st = file.stat(td).map_err(SysErr::Object)?;
+
+        td.proc().vm().write(ptr, st.as_bytes())?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    fn with(
+        files: Vec<Option<FileEntry>>,
+        cwd: Option<Arc<Vnode>>,
+        root: Option<Arc<Vnode>>,
+    ) -> Arc<Self> {
+        let gg = GutexGroup::new();
+
+        Arc::new(Self {
+            files: gg.clone().spawn(files),
+            cwd: gg.clone().spawn(cwd),
+            root: gg.spawn(root),
+        })
+    }
+
+    /// See `_fget` on the PS4 for a reference.
+    fn get_internal(&self, fd: c_int, flags: FileFlags) -> Result<Arc<File>, FileDescError> {
+        let file = Self::lookup(&self.files.write(), fd)?.file.clone();
+
+        if flags.into_bits() != 0 && !file.flags().has(flags) {
+            return Err(FileDescError::BadFd);
+        }
+
+        Ok(file)
+    }
+
+    fn lookup(files: &[Option<FileEntry>], fd: c_int) -> Result<&FileEntry, FileDescError> {
+        usize::try_from(fd)
+            .ok()
+            .and_then(|i| files.get(i))
+            .and_then(|e| e.as_ref())
+            .ok_or(FileDescError::BadFd)
+    }
+
+    /// See `fdalloc` on the PS4 for a reference.
+    fn find_free(files: &[Option<FileEntry>], min: usize) -> Result<usize, FileDescError> {
+        (min..MAX_FILES)
+            .find(|&i| files.get(i).is_none_or(|e| e.is_none()))
+            .ok_or(FileDescError::TooManyFiles)
+    }
+
+    /// Returns the entry that was replaced.
+    fn set(files: &mut Vec<Option<FileEntry>>, i: usize, e: FileEntry) -> Option<FileEntry> {
+        if i >= files.len() {
+            files.resize_with(i + 1, || None);
+        }
+
+        files[i].replace(e)
+    }
+}
+
+/// An entry in [`FileDesc`].
+#[derive(Clone)]
+struct FileEntry {
+    file: Arc<File>,
+    cloexec: bool,
+}
+
+/// Maximum number of file descriptors for each process.
+///
+/// TODO: Use `RLIMIT_NOFILE` and `maxfilesperproc` once we have resource limits.
+const MAX_FILES: usize = 0x10000;
+
+/// Maximum size of each read or write.
+const IOSIZE_MAX: usize = c_int::MAX as usize;
+
+/// Duplicate file descriptor.
+const F_DUPFD: c_int = 0;
+
+/// Get file descriptor flags.
+const F_GETFD: c_int = 1;
+
+/// Set file descriptor flags.
+const F_SETFD: c_int = 2;
+
+/// Get file status flags.
+const F_GETFL: c_int = 3;
+
+/// Set file status flags.
+const F_SETFL: c_int = 4;
+
+/// Duplicate file descriptor to arg.
+const F_DUP2FD: c_int = 10;
+
+/// Close-on-exec flag.
+const FD_CLOEXEC: c_int = 1;
+
+/// Represents an error when operation on [`FileDesc`] fails.
+#[derive(Debug)]
+pub enum FileDescError {
+    BadFd,
+    TooManyFiles,
+    InvalidFd,
+}
+
+impl Error for FileDescError {}
+
+impl Display for FileDescError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BadFd => f.write_str("bad file descriptor"),
+            Self::TooManyFiles => f.write_str("too many open files"),
+            Self::InvalidFd => f.write_str("invalid file descriptor"),
+        }
+    }
+}
+
+impl Errno for FileDescError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::BadFd => EBADF,
+            Self::TooManyFiles => EMFILE,
+            Self::InvalidFd => EINVAL,
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use super::{Pid, Session};
+use alloc::sync::Arc;
+
+/// Implementation of `pgrp` structure.
+pub struct ProcGroup {
+    id: Pid,               // pg_id
+    session: Arc<Session>, // pg_session
+}
+
+impl ProcGroup {
+    pub fn new(id: Pid, session: Arc<Session>) -> Arc<Self> {
+        Arc::new(Self { id, session })
+    }
+
+    pub fn id(&self) -> Pid {
+        self.id
+    }
+
+    pub fn session(&self) -> &Arc<Session> {
+        &self.session
+    }
+}
@@ -1,39 +1,83 @@
 pub use self::abi::*;
+pub use self::filedesc::*;
+pub use self::group::*;
 pub use self::pid::*;
 pub use self::process::*;
+pub use self::session::*;
 pub use self::thread::*;
 
+use crate::context::{current_procmgr, current_thread};
+use crate::errno::{Errno, ECHILD, EINVAL, EPERM};
 use crate::event::{Event, EventSet};
 use crate::lock::{MappedMutex, Mutex, MutexGuard};
-use crate::signal::Signal;
+use crate::sched::thread_exit;
+use crate::signal::{self, Signal, SIGCHLD};
 use crate::subsystem::Subsystem;
+use crate::syscalls::{SysErr, SysIn, SysOut, Syscalls};
+use crate::ucred::Privilege;
 use alloc::sync::{Arc, Weak};
 use bitfield_struct::bitfield;
 use core::error::Error;
+use core::ffi::c_int;
 use core::fmt::{Display, Formatter};
+use core::num::NonZero;
 use hashbrown::HashMap;
 
 mod abi;
+mod filedesc;
+mod group;
 mod pid;
 mod process;
+mod session;
 mod thread;
 
 /// Manage all processes in the system.
+///
+/// The locks must be acquired in the same order as the fields.
 pub struct ProcMgr {
-    procs: Mutex<HashMap<Pid, Weak<Proc>>>, // allproc + pidhashtbl + zombproc
+    last_pid: Mutex<c_int>,                       // lastpid
+    procs: Mutex<HashMap<Pid, Weak<Proc>>>,       // allproc + pidhashtbl + zombproc
+    groups: Mutex<HashMap<Pid, Weak<ProcGroup>>>, // pgrphashtbl
+    sessions: Mutex<HashMap<Pid, Weak<Session>>>,
     events: Arc<EventSet<ProcEvents>>,
 }
 
 impl ProcMgr {
+    /// `proc0` will be placed in `session0` and `pgrp0`.
+    ///
+    /// See `proc0_init` on the PS4 for a reference.
     pub fn new() -> Arc<Self> {
         let events = Arc::default();
+        let td = current_thread();
+        let proc0 = td.proc();
+        let session0 = Session::new(proc0.id());
+        let pgrp0 = ProcGroup::new(proc0.id(), session0.clone());
+        let mut groups = HashMap::new();
+        let mut sessions = HashMap::new();
+
+        groups.insert(pgrp0.id(), Arc::downgrade(&pgrp0));
+        sessions.insert(session0.id(), Arc::downgrade(&session0));
 
+        *proc0.group_mut() = Some(pgrp0);
+
+        // We don't put proc0 on the process list since the PS4 scheduler expect it to be empty
+        // until init has been created.
         Arc::new(Self {
+            last_pid: Mutex::new(0),
             procs: Mutex::new(HashMap::new()),
+            groups: Mutex::new(groups),
+            sessions: Mutex::new(sessions),
             events,
         })
     }
 
+    /// Returns [`None`] if the process does not exists or it has been reaped.
+    ///
+    /// See `pfind` on the PS4 for a reference.
+    pub fn find(&self, pid: Pid) -> Option<Arc<Proc>> {
+        self.procs.lock().get(&pid).and_then(|p| p.upgrade())
+    }
+
     pub fn list(&self) -> MappedMutex<impl ExactSizeIterator<Item = &Weak<Proc>> + '_> {
         MutexGuard::map(self.procs.lock(), |procs| procs.values())
     }
@@ -54,8 +98,274 @@ impl ProcMgr {
             todo!()
         }
 
+        // Setup virtual memory for the child. See vm_forkproc on the PS4 for a reference.
+        let td = current_thread();
+        let parent = td.proc();
+        let vm = if flags.share_vm() {
+            parent.vm().clone()
+        } else {
+            parent.vm().fork().ok_or(ForkError::NoMemory)?
+        };
+
+        // Setup file descriptor table for the child.
+        let files = if flags.clear_fd() {
+            parent.files().init()
+        } else if flags.copy_fd() {
+            parent.files().copy()
+        } else {
+            parent.files().clone()
+        };
+
+        // Allocate PID. We need to hold the lock on the process list until the process has been
+        // added otherwise the same PID can be allocated twice.
+        let mut last_pid = self.last_pid.lock();
+        let mut procs = self.procs.lock();
+        let pid = {
+            let groups = self.groups.lock();
+            let sessions = self.sessions.lock();
+
+            Pid::find(*last_pid, flags.high_pid(), |v| {
+                procs.contains_key(&v) || groups.contains_key(&v) || sessions.contains_key(&v)
+            })
+            .ok_or(ForkError::NoPid)?
+        };
+
+        if !flags.high_pid() {
+            *last_pid = pid.into();
+        }
+
         // Create process.
-        Ok(Proc::new(abi, &self.events))
+        let exit_signal = if flags.custom_signal() {
+            flags.parent_signal()
+        } else {
+            SIGCHLD
+        };
+
+        let proc = Proc::new(pid, abi, vm, files, parent, exit_signal, &self.events);
+
+        procs.insert(pid, Arc::downgrade(&proc));
+        parent.children_mut().push(proc.clone());
+
+        Ok(proc)
+    }
+
+    /// Turn `p` into a zombie. All of its children will be given to `init`. The parent need to call
+    /// [`Self::wait()`] to release the process.
+    ///
+    /// See `exit1` on the PS4 for a reference.
+    ///
+    /// # Panics
+    /// If `p` is `init`.
+    pub fn exit(&self, p: &Arc<Proc>, status: c_int) {
+        if p.id() == Pid::INIT {
+            panic!("init exited with status {status}");
+        }
+
+        // Trigger process_exit event.
+        let mut et = self.events.trigger();
+
+        for h in et.select(|s| &s.process_exit) {
+            h(p);
+        }
+
+        drop(et);
+
+        // Give all children to init. The PS4 did this in proc_reparent.
+        let init = self.find(Pid::INIT).unwrap();
+        let children = core::mem::take(&mut *p.children_mut());
+
+        for c in &children {
+            *c.parent_mut() = Arc::downgrade(&init);
+        }
+
+        init.children_mut().extend(children);
+
+        // Turn into a zombie. TODO: Wakeup the parent when the sleep queue is implemented.
+        *p.state_mut() = ProcState::Zombie(status);
+
+        // Notify the parent. TODO: Give the process to init if the parent has PS_NOCLDWAIT or
+        // PS_CLDSIGIGN.
+        let parent = p.parent_mut().upgrade();
+
+        if let Some(parent) = parent {
+            let sig = if parent.id() == Pid::INIT {
+                SIGCHLD
+            } else {
+                p.exit_signal()
+            };
+
+            if sig.into_bits() != 0 {
+                signal::send(&parent, sig);
+            }
+        }
+    }
+
+    /// Reap a zombie child of `p`. If `pid` is [`None`] any child can be reaped. Returns the PID of
+    /// the reaped child with its exit status or [`None`] if no child has been exited yet.
+    ///
+    /// Our implementation imply `WNOHANG` since we don't have a sleep queue yet.
+    ///
+    /// See `kern_wait` on the PS4 for a reference.
+    pub fn wait(&self, p: &Arc<Proc>, pid: Option<Pid>) -> Result<Option<(Pid, c_int)>, WaitError> {
+        let mut children = p.children_mut();
+        let mut found = false;
+        let mut zombie = None;
+
+        for (i, c) in children.iter().enumerate() {
+            if pid.is_some_and(|v| v != c.id()) {
+                continue;
+            }
+
+            found = true;
+
+            if let ProcState::Zombie(v) = *c.state_mut() {
+                zombie = Some((i, v));
+                break;
+            }
+        }
+
+        // Reap the child. See proc_reap on the PS4 for a reference.
+        if let Some((i, status)) = zombie {
+            let c = children.remove(i);
+            let group = c.group_mut().take();
+
+            drop(children);
+
+            self.procs.lock().remove(&c.id());
+
+            if let Some(g) = group {
+                self.leave_group(g);
+            }
+
+            return Ok(Some((c.id(), status)));
+        }
+
+        if found {
+            Ok(None)
+        } else {
+            Err(WaitError::NoChild)
+        }
+    }
+
+    /// Create a new session with `p` as the leader. Returns the ID of the new session.
+    ///
+    /// See `sys_setsid` on the PS4 for a reference.
+    pub fn setsid(&self, p: &Arc<Proc>) -> Result<Pid, SetsidError> {
+        let id = p.id();
+        let mut groups = self.groups.lock();
+        let mut sessions = self.sessions.lock();
+
+        // The process cannot be a group leader.
+        if groups.get(&id).is_some_and(|g| g.strong_count() != 0) {
+            return Err(SetsidError::GroupLeader);
+        }
+
+        // Create a new session and a new group.
+        let session = Session::new(id);
+        let group = ProcGroup::new(id, session.clone());
+
+        groups.insert(id, Arc::downgrade(&group));
+        sessions.insert(id, Arc::downgrade(&session));
+
+        drop(sessions);
+        drop(groups);
+
+        // Move the process to the new group.
+        let old = p.group_mut().replace(group);
+
+        if let Some(g) = old {
+            self.leave_group(g);
+        }
+
+        Ok(id)
+    }
+
+    pub fn register_syscalls(sys: &mut Syscalls) {
+        sys.register(1, Self::sys_exit);
+        sys.register(7, Self::sys_wait4);
+        sys.register(20, Self::sys_getpid);
+        sys.register(39, Self::sys_getppid);
+        sys.register(147, Self::sys_setsid);
+    }
+
+    /// See `sys_sys_exit` on the PS4 for a reference.
+    fn sys_exit(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let status: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let pmgr = current_procmgr().unwrap();
+
+        // The status is encoded with W_EXITCODE.
+        pmgr.exit(td.proc(), (status & 0xff) << 8);
+
+        thread_exit()
+    }
+
+    /// We always behave like `WNOHANG` was specified, which mean this will return zero immediately
+    /// if there are no exited child. See [`Self::wait()`] for more details.
+    ///
+    /// See `sys_wait4` on the PS4 for a reference.
+    fn sys_wait4(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let pid: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let status: usize = i.args[1].into();
+
+        // TODO: Implement options, rusage and waiting for a process group (pid = 0 or pid < -1).
+        let pid = match pid {
+            -1 => None,
+            v if v > 0 => Pid::new(v),
+            _ => return Err(SysErr::Raw(EINVAL)),
+        };
+
+        // Reap the child.
+        let pmgr = current_procmgr().unwrap();
+        let (pid, v) = match pmgr.wait(td.proc(), pid)? {
+            Some(v) => v,
+            None => return Ok(SysOut::ZERO),
+        };
+
+        if status != 0 {
+            td.proc().vm().write(status, &v.to_le_bytes())?;
+        }
+
+        Ok(c_int::from(pid).into())
+    }
+
+    /// See `sys_getpid` on the PS4 for a reference.
+    fn sys_getpid(td: &Thread, _: &SysIn) -> Result<SysOut, SysErr> {
+        Ok(c_int::from(td.proc().id()).into())
+    }
+
+    /// See `sys_getppid` on the PS4 for a reference.
+    fn sys_getppid(td: &Thread, _: &SysIn) -> Result<SysOut, SysErr> {
+        let parent = td.proc().parent_mut().upgrade();
+
+        Ok(parent.map_or(0, |p| c_int::from(p.id())).into())
+    }
+
+    /// See `sys_setsid` on the PS4 for a reference.
+    fn sys_setsid(td: &Thread, _: &SysIn) -> Result<SysOut, SysErr> {
+        td.priv_check(Privilege::SCE680)?;
+
+        let pmgr = current_procmgr().unwrap();
+        let id = pmgr.setsid(td.proc())?;
+
+        Ok(c_int::from(id).into())
+    }
+
+    /// Remove `g` from the group list if it is the last reference.
+    ///
+    /// See `pgdelete` on the PS4 for a reference.
+    fn leave_group(&self, g: Arc<ProcGroup>) {
+        let mut groups = self.groups.lock();
+        let mut sessions = self.sessions.lock();
+
+        if Arc::strong_count(&g) != 1 {
+            return;
+        }
+
+        groups.remove(&g.id());
+
+        if Arc::strong_count(g.session()) == 1 {
+            sessions.remove(&g.session().id());
+        }
     }
 }
 
@@ -65,6 +375,7 @@ impl Subsystem for ProcMgr {}
 #[derive(Default)]
 pub struct ProcEvents {
     pub process_init: Event<fn(&mut Proc)>,
+    pub process_exit: Event<fn(&Proc)>,
 }
 
 /// Flags to control behavior of [`ProcMgr::fork()`].
@@ -82,7 +393,10 @@ pub struct Fork {
     ///
     /// This has the same value as `RFPROC`.
     pub create_process: bool,
-    __: bool,
+    /// Share the virtual memory with the parent instead of copying it.
+    ///
+    /// This has the same value as `RFMEM`.
+    pub share_vm: bool,
     __: bool,
     __: bool,
     __: bool,
@@ -99,7 +413,11 @@ pub struct Fork {
     __: bool,
     __: bool,
     __: bool,
-    __: bool,
+    /// Allocate the PID from the highest PID that was allocated without this flag and exclude PID
+    /// below 10.
+    ///
+    /// This has the same value as `RFHIGHPID`.
+    pub high_pid: bool,
     /// Enable [`Self::parent_signal()`].
     ///
     /// This has the same value as `RFTSIGZMB`.
@@ -120,6 +438,8 @@ pub struct Fork {
 #[derive(Debug)]
 pub enum ForkError {
     InvalidFlags,
+    NoMemory,
+    NoPid,
 }
 
 impl Error for ForkError {}
@@ -128,6 +448,56 @@ impl Display for ForkError {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::InvalidFlags => f.write_str("invalid flags"),
+            Self::NoMemory => f.write_str("not enough memory"),
+            Self::NoPid => f.write_str("no PID available"),
+        }
+    }
+}
+
+/// Represents an error when [`ProcMgr::wait()`] fails.
+#[derive(Debug)]
+pub enum WaitError {
+    NoChild,
+}
+
+impl Error for WaitError {}
+
+impl Errno for WaitError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NoChild => ECHILD,
+        }
+    }
+}
+
+impl Display for WaitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoChild => f.write_str("no child process"),
+        }
+    }
+}
+
+/// Represents an error when [`ProcMgr::setsid()`] fails.
+#[derive(Debug)]
+pub enum SetsidError {
+    GroupLeader,
+}
+
+impl Error for SetsidError {}
+
+impl Errno for SetsidError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::GroupLeader => EPERM,
+        }
+    }
+}
+
+impl Display for SetsidError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::GroupLeader => f.write_str("the process is already a group leader"),
         }
     }
 }
@@ -8,8 +8,12 @@ pub struct Pid(c_int);
 
 impl Pid {
     pub const KERNEL: Self = Self(0);
+    pub const INIT: Self = Self(1);
     pub const IDLE: Self = Self(10);
 
+    /// Maximum value of PID (AKA `pid_max`).
+    pub const MAX: c_int = 99999;
+
     /// Returns [`None`] if `v` is negative.
     pub const fn new(v: c_int) -> Option<Self> {
         if v >= 0 {
@@ -18,6 +22,45 @@ impl Pid {
             None
         }
     }
+
+    /// Find the next PID after `last` that `used` returns `false`. Returns [`None`] if all PIDs are
+    /// in use.
+    ///
+    /// See `fork_findpid` on the PS4 for a reference.
+    pub fn find(last: c_int, high: bool, mut used: impl FnMut(c_int) -> bool) -> Option<Self> {
+        // TODO: Implement randompid.
+        let mut pid = last + 1;
+
+        if high && pid < 10 {
+            pid = 10;
+        }
+
+        // We use a different algorithm here. The PS4 keep track of the range that it has checked
+        // with a global variable (AKA pidchecked), which is error-prone.
+        for _ in 0..Self::MAX {
+            if pid >= Self::MAX {
+                pid %= Self::MAX;
+
+                if pid < 100 {
+                    pid += 100;
+                }
+            }
+
+            if !used(pid) {
+                return Some(Self(pid));
+            }
+
+            pid += 1;
+        }
+
+        None
+    }
+}
+
+impl From<Pid> for c_int {
+    fn from(value: Pid) -> Self {
+        value.0
+    }
 }
 
 impl Borrow<c_int> for Pid {
@@ -37,3 +80,17 @@ impl PartialEq<Pid> for c_int {
         *self == other.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find() {
+        assert_eq!(Pid::find(0, false, |_| false), Some(Pid(1)));
+        assert_eq!(Pid::find(0, true, |_| false), Some(Pid(10)));
+        assert_eq!(Pid::find(1, false, |v| v < 5), Some(Pid(5)));
+        assert_eq!(Pid::find(Pid::MAX - 1, false, |_| false), Some(Pid(100)));
+        assert_eq!(Pid::find(0, false, |_| true), None);
+    }
+}
@@ -1,15 +1,59 @@
-use super::{ProcAbi, ProcEvents};
+use super::{FileDesc, Pid, ProcAbi, ProcEvents, ProcGroup};
+use crate::dmem::{BlockPool, DmemContainer};
 use crate::event::EventSet;
-use alloc::sync::Arc;
+use crate::lock::{Gutex, GutexGroup, GutexWrite};
+use crate::rtld::Dynlib;
+use crate::signal::{SigActs, Signal, SignalSet};
+use crate::ucred::Ucred;
+use crate::vm::VmSpace;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::ffi::c_int;
 
 /// Implementation of `proc` structure.
 pub struct Proc {
-    abi: Arc<dyn ProcAbi>, // p_sysent
+    id: Pid,                              // p_pid
+    abi: Arc<dyn ProcAbi>,                // p_sysent
+    cred: Gutex<Arc<Ucred>>,              // p_ucred
+    vm: Arc<VmSpace>,                     // p_vmspace
+    files: Arc<FileDesc>,                 // p_fd
+    parent: Gutex<Weak<Self>>,            // p_pptr
+    children: Gutex<Vec<Arc<Self>>>,      // p_children
+    group: Gutex<Option<Arc<ProcGroup>>>, // p_pgrp
+    state: Gutex<ProcState>,              // p_state + p_xstat
+    sigacts: Gutex<SigActs>,              // p_sigacts
+    siglist: Gutex<SignalSet>,            // p_siglist
+    exit_signal: Signal,                  // p_sigparent
+    dynlib: Gutex<Option<Dynlib>>,        // p_dynlib
+    dmem_container: Gutex<DmemContainer>,
+    blockpool: Gutex<Option<Arc<BlockPool>>>,
 }
 
 impl Proc {
-    pub fn new(abi: Arc<dyn ProcAbi>, events: &Arc<EventSet<ProcEvents>>) -> Arc<Self> {
-        let mut proc = Self { abi };
+    /// The new process will be in the same group and have the same credential and signal actions
+    /// as `parent`. `exit_signal` will be sent to `parent` when the new process exit.
+    pub fn new(
+        id: Pid,
+        abi: Arc<dyn ProcAbi>,
+        vm: Arc<VmSpace>,
+        files: Arc<FileDesc>,
+        parent: &Arc<Self>,
+        exit_signal: Signal,
+        events: &Arc<EventSet<ProcEvents>>,
+    ) -> Arc<Self> {
+        let cred = parent.cred_mut().clone();
+        let group = parent.group_mut().clone();
+        let sigacts = parent.sigacts_mut().clone();
+        let dmem_container = *parent.dmem_container_mut();
+        let mut proc = Self::new_bare(id, abi, vm, cred);
+
+        proc.files = files;
+
+        *proc.parent.get_mut() = Arc::downgrade(parent);
+        *proc.group.get_mut() = group;
+        *proc.sigacts.get_mut() = sigacts;
+        *proc.dmem_container.get_mut() = dmem_container;
+        proc.exit_signal = exit_signal;
 
         // Trigger process_init event.
         let mut et = events.trigger();
@@ -18,7 +62,7 @@ impl Proc {
             h(&mut proc);
         }
 
-        todo!()
+        Arc::new(proc)
     }
 
     /// This function does not do anything except initialize the struct memory. It is the caller
@@ -27,11 +71,102 @@ impl Proc {
     ///
     /// # Context safety
     /// This function does not require a CPU context.
-    pub fn new_bare(abi: Arc<dyn ProcAbi>) -> Self {
-        Self { abi }
+    pub fn new_bare(id: Pid, abi: Arc<dyn ProcAbi>, vm: Arc<VmSpace>, cred: Arc<Ucred>) -> Self {
+        let gg = GutexGroup::new();
+
+        Self {
+            id,
+            abi,
+            cred: gg.clone().spawn(cred),
+            vm,
+            files: FileDesc::new(),
+            parent: gg.clone().spawn(Weak::new()),
+            children: gg.clone().spawn(Vec::new()),
+            group: gg.clone().spawn(None),
+            state: gg.clone().spawn(ProcState::Normal),
+            sigacts: gg.clone().spawn_default(),
+            siglist: gg.clone().spawn_default(),
+            exit_signal: Signal::from_bits(0),
+            dynlib: gg.clone().spawn(None),
+            dmem_container: gg.clone().spawn(DmemContainer::Zero),
+            blockpool: gg.spawn(None),
+        }
+    }
+
+    pub fn id(&self) -> Pid {
+        self.id
     }
 
     pub fn abi(&self) -> &Arc<dyn ProcAbi> {
         &self.abi
     }
+
+    pub fn vm(&self) -> &Arc<VmSpace> {
+        &self.vm
+    }
+
+    pub fn files(&self) -> &Arc<FileDesc> {
+        &self.files
+    }
+
+    /// Replace the value to change the credential of this process. Each thread will pick up the
+    /// new credential on their next syscall.
+    pub fn cred_mut(&self) -> GutexWrite<'_, Arc<Ucred>> {
+        self.cred.write()
+    }
+
+    /// Returns [`Weak::new()`] if this is `proc0`.
+    pub fn parent_mut(&self) -> GutexWrite<'_, Weak<Self>> {
+        self.parent.write()
+    }
+
+    pub fn children_mut(&self) -> GutexWrite<'_, Vec<Arc<Self>>> {
+        self.children.write()
+    }
+
+    pub fn group_mut(&self) -> GutexWrite<'_, Option<Arc<ProcGroup>>> {
+        self.group.write()
+    }
+
+    pub fn state_mut(&self) -> GutexWrite<'_, ProcState> {
+        self.state.write()
+    }
+
+    pub fn sigacts_mut(&self) -> GutexWrite<'_, SigActs> {
+        self.sigacts.write()
+    }
+
+    /// Signals that was sent to this process but not delivered to any threads yet.
+    pub fn siglist_mut(&self) -> GutexWrite<'_, SignalSet> {
+        self.siglist.write()
+    }
+
+    /// Signal to send to the parent when this process exit. Zero if no signal to send.
+    pub fn exit_signal(&self) -> Signal {
+        self.exit_signal
+    }
+
+    /// Returns [`None`] if the process is not a dynamic linking executable.
+    pub fn dynlib_mut(&self) -> GutexWrite<'_, Option<Dynlib>> {
+        self.dynlib.write()
+    }
+
+    /// Container of direct memory that this process can access.
+    pub fn dmem_container_mut(&self) -> GutexWrite<'_, DmemContainer> {
+        self.dmem_container.write()
+    }
+
+    /// Returns [`None`] if the process has not opened any blockpool.
+    pub fn blockpool_mut(&self) -> GutexWrite<'_, Option<Arc<BlockPool>>> {
+        self.blockpool.write()
+    }
+}
+
+/// State of [`Proc`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ProcState {
+    /// `PRS_NORMAL`.
+    Normal,
+    /// `PRS_ZOMBIE` with the exit status (AKA `p_xstat`).
+    Zombie(c_int),
 }
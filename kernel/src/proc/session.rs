@@ -0,0 +1,17 @@
+use super::Pid;
+use alloc::sync::Arc;
+
+/// Implementation of `session` structure.
+pub struct Session {
+    id: Pid, // s_sid
+}
+
+impl Session {
+    pub fn new(id: Pid) -> Arc<Self> {
+        Arc::new(Self { id })
+    }
+
+    pub fn id(&self) -> Pid {
+        self.id
+    }
+}
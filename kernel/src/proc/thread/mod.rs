@@ -1,6 +1,8 @@
 use self::cell::{borrow_mut, PrivateCell};
 use super::Proc;
 use crate::lock::{Gutex, GutexGroup, GutexWrite};
+use crate::signal::SignalSet;
+use crate::ucred::{Privilege, PrivilegeError, Ucred};
 use alloc::sync::Arc;
 use core::cell::RefMut;
 use core::sync::atomic::{AtomicU8, Ordering};
@@ -19,10 +21,13 @@ mod cell;
 /// currently locked, which will can cause a panic.
 pub struct Thread {
     proc: Arc<Proc>,                   // td_proc
+    cred: PrivateCell<Arc<Ucred>>,     // td_ucred
     active_pins: AtomicU8,             // td_critnest
     active_interrupts: AtomicU8,       // td_intr_nesting_level
     active_mutexes: PrivateCell<u16>,  // td_locks
     sleeping: Gutex<usize>,            // td_wchan
+    sigmask: Gutex<SignalSet>,         // td_sigmask
+    siglist: Gutex<SignalSet>,         // td_siglist
     profiling_ticks: PrivateCell<u32>, // td_pticks
 }
 
@@ -33,17 +38,20 @@ impl Thread {
     ///
     /// # Context safety
     /// This function does not require a CPU context.
-    pub fn new_bare(proc: Arc<Proc>) -> Self {
+    pub fn new_bare(proc: Arc<Proc>, cred: Arc<Ucred>) -> Self {
         // td_critnest on the PS4 started with 1 but this does not work in our case because we use
         // RAII to increase and decrease it.
         let gg = GutexGroup::new();
 
         Self {
             proc,
+            cred: PrivateCell::new(cred),
             active_pins: AtomicU8::new(0),
             active_interrupts: AtomicU8::new(0),
             active_mutexes: PrivateCell::new(0),
-            sleeping: gg.spawn(0),
+            sleeping: gg.clone().spawn(0),
+            sigmask: gg.clone().spawn_default(),
+            siglist: gg.spawn_default(),
             profiling_ticks: PrivateCell::new(0),
         }
     }
@@ -60,6 +68,35 @@ impl Thread {
         &self.proc
     }
 
+    /// # Panics
+    /// If called from the other thread.
+    pub fn cred_mut(&self) -> RefMut<Arc<Ucred>> {
+        borrow_mut!(self, cred)
+    }
+
+    /// Replace the credential of this thread with the one from the process if it has been changed.
+    ///
+    /// See `cred_update_thread` on the PS4 for a reference.
+    ///
+    /// # Panics
+    /// If called from the other thread.
+    pub fn update_cred(&self) {
+        let cred = self.proc.cred_mut();
+        let mut cur = self.cred_mut();
+
+        if !Arc::ptr_eq(&cur, &cred) {
+            *cur = cred.clone();
+        }
+    }
+
+    /// See `priv_check` on the PS4 for a reference.
+    ///
+    /// # Panics
+    /// If called from the other thread.
+    pub fn priv_check(&self, p: Privilege) -> Result<(), PrivilegeError> {
+        self.cred_mut().priv_check(p)
+    }
+
     /// See [`crate::context::pin_cpu()`] for a safe wrapper.
     ///
     /// # Safety
@@ -88,6 +125,16 @@ impl Thread {
         self.sleeping.write()
     }
 
+    /// Signals that are blocked from delivery to this thread.
+    pub fn sigmask_mut(&self) -> GutexWrite<'_, SignalSet> {
+        self.sigmask.write()
+    }
+
+    /// Signals that was sent to this thread but not delivered yet.
+    pub fn siglist_mut(&self) -> GutexWrite<'_, SignalSet> {
+        self.siglist.write()
+    }
+
     /// # Panics
     /// If called from the other thread.
     pub fn profiling_ticks_mut(&self) -> RefMut<u32> {
@@ -0,0 +1,1153 @@
+pub use self::module::*;
+pub use self::nid::*;
+
+use self::resolver::{LookupName, ResolveFlags, SymbolResolver, NID_CHARS};
+use crate::context::current_fs;
+use crate::errno::{Errno, EINVAL, ENOEXEC, ENOMEM, EPERM, ESRCH};
+use crate::fs::Access;
+use crate::imgact::MapProgramError;
+use crate::imgfmt::elf::{
+    DynamicTag, Elf, FileType, OpenError, ReadSymbolError, Relocation, StringTableError,
+};
+use crate::proc::Thread;
+use crate::syscalls::{SysErr, SysIn, SysOut, Syscalls};
+use crate::vm::{FaultError, MapError as VmMapError, VmSpace};
+use alloc::borrow::ToOwned;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::mem::size_of;
+use core::num::NonZero;
+use krt::info;
+use macros::bitflag;
+use sha1::{Digest, Sha1};
+
+mod module;
+mod nid;
+mod resolver;
+
+/// Dynamic linking information of a process. Each process on the PS4 have one field for holding
+/// this (AKA `p_dynlib`).
+///
+/// See https://github.com/freebsd/freebsd-src/blob/release/9.1.0/libexec/rtld-elf/rtld.c for the
+/// original implementation.
+pub struct Dynlib {
+    list: Vec<Arc<Module>>,    // obj_list + obj_tail
+    mains: Vec<Arc<Module>>,   // list_main
+    globals: Vec<Arc<Module>>, // list_global
+    next_id: u32,
+    tls: TlsAlloc,
+    flags: DynlibFlags,
+    nids: Arc<NidDb>,
+}
+
+impl Dynlib {
+    /// `app` must be the main executable that already mapped.
+    ///
+    /// See `dynlib_proc_initialize_step1` on the PS4 for a reference.
+    pub fn new(
+        vm: &VmSpace,
+        app: Elf,
+        base: usize,
+        path: String,
+        nids: Arc<NidDb>,
+    ) -> Result<Self, MapError> {
+        if app.info().is_none() {
+            return Err(MapError::NotDynamic);
+        }
+
+        // Check if application need certain modules.
+        let app = Module::new(vm, app, base, path, 0, 1)?;
+        let mut flags = DynlibFlags::zeroed();
+
+        for m in app.modules() {
+            match m.name() {
+                "libSceDbgUndefinedBehaviorSanitizer" => flags |= DynlibFlags::HAS_UBSAN,
+                "libSceDbgAddressSanitizer" => flags |= DynlibFlags::HAS_ASAN,
+                _ => {}
+            }
+        }
+
+        *app.flags_mut() |= ModuleFlags::MAINPROG;
+
+        // TODO: Apply the remaining logics from dynlib_proc_initialize_step1.
+        let app = Arc::new(app);
+
+        Ok(Self {
+            list: vec![app.clone()],
+            mains: vec![app],
+            globals: Vec::new(),
+            next_id: 1,
+            tls: TlsAlloc {
+                max_index: 1,
+                last_offset: 0,
+                last_size: 0,
+                static_space: 0,
+            },
+            flags,
+            nids,
+        })
+    }
+
+    /// Returns the main executable (AKA `obj_main`).
+    pub fn app(&self) -> &Arc<Module> {
+        &self.list[0]
+    }
+
+    /// The returned iterator will never be empty and the first item is always the application
+    /// itself.
+    pub fn list(&self) -> impl ExactSizeIterator<Item = &Arc<Module>> {
+        self.list.iter()
+    }
+
+    /// The returned iterator will never be empty and the first item is always the application
+    /// itself.
+    pub fn mains(&self) -> impl Iterator<Item = &Arc<Module>> {
+        self.mains.iter()
+    }
+
+    pub fn globals(&self) -> impl Iterator<Item = &Arc<Module>> {
+        self.globals.iter()
+    }
+
+    pub fn nids(&self) -> &NidDb {
+        &self.nids
+    }
+
+    /// Returns [`None`] if no module with `id`.
+    pub fn find(&self, id: u32) -> Option<&Arc<Module>> {
+        self.list.iter().find(|m| m.id() == id)
+    }
+
+    /// Load `image` from `path` into `vm`.
+    ///
+    /// See `load_object`, `do_load_object` and `self_load_shared_object` on the PS4 for a
+    /// reference.
+    pub fn load(
+        &mut self,
+        vm: &VmSpace,
+        path: &str,
+        image: &[u8],
+        force: bool,
+        main: bool,
+    ) -> Result<Arc<Module>, LoadError> {
+        // Check if module with the same path already loaded.
+        if let Some(m) = self.list.iter().skip(1).find(|m| m.path() == path) {
+            *m.ref_count_mut() += 1;
+
+            return Ok(m.clone());
+        }
+
+        // Check if module with the same base name already loaded.
+        let name = path.rsplit('/').next().unwrap();
+
+        if !force {
+            let loaded = self
+                .list
+                .iter()
+                .skip(1)
+                .find(|m| m.names().iter().any(|n| n == name));
+
+            if let Some(m) = loaded {
+                return Ok(m.clone());
+            }
+        }
+
+        // TODO: Implement the path of do_load_object when sanitizer & 2 is set.
+        if self.flags.has(DynlibFlags::HAS_ASAN) {
+            return Err(LoadError::SanitizerNotSupported);
+        }
+
+        // Load (S)ELF.
+        let elf = Elf::parse(image).map_err(LoadError::OpenElfFailed)?;
+
+        if elf.ty() != FileType::ET_SCE_DYNAMIC || elf.info().is_none() {
+            return Err(LoadError::InvalidElf);
+        }
+
+        // Search for TLS free slot.
+        let tls = elf.tls().map(|i| &elf.programs()[i]);
+        let tls = if tls.map_or(0, |p| p.memory_size()) == 0 {
+            0
+        } else {
+            let mut index = 1;
+
+            loop {
+                // Check if the current value has been used.
+                if !self.list.iter().any(|m| m.tls_index() == index) {
+                    break;
+                }
+
+                // Someone already use the current value, increase the value and try again.
+                index += 1;
+
+                if index > self.tls.max_index {
+                    self.tls.max_index = index;
+                    break;
+                }
+            }
+
+            index
+        };
+
+        // Map the module.
+        let id = self.next_id;
+        let mut md =
+            Module::map(vm, elf, path.to_owned(), id, tls).map_err(LoadError::MapFailed)?;
+
+        md.names_mut().push(name.to_owned());
+
+        if md.flags_mut().has(ModuleFlags::TEXT_REL) {
+            return Err(LoadError::ImpureText);
+        }
+
+        // TODO: Check the call to sceSblAuthMgrIsLoadable in the self_load_shared_object on the PS4
+        // to see how it is return the value.
+        if name != "libc.sprx" && name != "libSceFios2.sprx" {
+            *md.flags_mut() |= ModuleFlags::IS_SYSTEM;
+        }
+
+        // Add to the list.
+        let md = Arc::new(md);
+
+        self.next_id += 1;
+        self.list.push(md.clone());
+
+        if main {
+            self.mains.push(md.clone());
+        }
+
+        Ok(md)
+    }
+
+    /// See `relocate_objects` on the PS4 for a reference.
+    pub fn relocate(&self, vm: &VmSpace, md: &Arc<Module>) -> Result<(), RelocateError> {
+        let resolver = SymbolResolver::new(self, self.new_algorithm());
+
+        // TODO: Implement flags & 0x800.
+        self.relocate_single(vm, md, &resolver)?;
+
+        // Relocate other modules.
+        for m in &self.list {
+            if !Arc::ptr_eq(m, md) {
+                self.relocate_single(vm, m, &resolver)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// See `do_dlsym` on the PS4 for a reference.
+    pub fn dlsym(&self, md: &Arc<Module>, name: &str, flags: ResolveFlags) -> Option<usize> {
+        let mut mname = md.modules().iter().find(|i| i.id() == 0).map(|i| i.name());
+        let mut lib = None;
+        let nid;
+        let name = if flags.has(ResolveFlags::UNK1) {
+            mname = None;
+            name
+        } else {
+            lib = mname;
+            nid = get_nid(name);
+            nid.as_str()
+        };
+
+        if md.flags_mut().has(ModuleFlags::MAINPROG) {
+            // TODO: Implement the lookup on the main program.
+            return None;
+        }
+
+        // TODO: Lookup from the DAG of the module once init_dag is fully implemented.
+        let resolver = SymbolResolver::new(self, self.new_algorithm());
+        let name = LookupName {
+            name: Some(name),
+            decoded_name: None,
+            symmod: mname,
+            symlib: lib,
+            hash: SymbolResolver::hash(Some(name), lib, mname),
+        };
+
+        resolver
+            .resolve_from_list(&name, flags | ResolveFlags::UNK3 | ResolveFlags::UNK4, [md])
+            .map(|(m, s)| m.base() + m.symbol(s).unwrap().value())
+    }
+
+    pub fn register_syscalls(sys: &mut Syscalls) {
+        sys.register(591, Self::sys_dynlib_dlsym);
+        sys.register(592, Self::sys_dynlib_get_list);
+        sys.register(593, Self::sys_dynlib_get_info);
+        sys.register(594, Self::sys_dynlib_load_prx);
+        sys.register(596, Self::sys_dynlib_do_copy_relocations);
+        sys.register(598, Self::sys_dynlib_get_proc_param);
+        sys.register(599, Self::sys_dynlib_process_needed_and_relocate);
+        sys.register(608, Self::sys_dynlib_get_info_ex);
+        sys.register(649, Self::sys_dynlib_get_obj_member);
+    }
+
+    /// See `sys_dynlib_dlsym` on the PS4 for a reference.
+    fn sys_dynlib_dlsym(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let handle = u32::try_from(i.args[0]).map_err(|_| SysErr::Raw(EINVAL))?;
+        let vm = td.proc().vm();
+        let name = vm.read_str(i.args[1].into(), 2560)?;
+        let out: usize = i.args[2].into();
+
+        // Get target module.
+        let dynlib = td.proc().dynlib_mut();
+        let dynlib = dynlib.as_ref().ok_or(SysErr::Raw(EPERM))?;
+        let md = dynlib.find(handle).ok_or(SysErr::Raw(ESRCH))?;
+
+        info!(
+            "Getting symbol '{}' from {}.",
+            dynlib.nids.symbol(&name),
+            md.path()
+        );
+
+        // Get resolving flags.
+        let flags = if name == "BaOKcng8g88" || name == "KpDMrPHvt3Q" {
+            ResolveFlags::UNK1
+        } else {
+            ResolveFlags::zeroed()
+        };
+
+        // Resolve the symbol.
+        let addr = dynlib.dlsym(md, &name, flags).ok_or(SysErr::Raw(ESRCH))?;
+
+        vm.write(out, &addr.to_le_bytes())?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_dynlib_get_list` on the PS4 for a reference.
+    fn sys_dynlib_get_list(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let buf: usize = i.args[0].into();
+        let max: usize = i.args[1].into();
+        let copied: usize = i.args[2].into();
+        let dynlib = td.proc().dynlib_mut();
+        let dynlib = dynlib.as_ref().ok_or(SysErr::Raw(EPERM))?;
+
+        // Copy module ID.
+        if dynlib.list().len() > max {
+            return Err(SysErr::Raw(ENOMEM));
+        }
+
+        let vm = td.proc().vm();
+        let ids: Vec<u8> = dynlib.list().flat_map(|m| m.id().to_le_bytes()).collect();
+
+        vm.write(buf, &ids)?;
+        vm.write(copied, &dynlib.list.len().to_le_bytes())?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_dynlib_get_info` on the PS4 for a reference.
+    fn sys_dynlib_get_info(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let handle = u32::try_from(i.args[0]).map_err(|_| SysErr::Raw(EINVAL))?;
+        let out: usize = i.args[1].into();
+        let vm = td.proc().vm();
+        let dynlib = td.proc().dynlib_mut();
+        let dynlib = dynlib.as_ref().ok_or(SysErr::Raw(EPERM))?;
+
+        // Check buffer size.
+        if Self::read_size(vm, out)? != size_of::<DynlibInfo>() {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        // Lookup the module.
+        let md = dynlib.find(handle).ok_or(SysErr::Raw(ESRCH))?;
+
+        if md.flags_mut().has(ModuleFlags::IS_SYSTEM) {
+            return Err(SysErr::Raw(EPERM));
+        }
+
+        // Fill the info.
+        let mut info = DynlibInfo {
+            size: size_of::<DynlibInfo>(),
+            name: [0; 256],
+            segments: [SegmentInfo::default(); 4],
+            segment_count: 0,
+            fingerprint: md.fingerprint(),
+        };
+
+        Self::copy_name(&mut info.name, md.file_name());
+        info.segment_count = Self::fill_segments(&mut info.segments, md, true);
+
+        vm.write(out, info.as_bytes())?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// Only absolute path is supported for now.
+    ///
+    /// See `sys_dynlib_load_prx` on the PS4 for a reference.
+    fn sys_dynlib_load_prx(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        // Not sure what is this. Maybe kernel only flags?
+        let flags = u32::try_from(i.args[1]).map_err(|_| SysErr::Raw(EINVAL))?;
+        let out: usize = i.args[2].into();
+
+        if (flags & 0xfff8ffff) != 0 {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        // TODO: It looks like the PS4 check if this get called from a browser. The problem is this
+        // check has been patched when jailbreaking so we need to see the original code before
+        // implement this.
+        let vm = td.proc().vm();
+        let path = vm.read_str(i.args[0].into(), 1024)?;
+
+        if !path.starts_with('/') {
+            // TODO: Implement relative path.
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        info!("Loading {path} with {flags:#x}.");
+
+        // Load the module. TODO: Set BIG_APP flag when the budget is implemented.
+        let mut dynlib = td.proc().dynlib_mut();
+        let dynlib = dynlib.as_mut().ok_or(SysErr::Raw(EPERM))?;
+        let image = read_file(&path, td)?;
+        let md = dynlib.load(vm, &path, &image, true, false)?;
+
+        // Add to global list if it is not in the list yet.
+        if !dynlib.globals.iter().any(|m| Arc::ptr_eq(m, &md)) {
+            dynlib.globals.push(md.clone());
+        }
+
+        // Relocate the module if this is the first time it was loaded.
+        let mut mf = md.flags_mut();
+
+        if !mf.has(ModuleFlags::DAG_INITED) {
+            if (flags & 0x20000) == 0 {
+                mf.remove(ModuleFlags::JMPSLOTS_DONE);
+            } else {
+                *mf |= ModuleFlags::JMPSLOTS_DONE;
+            }
+
+            if (flags & 0x40000) == 0 {
+                mf.remove(ModuleFlags::NOT_GET_PROC);
+            } else {
+                *mf |= ModuleFlags::NOT_GET_PROC;
+            }
+
+            // TODO: Apply the remaining logics from init_dag.
+            *mf |= ModuleFlags::DAG_INITED;
+            drop(mf);
+
+            dynlib.relocate(vm, &md)?;
+        } else {
+            drop(mf);
+        }
+
+        info!("Module {} is loaded with ID = {}.", path, md.id());
+
+        // TODO: Apply the remaining logics from the PS4.
+        vm.write(out, &md.id().to_le_bytes())?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_dynlib_do_copy_relocations` on the PS4 for a reference.
+    fn sys_dynlib_do_copy_relocations(td: &Thread, _: &SysIn) -> Result<SysOut, SysErr> {
+        let dynlib = td.proc().dynlib_mut();
+        let dynlib = dynlib.as_ref().ok_or(SysErr::Raw(EPERM))?;
+        let info = dynlib.app().info().unwrap();
+
+        if info.relocs().any(|r| r.ty() == Relocation::R_X86_64_COPY) {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_dynlib_get_proc_param` on the PS4 for a reference.
+    fn sys_dynlib_get_proc_param(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let param: usize = i.args[0].into();
+        let size: usize = i.args[1].into();
+        let vm = td.proc().vm();
+        let dynlib = td.proc().dynlib_mut();
+        let dynlib = dynlib.as_ref().ok_or(SysErr::Raw(EPERM))?;
+        let (addr, len) = match dynlib.app().proc_param() {
+            Some(v) => v,
+            None => return Err(SysErr::Raw(EINVAL)),
+        };
+
+        vm.write(param, &addr.to_le_bytes())?;
+        vm.write(size, &len.to_le_bytes())?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_dynlib_process_needed_and_relocate` on the PS4 for a reference.
+    fn sys_dynlib_process_needed_and_relocate(td: &Thread, _: &SysIn) -> Result<SysOut, SysErr> {
+        let mut dynlib = td.proc().dynlib_mut();
+        let dynlib = dynlib.as_mut().ok_or(SysErr::Raw(EINVAL))?;
+
+        // Initialize module DAG. TODO: Apply the remaining logics from init_dag.
+        for md in &dynlib.list {
+            *md.flags_mut() |= ModuleFlags::DAG_INITED;
+        }
+
+        // Initialize TLS.
+        for md in &dynlib.mains {
+            let mut flags = md.flags_mut();
+
+            if flags.has(ModuleFlags::TLS_DONE) {
+                continue;
+            }
+
+            if let Some(t) = md.tls_info().filter(|i| i.size() != 0) {
+                let off = match dynlib.tls.alloc(md.tls_index(), t.size(), t.align()) {
+                    Some(v) => v,
+                    None => continue,
+                };
+
+                *md.tls_offset_mut() = off;
+            }
+
+            *flags |= ModuleFlags::TLS_DONE;
+        }
+
+        // Do relocation.
+        info!("Relocating initial modules.");
+
+        dynlib.relocate(td.proc().vm(), dynlib.app())?;
+
+        // TODO: Apply the remaining logics from the PS4.
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_dynlib_get_info_ex` on the PS4 for a reference.
+    fn sys_dynlib_get_info_ex(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let handle = u32::try_from(i.args[0]).map_err(|_| SysErr::Raw(EINVAL))?;
+        let flags = u32::try_from(i.args[1]).map_err(|_| SysErr::Raw(EINVAL))?;
+        let out: usize = i.args[2].into();
+        let vm = td.proc().vm();
+        let dynlib = td.proc().dynlib_mut();
+        let dynlib = dynlib.as_ref().ok_or(SysErr::Raw(EPERM))?;
+
+        // Check buffer size.
+        if Self::read_size(vm, out)? != size_of::<DynlibInfoEx>() {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        // Lookup the module.
+        let md = dynlib.find(handle).ok_or(SysErr::Raw(ESRCH))?;
+        let mf = *md.flags_mut();
+        let mut info = DynlibInfoEx {
+            size: size_of::<DynlibInfoEx>(),
+            handle: md.id(),
+            refcount: *md.ref_count_mut(),
+            ..Default::default()
+        };
+
+        // Copy module name.
+        if flags & 2 == 0 || !mf.has(ModuleFlags::IS_SYSTEM) {
+            Self::copy_name(&mut info.name, md.file_name());
+        }
+
+        // Set TLS information. Not sure if the tlsinit can be zero when the tlsinitsize is zero.
+        // Let's keep the same behavior as the PS4 for now.
+        info.tlsindex = md.tls_index() & 0xffff;
+
+        if flags & 1 != 0 {
+            let mut upper = 0;
+
+            if mf.has(ModuleFlags::IS_SYSTEM) {
+                upper |= 1;
+            }
+
+            if mf.has(ModuleFlags::MAINPROG) {
+                upper |= 2;
+            }
+
+            info.tlsindex |= upper << 16;
+        }
+
+        if let Some(i) = md.tls_info() {
+            info.tlsinit = i.init();
+            info.tlsinitsize = i.init_size() as u32;
+            info.tlssize = i.size() as u32;
+            info.tlsalign = i.align() as u32;
+        } else {
+            info.tlsinit = md.base();
+        }
+
+        info.tlsoffset = *md.tls_offset_mut() as u32;
+
+        // Initialization and finalization functions.
+        if !mf.has(ModuleFlags::NOT_GET_PROC) {
+            info.init = md.init().unwrap_or(0);
+            info.fini = md.fini().unwrap_or(0);
+        }
+
+        // Exception handling.
+        if let Some(i) = md.eh_info() {
+            info.eh_frame_hdr = i.header();
+            info.eh_frame_hdr_size = i.header_size() as u32;
+            info.eh_frame = i.frame();
+            info.eh_frame_size = i.frame_size() as u32;
+        } else {
+            info.eh_frame_hdr = md.base();
+        }
+
+        info.segment_count = Self::fill_segments(&mut info.segments, md, false);
+
+        vm.write(out, info.as_bytes())?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_dynlib_get_obj_member` on the PS4 for a reference.
+    fn sys_dynlib_get_obj_member(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let handle = u32::try_from(i.args[0]).map_err(|_| SysErr::Raw(EINVAL))?;
+        let ty: usize = i.args[1].into();
+        let out: usize = i.args[2].into();
+        let dynlib = td.proc().dynlib_mut();
+        let dynlib = dynlib.as_ref().ok_or(SysErr::Raw(EINVAL))?;
+        let md = dynlib.find(handle).ok_or(SysErr::Raw(ESRCH))?;
+        let value = match ty {
+            8 => md.mod_param().unwrap_or(0),
+            _ => return Err(SysErr::Raw(EINVAL)), // TODO: Implement ty = 1-4 and 7.
+        };
+
+        td.proc().vm().write(out, &value.to_le_bytes())?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `relocate_one_object` on the PS4 for a reference.
+    fn relocate_single(
+        &self,
+        vm: &VmSpace,
+        md: &Arc<Module>,
+        resolver: &SymbolResolver,
+    ) -> Result<(), RelocateError> {
+        let mut relocated = md.relocated_mut();
+
+        self.relocate_rela(vm, md, &mut relocated, resolver)?;
+
+        if !md.flags_mut().has(ModuleFlags::JMPSLOTS_DONE) {
+            self.relocate_plt(vm, md, &mut relocated, resolver)?;
+        }
+
+        Ok(())
+    }
+
+    /// See `reloc_non_plt` on the PS4 for a reference.
+    fn relocate_rela(
+        &self,
+        vm: &VmSpace,
+        md: &Arc<Module>,
+        relocated: &mut [bool],
+        resolver: &SymbolResolver,
+    ) -> Result<(), RelocateError> {
+        let info = match md.info() {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        for (i, reloc) in info.relocs().enumerate() {
+            // Check if the entry already relocated.
+            if relocated[i] {
+                continue;
+            }
+
+            // Resolve value.
+            let addr = md.base() + reloc.offset();
+            let addend = reloc.addend();
+            let sym = reloc.symbol();
+            let flags = ResolveFlags::zeroed();
+            let read = || {
+                let mut buf = [0; 8];
+
+                vm.force_read(addr, &mut buf)
+                    .map(|_| usize::from_le_bytes(buf))
+                    .map_err(|e| RelocateError::ReadFailed(md.path().to_owned(), addr, e))
+            };
+
+            // TODO: Apply checks from reloc_non_plt.
+            let value = match reloc.ty() {
+                Relocation::R_X86_64_NONE => break,
+                Relocation::R_X86_64_64 => match resolver.resolve_with_local(md, sym, flags) {
+                    Some((m, s)) => Self::symbol_addr(&m, s).wrapping_add_signed(addend),
+                    None => continue,
+                },
+                Relocation::R_X86_64_GLOB_DAT => {
+                    match resolver.resolve_with_local(md, sym, flags) {
+                        Some((m, s)) => Self::symbol_addr(&m, s),
+                        None => continue,
+                    }
+                }
+                Relocation::R_X86_64_RELATIVE => md.base().wrapping_add_signed(addend),
+                Relocation::R_X86_64_DTPMOD64 => {
+                    match resolver.resolve_with_local(md, sym, flags) {
+                        Some((m, _)) => read()? + m.tls_index() as usize,
+                        None => continue,
+                    }
+                }
+                Relocation::R_X86_64_DTPOFF64 => {
+                    match resolver.resolve_with_local(md, sym, flags) {
+                        Some((m, s)) => {
+                            (read()? + m.symbol(s).unwrap().value()).wrapping_add_signed(addend)
+                        }
+                        None => continue,
+                    }
+                }
+                v => {
+                    return Err(RelocateError::UnsupportedRela(
+                        md.path().to_owned(),
+                        v,
+                        self.symbol_name(md, sym),
+                    ))
+                }
+            };
+
+            // TODO: Check what relocate_text_or_data_segment on the PS4 is doing.
+            vm.force_write(addr, &value.to_le_bytes())
+                .map_err(|e| RelocateError::WriteFailed(md.path().to_owned(), addr, e))?;
+
+            relocated[i] = true;
+        }
+
+        Ok(())
+    }
+
+    /// See `reloc_jmplots` on the PS4 for a reference.
+    fn relocate_plt(
+        &self,
+        vm: &VmSpace,
+        md: &Arc<Module>,
+        relocated: &mut [bool],
+        resolver: &SymbolResolver,
+    ) -> Result<(), RelocateError> {
+        let info = match md.info() {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        for (i, reloc) in info.plt_relocs().enumerate() {
+            // Check if the entry already relocated.
+            let index = info.reloc_count() + i;
+
+            if relocated[index] {
+                continue;
+            }
+
+            // Check relocation type.
+            if reloc.ty() != Relocation::R_X86_64_JUMP_SLOT {
+                return Err(RelocateError::UnsupportedPlt(
+                    md.path().to_owned(),
+                    reloc.ty(),
+                    self.symbol_name(md, reloc.symbol()),
+                ));
+            }
+
+            // Resolve symbol.
+            let (m, s) = match resolver.resolve_with_local(md, reloc.symbol(), ResolveFlags::UNK1) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            // Write the value.
+            let addr = md.base() + reloc.offset();
+            let value = Self::symbol_addr(&m, s).wrapping_add_signed(reloc.addend());
+
+            vm.force_write(addr, &value.to_le_bytes())
+                .map_err(|e| RelocateError::WriteFailed(md.path().to_owned(), addr, e))?;
+
+            relocated[index] = true;
+        }
+
+        Ok(())
+    }
+
+    /// Returns a human-readable name of symbol `index` in `md` or [`None`] if the relocation does
+    /// not reference any symbol.
+    fn symbol_name(&self, md: &Module, index: usize) -> Option<String> {
+        let sym = md.symbol(index).filter(|_| index != 0)?;
+
+        Some(self.nids.symbol(sym.name()).to_string())
+    }
+
+    fn symbol_addr(md: &Module, sym: usize) -> usize {
+        // The null symbol is used as sym_zero for an unresolved weak symbol so it is always zero.
+        if sym == 0 {
+            return 0;
+        }
+
+        md.base() + md.symbol(sym).unwrap().value()
+    }
+
+    fn new_algorithm(&self) -> bool {
+        self.app().sdk_ver() >= 0x5000000 || self.flags.has(DynlibFlags::HAS_ASAN)
+    }
+
+    /// Read the first field of `DynlibInfo` or `DynlibInfoEx` from the user.
+    fn read_size(vm: &VmSpace, addr: usize) -> Result<usize, FaultError> {
+        let mut buf = [0; 8];
+
+        vm.read(addr, &mut buf)?;
+
+        Ok(usize::from_le_bytes(buf))
+    }
+
+    fn copy_name(buf: &mut [u8; 256], name: &str) {
+        let len = name.len().min(buf.len() - 1);
+
+        buf[..len].copy_from_slice(&name.as_bytes()[..len]);
+    }
+
+    /// Returns the number of segments.
+    fn fill_segments(segs: &mut [SegmentInfo; 4], md: &Module, relro: bool) -> u32 {
+        let text = md.text();
+        let data = md.data();
+
+        segs[0] = SegmentInfo {
+            addr: text.start,
+            size: text.len() as u32,
+            prot: 5,
+        };
+
+        segs[1] = SegmentInfo {
+            addr: data.start,
+            size: data.len() as u32,
+            prot: 3,
+        };
+
+        match md.relro() {
+            Some(v) if relro => {
+                segs[2] = SegmentInfo {
+                    addr: v.start,
+                    size: v.len() as u32,
+                    prot: 1,
+                };
+
+                3
+            }
+            _ => 2,
+        }
+    }
+}
+
+/// Returns NID of `name`.
+pub fn get_nid(name: &str) -> String {
+    const NID_SALT: [u8; 16] = [
+        0x51, 0x8d, 0x64, 0xa6, 0x35, 0xde, 0xd8, 0xc1, 0xe6, 0xb0, 0x39, 0xb1, 0xc3, 0xe5, 0x52,
+        0x30,
+    ];
+
+    // Get hash.
+    let mut sha1 = Sha1::new();
+
+    sha1.update(name.as_bytes());
+    sha1.update(NID_SALT);
+
+    // Get NID.
+    let hash = u64::from_ne_bytes(sha1.finalize()[..8].try_into().unwrap());
+    let mut nid = String::with_capacity(11);
+
+    for i in 0..10 {
+        nid.push(NID_CHARS[((hash >> (58 - i * 6)) & 0x3f) as usize].into());
+    }
+
+    nid.push(NID_CHARS[((hash & 0xf) * 4) as usize].into());
+    nid
+}
+
+/// Read the whole file at `path`.
+fn read_file(path: &str, td: &Thread) -> Result<Vec<u8>, SysErr> {
+    let fs = current_fs().unwrap();
+    let vn = fs.lookup(path, true, td)?;
+
+    vn.access(td, Access::READ).map_err(SysErr::Object)?;
+    vn.read_all(td).map_err(SysErr::Object)
+}
+
+/// Contains how TLS was allocated so far.
+struct TlsAlloc {
+    max_index: u32,      // tls_max_index
+    last_offset: usize,  // tls_last_offset
+    last_size: usize,    // tls_last_size
+    static_space: usize, // tls_static_space
+}
+
+impl TlsAlloc {
+    /// Returns the offset of the TLS block for the module or [`None`] if there are no static space
+    /// available.
+    ///
+    /// See `allocate_tls_offset` on the PS4 for a reference.
+    fn alloc(&mut self, index: u32, size: usize, align: usize) -> Option<usize> {
+        let align = align.max(1);
+        let off = if index == 1 {
+            size.next_multiple_of(align)
+        } else {
+            (self.last_offset + size).next_multiple_of(align)
+        };
+
+        if self.static_space != 0 && off > self.static_space {
+            return None;
+        }
+
+        self.last_offset = off;
+        self.last_size = size;
+
+        Some(off)
+    }
+}
+
+/// Flags of [`Dynlib`].
+#[bitflag(u32)]
+enum DynlibFlags {
+    HAS_UBSAN = 0x01,
+    HAS_ASAN = 0x02,
+}
+
+/// Implementation of `dynlib_segment_info`.
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct SegmentInfo {
+    addr: usize,
+    size: u32,
+    prot: u32,
+}
+
+/// Implementation of `dynlib_info`.
+#[repr(C)]
+struct DynlibInfo {
+    size: usize,
+    name: [u8; 256],
+    segments: [SegmentInfo; 4],
+    segment_count: u32,
+    fingerprint: [u8; 0x14],
+}
+
+impl DynlibInfo {
+    fn as_bytes(&self) -> &[u8] {
+        // SAFETY: The struct does not have any padding.
+        unsafe { core::slice::from_raw_parts((self as *const Self).cast(), size_of::<Self>()) }
+    }
+}
+
+const _: () = assert!(size_of::<DynlibInfo>() == 0x160);
+
+/// Implementation of `dynlib_info_ex`.
+#[repr(C)]
+struct DynlibInfoEx {
+    size: usize,
+    name: [u8; 256],
+    handle: u32,
+    tlsindex: u32,
+    tlsinit: usize,
+    tlsinitsize: u32,
+    tlssize: u32,
+    tlsoffset: u32,
+    tlsalign: u32,
+    init: usize,
+    fini: usize,
+    unk1: u64, // Always zero.
+    unk2: u64, // Same here.
+    eh_frame_hdr: usize,
+    eh_frame: usize,
+    eh_frame_hdr_size: u32,
+    eh_frame_size: u32,
+    segments: [SegmentInfo; 4],
+    segment_count: u32, // Always 2.
+    refcount: u32,
+}
+
+impl DynlibInfoEx {
+    fn as_bytes(&self) -> &[u8] {
+        // SAFETY: The struct does not have any padding.
+        unsafe { core::slice::from_raw_parts((self as *const Self).cast(), size_of::<Self>()) }
+    }
+}
+
+impl Default for DynlibInfoEx {
+    fn default() -> Self {
+        Self {
+            size: 0,
+            name: [0; 256],
+            handle: 0,
+            tlsindex: 0,
+            tlsinit: 0,
+            tlsinitsize: 0,
+            tlssize: 0,
+            tlsoffset: 0,
+            tlsalign: 0,
+            init: 0,
+            fini: 0,
+            unk1: 0,
+            unk2: 0,
+            eh_frame_hdr: 0,
+            eh_frame: 0,
+            eh_frame_hdr_size: 0,
+            eh_frame_size: 0,
+            segments: [SegmentInfo::default(); 4],
+            segment_count: 0,
+            refcount: 0,
+        }
+    }
+}
+
+const _: () = assert!(size_of::<DynlibInfoEx>() == 0x1a8);
+
+/// Represents an error when [`Module`] fails to construct.
+#[derive(Debug)]
+pub enum MapError {
+    NotDynamic,
+    ReserveFailed(VmMapError),
+    MapProgramFailed(MapProgramError),
+    ReadProcParamFailed(FaultError),
+    ReadSymbolFailed(usize, ReadSymbolError),
+    ReadStringFailed(usize, DynamicTag, StringTableError),
+    ObsoleteFlags(usize),
+    InvalidFingerprint,
+    InitPltFailed(usize, FaultError),
+}
+
+impl Error for MapError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::ReserveFailed(e) => Some(e),
+            Self::MapProgramFailed(e) => Some(e),
+            Self::ReadProcParamFailed(e) | Self::InitPltFailed(_, e) => Some(e),
+            Self::ReadSymbolFailed(_, e) => Some(e),
+            Self::ReadStringFailed(_, _, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Display for MapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotDynamic => f.write_str("the image is not dynamic linking"),
+            Self::ReserveFailed(_) => f.write_str("couldn't reserve the address space"),
+            Self::MapProgramFailed(_) => f.write_str("couldn't map the programs"),
+            Self::ReadProcParamFailed(_) => f.write_str("couldn't read PT_SCE_PROCPARAM"),
+            Self::ReadSymbolFailed(i, _) => write!(f, "couldn't read symbol #{i}"),
+            Self::ReadStringFailed(i, t, _) => write!(f, "couldn't read {t} at dynamic entry #{i}"),
+            Self::ObsoleteFlags(i) => write!(f, "DT_FLAGS at dynamic entry #{i} is obsolete"),
+            Self::InvalidFingerprint => f.write_str("DT_SCE_FINGERPRINT is not valid"),
+            Self::InitPltFailed(i, _) => write!(f, "couldn't initialize PLT entry #{i}"),
+        }
+    }
+}
+
+/// Represents an error when [`Dynlib::load()`] fails.
+#[derive(Debug)]
+pub enum LoadError {
+    SanitizerNotSupported,
+    OpenElfFailed(OpenError),
+    InvalidElf,
+    MapFailed(MapError),
+    ImpureText,
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::OpenElfFailed(e) => Some(e),
+            Self::MapFailed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::SanitizerNotSupported => {
+                f.write_str("loading a module with address sanitizer is not supported")
+            }
+            Self::OpenElfFailed(_) => f.write_str("couldn't open (S)ELF"),
+            Self::InvalidElf => f.write_str("the specified file is not a valid module"),
+            Self::MapFailed(_) => f.write_str("couldn't map the module"),
+            Self::ImpureText => f.write_str("the specified file has impure text"),
+        }
+    }
+}
+
+impl Errno for LoadError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::SanitizerNotSupported
+            | Self::OpenElfFailed(_)
+            | Self::InvalidElf
+            | Self::MapFailed(_) => ENOEXEC,
+            Self::ImpureText => EINVAL,
+        }
+    }
+}
+
+/// Represents an error when [`Dynlib::relocate()`] fails.
+#[derive(Debug)]
+pub enum RelocateError {
+    ReadFailed(String, usize, FaultError),
+    WriteFailed(String, usize, FaultError),
+    UnsupportedRela(String, u32, Option<String>),
+    UnsupportedPlt(String, u32, Option<String>),
+}
+
+impl Error for RelocateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::ReadFailed(_, _, e) | Self::WriteFailed(_, _, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Display for RelocateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ReadFailed(m, a, _) => write!(f, "couldn't read {a:#x} on {m}"),
+            Self::WriteFailed(m, a, _) => write!(f, "couldn't write {a:#x} on {m}"),
+            Self::UnsupportedRela(m, t, s) => {
+                write!(f, "relocation type {t} on {m} is not supported")?;
+
+                if let Some(s) = s {
+                    write!(f, " (symbol {s})")?;
+                }
+
+                Ok(())
+            }
+            Self::UnsupportedPlt(m, t, s) => {
+                write!(f, "PLT relocation type {t} on {m} is not supported")?;
+
+                if let Some(s) = s {
+                    write!(f, " (symbol {s})")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Errno for RelocateError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::ReadFailed(_, _, e) | Self::WriteFailed(_, _, e) => e.errno(),
+            Self::UnsupportedRela(_, _, _) => ENOEXEC,
+            Self::UnsupportedPlt(_, _, _) => EINVAL,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nid() {
+        assert_eq!(get_nid("sceKernelGetProcParam"), "959qrazPIrg");
+        assert_eq!(get_nid("printf"), "hcuQgD53UxM");
+        assert_eq!(
+            SymbolResolver::hash(Some("a"), Some("b"), Some("c")),
+            0x639493
+        );
+    }
+}
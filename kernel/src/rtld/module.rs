@@ -0,0 +1,538 @@
+use super::MapError;
+use crate::imgact::map_programs;
+use crate::imgfmt::elf::{DynamicFlags, DynamicTag, Elf, FileInfo, Program, Symbol};
+use crate::lock::{Gutex, GutexGroup, GutexWrite};
+use crate::vm::{VmProt, VmSpace};
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+use macros::bitflag;
+
+/// Implementation of `Obj_Entry` structure.
+pub struct Module {
+    id: u32,
+    path: String,                       // path
+    names: Vec<String>,                 // names
+    base: usize,                        // relocbase
+    text: Range<usize>,                 // mapbase + textsize
+    data: Range<usize>,                 // database + datasize
+    relro: Option<Range<usize>>,        // relro_addr + relro_size
+    entry: Option<usize>,               // entry
+    init: Option<usize>,                // init
+    fini: Option<usize>,                // fini
+    tls_index: u32,                     // tlsindex
+    tls_offset: Gutex<usize>,           // tlsoffset
+    tls_info: Option<ModuleTls>,        // tlsinit + tlsinitsize + tlssize + tlsalign
+    eh_info: Option<ModuleEh>, // eh_frame_hdr + eh_frame_hdr_size + eh_frame + eh_frame_size
+    proc_param: Option<(usize, usize)>, // proc_param + proc_param_size
+    mod_param: Option<usize>,  // module_param
+    sdk_ver: u32,
+    flags: Gutex<ModuleFlags>,
+    needed: Vec<String>,         // needed
+    modules: Vec<ModuleInfo>,    // file_info->modules
+    libraries: Vec<LibraryInfo>, // file_info->libraries
+    fingerprint: [u8; 20],
+    info: Option<FileInfo>, // file_info
+    symbols: Vec<Symbol>,
+    relocated: Gutex<Vec<bool>>,
+    ref_count: Gutex<u32>, // refcount
+}
+
+impl Module {
+    /// Map `elf` to the new location in `vm`.
+    ///
+    /// See `self_load_shared_object` on the PS4 for a reference.
+    pub fn map(
+        vm: &VmSpace,
+        elf: Elf,
+        path: String,
+        id: u32,
+        tls_index: u32,
+    ) -> Result<Self, MapError> {
+        // Reserve the address space.
+        let mapping = elf.mapping().clone();
+        let addr = vm
+            .mmap(0, mapping.len(), VmProt::zeroed(), false)
+            .map_err(MapError::ReserveFailed)?;
+        let base = addr - mapping.start;
+
+        map_programs(vm, &elf, base).map_err(MapError::MapProgramFailed)?;
+
+        Self::new(vm, elf, base, path, id, tls_index)
+    }
+
+    /// Create a [`Module`] from `elf` that already mapped in `vm` at `base`.
+    pub fn new(
+        vm: &VmSpace,
+        elf: Elf,
+        base: usize,
+        path: String,
+        id: u32,
+        tls_index: u32,
+    ) -> Result<Self, MapError> {
+        let prog = |i: Option<usize>| i.map(|i| &elf.programs()[i]);
+        let seg = |p: &Program| (base + p.addr())..(base + Program::align_page(p.end()));
+
+        // Get segments.
+        let text = prog(elf.code()).map(seg).unwrap_or_default();
+        let data = prog(elf.data()).map(seg).unwrap_or_default();
+        let relro = prog(elf.relro()).map(seg);
+
+        // Get TLS.
+        let tls_info = prog(elf.tls()).map(|p| ModuleTls {
+            init: base + p.addr(),
+            init_size: p.file_size(),
+            size: p.memory_size(),
+            align: p.alignment(),
+        });
+
+        // Get EH frame.
+        let eh_info = match prog(elf.eh()) {
+            Some(p) if p.addr() != 0 && p.memory_size() != 0 => {
+                let header = base + p.addr();
+                let header_size = p.memory_size();
+                let (frame, frame_size) = Self::digest_eh(vm, header, &text);
+
+                Some(ModuleEh {
+                    header,
+                    header_size,
+                    frame,
+                    frame_size,
+                })
+            }
+            _ => None,
+        };
+
+        // Get parameters.
+        let proc_param = prog(elf.proc_param()).map(|p| (base + p.addr(), p.file_size()));
+        let mod_param = prog(elf.mod_param()).map(|p| base + p.addr());
+        let sdk_ver = match proc_param {
+            Some((addr, size)) if size >= 0x14 => {
+                let mut buf = [0; 4];
+
+                vm.force_read(addr + 0x10, &mut buf)
+                    .map_err(MapError::ReadProcParamFailed)?;
+
+                u32::from_le_bytes(buf)
+            }
+            _ => 0,
+        };
+
+        // Load symbols.
+        let entry = elf.entry_addr().map(|v| base + v);
+        let info = elf.into_info();
+        let symbols = match &info {
+            Some(info) => {
+                let mut r = Vec::with_capacity(info.symbol_count());
+
+                for (i, s) in info.symbols().enumerate() {
+                    r.push(s.map_err(|e| MapError::ReadSymbolFailed(i, e))?);
+                }
+
+                r
+            }
+            None => Vec::new(),
+        };
+
+        // Build the module.
+        let gg = GutexGroup::new();
+        let relocated = info
+            .as_ref()
+            .map(|i| vec![false; i.reloc_count() + i.plt_count()])
+            .unwrap_or_default();
+        let mut md = Self {
+            id,
+            path,
+            names: Vec::new(),
+            base,
+            text,
+            data,
+            relro,
+            entry,
+            init: None,
+            fini: None,
+            tls_index,
+            tls_offset: gg.clone().spawn(0),
+            tls_info,
+            eh_info,
+            proc_param,
+            mod_param,
+            sdk_ver,
+            flags: gg.clone().spawn(ModuleFlags::IS_NEW),
+            needed: Vec::new(),
+            modules: Vec::new(),
+            libraries: Vec::new(),
+            fingerprint: [0; 20],
+            info: None,
+            symbols,
+            relocated: gg.clone().spawn(relocated),
+            ref_count: gg.spawn(1),
+        };
+
+        if let Some(info) = info {
+            md.digest_dynamic(&info)?;
+            md.init_plt(vm, &info)?;
+            md.info = Some(info);
+        }
+
+        Ok(md)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the file name of [`Self::path()`].
+    pub fn file_name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap()
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn names_mut(&mut self) -> &mut Vec<String> {
+        &mut self.names
+    }
+
+    /// Returns the value need to add to the virtual address in the image to get the actual address.
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    pub fn text(&self) -> &Range<usize> {
+        &self.text
+    }
+
+    pub fn data(&self) -> &Range<usize> {
+        &self.data
+    }
+
+    pub fn relro(&self) -> Option<&Range<usize>> {
+        self.relro.as_ref()
+    }
+
+    #[allow(dead_code)] // TODO: Remove this once create_init can read mini-syscore.elf.
+    pub fn entry(&self) -> Option<usize> {
+        self.entry
+    }
+
+    pub fn init(&self) -> Option<usize> {
+        self.init
+    }
+
+    pub fn fini(&self) -> Option<usize> {
+        self.fini
+    }
+
+    pub fn tls_index(&self) -> u32 {
+        self.tls_index
+    }
+
+    pub fn tls_offset_mut(&self) -> GutexWrite<'_, usize> {
+        self.tls_offset.write()
+    }
+
+    pub fn tls_info(&self) -> Option<&ModuleTls> {
+        self.tls_info.as_ref()
+    }
+
+    pub fn eh_info(&self) -> Option<&ModuleEh> {
+        self.eh_info.as_ref()
+    }
+
+    /// Returns the address and the size of `PT_SCE_PROCPARAM`.
+    pub fn proc_param(&self) -> Option<(usize, usize)> {
+        self.proc_param
+    }
+
+    /// Returns the address of `PT_SCE_MODULEPARAM`.
+    pub fn mod_param(&self) -> Option<usize> {
+        self.mod_param
+    }
+
+    pub fn sdk_ver(&self) -> u32 {
+        self.sdk_ver
+    }
+
+    pub fn flags_mut(&self) -> GutexWrite<'_, ModuleFlags> {
+        self.flags.write()
+    }
+
+    #[allow(dead_code)] // TODO: Remove this once the filesystem is implemented.
+    pub fn needed(&self) -> &[String] {
+        &self.needed
+    }
+
+    pub fn modules(&self) -> &[ModuleInfo] {
+        &self.modules
+    }
+
+    pub fn libraries(&self) -> &[LibraryInfo] {
+        &self.libraries
+    }
+
+    pub fn fingerprint(&self) -> [u8; 20] {
+        self.fingerprint
+    }
+
+    /// Returns [`None`] if the module is not dynamic linking.
+    pub fn info(&self) -> Option<&FileInfo> {
+        self.info.as_ref()
+    }
+
+    pub fn symbol(&self, i: usize) -> Option<&Symbol> {
+        self.symbols.get(i)
+    }
+
+    /// Returns a flag for each relocation entry to indicate if the entry has been relocated. The
+    /// entries of `DT_SCE_JMPREL` will be after `DT_SCE_RELA`.
+    pub fn relocated_mut(&self) -> GutexWrite<'_, Vec<bool>> {
+        self.relocated.write()
+    }
+
+    pub fn ref_count_mut(&self) -> GutexWrite<'_, u32> {
+        self.ref_count.write()
+    }
+
+    /// Returns the address and the size of `.eh_frame`. Returns zero for both if the header is not
+    /// supported.
+    fn digest_eh(vm: &VmSpace, hdr: usize, text: &Range<usize>) -> (usize, usize) {
+        let read = |addr: usize, buf: &mut [u8]| vm.force_read(addr, buf).is_ok();
+
+        // Get the first frame.
+        let mut buf = [0; 8];
+
+        if !read(hdr, &mut buf) {
+            return (0, 0);
+        }
+
+        let off = i32::from_le_bytes(buf[4..].try_into().unwrap()) as isize;
+        let frame = match buf[1] {
+            27 if off != 0 => match (hdr + 4).checked_add_signed(off) {
+                Some(v) => v,
+                None => return (0, 0),
+            },
+            _ => return (0, 0),
+        };
+
+        // Get the size of all frames.
+        let mut next = frame;
+        let mut total = 0;
+
+        loop {
+            let mut buf = [0; 12];
+
+            if !text.contains(&next) || !read(next, &mut buf) {
+                return (0, 0);
+            }
+
+            let size = match u32::from_le_bytes(buf[..4].try_into().unwrap()) {
+                0 => {
+                    total += 4;
+                    break;
+                }
+                0xffffffff => u64::from_le_bytes(buf[4..].try_into().unwrap()) as usize + 12,
+                v => v as usize + 4,
+            };
+
+            next += size;
+            total += size;
+        }
+
+        (frame, total)
+    }
+
+    /// See `dynlib_initialize_pltgot_each` on the PS4 for a reference.
+    fn init_plt(&self, vm: &VmSpace, info: &FileInfo) -> Result<(), MapError> {
+        for (i, reloc) in info.plt_relocs().enumerate() {
+            // Not sure why Sony initialize each PLT relocation to 0xeffffffe????????. My guess is
+            // that they use this value to catch unpatched PLT entry.
+            let addr = self.base + reloc.offset();
+            let value = i as u64 | 0xeffffffe00000000;
+
+            vm.force_write(addr, &value.to_le_bytes())
+                .map_err(|e| MapError::InitPltFailed(i, e))?;
+        }
+
+        Ok(())
+    }
+
+    /// See `digest_dynamic` on the PS4 for a reference.
+    fn digest_dynamic(&mut self, info: &FileInfo) -> Result<(), MapError> {
+        // TODO: Implement the remaining tags.
+        let mut fingerprint = 0;
+
+        for (i, (tag, value)) in info.dynamic().enumerate() {
+            let str = |v: [u8; 8]| -> Result<String, MapError> {
+                info.read_str(u64::from_le_bytes(v) as usize)
+                    .map(|v| v.to_owned())
+                    .map_err(|e| MapError::ReadStringFailed(i, tag, e))
+            };
+
+            match tag {
+                DynamicTag::DT_NULL => break,
+                DynamicTag::DT_NEEDED => self.needed.push(str(value)?),
+                DynamicTag::DT_INIT => {
+                    self.init = Some(self.base + u64::from_le_bytes(value) as usize)
+                }
+                DynamicTag::DT_FINI => {
+                    self.fini = Some(self.base + u64::from_le_bytes(value) as usize)
+                }
+                DynamicTag::DT_SONAME => self.names.push(str(value)?),
+                DynamicTag::DT_TEXTREL => *self.flags.get_mut() |= ModuleFlags::TEXT_REL,
+                DynamicTag::DT_FLAGS => {
+                    let flags = DynamicFlags::from(u64::from_le_bytes(value));
+
+                    if flags.has(DynamicFlags::DF_SYMBOLIC) || flags.has(DynamicFlags::DF_BIND_NOW)
+                    {
+                        return Err(MapError::ObsoleteFlags(i));
+                    } else if flags.has(DynamicFlags::DF_TEXTREL) {
+                        *self.flags.get_mut() |= ModuleFlags::TEXT_REL;
+                    }
+                }
+                DynamicTag::DT_SCE_FINGERPRINT => fingerprint = u64::from_le_bytes(value) as usize,
+                DynamicTag::DT_SCE_MODULE_INFO | DynamicTag::DT_SCE_NEEDED_MODULE => {
+                    let (name, id) = info
+                        .read_module(value)
+                        .map_err(|e| MapError::ReadStringFailed(i, tag, e))?;
+
+                    self.modules.push(ModuleInfo {
+                        id,
+                        name: name.to_owned(),
+                    });
+                }
+                DynamicTag::DT_SCE_EXPORT_LIB | DynamicTag::DT_SCE_IMPORT_LIB => {
+                    let (name, id) = info
+                        .read_library(value)
+                        .map_err(|e| MapError::ReadStringFailed(i, tag, e))?;
+
+                    self.libraries.push(LibraryInfo {
+                        id,
+                        name: name.to_owned(),
+                        export: tag == DynamicTag::DT_SCE_EXPORT_LIB,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        self.fingerprint = info
+            .read_fingerprint(fingerprint)
+            .ok_or(MapError::InvalidFingerprint)?;
+
+        Ok(())
+    }
+}
+
+/// Information of `PT_TLS` after mapped.
+pub struct ModuleTls {
+    init: usize,      // tlsinit
+    init_size: usize, // tlsinitsize
+    size: usize,      // tlssize
+    align: usize,     // tlsalign
+}
+
+impl ModuleTls {
+    pub fn init(&self) -> usize {
+        self.init
+    }
+
+    pub fn init_size(&self) -> usize {
+        self.init_size
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn align(&self) -> usize {
+        self.align
+    }
+}
+
+/// Information of `PT_GNU_EH_FRAME` after mapped.
+pub struct ModuleEh {
+    header: usize,      // eh_frame_hdr
+    header_size: usize, // eh_frame_hdr_size
+    frame: usize,       // eh_frame
+    frame_size: usize,  // eh_frame_size
+}
+
+impl ModuleEh {
+    pub fn header(&self) -> usize {
+        self.header
+    }
+
+    pub fn header_size(&self) -> usize {
+        self.header_size
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+}
+
+/// Entry of `DT_SCE_MODULE_INFO` or `DT_SCE_NEEDED_MODULE`.
+pub struct ModuleInfo {
+    id: u16,
+    name: String,
+}
+
+impl ModuleInfo {
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Entry of `DT_SCE_EXPORT_LIB` or `DT_SCE_IMPORT_LIB`.
+pub struct LibraryInfo {
+    id: u16,
+    name: String,
+    export: bool,
+}
+
+impl LibraryInfo {
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[allow(dead_code)] // TODO: Remove this once symlook_obj is fully implemented.
+    pub fn is_export(&self) -> bool {
+        self.export
+    }
+}
+
+/// Flags of [`Module`].
+#[bitflag(u16)]
+pub enum ModuleFlags {
+    MAINPROG = 0x0001,
+    TEXT_REL = 0x0002,
+    TLS_DONE = 0x0008,
+    INIT_SCANNED = 0x0010,
+    ON_FINI_LIST = 0x0020,
+    DAG_INITED = 0x0040,
+    IS_SYSTEM = 0x0100,
+    IS_NEW = 0x0200,
+    LIBC_FIOS = 0x0400,
+    JMPSLOTS_DONE = 0x0800,
+    NOT_GET_PROC = 0x1000,
+}
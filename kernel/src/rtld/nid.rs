@@ -0,0 +1,142 @@
+use super::NID_CHARS;
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use core::error::Error;
+use core::fmt::{Display, Formatter};
+use hashbrown::HashMap;
+
+/// Database to map NID to the name of the symbol.
+///
+/// The database is a plain text file where each line is a NID followed by the name, separated by
+/// whitespaces. Empty lines and lines start with `#` are ignored.
+pub struct NidDb {
+    names: HashMap<String, String>,
+}
+
+impl NidDb {
+    /// Returns the database that shipped with the kernel.
+    pub fn builtin() -> Self {
+        Self::parse(include_str!("nids.txt")).unwrap()
+    }
+
+    pub fn parse(text: &str) -> Result<Self, NidDbError> {
+        let mut names = HashMap::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // Parse the line.
+            let n = i + 1;
+            let (nid, name) = line
+                .split_once(char::is_whitespace)
+                .ok_or(NidDbError::NoName(n))?;
+            let name = name.trim();
+
+            if nid.len() != 11 || !nid.bytes().all(|b| NID_CHARS.contains(&b)) {
+                return Err(NidDbError::InvalidNid(n));
+            } else if name.is_empty() {
+                return Err(NidDbError::NoName(n));
+            }
+
+            if names.insert(nid.to_owned(), name.to_owned()).is_some() {
+                return Err(NidDbError::DuplicatedNid(n));
+            }
+        }
+
+        Ok(Self { names })
+    }
+
+    /// Returns [`None`] if `nid` is not in the database.
+    pub fn name(&self, nid: &str) -> Option<&str> {
+        self.names.get(nid).map(|v| v.as_str())
+    }
+
+    /// Returns an object to display `sym` in a human-readable form. `sym` can be either a plain
+    /// NID or in the form of `NID#LIB#MOD`.
+    pub fn symbol<'a>(&'a self, sym: &'a str) -> SymbolName<'a> {
+        let nid = sym.split('#').next().unwrap();
+
+        SymbolName {
+            sym,
+            name: self.name(nid),
+        }
+    }
+}
+
+/// Implementation of [`Display`] for the symbol name from [`NidDb::symbol()`].
+pub struct SymbolName<'a> {
+    sym: &'a str,
+    name: Option<&'a str>,
+}
+
+impl Display for SymbolName<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self.name {
+            Some(n) => write!(f, "{} ({})", n, self.sym),
+            None => f.write_str(self.sym),
+        }
+    }
+}
+
+/// Represents an error when [`NidDb::parse()`] fails.
+#[derive(Debug)]
+pub enum NidDbError {
+    InvalidNid(usize),
+    NoName(usize),
+    DuplicatedNid(usize),
+}
+
+impl Error for NidDbError {}
+
+impl Display for NidDbError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidNid(l) => write!(f, "invalid NID at line {l}"),
+            Self::NoName(l) => write!(f, "no symbol name at line {l}"),
+            Self::DuplicatedNid(l) => write!(f, "duplicated NID at line {l}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtld::get_nid;
+    use alloc::string::ToString;
+
+    #[test]
+    fn builtin() {
+        let db = NidDb::builtin();
+
+        for (nid, name) in &db.names {
+            assert_eq!(get_nid(name), *nid);
+        }
+
+        assert_eq!(
+            db.symbol("hcuQgD53UxM#A#B").to_string(),
+            "printf (hcuQgD53UxM#A#B)"
+        );
+        assert_eq!(db.symbol("AAAAAAAAAAA").to_string(), "AAAAAAAAAAA");
+    }
+
+    #[test]
+    fn parse() {
+        assert!(NidDb::parse("# comment\n\nhcuQgD53UxM printf\n").is_ok());
+        assert!(matches!(
+            NidDb::parse("hcuQgD53UxM"),
+            Err(NidDbError::NoName(1))
+        ));
+        assert!(matches!(
+            NidDb::parse("hcuQ printf"),
+            Err(NidDbError::InvalidNid(1))
+        ));
+        assert!(matches!(
+            NidDb::parse("hcuQgD53UxM printf\nhcuQgD53UxM printf"),
+            Err(NidDbError::DuplicatedNid(2))
+        ));
+    }
+}
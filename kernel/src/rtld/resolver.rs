@@ -0,0 +1,431 @@
+use super::{Dynlib, Module};
+use crate::imgfmt::elf::Symbol;
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use krt::info;
+use macros::bitflag;
+
+/// An object to resolve a symbol from the loaded modules.
+pub struct SymbolResolver<'a> {
+    dynlib: &'a Dynlib,
+    new_algorithm: bool,
+}
+
+impl<'a> SymbolResolver<'a> {
+    pub fn new(dynlib: &'a Dynlib, new_algorithm: bool) -> Self {
+        Self {
+            dynlib,
+            new_algorithm,
+        }
+    }
+
+    /// See `find_symdef` on the PS4 for a reference.
+    pub fn resolve_with_local(
+        &self,
+        md: &Arc<Module>,
+        index: usize,
+        mut flags: ResolveFlags,
+    ) -> Option<(Arc<Module>, usize)> {
+        // Check if symbol index is valid.
+        let sym = md.symbol(index)?;
+        let info = md.info()?;
+
+        if index >= info.chains().len() {
+            return None;
+        }
+
+        // Get symbol information.
+        let (name, decoded_name, symmod, symlib, hash) = if self.new_algorithm {
+            let name = sym.name();
+            let mut p = name.split('#').skip(1);
+            let l = p
+                .next()
+                .and_then(Self::decode_id)
+                .and_then(|v| md.libraries().iter().find(|&i| i.id() == v))
+                .map(|i| i.name());
+            let m = p
+                .next()
+                .and_then(Self::decode_id)
+                .and_then(|v| md.modules().iter().find(|&i| i.id() == v))
+                .map(|i| i.name());
+
+            (Some(name), None, m, l, Self::hash(Some(name), l, m))
+        } else {
+            // The only different with the new algorithm is all components in the name must be valid
+            // otherwise fallback to the original name. The new algorithm will relax this rule.
+            let name = match Self::decode_legacy(md, sym.name()) {
+                Some(v) => Cow::Owned(v),
+                None => Cow::Borrowed(sym.name()),
+            };
+
+            // This is identical to hash() except it does not stop on # in the module name.
+            let mut hash = 0u64;
+
+            for b in name.bytes() {
+                let t = u64::from(b) + (hash << 4);
+
+                hash = t & 0xf0000000;
+                hash = ((hash >> 24) ^ t) & !hash;
+            }
+
+            flags |= ResolveFlags::UNK2;
+
+            (None, Some(name), None, None, hash)
+        };
+
+        // Return this symbol if the binding is local. The reason we don't check this in the
+        // first place is because we want to maintain the same behavior as the PS4.
+        if sym.binding() == Symbol::STB_LOCAL {
+            return Some((md.clone(), index));
+        } else if sym.ty() == Symbol::STT_SECTION {
+            return None;
+        }
+
+        // Lookup from global list if the symbol is not local.
+        let name = LookupName {
+            name,
+            decoded_name: decoded_name.as_deref(),
+            symmod,
+            symlib,
+            hash,
+        };
+
+        if let Some(v) = self.resolve_from_global(&name, flags) {
+            return Some(v);
+        } else if sym.binding() == Symbol::STB_WEAK {
+            // The PS4 return sym_zero from the main executable, which is identical to its null
+            // symbol.
+            return Some((self.dynlib.app().clone(), 0));
+        }
+
+        info!(
+            "Couldn't resolve {} required by {}.",
+            self.dynlib.nids().symbol(sym.name()),
+            md.path()
+        );
+
+        None
+    }
+
+    /// See `symlook_global` on the PS4 for a reference.
+    pub fn resolve_from_global(
+        &self,
+        name: &LookupName,
+        flags: ResolveFlags,
+    ) -> Option<(Arc<Module>, usize)> {
+        // Resolve from list_main.
+        let mut result = self.resolve_from_list(name, flags, self.dynlib.mains());
+
+        // Resolve from list_global. TODO: Use the DAG of each module once init_dag is fully
+        // implemented.
+        for md in self.dynlib.globals() {
+            if let Some((md, sym)) = &result {
+                if md.symbol(*sym).unwrap().binding() != Symbol::STB_WEAK {
+                    break;
+                }
+            }
+
+            if let Some((md, sym)) = self.resolve_from_list(name, flags, [md]) {
+                if result.is_none() || md.symbol(sym).unwrap().binding() != Symbol::STB_WEAK {
+                    result = Some((md, sym));
+                }
+            }
+        }
+
+        result
+    }
+
+    /// See `symlook_list` on the PS4 for a reference.
+    pub fn resolve_from_list<'b>(
+        &self,
+        name: &LookupName,
+        flags: ResolveFlags,
+        list: impl IntoIterator<Item = &'b Arc<Module>>,
+    ) -> Option<(Arc<Module>, usize)> {
+        // Get module name.
+        let symmod = if !flags.has(ResolveFlags::UNK2) {
+            name.symmod
+        } else if let Some(v) = name.decoded_name {
+            v.rfind('#').map(|i| &v[(i + 1)..])
+        } else {
+            None
+        };
+
+        let mut result = None;
+
+        for md in list {
+            // TODO: Implement DoneList.
+            if let Some(name) = symmod {
+                if !md.modules().iter().any(|i| i.id() == 0 && i.name() == name) {
+                    continue;
+                }
+            }
+
+            // Lookup from the module.
+            let (md, index) = match self.resolve_from_module(name, symmod, flags, md) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            // Return the symbol if it is not a weak binding.
+            if md.symbol(index).unwrap().binding() != Symbol::STB_WEAK {
+                return Some((md, index));
+            } else if result.is_none() {
+                // Use the first weak, not the last weak; if no non-weak.
+                result = Some((md, index));
+            }
+        }
+
+        result
+    }
+
+    /// See `symlook_obj` on the PS4 for a reference.
+    fn resolve_from_module(
+        &self,
+        name: &LookupName,
+        symmod: Option<&str>,
+        flags: ResolveFlags,
+        md: &Arc<Module>,
+    ) -> Option<(Arc<Module>, usize)> {
+        let info = md.info()?;
+        let buckets = info.buckets();
+
+        if !flags.has(ResolveFlags::UNK2) {
+            let mut index = buckets[name.hash as usize % buckets.len()] as usize;
+
+            while index != 0 {
+                let sym = md.symbol(index)?;
+
+                if Self::is_match(name.name, symmod, name.symlib, sym, flags, md) {
+                    // TODO: Implement the remaining symlook_obj.
+                    return Some((md.clone(), index));
+                }
+
+                index = *info.chains().get(index)? as usize;
+            }
+        } else if let Some(target) = name.decoded_name {
+            let mut index = buckets[(name.hash & 0xffffffff) as usize % buckets.len()] as usize;
+            let target = if target.contains('#') {
+                Cow::Borrowed(target)
+            } else if let Some(v) = Self::decode_legacy(md, target) {
+                Cow::Owned(v)
+            } else {
+                Cow::Borrowed(target)
+            };
+
+            while index != 0 {
+                let sym = md.symbol(index)?;
+
+                if Self::is_defined(sym, flags) {
+                    let name = match Self::decode_legacy(md, sym.name()) {
+                        Some(v) => Cow::Owned(v),
+                        None => Cow::Borrowed(sym.name()),
+                    };
+
+                    if name == target {
+                        // TODO: Implement the remaining symlook_obj.
+                        return Some((md.clone(), index));
+                    }
+                }
+
+                index = *info.chains().get(index)? as usize;
+            }
+        }
+
+        None
+    }
+
+    /// Returns the hash of `name#libname#modname` that used as a key of `DT_SCE_HASH`.
+    pub fn hash(name: Option<&str>, libname: Option<&str>, modname: Option<&str>) -> u64 {
+        let mut h: u64 = 0;
+        let mut c = |b: u8| {
+            let t = u64::from(b) + (h << 4);
+
+            h = t & 0xf0000000;
+            h = ((h >> 24) ^ t) & !h;
+        };
+
+        // Hash symbol name.
+        let mut sep = false;
+
+        if let Some(v) = name {
+            sep = true;
+
+            for b in v.bytes() {
+                c(b);
+
+                if b == b'#' {
+                    sep = false;
+                    break;
+                }
+            }
+        }
+
+        // Hash library name.
+        let v = match libname {
+            Some(v) => v,
+            None => return h,
+        };
+
+        if sep {
+            c(b'#');
+        }
+
+        sep = true;
+
+        for b in v.bytes() {
+            c(b);
+
+            if b == b'#' {
+                sep = false;
+                break;
+            }
+        }
+
+        // Hash module name.
+        let v = match modname {
+            Some(v) => v,
+            None => return h,
+        };
+
+        if sep {
+            c(b'#');
+        }
+
+        for b in v.bytes() {
+            c(b);
+
+            if b == b'#' {
+                break;
+            }
+        }
+
+        h
+    }
+
+    fn is_defined(sym: &Symbol, flags: ResolveFlags) -> bool {
+        let ty = sym.ty();
+
+        match ty {
+            Symbol::STT_NOTYPE | Symbol::STT_OBJECT | Symbol::STT_FUNC | Symbol::STT_ENTRY => {
+                if sym.value() == 0 {
+                    return false;
+                }
+            }
+            Symbol::STT_TLS => {}
+            _ => return false,
+        }
+
+        sym.shndx() != 0 || (ty == Symbol::STT_FUNC && !flags.has(ResolveFlags::UNK3))
+    }
+
+    fn is_match(
+        name: Option<&str>,
+        symmod: Option<&str>,
+        symlib: Option<&str>,
+        sym: &Symbol,
+        flags: ResolveFlags,
+        md: &Module,
+    ) -> bool {
+        // Check type.
+        if !Self::is_defined(sym, flags) {
+            return false;
+        }
+
+        // Do nothing if no target.
+        let name = match name {
+            Some(v) => v,
+            None => return false,
+        };
+
+        // TODO: This logic is not exactly matched with the PS4. The reason is because it is too
+        // complicated to mimic the same behavior. Our implementation here is a "best" guess on what
+        // the PS4 is actually doing.
+        let mut parts = sym.name().split('#').skip(1);
+        let li = parts
+            .next()
+            .and_then(Self::decode_id)
+            .and_then(|v| md.libraries().iter().find(|&i| i.id() == v));
+        let mi = parts
+            .next()
+            .and_then(Self::decode_id)
+            .and_then(|v| md.modules().iter().find(|&i| i.id() == v));
+        let mut b = sym.name().bytes();
+
+        for a in name.bytes() {
+            if b.next() != Some(a) {
+                return false;
+            }
+
+            if a == b'#' {
+                break;
+            }
+        }
+
+        // Compare library name and module name.
+        match (symlib, li) {
+            (Some(n), Some(i)) if i.name() == n => {}
+            _ => return false,
+        }
+
+        matches!((symmod, mi), (Some(n), Some(i)) if i.name() == n)
+    }
+
+    /// See `convert_mangled_name_to_long` on the PS4 for a reference.
+    fn decode_legacy(md: &Module, name: &str) -> Option<String> {
+        // Split the name.
+        let mut p = name.splitn(3, '#');
+        let n = p.next()?;
+        let l = p.next()?;
+        let m = p.next()?;
+
+        if l.len() > 3 || m.len() > 3 {
+            return None;
+        }
+
+        // Decode library ID and module ID.
+        let l = Self::decode_id(l)?;
+        let m = Self::decode_id(m)?;
+
+        // Get library name and module name.
+        let l = md.libraries().iter().find(|&i| i.id() == l)?;
+        let m = md.modules().iter().find(|&i| i.id() == m)?;
+
+        Some(format!("{}#{}#{}", n, l.name(), m.name()))
+    }
+
+    fn decode_id(v: &str) -> Option<u16> {
+        let mut r = 0u64;
+
+        for c in v.bytes() {
+            r <<= 6;
+            r |= NID_CHARS.iter().position(|&v| v == c)? as u64;
+        }
+
+        Some(r as u16)
+    }
+}
+
+/// Name of the symbol to lookup.
+pub struct LookupName<'a> {
+    pub name: Option<&'a str>,
+    pub decoded_name: Option<&'a str>,
+    pub symmod: Option<&'a str>,
+    pub symlib: Option<&'a str>,
+    pub hash: u64,
+}
+
+/// Characters that used to encode NID and the ID of library and module.
+pub const NID_CHARS: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+-";
+
+/// Flags to control behavior of [`SymbolResolver`].
+#[bitflag(u32)]
+pub enum ResolveFlags {
+    UNK1 = 0x00000001,
+    UNK3 = 0x00000002,
+    UNK4 = 0x00000008,
+    UNK2 = 0x00000100,
+}
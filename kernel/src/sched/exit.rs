@@ -0,0 +1,16 @@
+use super::cpu_idle;
+
+/// Terminate the current thread. This function never return.
+///
+/// The caller is responsible for releasing all resources that owned by the thread before calling
+/// this function since it will not unwind the stack.
+///
+/// We don't have a run queue yet so there is no other thread to switch to. What we can do for now
+/// is leave the CPU idle.
+///
+/// See `thread_exit` and `sched_throw` on the PS4 for a reference.
+pub fn thread_exit() -> ! {
+    loop {
+        cpu_idle();
+    }
+}
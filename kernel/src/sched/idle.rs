@@ -0,0 +1,14 @@
+/// Put the current CPU into a low power state until the next interrupt.
+///
+/// See `cpu_idle` on the PS4 for a reference.
+pub fn cpu_idle() {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        core::arch::asm!("hlt", options(nomem, nostack))
+    };
+
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        core::arch::asm!("wfi", options(nomem, nostack))
+    };
+}
@@ -1,3 +1,7 @@
-pub use self::sleep::*;
+pub use self::exit::*;
+pub use self::idle::*;
 
+mod exit;
+mod idle;
+#[allow(dead_code)] // TODO: Use this in the swapper once we have a scheduler.
 mod sleep;
@@ -0,0 +1,238 @@
+use super::{DefaultAction, Signal, SignalSet, SIGCHLD, SIGCONT};
+use macros::bitflag;
+
+/// Default action of the signal (AKA `SIG_DFL`).
+pub const SIG_DFL: usize = 0;
+
+/// Ignore the signal (AKA `SIG_IGN`).
+pub const SIG_IGN: usize = 1;
+
+/// Implementation of `sigaction` structure.
+#[derive(Clone, Copy)]
+pub struct SignalAct {
+    pub handler: usize,     // sa_handler
+    pub flags: SignalFlags, // sa_flags
+    pub mask: SignalSet,    // sa_mask
+}
+
+impl SignalAct {
+    /// Size of `sigaction` structure in the user space.
+    pub const SIZE: usize = 32;
+
+    pub fn from_bytes(v: [u8; Self::SIZE]) -> Self {
+        Self {
+            handler: usize::from_le_bytes(v[..8].try_into().unwrap()),
+            flags: u32::from_le_bytes(v[8..12].try_into().unwrap()).into(),
+            mask: SignalSet::from_bytes(v[12..28].try_into().unwrap()),
+        }
+    }
+
+    pub fn to_bytes(self) -> [u8; Self::SIZE] {
+        let mut v = [0; Self::SIZE];
+
+        v[..8].copy_from_slice(&self.handler.to_le_bytes());
+        v[8..12].copy_from_slice(&self.flags.into_bits().to_le_bytes());
+        v[12..28].copy_from_slice(&self.mask.to_bytes());
+
+        v
+    }
+}
+
+impl Default for SignalAct {
+    fn default() -> Self {
+        Self {
+            handler: SIG_DFL,
+            flags: SignalFlags::zeroed(),
+            mask: SignalSet::default(),
+        }
+    }
+}
+
+/// Flags of [`SignalAct`].
+#[bitflag(u32)]
+pub enum SignalFlags {
+    /// Take signal on signal stack.
+    SA_ONSTACK = 0x0001,
+    /// Restart system call on signal return.
+    SA_RESTART = 0x0002,
+    /// Reset to [`SIG_DFL`] when taking signal.
+    SA_RESETHAND = 0x0004,
+    /// Do not generate `SIGCHLD` on child stop.
+    SA_NOCLDSTOP = 0x0008,
+    /// Do not mask the signal being delivered.
+    SA_NODEFER = 0x0010,
+    /// Do not create zombies on child exit.
+    SA_NOCLDWAIT = 0x0020,
+    /// Deliver the signal with `siginfo_t`.
+    SA_SIGINFO = 0x0040,
+}
+
+impl SignalFlags {
+    pub fn into_bits(self) -> u32 {
+        self.0
+    }
+}
+
+/// Implementation of `sigacts` structure.
+///
+/// We store [`SignalAct`] as-is instead of splitting it into multiple sets like the PS4.
+#[derive(Clone)]
+pub struct SigActs {
+    actions: [SignalAct; Signal::MAX as usize], // ps_sigact + ps_catchmask + ps_sigignore + ...
+    flags: SigActsFlags,                        // ps_flag
+}
+
+impl SigActs {
+    pub fn get(&self, sig: Signal) -> SignalAct {
+        self.actions[Self::index(sig)]
+    }
+
+    /// The caller is responsible to make sure `act` can be set to `sig` and discard the pending
+    /// signal if it become ignored.
+    ///
+    /// See `kern_sigaction` on the PS4 for a reference.
+    pub fn set(&mut self, sig: Signal, mut act: SignalAct, init: bool) {
+        act.mask.remove_uncatchable();
+
+        if sig == SIGCHLD {
+            self.set_flag(
+                SigActsFlags::PS_NOCLDSTOP,
+                act.flags.has(SignalFlags::SA_NOCLDSTOP),
+            );
+
+            // The init never get zombies.
+            self.set_flag(
+                SigActsFlags::PS_NOCLDWAIT,
+                !init && act.flags.has(SignalFlags::SA_NOCLDWAIT),
+            );
+
+            self.set_flag(SigActsFlags::PS_CLDSIGIGN, act.handler == SIG_IGN);
+        }
+
+        self.actions[Self::index(sig)] = act;
+    }
+
+    /// Reset the action of `sig` to [`SIG_DFL`] as requested by [`SignalFlags::SA_RESETHAND`].
+    pub fn reset(&mut self, sig: Signal) {
+        let act = &mut self.actions[Self::index(sig)];
+
+        act.handler = SIG_DFL;
+        act.flags.remove(SignalFlags::SA_SIGINFO);
+        act.flags.remove(SignalFlags::SA_RESETHAND);
+    }
+
+    /// Returns `true` if `sig` will be discarded without any effects when it is sent.
+    pub fn is_ignored(&self, sig: Signal) -> bool {
+        // SIGCONT always need to continue the process even if it is ignored.
+        if sig == SIGCONT {
+            return false;
+        }
+
+        match self.get(sig).handler {
+            SIG_IGN => true,
+            SIG_DFL => sig.default_action() == DefaultAction::Ignore,
+            _ => false,
+        }
+    }
+
+    pub fn flags(&self) -> SigActsFlags {
+        self.flags
+    }
+
+    fn set_flag(&mut self, flag: SigActsFlags, v: bool) {
+        if v {
+            self.flags |= flag;
+        } else {
+            self.flags.remove(flag);
+        }
+    }
+
+    fn index(sig: Signal) -> usize {
+        usize::from(sig.into_bits() - 1)
+    }
+}
+
+impl Default for SigActs {
+    fn default() -> Self {
+        Self {
+            actions: [SignalAct::default(); Signal::MAX as usize],
+            flags: SigActsFlags::zeroed(),
+        }
+    }
+}
+
+/// Flags of [`SigActs`].
+#[bitflag(u32)]
+pub enum SigActsFlags {
+    /// No zombies if child dies.
+    PS_NOCLDWAIT = 0x0001,
+    /// No `SIGCHLD` when children stop.
+    PS_NOCLDSTOP = 0x0002,
+    /// The `SIGCHLD` handler is [`SIG_IGN`].
+    PS_CLDSIGIGN = 0x0004,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signal::{SIGCONT, SIGHUP, SIGURG, SIGUSR1};
+
+    #[test]
+    fn act() {
+        let mut mask = SignalSet::default();
+
+        mask.add(SIGUSR1);
+
+        let act = SignalAct {
+            handler: 0x1234,
+            flags: SignalFlags::SA_SIGINFO | SignalFlags::SA_RESETHAND,
+            mask,
+        };
+
+        let v = act.to_bytes();
+        let act = SignalAct::from_bytes(v);
+
+        assert_eq!(v[..8], 0x1234usize.to_le_bytes());
+        assert_eq!(v[8..12], 0x44u32.to_le_bytes());
+        assert_eq!(act.handler, 0x1234);
+        assert_eq!(act.mask, mask);
+
+        // Test SA_RESETHAND.
+        let mut acts = SigActs::default();
+
+        assert!(!acts.is_ignored(SIGHUP));
+        assert!(acts.is_ignored(SIGCHLD));
+        assert!(!acts.is_ignored(SIGCONT));
+
+        acts.set(SIGHUP, act, false);
+        acts.set(SIGURG, act, false);
+
+        assert!(!acts.is_ignored(SIGHUP));
+        assert!(!acts.is_ignored(SIGURG));
+
+        acts.reset(SIGHUP);
+        acts.reset(SIGURG);
+
+        assert_eq!(acts.get(SIGHUP).handler, SIG_DFL);
+        assert_eq!(acts.get(SIGURG).handler, SIG_DFL);
+        assert!(acts.is_ignored(SIGURG));
+        assert!(!acts.get(SIGHUP).flags.has(SignalFlags::SA_RESETHAND));
+
+        // Test SIGCHLD.
+        let act = SignalAct {
+            handler: SIG_IGN,
+            flags: SignalFlags::SA_NOCLDWAIT,
+            mask: SignalSet::default(),
+        };
+
+        acts.set(SIGCHLD, act, false);
+
+        assert!(acts
+            .flags()
+            .has_all(SigActsFlags::PS_NOCLDWAIT | SigActsFlags::PS_CLDSIGIGN));
+
+        acts.set(SIGCHLD, act, true);
+
+        assert!(!acts.flags().has(SigActsFlags::PS_NOCLDWAIT));
+    }
+}
@@ -1,10 +1,38 @@
+pub use self::act::*;
+pub use self::set::*;
+
+use crate::context::current_procmgr;
+use crate::errno::{EINVAL, ESRCH};
+use crate::proc::{Pid, Proc, ProcState, Thread};
+use crate::sched::thread_exit;
+use crate::syscalls::{SysErr, SysIn, SysOut, Syscalls};
+use crate::trap::TrapFrame;
+use crate::ucred::PrivilegeError;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use krt::info;
+
+mod act;
+mod set;
+
 /// Value of *nix signal.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Signal(u8);
 
 impl Signal {
     const MAX: u8 = 128; // _SIG_MAXSIG
 
+    /// Returns [`None`] if `v` is not a valid signal number.
+    pub const fn new(v: c_int) -> Option<Self> {
+        if v > 0 && v <= Self::MAX as c_int {
+            Some(Self(v as u8))
+        } else {
+            None
+        }
+    }
+
     /// # Panics
     /// If `v` is not a valid signal number.
     pub const fn from_bits(v: u8) -> Self {
@@ -15,4 +43,447 @@ impl Signal {
     pub const fn into_bits(self) -> u8 {
         self.0
     }
+
+    pub fn get(self) -> c_int {
+        self.0.into()
+    }
+
+    pub fn register_syscalls(sys: &mut Syscalls) {
+        sys.register(37, Self::sys_kill);
+        sys.register(340, Self::sys_sigprocmask);
+        sys.register(416, Self::sys_sigaction);
+    }
+
+    /// See `sys_kill` on the PS4 for a reference.
+    fn sys_kill(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let pid: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let sig: c_int = i.args[1].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let pmgr = current_procmgr().unwrap();
+
+        // Zero signal is used to check if the target exists.
+        let sig = match sig {
+            0 => None,
+            v => Some(Self::new(v).ok_or(SysErr::Raw(EINVAL))?),
+        };
+
+        // Check if the target is a single process.
+        if pid > 0 {
+            let p = Pid::new(pid)
+                .and_then(|v| pmgr.find(v))
+                .ok_or(SysErr::Raw(ESRCH))?;
+
+            can_signal(td, &p, sig)?;
+
+            if let Some(sig) = sig {
+                send(&p, sig);
+            }
+
+            return Ok(SysOut::ZERO);
+        }
+
+        // Get the target group. None mean all processes.
+        let group = match pid {
+            0 => Some(td.proc().group_mut().as_ref().unwrap().id()),
+            -1 => None,
+            v => Some(
+                v.checked_neg()
+                    .and_then(Pid::new)
+                    .ok_or(SysErr::Raw(ESRCH))?,
+            ),
+        };
+
+        // Send the signal. See killpg1 on the PS4 for a reference.
+        let procs: Vec<Arc<Proc>> = pmgr.list().by_ref().filter_map(|p| p.upgrade()).collect();
+        let mut r = Err(SysErr::Raw(ESRCH));
+
+        for p in procs {
+            if p.id() == Pid::KERNEL || p.id() == Pid::INIT {
+                continue;
+            }
+
+            match group {
+                Some(g) => {
+                    if p.group_mut().as_ref().is_none_or(|v| v.id() != g) {
+                        continue;
+                    }
+                }
+                None => {
+                    if Arc::ptr_eq(&p, td.proc()) {
+                        continue;
+                    }
+                }
+            }
+
+            match can_signal(td, &p, sig) {
+                Ok(_) => {
+                    if let Some(sig) = sig {
+                        send(&p, sig);
+                    }
+
+                    r = Ok(SysOut::ZERO);
+                }
+                Err(e) => {
+                    if r.is_err() {
+                        r = Err(e.into());
+                    }
+                }
+            }
+        }
+
+        r
+    }
+
+    /// See `sys_sigprocmask` on the PS4 for a reference.
+    fn sys_sigprocmask(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let how: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let set: usize = i.args[1].into();
+        let oset: usize = i.args[2].into();
+        let vm = td.proc().vm();
+
+        // Read the new mask.
+        let set = if set == 0 {
+            None
+        } else {
+            let mut buf = [0; SignalSet::SIZE];
+
+            vm.read(set, &mut buf)?;
+
+            Some(SignalSet::from_bytes(buf))
+        };
+
+        // Update the mask. The pending signals that was unblocked will be delivered when returning
+        // to the user space.
+        let mut mask = td.sigmask_mut();
+        let old = *mask;
+
+        if let Some(mut set) = set {
+            set.remove_uncatchable();
+
+            match how {
+                SIG_BLOCK => *mask |= set,
+                SIG_UNBLOCK => *mask &= !set,
+                SIG_SETMASK => *mask = set,
+                _ => return Err(SysErr::Raw(EINVAL)),
+            }
+        }
+
+        drop(mask);
+
+        if oset != 0 {
+            vm.write(oset, &old.to_bytes())?;
+        }
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_sigaction` on the PS4 for a reference.
+    fn sys_sigaction(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let sig: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let sig = Self::new(sig).ok_or(SysErr::Raw(EINVAL))?;
+        let act: usize = i.args[1].into();
+        let oact: usize = i.args[2].into();
+        let p = td.proc();
+        let vm = p.vm();
+
+        // Read the new action.
+        let act = if act == 0 {
+            None
+        } else {
+            let mut buf = [0; SignalAct::SIZE];
+
+            vm.read(act, &mut buf)?;
+
+            Some(SignalAct::from_bytes(buf))
+        };
+
+        // SIGKILL and SIGSTOP cannot be caught or ignored.
+        if act.is_some_and(|a| (sig == SIGKILL || sig == SIGSTOP) && a.handler != SIG_DFL) {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        // Update the action.
+        let mut acts = p.sigacts_mut();
+        let old = acts.get(sig);
+
+        if let Some(act) = act {
+            acts.set(sig, act, p.id() == Pid::INIT);
+
+            // Discard the pending signal if it is now ignored. TODO: Discard the signal from the
+            // other threads once the process can have more than one thread.
+            if acts.is_ignored(sig) {
+                p.siglist_mut().remove(sig);
+                td.siglist_mut().remove(sig);
+            }
+        }
+
+        drop(acts);
+
+        if oact != 0 {
+            vm.write(oact, &old.to_bytes())?;
+        }
+
+        Ok(SysOut::ZERO)
+    }
+}
+
+impl Display for Signal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        // This function is generated inside the macro `signals!`.
+        self.name(f)
+    }
+}
+
+macro_rules! signals {
+    ($($name:ident($num:literal) => $act:ident,)*) => {
+        $(
+            #[allow(dead_code)]
+            pub const $name: Signal = Signal($num);
+        )*
+
+        impl Signal {
+            /// See `sigprop` on the PS4 for a reference.
+            pub fn default_action(self) -> DefaultAction {
+                match self.0 {
+                    $( $num => DefaultAction::$act, )*
+                    _ => DefaultAction::Terminate,
+                }
+            }
+
+            fn name(self, f: &mut Formatter<'_>) -> core::fmt::Result {
+                match self.0 {
+                    $( $num => f.write_str(stringify!($name)), )*
+                    v => write!(f, "{v}"),
+                }
+            }
+        }
+    };
+}
+
+// List of PS4 signals. The value must be the same as PS4 kernel.
+signals! {
+    SIGHUP(1) => Terminate,
+    SIGINT(2) => Terminate,
+    SIGQUIT(3) => Dump,
+    SIGILL(4) => Dump,
+    SIGTRAP(5) => Dump,
+    SIGABRT(6) => Dump,
+    SIGEMT(7) => Dump,
+    SIGFPE(8) => Dump,
+    SIGKILL(9) => Terminate,
+    SIGBUS(10) => Dump,
+    SIGSEGV(11) => Dump,
+    SIGSYS(12) => Dump,
+    SIGPIPE(13) => Terminate,
+    SIGALRM(14) => Terminate,
+    SIGTERM(15) => Terminate,
+    SIGURG(16) => Ignore,
+    SIGSTOP(17) => Stop,
+    SIGTSTP(18) => Stop,
+    SIGCONT(19) => Continue,
+    SIGCHLD(20) => Ignore,
+    SIGTTIN(21) => Stop,
+    SIGTTOU(22) => Stop,
+    SIGIO(23) => Ignore,
+    SIGXCPU(24) => Dump,
+    SIGXFSZ(25) => Dump,
+    SIGVTALRM(26) => Terminate,
+    SIGPROF(27) => Terminate,
+    SIGWINCH(28) => Ignore,
+    SIGINFO(29) => Ignore,
+    SIGUSR1(30) => Terminate,
+    SIGUSR2(31) => Terminate,
+    SIGTHR(32) => Terminate,
+    SIGNONE(128) => Terminate,
+}
+
+/// Action to take when the signal with [`SIG_DFL`] is delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefaultAction {
+    /// `SA_KILL`.
+    Terminate,
+    /// `SA_KILL` + `SA_CORE`.
+    Dump,
+    /// `SA_STOP`.
+    Stop,
+    /// `SA_IGNORE`.
+    Ignore,
+    /// `SA_IGNORE` + `SA_CONT`.
+    Continue,
+}
+
+/// Block the signals in the set.
+const SIG_BLOCK: c_int = 1;
+
+/// Unblock the signals in the set.
+const SIG_UNBLOCK: c_int = 2;
+
+/// Replace the mask with the set.
+const SIG_SETMASK: c_int = 3;
+
+/// Send `sig` to `p`. The signal will be delivered when one of the thread in `p` is returning to
+/// the user space.
+///
+/// See `kern_psignal` on the PS4 for a reference.
+pub fn send(p: &Proc, sig: Signal) {
+    if *p.state_mut() != ProcState::Normal {
+        return;
+    }
+
+    // Stop signals and SIGCONT cancel each other.
+    let acts = p.sigacts_mut();
+    let mut pending = p.siglist_mut();
+
+    match sig.default_action() {
+        DefaultAction::Stop => pending.remove(SIGCONT),
+        DefaultAction::Continue => {
+            for s in [SIGSTOP, SIGTSTP, SIGTTIN, SIGTTOU] {
+                pending.remove(s);
+            }
+        }
+        _ => {}
+    }
+
+    if acts.is_ignored(sig) {
+        return;
+    }
+
+    // TODO: Continue the process if it was stopped and wakeup the thread that can handle the
+    // signal once the sleep queue is implemented.
+    pending.add(sig);
+}
+
+/// Send `sig` that was caused by a trap to `td` and deliver it immediately.
+///
+/// See `trapsignal` on the PS4 for a reference.
+pub fn trap(td: &Thread, frame: &mut TrapFrame, sig: Signal) {
+    let p = td.proc();
+    let mut acts = p.sigacts_mut();
+    let mut mask = td.sigmask_mut();
+
+    // The signal cannot be blocked or ignored otherwise the thread will trap again.
+    if mask.contains(sig) || acts.get(sig).handler == SIG_IGN {
+        acts.set(sig, SignalAct::default(), p.id() == Pid::INIT);
+        mask.remove(sig);
+    }
+
+    td.siglist_mut().add(sig);
+
+    drop(mask);
+    drop(acts);
+
+    deliver(td, frame);
+}
+
+/// Deliver all pending signals that are not blocked by `td`. This must be called right before
+/// returning to the user space.
+///
+/// See `ast` and `postsig` on the PS4 for a reference.
+pub fn deliver(td: &Thread, frame: &mut TrapFrame) {
+    let p = td.proc();
+
+    loop {
+        // Dequeue the signal. The signal that was sent to the thread take priority.
+        let (sig, act, mask) = {
+            let mut acts = p.sigacts_mut();
+            let mut ppending = p.siglist_mut();
+            let mut tpending = td.siglist_mut();
+            let mut mask = td.sigmask_mut();
+            let sig = match ((*tpending | *ppending) & !*mask).first() {
+                Some(v) => v,
+                None => break,
+            };
+
+            if tpending.contains(sig) {
+                tpending.remove(sig);
+            } else {
+                ppending.remove(sig);
+            }
+
+            // Block the signals while the handler is running. The mask will be restored by
+            // sigreturn.
+            let act = acts.get(sig);
+
+            match act.handler {
+                SIG_DFL | SIG_IGN => (sig, act, *mask),
+                _ => {
+                    let old = *mask;
+
+                    *mask |= act.mask;
+
+                    if !act.flags.has(SignalFlags::SA_NODEFER) {
+                        mask.add(sig);
+                    }
+
+                    if act.flags.has(SignalFlags::SA_RESETHAND) {
+                        acts.reset(sig);
+                    }
+
+                    (sig, act, old)
+                }
+            }
+        };
+
+        // Take the action.
+        match act.handler {
+            SIG_DFL => match sig.default_action() {
+                DefaultAction::Terminate | DefaultAction::Dump => exit(td, sig),
+                DefaultAction::Stop => {
+                    // TODO: Suspend all threads in the process once we have a scheduler.
+                    info!(
+                        "Ignoring {sig} on process {} since stopping is not supported yet.",
+                        c_int::from(p.id())
+                    );
+
+                    continue;
+                }
+                DefaultAction::Ignore | DefaultAction::Continue => continue,
+            },
+            SIG_IGN => continue,
+            _ => {}
+        }
+
+        if let Err(e) = p.abi().send_signal(td, frame, sig, &act, mask) {
+            info!(
+                "Couldn't deliver {sig} to process {}: {e}.",
+                c_int::from(p.id())
+            );
+
+            exit(td, SIGILL);
+        }
+    }
+}
+
+/// Terminate the process of `td` with `sig`.
+///
+/// See `sigexit` on the PS4 for a reference.
+fn exit(td: &Thread, sig: Signal) -> ! {
+    let pmgr = current_procmgr().unwrap();
+
+    // TODO: Dump the core for DefaultAction::Dump.
+    pmgr.exit(td.proc(), sig.get());
+
+    thread_exit()
+}
+
+/// See `p_cansignal` on the PS4 for a reference.
+fn can_signal(td: &Thread, p: &Arc<Proc>, sig: Option<Signal>) -> Result<(), PrivilegeError> {
+    if Arc::ptr_eq(td.proc(), p) {
+        return Ok(());
+    }
+
+    // SIGCONT can always be sent to the process in the same session.
+    if sig == Some(SIGCONT) {
+        let s1 = td.proc().group_mut().as_ref().map(|g| g.session().clone());
+        let s2 = p.group_mut().as_ref().map(|g| g.session().clone());
+
+        if let (Some(s1), Some(s2)) = (s1, s2) {
+            if Arc::ptr_eq(&s1, &s2) {
+                return Ok(());
+            }
+        }
+    }
+
+    let cred = p.cred_mut().clone();
+
+    td.cred_mut().can_signal(&cred)
 }
@@ -0,0 +1,170 @@
+use super::Signal;
+use core::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
+
+/// Implementation of `sigset_t`.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SignalSet {
+    bits: [u32; 4],
+}
+
+impl SignalSet {
+    /// Size of `sigset_t` in the user space.
+    pub const SIZE: usize = size_of::<Self>();
+
+    pub fn from_bytes(v: [u8; Self::SIZE]) -> Self {
+        let mut bits = [0; 4];
+
+        for (i, b) in bits.iter_mut().enumerate() {
+            *b = u32::from_le_bytes(v[(i * 4)..(i * 4 + 4)].try_into().unwrap());
+        }
+
+        Self { bits }
+    }
+
+    pub fn to_bytes(self) -> [u8; Self::SIZE] {
+        let mut v = [0; Self::SIZE];
+
+        for (i, b) in self.bits.into_iter().enumerate() {
+            v[(i * 4)..(i * 4 + 4)].copy_from_slice(&b.to_le_bytes());
+        }
+
+        v
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|&v| v == 0)
+    }
+
+    /// See `SIGISMEMBER` on the PS4 for a reference.
+    pub fn contains(&self, sig: Signal) -> bool {
+        (self.bits[Self::word(sig)] & Self::bit(sig)) != 0
+    }
+
+    /// See `SIGADDSET` on the PS4 for a reference.
+    pub fn add(&mut self, sig: Signal) {
+        self.bits[Self::word(sig)] |= Self::bit(sig);
+    }
+
+    /// See `SIGDELSET` on the PS4 for a reference.
+    pub fn remove(&mut self, sig: Signal) {
+        self.bits[Self::word(sig)] &= !Self::bit(sig);
+    }
+
+    /// Returns the lowest signal in this set or [`None`] if this set is empty.
+    ///
+    /// See `sig_ffs` on the PS4 for a reference.
+    pub fn first(&self) -> Option<Signal> {
+        for (i, &v) in self.bits.iter().enumerate() {
+            if v != 0 {
+                let n = i * 32 + usize::try_from(v.trailing_zeros()).unwrap() + 1;
+
+                return Some(Signal::from_bits(n.try_into().unwrap()));
+            }
+        }
+
+        None
+    }
+
+    /// Remove the signals that cannot be caught or blocked (e.g. `SIGKILL`).
+    ///
+    /// See `SIG_CANTMASK` on the PS4 for a reference.
+    pub fn remove_uncatchable(&mut self) {
+        self.remove(super::SIGKILL);
+        self.remove(super::SIGSTOP);
+    }
+
+    /// See `_SIG_WORD` on the PS4 for a reference.
+    fn word(sig: Signal) -> usize {
+        usize::from(sig.into_bits() - 1) >> 5
+    }
+
+    /// See `_SIG_BIT` on the PS4 for a reference.
+    fn bit(sig: Signal) -> u32 {
+        1 << ((sig.into_bits() - 1) & 31)
+    }
+}
+
+impl BitOr for SignalSet {
+    type Output = Self;
+
+    fn bitor(mut self, rhs: Self) -> Self::Output {
+        self |= rhs;
+        self
+    }
+}
+
+impl BitOrAssign for SignalSet {
+    fn bitor_assign(&mut self, rhs: Self) {
+        for (l, r) in self.bits.iter_mut().zip(rhs.bits) {
+            *l |= r;
+        }
+    }
+}
+
+impl BitAnd for SignalSet {
+    type Output = Self;
+
+    fn bitand(mut self, rhs: Self) -> Self::Output {
+        self &= rhs;
+        self
+    }
+}
+
+impl BitAndAssign for SignalSet {
+    fn bitand_assign(&mut self, rhs: Self) {
+        for (l, r) in self.bits.iter_mut().zip(rhs.bits) {
+            *l &= r;
+        }
+    }
+}
+
+impl Not for SignalSet {
+    type Output = Self;
+
+    fn not(mut self) -> Self::Output {
+        for v in &mut self.bits {
+            *v = !*v;
+        }
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signal::{SIGCHLD, SIGHUP, SIGKILL, SIGNONE, SIGSTOP, SIGTHR};
+
+    #[test]
+    fn set() {
+        let mut set = SignalSet::default();
+
+        assert!(set.is_empty());
+        assert!(set.first().is_none());
+
+        set.add(SIGNONE);
+        set.add(SIGCHLD);
+        set.add(SIGTHR);
+
+        assert!(set.contains(SIGCHLD));
+        assert!(!set.contains(SIGHUP));
+        assert_eq!(set.first(), Some(SIGCHLD));
+        assert_eq!(set.to_bytes()[..4], 0x80080000u32.to_le_bytes());
+        assert_eq!(set.to_bytes()[12..], 0x80000000u32.to_le_bytes());
+        assert_eq!(SignalSet::from_bytes(set.to_bytes()), set);
+
+        set.remove(SIGCHLD);
+
+        assert_eq!(set.first(), Some(SIGTHR));
+
+        let mut all = !SignalSet::default();
+
+        all.remove_uncatchable();
+
+        assert!(!all.contains(SIGKILL));
+        assert!(!all.contains(SIGSTOP));
+        assert_eq!(all.first(), Some(SIGHUP));
+        assert_eq!(set & !all, SignalSet::default());
+    }
+}
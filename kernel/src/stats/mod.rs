@@ -0,0 +1,21 @@
+use crate::context::current_uma;
+use config::BootEnv;
+use krt::boot_env;
+
+mod vm;
+
+/// Publish a snapshot of kernel memory statistics to the host.
+///
+/// This is our equivalent of `vmstat -z` and `vmstat -m`, which read the statistics from
+/// `vm.zone_stats` and `kern.malloc_stats` sysctl.
+///
+/// When running inside a VM each call will cause a VM to exit multiple times so don't do this in a
+/// performance critical path.
+pub fn publish() {
+    let uma = current_uma().unwrap();
+    let heap = crate::KERNEL_HEAP.stats();
+
+    match boot_env() {
+        BootEnv::Vm(env) => self::vm::publish(env, &uma, heap),
+    }
+}
@@ -0,0 +1,52 @@
+use crate::malloc::MallocStats;
+use crate::uma::Uma;
+use config::{StatsMemory, Vm, ZoneStats};
+use core::ptr::write_volatile;
+
+pub fn publish(env: &Vm, uma: &Uma, heap: Option<MallocStats>) {
+    let m = env.stats as *mut StatsMemory;
+
+    // Write zones.
+    uma.for_each_zone(|z| {
+        let s = z.stats();
+        let v = ZoneStats {
+            name: name(z.name()),
+            size: z.size().get().try_into().unwrap(),
+            pages: s.pages.try_into().unwrap(),
+            keg_free: s.keg_free.try_into().unwrap(),
+            zone_free: s.zone_free.try_into().unwrap(),
+            allocs: s.allocs,
+            frees: s.frees,
+            fails: s.fails,
+        };
+
+        unsafe { write_volatile(&raw mut (*m).zone, &raw const v as usize) };
+    });
+
+    // Write malloc types. The kernel heap does not have malloc types since everything goes through
+    // the global allocator so we write a single entry for the whole heap instead.
+    if let Some(s) = heap {
+        let v = config::MallocStats {
+            name: name("(total)"),
+            alloc_bytes: s.alloc_bytes,
+            free_bytes: s.free_bytes,
+            alloc_count: s.alloc_count,
+            free_count: s.free_count,
+            zones: s.zones,
+        };
+
+        unsafe { write_volatile(&raw mut (*m).malloc, &raw const v as usize) };
+    }
+
+    unsafe { write_volatile(&raw mut (*m).commit, 0) };
+}
+
+/// Truncate `v` if it is too long.
+fn name(v: &str) -> [u8; 32] {
+    let mut buf = [0; 32];
+    let len = v.len().min(buf.len() - 1);
+
+    buf[..len].copy_from_slice(&v.as_bytes()[..len]);
+
+    buf
+}
@@ -0,0 +1,46 @@
+use crate::errno::{strerror, Errno};
+use alloc::boxed::Box;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+
+/// Error of each syscall.
+#[derive(Debug)]
+pub enum SysErr {
+    Raw(NonZero<c_int>),
+    Object(Box<dyn Errno>),
+}
+
+impl SysErr {
+    pub fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::Raw(v) => *v,
+            Self::Object(v) => v.errno(),
+        }
+    }
+}
+
+impl<T: Errno + 'static> From<T> for SysErr {
+    fn from(value: T) -> Self {
+        Self::Object(Box::new(value))
+    }
+}
+
+impl Error for SysErr {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Raw(_) => None,
+            Self::Object(e) => e.source(),
+        }
+    }
+}
+
+impl Display for SysErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Raw(v) => f.write_str(strerror(*v)),
+            Self::Object(e) => Display::fmt(&e, f),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use core::ffi::c_int;
+use core::fmt::{Formatter, LowerHex};
+use core::num::TryFromIntError;
+
+/// Input of the syscall entry point.
+pub struct SysIn {
+    pub id: u32,
+    pub offset: usize,
+    pub args: [SysArg; 6],
+}
+
+/// An argument of the syscall.
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+pub struct SysArg(usize);
+
+impl SysArg {
+    pub fn get(self) -> usize {
+        self.0
+    }
+}
+
+impl<T> From<SysArg> for *const T {
+    fn from(v: SysArg) -> Self {
+        v.0 as _
+    }
+}
+
+impl<T> From<SysArg> for *mut T {
+    fn from(v: SysArg) -> Self {
+        v.0 as _
+    }
+}
+
+impl From<SysArg> for usize {
+    fn from(v: SysArg) -> Self {
+        v.0
+    }
+}
+
+impl From<SysArg> for i64 {
+    fn from(v: SysArg) -> Self {
+        v.0 as _
+    }
+}
+
+impl TryFrom<SysArg> for c_int {
+    type Error = TryFromIntError;
+
+    fn try_from(v: SysArg) -> Result<Self, Self::Error> {
+        TryInto::<u32>::try_into(v.0).map(|v| v as c_int)
+    }
+}
+
+impl TryFrom<SysArg> for u32 {
+    type Error = TryFromIntError;
+
+    fn try_from(v: SysArg) -> Result<Self, Self::Error> {
+        v.0.try_into()
+    }
+}
+
+impl LowerHex for SysArg {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        LowerHex::fmt(&self.0, f)
+    }
+}
+
+impl From<usize> for SysArg {
+    fn from(value: usize) -> Self {
+        SysArg(value)
+    }
+}
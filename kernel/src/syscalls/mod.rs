@@ -0,0 +1,64 @@
+pub use self::error::*;
+pub use self::input::*;
+pub use self::output::*;
+
+use crate::errno::ENOSYS;
+use crate::proc::Thread;
+use krt::info;
+
+mod error;
+mod input;
+mod output;
+
+/// Provides PS4 kernel routines for PS4 application and system libraries.
+///
+/// The handler does not receive a context like the legacy implementation since each subsystem can be
+/// accessed from the CPU context.
+pub struct Syscalls([Option<Handler>; 680]);
+
+impl Syscalls {
+    pub const fn new() -> Self {
+        Self([None; 680])
+    }
+
+    /// # Panics
+    /// If `id` is not a valid number or the syscall with identifier `id` is already registered.
+    pub fn register(&mut self, id: u32, handler: Handler) {
+        let id: usize = id.try_into().unwrap();
+
+        assert!(self.0[id].replace(handler).is_none());
+    }
+
+    /// Execute the syscall that was specified in `i`. Unimplemented syscall will be logged and
+    /// [`ENOSYS`] will be returned instead.
+    ///
+    /// See `amd64_syscall` on the PS4 for a reference.
+    pub fn exec(&self, td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        // See https://github.com/freebsd/freebsd-src/blob/release/9.1.0/sys/kern/init_sysent.c#L36
+        // for standard FreeBSD syscalls.
+        let id: usize = i.id.try_into().unwrap();
+        let handler = match self.0.get(id) {
+            Some(Some(v)) => v,
+            Some(None) => {
+                info!(
+                    "Unimplemented syscall {} at {:#x} with args = [{:#x}, {:#x}, {:#x}, {:#x}, {:#x}, {:#x}].",
+                    i.id,
+                    i.offset,
+                    i.args[0],
+                    i.args[1],
+                    i.args[2],
+                    i.args[3],
+                    i.args[4],
+                    i.args[5],
+                );
+
+                return Err(SysErr::Raw(ENOSYS));
+            }
+            None => return Err(SysErr::Raw(ENOSYS)),
+        };
+
+        handler(td, i)
+    }
+}
+
+type Handler = fn(&Thread, &SysIn) -> Result<SysOut, SysErr>;
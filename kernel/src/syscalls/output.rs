@@ -0,0 +1,35 @@
+use core::ffi::c_int;
+
+/// Outputs of the syscall entry point.
+#[derive(Clone, Copy)]
+pub struct SysOut {
+    rax: usize,
+    rdx: usize,
+}
+
+impl SysOut {
+    pub const ZERO: Self = Self { rax: 0, rdx: 0 };
+
+    pub fn rax(&self) -> usize {
+        self.rax
+    }
+
+    pub fn rdx(&self) -> usize {
+        self.rdx
+    }
+}
+
+impl From<c_int> for SysOut {
+    fn from(value: c_int) -> Self {
+        Self {
+            rax: value as isize as usize, // Sign extended.
+            rdx: 0,
+        }
+    }
+}
+
+impl From<usize> for SysOut {
+    fn from(value: usize) -> Self {
+        Self { rax: value, rdx: 0 }
+    }
+}
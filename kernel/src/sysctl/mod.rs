@@ -0,0 +1,536 @@
+pub use self::oid::*;
+pub use self::req::*;
+
+use crate::context::current_sysctl;
+use crate::errno::{EINVAL, EISDIR, ENAMETOOLONG, ENOENT, ENOMEM, ENOTDIR, EPERM};
+use crate::proc::Thread;
+use crate::subsystem::Subsystem;
+use crate::syscalls::{SysErr, SysIn, SysOut, Syscalls};
+use crate::ucred::Privilege;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ffi::c_int;
+use macros::sysctl;
+
+mod oid;
+mod req;
+
+/// Maximum number of components in the name.
+pub const CTL_MAXNAME: usize = 24;
+
+/// Mask for the type of [`Oid`].
+pub const CTLTYPE: u32 = 0xf;
+pub const CTLTYPE_NODE: u32 = 1;
+pub const CTLTYPE_INT: u32 = 2;
+pub const CTLTYPE_STRING: u32 = 3;
+#[allow(dead_code)]
+pub const CTLTYPE_S64: u32 = 4;
+#[allow(dead_code)]
+pub const CTLTYPE_OPAQUE: u32 = 5;
+#[allow(dead_code)]
+pub const CTLTYPE_UINT: u32 = 6;
+#[allow(dead_code)]
+pub const CTLTYPE_LONG: u32 = 7;
+pub const CTLTYPE_ULONG: u32 = 8;
+#[allow(dead_code)]
+pub const CTLTYPE_U64: u32 = 9;
+
+/// Allow reads of variable.
+pub const CTLFLAG_RD: u32 = 0x80000000;
+/// Allow writes to the variable.
+pub const CTLFLAG_WR: u32 = 0x40000000;
+pub const CTLFLAG_RW: u32 = CTLFLAG_RD | CTLFLAG_WR;
+/// All users can set this var.
+pub const CTLFLAG_ANYBODY: u32 = 0x10000000;
+/// Permit set only if securelevel <= 0.
+pub const CTLFLAG_SECURE: u32 = 0x08000000;
+/// Prisoned roots can fiddle.
+#[allow(dead_code)]
+pub const CTLFLAG_PRISON: u32 = 0x04000000;
+/// Skip this sysctl when listing.
+pub const CTLFLAG_SKIP: u32 = 0x01000000;
+/// Handler is MP safe.
+pub const CTLFLAG_MPSAFE: u32 = 0x00040000;
+/// Can be read in capability mode.
+pub const CTLFLAG_CAPRD: u32 = 0x00008000;
+/// Can be written in capability mode.
+pub const CTLFLAG_CAPWR: u32 = 0x00004000;
+
+/// Mask for the securelevel of [`CTLFLAG_SECURE`].
+const CTLMASK_SECURE: u32 = 0x00F00000;
+const CTLSHIFT_SECURE: u32 = 20;
+
+pub const CTL_SYSCTL: c_int = 0;
+pub const CTL_KERN: c_int = 1;
+pub const CTL_VM: c_int = 2;
+pub const CTL_VFS: c_int = 3;
+pub const CTL_NET: c_int = 4;
+pub const CTL_DEBUG: c_int = 5;
+pub const CTL_HW: c_int = 6;
+pub const CTL_MACHDEP: c_int = 7;
+pub const CTL_USER: c_int = 8;
+pub const CTL_P1003_1B: c_int = 9;
+
+pub static SYSCTL: Oid = Oid::node(
+    None,
+    CTL_SYSCTL,
+    "sysctl",
+    CTLFLAG_RW,
+    "Sysctl internal magic",
+);
+
+pub static KERN: Oid = Oid::node(
+    None,
+    CTL_KERN,
+    "kern",
+    CTLFLAG_RW | CTLFLAG_CAPRD,
+    "High kernel, proc, limits &c",
+);
+
+pub static VM: Oid = Oid::node(None, CTL_VM, "vm", CTLFLAG_RW, "Virtual memory");
+
+pub static VFS: Oid = Oid::node(None, CTL_VFS, "vfs", CTLFLAG_RW, "File system");
+
+pub static NET: Oid = Oid::node(None, CTL_NET, "net", CTLFLAG_RW, "Network, (see socket.h)");
+
+pub static DEBUG: Oid = Oid::node(None, CTL_DEBUG, "debug", CTLFLAG_RW, "Debugging");
+
+pub static HW: Oid = Oid::node(None, CTL_HW, "hw", CTLFLAG_RW, "hardware");
+
+pub static MACHDEP: Oid = Oid::node(
+    None,
+    CTL_MACHDEP,
+    "machdep",
+    CTLFLAG_RW,
+    "machine dependent",
+);
+
+pub static USER: Oid = Oid::node(None, CTL_USER, "user", CTLFLAG_RW, "user-level");
+
+pub static P1003_1B: Oid = Oid::node(None, CTL_P1003_1B, "p1003_1b", CTLFLAG_RW, "P1003_1B");
+
+/// Implementation of the MIB tree.
+///
+/// The PS4 put all of OIDs in a linker set and register it in `sysctl_register_all`. We require
+/// each subsystem to register its OIDs with [`Sysctl::register()`] instead.
+pub struct Sysctl {
+    children: Vec<Entry>, // sysctl__children
+}
+
+impl Sysctl {
+    /// Returns a tree with the top-level nodes and `sysctl` meta-nodes.
+    pub fn new() -> Self {
+        let mut ctl = Self {
+            children: Vec::new(),
+        };
+
+        for oid in [
+            &SYSCTL, &KERN, &VM, &VFS, &NET, &DEBUG, &HW, &MACHDEP, &USER, &P1003_1B,
+        ] {
+            ctl.register(oid);
+        }
+
+        ctl.register(&SYSCTL_NEXT);
+        ctl.register(&SYSCTL_NAME2OID);
+        ctl.register(&SYSCTL_OIDDESCR);
+
+        ctl
+    }
+
+    pub fn register_syscalls(sys: &mut Syscalls) {
+        sys.register(202, Self::sys_sysctl);
+    }
+
+    /// # Panics
+    /// If the parent of `oid` is not registered or the OID with the same number or name already
+    /// registered.
+    ///
+    /// See `sysctl_register_oid` on the PS4 for a reference.
+    pub fn register(&mut self, oid: &'static Oid) {
+        // Get ancestors.
+        let mut ancestors = Vec::new();
+        let mut next = oid.parent();
+
+        while let Some(v) = next {
+            ancestors.push(v);
+            next = v.parent();
+        }
+
+        // Get the parent list.
+        let mut list = &mut self.children;
+
+        for p in ancestors.into_iter().rev() {
+            let e = list
+                .iter_mut()
+                .find(|e| core::ptr::eq(e.oid, p))
+                .unwrap_or_else(|| panic!("parent of {} is not registered", oid.name()));
+
+            assert!(p.is_container());
+
+            list = &mut e.children;
+        }
+
+        // Insert the OID. We keep the list sorted by number so we don't need to sort it when
+        // looking for the next OID.
+        let i = match list.binary_search_by_key(&oid.number(), |e| e.oid.number()) {
+            Ok(_) => panic!("OID {} is already registered", oid.number()),
+            Err(v) => v,
+        };
+
+        assert!(list.iter().all(|e| e.oid.name() != oid.name()));
+
+        list.insert(
+            i,
+            Entry {
+                oid,
+                children: Vec::new(),
+            },
+        );
+    }
+
+    /// See `sysctl_root` on the PS4 for a reference.
+    pub fn exec(&self, name: &[c_int], req: &mut SysctlReq) -> Result<(), SysErr> {
+        let (oid, len) = self.find(name)?;
+
+        if oid.is_container() {
+            return Err(SysErr::Raw(EISDIR));
+        }
+
+        // Check if write is allowed.
+        let kind = oid.kind();
+
+        if req.new_len().is_some() {
+            if (kind & CTLFLAG_WR) == 0 {
+                return Err(SysErr::Raw(EPERM));
+            }
+
+            if (kind & CTLFLAG_SECURE) != 0 {
+                // TODO: Use the securelevel of the prison once we have it. The default of prison0
+                // is -1.
+                let securelevel: c_int = -1;
+                let level = ((kind & CTLMASK_SECURE) >> CTLSHIFT_SECURE) as c_int;
+
+                if securelevel > level {
+                    return Err(SysErr::Raw(EPERM));
+                }
+            }
+
+            // TODO: Check PRIV_SYSCTL_WRITEJAIL once we have jail.
+            if (kind & CTLFLAG_ANYBODY) == 0 {
+                req.td().priv_check(Privilege::SYSCTL_WRITE)?;
+            }
+        }
+
+        // Execute.
+        match oid.data() {
+            OidData::Node => unreachable!(),
+            OidData::Int(v) => req.write(&v.to_ne_bytes()),
+            OidData::UInt(v) => req.write(&v.to_ne_bytes()),
+            OidData::Long(v) => req.write(&v.to_ne_bytes()),
+            OidData::ULong(v) => req.write(&v.to_ne_bytes()),
+            OidData::S64(v) => req.write(&v.to_ne_bytes()),
+            OidData::U64(v) => req.write(&v.to_ne_bytes()),
+            OidData::String(v) => {
+                req.write(v.as_bytes())?;
+                req.write(&[0])
+            }
+            OidData::Handler(f) => f(self, &name[len..], req),
+        }
+    }
+
+    /// Returns the OID and the number of components in `name` that was consumed.
+    ///
+    /// See `sysctl_find_oid` on the PS4 for a reference.
+    pub fn find(&self, name: &[c_int]) -> Result<(&'static Oid, usize), SysErr> {
+        let mut list = &self.children;
+
+        for (i, &n) in name.iter().enumerate() {
+            let oid = match list.iter().find(|e| e.oid.number() == n) {
+                Some(v) => v,
+                None => break,
+            };
+
+            if oid.oid.is_container() {
+                if i + 1 == name.len() {
+                    return Ok((oid.oid, i + 1));
+                }
+
+                list = &oid.children;
+            } else if (oid.oid.kind() & CTLTYPE) == CTLTYPE_NODE {
+                // The remaining name is an argument of the handler.
+                return Ok((oid.oid, i + 1));
+            } else if i + 1 != name.len() {
+                return Err(SysErr::Raw(ENOTDIR));
+            } else {
+                return Ok((oid.oid, i + 1));
+            }
+        }
+
+        Err(SysErr::Raw(ENOENT))
+    }
+
+    /// See `name2oid` on the PS4 for a reference.
+    pub fn name2oid(&self, name: &str) -> Option<Vec<c_int>> {
+        let mut list = &self.children;
+        let mut path = name.split('.').peekable();
+        let mut oid = Vec::new();
+
+        while let Some(name) = path.next() {
+            let e = list.iter().find(|e| e.oid.name() == name)?;
+
+            oid.push(e.oid.number());
+
+            if path.peek().is_none() {
+                return Some(oid);
+            } else if !e.oid.is_container() {
+                break;
+            }
+
+            list = &e.children;
+        }
+
+        None
+    }
+
+    /// Returns the first leaf that come after `name`.
+    ///
+    /// See `sysctl_sysctl_next_ls` on the PS4 for a reference.
+    pub fn next(&self, name: &[c_int]) -> Option<Vec<c_int>> {
+        fn walk(list: &[Entry], name: &[c_int], path: &mut Vec<c_int>) -> bool {
+            for e in list {
+                if e.oid.is_skipped() {
+                    continue;
+                }
+
+                path.push(e.oid.number());
+
+                if e.oid.is_container() {
+                    if walk(&e.children, name, path) {
+                        return true;
+                    }
+                } else if path.as_slice() > name {
+                    return true;
+                }
+
+                path.pop();
+            }
+
+            false
+        }
+
+        let mut path = Vec::new();
+
+        walk(&self.children, name, &mut path).then_some(path)
+    }
+
+    /// See `sys___sysctl` on the PS4 for a reference.
+    fn sys_sysctl(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let name: usize = i.args[0].into();
+        let namelen: usize = i.args[1].into();
+        let old: usize = i.args[2].into();
+        let oldlenp: usize = i.args[3].into();
+        let new: usize = i.args[4].into();
+        let newlen: usize = i.args[5].into();
+        let vm = td.proc().vm();
+
+        // Read name.
+        if !(2..=CTL_MAXNAME).contains(&namelen) {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        let mut buf = vec![0; namelen * 4];
+
+        vm.read(name, &mut buf)?;
+
+        let name: Vec<c_int> = buf
+            .chunks_exact(4)
+            .map(|v| c_int::from_le_bytes(v.try_into().unwrap()))
+            .collect();
+
+        if name[0] == CTL_DEBUG && !td.cred_mut().is_system() {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        // Read the size of output buffer.
+        let oldlen = if oldlenp != 0 {
+            let mut buf = [0; 8];
+
+            vm.read(oldlenp, &mut buf)?;
+
+            usize::from_le_bytes(buf)
+        } else {
+            0
+        };
+
+        // Execute.
+        let mut req = SysctlReq::new(
+            td,
+            (old != 0).then_some(old),
+            oldlen,
+            (new != 0).then_some(new),
+            newlen,
+        );
+
+        let r = current_sysctl().unwrap().exec(&name, &mut req);
+
+        match r {
+            Err(e) if e.errno() != ENOMEM => return Err(e),
+            _ => {}
+        }
+
+        // Write the size of output data. ENOMEM still need to report how many data is available.
+        if oldlenp != 0 {
+            vm.write(oldlenp, &req.old_len().to_le_bytes())?;
+        }
+
+        r.map(|_| SysOut::ZERO)
+    }
+}
+
+impl Subsystem for Sysctl {}
+
+/// An entry in the MIB tree.
+struct Entry {
+    oid: &'static Oid,
+    children: Vec<Entry>, // Only available if oid is a node without handler.
+}
+
+/// See `sysctl_sysctl_next` on the PS4 for a reference.
+#[sysctl(
+    parent = SYSCTL,
+    number = 2,
+    name = "next",
+    ty = CTLTYPE_NODE,
+    flags = CTLFLAG_RD | CTLFLAG_MPSAFE | CTLFLAG_CAPRD,
+    descr = ""
+)]
+fn sysctl_next(ctl: &Sysctl, name: &[c_int], req: &mut SysctlReq) -> Result<(), SysErr> {
+    let next = ctl.next(name).ok_or(SysErr::Raw(ENOENT))?;
+
+    for v in next {
+        req.write(&v.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// See `sysctl_sysctl_name2oid` on the PS4 for a reference.
+#[sysctl(
+    parent = SYSCTL,
+    number = 3,
+    name = "name2oid",
+    ty = CTLTYPE_INT,
+    flags = CTLFLAG_RW | CTLFLAG_ANYBODY | CTLFLAG_MPSAFE | CTLFLAG_CAPRD | CTLFLAG_CAPWR,
+    descr = ""
+)]
+fn sysctl_name2oid(ctl: &Sysctl, _: &[c_int], req: &mut SysctlReq) -> Result<(), SysErr> {
+    // Check input size.
+    let len = match req.new_len() {
+        Some(0) | None => return Err(SysErr::Raw(ENOENT)),
+        Some(v) if v >= 0x400 => return Err(SysErr::Raw(ENAMETOOLONG)),
+        Some(v) => v,
+    };
+
+    // Read name.
+    let mut buf = vec![0; len];
+
+    req.read(&mut buf)?;
+
+    if let Some(i) = buf.iter().position(|&b| b == 0) {
+        buf.truncate(i);
+    }
+
+    let mut name = String::from_utf8(buf).map_err(|_| SysErr::Raw(ENOENT))?;
+
+    // Remove '.' at the end if present.
+    if name.ends_with('.') {
+        name.pop();
+    }
+
+    // Map name to OID.
+    let oid = ctl.name2oid(&name).ok_or(SysErr::Raw(ENOENT))?;
+
+    for v in oid {
+        req.write(&v.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// See `sysctl_sysctl_oiddescr` on the PS4 for a reference.
+#[sysctl(
+    parent = SYSCTL,
+    number = 5,
+    name = "oiddescr",
+    ty = CTLTYPE_NODE,
+    flags = CTLFLAG_RD | CTLFLAG_MPSAFE | CTLFLAG_CAPRD,
+    descr = ""
+)]
+fn sysctl_oiddescr(ctl: &Sysctl, name: &[c_int], req: &mut SysctlReq) -> Result<(), SysErr> {
+    let (oid, _) = ctl.find(name)?;
+    let descr = oid.descr();
+
+    if descr.is_empty() {
+        return Err(SysErr::Raw(ENOENT));
+    }
+
+    req.write(descr.as_bytes())?;
+    req.write(&[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static NODE: Oid = Oid::node(Some(&KERN), 0x100, "node", CTLFLAG_RD, "Test node");
+
+    #[sysctl(parent = NODE, number = 1, name = "int", flags = CTLFLAG_RD, descr = "Test int")]
+    static NODE_INT: c_int = 1;
+
+    #[sysctl(parent = NODE, number = 2, name = "str", flags = CTLFLAG_RD | CTLFLAG_SKIP)]
+    static NODE_STR: &str = "abc";
+
+    #[test]
+    fn tree() {
+        let mut ctl = Sysctl::new();
+
+        ctl.register(&NODE);
+        ctl.register(&NODE_INT);
+        ctl.register(&NODE_STR);
+
+        // Test find.
+        let (oid, len) = ctl.find(&[CTL_KERN, 0x100, 1]).unwrap();
+
+        assert!(core::ptr::eq(oid, &NODE_INT));
+        assert_eq!(len, 3);
+        assert_eq!(oid.descr(), "Test int");
+        assert!(matches!(oid.data(), OidData::Int(1)));
+
+        let (oid, len) = ctl.find(&[CTL_SYSCTL, 2, CTL_KERN]).unwrap();
+
+        assert!(core::ptr::eq(oid, &SYSCTL_NEXT));
+        assert_eq!(len, 2);
+
+        assert!(ctl
+            .find(&[CTL_KERN, 0x100, 1, 0])
+            .is_err_and(|e| e.errno() == ENOTDIR));
+        assert!(ctl
+            .find(&[CTL_KERN, 0x101])
+            .is_err_and(|e| e.errno() == ENOENT));
+
+        // Test name2oid.
+        assert_eq!(
+            ctl.name2oid("kern.node.int"),
+            Some(vec![CTL_KERN, 0x100, 1])
+        );
+        assert_eq!(ctl.name2oid("kern.node"), Some(vec![CTL_KERN, 0x100]));
+        assert_eq!(ctl.name2oid("kern.node.int.abc"), None);
+        assert_eq!(ctl.name2oid("kern.abc"), None);
+
+        // Test next.
+        assert_eq!(ctl.next(&[CTL_SYSCTL]), Some(vec![CTL_SYSCTL, 2]));
+        assert_eq!(ctl.next(&[CTL_SYSCTL, 5]), Some(vec![CTL_KERN, 0x100, 1]));
+        assert_eq!(ctl.next(&[CTL_KERN, 0x100, 1]), None);
+    }
+}
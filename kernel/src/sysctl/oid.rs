@@ -0,0 +1,108 @@
+use super::{Sysctl, SysctlReq, CTLFLAG_SKIP, CTLTYPE, CTLTYPE_NODE};
+use crate::syscalls::SysErr;
+use core::ffi::c_int;
+
+/// Implementation of `sysctl_oid` structure.
+///
+/// Use [`macros::sysctl`] to define a leaf and [`Oid::node()`] to define a node.
+pub struct Oid {
+    parent: Option<&'static Self>, // oid_parent
+    number: c_int,                 // oid_number
+    name: &'static str,            // oid_name
+    kind: u32,                     // oid_kind
+    data: OidData,                 // oid_arg1 + oid_arg2 + oid_handler
+    descr: &'static str,           // oid_descr
+}
+
+impl Oid {
+    pub const fn new(
+        parent: Option<&'static Self>,
+        number: c_int,
+        name: &'static str,
+        kind: u32,
+        data: OidData,
+        descr: &'static str,
+    ) -> Self {
+        Self {
+            parent,
+            number,
+            name,
+            kind,
+            data,
+            descr,
+        }
+    }
+
+    /// See `SYSCTL_NODE` on the PS4 for a reference.
+    pub const fn node(
+        parent: Option<&'static Self>,
+        number: c_int,
+        name: &'static str,
+        flags: u32,
+        descr: &'static str,
+    ) -> Self {
+        Self::new(
+            parent,
+            number,
+            name,
+            CTLTYPE_NODE | flags,
+            OidData::Node,
+            descr,
+        )
+    }
+
+    pub fn parent(&self) -> Option<&'static Self> {
+        self.parent
+    }
+
+    pub fn number(&self) -> c_int {
+        self.number
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn kind(&self) -> u32 {
+        self.kind
+    }
+
+    pub fn data(&self) -> &OidData {
+        &self.data
+    }
+
+    pub fn descr(&self) -> &'static str {
+        self.descr
+    }
+
+    /// Returns `true` if this OID is a node that can contains the other OIDs.
+    ///
+    /// A node with a handler is not a container since the handler will receive the remaining
+    /// name instead.
+    pub fn is_container(&self) -> bool {
+        (self.kind & CTLTYPE) == CTLTYPE_NODE && matches!(self.data, OidData::Node)
+    }
+
+    pub fn is_skipped(&self) -> bool {
+        (self.kind & CTLFLAG_SKIP) != 0
+    }
+}
+
+/// Data of [`Oid`].
+///
+/// The PS4 use `oid_arg1` and `oid_arg2` with a generic handler for the static data. We use an
+/// enum for it instead.
+pub enum OidData {
+    Node,
+    Int(c_int),
+    UInt(u32),
+    Long(isize),
+    ULong(usize),
+    S64(i64),
+    U64(u64),
+    String(&'static str),
+    Handler(OidHandler),
+}
+
+/// Handler of [`Oid`]. The second argument is the remaining name when the [`Oid`] is a node.
+pub type OidHandler = fn(&Sysctl, &[c_int], &mut SysctlReq) -> Result<(), SysErr>;
@@ -0,0 +1,99 @@
+use crate::errno::{EINVAL, ENOMEM};
+use crate::proc::Thread;
+use crate::syscalls::SysErr;
+
+/// Implementation of `sysctl_req` structure.
+pub struct SysctlReq<'a> {
+    td: &'a Thread,     // td
+    old: Option<usize>, // oldptr
+    oldlen: usize,      // oldlen + validlen
+    oldidx: usize,      // oldidx
+    new: Option<usize>, // newptr
+    newlen: usize,      // newlen
+    newidx: usize,      // newidx
+}
+
+impl<'a> SysctlReq<'a> {
+    /// `old` and `new` is the address in the user space of `td`.
+    pub fn new(
+        td: &'a Thread,
+        old: Option<usize>,
+        oldlen: usize,
+        new: Option<usize>,
+        newlen: usize,
+    ) -> Self {
+        Self {
+            td,
+            old,
+            oldlen,
+            oldidx: 0,
+            new,
+            newlen: new.map_or(0, |_| newlen),
+            newidx: 0,
+        }
+    }
+
+    pub fn td(&self) -> &'a Thread {
+        self.td
+    }
+
+    /// Returns [`None`] if the caller does not want to change the value.
+    pub fn new_len(&self) -> Option<usize> {
+        self.new.map(|_| self.newlen)
+    }
+
+    /// Returns the total length of the data that was written by [`Self::write()`], which may be
+    /// larger than the buffer provided by the caller.
+    ///
+    /// The result is limited to the size of buffer if the caller provided it.
+    pub fn old_len(&self) -> usize {
+        match self.old {
+            Some(_) if self.oldidx > self.oldlen => self.oldlen,
+            _ => self.oldidx,
+        }
+    }
+
+    /// See `sysctl_new_user` on the PS4 for a reference.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<(), SysErr> {
+        let new = match self.new {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        if self.newlen - self.newidx < buf.len() {
+            return Err(SysErr::Raw(EINVAL));
+        }
+
+        self.td.proc().vm().read(new + self.newidx, buf)?;
+        self.newidx += buf.len();
+
+        Ok(())
+    }
+
+    /// See `sysctl_old_user` on the PS4 for a reference.
+    pub fn write(&mut self, data: &[u8]) -> Result<(), SysErr> {
+        // Update the index.
+        let origidx = self.oldidx;
+
+        self.oldidx += data.len();
+
+        // Check if output buffer is available.
+        let old = match self.old {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        // Copy data.
+        let len = self.oldlen.saturating_sub(origidx).min(data.len());
+
+        if len > 0 {
+            self.td.proc().vm().write(old + origidx, &data[..len])?;
+        }
+
+        if len != data.len() {
+            Err(SysErr::Raw(ENOMEM))
+        } else {
+            Ok(())
+        }
+    }
+}
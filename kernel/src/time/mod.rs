@@ -0,0 +1,37 @@
+/// Implementation of `timespec` structure.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TimeSpec {
+    pub sec: i64,  // tv_sec
+    pub nsec: i64, // tv_nsec
+}
+
+impl TimeSpec {
+    /// See `vfs_timestamp` on the PS4 for a reference.
+    pub fn now() -> Self {
+        // TODO: Read the clock once we have it.
+        Self::default()
+    }
+}
+
+/// Implementation of `timeval` structure.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TimeVal {
+    pub sec: i64,  // tv_sec
+    pub usec: i64, // tv_usec
+}
+
+impl TimeVal {
+    /// Returns [`None`] if [`TimeVal::usec`] is out of range.
+    pub fn to_timespec(self) -> Option<TimeSpec> {
+        if !(0..1000000).contains(&self.usec) {
+            return None;
+        }
+
+        Some(TimeSpec {
+            sec: self.sec,
+            nsec: self.usec * 1000,
+        })
+    }
+}
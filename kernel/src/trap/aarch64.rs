@@ -5,6 +5,16 @@ pub extern "C" fn interrupt_handler(_: &mut TrapFrame) {
     todo!()
 }
 
+/// Carry flag, which indicate the syscall was failed.
+pub const PSR_C: usize = 0x20000000;
+
 /// Contains states of the interupted program.
 #[repr(C)]
-pub struct TrapFrame {}
+pub struct TrapFrame {
+    pub sp: usize,      // tf_sp
+    pub lr: usize,      // tf_lr
+    pub elr: usize,     // tf_elr
+    pub spsr: usize,    // tf_spsr
+    pub esr: usize,     // tf_esr
+    pub x: [usize; 30], // tf_x
+}
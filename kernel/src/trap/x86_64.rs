@@ -1,4 +1,6 @@
 use crate::context::current_thread;
+use crate::signal::{self, SIGSEGV};
+use crate::vm::{VmProt, VmSpace};
 use config::BootEnv;
 use core::sync::atomic::Ordering;
 use krt::boot_env;
@@ -11,15 +13,50 @@ use krt::boot_env;
 pub extern "C" fn interrupt_handler(frame: &mut TrapFrame) {
     let td = current_thread();
 
-    unsafe { td.active_interrupts().fetch_add(1, Ordering::Relaxed) };
-
     match frame.num {
-        TrapNo::Breakpoint => match boot_env() {
-            BootEnv::Vm(vm) => super::vm::interrupt_handler(vm, frame),
-        },
+        T_BPTFLT => {
+            unsafe { td.active_interrupts().fetch_add(1, Ordering::Relaxed) };
+
+            match boot_env() {
+                BootEnv::Vm(vm) => super::vm::interrupt_handler(vm, frame),
+            }
+
+            unsafe { td.active_interrupts().fetch_sub(1, Ordering::Relaxed) };
+        }
+        // Page fault belong to the current thread so it is allowed to sleep.
+        T_PAGEFLT => page_fault(frame),
+        v => panic!("unexpected trap {v} at {:#x}", frame.rip),
+    }
+}
+
+/// See `trap_pfault` on the PS4 for a reference.
+fn page_fault(frame: &mut TrapFrame) {
+    let addr = frame.addr;
+    let user = (frame.cs & 3) != 0;
+    let ty = if (frame.err & PGEX_I) != 0 {
+        VmProt::Execute
+    } else if (frame.err & PGEX_W) != 0 {
+        VmProt::Write
+    } else {
+        VmProt::Read
+    };
+
+    // The kernel memory is never paged.
+    if addr >= VmSpace::USER_MAX {
+        panic!("page fault on kernel address {addr:#x} at {:#x}", frame.rip);
     }
 
-    unsafe { td.active_interrupts().fetch_sub(1, Ordering::Relaxed) };
+    // Fault in the page.
+    let td = current_thread();
+
+    if let Err(e) = td.proc().vm().fault(addr, ty) {
+        if user {
+            signal::trap(&td, frame, SIGSEGV);
+            return;
+        }
+
+        panic!("page fault on {addr:#x} at {:#x} ({e})", frame.rip);
+    }
 }
 
 /// Main entry point for `syscall` instruction.
@@ -27,7 +64,7 @@ pub extern "C" fn interrupt_handler(frame: &mut TrapFrame) {
 /// This will be called by an inline assembly.
 ///
 /// See `amd64_syscall` function on the PS4 for a reference.
-pub extern "C" fn syscall_handler() {
+pub extern "C" fn syscall_handler(frame: &mut TrapFrame) {
     // TODO: Implement pc_cnt.v_syscall increment.
     let td = current_thread();
     let p = td.proc();
@@ -35,38 +72,57 @@ pub extern "C" fn syscall_handler() {
     *td.profiling_ticks_mut() = 0;
 
     // We merge sv_fetch_syscall_args and the code to invoke each syscall handler together.
-    p.abi().syscall_handler();
+    p.abi().syscall_handler(&td, frame);
 
-    todo!()
+    // Deliver pending signals. The PS4 do this in ast, which is invoked by userret. TODO: Implement
+    // the remaining of userret.
+    signal::deliver(&td, frame);
 }
 
-/// Predefined interrupt vector number.
-#[allow(dead_code)] // Used by inline assembly.
-#[repr(u32)]
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub enum TrapNo {
-    Breakpoint = 3, // T_BPTFLT
-}
+/// Breakpoint trap (AKA `T_BPTFLT`).
+pub const T_BPTFLT: u32 = 3;
+
+/// Page fault (AKA `T_PAGEFLT`).
+pub const T_PAGEFLT: u32 = 14;
+
+/// Carry flag, which indicate the syscall was failed.
+pub const PSL_C: usize = 0x1;
+
+/// Page fault was caused by a write (AKA `PGEX_W`).
+const PGEX_W: usize = 0x02;
+
+/// Page fault was caused by an instruction fetch (AKA `PGEX_I`).
+const PGEX_I: usize = 0x10;
 
 /// Contains states of the interupted program.
 #[repr(C)]
 pub struct TrapFrame {
-    pub rdi: usize,  // tf_rdi
-    pub rsi: usize,  // tf_rsi
-    pub rdx: usize,  // tf_rdx
-    pub rcx: usize,  // tf_rcx
-    pub r8: usize,   // tf_r8
-    pub r9: usize,   // tf_r9
-    pub rax: usize,  // tf_rax
-    pub rbx: usize,  // tf_rbx
-    pub rbp: usize,  // tf_rbp
-    pub r10: usize,  // tf_r10
-    pub r11: usize,  // tf_r11
-    pub r12: usize,  // tf_r12
-    pub r13: usize,  // tf_r13
-    pub r14: usize,  // tf_r14
-    pub r15: usize,  // tf_r15
-    pub num: TrapNo, // tf_trapno
-    pub fs: u16,     // tf_fs
-    pub gs: u16,     // tf_gs
+    pub rdi: usize,    // tf_rdi
+    pub rsi: usize,    // tf_rsi
+    pub rdx: usize,    // tf_rdx
+    pub rcx: usize,    // tf_rcx
+    pub r8: usize,     // tf_r8
+    pub r9: usize,     // tf_r9
+    pub rax: usize,    // tf_rax
+    pub rbx: usize,    // tf_rbx
+    pub rbp: usize,    // tf_rbp
+    pub r10: usize,    // tf_r10
+    pub r11: usize,    // tf_r11
+    pub r12: usize,    // tf_r12
+    pub r13: usize,    // tf_r13
+    pub r14: usize,    // tf_r14
+    pub r15: usize,    // tf_r15
+    pub num: u32,      // tf_trapno (the syscall number for syscall)
+    pub fs: u16,       // tf_fs
+    pub gs: u16,       // tf_gs
+    pub addr: usize,   // tf_addr
+    pub flags: u32,    // tf_flags
+    pub es: u16,       // tf_es
+    pub ds: u16,       // tf_ds
+    pub err: usize,    // tf_err
+    pub rip: usize,    // tf_rip
+    pub cs: usize,     // tf_cs
+    pub rflags: usize, // tf_rflags
+    pub rsp: usize,    // tf_rsp
+    pub ss: usize,     // tf_ss
 }
@@ -0,0 +1,132 @@
+/// Implementation of `self_auth_info`.
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct AuthInfo {
+    pub paid: AuthPaid,
+    pub caps: AuthCaps,
+    pub attrs: AuthAttrs,
+    pub unk: [u8; 0x40],
+}
+
+impl AuthInfo {
+    pub const KERNEL: Self = Self {
+        paid: AuthPaid::KERNEL,
+        caps: AuthCaps([0x4000000000000000, 0, 0, 0]),
+        attrs: AuthAttrs([0, 0, 0, 0]),
+        unk: [0; 0x40],
+    };
+
+    #[allow(dead_code)] // TODO: Remove this once create_init can read mini-syscore.elf.
+    pub const SYS_CORE: Self = Self {
+        paid: AuthPaid::SYS_CORE,
+        caps: AuthCaps([
+            0x40001C0000000000,
+            0x800000000000FF00,
+            0x0000000000000000,
+            0x0000000000000000,
+        ]),
+        attrs: AuthAttrs([
+            0x4000400080000000,
+            0x8000000000000000,
+            0x0800000000000000,
+            0xF0000000FFFF4000,
+        ]),
+        unk: [0; 0x40],
+    };
+}
+
+/// A wrapper type for `paid` field of [`AuthInfo`].
+///
+/// PAID is an abbreviation of "Program Authority ID", not the game has been paid!
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthPaid(u64);
+
+impl AuthPaid {
+    pub const KERNEL: Self = Self(0);
+    pub const SYS_CORE: Self = Self(0x3800000000000007);
+
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+/// A wrapper type for `caps` field of [`AuthInfo`].
+#[repr(transparent)]
+#[derive(Debug, Clone)]
+pub struct AuthCaps([u64; 4]);
+
+#[allow(dead_code)] // TODO: Remove this once all callers are ported.
+impl AuthCaps {
+    pub fn new(raw: [u64; 4]) -> Self {
+        Self(raw)
+    }
+
+    pub fn get(&self, i: usize) -> u64 {
+        self.0[i]
+    }
+
+    pub fn clear_non_type(&mut self) {
+        self.0[0] &= 0x7000000000000000;
+        self.0[1] = 0;
+        self.0[2] = 0;
+        self.0[3] = 0;
+    }
+
+    pub fn is_nongame(&self) -> bool {
+        (self.0[0] & 0x1000000000000000) != 0
+    }
+
+    pub fn is_user(&self) -> bool {
+        (self.0[0] & 0x2000000000000000) != 0
+    }
+
+    pub fn is_system(&self) -> bool {
+        (self.0[0] & 0x4000000000000000) != 0
+    }
+
+    pub fn has_use_video_service(&self) -> bool {
+        (self.0[1] & 0x0200000000000000) != 0
+    }
+
+    pub fn is_unk1(&self) -> bool {
+        (self.0[1] & 0x4000000000000000) != 0
+    }
+}
+
+/// A wrapper type for `attrs` field of [`AuthInfo`].
+#[repr(transparent)]
+#[derive(Debug, Clone)]
+pub struct AuthAttrs([u64; 4]);
+
+#[allow(dead_code)] // TODO: Remove this once all callers are ported.
+impl AuthAttrs {
+    pub fn new(raw: [u64; 4]) -> Self {
+        Self(raw)
+    }
+
+    pub fn is_unk2(&self) -> bool {
+        (self.0[0] & 0x00400000) != 0
+    }
+
+    pub fn is_unk1(&self) -> bool {
+        (self.0[0] & 0x00800000) != 0
+    }
+
+    /// Returns [`None`] if the result depends on the resource controller.
+    ///
+    /// See `sceSblACMgrIsDebuggableProcess` on the PS4 for a reference.
+    pub fn is_debuggable_process(&self) -> Option<bool> {
+        // TODO: Check is_allow_ul_debugger and is_softwagner_qaf_for_acmgr from the resource
+        // controller once it is implemented.
+        if (self.0[0] & 0x01000000) == 0 && (self.0[0] & 0x02000000) != 0 {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    pub fn has_sce_program_attribute(&self) -> bool {
+        (self.0[0] & 0x80000000) != 0
+    }
+}
@@ -0,0 +1,49 @@
+use core::ffi::c_int;
+
+/// Implementation of `uid_t`.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Uid(c_int);
+
+impl Uid {
+    pub const ROOT: Self = Self(0);
+
+    /// Returns [`None`] if `v` is negative.
+    pub const fn new(v: c_int) -> Option<Self> {
+        if v >= 0 {
+            Some(Self(v))
+        } else {
+            None
+        }
+    }
+}
+
+impl From<Uid> for c_int {
+    fn from(value: Uid) -> Self {
+        value.0
+    }
+}
+
+/// Implementation of `gid_t`.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Gid(c_int);
+
+impl Gid {
+    pub const ROOT: Self = Self(0);
+
+    /// Returns [`None`] if `v` is negative.
+    pub const fn new(v: c_int) -> Option<Self> {
+        if v >= 0 {
+            Some(Self(v))
+        } else {
+            None
+        }
+    }
+}
+
+impl From<Gid> for c_int {
+    fn from(value: Gid) -> Self {
+        value.0
+    }
+}
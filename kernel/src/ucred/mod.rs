@@ -0,0 +1,426 @@
+pub use self::auth::*;
+pub use self::id::*;
+pub use self::privilege::*;
+
+use crate::errno::{Errno, EINVAL, EPERM};
+use crate::proc::Thread;
+use crate::syscalls::{SysErr, SysIn, SysOut, Syscalls};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+
+mod auth;
+mod id;
+mod privilege;
+
+/// Implementation of `ucred` structure.
+///
+/// This type is immutable. Any modification must be done on a copy then replace the one on the
+/// process with it.
+#[derive(Debug, Clone)]
+pub struct Ucred {
+    effective_uid: Uid, // cr_uid
+    real_uid: Uid,      // cr_ruid
+    saved_uid: Uid,     // cr_svuid
+    real_gid: Gid,      // cr_rgid
+    saved_gid: Gid,     // cr_svgid
+    groups: Vec<Gid>,   // cr_groups + cr_ngroups
+    auth: AuthInfo,
+}
+
+impl Ucred {
+    /// The first item of `groups` is the effective group.
+    ///
+    /// # Panics
+    /// If `groups` is empty.
+    pub fn new(effective_uid: Uid, real_uid: Uid, mut groups: Vec<Gid>, auth: AuthInfo) -> Self {
+        assert!(!groups.is_empty()); // Must have primary group.
+
+        groups[1..].sort_unstable(); // The first one must be primary group.
+
+        Self {
+            effective_uid,
+            real_uid,
+            saved_uid: effective_uid,
+            real_gid: groups[0],
+            saved_gid: groups[0],
+            groups,
+            auth,
+        }
+    }
+
+    pub fn effective_uid(&self) -> Uid {
+        self.effective_uid
+    }
+
+    pub fn real_uid(&self) -> Uid {
+        self.real_uid
+    }
+
+    #[allow(dead_code)] // TODO: Remove this once exec is ported.
+    pub fn saved_uid(&self) -> Uid {
+        self.saved_uid
+    }
+
+    pub fn effective_gid(&self) -> Gid {
+        self.groups[0]
+    }
+
+    pub fn real_gid(&self) -> Gid {
+        self.real_gid
+    }
+
+    #[allow(dead_code)] // TODO: Remove this once exec is ported.
+    pub fn saved_gid(&self) -> Gid {
+        self.saved_gid
+    }
+
+    #[allow(dead_code)] // TODO: Remove this once exec is ported.
+    pub fn auth(&self) -> &AuthInfo {
+        &self.auth
+    }
+
+    /// See `groupmember` on the PS4 for a reference.
+    pub fn is_member(&self, gid: Gid) -> bool {
+        if self.groups[0] == gid {
+            return true;
+        }
+
+        self.groups[1..].binary_search(&gid).is_ok()
+    }
+
+    /// See `priv_check_cred` on the PS4 for a reference.
+    pub fn priv_check(&self, p: Privilege) -> Result<(), PrivilegeError> {
+        // TODO: Check suser_enabled.
+        self.prison_priv_check()?;
+
+        let r = match p {
+            Privilege::MAXFILES
+            | Privilege::PROC_SETLOGIN
+            | Privilege::SCE680
+            | Privilege::SCE683
+            | Privilege::SCE686 => self.is_system(),
+            // TODO: Check if the PS4 has any special handling for the other privileges. The
+            // following is the default behavior of FreeBSD.
+            _ => self.effective_uid == Uid::ROOT,
+        };
+
+        if r {
+            Ok(())
+        } else {
+            Err(PrivilegeError::NoPrivilege(p))
+        }
+    }
+
+    /// Check if the process with `self` can send a signal to the process with `other`.
+    ///
+    /// See `cr_cansignal` on the PS4 for a reference.
+    pub fn can_signal(&self, other: &Self) -> Result<(), PrivilegeError> {
+        // TODO: Implement prison_check and P_SUGID check.
+        if self.real_uid != other.real_uid
+            && self.real_uid != other.saved_uid
+            && self.effective_uid != other.real_uid
+            && self.effective_uid != other.saved_uid
+        {
+            self.priv_check(Privilege::SIGNAL_DIFFCRED)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn register_syscalls(sys: &mut Syscalls) {
+        sys.register(23, Self::sys_setuid);
+        sys.register(24, Self::sys_getuid);
+        sys.register(25, Self::sys_geteuid);
+        sys.register(43, Self::sys_getegid);
+        sys.register(47, Self::sys_getgid);
+        sys.register(181, Self::sys_setgid);
+        sys.register(182, Self::sys_setegid);
+        sys.register(183, Self::sys_seteuid);
+    }
+
+    /// See `prison_priv_check` on the PS4 for a reference.
+    fn prison_priv_check(&self) -> Result<(), PrivilegeError> {
+        // TODO: Implement this.
+        Ok(())
+    }
+
+    /// See `sys_setuid` on the PS4 for a reference.
+    fn sys_setuid(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let uid: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let uid = Uid::new(uid).ok_or(SysErr::Raw(EINVAL))?;
+        let mut cred = td.proc().cred_mut();
+        let old = cred.clone();
+
+        // We use the same rules as FreeBSD with _POSIX_SAVED_IDS and POSIX_APPENDIX_B_4_2_2.
+        if uid != old.real_uid && uid != old.effective_uid {
+            old.priv_check(Privilege::CRED_SETUID)?;
+        }
+
+        // Set real and saved user ID only if the new ID is the effective user ID or we are using
+        // privileges.
+        let mut new = Ucred::clone(&old);
+
+        if uid == old.effective_uid || old.priv_check(Privilege::CRED_SETUID).is_ok() {
+            new.real_uid = uid;
+            new.saved_uid = uid;
+        }
+
+        // TODO: Set P_SUGID.
+        new.effective_uid = uid;
+
+        Self::replace(td, &mut cred, new);
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_getuid` on the PS4 for a reference.
+    fn sys_getuid(td: &Thread, _: &SysIn) -> Result<SysOut, SysErr> {
+        Ok(c_int::from(td.cred_mut().real_uid()).into())
+    }
+
+    /// See `sys_geteuid` on the PS4 for a reference.
+    fn sys_geteuid(td: &Thread, _: &SysIn) -> Result<SysOut, SysErr> {
+        Ok(c_int::from(td.cred_mut().effective_uid()).into())
+    }
+
+    /// See `sys_getegid` on the PS4 for a reference.
+    fn sys_getegid(td: &Thread, _: &SysIn) -> Result<SysOut, SysErr> {
+        Ok(c_int::from(td.cred_mut().effective_gid()).into())
+    }
+
+    /// See `sys_getgid` on the PS4 for a reference.
+    fn sys_getgid(td: &Thread, _: &SysIn) -> Result<SysOut, SysErr> {
+        Ok(c_int::from(td.cred_mut().real_gid()).into())
+    }
+
+    /// See `sys_setgid` on the PS4 for a reference.
+    fn sys_setgid(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let gid: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let gid = Gid::new(gid).ok_or(SysErr::Raw(EINVAL))?;
+        let mut cred = td.proc().cred_mut();
+        let old = cred.clone();
+
+        // Same rules as sys_setuid.
+        if gid != old.real_gid && gid != old.effective_gid() {
+            old.priv_check(Privilege::CRED_SETGID)?;
+        }
+
+        let mut new = Ucred::clone(&old);
+
+        if gid == old.effective_gid() || old.priv_check(Privilege::CRED_SETGID).is_ok() {
+            new.real_gid = gid;
+            new.saved_gid = gid;
+        }
+
+        // TODO: Set P_SUGID.
+        new.groups[0] = gid;
+
+        Self::replace(td, &mut cred, new);
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_setegid` on the PS4 for a reference.
+    fn sys_setegid(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let gid: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let gid = Gid::new(gid).ok_or(SysErr::Raw(EINVAL))?;
+        let mut cred = td.proc().cred_mut();
+
+        if gid != cred.real_gid && gid != cred.saved_gid {
+            cred.priv_check(Privilege::CRED_SETEGID)?;
+        }
+
+        // TODO: Set P_SUGID.
+        let mut new = Ucred::clone(&cred);
+
+        new.groups[0] = gid;
+
+        Self::replace(td, &mut cred, new);
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_seteuid` on the PS4 for a reference.
+    fn sys_seteuid(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let uid: c_int = i.args[0].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let uid = Uid::new(uid).ok_or(SysErr::Raw(EINVAL))?;
+        let mut cred = td.proc().cred_mut();
+
+        if uid != cred.real_uid && uid != cred.saved_uid {
+            cred.priv_check(Privilege::CRED_SETEUID)?;
+        }
+
+        // TODO: Set P_SUGID.
+        let mut new = Ucred::clone(&cred);
+
+        new.effective_uid = uid;
+
+        Self::replace(td, &mut cred, new);
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// Set `new` as a credential of the process and the calling thread. The other threads will
+    /// pick up the new credential on their next syscall.
+    fn replace(td: &Thread, cred: &mut Arc<Self>, new: Self) {
+        let new = Arc::new(new);
+
+        *cred = new.clone();
+        *td.cred_mut() = new;
+    }
+}
+
+#[allow(dead_code)] // TODO: Remove this once all callers are ported.
+impl Ucred {
+    pub fn is_libkernel_web(&self) -> bool {
+        // TODO: Refactor this for readability.
+        let val = self.auth.paid.get().wrapping_add(0xc7ffffffeffffffc);
+        (val < 0xf) && ((0x6001 >> (val & 0x3f) & 1) != 0)
+    }
+
+    pub fn is_webprocess_webapp_or_webmas(&self) -> bool {
+        matches!(
+            self.auth.paid.get(),
+            0x380000001000000f | 0x3800000010000013
+        )
+    }
+
+    /// See `sceSblACMgrIsDiskplayeruiProcess` on the PS4 for a reference.
+    pub fn is_diskplayerui_process(&self) -> bool {
+        self.auth.paid.get() == 0x3800000010000009
+    }
+
+    /// See `sceSblACMgrIsJitCompilerProcess` on the PS4 for a reference.
+    pub fn is_jit_compiler_process(&self) -> bool {
+        let val = self.auth.caps.get(1);
+
+        if val >> 0x3e & 1 != 0 {
+            true
+        } else if val >> 0x38 & 1 != 0 || (self.auth.paid.get() >> 56) == 0x31 {
+            // TODO: The PS4 check for some additional capabilities in this case, which we don't
+            // have a reference for yet. Deny it until we know what it is.
+            false
+        } else {
+            false
+        }
+    }
+
+    /// See `sceSblACMgrIsJitApplicationProcess` on the PS4 for a reference.
+    pub fn is_jit_application_process(&self) -> bool {
+        let val = self.auth.caps.get(1);
+
+        if val >> 0x3d & 1 != 0 {
+            true
+        } else if val >> 0x38 & 1 != 0 || (self.auth.paid.get() >> 56) == 0x31 {
+            // TODO: The PS4 check for some additional capabilities in this case, which we don't
+            // have a reference for yet. Deny it until we know what it is.
+            false
+        } else {
+            false
+        }
+    }
+
+    /// See `sceSblACMgrIsVideoplayerProcess` on the PS4 for a reference.
+    pub fn is_videoplayer_process(&self) -> bool {
+        self.auth.paid.get().wrapping_add(0xc7ffffffefffffff) < 2
+    }
+
+    /// See `sceSblACMgrHasUseVideoServiceCapability` on the PS4 for a reference.
+    pub fn has_use_video_service_capability(&self) -> bool {
+        self.auth.caps.has_use_video_service()
+    }
+
+    /// See `sceSblACMgrIsWebcoreProcess` on the PS4 for a reference.
+    pub fn is_webcore_process(&self) -> bool {
+        let val = self.auth.paid.get().wrapping_add(0xc7ffffffeffffffd);
+
+        (val < 0x11) && (0x1d003 >> (val & 0x3f) & 1 != 0)
+    }
+
+    /// See `sceSblACMgrHasSceProgramAttribute` on the PS4 for a reference.
+    pub fn has_sce_program_attribute(&self) -> bool {
+        self.auth.attrs.has_sce_program_attribute()
+    }
+
+    /// See `sceSblACMgrIsNongameUcred` on the PS4 for a reference.
+    pub fn is_nongame(&self) -> bool {
+        self.auth.caps.is_nongame()
+    }
+
+    /// See `sceSblACMgrIsSystemUcred` on the PS4 for a reference.
+    pub fn is_system(&self) -> bool {
+        self.auth.caps.is_system()
+    }
+
+    pub fn is_unk1(&self) -> bool {
+        self.auth.caps.is_unk1() && self.auth.attrs.is_unk1()
+    }
+
+    pub fn is_unk2(&self) -> bool {
+        self.auth.caps.is_unk1() && self.auth.attrs.is_unk2()
+    }
+
+    pub fn unk_gc_check(&self) -> bool {
+        matches!(
+            self.auth.paid.get(),
+            0x3800000000000009 | 0x380100000000002c
+        )
+    }
+}
+
+/// Represents an error when [`Ucred::priv_check()`] fails.
+#[derive(Debug)]
+pub enum PrivilegeError {
+    NoPrivilege(Privilege),
+}
+
+impl Error for PrivilegeError {}
+
+impl Display for PrivilegeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoPrivilege(p) => write!(f, "the credential does not have {p} privilege"),
+        }
+    }
+}
+
+impl Errno for PrivilegeError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NoPrivilege(_) => EPERM,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn priv_check() {
+        let uid = Uid::new(1).unwrap();
+        let gid = Gid::new(1).unwrap();
+        let root = Ucred::new(Uid::ROOT, Uid::ROOT, vec![Gid::ROOT], AuthInfo::KERNEL);
+        let user = Ucred::new(
+            uid,
+            uid,
+            vec![gid, Gid::new(3).unwrap(), Gid::ROOT],
+            AuthInfo::KERNEL,
+        );
+
+        assert!(root.priv_check(Privilege::CRED_SETUID).is_ok());
+        assert!(user.priv_check(Privilege::CRED_SETUID).is_err());
+        assert!(user.priv_check(Privilege::SCE680).is_ok());
+        assert!(user.is_member(gid));
+        assert!(user.is_member(Gid::ROOT));
+        assert!(!user.is_member(Gid::new(2).unwrap()));
+        assert!(root.can_signal(&user).is_ok());
+        assert!(user.can_signal(&user).is_ok());
+        assert!(user.can_signal(&root).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+macro_rules! privileges {
+    (
+        $( #[$attr:meta] )*
+        pub enum $name:ident {
+            $(
+                $( #[$var_attr:meta] )*
+                $variant:ident = $value:expr,
+            )*
+        }
+    ) => {
+        $( #[$attr] )*
+        pub enum $name {
+            $(
+                $( #[$var_attr] )*
+                #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+                $variant = $value
+            ),*
+        }
+
+        impl core::fmt::Display for $name {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                match *self {
+                    $(
+                        Self::$variant => f.write_str(stringify!($variant)),
+                    )*
+                }
+            }
+        }
+    };
+}
+
+privileges! {
+    /// Privilege identifier.
+    ///
+    /// See https://github.com/freebsd/freebsd-src/blob/release/9.1.0/sys/sys/priv.h for standard
+    /// FreeBSD privileges.
+    #[repr(i32)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Privilege {
+        /// Exceed system open files limit.
+        #[allow(dead_code)]
+        MAXFILES = 3,
+        /// setuid.
+        CRED_SETUID = 50,
+        /// seteuid to !ruid and !svuid.
+        CRED_SETEUID = 51,
+        /// setgid.
+        CRED_SETGID = 52,
+        /// setgid to !rgid and !svgid.
+        CRED_SETEGID = 53,
+        /// Exempt bsd.seeothergids.
+        #[allow(dead_code)]
+        SEEOTHERGIDS = 59,
+        /// Exempt bsd.seeotheruids.
+        #[allow(dead_code)]
+        SEEOTHERUIDS = 60,
+        /// Can signal process with different credential.
+        SIGNAL_DIFFCRED = 140,
+        /// Can call setlogin.
+        #[allow(dead_code)]
+        PROC_SETLOGIN = 161,
+        /// Write a kernel.* entry.
+        SYSCTL_WRITE = 241,
+        /// Override vnode DAC read perm.
+        VFS_READ = 310,
+        /// Override vnode DAC write perm.
+        VFS_WRITE = 311,
+        /// Override vnode DAC admin perm.
+        VFS_ADMIN = 312,
+        /// Override vnode DAC exec perm.
+        VFS_EXEC = 313,
+        /// Override vnode DAC lookup perm.
+        VFS_LOOKUP = 314,
+        /// Can call chroot().
+        VFS_CHROOT = 318,
+        /// Can mount().
+        VFS_MOUNT = 333,
+        /// Can unmount().
+        VFS_UNMOUNT = 343,
+        /// Currently unknown.
+        SCE680 = 680,
+        /// Currently unknown.
+        #[allow(dead_code)]
+        SCE683 = 683,
+        /// Currently unknown.
+        #[allow(dead_code)]
+        SCE685 = 685,
+        /// Currently unknown.
+        #[allow(dead_code)]
+        SCE686 = 686,
+    }
+}
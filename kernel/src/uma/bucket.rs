@@ -1,10 +1,229 @@
+use super::{Uma, UmaZone};
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, Ordering};
+
 /// Implementation of `uma_bucket` structure.
-pub struct UmaBucket {
-    len: usize, // ub_cnt
-}
+///
+/// The memory of the bucket is allocated from one of [`BucketZones`].
+pub struct UmaBucket(NonNull<BucketMem>);
 
 impl UmaBucket {
+    /// # Safety
+    /// `mem` must be valid for reads and writes with enough space for `entries` items.
+    unsafe fn new(mem: *mut u8, entries: usize) -> Self {
+        let mem = core::ptr::slice_from_raw_parts_mut(mem.cast::<*mut u8>(), entries);
+        let mem = mem as *mut BucketMem;
+
+        (&raw mut (*mem).link).write(None);
+        (&raw mut (*mem).len).write(0);
+
+        Self(NonNull::new_unchecked(mem))
+    }
+
     pub fn len(&self) -> usize {
-        self.len
+        unsafe { self.0.as_ref().len }
+    }
+
+    /// Returns the maximum number of items this bucket can hold (AKA `ub_entries`).
+    pub fn entries(&self) -> usize {
+        unsafe { self.0.as_ref().items.len() }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == self.entries()
+    }
+
+    /// # Panics
+    /// If this bucket is full.
+    pub fn push(&mut self, item: *mut u8) {
+        let b = unsafe { self.0.as_mut() };
+
+        b.items[b.len] = item;
+        b.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<*mut u8> {
+        let b = unsafe { self.0.as_mut() };
+
+        b.len = b.len.checked_sub(1)?;
+
+        Some(b.items[b.len])
+    }
+
+    fn into_raw(self) -> *mut u8 {
+        self.0.as_ptr().cast()
+    }
+}
+
+// SAFETY: The bucket is exclusively owned by the holder.
+unsafe impl Send for UmaBucket {}
+
+/// Memory layout of [`UmaBucket`].
+#[repr(C)]
+struct BucketMem<I: ?Sized = [*mut u8]> {
+    link: Option<UmaBucket>, // ub_link
+    len: usize,              // ub_cnt
+    items: I,                // ub_bucket
+}
+
+/// Intrusive list of [`UmaBucket`].
+#[derive(Default)]
+pub struct BucketList {
+    head: Option<UmaBucket>, // lh_first
+}
+
+impl BucketList {
+    pub fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+
+    /// Returns the total number of items in all buckets.
+    pub fn items(&self) -> usize {
+        let mut n = 0;
+        let mut b = self.head.as_ref();
+
+        while let Some(v) = b {
+            let m = unsafe { v.0.as_ref() };
+
+            n += m.len;
+            b = m.link.as_ref();
+        }
+
+        n
+    }
+
+    pub fn push_front(&mut self, mut b: UmaBucket) {
+        unsafe { b.0.as_mut().link = self.head.take() };
+
+        self.head = Some(b);
+    }
+
+    pub fn pop_front(&mut self) -> Option<UmaBucket> {
+        let mut b = self.head.take()?;
+
+        self.head = unsafe { b.0.as_mut().link.take() };
+
+        Some(b)
+    }
+}
+
+/// Zones to allocate [`UmaBucket`].
+///
+/// This is a combination of `bucket_zones`, `bucket_size` and `bucketdisable`.
+pub struct BucketZones {
+    enable: AtomicBool,
+    keys: [usize; Uma::BUCKET_ZONES],
+    zones: [UmaZone; Uma::BUCKET_SIZES.len()],
+}
+
+impl BucketZones {
+    /// See `bucket_init` on the Orbis for a reference.
+    pub(super) fn new(mut f: impl FnMut(&str, usize) -> UmaZone) -> Self {
+        let mut keys = [0; Uma::BUCKET_ZONES];
+        let mut ki = 0;
+
+        for (si, size) in Uma::BUCKET_SIZES.into_iter().enumerate() {
+            while ki <= size {
+                keys[ki >> Uma::BUCKET_SHIFT] = si;
+                ki += 1 << Uma::BUCKET_SHIFT;
+            }
+        }
+
+        // Create zones. The size of each zone is the number of pointers including the header.
+        let zones = Uma::BUCKET_SIZES.map(|n| {
+            let name = match n {
+                16 => "16 Bucket",
+                32 => "32 Bucket",
+                64 => "64 Bucket",
+                128 => "128 Bucket",
+                _ => unreachable!(),
+            };
+
+            f(name, n * size_of::<*mut u8>())
+        });
+
+        Self {
+            enable: AtomicBool::new(true), // TODO: Use a proper value.
+            keys,
+            zones,
+        }
+    }
+
+    pub fn zones(&self) -> &[UmaZone] {
+        &self.zones
+    }
+
+    /// Returns [`None`] if the bucket is disabled or there are no memory available.
+    ///
+    /// See `bucket_alloc` on the Orbis for a reference.
+    pub fn alloc(&self, entries: usize) -> Option<UmaBucket> {
+        if !self.enable.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        // Get zone.
+        let zone = &self.zones[self.keys[entries.div_ceil(1 << Uma::BUCKET_SHIFT)]];
+        let mem = zone.alloc_item();
+
+        if mem.is_null() {
+            return None;
+        }
+
+        // The header has the same size as a pointers so we can use it to calculate the entries.
+        let hdr = size_of::<BucketMem<[*mut u8; 0]>>();
+        let entries = (zone.size().get() - hdr) / size_of::<*mut u8>();
+
+        Some(unsafe { UmaBucket::new(mem, entries) })
+    }
+
+    /// See `bucket_free` on the Orbis for a reference.
+    pub fn free(&self, b: UmaBucket) {
+        let zone = &self.zones[self.keys[b.entries().div_ceil(1 << Uma::BUCKET_SHIFT)]];
+
+        unsafe { zone.free_item(b.into_raw()) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::alloc::{alloc, dealloc};
+    use core::alloc::Layout;
+
+    #[test]
+    fn bucket() {
+        // The header of uma_bucket on the PS4 is 24 bytes.
+        assert_eq!(size_of::<BucketMem<[*mut u8; 0]>>(), 24);
+
+        // Setup a list with 2 buckets.
+        let layout = Layout::array::<*mut u8>(16).unwrap();
+        let m1 = unsafe { alloc(layout) };
+        let m2 = unsafe { alloc(layout) };
+        let mut b1 = unsafe { UmaBucket::new(m1, 13) };
+        let b2 = unsafe { UmaBucket::new(m2, 13) };
+        let mut list = BucketList::default();
+
+        for i in 0..13 {
+            assert!(!b1.is_full());
+            b1.push(i as *mut u8);
+        }
+
+        assert!(b1.is_full());
+        assert_eq!(b1.len(), 13);
+
+        list.push_front(b1);
+        list.push_front(b2);
+
+        // The last pushed should be the first.
+        let b2 = list.pop_front().unwrap();
+        let mut b1 = list.pop_front().unwrap();
+
+        assert!(list.is_empty());
+        assert_eq!(b2.len(), 0);
+        assert_eq!(b1.pop(), Some(12 as *mut u8));
+        assert_eq!(b1.len(), 12);
+
+        unsafe { dealloc(b1.into_raw(), layout) };
+        unsafe { dealloc(b2.into_raw(), layout) };
     }
 }
@@ -1,36 +1,60 @@
-use super::slab::RcFree;
-use super::UmaFlags;
+use super::slab::{FreeList, SlabHdr, SlabList, SlabZones};
+use super::{Uma, UmaFlags, UmaZone};
 use crate::config::{PAGE_MASK, PAGE_SHIFT, PAGE_SIZE};
-use crate::uma::slab::{Free, SlabHdr};
-use crate::uma::Uma;
+use crate::lock::{Gutex, GutexGroup};
+use crate::vm::KmemArena;
+use alloc::sync::Arc;
 use core::alloc::Layout;
 use core::cmp::{max, min};
 use core::num::NonZero;
+use core::ptr::null_mut;
 
 /// Implementation of `uma_keg` structure.
 pub struct UmaKeg {
-    size: NonZero<usize>, // uk_size
-    ipers: usize,         // uk_ipers
-    flags: UmaFlags,      // uk_flags
+    kmem: Arc<KmemArena>,          // uk_allocf + uk_freef
+    slabs: Option<Arc<SlabZones>>, // uk_slabzone
+    size: NonZero<usize>,          // uk_size
+    rsize: usize,                  // uk_rsize
+    ppera: usize,                  // uk_ppera
+    ipers: usize,                  // uk_ipers
+    pgoff: usize,                  // uk_pgoff
+    free_list: FreeList,           // UMA_FRITM_SZ + UMA_FRITMREF_SZ
+    flags: UmaFlags,               // uk_flags
+    part_slabs: Gutex<SlabList>,   // uk_part_slab
+    free_slabs: Gutex<SlabList>,   // uk_free_slab
+    full_slabs: Gutex<SlabList>,   // uk_full_slab
+    pages: Gutex<usize>,           // uk_pages
+    free: Gutex<usize>,            // uk_free
 }
 
 impl UmaKeg {
+    /// Maximum number of items per slab, which limited by the size of `us_item`.
+    pub const MAX_IPERS: usize = 256;
+
     /// `align` is the actual alignment **minus** one, which mean if you want each item to be 8
     /// bytes alignment this value will be 7.
     ///
+    /// `slabs` is required only if the keg end up using off-page slab, which never happen on the keg
+    /// with [`UmaFlags::Internal`].
+    ///
     /// See `keg_ctor` on the Orbis for a reference.
     ///
+    /// # Context safety
+    /// This function does not require a CPU context.
+    ///
     /// # Reference offsets
     /// | Version | Offset |
     /// |---------|--------|
     /// |PS4 11.00|0x13CF40|
-    pub(super) fn new(size: NonZero<usize>, align: usize, mut flags: UmaFlags) -> Self {
+    pub(super) fn new(
+        kmem: Arc<KmemArena>,
+        slabs: Option<&Arc<SlabZones>>,
+        size: NonZero<usize>,
+        align: usize,
+        mut flags: UmaFlags,
+    ) -> Self {
         if flags.has(UmaFlags::Vm) {
-            todo!()
-        }
-
-        if flags.has(UmaFlags::ZInit) {
-            todo!()
+            flags |= UmaFlags::CacheOnly;
         }
 
         if flags.has(UmaFlags::Malloc | UmaFlags::RefCnt) {
@@ -38,21 +62,14 @@ impl UmaKeg {
         }
 
         // Get header layout.
-        let hdr = Layout::new::<SlabHdr>();
-        let (mut hdr, off) = if flags.has(UmaFlags::RefCnt) {
-            hdr.extend(Layout::new::<RcFree>()).unwrap()
-        } else {
-            hdr.extend(Layout::new::<Free>()).unwrap()
-        };
-
-        hdr = hdr.pad_to_align();
+        let (free_list, hdr) = FreeList::new(flags.has(UmaFlags::RefCnt));
 
         // Get UMA_FRITM_SZ and UMA_FRITMREF_SZ.
-        let free_item = hdr.size() - off;
+        let free_item = free_list.item_size();
         let available = PAGE_SIZE.get() - hdr.size();
 
-        // Get uk_ppera and uk_ipers.
-        let (ppera, ipers) = if flags.has(UmaFlags::CacheSpread) {
+        // Get uk_rsize, uk_ppera and uk_ipers.
+        let (rsize, ppera, ipers) = if flags.has(UmaFlags::CacheSpread) {
             // Round size.
             let rsize = if (size.get() & align) == 0 {
                 size.get()
@@ -60,10 +77,10 @@ impl UmaKeg {
                 (size.get() & !align) + align + 1
             };
 
-            // Get uk_rsize.
+            // Make sure the item size is an odd multiple of alignment so each item will start on a
+            // different cache line.
             let align = align + 1;
             let rsize = if (rsize & align) == 0 {
-                // TODO: What is this?
                 rsize + align
             } else {
                 rsize
@@ -76,85 +93,101 @@ impl UmaKeg {
             // Get uk_ipers.
             let ipers = (ppera * PAGE_SIZE.get() + (rsize - size.get())) / rsize;
 
-            (ppera, ipers)
-        } else {
-            // TODO: Not sure why we need space at least for 2 free item?
-            if (size.get() + free_item) > available {
-                // TODO: Set uk_ppera and uk_rsize.
-                if !flags.has(UmaFlags::Internal) {
-                    flags |= UmaFlags::Offpage;
-
-                    if !flags.has(UmaFlags::VToSlab) {
-                        flags |= UmaFlags::Hash;
-                    }
-                }
+            flags |= UmaFlags::Offpage | UmaFlags::VToSlab;
 
-                // Get uk_ppera.
-                let mut ppera = size.get() >> PAGE_SHIFT;
+            (rsize, ppera, ipers)
+        } else if (size.get() + free_item) > available {
+            // The item is too large to fit in the same page as the slab header.
+            if !flags.has(UmaFlags::Internal) {
+                flags |= UmaFlags::Offpage;
 
-                if size.get() > (size.get() & !PAGE_MASK.get()) {
-                    ppera += 1;
+                if !flags.has(UmaFlags::VToSlab) {
+                    flags |= UmaFlags::Hash;
                 }
+            }
 
-                (ppera, 1)
-            } else {
-                // Get uk_rsize.
-                let rsize = max(size, Uma::SMALLEST_UNIT);
-                let rsize = if (align & rsize.get()) == 0 {
-                    rsize.get()
-                } else {
-                    // Size is not multiple of alignment, align up.
-                    align + 1 + (!align & rsize.get())
-                };
-
-                // Get uk_ipers.
-                let ipers = available / (rsize + free_item);
-
-                // TODO: Verify if this valid for PAGE_SIZE < 0x4000.
-                if !flags.has(UmaFlags::Internal | UmaFlags::CacheOnly)
-                    && (available % (rsize + free_item)) >= Uma::MAX_WASTE.get()
-                    && (PAGE_SIZE.get() / rsize) > ipers
-                {
-                    todo!()
-                }
+            // Get uk_ppera.
+            let mut ppera = size.get() >> PAGE_SHIFT;
 
-                (1, ipers)
+            if size.get() > (size.get() & !PAGE_MASK.get()) {
+                ppera += 1;
             }
-        };
 
-        if flags.has(UmaFlags::Offpage) {
-            if flags.has(UmaFlags::RefCnt) {
-                // TODO: Set uk_slabzone to slabrefzone.
+            (size.get(), ppera, 1)
+        } else {
+            // Get uk_rsize.
+            let rsize = max(size, Uma::SMALLEST_UNIT);
+            let rsize = if (align & rsize.get()) == 0 {
+                rsize.get()
             } else {
-                // TODO: Set uk_slabzone to slabzone.
+                // Size is not multiple of alignment, align up.
+                align + 1 + (!align & rsize.get())
+            };
+
+            // Get uk_ipers.
+            let mut ipers = available / (rsize + free_item);
+
+            // Move the slab header to off-page if it waste too much space.
+            if !flags.has(UmaFlags::Internal | UmaFlags::CacheOnly)
+                && (available % (rsize + free_item)) >= Uma::MAX_WASTE.get()
+                && (PAGE_SIZE.get() / rsize) > ipers
+            {
+                ipers = PAGE_SIZE.get() / rsize;
+                flags |= UmaFlags::Offpage;
+
+                if !flags.has(UmaFlags::VToSlab) {
+                    flags |= UmaFlags::Hash;
+                }
             }
-        }
 
-        if ppera == 1 {
-            // TODO: Set uk_allocf and uk_freef.
-        }
+            (rsize, 1, ipers)
+        };
 
-        if flags.has(UmaFlags::MtxClass) {
-            todo!()
-        }
+        assert!(ipers != 0 && ipers <= Self::MAX_IPERS);
+
+        let slabs = if flags.has(UmaFlags::Offpage) {
+            slabs.cloned()
+        } else {
+            None
+        };
+
+        // We don't need uk_allocf and uk_freef since we always allocate from kmem.
+        let mut pgoff = 0;
 
         if !flags.has(UmaFlags::Offpage) {
             let space = ppera * PAGE_SIZE.get();
-            let pgoff = (space - hdr.size()) - ipers * free_item;
+            let hdr = free_list.header_size(ipers);
 
-            // TODO: What is this?
-            if space < pgoff + hdr.size() + ipers * free_item {
+            if space < rsize * ipers + hdr {
                 panic!("UMA slab won't fit");
             }
-        }
 
-        if flags.has(UmaFlags::Hash) {
-            todo!()
+            pgoff = space - hdr;
         }
 
+        // The Orbis use a hash table to lookup the slab for the zone that has UMA_ZONE_HASH. We use
+        // kmem to lookup the slab for both UMA_ZONE_HASH and UMA_ZONE_VTOSLAB since all slabs are
+        // allocated from it.
         // TODO: Add uk_zones.
         // TODO: Add uma_kegs.
-        Self { size, ipers, flags }
+        let gg = GutexGroup::new();
+
+        Self {
+            kmem,
+            slabs,
+            size,
+            rsize,
+            ppera,
+            ipers,
+            pgoff,
+            free_list,
+            flags,
+            part_slabs: gg.clone().spawn_default(),
+            free_slabs: gg.clone().spawn_default(),
+            full_slabs: gg.clone().spawn_default(),
+            pages: gg.clone().spawn_default(),
+            free: gg.spawn(0),
+        }
     }
 
     pub fn size(&self) -> NonZero<usize> {
@@ -168,4 +201,278 @@ impl UmaKeg {
     pub fn item_per_slab(&self) -> usize {
         self.ipers
     }
+
+    /// Returns the number of pages currently allocated by this keg.
+    pub fn pages(&self) -> usize {
+        *self.pages.write()
+    }
+
+    /// Returns the number of free items in all slabs.
+    pub fn free_items(&self) -> usize {
+        *self.free.write()
+    }
+
+    /// Returns null if there are no memory available.
+    ///
+    /// This is a combination of `keg_fetch_slab` and `slab_alloc_item`.
+    pub fn alloc_item(&self) -> *mut u8 {
+        let mut parts = self.part_slabs.write();
+        let mut slab = parts.first();
+
+        if slab.is_null() {
+            // Try a free slab first.
+            slab = self.free_slabs.write().pop();
+
+            if slab.is_null() {
+                slab = self.alloc_slab();
+
+                if slab.is_null() {
+                    return null_mut();
+                }
+            }
+
+            unsafe { parts.insert_head(slab) };
+        }
+
+        // Allocate item.
+        let item = unsafe { SlabHdr::alloc_item(slab, self.free_list, self.rsize) };
+
+        *self.free.write() -= 1;
+
+        // Move the slab to full list if there are no free item.
+        if unsafe { (*slab).free_count() } == 0 {
+            unsafe { parts.remove(slab) };
+            unsafe { self.full_slabs.write().insert_head(slab) };
+        }
+
+        item
+    }
+
+    /// Put `item` back to its slab.
+    ///
+    /// This is the keg part of `zone_free_item`.
+    ///
+    /// # Safety
+    /// `item` must be allocated from this keg.
+    pub unsafe fn free_item(&self, item: *mut u8) {
+        let slab = self.slab(item);
+        let mut parts = self.part_slabs.write();
+
+        // Move the slab to the appropriate list.
+        if (*slab).free_count() + 1 == self.ipers {
+            if self.ipers != 1 {
+                parts.remove(slab);
+            } else {
+                self.full_slabs.write().remove(slab);
+            }
+
+            self.free_slabs.write().insert_head(slab);
+        } else if (*slab).free_count() == 0 {
+            self.full_slabs.write().remove(slab);
+            parts.insert_head(slab);
+        }
+
+        // Put the item back.
+        SlabHdr::free_item(slab, self.free_list, self.rsize, item);
+
+        *self.free.write() += 1;
+    }
+
+    /// Release all free slabs back to the system.
+    ///
+    /// See `keg_drain` on the Orbis for a reference.
+    pub fn drain(&self) {
+        let mut frees = self.free_slabs.write();
+
+        loop {
+            let slab = frees.pop();
+
+            if slab.is_null() {
+                break;
+            }
+
+            *self.pages.write() -= self.ppera;
+            *self.free.write() -= self.ipers;
+
+            unsafe { self.free_slab(slab) };
+        }
+    }
+
+    /// Returns null if there are no memory available.
+    ///
+    /// See `keg_alloc_slab` on the Orbis for a reference.
+    fn alloc_slab(&self) -> *mut SlabHdr {
+        // Allocate slab header.
+        let hdr = if self.flags.has(UmaFlags::Offpage) {
+            let hdr = self.slab_zone().alloc_item();
+
+            if hdr.is_null() {
+                return null_mut();
+            }
+
+            hdr
+        } else {
+            null_mut()
+        };
+
+        // Allocate pages.
+        let mem = self.kmem.alloc(self.slab_layout());
+
+        if mem.is_null() {
+            if !hdr.is_null() {
+                unsafe { self.slab_zone().free_item(hdr) };
+            }
+
+            return null_mut();
+        }
+
+        // Setup slab.
+        let hdr = if hdr.is_null() {
+            unsafe { mem.add(self.pgoff).cast::<SlabHdr>() }
+        } else {
+            hdr.cast()
+        };
+
+        unsafe { SlabHdr::init(hdr, mem, self.ipers, self.free_list) };
+
+        if self.flags.has(UmaFlags::VToSlab | UmaFlags::Hash) {
+            self.kmem.set_slab(mem, self.ppera, hdr.cast());
+        }
+
+        // Initialize items. This is uk_init.
+        if self.flags.has(UmaFlags::ZInit) {
+            unsafe { mem.write_bytes(0, self.items_len()) };
+        }
+
+        *self.pages.write() += self.ppera;
+        *self.free.write() += self.ipers;
+
+        hdr
+    }
+
+    /// # Safety
+    /// `slab` must be allocated by [`Self::alloc_slab()`] and it must not in any lists.
+    unsafe fn free_slab(&self, slab: *mut SlabHdr) {
+        let mem = (*slab).data();
+
+        if self.flags.has(UmaFlags::VToSlab | UmaFlags::Hash) {
+            self.kmem.set_slab(mem, self.ppera, null_mut());
+        }
+
+        if self.flags.has(UmaFlags::Offpage) {
+            self.slab_zone().free_item(slab.cast());
+        }
+
+        self.kmem.free(mem, self.slab_layout());
+    }
+
+    fn slab_zone(&self) -> &UmaZone {
+        self.slabs
+            .as_ref()
+            .expect("off-page slab requires slab zones")
+            .get(self.flags)
+    }
+
+    /// See `vtoslab` on the Orbis for a reference.
+    fn slab(&self, item: *mut u8) -> *mut SlabHdr {
+        if self.flags.has(UmaFlags::VToSlab | UmaFlags::Hash) {
+            self.kmem.slab(item).cast()
+        } else {
+            let mem = item as usize & !PAGE_MASK.get();
+
+            (mem + self.pgoff) as *mut SlabHdr
+        }
+    }
+
+    /// Returns the number of bytes from the start of the slab to the end of the last item.
+    ///
+    /// The padding after the last item is not included since it may be past the end of the slab
+    /// with [`UmaFlags::CacheSpread`].
+    fn items_len(&self) -> usize {
+        self.rsize * (self.ipers - 1) + self.size.get()
+    }
+
+    fn slab_layout(&self) -> Layout {
+        Layout::from_size_align(self.ppera * PAGE_SIZE.get(), PAGE_SIZE.get()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+
+    #[test]
+    fn layout() {
+        // Small item.
+        let keg = new(16, 7, UmaFlags::zeroed());
+
+        assert!(!keg.flags().has(UmaFlags::Offpage));
+        assert_eq!(keg.ppera, 1);
+        assert_eq!(keg.rsize, Uma::SMALLEST_UNIT.get());
+        assert!(keg.pgoff + keg.free_list.header_size(keg.ipers) <= PAGE_SIZE.get());
+        assert!(keg.rsize * keg.ipers <= keg.pgoff);
+        assert!(keg.items_len() <= keg.pgoff);
+
+        // Unaligned item.
+        let keg = new(Uma::SMALLEST_UNIT.get() + 1, 7, UmaFlags::zeroed());
+
+        assert_eq!(keg.rsize, Uma::SMALLEST_UNIT.get() + 8);
+
+        // Large item.
+        let keg = new(PAGE_SIZE.get() * 2 + 1, 7, UmaFlags::zeroed());
+
+        assert!(keg.flags().has_all(UmaFlags::Offpage | UmaFlags::Hash));
+        assert_eq!(keg.ppera, 3);
+        assert_eq!(keg.ipers, 1);
+
+        // Large malloc item.
+        let keg = new(PAGE_SIZE.get(), 7, UmaFlags::Malloc);
+
+        assert!(keg.flags().has_all(UmaFlags::Offpage | UmaFlags::VToSlab));
+        assert!(!keg.flags().has(UmaFlags::Hash));
+        assert_eq!(keg.ppera, 1);
+    }
+
+    #[test]
+    fn waste() {
+        // An item that waste too much space should use off-page slab.
+        let size = PAGE_SIZE.get() / 4;
+        let keg = new(size, 7, UmaFlags::zeroed());
+
+        assert!(keg.flags().has_all(UmaFlags::Offpage | UmaFlags::Hash));
+        assert_eq!(keg.ipers, PAGE_SIZE.get() / keg.rsize);
+
+        // Except internal keg.
+        let keg = new(size, 7, UmaFlags::Internal);
+
+        assert!(!keg.flags().has(UmaFlags::Offpage));
+    }
+
+    #[test]
+    fn cache_spread() {
+        let keg = new(128, 63, UmaFlags::CacheSpread);
+
+        assert!(keg.flags().has_all(UmaFlags::Offpage | UmaFlags::VToSlab));
+        assert_eq!(keg.rsize, 192);
+        assert_eq!(keg.rsize % 64, 0);
+        assert_eq!((keg.rsize / 64) % 2, 1);
+        assert!(keg.ppera >= 1);
+        assert!(keg.ipers * keg.rsize <= keg.ppera * PAGE_SIZE.get() + (keg.rsize - 128));
+        assert!(keg.items_len() <= keg.ppera * PAGE_SIZE.get());
+    }
+
+    #[test]
+    fn vm() {
+        let keg = new(64, 7, UmaFlags::Vm);
+
+        assert!(keg.flags().has(UmaFlags::CacheOnly));
+    }
+
+    fn new(size: usize, align: usize, flags: UmaFlags) -> UmaKeg {
+        let mem = Box::leak(Box::new([0u8; 0x10000]));
+        let kmem = Arc::new(unsafe { KmemArena::new(mem) });
+
+        UmaKeg::new(kmem, None, NonZero::new(size).unwrap(), align, flags)
+    }
 }
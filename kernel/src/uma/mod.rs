@@ -1,10 +1,16 @@
 pub use self::zone::*;
 
+use self::bucket::BucketZones;
+use self::keg::UmaKeg;
+use self::slab::SlabZones;
 use crate::config::PAGE_SIZE;
+use crate::lock::{Gutex, GutexGroup};
+use crate::vm::KmemArena;
 use alloc::string::String;
-use alloc::sync::Arc;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::alloc::Layout;
 use core::num::NonZero;
-use core::sync::atomic::AtomicBool;
 use macros::bitflag;
 
 mod bucket;
@@ -14,7 +20,10 @@ mod zone;
 
 /// Implementation of UMA system.
 pub struct Uma {
-    bucket_enable: Arc<AtomicBool>,
+    kmem: Arc<KmemArena>,
+    slabs: Arc<SlabZones>,            // slabzone + slabrefzone
+    buckets: Arc<BucketZones>,        // bucket_zones
+    zones: Gutex<Vec<Weak<UmaZone>>>, // uma_kegs
 }
 
 impl Uma {
@@ -30,26 +39,35 @@ impl Uma {
     /// `bucket_zones`.
     const BUCKET_SIZES: [usize; 4] = [16, 32, 64, 128];
 
+    /// `UMA_ALIGN_PTR`.
+    const ALIGN_PTR: usize = size_of::<*mut u8>() - 1;
+
+    /// `uma_align_cache`.
+    const ALIGN_CACHE: usize = 63;
+
     /// See `uma_startup` on the Orbis for a reference.
     ///
     /// # Reference offsets
     /// | Version | Offset |
     /// |---------|--------|
     /// |PS4 11.00|0x13CA70|
-    pub fn new() -> Arc<Self> {
-        let bucket_enable = Arc::new(AtomicBool::new(true)); // TODO: Use a proper value.
-        let mut bucket_keys = [0; Self::BUCKET_ZONES];
-        let mut ki = 0;
+    pub fn new(kmem: Arc<KmemArena>) -> Arc<Self> {
+        // Create zones for off-page slab.
+        let slabs = Arc::new(SlabZones::new(UmaKeg::MAX_IPERS, |name, size| {
+            Self::create_internal(&kmem, name, size, UmaFlags::zeroed())
+        }));
 
         // Create bucket zones.
-        for (si, size) in Self::BUCKET_SIZES.into_iter().enumerate() {
-            while ki <= size {
-                bucket_keys[ki >> Self::BUCKET_SHIFT] = si;
-                ki += 1 << Self::BUCKET_SHIFT;
-            }
-        }
+        let buckets = Arc::new(BucketZones::new(|name, size| {
+            Self::create_internal(&kmem, name, size, UmaFlags::Bucket)
+        }));
 
-        Arc::new(Self { bucket_enable })
+        Arc::new(Self {
+            kmem,
+            slabs,
+            buckets,
+            zones: GutexGroup::new().spawn_default(),
+        })
     }
 
     /// See `uma_zcreate` on the Orbis for a reference.
@@ -64,10 +82,87 @@ impl Uma {
         size: NonZero<usize>,
         align: Option<usize>,
         flags: UmaFlags,
-    ) -> UmaZone {
-        // The Orbis will allocate a new zone from masterzone_z. We choose to remove this since it
-        // does not idomatic to Rust, which mean our uma_zone itself can live on the stack.
-        UmaZone::new(self.bucket_enable.clone(), name, None, size, align, flags)
+    ) -> Arc<UmaZone> {
+        // The Orbis will allocate a new zone from masterzone_z. We use Arc instead so we can keep
+        // track of it without owning it.
+        let align = align.unwrap_or(Self::ALIGN_CACHE);
+        let keg = UmaKeg::new(self.kmem.clone(), Some(&self.slabs), size, align, flags);
+        let buckets = if flags.has(UmaFlags::Internal) {
+            None
+        } else {
+            Some(self.buckets.clone())
+        };
+
+        let zone = Arc::new(UmaZone::new(buckets, name, keg, flags));
+        let mut zones = self.zones.write();
+
+        zones.retain(|z| z.strong_count() != 0);
+        zones.push(Arc::downgrade(&zone));
+
+        zone
+    }
+
+    /// Invoke `f` for each zone, including the zones that owned by UMA.
+    ///
+    /// See `zone_foreach` on the Orbis for a reference.
+    pub fn for_each_zone(&self, mut f: impl FnMut(&UmaZone)) {
+        // We need to collect the zones first since f may cause a zone to get dropped.
+        let zones: Vec<Arc<UmaZone>> = self
+            .zones
+            .write()
+            .iter()
+            .filter_map(|z| z.upgrade())
+            .collect();
+
+        for z in zones {
+            f(&z);
+        }
+
+        for z in self.slabs.zones() {
+            f(z);
+        }
+
+        for z in self.buckets.zones() {
+            f(z);
+        }
+    }
+
+    /// Create a zone for UMA itself. The zone never use a bucket and off-page slab so it does not
+    /// depend on any other zones.
+    fn create_internal(kmem: &Arc<KmemArena>, name: &str, size: usize, flags: UmaFlags) -> UmaZone {
+        let size = NonZero::new(size).unwrap();
+        let flags = flags | UmaFlags::Internal;
+        let keg = UmaKeg::new(kmem.clone(), None, size, Self::ALIGN_PTR, flags);
+
+        UmaZone::new(None, name, keg, flags)
+    }
+
+    /// Release all unused memory on all zones.
+    ///
+    /// See `uma_reclaim` on the Orbis for a reference.
+    pub fn reclaim(&self) {
+        // The zones that owned by UMA are visited last so we can free the slabs and buckets that
+        // was released by the other zones.
+        self.for_each_zone(|z| z.drain());
+    }
+
+    /// Allocate a memory that too large to fit in any zone. Returns null on failure.
+    ///
+    /// The Orbis also allocate a slab from `slabzone` to keep track the size of allocation. We don't
+    /// need it since the caller always know the size of allocation when freeing it.
+    ///
+    /// See `uma_large_malloc` on the Orbis for a reference.
+    pub fn large_alloc(&self, layout: Layout) -> *mut u8 {
+        self.kmem.alloc(layout)
+    }
+
+    /// See `uma_large_free` on the Orbis for a reference.
+    ///
+    /// # Safety
+    /// `ptr` must be obtained with [`Self::large_alloc()`] and `layout` must be the same one that
+    /// was passed to that method.
+    pub unsafe fn large_free(&self, ptr: *mut u8, layout: Layout) {
+        self.kmem.free(ptr, layout);
     }
 }
 
@@ -86,8 +181,6 @@ pub enum UmaFlags {
     Vm = 0x80,
     /// `UMA_ZONE_HASH`.
     Hash = 0x100,
-    /// `UMA_ZONE_SECONDARY`.
-    Secondary = 0x200,
     /// `UMA_ZONE_REFCNT`.
     RefCnt = 0x400,
     /// `UMA_ZONE_MAXBUCKET`.
@@ -96,6 +189,8 @@ pub enum UmaFlags {
     CacheSpread = 0x1000,
     /// `UMA_ZONE_VTOSLAB`.
     VToSlab = 0x2000,
+    /// `UMA_ZFLAG_BUCKET`.
+    Bucket = 0x2000000,
     /// `UMA_ZFLAG_INTERNAL`.
     Internal = 0x20000000,
     /// `UMA_ZFLAG_CACHEONLY`.
@@ -1,13 +1,317 @@
+use super::{UmaFlags, UmaZone};
+use core::alloc::Layout;
+use core::ptr::null_mut;
+
 /// Implementation of `uma_slab_head`, `uma_slab` and `uma_slab_refcnt`.
 ///
-/// We use slightly different mechanism here but has the same memory layout.
+/// We use slightly different mechanism here but has the same memory layout. The free list
+/// immediately follow this header with each item is either [`Free`] or [`RcFree`], depend on the
+/// keg.
 #[repr(C)]
-pub struct SlabHdr {}
+pub struct SlabHdr {
+    next: *mut Self, // us_link.le_next
+    prev: *mut Self, // us_link.le_prev
+    data: *mut u8,   // us_data
+    free_count: u16, // us_freecount
+    first_free: u8,  // us_firstfree
+}
+
+impl SlabHdr {
+    /// # Safety
+    /// `hdr` must be valid for writes with enough space for `ipers` items of free list. `data` must
+    /// be valid for reads and writes for `ipers * rsize` bytes.
+    pub unsafe fn init(hdr: *mut Self, data: *mut u8, ipers: usize, fl: FreeList) {
+        hdr.write(Self {
+            next: null_mut(),
+            prev: null_mut(),
+            data,
+            free_count: ipers.try_into().unwrap(),
+            first_free: 0,
+        });
+
+        // Chain all items together. The next of the last item will be truncated when ipers is 256
+        // but it does not matter since it will never be used.
+        for i in 0..ipers {
+            *Self::next_free(hdr, fl, i) = (i + 1) as u8;
+        }
+    }
+
+    pub fn data(&self) -> *mut u8 {
+        self.data
+    }
+
+    pub fn free_count(&self) -> usize {
+        self.free_count.into()
+    }
+
+    /// Returns null if there are no free item.
+    ///
+    /// See `slab_alloc_item` on the Orbis for a reference.
+    ///
+    /// # Safety
+    /// `hdr` must be initialized with the same `fl` and `rsize` must be the same one that used to
+    /// layout the slab.
+    pub unsafe fn alloc_item(hdr: *mut Self, fl: FreeList, rsize: usize) -> *mut u8 {
+        let s = &mut *hdr;
+
+        if s.free_count == 0 {
+            return null_mut();
+        }
+
+        let i = usize::from(s.first_free);
+        let item = s.data.add(rsize * i);
+
+        s.first_free = *Self::next_free(hdr, fl, i);
+        s.free_count -= 1;
+
+        item
+    }
+
+    /// # Safety
+    /// `hdr` must be initialized with the same `fl`, `rsize` must be the same one that used to
+    /// layout the slab and `item` must be allocated from this slab.
+    pub unsafe fn free_item(hdr: *mut Self, fl: FreeList, rsize: usize, item: *mut u8) {
+        let i = (item as usize - (*hdr).data as usize) / rsize;
+
+        *Self::next_free(hdr, fl, i) = (*hdr).first_free;
+
+        (*hdr).first_free = i as u8;
+        (*hdr).free_count += 1;
+    }
+
+    /// Returns a pointer to `us_item` of the item at `i`.
+    unsafe fn next_free(hdr: *mut Self, fl: FreeList, i: usize) -> *mut u8 {
+        hdr.cast::<u8>().add(fl.off + fl.size * i)
+    }
+}
 
 /// Item in the slab to represents `uma_slab` structure.
+#[allow(dead_code)] // Only the layout is being used.
 #[repr(C)]
-pub struct Free {}
+pub struct Free {
+    item: u8, // us_item
+}
 
 /// Item in the slab to represents `uma_slab_refcnt` structure.
+#[allow(dead_code)] // Only the layout is being used.
 #[repr(C)]
-pub struct RcFree {}
+pub struct RcFree {
+    item: u8,    // us_item
+    refcnt: u32, // us_refcnt
+}
+
+/// Layout of the free list in the slab.
+#[derive(Clone, Copy)]
+pub struct FreeList {
+    off: usize,
+    size: usize,
+}
+
+impl FreeList {
+    /// Returns the layout of the free list and the layout of the slab header with one item.
+    pub fn new(refcnt: bool) -> (Self, Layout) {
+        let hdr = Layout::new::<SlabHdr>();
+        let item = if refcnt {
+            Layout::new::<RcFree>()
+        } else {
+            Layout::new::<Free>()
+        };
+
+        let (hdr, off) = hdr.extend(item).unwrap();
+        let fl = Self {
+            off,
+            size: item.pad_to_align().size(),
+        };
+
+        (fl, hdr.pad_to_align())
+    }
+
+    /// Size of a free item (AKA `UMA_FRITM_SZ` or `UMA_FRITMREF_SZ`).
+    pub fn item_size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the size of slab header with `ipers` items.
+    pub fn header_size(&self, ipers: usize) -> usize {
+        (self.off + self.size * ipers).next_multiple_of(align_of::<SlabHdr>())
+    }
+}
+
+/// Intrusive list of [`SlabHdr`].
+pub struct SlabList {
+    head: *mut SlabHdr, // lh_first
+}
+
+impl SlabList {
+    pub fn first(&self) -> *mut SlabHdr {
+        self.head
+    }
+
+    /// # Safety
+    /// `s` must not be in any list.
+    pub unsafe fn insert_head(&mut self, s: *mut SlabHdr) {
+        (*s).next = self.head;
+        (*s).prev = null_mut();
+
+        if !self.head.is_null() {
+            (*self.head).prev = s;
+        }
+
+        self.head = s;
+    }
+
+    /// # Safety
+    /// `s` must be in this list.
+    pub unsafe fn remove(&mut self, s: *mut SlabHdr) {
+        let next = (*s).next;
+        let prev = (*s).prev;
+
+        if prev.is_null() {
+            self.head = next;
+        } else {
+            (*prev).next = next;
+        }
+
+        if !next.is_null() {
+            (*next).prev = prev;
+        }
+
+        (*s).next = null_mut();
+        (*s).prev = null_mut();
+    }
+
+    /// Remove the first slab from the list.
+    pub fn pop(&mut self) -> *mut SlabHdr {
+        let s = self.head;
+
+        if !s.is_null() {
+            unsafe { self.remove(s) };
+        }
+
+        s
+    }
+}
+
+impl Default for SlabList {
+    fn default() -> Self {
+        Self { head: null_mut() }
+    }
+}
+
+// SAFETY: The slabs is owned by the keg, which protect the list with a lock.
+unsafe impl Send for SlabList {}
+
+/// Zones to allocate off-page slab header (AKA `slabzone` and `slabrefzone`).
+pub struct SlabZones {
+    slab: UmaZone,
+    rc: UmaZone,
+}
+
+impl SlabZones {
+    /// `f` will be called with the name and size of each zone.
+    ///
+    /// The Orbis size the zones for the number of items that fit in a slab without exceeding
+    /// `UMA_MAX_WASTE`. We use the maximum number of items per slab instead since our off-page slab
+    /// is not limited to that.
+    pub(super) fn new(ipers: usize, mut f: impl FnMut(&str, usize) -> UmaZone) -> Self {
+        let (slab, _) = FreeList::new(false);
+        let (rc, _) = FreeList::new(true);
+
+        Self {
+            slab: f("UMA Slabs", slab.header_size(ipers)),
+            rc: f("UMA RCntSlabs", rc.header_size(ipers)),
+        }
+    }
+
+    pub fn zones(&self) -> [&UmaZone; 2] {
+        [&self.slab, &self.rc]
+    }
+
+    /// Returns the zone for the keg with `flags`.
+    pub fn get(&self, flags: UmaFlags) -> &UmaZone {
+        if flags.has(UmaFlags::RefCnt) {
+            &self.rc
+        } else {
+            &self.slab
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::alloc::{alloc, dealloc};
+
+    #[test]
+    fn free_list() {
+        let (fl, hdr) = FreeList::new(false);
+
+        assert_eq!(fl.item_size(), 1);
+        assert_eq!(fl.header_size(0), size_of::<SlabHdr>());
+        assert_eq!(fl.header_size(1), hdr.size());
+
+        let (fl, _) = FreeList::new(true);
+
+        assert_eq!(fl.item_size(), 8);
+    }
+
+    #[test]
+    fn alloc_free() {
+        // Setup slab.
+        let (fl, _) = FreeList::new(false);
+        let rsize = 32;
+        let ipers = 4;
+        let layout = Layout::from_size_align(rsize * ipers + fl.header_size(ipers), 8).unwrap();
+        let mem = unsafe { alloc(layout) };
+        let hdr = unsafe { mem.add(rsize * ipers).cast::<SlabHdr>() };
+
+        unsafe { SlabHdr::init(hdr, mem, ipers, fl) };
+
+        // Allocate all items.
+        let items: [*mut u8; 4] =
+            core::array::from_fn(|_| unsafe { SlabHdr::alloc_item(hdr, fl, rsize) });
+
+        assert_eq!(
+            items,
+            core::array::from_fn(|i| unsafe { mem.add(i * rsize) })
+        );
+        assert_eq!(unsafe { (*hdr).free_count() }, 0);
+        assert!(unsafe { SlabHdr::alloc_item(hdr, fl, rsize) }.is_null());
+
+        // Free some items. The last freed item should be allocated first.
+        unsafe { SlabHdr::free_item(hdr, fl, rsize, items[1]) };
+        unsafe { SlabHdr::free_item(hdr, fl, rsize, items[3]) };
+
+        assert_eq!(unsafe { (*hdr).free_count() }, 2);
+        assert_eq!(unsafe { SlabHdr::alloc_item(hdr, fl, rsize) }, items[3]);
+        assert_eq!(unsafe { SlabHdr::alloc_item(hdr, fl, rsize) }, items[1]);
+        assert!(unsafe { SlabHdr::alloc_item(hdr, fl, rsize) }.is_null());
+
+        unsafe { dealloc(mem, layout) };
+    }
+
+    #[test]
+    fn list() {
+        let (fl, hdr) = FreeList::new(false);
+        let layout = Layout::from_size_align(hdr.size() * 3, hdr.align()).unwrap();
+        let mem = unsafe { alloc(layout) };
+        let slabs: [*mut SlabHdr; 3] =
+            core::array::from_fn(|i| unsafe { mem.add(i * hdr.size()).cast() });
+        let mut list = SlabList::default();
+
+        for s in slabs {
+            unsafe { SlabHdr::init(s, null_mut(), 0, fl) };
+            unsafe { list.insert_head(s) };
+        }
+
+        assert_eq!(list.first(), slabs[2]);
+
+        unsafe { list.remove(slabs[1]) };
+
+        assert_eq!(list.pop(), slabs[2]);
+        assert_eq!(list.pop(), slabs[0]);
+        assert!(list.pop().is_null());
+
+        unsafe { dealloc(mem, layout) };
+    }
+}
@@ -1,63 +1,54 @@
-use super::bucket::UmaBucket;
+use super::bucket::{BucketList, BucketZones, UmaBucket};
 use super::keg::UmaKeg;
 use super::{Uma, UmaFlags};
 use crate::context::{current_thread, CpuLocal};
 use crate::lock::{Gutex, GutexGroup, GutexWrite};
-use alloc::collections::VecDeque;
 use alloc::string::String;
 use alloc::sync::Arc;
 use core::cell::RefCell;
 use core::cmp::min;
 use core::num::NonZero;
 use core::ops::DerefMut;
-use core::ptr::null_mut;
-use core::sync::atomic::{AtomicBool, Ordering};
 
 /// Implementation of `uma_zone` structure.
 pub struct UmaZone {
-    bucket_enable: Arc<AtomicBool>,
+    buckets: Option<Arc<BucketZones>>,
     ty: ZoneType,
-    size: NonZero<usize>,                     // uz_size
-    caches: CpuLocal<RefCell<UmaCache>>,      // uz_cpu
-    full_buckets: Gutex<VecDeque<UmaBucket>>, // uz_full_bucket
-    free_buckets: Gutex<VecDeque<UmaBucket>>, // uz_free_bucket
-    alloc_count: Gutex<u64>,                  // uz_allocs
-    free_count: Gutex<u64>,                   // uz_frees
-    count: Gutex<usize>,                      // uz_count
-    flags: UmaFlags,                          // uz_flags
+    name: String,                        // uz_name
+    keg: UmaKeg,                         // uz_klink
+    size: NonZero<usize>,                // uz_size
+    caches: CpuLocal<RefCell<UmaCache>>, // uz_cpu
+    full_buckets: Gutex<BucketList>,     // uz_full_bucket
+    free_buckets: Gutex<BucketList>,     // uz_free_bucket
+    alloc_count: Gutex<u64>,             // uz_allocs
+    free_count: Gutex<u64>,              // uz_frees
+    fail_count: Gutex<u64>,              // uz_fails
+    count: Gutex<usize>,                 // uz_count
+    flags: UmaFlags,                     // uz_flags
 }
 
 impl UmaZone {
-    const ALIGN_CACHE: usize = 63; // uma_align_cache
-
+    /// `buckets` must be [`None`] if `keg` has [`UmaFlags::Internal`].
+    ///
     /// See `zone_ctor` on Orbis for a reference.
     ///
+    /// # Context safety
+    /// This function does not require a CPU context.
+    ///
     /// # Reference offsets
     /// | Version | Offset |
     /// |---------|--------|
     /// |PS4 11.00|0x13D490|
     pub(super) fn new(
-        bucket_enable: Arc<AtomicBool>,
+        buckets: Option<Arc<BucketZones>>,
         name: impl Into<String>,
-        keg: Option<UmaKeg>,
-        size: NonZero<usize>,
-        align: Option<usize>,
+        keg: UmaKeg,
         flags: UmaFlags,
     ) -> Self {
+        // We use a different approach here to make it idiomatic to Rust. On Orbis it will construct
+        // a keg here if it is not passed from the caller. We require the caller to always construct
+        // it instead.
         let name = name.into();
-        let (keg, flags) = if flags.has(UmaFlags::Secondary) {
-            todo!()
-        } else {
-            // We use a different approach here to make it idiomatic to Rust. On Orbis it will
-            // construct a keg here if it is passed from the caller. If not it will allocate a new
-            // keg from masterzone_k.
-            let keg = match keg {
-                Some(v) => v,
-                None => UmaKeg::new(size, align.unwrap_or(Self::ALIGN_CACHE), flags),
-            };
-
-            (keg, UmaFlags::zeroed())
-        };
 
         // Get type and uz_count.
         let mut ty = ZoneType::Other;
@@ -96,26 +87,60 @@ impl UmaZone {
         }
 
         // Construct uma_zone.
+        let inherit = UmaFlags::Offpage
+            | UmaFlags::Malloc
+            | UmaFlags::Hash
+            | UmaFlags::RefCnt
+            | UmaFlags::VToSlab
+            | UmaFlags::Bucket
+            | UmaFlags::Internal
+            | UmaFlags::CacheOnly; // UMA_ZONE_INHERIT
         let gg = GutexGroup::new();
 
         Self {
-            bucket_enable,
+            buckets,
             ty,
+            name,
             size: keg.size(),
+            flags: flags | (keg.flags() & inherit),
+            keg,
             caches: CpuLocal::new(|_| RefCell::default()),
             full_buckets: gg.clone().spawn_default(),
             free_buckets: gg.clone().spawn_default(),
             alloc_count: gg.clone().spawn_default(),
             free_count: gg.clone().spawn_default(),
+            fail_count: gg.clone().spawn_default(),
             count: gg.spawn(count),
-            flags: flags | (keg.flags() & UmaFlags::from(0xa2002518)), // TODO: Use named flags.
         }
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     pub fn size(&self) -> NonZero<usize> {
         self.size
     }
 
+    /// Returns the statistics of this zone.
+    ///
+    /// The Orbis also include the statistics from per-CPU caches. We only include the statistics
+    /// that already flushed to the zone since we can't access the cache of the other CPUs.
+    ///
+    /// See `sysctl_vm_zone_stats` on the Orbis for a reference.
+    pub fn stats(&self) -> ZoneStats {
+        let fulls = self.full_buckets.write();
+
+        ZoneStats {
+            pages: self.keg.pages(),
+            keg_free: self.keg.free_items(),
+            zone_free: fulls.items(),
+            allocs: *self.alloc_count.write(),
+            frees: *self.free_count.write(),
+            fails: *self.fail_count.write(),
+        }
+    }
+
     /// See `uma_zalloc_arg` on the Orbis for a reference.
     ///
     /// # Reference offsets
@@ -131,12 +156,18 @@ impl UmaZone {
             panic!("heap allocation in a non-sleeping context is not supported");
         }
 
+        // Internal zone never use a bucket.
+        if self.flags.has(UmaFlags::Internal) {
+            return self.alloc_item();
+        }
+
         loop {
             // Try allocate from per-CPU cache first so we don't need to acquire a mutex lock.
             let caches = self.caches.lock();
             let mem = Self::alloc_from_cache(caches.borrow_mut().deref_mut());
 
             if !mem.is_null() {
+                unsafe { mem.write_bytes(0, self.size.get()) };
                 return mem;
             }
 
@@ -151,43 +182,31 @@ impl UmaZone {
             let mem = Self::alloc_from_cache(&mut cache);
 
             if !mem.is_null() {
+                unsafe { mem.write_bytes(0, self.size.get()) };
                 return mem;
             }
 
-            // TODO: What actually we are doing here?
+            // Flush the statistics from the cache.
             *self.alloc_count.write() += core::mem::take(&mut cache.allocs);
             *self.free_count.write() += core::mem::take(&mut cache.frees);
 
+            // Our old bucket is now a free bucket.
             if let Some(b) = cache.alloc.take() {
                 frees.push_front(b);
             }
 
+            // Check the zone for a full bucket.
             if let Some(b) = self.full_buckets.write().pop_front() {
                 cache.alloc = Some(b);
-
-                // Seems like this should never fail.
-                let m = Self::alloc_from_cache(&mut cache);
-
-                assert!(!m.is_null());
-
-                return m;
+                continue;
             }
 
             drop(cache);
             drop(caches);
 
-            // TODO: What is this?
-            if matches!(
-                self.ty,
-                ZoneType::MbufPacket
-                    | ZoneType::MbufJumboPage
-                    | ZoneType::Mbuf
-                    | ZoneType::MbufCluster
-            ) {
-                todo!()
-            }
-
-            // TODO: What is this?
+            // TODO: The Orbis do something specific to the mbuf zones here. We don't have any mbuf
+            // zone yet so we treat them the same as the other zones for now.
+            // Increase the bucket size since we are missing the cache.
             if !matches!(
                 self.ty,
                 ZoneType::MbufCluster
@@ -200,7 +219,8 @@ impl UmaZone {
                 *count += 1;
             }
 
-            if self.alloc_bucket(frees) {
+            // Try to fill a new bucket then retry.
+            if !self.alloc_bucket(frees, *count) {
                 return self.alloc_item();
             }
         }
@@ -208,8 +228,9 @@ impl UmaZone {
 
     fn alloc_from_cache(c: &mut UmaCache) -> *mut u8 {
         while let Some(b) = &mut c.alloc {
-            if b.len() != 0 {
-                todo!()
+            if let Some(item) = b.pop() {
+                c.allocs += 1;
+                return item;
             }
 
             if c.free.as_ref().is_some_and(|b| b.len() != 0) {
@@ -220,41 +241,211 @@ impl UmaZone {
             break;
         }
 
-        null_mut()
+        core::ptr::null_mut()
+    }
+
+    /// See `uma_zfree_arg` on the Orbis for a reference.
+    ///
+    /// # Safety
+    /// `item` must be obtained with [`Self::alloc()`] on this zone.
+    pub unsafe fn free(&self, item: *mut u8) {
+        // Our implementation imply M_WAITOK. Beware that we can't call into global allocator here
+        // otherwise it will cause a recursive call, which will end up panic.
+        let td = current_thread();
+
+        if !td.can_sleep() {
+            panic!("heap deallocation in a non-sleeping context is not supported");
+        }
+
+        if self.flags.has(UmaFlags::Internal) {
+            return self.free_item(item);
+        }
+
+        loop {
+            // Try free to per-CPU cache first so we don't need to acquire a mutex lock.
+            let caches = self.caches.lock();
+
+            if Self::free_to_cache(caches.borrow_mut().deref_mut(), item) {
+                return;
+            }
+
+            drop(caches); // Exit from non-sleeping context before acquire the mutex.
+
+            // Cache is full, return the full bucket to the zone. We need to re-check the cache again
+            // because we may on a different CPU since we drop the CPU pinning on the above.
+            let mut fulls = self.full_buckets.write();
+            let caches = self.caches.lock();
+            let mut cache = caches.borrow_mut();
+
+            if Self::free_to_cache(&mut cache, item) {
+                return;
+            }
+
+            if let Some(b) = cache.free.take() {
+                fulls.push_front(b);
+            }
+
+            // Get an empty bucket from the zone.
+            if let Some(b) = self.free_buckets.write().pop_front() {
+                cache.free = Some(b);
+                continue;
+            }
+
+            drop(cache);
+            drop(caches);
+            drop(fulls);
+
+            // We need a new bucket to put the item.
+            let count = *self.count.write();
+
+            if let Some(b) = self.buckets.as_ref().and_then(|z| z.alloc(count)) {
+                self.free_buckets.write().push_front(b);
+                continue;
+            }
+
+            // Bucket is not available so free the item directly to the keg.
+            return self.free_item(item);
+        }
+    }
+
+    fn free_to_cache(c: &mut UmaCache, item: *mut u8) -> bool {
+        while let Some(b) = &mut c.free {
+            if !b.is_full() {
+                b.push(item);
+                c.frees += 1;
+                return true;
+            }
+
+            if c.alloc.as_ref().is_some_and(|a| a.len() < b.len()) {
+                core::mem::swap(&mut c.alloc, &mut c.free);
+                continue;
+            }
+
+            break;
+        }
+
+        false
     }
 
+    /// Returns `true` if a new full bucket has been put on the zone.
+    ///
     /// See `zone_alloc_bucket` on the Orbis for a reference.
     ///
     /// # Reference offsets
     /// | Version | Offset |
     /// |---------|--------|
     /// |PS4 11.00|0x13EBA0|
-    fn alloc_bucket(&self, frees: GutexWrite<VecDeque<UmaBucket>>) -> bool {
-        match frees.front() {
-            Some(_) => todo!(),
-            None => {
-                if self.bucket_enable.load(Ordering::Relaxed) {
-                    if self.flags.has(UmaFlags::CacheOnly) {
-                        todo!()
-                    } else {
-                        todo!()
-                    }
-                }
+    fn alloc_bucket(&self, mut frees: GutexWrite<BucketList>, count: usize) -> bool {
+        // We don't need M_NOVM for UMA_ZFLAG_CACHEONLY since the bucket never allocated from VM.
+        let mut b = match frees.pop_front() {
+            Some(v) => v,
+            None => match self.buckets.as_ref().and_then(|z| z.alloc(count)) {
+                Some(v) => v,
+                None => return false,
+            },
+        };
+
+        drop(frees);
+
+        // Fill the bucket from the keg.
+        let max = min(b.entries(), count);
+
+        while b.len() < max {
+            let item = self.keg.alloc_item();
+
+            if item.is_null() {
+                break;
             }
+
+            b.push(item);
+        }
+
+        if b.len() != 0 {
+            self.full_buckets.write().push_front(b);
+            return true;
+        }
+
+        if let Some(z) = &self.buckets {
+            z.free(b);
         }
 
-        true
+        false
     }
 
+    /// Allocate an item directly from the keg without using any bucket. Returns null if there are
+    /// no memory available.
+    ///
     /// See `zone_alloc_item` on the Orbis for a reference.
     ///
     /// # Reference offsets
     /// | Version | Offset |
     /// |---------|--------|
     /// |PS4 11.00|0x13DD50|
-    fn alloc_item(&self) -> *mut u8 {
-        todo!()
+    pub(super) fn alloc_item(&self) -> *mut u8 {
+        let item = self.keg.alloc_item();
+
+        if item.is_null() {
+            *self.fail_count.write() += 1;
+            return item;
+        }
+
+        *self.alloc_count.write() += 1;
+
+        unsafe { item.write_bytes(0, self.size.get()) };
+
+        item
     }
+
+    /// See `zone_free_item` on the Orbis for a reference.
+    ///
+    /// # Safety
+    /// `item` must be allocated from this zone.
+    pub(super) unsafe fn free_item(&self, item: *mut u8) {
+        *self.free_count.write() += 1;
+
+        self.keg.free_item(item);
+    }
+
+    /// Release all cached items back to the keg then release all free slabs.
+    ///
+    /// This does not drain the per-CPU caches.
+    ///
+    /// See `zone_drain` on the Orbis for a reference.
+    pub fn drain(&self) {
+        // Release all full buckets.
+        let mut fulls = self.full_buckets.write();
+        let mut frees = self.free_buckets.write();
+
+        while let Some(mut b) = fulls.pop_front() {
+            while let Some(item) = b.pop() {
+                unsafe { self.keg.free_item(item) };
+            }
+
+            frees.push_front(b);
+        }
+
+        // Release all free buckets.
+        while let Some(b) = frees.pop_front() {
+            if let Some(z) = &self.buckets {
+                z.free(b);
+            }
+        }
+
+        drop(frees);
+        drop(fulls);
+
+        self.keg.drain();
+    }
+}
+
+/// Statistics of [`UmaZone`].
+pub struct ZoneStats {
+    pub pages: usize,     // uth_pages
+    pub keg_free: usize,  // uth_keg_free
+    pub zone_free: usize, // uth_zone_free
+    pub allocs: u64,      // uth_allocs
+    pub frees: u64,       // uth_frees
+    pub fails: u64,       // uth_fails
 }
 
 /// Type of [`UmaZone`].
@@ -0,0 +1,146 @@
+use crate::config::PAGE_SIZE;
+use crate::lock::Mutex;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::cmp::max;
+use core::ptr::null_mut;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Arena of kernel virtual memory.
+///
+/// This is our implementation of `kmem_map` and the functions that operate on it (e.g.
+/// `kmem_malloc` and `kmem_free`). The Orbis allocate the virtual address from a `vm_map` then back
+/// it with a physical pages. We use a static region of the kernel image instead, which is already
+/// mapped by the VMM. This region is not a part of [`PhysMem`](super::PhysMem) and
+/// [`VmSpace`](super::VmSpace) only manage the user space so the arena never fault.
+///
+/// The arena track the allocation with a bitmap so it never need a heap allocation after it has been
+/// created. This is required since it is being used by the heap itself.
+pub struct KmemArena {
+    start: usize,
+    len: usize,
+    pages: Mutex<Vec<u64>>,
+    slabs: Vec<AtomicUsize>,
+}
+
+impl KmemArena {
+    /// # Safety
+    /// The specified memory must be valid for reads and writes and it must be exclusively available
+    /// to [`KmemArena`].
+    ///
+    /// # Context safety
+    /// This function does not require a CPU context on **stage 1** heap.
+    pub unsafe fn new<const L: usize>(mem: *mut [u8; L]) -> Self {
+        // Only use the part that aligned to page size.
+        let mem = mem.cast::<u8>();
+        let off = mem.align_offset(PAGE_SIZE.get());
+        let pages = L.saturating_sub(off) / PAGE_SIZE;
+
+        assert_ne!(pages, 0);
+
+        Self {
+            start: mem.add(off) as usize,
+            len: pages,
+            pages: Mutex::new(vec![0; pages.div_ceil(64)]),
+            slabs: (0..pages).map(|_| AtomicUsize::new(0)).collect(),
+        }
+    }
+
+    /// Returns null if there are no available space.
+    ///
+    /// The returned memory will always be aligned to a page size or `layout.align()`, whichever is
+    /// larger.
+    ///
+    /// See `kmem_malloc` on the PS4 for a reference.
+    ///
+    /// # Panics
+    /// If `layout` has zero size.
+    pub fn alloc(&self, layout: Layout) -> *mut u8 {
+        let len = Self::pages(layout);
+        let align = max(layout.align(), PAGE_SIZE.get()) / PAGE_SIZE;
+        let mut pages = self.pages.lock();
+
+        // The alignment need to be calculated from the absolute address since the arena may not
+        // start at the boundary of the requested alignment.
+        let base = self.start / PAGE_SIZE;
+        let mut first = base.next_multiple_of(align) - base;
+
+        // Find the first free range that large enough.
+        while first + len <= self.len {
+            if let Some(i) = (first..(first + len)).rfind(|&i| Self::test(&pages, i)) {
+                first = (base + i + 1).next_multiple_of(align) - base;
+                continue;
+            }
+
+            // Mark the pages as used.
+            for i in first..(first + len) {
+                pages[i / 64] |= 1 << (i % 64);
+            }
+
+            return (self.start + first * PAGE_SIZE.get()) as *mut u8;
+        }
+
+        null_mut()
+    }
+
+    /// See `kmem_free` on the PS4 for a reference.
+    ///
+    /// # Safety
+    /// `ptr` must be obtained with [`Self::alloc()`] and `layout` must be the same one that was
+    /// passed to that method.
+    pub unsafe fn free(&self, ptr: *mut u8, layout: Layout) {
+        let first = self.page(ptr);
+        let len = Self::pages(layout);
+        let mut pages = self.pages.lock();
+
+        for i in first..(first + len) {
+            assert!(Self::test(&pages, i), "double free on kernel memory arena");
+
+            pages[i / 64] &= !(1 << (i % 64));
+        }
+    }
+
+    /// Associate `slab` with `pages` pages start at `mem`. Specify null to remove the association.
+    ///
+    /// This is our implementation of `vsetslab`. The Orbis store the slab in the `vm_page` that back
+    /// the memory but we don't have it yet.
+    ///
+    /// # Panics
+    /// If `mem` is not obtained with [`Self::alloc()`].
+    pub fn set_slab(&self, mem: *mut u8, pages: usize, slab: *mut u8) {
+        let first = self.page(mem);
+
+        for i in first..(first + pages) {
+            self.slabs[i].store(slab as usize, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the slab that was associated with `mem` by [`Self::set_slab()`].
+    ///
+    /// This is our implementation of `vtoslab`.
+    ///
+    /// # Panics
+    /// If `mem` is not obtained with [`Self::alloc()`].
+    pub fn slab(&self, mem: *mut u8) -> *mut u8 {
+        self.slabs[self.page(mem)].load(Ordering::Relaxed) as *mut u8
+    }
+
+    fn page(&self, mem: *mut u8) -> usize {
+        let off = (mem as usize)
+            .checked_sub(self.start)
+            .expect("address is not in the kernel memory arena");
+
+        off / PAGE_SIZE
+    }
+
+    fn pages(layout: Layout) -> usize {
+        assert_ne!(layout.size(), 0);
+
+        layout.size().div_ceil(PAGE_SIZE.get())
+    }
+
+    fn test(pages: &[u64], i: usize) -> bool {
+        pages[i / 64] & (1 << (i % 64)) != 0
+    }
+}
@@ -0,0 +1,351 @@
+use super::VmObject;
+use crate::errno::{Errno, EINVAL, ENOMEM};
+use alloc::collections::btree_map::BTreeMap;
+use alloc::sync::Arc;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+use core::ops::Range;
+use macros::bitflag;
+
+/// Implementation of `vm_map` structure.
+///
+/// The Orbis keep the entries in a linked list together with a splay tree. We use [`BTreeMap`]
+/// keyed by the start address instead.
+#[derive(Clone)]
+pub struct VmMap {
+    entries: BTreeMap<usize, VmMapEntry>, // header
+    min: usize,                           // header.start
+    max: usize,                           // header.end
+}
+
+impl VmMap {
+    pub fn new(min: usize, max: usize) -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            min,
+            max,
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &VmMapEntry> {
+        self.entries.values()
+    }
+
+    /// Returns the entry that contains `addr`.
+    ///
+    /// See `vm_map_lookup_entry` on the PS4 for a reference.
+    pub fn lookup(&self, addr: usize) -> Option<&VmMapEntry> {
+        self.entries
+            .range(..=addr)
+            .next_back()
+            .map(|(_, e)| e)
+            .filter(|e| addr < e.end)
+    }
+
+    /// Returns the lowest address that is not lower than `hint` and have a free space for `len`
+    /// bytes.
+    ///
+    /// See `vm_map_findspace` on the PS4 for a reference.
+    pub fn find_space(&self, hint: usize, len: usize) -> Option<usize> {
+        let mut addr = hint.max(self.min);
+
+        // Skip the entry that contains the hint.
+        if let Some(e) = self.lookup(addr) {
+            addr = e.end;
+        }
+
+        for e in self.entries.range(addr..).map(|(_, e)| e) {
+            if addr.checked_add(len)? <= e.start {
+                break;
+            }
+
+            addr = e.end;
+        }
+
+        if addr.checked_add(len)? > self.max {
+            None
+        } else {
+            Some(addr)
+        }
+    }
+
+    /// Insert an anonymous mapping.
+    ///
+    /// See `vm_map_insert` on the PS4 for a reference.
+    pub fn insert(&mut self, addr: usize, len: usize, prot: VmProt) -> Result<(), MapError> {
+        self.insert_entry(addr, len, prot, None, 0)
+    }
+
+    /// Insert a mapping that backed by `object` starting at `off`.
+    ///
+    /// See `vm_map_insert` on the PS4 for a reference.
+    pub fn insert_object(
+        &mut self,
+        addr: usize,
+        len: usize,
+        prot: VmProt,
+        object: Arc<dyn VmObject>,
+        off: usize,
+    ) -> Result<(), MapError> {
+        self.insert_entry(addr, len, prot, Some(object), off)
+    }
+
+    /// Returns the end of the range if a mapping can be placed at `addr` with `len` bytes, ignoring
+    /// the existing mappings.
+    pub fn check_range(&self, addr: usize, len: usize) -> Result<usize, MapError> {
+        let end = addr.checked_add(len).ok_or(MapError::InvalidAddress)?;
+
+        if len == 0 || addr < self.min || end > self.max {
+            return Err(MapError::InvalidAddress);
+        }
+
+        Ok(end)
+    }
+
+    /// Removes all mappings within `range`. The entries that partially inside the range will be
+    /// clipped.
+    ///
+    /// See `vm_map_delete` on the PS4 for a reference.
+    pub fn remove(&mut self, range: Range<usize>) {
+        self.clip(range.start);
+        self.clip(range.end);
+
+        let keys: alloc::vec::Vec<usize> = self.entries.range(range).map(|(&k, _)| k).collect();
+
+        for k in keys {
+            self.entries.remove(&k);
+        }
+    }
+
+    /// Set protection of all mappings within `range`. The entries that partially inside the range
+    /// will be clipped.
+    ///
+    /// See `vm_map_protect` on the PS4 for a reference.
+    pub fn protect(&mut self, range: Range<usize>, prot: VmProt) {
+        self.clip(range.start);
+        self.clip(range.end);
+
+        for (_, e) in self.entries.range_mut(range) {
+            e.prot = prot;
+        }
+    }
+
+    /// Split the entry that contains `addr` so `addr` become a start of the entry.
+    ///
+    /// This is a combination of `vm_map_clip_start` and `vm_map_clip_end`.
+    fn clip(&mut self, addr: usize) {
+        let e = match self.entries.range_mut(..addr).next_back() {
+            Some((_, e)) if addr < e.end => e,
+            _ => return,
+        };
+
+        let new = VmMapEntry {
+            start: addr,
+            end: e.end,
+            prot: e.prot,
+            object: e.object.clone(),
+            offset: e.offset + (addr - e.start),
+        };
+
+        e.end = addr;
+
+        self.entries.insert(addr, new);
+    }
+
+    fn insert_entry(
+        &mut self,
+        addr: usize,
+        len: usize,
+        prot: VmProt,
+        object: Option<Arc<dyn VmObject>>,
+        off: usize,
+    ) -> Result<(), MapError> {
+        // Check if the range is valid.
+        let end = self.check_range(addr, len)?;
+
+        // Check if the range overlap with any entry.
+        if self.lookup(addr).is_some() || self.entries.range(addr..end).next().is_some() {
+            return Err(MapError::NoSpace);
+        }
+
+        self.entries.insert(
+            addr,
+            VmMapEntry {
+                start: addr,
+                end,
+                prot,
+                object,
+                offset: off,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+/// Implementation of `vm_map_entry` structure.
+#[derive(Clone)]
+pub struct VmMapEntry {
+    start: usize,                      // start
+    end: usize,                        // end
+    prot: VmProt,                      // protection
+    object: Option<Arc<dyn VmObject>>, // object.vm_object
+    offset: usize,                     // offset
+}
+
+impl VmMapEntry {
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn prot(&self) -> VmProt {
+        self.prot
+    }
+
+    /// Returns [`None`] if this is an anonymous mapping.
+    pub fn object(&self) -> Option<&Arc<dyn VmObject>> {
+        self.object.as_ref()
+    }
+
+    /// Returns the offset in the object of [`Self::start()`].
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// Implementation of `vm_prot_t`.
+#[bitflag(u8)]
+pub enum VmProt {
+    /// `VM_PROT_READ`.
+    Read = 0x01,
+    /// `VM_PROT_WRITE`.
+    Write = 0x02,
+    /// `VM_PROT_EXECUTE`.
+    Execute = 0x04,
+    /// `VM_PROT_GPU_READ`.
+    GpuRead = 0x10,
+    /// `VM_PROT_GPU_WRITE`.
+    GpuWrite = 0x20,
+}
+
+/// Represents an error when operation on [`VmMap`] fails.
+#[derive(Debug)]
+pub enum MapError {
+    InvalidAddress,
+    NoSpace,
+}
+
+impl Error for MapError {}
+
+impl Errno for MapError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::InvalidAddress => EINVAL,
+            Self::NoSpace => ENOMEM,
+        }
+    }
+}
+
+impl Display for MapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidAddress => f.write_str("invalid address"),
+            Self::NoSpace => f.write_str("no space available"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert() {
+        let mut map = VmMap::new(0x1000, 0x10000);
+
+        map.insert(0x2000, 0x2000, VmProt::Read).unwrap();
+
+        assert!(map.insert(0x3000, 0x1000, VmProt::Read).is_err());
+        assert!(map.insert(0x1000, 0x2000, VmProt::Read).is_err());
+        assert!(map.insert(0x0, 0x1000, VmProt::Read).is_err());
+        assert!(map.insert(0xF000, 0x2000, VmProt::Read).is_err());
+        assert!(map.lookup(0x1FFF).is_none());
+        assert_eq!(map.lookup(0x3FFF).unwrap().start(), 0x2000);
+        assert!(map.lookup(0x4000).is_none());
+
+        // The existing mappings are not considered.
+        assert_eq!(map.check_range(0x3000, 0x1000).unwrap(), 0x4000);
+        assert!(map.check_range(0xF000, 0x2000).is_err());
+        assert!(map.check_range(0x2000, 0).is_err());
+    }
+
+    #[test]
+    fn find_space() {
+        let mut map = VmMap::new(0x1000, 0x10000);
+
+        map.insert(0x1000, 0x1000, VmProt::Read).unwrap();
+        map.insert(0x3000, 0x1000, VmProt::Read).unwrap();
+
+        assert_eq!(map.find_space(0, 0x1000), Some(0x2000));
+        assert_eq!(map.find_space(0, 0x2000), Some(0x4000));
+        assert_eq!(map.find_space(0x3800, 0x1000), Some(0x4000));
+        assert_eq!(map.find_space(0, 0xC000), Some(0x4000));
+        assert_eq!(map.find_space(0, 0xD000), None);
+    }
+
+    #[test]
+    fn remove() {
+        let mut map = VmMap::new(0x1000, 0x10000);
+
+        map.insert(0x1000, 0x4000, VmProt::Read).unwrap();
+        map.remove(0x2000..0x3000);
+
+        let entries: alloc::vec::Vec<(usize, usize)> =
+            map.entries().map(|e| (e.start(), e.end())).collect();
+
+        assert_eq!(entries, [(0x1000, 0x2000), (0x3000, 0x5000)]);
+    }
+
+    #[test]
+    fn protect() {
+        let mut map = VmMap::new(0x1000, 0x10000);
+
+        map.insert(0x1000, 0x4000, VmProt::Read).unwrap();
+        map.protect(0x2000..0x3000, VmProt::Read | VmProt::Write);
+
+        assert!(!map.lookup(0x1000).unwrap().prot().has(VmProt::Write));
+        assert!(map.lookup(0x2000).unwrap().prot().has(VmProt::Write));
+        assert_eq!(map.lookup(0x2FFF).unwrap().end(), 0x3000);
+        assert!(!map.lookup(0x3000).unwrap().prot().has(VmProt::Write));
+        assert_eq!(map.entries().count(), 3);
+    }
+
+    #[test]
+    fn clip_object() {
+        let mut map = VmMap::new(0x1000, 0x10000);
+
+        map.insert_object(0x1000, 0x4000, VmProt::Read, Arc::new(Dummy), 0x8000)
+            .unwrap();
+        map.remove(0x2000..0x3000);
+
+        let e = map.lookup(0x3000).unwrap();
+
+        assert!(e.object().is_some());
+        assert_eq!(e.offset(), 0xA000);
+        assert_eq!(map.lookup(0x1000).unwrap().offset(), 0x8000);
+    }
+
+    struct Dummy;
+
+    impl VmObject for Dummy {
+        fn page(&self, _: usize, _: VmProt) -> Result<usize, alloc::boxed::Box<dyn Errno>> {
+            unimplemented!()
+        }
+    }
+}
@@ -0,0 +1,13 @@
+pub use self::kmem::*;
+pub use self::map::*;
+pub use self::object::*;
+pub use self::phys::*;
+pub use self::pmap::*;
+pub use self::space::*;
+
+mod kmem;
+mod map;
+mod object;
+mod phys;
+mod pmap;
+mod space;
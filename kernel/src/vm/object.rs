@@ -0,0 +1,16 @@
+use super::VmProt;
+use crate::errno::Errno;
+use alloc::boxed::Box;
+
+/// Implementation of `vm_object` for the mapping that is not anonymous.
+///
+/// The Orbis use a single structure with a pager for each type of the object. We use a trait
+/// instead so each subsystem can provide the pages in its own way. The pages provided by the object
+/// are owned by the object so [`super::VmSpace`] will never free it.
+pub trait VmObject: Send + Sync {
+    /// Returns the physical address of the page at `off`, which is always page aligned. `prot` is
+    /// the protection of the mapping.
+    ///
+    /// This is a combination of `pgo_getpages` and `pgo_haspage`.
+    fn page(&self, off: usize, prot: VmProt) -> Result<usize, Box<dyn Errno>>;
+}
@@ -0,0 +1,79 @@
+use crate::config::PAGE_SIZE;
+use crate::lock::Mutex;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Allocator of physical pages.
+///
+/// This is our implementation of `vm_phys` and `vm_page_alloc`. The Orbis track each page with
+/// `vm_page` structure and keep the free pages in the buddy queues. We use a bitmap instead since we
+/// only have a single range of physical memory that the VMM give to us.
+///
+/// All physical memory can be accessed from the kernel via a direct map (AKA DMAP).
+pub struct PhysMem {
+    dmap: usize,
+    start: usize,
+    len: usize,
+    pages: Mutex<Vec<u64>>,
+}
+
+impl PhysMem {
+    /// # Safety
+    /// - `dmap` must be the virtual address where the whole physical memory is directly mapped.
+    /// - The physical memory at `addr` with `len` bytes must be exclusively available to
+    ///   [`PhysMem`].
+    ///
+    /// # Context safety
+    /// This function does not require a CPU context on **stage 1** heap.
+    pub unsafe fn new(dmap: usize, addr: usize, len: usize) -> Self {
+        // Only use the part that aligned to page size.
+        let start = addr.next_multiple_of(PAGE_SIZE.get());
+        let len = (addr + len).saturating_sub(start) / PAGE_SIZE;
+
+        assert_ne!(len, 0);
+
+        Self {
+            dmap,
+            start,
+            len,
+            pages: Mutex::new(vec![0; len.div_ceil(64)]),
+        }
+    }
+
+    /// Returns a virtual address on the direct map for `addr`.
+    pub fn map(&self, addr: usize) -> *mut u8 {
+        (self.dmap + addr) as *mut u8
+    }
+
+    /// Allocate a zeroed page. Returns its physical address or [`None`] if there are no free page.
+    ///
+    /// See `vm_page_alloc` on the PS4 for a reference.
+    pub fn alloc(&self) -> Option<usize> {
+        let mut pages = self.pages.lock();
+        let i = (0..self.len).find(|&i| (pages[i / 64] & (1 << (i % 64))) == 0)?;
+
+        pages[i / 64] |= 1 << (i % 64);
+
+        drop(pages);
+
+        // Zero the page.
+        let addr = self.start + i * PAGE_SIZE.get();
+
+        unsafe { self.map(addr).write_bytes(0, PAGE_SIZE.get()) };
+
+        Some(addr)
+    }
+
+    /// See `vm_page_free` on the PS4 for a reference.
+    ///
+    /// # Safety
+    /// `addr` must be allocated with [`Self::alloc()`] and it must not be in use.
+    pub unsafe fn free(&self, addr: usize) {
+        let i = (addr - self.start) / PAGE_SIZE;
+        let mut pages = self.pages.lock();
+
+        assert_ne!(pages[i / 64] & (1 << (i % 64)), 0);
+
+        pages[i / 64] &= !(1 << (i % 64));
+    }
+}
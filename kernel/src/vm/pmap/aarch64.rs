@@ -0,0 +1,88 @@
+use crate::vm::VmProt;
+use core::arch::asm;
+
+/// Number of translation table levels (level 0 to level 3).
+pub const LEVELS: usize = 4;
+
+/// Number of descriptors in a translation table with 16K granule.
+pub const TABLE_LEN: usize = 2048;
+
+/// Bits of the output address in a descriptor.
+pub const FRAME: usize = 0x0000FFFFFFFFC000;
+
+/// Ignored bit to indicate the descriptor is shared with the kernel.
+pub const SHARED: usize = 1 << 55;
+
+const ATTR_VALID: usize = 0b01;
+const ATTR_TABLE: usize = 0b10; // Also page descriptor on level 3.
+const ATTR_IDX_NOR: usize = 1 << 2; // Same as MA_NOR on the VMM.
+const ATTR_AP_USER: usize = 0b01 << 6;
+const ATTR_AP_RO: usize = 0b10 << 6;
+const ATTR_SH_IS: usize = 0b11 << 8;
+const ATTR_AF: usize = 1 << 10;
+const ATTR_NG: usize = 1 << 11;
+const ATTR_PXN: usize = 1 << 53;
+const ATTR_UXN: usize = 1 << 54;
+
+/// Returns an index of the descriptor for `addr` in the table at `level`, which 0 is the lowest
+/// (AKA level 3 on the Arm).
+pub fn index(addr: usize, level: usize) -> usize {
+    // Level 0 on the Arm only has 2 descriptors with 16K granule and 48-bit address.
+    let i = addr >> (14 + 11 * level);
+
+    match level {
+        3 => i & 1,
+        _ => i & (TABLE_LEN - 1),
+    }
+}
+
+pub fn is_valid(e: usize) -> bool {
+    (e & ATTR_VALID) != 0
+}
+
+/// Returns `true` if `e` at `level` is a table descriptor instead of a block descriptor.
+pub fn is_table(e: usize, level: usize) -> bool {
+    level == 0 || (e & ATTR_TABLE) != 0
+}
+
+pub fn table_entry(addr: usize) -> usize {
+    addr | ATTR_VALID | ATTR_TABLE
+}
+
+/// See `pmap_enter` on the PS4 for a reference.
+pub fn page_entry(addr: usize, prot: VmProt) -> usize {
+    let mut e = addr | ATTR_VALID | ATTR_TABLE | ATTR_IDX_NOR | ATTR_SH_IS | ATTR_AF | ATTR_NG;
+
+    e |= ATTR_AP_USER | ATTR_PXN;
+
+    if !prot.has(VmProt::Write) {
+        e |= ATTR_AP_RO;
+    }
+
+    if !prot.has(VmProt::Execute) {
+        e |= ATTR_UXN;
+    }
+
+    e
+}
+
+/// Returns a physical address of the level 0 table that currently used for the lower VA.
+pub unsafe fn current_root() -> usize {
+    let v: usize;
+
+    asm!("mrs {v}, ttbr0_el1", v = out(reg) v, options(nomem, nostack, preserves_flags));
+
+    v & FRAME
+}
+
+/// See `pmap_invalidate_page` on the PS4 for a reference.
+pub unsafe fn invalidate(addr: usize) {
+    asm!(
+        "dsb ishst",
+        "tlbi vaae1is, {v}",
+        "dsb ish",
+        "isb",
+        v = in(reg) addr >> 12,
+        options(nostack, preserves_flags)
+    );
+}
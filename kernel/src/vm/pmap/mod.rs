@@ -0,0 +1,196 @@
+pub use self::arch::*;
+
+use super::{PhysMem, VmProt};
+use crate::config::PAGE_MASK;
+use alloc::sync::Arc;
+use core::error::Error;
+use core::fmt::{Display, Formatter};
+
+#[cfg_attr(target_arch = "aarch64", path = "aarch64.rs")]
+#[cfg_attr(target_arch = "x86_64", path = "x86_64.rs")]
+mod arch;
+
+/// Implementation of `pmap` structure.
+///
+/// All page tables has the same size as a page on both x86-64 (4K) and AArch64 (16K) so each of it
+/// is allocated from [`PhysMem`].
+///
+/// The user page table share all top-level entries that was setup by the VMM with the kernel (e.g.
+/// the kernel itself, the direct map and the virtual devices). Those entries are marked with
+/// [`SHARED`] and any attempt to map a page under it will fail.
+pub struct Pmap {
+    phys: Arc<PhysMem>,
+    root: usize, // pm_pml4
+    owned: bool,
+}
+
+impl Pmap {
+    /// Returns a pmap for the page table that currently active (AKA `kernel_pmap`).
+    ///
+    /// # Safety
+    /// The returned [`Pmap`] must not outlive the current page table.
+    ///
+    /// # Context safety
+    /// This function does not require a CPU context.
+    pub unsafe fn kernel(phys: Arc<PhysMem>) -> Self {
+        Self {
+            phys,
+            root: current_root(),
+            owned: false,
+        }
+    }
+
+    /// Returns [`None`] if there are no physical memory available.
+    ///
+    /// See `pmap_pinit` on the PS4 for a reference.
+    pub fn new(kernel: &Self) -> Option<Self> {
+        let phys = kernel.phys.clone();
+        let root = phys.alloc()?;
+        let src = kernel.table(kernel.root);
+        let dst = unsafe { &mut *phys.map(root).cast::<[usize; TABLE_LEN]>() };
+
+        for (d, s) in dst.iter_mut().zip(src) {
+            if is_valid(*s) {
+                *d = *s | SHARED;
+            }
+        }
+
+        Some(Self {
+            phys,
+            root,
+            owned: true,
+        })
+    }
+
+    /// Map a page at `addr` to `paddr`.
+    ///
+    /// See `pmap_enter` on the PS4 for a reference.
+    pub fn enter(&mut self, addr: usize, paddr: usize, prot: VmProt) -> Result<(), PmapError> {
+        let mut table = self.root;
+
+        for level in (1..LEVELS).rev() {
+            let e = &mut self.table(table)[index(addr, level)];
+
+            if !is_valid(*e) {
+                let pa = self.phys.alloc().ok_or(PmapError::NoMemory)?;
+
+                *e = table_entry(pa);
+            } else if (*e & SHARED) != 0 || !is_table(*e, level) {
+                return Err(PmapError::Shared);
+            }
+
+            table = *e & FRAME;
+        }
+
+        let e = &mut self.table(table)[index(addr, 0)];
+
+        if is_valid(*e) {
+            return Err(PmapError::AlreadyMapped);
+        }
+
+        *e = page_entry(paddr, prot);
+
+        Ok(())
+    }
+
+    /// Unmap a page at `addr`. Returns the physical address of the page that was mapped.
+    ///
+    /// See `pmap_remove` on the PS4 for a reference.
+    pub fn remove(&mut self, addr: usize) -> Option<usize> {
+        let e = self.pte_mut(addr)?;
+        let pa = *e & FRAME;
+
+        *e = 0;
+
+        unsafe { invalidate(addr) };
+
+        Some(pa)
+    }
+
+    /// See `pmap_protect` on the PS4 for a reference.
+    pub fn protect(&mut self, addr: usize, prot: VmProt) {
+        if let Some(e) = self.pte_mut(addr) {
+            *e = page_entry(*e & FRAME, prot);
+
+            unsafe { invalidate(addr) };
+        }
+    }
+
+    /// Returns the physical address that `addr` is mapped to.
+    ///
+    /// See `pmap_extract` on the PS4 for a reference.
+    pub fn extract(&mut self, addr: usize) -> Option<usize> {
+        let off = addr & PAGE_MASK.get();
+
+        self.pte_mut(addr).map(|e| (*e & FRAME) + off)
+    }
+
+    fn pte_mut(&mut self, addr: usize) -> Option<&mut usize> {
+        let mut table = self.root;
+
+        for level in (1..LEVELS).rev() {
+            let e = self.table(table)[index(addr, level)];
+
+            if !is_valid(e) || (e & SHARED) != 0 || !is_table(e, level) {
+                return None;
+            }
+
+            table = e & FRAME;
+        }
+
+        let e = &mut self.table(table)[index(addr, 0)];
+
+        if is_valid(*e) {
+            Some(e)
+        } else {
+            None
+        }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    fn table(&self, addr: usize) -> &mut [usize; TABLE_LEN] {
+        unsafe { &mut *self.phys.map(addr).cast() }
+    }
+
+    /// Free all page tables that owned by this pmap under `table` at `level`.
+    fn release(&self, table: usize, level: usize) {
+        if level != 0 {
+            for &e in self.table(table).iter() {
+                if is_valid(e) && (e & SHARED) == 0 && is_table(e, level) {
+                    self.release(e & FRAME, level - 1);
+                }
+            }
+        }
+
+        unsafe { self.phys.free(table) };
+    }
+}
+
+impl Drop for Pmap {
+    /// See `pmap_release` on the PS4 for a reference.
+    fn drop(&mut self) {
+        if self.owned {
+            self.release(self.root, LEVELS - 1);
+        }
+    }
+}
+
+/// Represents an error when [`Pmap::enter()`] fails.
+#[derive(Debug)]
+pub enum PmapError {
+    NoMemory,
+    Shared,
+    AlreadyMapped,
+}
+
+impl Error for PmapError {}
+
+impl Display for PmapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoMemory => f.write_str("no physical memory available"),
+            Self::Shared => f.write_str("the address is shared with the kernel"),
+            Self::AlreadyMapped => f.write_str("the address already mapped"),
+        }
+    }
+}
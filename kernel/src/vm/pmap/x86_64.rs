@@ -0,0 +1,91 @@
+use crate::vm::VmProt;
+use core::arch::asm;
+
+/// Number of page table levels (PML4, PDP, PD and PT).
+pub const LEVELS: usize = 4;
+
+/// Number of entries in a page table.
+pub const TABLE_LEN: usize = 512;
+
+/// Bits of the physical address in a page table entry (AKA `PG_FRAME`).
+pub const FRAME: usize = 0x000FFFFFFFFFF000;
+
+/// Available bit to indicate the entry is shared with the kernel.
+pub const SHARED: usize = 1 << 9;
+
+const PG_V: usize = 0x001;
+const PG_RW: usize = 0x002;
+const PG_U: usize = 0x004;
+const PG_PS: usize = 0x080;
+
+/// Returns an index of the entry for `addr` in the page table at `level`, which 0 is the lowest.
+pub fn index(addr: usize, level: usize) -> usize {
+    (addr >> (12 + 9 * level)) & (TABLE_LEN - 1)
+}
+
+pub fn is_valid(e: usize) -> bool {
+    (e & PG_V) != 0
+}
+
+/// Returns `true` if `e` at `level` is point to the next level table instead of a large page.
+pub fn is_table(e: usize, _: usize) -> bool {
+    (e & PG_PS) == 0
+}
+
+pub fn table_entry(addr: usize) -> usize {
+    addr | PG_V | PG_RW | PG_U
+}
+
+/// See `pmap_enter` on the PS4 for a reference.
+pub fn page_entry(addr: usize, prot: VmProt) -> usize {
+    // TODO: Set PG_NX when VM_PROT_EXECUTE is not specified once we enabled EFER.NXE.
+    let mut e = addr | PG_V | PG_U;
+
+    if prot.has(VmProt::Write) {
+        e |= PG_RW;
+    }
+
+    e
+}
+
+/// Returns a physical address of the current PML4 table.
+pub unsafe fn current_root() -> usize {
+    let v: usize;
+
+    asm!("mov {v}, cr3", v = out(reg) v, options(nomem, nostack, preserves_flags));
+
+    v & FRAME
+}
+
+/// See `pmap_invalidate_page` on the PS4 for a reference.
+pub unsafe fn invalidate(addr: usize) {
+    asm!("invlpg [{v}]", v = in(reg) addr, options(nostack, preserves_flags));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index() {
+        let addr = 0xffffffff82204000;
+
+        assert_eq!(super::index(addr, 3), 511);
+        assert_eq!(super::index(addr, 2), 510);
+        assert_eq!(super::index(addr, 1), 0x11);
+        assert_eq!(super::index(addr, 0), 4);
+    }
+
+    #[test]
+    fn page_entry() {
+        let e = super::page_entry(0x5000, VmProt::Read);
+
+        assert!(is_valid(e));
+        assert_eq!(e & FRAME, 0x5000);
+        assert_eq!(e & PG_RW, 0);
+        assert_ne!(
+            super::page_entry(0x5000, VmProt::Read | VmProt::Write) & PG_RW,
+            0
+        );
+    }
+}
@@ -0,0 +1,602 @@
+use super::{MapError, PhysMem, Pmap, PmapError, VmMap, VmObject, VmProt};
+use crate::config::{PAGE_MASK, PAGE_SIZE};
+use crate::errno::{Errno, EACCES, EFAULT, EINVAL, ENAMETOOLONG, ENODEV, ENOMEM};
+use crate::fs::{CdevFileBackend, DevicePager, FileFlags};
+use crate::lock::Mutex;
+use crate::proc::Thread;
+use crate::syscalls::{SysErr, SysIn, SysOut, Syscalls};
+use crate::sysctl::{Sysctl, HW};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::ffi::c_int;
+use core::fmt::{Display, Formatter};
+use core::num::NonZero;
+use macros::sysctl;
+
+/// Implementation of `vmspace` structure.
+pub struct VmSpace {
+    phys: Arc<PhysMem>,
+    map: Mutex<VmMap>, // vm_map
+    pmap: Mutex<Pmap>, // vm_pmap
+}
+
+impl VmSpace {
+    /// Lowest address of the user space (AKA `VM_MIN_ADDRESS`).
+    pub const USER_MIN: usize = 0;
+
+    /// Highest address of the user space (AKA `VM_MAXUSER_ADDRESS`).
+    pub const USER_MAX: usize = 0x800000000000;
+
+    /// Lowest address that can be mapped. The first page is never mapped so a NULL dereference in
+    /// the user code always fault. This is the same as `security.bsd.map_at_zero` is disabled.
+    const MAP_MIN: usize = Self::USER_MIN + PAGE_SIZE.get();
+
+    /// Returns `vmspace0`, which use the page table that was setup by the VMM.
+    ///
+    /// # Safety
+    /// This function can be called only once.
+    ///
+    /// # Context safety
+    /// This function does not require a CPU context on **stage 1** heap.
+    pub unsafe fn kernel(phys: Arc<PhysMem>) -> Arc<Self> {
+        let pmap = Pmap::kernel(phys.clone());
+
+        Arc::new(Self {
+            phys,
+            map: Mutex::new(VmMap::new(Self::MAP_MIN, Self::USER_MAX)),
+            pmap: Mutex::new(pmap),
+        })
+    }
+
+    pub fn phys(&self) -> &Arc<PhysMem> {
+        &self.phys
+    }
+
+    /// Returns [`None`] if there are no physical memory available.
+    ///
+    /// The pages of anonymous mappings that already faulted in are copied to the new space. The
+    /// pages that owned by an object are shared with the new space.
+    ///
+    /// See `vmspace_fork` on the PS4 for a reference.
+    pub fn fork(&self) -> Option<Arc<Self>> {
+        // TODO: Implement copy-on-write instead of copying the pages.
+        let map = self.map.lock();
+        let mut pmap = self.pmap.lock();
+        let vm = Self {
+            phys: self.phys.clone(),
+            map: Mutex::new(map.clone()),
+            pmap: Mutex::new(Pmap::new(&pmap)?),
+        };
+
+        // Copy the pages. The pages that already copied will be freed when the new space is
+        // dropped on failure.
+        let mut dst = vm.pmap.lock();
+
+        for e in map.entries() {
+            for addr in (e.start()..e.end()).step_by(PAGE_SIZE.get()) {
+                let src = match pmap.extract(addr) {
+                    Some(v) => v,
+                    None => continue,
+                };
+
+                let page = match e.object() {
+                    Some(_) => src,
+                    None => {
+                        let page = self.phys.alloc()?;
+                        let src = self.phys.map(src);
+
+                        unsafe {
+                            self.phys
+                                .map(page)
+                                .copy_from_nonoverlapping(src, PAGE_SIZE.get())
+                        };
+
+                        page
+                    }
+                };
+
+                if dst.enter(addr, page, e.prot()).is_err() {
+                    if e.object().is_none() {
+                        unsafe { self.phys.free(page) };
+                    }
+
+                    return None;
+                }
+            }
+        }
+
+        drop(dst);
+
+        Some(Arc::new(vm))
+    }
+
+    /// Returns the address of the mapping. The pages will be allocated on the first access.
+    ///
+    /// See `vm_mmap` on the PS4 for a reference.
+    pub fn mmap(
+        &self,
+        addr: usize,
+        len: usize,
+        prot: VmProt,
+        fixed: bool,
+    ) -> Result<usize, MapError> {
+        self.map(addr, len, prot, fixed, None)
+    }
+
+    /// Returns the address of the mapping. The pages will be requested from `object` starting at
+    /// `off` on the first access.
+    ///
+    /// See `vm_mmap` on the PS4 for a reference.
+    pub fn mmap_object(
+        &self,
+        addr: usize,
+        len: usize,
+        prot: VmProt,
+        fixed: bool,
+        object: Arc<dyn VmObject>,
+        off: usize,
+    ) -> Result<usize, MapError> {
+        if (off & PAGE_MASK.get()) != (addr & PAGE_MASK.get()) {
+            return Err(MapError::InvalidAddress);
+        }
+
+        self.map(
+            addr,
+            len,
+            prot,
+            fixed,
+            Some((object, off & !PAGE_MASK.get())),
+        )
+    }
+
+    /// See `kern_munmap` on the PS4 for a reference.
+    pub fn munmap(&self, addr: usize, len: usize) -> Result<(), MapError> {
+        let len = Self::round_len(addr, len)?;
+        let addr = addr & !PAGE_MASK.get();
+        let end = addr.checked_add(len).ok_or(MapError::InvalidAddress)?;
+        let mut map = self.map.lock();
+
+        self.unmap_pages(&map, addr, len);
+        map.remove(addr..end);
+
+        Ok(())
+    }
+
+    /// See `kern_mprotect` on the PS4 for a reference.
+    pub fn mprotect(&self, addr: usize, len: usize, prot: VmProt) -> Result<(), MapError> {
+        let len = Self::round_len(addr, len)?;
+        let addr = addr & !PAGE_MASK.get();
+        let end = addr.checked_add(len).ok_or(MapError::InvalidAddress)?;
+        let mut map = self.map.lock();
+        let mut pmap = self.pmap.lock();
+
+        map.protect(addr..end, prot);
+
+        for addr in (addr..end).step_by(PAGE_SIZE.get()) {
+            pmap.protect(addr, prot);
+        }
+
+        Ok(())
+    }
+
+    /// Returns [`None`] if `addr` is not mapped.
+    pub fn protection(&self, addr: usize) -> Option<VmProt> {
+        self.map.lock().lookup(addr).map(|e| e.prot())
+    }
+
+    /// Write `data` to `addr`. All pages within the range must be writable. The pages that have not
+    /// been faulted in yet will be allocated.
+    ///
+    /// See `copyout` on the PS4 for a reference.
+    pub fn write(&self, addr: usize, data: &[u8]) -> Result<(), FaultError> {
+        self.rw(addr, data.len(), VmProt::Write, |off, pa, len| unsafe {
+            self.phys
+                .map(pa)
+                .copy_from_nonoverlapping(data[off..].as_ptr(), len)
+        })
+    }
+
+    /// Read `buf.len()` bytes from `addr`. All pages within the range must be readable. The pages
+    /// that have not been faulted in yet will be allocated.
+    ///
+    /// See `copyin` on the PS4 for a reference.
+    pub fn read(&self, addr: usize, buf: &mut [u8]) -> Result<(), FaultError> {
+        self.rw(addr, buf.len(), VmProt::Read, |off, pa, len| unsafe {
+            self.phys
+                .map(pa)
+                .copy_to_nonoverlapping(buf[off..].as_mut_ptr(), len)
+        })
+    }
+
+    /// Same as [`Self::write()`] but regardless the protection of the mapping. This is intended to
+    /// be used by the kernel itself (e.g. loading an executable).
+    ///
+    /// See `proc_rwmem` on the PS4 for a reference.
+    pub fn force_write(&self, addr: usize, data: &[u8]) -> Result<(), FaultError> {
+        self.rw(addr, data.len(), VmProt::zeroed(), |off, pa, len| unsafe {
+            self.phys
+                .map(pa)
+                .copy_from_nonoverlapping(data[off..].as_ptr(), len)
+        })
+    }
+
+    /// Same as [`Self::read()`] but regardless the protection of the mapping. This is intended to
+    /// be used by the kernel itself (e.g. loading an executable).
+    ///
+    /// See `proc_rwmem` on the PS4 for a reference.
+    pub fn force_read(&self, addr: usize, buf: &mut [u8]) -> Result<(), FaultError> {
+        self.rw(addr, buf.len(), VmProt::zeroed(), |off, pa, len| unsafe {
+            self.phys
+                .map(pa)
+                .copy_to_nonoverlapping(buf[off..].as_mut_ptr(), len)
+        })
+    }
+
+    /// Read a NUL-terminated string from `addr`. `max` is the maximum length including the NUL.
+    ///
+    /// See `copyinstr` on the PS4 for a reference.
+    pub fn read_str(&self, addr: usize, max: usize) -> Result<String, ReadStrError> {
+        let mut data = Vec::new();
+        let mut addr = addr;
+
+        while data.len() < max {
+            // Read until the end of the page.
+            let len = (PAGE_SIZE.get() - (addr & PAGE_MASK.get())).min(max - data.len());
+            let mut buf = vec![0; len];
+
+            self.read(addr, &mut buf).map_err(ReadStrError::Fault)?;
+
+            // Check if the string is terminated.
+            if let Some(i) = buf.iter().position(|&b| b == 0) {
+                data.extend_from_slice(&buf[..i]);
+
+                return String::from_utf8(data).map_err(|_| ReadStrError::NotUtf8);
+            }
+
+            data.extend(buf);
+            addr += len;
+        }
+
+        Err(ReadStrError::TooLong)
+    }
+
+    /// Handle a page fault at `addr`. `ty` is the type of access that cause the fault.
+    ///
+    /// See `vm_fault` on the PS4 for a reference.
+    pub fn fault(&self, addr: usize, ty: VmProt) -> Result<(), FaultError> {
+        self.fault_locked(&self.map.lock(), addr, ty)
+    }
+
+    fn fault_locked(&self, map: &VmMap, addr: usize, ty: VmProt) -> Result<(), FaultError> {
+        let e = map.lookup(addr).ok_or(FaultError::NotMapped)?;
+
+        if !e.prot().has_all(ty) {
+            return Err(FaultError::ProtectionViolated);
+        }
+
+        // Check if the page has been faulted in by another thread while we are waiting for the
+        // lock. We only need to retry the access in this case.
+        let addr = addr & !PAGE_MASK.get();
+        let mut pmap = self.pmap.lock();
+
+        if pmap.extract(addr).is_some() {
+            pmap.protect(addr, e.prot());
+            return Ok(());
+        }
+
+        // Get a page.
+        let page = match e.object() {
+            Some(o) => o
+                .page(e.offset() + (addr - e.start()), e.prot())
+                .map_err(FaultError::ObjectFailed)?,
+            None => self.phys.alloc().ok_or(FaultError::NoMemory)?,
+        };
+
+        match pmap.enter(addr, page, e.prot()) {
+            Ok(_) => Ok(()),
+            Err(v) => {
+                if e.object().is_none() {
+                    unsafe { self.phys.free(page) };
+                }
+
+                match v {
+                    PmapError::NoMemory => Err(FaultError::NoMemory),
+                    PmapError::Shared => Err(FaultError::NotMapped),
+                    PmapError::AlreadyMapped => unreachable!(),
+                }
+            }
+        }
+    }
+
+    pub fn register_syscalls(sys: &mut Syscalls) {
+        sys.register(73, Self::sys_munmap);
+        sys.register(74, Self::sys_mprotect);
+        sys.register(477, Self::sys_mmap);
+    }
+
+    pub fn register_sysctls(ctl: &mut Sysctl) {
+        ctl.register(&HW_PAGESIZE);
+    }
+
+    /// See `sys_munmap` on the PS4 for a reference.
+    fn sys_munmap(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        td.proc().vm().munmap(i.args[0].into(), i.args[1].into())?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// See `sys_mprotect` on the PS4 for a reference.
+    fn sys_mprotect(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let prot = Self::prot(i.args[2].get())?;
+
+        td.proc()
+            .vm()
+            .mprotect(i.args[0].into(), i.args[1].into(), prot)?;
+
+        Ok(SysOut::ZERO)
+    }
+
+    /// Only anonymous mapping and the mapping of a device are supported for now.
+    ///
+    /// See `sys_mmap` on the PS4 for a reference.
+    fn sys_mmap(td: &Thread, i: &SysIn) -> Result<SysOut, SysErr> {
+        let addr: usize = i.args[0].into();
+        let len: usize = i.args[1].into();
+        let prot = Self::prot(i.args[2].get())?;
+        let flags: usize = i.args[3].into();
+        let fixed = (flags & MAP_FIXED) != 0;
+        let vm = td.proc().vm();
+
+        if (flags & MAP_ANON) != 0 {
+            return Ok(vm.mmap(addr, len, prot, fixed)?.into());
+        }
+
+        // Get the device.
+        let fd: c_int = i.args[4].try_into().map_err(|_| SysErr::Raw(EINVAL))?;
+        let off: usize = i.args[5].into();
+        let file = td.proc().files().get(fd)?;
+        let dev = match file.backend::<CdevFileBackend>() {
+            Some(v) => v.device().clone(),
+            None => return Err(SysErr::Raw(ENODEV)), // TODO: Support mapping a vnode.
+        };
+
+        if !file.flags().has(FileFlags::FREAD) {
+            return Err(SysErr::Raw(EACCES));
+        }
+
+        let addr = vm.mmap_object(addr, len, prot, fixed, Arc::new(DevicePager::new(dev)), off)?;
+
+        Ok(addr.into())
+    }
+
+    /// Convert the protection from the user to [`VmProt`].
+    pub fn prot(v: usize) -> Result<VmProt, SysErr> {
+        let v = u8::try_from(v)
+            .ok()
+            .filter(|&v| (v & !0x37) == 0)
+            .ok_or(SysErr::Raw(EINVAL))?;
+
+        Ok(VmProt::from(v))
+    }
+
+    fn map(
+        &self,
+        addr: usize,
+        len: usize,
+        prot: VmProt,
+        fixed: bool,
+        object: Option<(Arc<dyn VmObject>, usize)>,
+    ) -> Result<usize, MapError> {
+        let len = Self::round_len(addr, len)?;
+        let addr = addr & !PAGE_MASK.get();
+        let mut map = self.map.lock();
+
+        // Make sure the new mapping is valid before we destroy the existing one. The insertion
+        // below cannot fail after this since the range is now empty.
+        if fixed {
+            let end = map.check_range(addr, len)?;
+
+            self.unmap_pages(&map, addr, len);
+            map.remove(addr..end);
+        }
+
+        let addr = if fixed {
+            addr
+        } else {
+            map.find_space(addr, len).ok_or(MapError::NoSpace)?
+        };
+
+        match object {
+            Some((o, off)) => map.insert_object(addr, len, prot, o, off)?,
+            None => map.insert(addr, len, prot)?,
+        }
+
+        Ok(addr)
+    }
+
+    /// Remove all pages within the range from the pmap and free the pages that are not owned by
+    /// any object. This must be called before removing the range from `map`.
+    fn unmap_pages(&self, map: &VmMap, addr: usize, len: usize) {
+        let mut pmap = self.pmap.lock();
+
+        for addr in (addr..(addr + len)).step_by(PAGE_SIZE.get()) {
+            let page = match pmap.remove(addr) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            if map.lookup(addr).is_none_or(|e| e.object().is_none()) {
+                unsafe { self.phys.free(page) };
+            }
+        }
+    }
+
+    /// Invoke `f` with the offset, the physical address and the length for each page from `addr`
+    /// to `addr + len`. Each page must have all of `prot`.
+    ///
+    /// The map is locked until all pages has been processed so the pages cannot be unmapped while
+    /// `f` is accessing it.
+    fn rw(
+        &self,
+        addr: usize,
+        len: usize,
+        prot: VmProt,
+        mut f: impl FnMut(usize, usize, usize),
+    ) -> Result<(), FaultError> {
+        let map = self.map.lock();
+        let mut off = 0;
+
+        while off < len {
+            let va = addr.checked_add(off).ok_or(FaultError::NotMapped)?;
+            let n = (PAGE_SIZE.get() - (va & PAGE_MASK.get())).min(len - off);
+
+            // Check protection.
+            if !map
+                .lookup(va)
+                .ok_or(FaultError::NotMapped)?
+                .prot()
+                .has_all(prot)
+            {
+                return Err(FaultError::ProtectionViolated);
+            }
+
+            // Get the page.
+            let pa = match self.pmap.lock().extract(va) {
+                Some(v) => v,
+                None => {
+                    self.fault_locked(&map, va, prot)?;
+                    self.pmap.lock().extract(va).ok_or(FaultError::NotMapped)?
+                }
+            };
+
+            f(off, pa, n);
+            off += n;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the length that cover all pages from `addr` to `addr + len`.
+    fn round_len(addr: usize, len: usize) -> Result<usize, MapError> {
+        (addr & PAGE_MASK.get())
+            .checked_add(len)
+            .and_then(|v| v.checked_next_multiple_of(PAGE_SIZE.get()))
+            .filter(|&v| v != 0)
+            .ok_or(MapError::InvalidAddress)
+    }
+}
+
+impl Drop for VmSpace {
+    /// See `vmspace_exit` on the PS4 for a reference.
+    fn drop(&mut self) {
+        let map = self.map.get_mut();
+        let pmap = self.pmap.get_mut();
+
+        for e in map.entries() {
+            for addr in (e.start()..e.end()).step_by(PAGE_SIZE.get()) {
+                let page = match pmap.remove(addr) {
+                    Some(v) => v,
+                    None => continue,
+                };
+
+                if e.object().is_none() {
+                    unsafe { self.phys.free(page) };
+                }
+            }
+        }
+    }
+}
+
+#[sysctl(
+    parent = HW,
+    number = 7,
+    name = "pagesize",
+    flags = CTLFLAG_RD | CTLFLAG_MPSAFE | CTLFLAG_CAPRD,
+    descr = "System memory page size"
+)]
+static HW_PAGESIZE: c_int = PAGE_SIZE.get() as c_int;
+
+/// Mapping must be placed at the specified address (AKA `MAP_FIXED`).
+pub const MAP_FIXED: usize = 0x10;
+
+/// Mapping is not backed by any file (AKA `MAP_ANON`).
+const MAP_ANON: usize = 0x1000;
+
+/// Represents an error when [`VmSpace::fault()`] fails.
+#[derive(Debug)]
+pub enum FaultError {
+    NotMapped,
+    ProtectionViolated,
+    NoMemory,
+    ObjectFailed(Box<dyn Errno>),
+}
+
+impl Error for FaultError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::ObjectFailed(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for FaultError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotMapped => f.write_str("the address is not mapped"),
+            Self::ProtectionViolated => f.write_str("protection violated"),
+            Self::NoMemory => f.write_str("no physical memory available"),
+            Self::ObjectFailed(_) => f.write_str("couldn't get the page from the object"),
+        }
+    }
+}
+
+impl Errno for FaultError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::NotMapped | Self::ProtectionViolated | Self::ObjectFailed(_) => EFAULT,
+            Self::NoMemory => ENOMEM,
+        }
+    }
+}
+
+/// Represents an error when [`VmSpace::read_str()`] fails.
+#[derive(Debug)]
+pub enum ReadStrError {
+    Fault(FaultError),
+    TooLong,
+    NotUtf8,
+}
+
+impl Error for ReadStrError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Fault(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ReadStrError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Fault(_) => f.write_str("couldn't read the string"),
+            Self::TooLong => f.write_str("the string is too long"),
+            Self::NotUtf8 => f.write_str("the string is not UTF-8"),
+        }
+    }
+}
+
+impl Errno for ReadStrError {
+    fn errno(&self) -> NonZero<c_int> {
+        match self {
+            Self::Fault(e) => e.errno(),
+            Self::TooLong => ENAMETOOLONG,
+            Self::NotUtf8 => EINVAL,
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::context::{current_trap_rsp_offset, current_user_rsp_offset, ContextArgs};
-use crate::trap::{interrupt_handler, syscall_handler};
+use crate::trap::{interrupt_handler, syscall_handler, T_BPTFLT, T_PAGEFLT};
 use bitfield_struct::bitfield;
 use core::arch::{asm, global_asm};
 use core::mem::{transmute, zeroed};
@@ -9,6 +9,8 @@ use x86_64::{Dpl, Efer, Rflags, SegmentSelector, Star};
 pub const GDT_KERNEL_CS: SegmentSelector = SegmentSelector::new().with_si(3);
 pub const GDT_KERNEL_DS: SegmentSelector = SegmentSelector::new().with_si(4);
 pub const GDT_USER_CS32: SegmentSelector = SegmentSelector::new().with_si(5).with_rpl(Dpl::Ring3);
+pub const GDT_USER_DS: SegmentSelector = SegmentSelector::new().with_si(6).with_rpl(Dpl::Ring3);
+pub const GDT_USER_CS64: SegmentSelector = SegmentSelector::new().with_si(7).with_rpl(Dpl::Ring3);
 
 /// # Safety
 /// This function can be called only once and must be called by main CPU entry point.
@@ -100,6 +102,7 @@ pub unsafe fn setup_main_cpu() -> ContextArgs {
     };
 
     set_idt(3, Xbpt, 0b1110, Dpl::Ring3, 0);
+    set_idt(14, Xpage, 0b1110, Dpl::Ring0, 0);
 
     // Set IDT.
     let limit = (size_of::<GateDescriptor>() * IDT_LEN - 1)
@@ -170,6 +173,7 @@ pub unsafe fn wrmsr(reg: u32, val: usize) {
 unsafe extern "C" {
     fn set_gdtr(v: &Gdtr, code: SegmentSelector, data: SegmentSelector);
     fn Xbpt() -> !;
+    fn Xpage() -> !;
     fn syscall_entry64() -> !;
     fn syscall_entry32() -> !;
 }
@@ -192,10 +196,11 @@ global_asm!(
 // See Xbpt on the PS4 for a reference.
 global_asm!(
     "Xbpt:", // TODO: Check if coming from user-space.
-    "sub rsp, 0x80", // TODO: Use const from Rust 1.82.
-    "mov dword ptr [rsp+0x78], 3", // TODO: Use const from Rust 1.82.
+    "sub rsp, 0x98", // TODO: Use const from Rust 1.82.
+    "mov dword ptr [rsp+0x78], {trapno}",
     "mov rdi, rsp",
     "call {f}",
+    trapno = const T_BPTFLT,
     f = sym interrupt_handler
 );
 
@@ -205,10 +210,53 @@ global_asm!(
     "swapgs",
     "mov gs:[{user_rsp}], rsp", // Save user RSP.
     "mov rsp, gs:[{trap_rsp}]",
+    "sub rsp, 0xc0",
+    "mov [rsp+0x00], rdi",
+    "mov [rsp+0x08], rsi",
+    "mov [rsp+0x10], rdx",
+    "mov [rsp+0x18], r10", // The fourth argument is passed via R10 since RCX is used by syscall.
+    "mov [rsp+0x20], r8",
+    "mov [rsp+0x28], r9",
+    "mov [rsp+0x30], rax",
+    "mov [rsp+0x38], rbx",
+    "mov [rsp+0x40], rbp",
+    "mov [rsp+0x48], r10",
+    "mov [rsp+0x58], r12",
+    "mov [rsp+0x60], r13",
+    "mov [rsp+0x68], r14",
+    "mov [rsp+0x70], r15",
+    "mov [rsp+0x78], eax", // Syscall number.
+    "mov qword ptr [rsp+0x90], 2", // Length of syscall instruction.
+    "mov [rsp+0x98], rcx", // RIP.
+    "mov qword ptr [rsp+0xa0], {ucs}",
+    "mov [rsp+0xa8], r11", // RFLAGS.
+    "mov r11, gs:[{user_rsp}]",
+    "mov [rsp+0xb0], r11",
+    "mov qword ptr [rsp+0xb8], {uds}",
+    "mov rdi, rsp",
     "call {handler}",
-    "ud2",
+    "mov rdi, [rsp+0x00]",
+    "mov rsi, [rsp+0x08]",
+    "mov rdx, [rsp+0x10]",
+    "mov r8, [rsp+0x20]",
+    "mov r9, [rsp+0x28]",
+    "mov rax, [rsp+0x30]",
+    "mov rbx, [rsp+0x38]",
+    "mov rbp, [rsp+0x40]",
+    "mov r10, [rsp+0x48]",
+    "mov r12, [rsp+0x58]",
+    "mov r13, [rsp+0x60]",
+    "mov r14, [rsp+0x68]",
+    "mov r15, [rsp+0x70]",
+    "mov rcx, [rsp+0x98]",
+    "mov r11, [rsp+0xa8]",
+    "mov rsp, [rsp+0xb0]",
+    "swapgs",
+    "sysretq",
     user_rsp = const current_user_rsp_offset(),
     trap_rsp = const current_trap_rsp_offset(),
+    ucs = const GDT_USER_CS64.into_bits(),
+    uds = const GDT_USER_DS.into_bits(),
     handler = sym syscall_handler
 );
 
@@ -331,3 +379,56 @@ struct GateDescriptor {
     offset2: u64,
     __: u32,
 }
+
+// See Xpage on the PS4 for a reference.
+global_asm!(
+    "Xpage:",
+    "sub rsp, 0x90", // The CPU already pushed the error code.
+    "test byte ptr [rsp+0xa0], 3", // Check if coming from user-space.
+    "jz 1f",
+    "swapgs",
+    "1:",
+    "mov dword ptr [rsp+0x78], {trapno}",
+    "mov [rsp+0x00], rdi",
+    "mov [rsp+0x08], rsi",
+    "mov [rsp+0x10], rdx",
+    "mov [rsp+0x18], rcx",
+    "mov [rsp+0x20], r8",
+    "mov [rsp+0x28], r9",
+    "mov [rsp+0x30], rax",
+    "mov [rsp+0x38], rbx",
+    "mov [rsp+0x40], rbp",
+    "mov [rsp+0x48], r10",
+    "mov [rsp+0x50], r11",
+    "mov [rsp+0x58], r12",
+    "mov [rsp+0x60], r13",
+    "mov [rsp+0x68], r14",
+    "mov [rsp+0x70], r15",
+    "mov rdi, cr2",
+    "mov [rsp+0x80], rdi",
+    "mov rdi, rsp",
+    "call {f}",
+    "mov rdi, [rsp+0x00]",
+    "mov rsi, [rsp+0x08]",
+    "mov rdx, [rsp+0x10]",
+    "mov rcx, [rsp+0x18]",
+    "mov r8, [rsp+0x20]",
+    "mov r9, [rsp+0x28]",
+    "mov rax, [rsp+0x30]",
+    "mov rbx, [rsp+0x38]",
+    "mov rbp, [rsp+0x40]",
+    "mov r10, [rsp+0x48]",
+    "mov r11, [rsp+0x50]",
+    "mov r12, [rsp+0x58]",
+    "mov r13, [rsp+0x60]",
+    "mov r14, [rsp+0x68]",
+    "mov r15, [rsp+0x70]",
+    "add rsp, 0x98",
+    "test byte ptr [rsp+0x08], 3", // Check if returning to user-space.
+    "jz 1f",
+    "swapgs",
+    "1:",
+    "iretq",
+    trapno = const T_PAGEFLT,
+    f = sym interrupt_handler
+);
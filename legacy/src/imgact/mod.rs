@@ -1 +1 @@
-pub mod orbis;
+pub use orbis;
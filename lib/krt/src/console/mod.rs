@@ -18,6 +18,16 @@ macro_rules! info {
     };
 }
 
+/// Write error log.
+///
+/// The LF character will be automatically appended.
+#[macro_export]
+macro_rules! error {
+    ($($args:tt)*) => {
+        $crate::error(file!(), line!(), format_args!($($args)*))
+    };
+}
+
 pub fn info(file: &str, line: u32, msg: impl Display) {
     let msg = Log {
         style: Style::new().effects(Effects::DIMMED),
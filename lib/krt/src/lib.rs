@@ -7,12 +7,14 @@
 
 pub use self::config::*;
 pub use self::console::*;
+pub use self::shutdown::*;
 
+use ::config::KernelExit;
 use core::panic::PanicInfo;
 
 mod config;
 mod console;
-mod panic;
+mod shutdown;
 
 /// Entry point of the kernel.
 ///
@@ -42,7 +44,7 @@ fn panic(i: &PanicInfo) -> ! {
 
     // Print the message.
     self::console::error(file, line, format_args!("Kernel panic - {}.", i.message()));
-    self::panic::panic();
+    self::shutdown::shutdown(KernelExit::Panic);
 }
 
 #[cfg(target_os = "none")]
@@ -0,0 +1,13 @@
+use crate::config::boot_env;
+use config::{BootEnv, KernelExit};
+
+mod vm;
+
+/// Stop the machine with `status`.
+///
+/// The panic handler use this with [`KernelExit::Panic`] after printing the panic message.
+pub fn shutdown(status: KernelExit) -> ! {
+    match boot_env() {
+        BootEnv::Vm(env) => self::vm::shutdown(env, status),
+    }
+}
@@ -2,9 +2,9 @@ use config::{KernelExit, Vm, VmmMemory};
 use core::hint::unreachable_unchecked;
 use core::ptr::{addr_of_mut, write_volatile};
 
-pub fn panic(env: &Vm) -> ! {
+pub fn shutdown(env: &Vm, status: KernelExit) -> ! {
     let vmm = env.vmm as *mut VmmMemory;
 
-    unsafe { write_volatile(addr_of_mut!((*vmm).shutdown), KernelExit::Panic) };
+    unsafe { write_volatile(addr_of_mut!((*vmm).shutdown), status) };
     unsafe { unreachable_unchecked() };
 }
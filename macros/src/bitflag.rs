@@ -63,6 +63,11 @@ pub fn transform(opts: Options, item: ItemEnum) -> syn::Result<TokenStream> {
         pub const fn has_all(self, rhs: Self) -> bool {
             (self.0 & rhs.0) == rhs.0
         }
+
+        /// Clear all flags in the `rhs` set from this set.
+        pub const fn remove(&mut self, rhs: Self) {
+            self.0 &= !rhs.0;
+        }
     });
 
     // Compose.
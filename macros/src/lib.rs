@@ -1,10 +1,11 @@
 use proc_macro::TokenStream;
-use syn::{parse_macro_input, Error, ItemEnum, ItemStatic, LitStr};
+use syn::{parse_macro_input, Error, Item, ItemEnum, ItemStatic, LitStr};
 
 mod bitflag;
 mod elf;
 mod enum_conversions;
 mod errno;
+mod sysctl;
 mod vpath;
 
 /// The reason we use `bitflag` as a name instead of `bitflags` is to make it matched with
@@ -36,6 +37,24 @@ pub fn elf_note(args: TokenStream, item: TokenStream) -> TokenStream {
         .into()
 }
 
+/// Define a sysctl OID from a `static` or a handler function.
+///
+/// The type of OID for a `static` will be determined from its type and its value must be constant.
+/// For a function the `ty` option is required and the OID will be placed in a `static` with the
+/// same name as the function in upper case.
+#[proc_macro_attribute]
+pub fn sysctl(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as Item);
+    let mut opts = self::sysctl::Options::default();
+    let parser = syn::meta::parser(|m| opts.parse(m));
+
+    parse_macro_input!(args with parser);
+
+    self::sysctl::transform(opts, item)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
 #[proc_macro_derive(EnumConversions)]
 pub fn implement_conversions(item: TokenStream) -> TokenStream {
     let item = parse_macro_input!(item as ItemEnum);
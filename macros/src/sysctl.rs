@@ -0,0 +1,187 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::meta::ParseNestedMeta;
+use syn::{Error, Expr, Ident, Item, ItemFn, ItemStatic, LitStr, StaticMutability, Type};
+
+const OPT_PARENT: &str = "parent";
+const OPT_NUMBER: &str = "number";
+const OPT_NAME: &str = "name";
+const OPT_TY: &str = "ty";
+const OPT_FLAGS: &str = "flags";
+const OPT_DESCR: &str = "descr";
+
+pub fn transform(opts: Options, item: Item) -> syn::Result<TokenStream> {
+    match item {
+        Item::Static(v) => transform_static(opts, v),
+        Item::Fn(v) => transform_fn(opts, v),
+        v => Err(Error::new_spanned(v, "expect static or function")),
+    }
+}
+
+fn transform_static(opts: Options, item: ItemStatic) -> syn::Result<TokenStream> {
+    // Static OID is read-only.
+    if let StaticMutability::Mut(t) = &item.mutability {
+        return Err(Error::new_spanned(t, "mutable OID is not supported"));
+    }
+
+    if let Some(v) = &opts.ty {
+        return Err(Error::new_spanned(
+            v,
+            format_args!("`{OPT_TY}` is not supported on static"),
+        ));
+    }
+
+    if let Some(v) = opts.flags.as_ref().and_then(find_writable) {
+        return Err(Error::new_spanned(v, "static OID cannot be writable"));
+    }
+
+    // Get type.
+    let (ty, data) = match item.ty.as_ref() {
+        Type::Path(p) if p.qself.is_none() => {
+            let last = p.path.segments.last().unwrap();
+
+            match last.ident.to_string().as_str() {
+                "c_int" | "i32" => ("CTLTYPE_INT", "Int"),
+                "c_uint" | "u32" => ("CTLTYPE_UINT", "UInt"),
+                "isize" => ("CTLTYPE_LONG", "Long"),
+                "usize" => ("CTLTYPE_ULONG", "ULong"),
+                "i64" => ("CTLTYPE_S64", "S64"),
+                "u64" => ("CTLTYPE_U64", "U64"),
+                _ => return Err(Error::new_spanned(p, "unsupported type")),
+            }
+        }
+        Type::Reference(r) if matches!(r.elem.as_ref(), Type::Path(p) if p.path.is_ident("str")) => {
+            ("CTLTYPE_STRING", "String")
+        }
+        t => return Err(Error::new_spanned(t, "unsupported type")),
+    };
+
+    // Compose.
+    let ty = Ident::new(ty, Span::call_site());
+    let data = Ident::new(data, Span::call_site());
+    let value = item.expr;
+    let oid = opts.compose(
+        quote!(crate::sysctl::#ty),
+        quote!(crate::sysctl::OidData::#data(#value)),
+    )?;
+    let attrs = item.attrs;
+    let vis = item.vis;
+    let ident = item.ident;
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis static #ident: crate::sysctl::Oid = #oid;
+    })
+}
+
+fn transform_fn(opts: Options, item: ItemFn) -> syn::Result<TokenStream> {
+    let ty = match &opts.ty {
+        Some(v) => v,
+        None => {
+            return Err(Error::new(
+                Span::call_site(),
+                format_args!("missing `{OPT_TY}` option"),
+            ));
+        }
+    };
+
+    // Compose.
+    let ident = &item.sig.ident;
+    let oid = opts.compose(
+        quote!({
+            #[allow(unused_imports)]
+            use crate::sysctl::*;
+            #ty
+        }),
+        quote!(crate::sysctl::OidData::Handler(#ident)),
+    )?;
+    let vis = &item.vis;
+    let name = format_ident!("{}", ident.to_string().to_uppercase(), span = ident.span());
+
+    Ok(quote! {
+        #item
+
+        #vis static #name: crate::sysctl::Oid = #oid;
+    })
+}
+
+/// Returns the flag that make the OID writable if any.
+fn find_writable(flags: &Expr) -> Option<&Expr> {
+    match flags {
+        Expr::Binary(v) => find_writable(&v.left).or_else(|| find_writable(&v.right)),
+        Expr::Paren(v) => find_writable(&v.expr),
+        Expr::Path(v) if v.path.is_ident("CTLFLAG_WR") || v.path.is_ident("CTLFLAG_RW") => {
+            Some(flags)
+        }
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+pub struct Options {
+    parent: Option<Expr>,
+    number: Option<Expr>,
+    name: Option<LitStr>,
+    ty: Option<Expr>,
+    flags: Option<Expr>,
+    descr: Option<LitStr>,
+}
+
+impl Options {
+    pub fn parse(&mut self, m: ParseNestedMeta) -> syn::Result<()> {
+        if m.path.is_ident(OPT_PARENT) {
+            self.parent = Some(m.value()?.parse()?);
+        } else if m.path.is_ident(OPT_NUMBER) {
+            self.number = Some(m.value()?.parse()?);
+        } else if m.path.is_ident(OPT_NAME) {
+            self.name = Some(m.value()?.parse()?);
+        } else if m.path.is_ident(OPT_TY) {
+            self.ty = Some(m.value()?.parse()?);
+        } else if m.path.is_ident(OPT_FLAGS) {
+            self.flags = Some(m.value()?.parse()?);
+        } else if m.path.is_ident(OPT_DESCR) {
+            self.descr = Some(m.value()?.parse()?);
+        } else {
+            return Err(m.error("unknown option"));
+        }
+
+        Ok(())
+    }
+
+    fn compose(&self, ty: TokenStream, data: TokenStream) -> syn::Result<TokenStream> {
+        fn missing(name: &str) -> Error {
+            Error::new(Span::call_site(), format_args!("missing `{name}` option"))
+        }
+
+        let parent = match &self.parent {
+            Some(v) => quote!(Some(&#v)),
+            None => quote!(None),
+        };
+        let number = self.number.as_ref().ok_or_else(|| missing(OPT_NUMBER))?;
+        let name = self.name.as_ref().ok_or_else(|| missing(OPT_NAME))?;
+        let flags = self.flags.as_ref().ok_or_else(|| missing(OPT_FLAGS))?;
+        let descr = match &self.descr {
+            Some(v) => v.clone(),
+            None => LitStr::new("", Span::call_site()),
+        };
+
+        if name.value().is_empty() || name.value().contains('.') {
+            return Err(Error::new_spanned(name, "invalid OID name"));
+        }
+
+        Ok(quote! {
+            crate::sysctl::Oid::new(
+                #parent,
+                #number,
+                #name,
+                #ty | {
+                    #[allow(unused_imports)]
+                    use crate::sysctl::*;
+                    #flags
+                },
+                #data,
+                #descr,
+            )
+        })
+    }
+}
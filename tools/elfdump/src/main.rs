@@ -0,0 +1,82 @@
+use self::report::Report;
+use clap::Parser;
+use erdp::ErrorDisplay;
+use orbis::{Elf, OpenError};
+use std::fs::File;
+use std::io::{BufReader, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use thiserror::Error;
+
+mod report;
+
+fn main() -> ExitCode {
+    let args = ProgramArgs::parse();
+
+    match run(&args) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}.", e.display());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &ProgramArgs) -> Result<(), ProgramError> {
+    // Open the file.
+    let path = &args.file;
+    let file = File::open(path).map_err(|e| ProgramError::OpenFile(path.clone(), e))?;
+    let name = path
+        .file_name()
+        .map(|v| v.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let elf = Elf::open(name, BufReader::new(file))
+        .map_err(|e| ProgramError::OpenElf(path.clone(), e))?;
+
+    // Write the report.
+    let report = Report::new(&elf, args.relocs);
+    let mut stdout = std::io::stdout().lock();
+
+    if args.json {
+        serde_json::to_writer_pretty(&mut stdout, &report).map_err(ProgramError::WriteJson)?;
+        writeln!(stdout).map_err(ProgramError::WriteReport)?;
+    } else {
+        report
+            .write(&mut stdout)
+            .map_err(ProgramError::WriteReport)?;
+    }
+
+    Ok(())
+}
+
+/// Program arguments parsed from command line.
+#[derive(Parser)]
+#[command(about = "Print information of a decrypted (S)ELF or PRX")]
+struct ProgramArgs {
+    /// Print the report as JSON instead of human-readable text.
+    #[arg(long)]
+    json: bool,
+
+    /// Include each relocation entry instead of a summary.
+    #[arg(long)]
+    relocs: bool,
+
+    /// Path to the file to inspect.
+    file: PathBuf,
+}
+
+/// Represents an error when our program fails.
+#[derive(Debug, Error)]
+enum ProgramError {
+    #[error("couldn't open {0}")]
+    OpenFile(PathBuf, #[source] std::io::Error),
+
+    #[error("couldn't open {0} as (S)ELF")]
+    OpenElf(PathBuf, #[source] OpenError),
+
+    #[error("couldn't write JSON")]
+    WriteJson(#[source] serde_json::Error),
+
+    #[error("couldn't write the report")]
+    WriteReport(#[source] std::io::Error),
+}
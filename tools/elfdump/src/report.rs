@@ -0,0 +1,557 @@
+use orbis::{DynamicTag, Elf, FileInfo, Relocation, Symbol};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, Write};
+
+/// Characters that used to encode NID and the ID of library and module.
+const NID_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+-";
+
+/// Contains everything we know about a (S)ELF.
+#[derive(Serialize)]
+pub struct Report {
+    name: String,
+    ty: String,
+    entry: Option<usize>,
+    self_segments: Option<Vec<SegmentReport>>,
+    programs: Vec<ProgramReport>,
+    dynamic: Option<DynamicReport>,
+}
+
+impl Report {
+    pub fn new<I: Read + Seek>(elf: &Elf<I>, relocs: bool) -> Self {
+        let self_segments = elf.self_segments().map(|l| {
+            l.iter()
+                .map(|s| SegmentReport {
+                    flags: s.flags().bits(),
+                    program: s.flags().program(),
+                    offset: s.offset(),
+                    compressed_size: s.compressed_size(),
+                    decompressed_size: s.decompressed_size(),
+                })
+                .collect()
+        });
+
+        let programs = elf
+            .programs()
+            .iter()
+            .map(|p| ProgramReport {
+                ty: p.ty().to_string(),
+                flags: p.flags().to_string(),
+                offset: p.offset(),
+                addr: p.addr(),
+                file_size: p.file_size(),
+                memory_size: p.memory_size(),
+                alignment: p.alignment(),
+            })
+            .collect();
+
+        Self {
+            name: elf.name().to_owned(),
+            ty: elf.ty().to_string(),
+            entry: elf.entry_addr(),
+            self_segments,
+            programs,
+            dynamic: elf.info().map(|i| DynamicReport::new(i, relocs)),
+        }
+    }
+
+    pub fn write(&self, w: &mut impl Write) -> Result<(), std::io::Error> {
+        writeln!(w, "Name  : {}", self.name)?;
+        writeln!(w, "Type  : {}", self.ty)?;
+
+        match self.entry {
+            Some(v) => writeln!(w, "Entry : {v:#x}")?,
+            None => writeln!(w, "Entry : none")?,
+        }
+
+        // SELF segments.
+        if let Some(l) = &self.self_segments {
+            writeln!(w)?;
+            writeln!(w, "SELF segments ({}):", l.len())?;
+
+            for (i, s) in l.iter().enumerate() {
+                writeln!(
+                    w,
+                    "  #{:<3} program = {:<3} flags = {:#010x} offset = {:#x} size = {:#x} -> {:#x}",
+                    i, s.program, s.flags, s.offset, s.compressed_size, s.decompressed_size
+                )?;
+            }
+        }
+
+        // Programs.
+        writeln!(w)?;
+        writeln!(w, "Programs ({}):", self.programs.len())?;
+
+        for (i, p) in self.programs.iter().enumerate() {
+            writeln!(
+                w,
+                "  #{:<3} {:<20} addr = {:#012x} memsz = {:#010x} offset = {:#010x} filesz = {:#010x} align = {:#x} [{}]",
+                i, p.ty, p.addr, p.memory_size, p.offset, p.file_size, p.alignment, p.flags
+            )?;
+        }
+
+        if let Some(d) = &self.dynamic {
+            d.write(w)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Information of a SELF segment.
+#[derive(Serialize)]
+struct SegmentReport {
+    flags: u64,
+    program: usize,
+    offset: u64,
+    compressed_size: u64,
+    decompressed_size: u64,
+}
+
+/// Information of an ELF program.
+#[derive(Serialize)]
+struct ProgramReport {
+    ty: String,
+    flags: String,
+    offset: u64,
+    addr: usize,
+    file_size: u64,
+    memory_size: usize,
+    alignment: usize,
+}
+
+/// Information from `PT_DYNAMIC` and `PT_SCE_DYNLIBDATA`.
+#[derive(Serialize)]
+struct DynamicReport {
+    original_filename: Option<String>,
+    fingerprint: Option<String>,
+    module: Option<ModuleReport>,
+    needed: Vec<String>,
+    needed_modules: Vec<ModuleReport>,
+    export_libraries: Vec<LibraryReport>,
+    import_libraries: Vec<LibraryReport>,
+    exports: Vec<SymbolReport>,
+    imports: Vec<SymbolReport>,
+    errors: Vec<String>,
+    relocs: RelocReport,
+    plt_relocs: RelocReport,
+}
+
+impl DynamicReport {
+    fn new(info: &FileInfo, relocs: bool) -> Self {
+        let mut r = Self {
+            original_filename: None,
+            fingerprint: None,
+            module: None,
+            needed: Vec::new(),
+            needed_modules: Vec::new(),
+            export_libraries: Vec::new(),
+            import_libraries: Vec::new(),
+            exports: Vec::new(),
+            imports: Vec::new(),
+            errors: Vec::new(),
+            relocs: RelocReport::new(info.relocs(), info.reloc_count(), relocs),
+            plt_relocs: RelocReport::new(info.plt_relocs(), info.plt_count(), relocs),
+        };
+
+        // Parse dynamic entries. We don't fail on invalid entry so we can still print the other
+        // information.
+        for (i, (tag, value)) in info.dynamic().enumerate() {
+            let offset = u64::from_le_bytes(value) as usize;
+            let str = |r: &mut Self| match info.read_str(offset) {
+                Ok(v) => Some(v.to_owned()),
+                Err(e) => {
+                    r.errors.push(format!("invalid {tag} at entry #{i}: {e}"));
+                    None
+                }
+            };
+
+            match tag {
+                DynamicTag::DT_NULL => break,
+                DynamicTag::DT_NEEDED => {
+                    if let Some(v) = str(&mut r) {
+                        r.needed.push(v);
+                    }
+                }
+                DynamicTag::DT_SCE_ORIGINAL_FILENAME => r.original_filename = str(&mut r),
+                DynamicTag::DT_SCE_FINGERPRINT => {
+                    let v = info.read_fingerprint(offset);
+                    let v = v.iter().map(|b| format!("{b:02x}")).collect();
+
+                    r.fingerprint = Some(v);
+                }
+                DynamicTag::DT_SCE_MODULE_INFO | DynamicTag::DT_SCE_NEEDED_MODULE => {
+                    let m = match info.read_module(value) {
+                        Ok(v) => ModuleReport {
+                            id: v.id(),
+                            name: v.name().to_owned(),
+                        },
+                        Err(e) => {
+                            r.errors.push(format!("invalid {tag} at entry #{i}: {e}"));
+                            continue;
+                        }
+                    };
+
+                    if tag == DynamicTag::DT_SCE_MODULE_INFO {
+                        r.module = Some(m);
+                    } else {
+                        r.needed_modules.push(m);
+                    }
+                }
+                DynamicTag::DT_SCE_EXPORT_LIB | DynamicTag::DT_SCE_IMPORT_LIB => {
+                    let l = match info.read_library(value) {
+                        Ok(v) => LibraryReport {
+                            id: v.id(),
+                            name: v.name().to_owned(),
+                        },
+                        Err(e) => {
+                            r.errors.push(format!("invalid {tag} at entry #{i}: {e}"));
+                            continue;
+                        }
+                    };
+
+                    if tag == DynamicTag::DT_SCE_EXPORT_LIB {
+                        r.export_libraries.push(l);
+                    } else {
+                        r.import_libraries.push(l);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Split symbols into imports and exports.
+        for (i, sym) in info.symbols().enumerate() {
+            let sym = match sym {
+                Ok(v) => v,
+                Err(e) => {
+                    r.errors.push(format!("invalid symbol #{i}: {e}"));
+                    continue;
+                }
+            };
+
+            if sym.name().is_empty() || sym.ty() == Symbol::STT_SECTION {
+                continue;
+            }
+
+            if sym.shndx() == 0 {
+                let s = SymbolReport::new(&sym, &r.import_libraries, &r.needed_modules);
+                r.imports.push(s);
+            } else if sym.binding() != Symbol::STB_LOCAL {
+                let s = SymbolReport::new(&sym, &r.export_libraries, r.module.as_slice());
+                r.exports.push(s);
+            }
+        }
+
+        r
+    }
+
+    fn write(&self, w: &mut impl Write) -> Result<(), std::io::Error> {
+        writeln!(w)?;
+        writeln!(w, "Dynamic linking:")?;
+
+        if let Some(v) = &self.original_filename {
+            writeln!(w, "  Original name: {v}")?;
+        }
+
+        if let Some(v) = &self.fingerprint {
+            writeln!(w, "  Fingerprint  : {v}")?;
+        }
+
+        if let Some(m) = &self.module {
+            writeln!(w, "  Module       : {} (ID = {})", m.name, m.id)?;
+        }
+
+        // Dependencies.
+        writeln!(w)?;
+        writeln!(w, "Needed files ({}):", self.needed.len())?;
+
+        for n in &self.needed {
+            writeln!(w, "  {n}")?;
+        }
+
+        writeln!(w)?;
+        writeln!(w, "Needed modules ({}):", self.needed_modules.len())?;
+
+        for m in &self.needed_modules {
+            writeln!(w, "  {:<5} {}", m.id, m.name)?;
+        }
+
+        // Libraries.
+        for (h, l) in [
+            ("Exported libraries", &self.export_libraries),
+            ("Imported libraries", &self.import_libraries),
+        ] {
+            writeln!(w)?;
+            writeln!(w, "{} ({}):", h, l.len())?;
+
+            for l in l {
+                writeln!(w, "  {:<5} {}", l.id, l.name)?;
+            }
+        }
+
+        // Symbols.
+        for (h, l) in [("Exports", &self.exports), ("Imports", &self.imports)] {
+            writeln!(w)?;
+            writeln!(w, "{} ({}):", h, l.len())?;
+
+            for s in l {
+                let lib = s.library.as_deref().unwrap_or("?");
+                let module = s.module.as_deref().unwrap_or("?");
+
+                writeln!(
+                    w,
+                    "  {:<11} {:<6} {:<6} {:#010x} {}@{}",
+                    s.nid, s.ty, s.binding, s.value, lib, module
+                )?;
+            }
+        }
+
+        // Relocations.
+        for (h, r) in [
+            ("Relocations", &self.relocs),
+            ("PLT relocations", &self.plt_relocs),
+        ] {
+            writeln!(w)?;
+            writeln!(w, "{} ({}):", h, r.count)?;
+
+            for (t, n) in &r.types {
+                writeln!(w, "  {t:<24} {n}")?;
+            }
+
+            if let Some(l) = &r.entries {
+                writeln!(w)?;
+
+                for e in l {
+                    writeln!(
+                        w,
+                        "  {:#012x} {:<24} symbol = {:<6} addend = {:#x}",
+                        e.offset, e.ty, e.symbol, e.addend
+                    )?;
+                }
+            }
+        }
+
+        // Errors.
+        if !self.errors.is_empty() {
+            writeln!(w)?;
+            writeln!(w, "Errors ({}):", self.errors.len())?;
+
+            for e in &self.errors {
+                writeln!(w, "  {e}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Information of a module from `DT_SCE_MODULE_INFO` or `DT_SCE_NEEDED_MODULE`.
+#[derive(Serialize)]
+struct ModuleReport {
+    id: u16,
+    name: String,
+}
+
+/// Information of a library from `DT_SCE_EXPORT_LIB` or `DT_SCE_IMPORT_LIB`.
+#[derive(Serialize)]
+struct LibraryReport {
+    id: u16,
+    name: String,
+}
+
+/// Information of a symbol.
+#[derive(Serialize)]
+struct SymbolReport {
+    nid: String,
+    library: Option<String>,
+    module: Option<String>,
+    ty: &'static str,
+    binding: &'static str,
+    value: usize,
+}
+
+impl SymbolReport {
+    fn new(sym: &Symbol, libs: &[LibraryReport], mods: &[ModuleReport]) -> Self {
+        // The name is in the form of NID#LIB#MOD where LIB and MOD is the encoded ID.
+        let mut parts = sym.name().split('#');
+        let nid = parts.next().unwrap().to_owned();
+        let library = parts
+            .next()
+            .and_then(decode_id)
+            .and_then(|id| libs.iter().find(|l| l.id == id))
+            .map(|l| l.name.clone());
+        let module = parts
+            .next()
+            .and_then(decode_id)
+            .and_then(|id| mods.iter().find(|m| m.id == id))
+            .map(|m| m.name.clone());
+
+        Self {
+            nid,
+            library,
+            module,
+            ty: match sym.ty() {
+                Symbol::STT_NOTYPE => "NOTYPE",
+                Symbol::STT_OBJECT => "OBJECT",
+                Symbol::STT_FUNC => "FUNC",
+                Symbol::STT_TLS => "TLS",
+                Symbol::STT_ENTRY => "ENTRY",
+                _ => "OTHER",
+            },
+            binding: match sym.binding() {
+                Symbol::STB_LOCAL => "LOCAL",
+                Symbol::STB_GLOBAL => "GLOBAL",
+                Symbol::STB_WEAK => "WEAK",
+                _ => "OTHER",
+            },
+            value: sym.value(),
+        }
+    }
+}
+
+/// Information of `DT_SCE_RELA` or `DT_SCE_JMPREL`.
+#[derive(Serialize)]
+struct RelocReport {
+    count: usize,
+    types: BTreeMap<String, usize>,
+    entries: Option<Vec<RelocEntry>>,
+}
+
+impl RelocReport {
+    fn new(relocs: impl Iterator<Item = Relocation>, count: usize, entries: bool) -> Self {
+        let mut r = Self {
+            count,
+            types: BTreeMap::new(),
+            entries: entries.then(Vec::new),
+        };
+
+        for reloc in relocs {
+            let ty = reloc_type(reloc.ty());
+
+            *r.types.entry(ty.clone()).or_default() += 1;
+
+            if let Some(l) = &mut r.entries {
+                l.push(RelocEntry {
+                    offset: reloc.offset(),
+                    ty,
+                    symbol: reloc.symbol(),
+                    addend: reloc.addend(),
+                });
+            }
+        }
+
+        r
+    }
+}
+
+/// A single relocation entry.
+#[derive(Serialize)]
+struct RelocEntry {
+    offset: usize,
+    ty: String,
+    symbol: usize,
+    addend: isize,
+}
+
+fn reloc_type(ty: u32) -> String {
+    let name = match ty {
+        Relocation::R_X86_64_NONE => "R_X86_64_NONE",
+        Relocation::R_X86_64_64 => "R_X86_64_64",
+        Relocation::R_X86_64_PC32 => "R_X86_64_PC32",
+        Relocation::R_X86_64_COPY => "R_X86_64_COPY",
+        Relocation::R_X86_64_GLOB_DAT => "R_X86_64_GLOB_DAT",
+        Relocation::R_X86_64_JUMP_SLOT => "R_X86_64_JUMP_SLOT",
+        Relocation::R_X86_64_RELATIVE => "R_X86_64_RELATIVE",
+        Relocation::R_X86_64_DTPMOD64 => "R_X86_64_DTPMOD64",
+        Relocation::R_X86_64_DTPOFF64 => "R_X86_64_DTPOFF64",
+        Relocation::R_X86_64_TPOFF64 => "R_X86_64_TPOFF64",
+        Relocation::R_X86_64_TPOFF32 => "R_X86_64_TPOFF32",
+        Relocation::R_X86_64_IRELATIVE => "R_X86_64_IRELATIVE",
+        v => return v.to_string(),
+    };
+
+    name.to_owned()
+}
+
+fn decode_id(v: &str) -> Option<u16> {
+    let mut r = 0u64;
+
+    for c in v.bytes() {
+        r <<= 6;
+        r |= NID_CHARS.iter().position(|&v| v == c)? as u64;
+    }
+
+    r.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn report() {
+        let elf = Elf::open("fixture.elf", Cursor::new(fixture())).unwrap();
+        let report = Report::new(&elf, false);
+        let mut text = Vec::new();
+
+        report.write(&mut text).unwrap();
+
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "Name  : fixture.elf\n\
+             Type  : ET_EXEC\n\
+             Entry : 0x400040\n\
+             \n\
+             Programs (1):\n  \
+             #0   PT_LOAD              addr = 0x0000400000 memsz = 0x00004000 offset = 0x00000000 filesz = 0x00000078 align = 0x4000 [EXECUTE | READ]\n"
+        );
+
+        // JSON.
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["entry"], 0x400040);
+        assert_eq!(json["programs"][0]["ty"], "PT_LOAD");
+        assert!(json["dynamic"].is_null());
+    }
+
+    #[test]
+    fn decode() {
+        assert_eq!(decode_id("A"), Some(0));
+        assert_eq!(decode_id("B"), Some(1));
+        assert_eq!(decode_id("BA"), Some(64));
+        assert_eq!(decode_id("#"), None);
+    }
+
+    /// Build a statically linked ELF with a single `PT_LOAD`.
+    fn fixture() -> Vec<u8> {
+        let mut data = vec![0u8; 0x78];
+
+        // ELF header.
+        data[..4].copy_from_slice(b"\x7fELF");
+        data[0x04] = 2; // EI_CLASS
+        data[0x05] = 1; // EI_DATA
+        data[0x06] = 1; // EI_VERSION
+        data[0x10..0x12].copy_from_slice(&2u16.to_le_bytes()); // e_type
+        data[0x12..0x14].copy_from_slice(&0x3eu16.to_le_bytes()); // e_machine
+        data[0x18..0x20].copy_from_slice(&0x400040u64.to_le_bytes()); // e_entry
+        data[0x20..0x28].copy_from_slice(&0x40u64.to_le_bytes()); // e_phoff
+        data[0x34..0x36].copy_from_slice(&0x40u16.to_le_bytes()); // e_ehsize
+        data[0x36..0x38].copy_from_slice(&0x38u16.to_le_bytes()); // e_phentsize
+        data[0x38..0x3a].copy_from_slice(&1u16.to_le_bytes()); // e_phnum
+
+        // PT_LOAD.
+        let p = &mut data[0x40..];
+
+        p[..0x04].copy_from_slice(&1u32.to_le_bytes()); // p_type
+        p[0x04..0x08].copy_from_slice(&5u32.to_le_bytes()); // p_flags
+        p[0x10..0x18].copy_from_slice(&0x400000u64.to_le_bytes()); // p_vaddr
+        p[0x18..0x20].copy_from_slice(&0x400000u64.to_le_bytes()); // p_paddr
+        p[0x20..0x28].copy_from_slice(&0x78u64.to_le_bytes()); // p_filesz
+        p[0x28..0x30].copy_from_slice(&0x4000u64.to_le_bytes()); // p_memsz
+        p[0x30..0x38].copy_from_slice(&0x4000u64.to_le_bytes()); // p_align
+
+        data
+    }
+}